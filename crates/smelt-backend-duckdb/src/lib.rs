@@ -1,41 +1,153 @@
 //! DuckDB backend implementation for smelt.
 
+mod lock;
+
+pub use lock::{lock_path, LockWait};
+
 use anyhow::Context;
 use arrow::array::RecordBatch;
 use async_trait::async_trait;
-use duckdb::Connection;
-use smelt_backend::{Backend, BackendCapabilities, BackendError, PartitionSpec, SqlDialect};
-use std::path::Path;
+use duckdb::types::Value;
+use duckdb::{params_from_iter, Connection};
+use futures::StreamExt;
+use lock::DatabaseLock;
+use smelt_backend::{
+    Backend, BackendCapabilities, BackendError, Materialization, PartitionSpec, QueryStats,
+    RecordBatchStream, RelationName, SqlDialect, SqlParam, STAGING_SUFFIX,
+};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Batches buffered between the DuckDB thread and a stream's consumer.
+const STREAM_BUFFER: usize = 2;
+
+/// Most rows the Arrow appender accepts per call (DuckDB's vector size).
+const APPEND_CHUNK_SIZE: usize = 2048;
+
+/// Metrics collected when profiling a build statement.
+const PROFILE_METRICS: &str = r#"{"LATENCY": "true", "CUMULATIVE_ROWS_SCANNED": "true"}"#;
+
+/// Numbers profile output files so concurrent statements don't share one.
+static PROFILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Build statistics per relation, waiting for `take_build_stats`.
+type BuildStats = Arc<Mutex<HashMap<String, QueryStats>>>;
+
+/// Connections to one DuckDB database, shared by concurrent operations.
+///
+/// A `Connection` is not `Sync`, so each operation checks one out for its
+/// duration. Extra connections are cloned from the primary one, which shares
+/// the open database rather than opening the file again, and are kept for
+/// reuse once returned.
+struct ConnectionPool {
+    primary: Mutex<Connection>,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl ConnectionPool {
+    fn new(connection: Connection) -> Self {
+        Self {
+            primary: Mutex::new(connection),
+            idle: Mutex::default(),
+        }
+    }
+
+    /// Check out an idle connection, or clone a new one if all are in use.
+    fn get(self: &Arc<Self>) -> Result<PooledConnection, BackendError> {
+        let idle = self.idle.lock().unwrap().pop();
+        let connection = match idle {
+            Some(connection) => connection,
+            None => self
+                .primary
+                .lock()
+                .unwrap()
+                .try_clone()
+                .map_err(|e| BackendError::connection_failed(e.to_string()))?,
+        };
+
+        Ok(PooledConnection {
+            pool: Arc::clone(self),
+            connection: Some(connection),
+        })
+    }
+
+    /// Number of connections waiting to be reused.
+    #[cfg(test)]
+    fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+}
+
+/// A connection checked out of a [`ConnectionPool`], returned to it on drop.
+struct PooledConnection {
+    pool: Arc<ConnectionPool>,
+    connection: Option<Connection>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.idle.lock().unwrap().push(connection);
+        }
+    }
+}
 
 /// DuckDB backend for smelt.
 ///
-/// Wraps a DuckDB connection and implements the Backend trait.
+/// Wraps a pool of DuckDB connections and implements the Backend trait, so
+/// independent models can run concurrently against the same database.
 /// DuckDB operations are synchronous, so they're wrapped in spawn_blocking.
-/// Uses Arc<Mutex<Connection>> since Connection is not Sync.
 pub struct DuckDbBackend {
-    connection: Arc<Mutex<Connection>>,
+    pool: Arc<ConnectionPool>,
+    build_stats: BuildStats,
     #[allow(dead_code)] // Used in new() for schema creation
     schema: String,
+    /// Held until the backend is dropped
+    _lock: DatabaseLock,
 }
 
 impl DuckDbBackend {
     /// Create a new DuckDB backend.
     ///
     /// Opens or creates a database file at the given path and ensures the schema exists.
+    /// Fails with [`BackendError::Locked`] if another backend has the database open.
     pub async fn new(database_path: &Path, schema: &str) -> Result<Self, BackendError> {
+        Self::with_lock_wait(database_path, schema, LockWait::NoWait).await
+    }
+
+    /// Create a new DuckDB backend, waiting per `wait` if another backend
+    /// (usually another smelt process) has the database open.
+    pub async fn with_lock_wait(
+        database_path: &Path,
+        schema: &str,
+        wait: LockWait,
+    ) -> Result<Self, BackendError> {
         let database_path = database_path.to_owned();
         let schema = schema.to_string();
         let schema_for_init = schema.clone();
 
         // Run blocking DuckDB operations in spawn_blocking
-        let connection = tokio::task::spawn_blocking(move || {
+        let (pool, lock) = tokio::task::spawn_blocking(move || {
             // Create parent directory if needed
             if let Some(parent) = database_path.parent() {
                 std::fs::create_dir_all(parent)
                     .with_context(|| format!("Failed to create directory: {:?}", parent))?;
             }
 
+            let lock = DatabaseLock::acquire(&database_path, wait)?;
+
             // Open file-based connection (persistent)
             let connection = Connection::open(&database_path)
                 .with_context(|| format!("Failed to open DuckDB database: {:?}", database_path))?;
@@ -43,49 +155,176 @@ impl DuckDbBackend {
             // Ensure schema exists
             connection
                 .execute(
-                    &format!("CREATE SCHEMA IF NOT EXISTS {}", schema_for_init),
+                    &format!(
+                        "CREATE SCHEMA IF NOT EXISTS {}",
+                        SqlDialect::DuckDB.quote_ident(&schema_for_init)
+                    ),
                     [],
                 )
                 .with_context(|| format!("Failed to create schema: {}", schema_for_init))?;
 
-            Ok::<_, anyhow::Error>(Arc::new(Mutex::new(connection)))
+            Ok::<_, anyhow::Error>((Arc::new(ConnectionPool::new(connection)), lock))
         })
         .await
         .map_err(|e| BackendError::connection_failed(e.to_string()))?
-        .map_err(|e| BackendError::connection_failed(e.to_string()))?;
+        .map_err(|e| match e.downcast::<BackendError>() {
+            Ok(e) => e,
+            Err(e) => BackendError::connection_failed(e.to_string()),
+        })?;
 
-        Ok(Self { connection, schema })
+        Ok(Self {
+            pool,
+            build_stats: BuildStats::default(),
+            schema,
+            _lock: lock,
+        })
     }
 
     /// Check if a table exists in the information schema.
-    pub async fn table_exists_sync(
-        &self,
-        schema: &str,
-        table_name: &str,
-    ) -> Result<bool, BackendError> {
-        let query = "SELECT COUNT(*) > 0 FROM information_schema.tables WHERE table_schema = ? AND table_name = ?";
-        let connection = Arc::clone(&self.connection);
-        let schema = schema.to_string();
-        let table_name = table_name.to_string();
+    pub async fn table_exists_sync(&self, relation: &RelationName) -> Result<bool, BackendError> {
+        let query = "SELECT COUNT(*) > 0 FROM information_schema.tables \
+                     WHERE table_schema = ? AND table_name = ? \
+                     AND table_catalog = COALESCE(?, current_database())";
+        let params = [
+            Some(relation.schema.clone()),
+            Some(relation.name.clone()),
+            relation.catalog.clone(),
+        ];
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
-            conn.query_row(query, [&schema, &table_name], |row| row.get(0))
-                .unwrap_or(false)
+            let conn = pool.get()?;
+            Ok(conn
+                .query_row(query, params, |row| row.get(0))
+                .unwrap_or(false))
         })
         .await
-        .map_err(|e| BackendError::Other(e.into()))
+        .map_err(|e| BackendError::Other(e.into()))?
     }
 }
 
+/// Run `f` with DuckDB's JSON profiler enabled on `conn`, returning its result
+/// and the statistics of the last statement it ran.
+///
+/// Profiling is best effort: if it can't be enabled or its output can't be
+/// read, `f` still runs and the statistics are empty.
+fn profiled<T>(
+    conn: &Connection,
+    f: impl FnOnce() -> duckdb::Result<T>,
+) -> (duckdb::Result<T>, QueryStats) {
+    let path = std::env::temp_dir().join(format!(
+        "smelt-profile-{}-{}.json",
+        std::process::id(),
+        PROFILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let enable = format!(
+        "PRAGMA enable_profiling = 'json';
+         PRAGMA profiling_output = '{}';
+         SET custom_profiling_settings = '{}';",
+        path.display().to_string().replace('\'', "''"),
+        PROFILE_METRICS
+    );
+    if conn.execute_batch(&enable).is_err() {
+        return (f(), QueryStats::default());
+    }
+
+    let result = f();
+    let _ = conn.execute_batch("PRAGMA disable_profiling");
+    let stats = match &result {
+        Ok(_) => std::fs::read_to_string(&path)
+            .map(|json| parse_profile(&json))
+            .unwrap_or_default(),
+        Err(_) => QueryStats::default(),
+    };
+    let _ = std::fs::remove_file(&path);
+
+    (result, stats)
+}
+
+/// Read the query-level metrics from DuckDB's JSON profile output.
+fn parse_profile(json: &str) -> QueryStats {
+    let Ok(profile) = serde_json::from_str::<serde_json::Value>(json) else {
+        return QueryStats::default();
+    };
+
+    QueryStats {
+        backend_time: profile["latency"]
+            .as_f64()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+        rows_scanned: profile["cumulative_rows_scanned"].as_u64(),
+        ..QueryStats::default()
+    }
+}
+
+/// Add a statement's statistics to those recorded for `relation`.
+fn record_stats(build_stats: &BuildStats, relation: &str, stats: QueryStats) {
+    let mut build_stats = build_stats.lock().unwrap();
+    let entry = build_stats.entry(relation.to_string()).or_default();
+    *entry = entry.merge(stats);
+}
+
+/// Convert bound parameters to DuckDB values.
+fn to_values(params: &[SqlParam]) -> Vec<Value> {
+    params
+        .iter()
+        .map(|param| match param {
+            SqlParam::Null => Value::Null,
+            SqlParam::Bool(b) => Value::Boolean(*b),
+            SqlParam::Int(i) => Value::BigInt(*i),
+            SqlParam::Float(f) => Value::Double(*f),
+            SqlParam::Text(s) => Value::Text(s.clone()),
+        })
+        .collect()
+}
+
+/// Run a query, handing each batch to `send` as DuckDB produces it and
+/// stopping early once `send` returns false.
+///
+/// `stream_arrow` needs the result schema up front, which comes from running
+/// the query with `LIMIT 0`. Statements that can't be wrapped in a subquery
+/// (PRAGMA, SHOW, DDL, ...) fall back to `query_arrow`.
+fn stream_query(
+    conn: &Connection,
+    sql: &str,
+    mut send: impl FnMut(RecordBatch) -> bool,
+) -> duckdb::Result<()> {
+    let probe = format!(
+        "SELECT * FROM (\n{}\n) AS smelt_stream LIMIT 0",
+        sql.trim().trim_end_matches(';')
+    );
+    let schema = conn
+        .prepare(&probe)
+        .and_then(|mut stmt| Ok(stmt.query_arrow([])?.get_schema()));
+
+    let mut stmt = conn.prepare(sql)?;
+    match schema {
+        Ok(schema) => {
+            for batch in stmt.stream_arrow([], schema)? {
+                if !send(batch) {
+                    break;
+                }
+            }
+        }
+        Err(_) => {
+            for batch in stmt.query_arrow([])? {
+                if !send(batch) {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[async_trait]
 impl Backend for DuckDbBackend {
     async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, BackendError> {
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
         let sql = sql.to_string();
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             let mut stmt = conn
                 .prepare(&sql)
                 .map_err(|e| BackendError::execution_failed("query", e.to_string()))?;
@@ -100,38 +339,93 @@ impl Backend for DuckDbBackend {
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
+    async fn execute_sql_with_params(
+        &self,
+        sql: &str,
+        params: &[SqlParam],
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        let pool = Arc::clone(&self.pool);
+        let sql = sql.to_string();
+        let values = to_values(params);
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get()?;
+            let mut stmt = conn
+                .prepare(&sql)
+                .map_err(|e| BackendError::execution_failed("query", e.to_string()))?;
+
+            let result = stmt
+                .query_arrow(params_from_iter(values))
+                .map_err(|e| BackendError::execution_failed("query", e.to_string()))?;
+
+            Ok(result.collect())
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
+    async fn execute_sql_stream(&self, sql: &str) -> Result<RecordBatchStream, BackendError> {
+        let pool = Arc::clone(&self.pool);
+        let sql = sql.to_string();
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+
+        // The connection stays checked out until the query finishes or the stream is dropped
+        tokio::task::spawn_blocking(move || {
+            let conn = match pool.get() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(e));
+                    return;
+                }
+            };
+            let result = stream_query(&conn, &sql, |batch| sender.blocking_send(Ok(batch)).is_ok());
+            if let Err(e) = result {
+                let error = BackendError::execution_failed("query", e.to_string());
+                let _ = sender.blocking_send(Err(error));
+            }
+        });
+
+        // Report a query that fails up front as an error rather than a stream item
+        let first = match receiver.recv().await {
+            Some(Err(e)) => return Err(e),
+            first => first,
+        };
+        let rest = futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|item| (item, receiver))
+        });
+
+        Ok(futures::stream::iter(first).chain(rest).boxed())
+    }
+
     async fn create_table_as(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         sql: &str,
     ) -> Result<(), BackendError> {
-        let table_name = format!("{}.{}", schema, name);
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
         let create_sql = format!("CREATE TABLE {} AS {}", table_name, sql);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
+        let build_stats = Arc::clone(&self.build_stats);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
-            conn.execute(&create_sql, [])
+            let conn = pool.get()?;
+            let (result, stats) = profiled(&conn, || conn.execute(&create_sql, []));
+            result
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))?;
+            record_stats(&build_stats, &table_name, stats);
             Ok(())
         })
         .await
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
-    async fn create_view_as(
-        &self,
-        schema: &str,
-        name: &str,
-        sql: &str,
-    ) -> Result<(), BackendError> {
-        let view_name = format!("{}.{}", schema, name);
+    async fn create_view_as(&self, relation: &RelationName, sql: &str) -> Result<(), BackendError> {
+        let view_name = SqlDialect::DuckDB.quote_relation(relation);
         let create_sql = format!("CREATE VIEW {} AS {}", view_name, sql);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&create_sql, [])
                 .map_err(|e| BackendError::execution_failed(view_name.clone(), e.to_string()))?;
             Ok(())
@@ -140,13 +434,113 @@ impl Backend for DuckDbBackend {
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
-    async fn drop_table_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
-        let table_name = format!("{}.{}", schema, name);
+    /// Drop and create in one transaction, so a failed build leaves the
+    /// previous table or view in place.
+    async fn replace_relation(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        materialization: Materialization,
+    ) -> Result<(), BackendError> {
+        let relation_name = SqlDialect::DuckDB.quote_relation(relation);
+        let kind = match materialization {
+            Materialization::Table => "TABLE",
+            Materialization::View => "VIEW",
+        };
+        let replace_sql = format!(
+            "DROP {kind} IF EXISTS {relation};\nCREATE {kind} {relation} AS {sql}",
+            kind = kind,
+            relation = relation_name,
+            sql = sql,
+        );
+        let pool = Arc::clone(&self.pool);
+        let build_stats = Arc::clone(&self.build_stats);
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get()?;
+            let replace = || {
+                conn.execute_batch("BEGIN TRANSACTION")?;
+                let (result, stats) = profiled(&conn, || conn.execute_batch(&replace_sql));
+                result?;
+                conn.execute_batch("COMMIT")?;
+                Ok(stats)
+            };
+            let stats = replace().map_err(|e: duckdb::Error| {
+                let _ = conn.execute_batch("ROLLBACK");
+                BackendError::execution_failed(relation_name.clone(), e.to_string())
+            })?;
+            record_stats(&build_stats, &relation_name, stats);
+            Ok(())
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
+    /// `COPY ... TO` a Parquet file and create a view over it with
+    /// `read_parquet`.
+    ///
+    /// Local files are written under a temporary name and renamed into place,
+    /// so a failed export leaves the previous file readable.
+    async fn export_parquet(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        location: &str,
+    ) -> Result<(), BackendError> {
+        let view_name = SqlDialect::DuckDB.quote_relation(relation);
+        let local = (!location.contains("://")).then(|| PathBuf::from(location));
+        let staging = match local {
+            Some(_) => format!("{}{}", location, STAGING_SUFFIX),
+            None => location.to_string(),
+        };
+        let copy_sql = format!(
+            "COPY ({}) TO '{}' (FORMAT PARQUET)",
+            sql,
+            staging.replace('\'', "''")
+        );
+        let view_sql = format!(
+            "CREATE OR REPLACE VIEW {} AS SELECT * FROM read_parquet('{}')",
+            view_name,
+            location.replace('\'', "''")
+        );
+        let pool = Arc::clone(&self.pool);
+        let build_stats = Arc::clone(&self.build_stats);
+
+        tokio::task::spawn_blocking(move || {
+            let failed = |e: &dyn std::fmt::Display| {
+                BackendError::execution_failed(view_name.clone(), e.to_string())
+            };
+            if let Some(parent) = local.as_ref().and_then(|path| path.parent()) {
+                std::fs::create_dir_all(parent).map_err(|e| failed(&e))?;
+            }
+
+            let conn = pool.get()?;
+            let (result, stats) = profiled(&conn, || conn.execute_batch(&copy_sql));
+            if let Err(e) = result {
+                if local.is_some() {
+                    let _ = std::fs::remove_file(&staging);
+                }
+                return Err(failed(&e));
+            }
+            if let Some(path) = &local {
+                std::fs::rename(&staging, path).map_err(|e| failed(&e))?;
+            }
+
+            conn.execute_batch(&view_sql).map_err(|e| failed(&e))?;
+            record_stats(&build_stats, &view_name, stats);
+            Ok(())
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
+    async fn drop_table_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
         let drop_sql = format!("DROP TABLE IF EXISTS {}", table_name);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&drop_sql, [])
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))?;
             Ok(())
@@ -155,13 +549,13 @@ impl Backend for DuckDbBackend {
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
-    async fn drop_view_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
-        let view_name = format!("{}.{}", schema, name);
+    async fn drop_view_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let view_name = SqlDialect::DuckDB.quote_relation(relation);
         let drop_sql = format!("DROP VIEW IF EXISTS {}", view_name);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&drop_sql, [])
                 .map_err(|e| BackendError::execution_failed(view_name.clone(), e.to_string()))?;
             Ok(())
@@ -170,13 +564,13 @@ impl Backend for DuckDbBackend {
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
-    async fn get_row_count(&self, schema: &str, name: &str) -> Result<usize, BackendError> {
-        let table_name = format!("{}.{}", schema, name);
+    async fn get_row_count(&self, relation: &RelationName) -> Result<usize, BackendError> {
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
         let sql = format!("SELECT COUNT(*) FROM {}", table_name);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.query_row(&sql, [], |row| row.get(0))
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))
         })
@@ -186,16 +580,15 @@ impl Backend for DuckDbBackend {
 
     async fn get_preview(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         limit: usize,
     ) -> Result<Vec<RecordBatch>, BackendError> {
-        let table_name = format!("{}.{}", schema, name);
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
         let sql = format!("SELECT * FROM {} LIMIT {}", table_name, limit);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             let mut stmt = conn
                 .prepare(&sql)
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))?;
@@ -210,16 +603,19 @@ impl Backend for DuckDbBackend {
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
-    async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, BackendError> {
-        self.table_exists_sync(schema, name).await
+    async fn table_exists(&self, relation: &RelationName) -> Result<bool, BackendError> {
+        self.table_exists_sync(relation).await
     }
 
     async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError> {
-        let sql = format!("CREATE SCHEMA IF NOT EXISTS {}", schema);
-        let connection = Arc::clone(&self.connection);
+        let sql = format!(
+            "CREATE SCHEMA IF NOT EXISTS {}",
+            SqlDialect::DuckDB.quote_ident(schema)
+        );
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&sql, [])
                 .map_err(|e| BackendError::execution_failed("schema", e.to_string()))?;
             Ok(())
@@ -236,33 +632,85 @@ impl Backend for DuckDbBackend {
         BackendCapabilities::duckdb()
     }
 
+    fn take_build_stats(&self, relation: &RelationName) -> QueryStats {
+        let relation = SqlDialect::DuckDB.quote_relation(relation);
+        self.build_stats
+            .lock()
+            .unwrap()
+            .remove(&relation)
+            .unwrap_or_default()
+    }
+
+    /// Appends through DuckDB's Arrow appender in one transaction, so a
+    /// failed load leaves the table unchanged. The appender only reaches
+    /// the default catalog.
+    async fn load_record_batches(
+        &self,
+        relation: &RelationName,
+        batches: &[RecordBatch],
+    ) -> Result<(), BackendError> {
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
+        if relation.catalog.is_some() {
+            return Err(BackendError::unsupported(
+                self.dialect().name(),
+                "loading record batches into another catalog",
+            ));
+        }
+        let schema = relation.schema.clone();
+        let name = relation.name.clone();
+        let batches = batches.to_vec();
+        let pool = Arc::clone(&self.pool);
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get()?;
+            let load = || {
+                conn.execute_batch("BEGIN TRANSACTION")?;
+                let mut appender = conn.appender_to_db(&name, &schema)?;
+                for batch in &batches {
+                    for start in (0..batch.num_rows()).step_by(APPEND_CHUNK_SIZE) {
+                        let len = APPEND_CHUNK_SIZE.min(batch.num_rows() - start);
+                        appender.append_record_batch(batch.slice(start, len))?;
+                    }
+                }
+                appender.flush()?;
+                drop(appender);
+                conn.execute_batch("COMMIT")
+            };
+            load().map_err(|e| {
+                let _ = conn.execute_batch("ROLLBACK");
+                BackendError::execution_failed(table_name.clone(), e.to_string())
+            })
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
     async fn delete_partitions(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         partition: &PartitionSpec,
     ) -> Result<(), BackendError> {
-        let table_name = format!("{}.{}", schema, name);
-
-        // Build WHERE clause: column IN ('value1', 'value2', ...)
-        let values_list = partition
-            .values
-            .iter()
-            .map(|v| format!("'{}'", v.replace("'", "''"))) // SQL escape
-            .collect::<Vec<_>>()
-            .join(", ");
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
 
+        let (placeholders, params) = partition.bound_values();
         let delete_sql = format!(
             "DELETE FROM {} WHERE {} IN ({})",
-            table_name, partition.column, values_list
+            table_name,
+            SqlDialect::DuckDB.quote_ident(&partition.column),
+            placeholders
         );
-
-        let connection = Arc::clone(&self.connection);
+        let values = to_values(&params);
+        let pool = Arc::clone(&self.pool);
+        let build_stats = Arc::clone(&self.build_stats);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
-            conn.execute(&delete_sql, [])
+            let conn = pool.get()?;
+            let (result, stats) = profiled(&conn, || {
+                conn.execute(&delete_sql, params_from_iter(values))
+            });
+            result
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))?;
+            record_stats(&build_stats, &table_name, stats);
             Ok(())
         })
         .await
@@ -271,18 +719,70 @@ impl Backend for DuckDbBackend {
 
     async fn insert_into_from_query(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         sql: &str,
     ) -> Result<(), BackendError> {
-        let table_name = format!("{}.{}", schema, name);
+        let table_name = SqlDialect::DuckDB.quote_relation(relation);
         let insert_sql = format!("INSERT INTO {} {}", table_name, sql);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
+        let build_stats = Arc::clone(&self.build_stats);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
-            conn.execute(&insert_sql, [])
+            let conn = pool.get()?;
+            let (result, stats) = profiled(&conn, || conn.execute(&insert_sql, []));
+            result
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))?;
+            record_stats(&build_stats, &table_name, stats);
+            Ok(())
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
+    async fn merge_into_from_query(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        unique_key: &[String],
+    ) -> Result<(), BackendError> {
+        let dialect = SqlDialect::DuckDB;
+        let table_name = dialect.quote_relation(relation);
+        let alias = dialect.quote_ident(&relation.name);
+        let key_match = unique_key
+            .iter()
+            .map(|k| dialect.quote_ident(k))
+            .map(|k| format!("{}.{} = smelt_merge.{}", alias, k, k))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        // Stage the new rows once, then replace matching rows in one transaction
+        let stage_sql = format!("CREATE OR REPLACE TEMP TABLE smelt_merge AS {}", sql);
+        let apply_sql = format!(
+            "DELETE FROM {table} AS {name} USING smelt_merge WHERE {key_match};
+             INSERT INTO {table} SELECT * FROM smelt_merge;
+             DROP TABLE smelt_merge;",
+            table = table_name,
+            name = alias,
+            key_match = key_match,
+        );
+        let pool = Arc::clone(&self.pool);
+        let build_stats = Arc::clone(&self.build_stats);
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get()?;
+            let merge = || {
+                conn.execute_batch("BEGIN TRANSACTION")?;
+                let (result, stats) = profiled(&conn, || conn.execute_batch(&stage_sql));
+                result?;
+                conn.execute_batch(&apply_sql)?;
+                conn.execute_batch("COMMIT")?;
+                Ok(stats)
+            };
+            let stats = merge().map_err(|e: duckdb::Error| {
+                let _ = conn.execute_batch("ROLLBACK");
+                BackendError::execution_failed(table_name.clone(), e.to_string())
+            })?;
+            record_stats(&build_stats, &table_name, stats);
             Ok(())
         })
         .await
@@ -293,7 +793,7 @@ impl Backend for DuckDbBackend {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use smelt_backend::Materialization;
+    use smelt_backend::{IncrementalStrategy, MaterializationStrategy};
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -314,7 +814,12 @@ mod tests {
 
         let sql = "SELECT 1 as id, 'test' as name";
         let result = backend
-            .execute_model("main", "test_model", sql, Materialization::Table, false)
+            .execute_model(
+                &RelationName::new("main", "test_model"),
+                sql,
+                Materialization::Table,
+                false,
+            )
             .await
             .unwrap();
 
@@ -332,7 +837,12 @@ mod tests {
 
         let sql = "SELECT 1 as id, 'test' as name";
         let result = backend
-            .execute_model("main", "test_view", sql, Materialization::View, false)
+            .execute_model(
+                &RelationName::new("main", "test_view"),
+                sql,
+                Materialization::View,
+                false,
+            )
             .await
             .unwrap();
 
@@ -349,7 +859,12 @@ mod tests {
 
         let sql = "SELECT 1 as id UNION SELECT 2 UNION SELECT 3";
         let result = backend
-            .execute_model("main", "test_preview", sql, Materialization::Table, true)
+            .execute_model(
+                &RelationName::new("main", "test_preview"),
+                sql,
+                Materialization::Table,
+                true,
+            )
             .await
             .unwrap();
 
@@ -361,6 +876,220 @@ mod tests {
         assert_eq!(total_rows, 3);
     }
 
+    #[tokio::test]
+    async fn test_build_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                &RelationName::new("main", "numbers"),
+                "SELECT * FROM range(1000) t(n)",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let result = backend
+            .execute_model(
+                &RelationName::new("main", "evens"),
+                "SELECT n FROM main.numbers WHERE n % 2 = 0",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.row_count, 500);
+        assert!(result.stats.backend_time.is_some());
+        assert_eq!(result.stats.rows_scanned, Some(1000));
+
+        // Stats are handed out once per build
+        assert!(backend
+            .take_build_stats(&RelationName::new("main", "evens"))
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_explain() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.numbers AS SELECT * FROM range(10) t(n)")
+            .await
+            .unwrap();
+
+        let sql = "SELECT n FROM main.numbers WHERE n > 5";
+        let plan = backend.explain(sql, false).await.unwrap();
+        assert!(plan.contains("numbers"), "{}", plan);
+
+        let analyzed = backend.explain(sql, true).await.unwrap();
+        assert!(analyzed.contains("Total Time"), "{}", analyzed);
+
+        // Explaining doesn't materialize anything
+        assert_eq!(backend.list_tables("main").await.unwrap(), vec!["numbers"]);
+    }
+
+    #[tokio::test]
+    async fn test_export_parquet() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let location = temp_dir.path().join("lake/events.parquet");
+        let location = location.to_str().unwrap();
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        let result = backend
+            .execute_model_external(
+                &RelationName::new("main", "events"),
+                "SELECT * FROM range(3) t(id)",
+                location,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 3);
+        assert!(Path::new(location).exists());
+
+        // A failed export leaves the previous file and view in place
+        assert!(backend
+            .export_parquet(
+                &RelationName::new("main", "events"),
+                "SELECT * FROM missing",
+                location
+            )
+            .await
+            .is_err());
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "events"))
+                .await
+                .unwrap(),
+            3
+        );
+
+        backend
+            .export_parquet(
+                &RelationName::new("main", "events"),
+                "SELECT * FROM range(5) t(id)",
+                location,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "events"))
+                .await
+                .unwrap(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_build_keeps_previous_relation() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+
+        for materialization in [Materialization::Table, Materialization::View] {
+            let sql = "SELECT 1 AS id UNION ALL SELECT 2";
+            backend
+                .execute_model(
+                    &RelationName::new("main", "model"),
+                    sql,
+                    materialization,
+                    false,
+                )
+                .await
+                .unwrap();
+
+            let err = backend
+                .execute_model(
+                    &RelationName::new("main", "model"),
+                    "SELECT * FROM missing",
+                    materialization,
+                    false,
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(err, BackendError::ExecutionFailed { .. }));
+            assert_eq!(
+                backend
+                    .get_row_count(&RelationName::new("main", "model"))
+                    .await
+                    .unwrap(),
+                2
+            );
+
+            // The failed build's transaction was rolled back
+            let result = backend
+                .execute_model(
+                    &RelationName::new("main", "model"),
+                    "SELECT 3 AS id",
+                    materialization,
+                    false,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.row_count, 1);
+
+            match materialization {
+                Materialization::Table => {
+                    backend
+                        .drop_table_if_exists(&RelationName::new("main", "model"))
+                        .await
+                }
+                Materialization::View => {
+                    backend
+                        .drop_view_if_exists(&RelationName::new("main", "model"))
+                        .await
+                }
+            }
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_queries_use_separate_connections() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        let sql = "SELECT range AS id FROM range(100000)";
+
+        // An open stream holds its connection; other operations get their own
+        let stream = backend.execute_sql_stream(sql).await.unwrap();
+        let timeout = std::time::Duration::from_secs(30);
+        tokio::time::timeout(
+            timeout,
+            backend.create_table_as(&RelationName::new("main", "ids"), "SELECT 1 AS id"),
+        )
+        .await
+        .expect("blocked behind the open stream")
+        .unwrap();
+        let rows = stream
+            .fold(
+                0,
+                |rows, batch| async move { rows + batch.unwrap().num_rows() },
+            )
+            .await;
+        assert_eq!(rows, 100000);
+
+        // Tables created on one connection are visible on the others
+        let idle = backend.pool.idle_count();
+        assert!(idle >= 2, "expected pooled connections, got {}", idle);
+        let connections: Vec<_> = (0..idle).map(|_| backend.pool.get().unwrap()).collect();
+        for conn in &connections {
+            let count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM main.ids", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(count, 1);
+        }
+    }
+
     #[tokio::test]
     async fn test_capabilities() {
         let temp_dir = TempDir::new().unwrap();
@@ -375,4 +1104,397 @@ mod tests {
         assert!(caps.supports_merge);
         assert!(caps.supports_create_or_replace_table);
     }
+
+    #[tokio::test]
+    async fn test_incremental_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                &RelationName::new("main", "orders"),
+                "SELECT * FROM (VALUES (1, '2024-01-01', 10), (2, '2024-01-02', 20)) t(id, day, amount)",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let partition = PartitionSpec {
+            column: "day".to_string(),
+            values: vec!["2024-01-02".to_string()],
+        };
+        let orders = RelationName::new("main", "orders");
+        let run = |strategy| {
+            backend.execute_model_incremental(
+                &orders,
+                "SELECT * FROM (VALUES (2, '2024-01-02', 25), (3, '2024-01-02', 30)) t(id, day, amount)",
+                Materialization::Table,
+                MaterializationStrategy::Incremental {
+                    partition: partition.clone(),
+                    strategy,
+                },
+                false,
+            )
+        };
+
+        let result = run(IncrementalStrategy::Merge {
+            unique_key: vec!["id".to_string()],
+        })
+        .await
+        .unwrap();
+        assert_eq!(result.row_count, 3);
+
+        let batches = backend
+            .execute_sql("SELECT SUM(amount)::BIGINT FROM main.orders")
+            .await
+            .unwrap();
+        let total = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<arrow::array::Int64Array>()
+            .unwrap()
+            .value(0);
+        assert_eq!(total, 10 + 25 + 30);
+
+        let err = run(IncrementalStrategy::InsertOverwrite).await.unwrap_err();
+        assert!(matches!(err, BackendError::UnsupportedFeature { .. }));
+
+        let err = run(IncrementalStrategy::Merge { unique_key: vec![] })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BackendError::ConfigurationError { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_quoted_identifiers() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        // A schema with a space, a keyword table name, and columns with spaces
+        let backend = DuckDbBackend::new(&db_path, "Sales Data").await.unwrap();
+        let order = RelationName::new("Sales Data", "order");
+        backend
+            .execute_model(
+                &order,
+                r#"SELECT * FROM (VALUES (1, '2024-01-01'), (2, '2024-01-02')) t("order id", "order day")"#,
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(backend.table_exists(&order).await.unwrap());
+
+        let partition = PartitionSpec {
+            column: "order day".to_string(),
+            values: vec!["2024-01-02".to_string()],
+        };
+        let run = |strategy| {
+            backend.execute_model_incremental(
+                &order,
+                r#"SELECT * FROM (VALUES (2, '2024-01-02'), (3, '2024-01-02')) t("order id", "order day")"#,
+                Materialization::Table,
+                MaterializationStrategy::Incremental {
+                    partition: partition.clone(),
+                    strategy,
+                },
+                false,
+            )
+        };
+        assert_eq!(
+            run(IncrementalStrategy::DeleteInsert)
+                .await
+                .unwrap()
+                .row_count,
+            3
+        );
+        let result = run(IncrementalStrategy::Merge {
+            unique_key: vec!["order id".to_string()],
+        })
+        .await
+        .unwrap();
+        assert_eq!(result.row_count, 3);
+
+        backend
+            .set_comments(
+                &order,
+                Materialization::Table,
+                Some("Orders"),
+                &[("order id".to_string(), "Order key".to_string())],
+            )
+            .await
+            .unwrap();
+
+        backend.drop_table_if_exists(&order).await.unwrap();
+        assert!(!backend.table_exists(&order).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_database_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let first = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        assert!(lock_path(&db_path).ends_with("test.duckdb.lock"));
+
+        let err = DuckDbBackend::new(&db_path, "main")
+            .await
+            .err()
+            .expect("second backend should be locked out");
+        match &err {
+            BackendError::Locked { holder, .. } => {
+                assert_eq!(holder, &format!("process {}", std::process::id()))
+            }
+            other => panic!("expected Locked, got {:?}", other),
+        }
+        assert!(err.to_string().contains("Another run is in progress"));
+
+        let wait = LockWait::Timeout(Duration::from_millis(300));
+        let started = std::time::Instant::now();
+        let err = DuckDbBackend::with_lock_wait(&db_path, "main", wait)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, BackendError::Locked { .. }));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        // A waiting backend opens the database once the first is dropped
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            drop(first);
+        });
+        let second = DuckDbBackend::with_lock_wait(&db_path, "main", LockWait::Forever)
+            .await
+            .unwrap();
+        release.await.unwrap();
+        assert_eq!(second.execute_sql("SELECT 1").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_sql_with_params() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.names (id BIGINT, name VARCHAR)")
+            .await
+            .unwrap();
+        backend
+            .execute_sql_with_params(
+                "INSERT INTO main.names VALUES (?, ?), (?, ?)",
+                &[
+                    SqlParam::Int(1),
+                    "O'Brien".into(),
+                    SqlParam::Int(2),
+                    SqlParam::Null,
+                ],
+            )
+            .await
+            .unwrap();
+
+        let batches = backend
+            .execute_sql_with_params(
+                "SELECT id FROM main.names WHERE name = ?",
+                &["O'Brien".into()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+
+        // Partition values are bound, so quotes in them need no escaping
+        let partition = PartitionSpec {
+            column: "name".to_string(),
+            values: vec![
+                "O'Brien".to_string(),
+                "'); DROP TABLE main.names; --".to_string(),
+            ],
+        };
+        backend
+            .delete_partitions(&RelationName::new("main", "names"), &partition)
+            .await
+            .unwrap();
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "names"))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_record_batches() {
+        use arrow::array::{Int64Array, StringArray};
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.events (id BIGINT, name VARCHAR)")
+            .await
+            .unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let ids: Int64Array = (0..5000).collect();
+        let names: StringArray = (0..5000)
+            .map(|i| (i % 2 == 0).then(|| format!("event '{}'", i)))
+            .collect();
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(ids), Arc::new(names)]).unwrap();
+
+        backend
+            .load_record_batches(
+                &RelationName::new("main", "events"),
+                &[batch.clone(), batch.slice(0, 10)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "events"))
+                .await
+                .unwrap(),
+            5010
+        );
+
+        // A batch that doesn't fit the table loads nothing
+        let narrow = batch.project(&[0]).unwrap();
+        let err = backend
+            .load_record_batches(&RelationName::new("main", "events"), &[batch, narrow])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BackendError::ExecutionFailed { .. }));
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "events"))
+                .await
+                .unwrap(),
+            5010
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_sql_stream() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        let sql = "SELECT range AS id FROM range(100000)";
+
+        let mut stream = backend.execute_sql_stream(sql).await.unwrap();
+        let mut batches = 0;
+        let mut rows = 0;
+        while let Some(batch) = stream.next().await {
+            batches += 1;
+            rows += batch.unwrap().num_rows();
+        }
+        assert!(batches > 1, "expected several batches, got {}", batches);
+        assert_eq!(rows, 100000);
+
+        // Stopping early releases the connection for the next query
+        let stream = backend.execute_sql_stream(sql).await.unwrap();
+        let batches = smelt_backend::collect_limited(stream, 5).await.unwrap();
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 5);
+
+        // Statements that can't be wrapped still stream
+        let stream = backend.execute_sql_stream("PRAGMA version").await.unwrap();
+        assert_eq!(stream.count().await, 1);
+
+        let err = backend
+            .execute_sql_stream("SELECT * FROM missing")
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, BackendError::ExecutionFailed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_catalog_qualified_relations() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let lake_path = temp_dir.path().join("lake.duckdb");
+        backend
+            .execute_sql(&format!("ATTACH '{}' AS lake", lake_path.display()))
+            .await
+            .unwrap();
+
+        let events = RelationName::new("main", "events").with_catalog("lake");
+        assert_eq!(events.to_string(), "lake.main.events");
+        backend
+            .execute_model(
+                &events,
+                "SELECT 1::BIGINT AS id UNION ALL SELECT 2",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert!(backend.table_exists(&events).await.unwrap());
+        assert!(!backend
+            .table_exists(&RelationName::new("main", "events"))
+            .await
+            .unwrap());
+        assert_eq!(backend.get_row_count(&events).await.unwrap(), 2);
+        let columns = backend.get_table_schema(&events).await.unwrap();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0].name, "id");
+
+        let err = backend.load_record_batches(&events, &[]).await.unwrap_err();
+        assert!(matches!(err, BackendError::UnsupportedFeature { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_schema_introspection() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                &RelationName::new("main", "users"),
+                "SELECT 1::BIGINT AS id, 'a' AS name",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let columns = backend
+            .get_table_schema(&RelationName::new("main", "users"))
+            .await
+            .unwrap();
+        let columns: Vec<_> = columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.as_str()))
+            .collect();
+        assert_eq!(columns, vec![("id", "BIGINT"), ("name", "VARCHAR")]);
+
+        assert!(backend
+            .get_table_schema(&RelationName::new("main", "missing"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        backend
+            .create_view_as(
+                &RelationName::new("main", "active_users"),
+                "SELECT * FROM main.users",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.list_tables("main").await.unwrap(),
+            vec!["active_users", "users"]
+        );
+        assert!(backend.list_tables("missing").await.unwrap().is_empty());
+    }
 }
@@ -0,0 +1,101 @@
+//! Advisory lock file guarding a DuckDB database against concurrent runs.
+//!
+//! DuckDB only allows one process to open a database file for writing, and a
+//! second process fails with an opaque I/O error. Each backend instead takes
+//! an exclusive lock on `<database>.lock` before opening the database, so a
+//! concurrent run can report who holds it or wait for it to be released.
+
+use smelt_backend::BackendError;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a waiting backend retries the lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What to do when another process holds the database's lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockWait {
+    /// Fail with [`BackendError::Locked`] straight away
+    #[default]
+    NoWait,
+    /// Wait up to this long, then fail
+    Timeout(Duration),
+    /// Wait until the lock is released
+    Forever,
+}
+
+/// Path of the lock file for `database_path`, e.g. `dev.duckdb.lock`.
+pub fn lock_path(database_path: &Path) -> PathBuf {
+    let mut name = database_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    database_path.with_file_name(name)
+}
+
+/// An exclusive lock on a database, released when dropped or when the
+/// process exits.
+#[derive(Debug)]
+pub(crate) struct DatabaseLock {
+    _file: File,
+}
+
+impl DatabaseLock {
+    /// Lock `database_path`, blocking the thread while waiting per `wait`.
+    pub(crate) fn acquire(database_path: &Path, wait: LockWait) -> Result<Self, BackendError> {
+        let path = lock_path(database_path);
+        let io_error = |e: std::io::Error| {
+            BackendError::connection_failed(format!("Failed to lock {:?}: {}", path, e))
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error)?;
+
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {
+                    let give_up = match wait {
+                        LockWait::NoWait => true,
+                        LockWait::Timeout(timeout) => started.elapsed() >= timeout,
+                        LockWait::Forever => false,
+                    };
+                    if give_up {
+                        return Err(BackendError::Locked {
+                            path: database_path.display().to_string(),
+                            holder: holder(&mut file),
+                        });
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                Err(TryLockError::Error(e)) => return Err(io_error(e)),
+            }
+        }
+
+        // Record who holds the lock, for the error other processes report
+        file.set_len(0)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .map_err(io_error)?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// Who holds the lock, from the process id its holder wrote to the file.
+fn holder(file: &mut File) -> String {
+    let mut contents = String::new();
+    let _ = file
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| file.read_to_string(&mut contents));
+    match contents.trim() {
+        "" => "another process".to_string(),
+        pid => format!("process {}", pid),
+    }
+}
@@ -0,0 +1,168 @@
+//! Key-pair authentication for the Snowflake SQL API.
+//!
+//! Each request carries a short-lived JWT signed (RS256) with the user's
+//! private key. Snowflake identifies the key by the SHA-256 fingerprint of the
+//! public key registered with `ALTER USER ... SET RSA_PUBLIC_KEY`.
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey};
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::RsaPrivateKey;
+use sha2::{Digest, Sha256};
+use smelt_backend::BackendError;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Snowflake rejects tokens valid for more than an hour.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(59 * 60);
+
+/// Tokens are renewed once they have less than this left.
+const TOKEN_RENEW_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Signs JWTs for one Snowflake user, reusing each token until it nears expiry.
+pub struct KeyPairAuth {
+    /// `ACCOUNT.USER`, the token subject.
+    subject: String,
+    /// `ACCOUNT.USER.SHA256:<fingerprint>`, the token issuer.
+    issuer: String,
+    signing_key: SigningKey<Sha256>,
+    cached: Mutex<Option<(String, SystemTime)>>,
+}
+
+impl KeyPairAuth {
+    /// Load an unencrypted PKCS#8 (`BEGIN PRIVATE KEY`) or PKCS#1
+    /// (`BEGIN RSA PRIVATE KEY`) PEM private key.
+    pub fn from_pem(account: &str, user: &str, pem: &str) -> Result<Self, BackendError> {
+        let private_key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .map_err(|e| BackendError::ConfigurationError {
+                message: format!("Invalid Snowflake private key: {}", e),
+            })?;
+
+        let public_key_der = private_key
+            .to_public_key()
+            .to_public_key_der()
+            .map_err(|e| BackendError::ConfigurationError {
+                message: format!("Invalid Snowflake private key: {}", e),
+            })?;
+        let fingerprint = STANDARD.encode(Sha256::digest(public_key_der.as_bytes()));
+
+        let subject = format!("{}.{}", account_identifier(account), user.to_uppercase());
+        Ok(Self {
+            issuer: format!("{}.SHA256:{}", subject, fingerprint),
+            subject,
+            signing_key: SigningKey::new(private_key),
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Base64 SHA-256 fingerprint of the public key, as shown by
+    /// `DESC USER` (`RSA_PUBLIC_KEY_FP`, without the `SHA256:` prefix).
+    pub fn fingerprint(&self) -> &str {
+        self.issuer
+            .rsplit_once("SHA256:")
+            .map(|(_, fingerprint)| fingerprint)
+            .unwrap_or_default()
+    }
+
+    /// A valid token, signing a new one if the cached token is close to expiry.
+    pub fn token(&self) -> String {
+        let now = SystemTime::now();
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if now + TOKEN_RENEW_MARGIN < *expires_at {
+                return token.clone();
+            }
+        }
+
+        let expires_at = now + TOKEN_LIFETIME;
+        let token = self.sign(now, expires_at);
+        *cached = Some((token.clone(), expires_at));
+        token
+    }
+
+    fn sign(&self, issued_at: SystemTime, expires_at: SystemTime) -> String {
+        let seconds = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+        let claims = serde_json::json!({
+            "iss": self.issuer,
+            "sub": self.subject,
+            "iat": seconds(issued_at),
+            "exp": seconds(expires_at),
+        });
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = self.signing_key.sign(signing_input.as_bytes());
+        format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+}
+
+/// The account part of a token subject: upper-cased, without the region
+/// suffix of a legacy locator (`xy12345.us-east-2.aws` -> `XY12345`).
+fn account_identifier(account: &str) -> String {
+    account.split('.').next().unwrap_or(account).to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::signature::Verifier;
+
+    const TEST_KEY: &str = include_str!("../testdata/rsa_key.p8");
+
+    #[test]
+    fn test_token_claims_and_signature() {
+        let auth = KeyPairAuth::from_pem("myorg-myaccount", "smelt_ci", TEST_KEY).unwrap();
+        // `openssl pkey -pubout -outform DER | openssl dgst -sha256 -binary | base64`
+        assert_eq!(
+            auth.fingerprint(),
+            "CBWEIvrHgNERHnfqBK5ym1yD99zC1fu4C6snTeS/9oY="
+        );
+
+        let token = auth.token();
+        assert_eq!(auth.token(), token, "tokens are reused until near expiry");
+
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        assert_eq!(claims["sub"], "MYORG-MYACCOUNT.SMELT_CI");
+        assert_eq!(
+            claims["iss"],
+            "MYORG-MYACCOUNT.SMELT_CI.SHA256:CBWEIvrHgNERHnfqBK5ym1yD99zC1fu4C6snTeS/9oY="
+        );
+        let lifetime = claims["exp"].as_u64().unwrap() - claims["iat"].as_u64().unwrap();
+        assert_eq!(lifetime, TOKEN_LIFETIME.as_secs());
+
+        let private_key = RsaPrivateKey::from_pkcs8_pem(TEST_KEY).unwrap();
+        let verifying_key = VerifyingKey::<Sha256>::new(private_key.to_public_key());
+        let signature =
+            Signature::try_from(URL_SAFE_NO_PAD.decode(parts[2]).unwrap().as_slice()).unwrap();
+        verifying_key
+            .verify(format!("{}.{}", parts[0], parts[1]).as_bytes(), &signature)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_account_identifier_and_invalid_key() {
+        assert_eq!(account_identifier("xy12345.us-east-2.aws"), "XY12345");
+        assert_eq!(account_identifier("myorg-myaccount"), "MYORG-MYACCOUNT");
+
+        let err = KeyPairAuth::from_pem("acct", "user", "not a key")
+            .err()
+            .unwrap();
+        assert!(matches!(err, BackendError::ConfigurationError { .. }));
+    }
+}
@@ -0,0 +1,269 @@
+//! Minimal client for the Snowflake SQL API (`/api/v2/statements`).
+//!
+//! Statements are submitted asynchronously with `POST /api/v2/statements`,
+//! which answers `202` with a statement handle; the handle's status URL is
+//! polled until the statement finishes, and the handle is what
+//! `POST /api/v2/statements/<handle>/cancel` interrupts. Large results are
+//! split into partitions; the first arrives with the final status and the
+//! rest are fetched with `?partition=N`.
+
+use crate::auth::KeyPairAuth;
+use crate::result::ColumnType;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use smelt_backend::{BackendError, SqlParam};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// First delay between status polls; doubles up to `MAX_POLL_INTERVAL`.
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Session context sent with every statement.
+///
+/// No schema is set: smelt qualifies every relation with its schema, and the
+/// target schema may not exist until the first statement creates it.
+#[derive(Debug, Clone)]
+pub struct StatementContext {
+    pub database: String,
+    pub warehouse: String,
+    pub role: Option<String>,
+}
+
+/// A finished statement's columns and rows, across all partitions.
+#[derive(Debug, Default)]
+pub struct StatementResult {
+    pub columns: Vec<ColumnType>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatementResponse {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    statement_handle: Option<String>,
+    #[serde(default)]
+    result_set_meta_data: Option<ResultSetMetaData>,
+    #[serde(default)]
+    data: Vec<Vec<Option<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultSetMetaData {
+    #[serde(default)]
+    row_type: Vec<ColumnType>,
+    #[serde(default)]
+    partition_info: Vec<serde_json::Value>,
+}
+
+/// The `bindings` object for a statement: typed values keyed by their
+/// 1-based placeholder position. The API takes every value as a string.
+fn bindings(params: &[SqlParam]) -> serde_json::Value {
+    let bindings = params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| {
+            let (ty, value) = match param {
+                SqlParam::Null => ("TEXT", serde_json::Value::Null),
+                SqlParam::Bool(b) => ("BOOLEAN", b.to_string().into()),
+                SqlParam::Int(i) => ("FIXED", i.to_string().into()),
+                SqlParam::Float(f) => ("REAL", f.to_string().into()),
+                SqlParam::Text(s) => ("TEXT", s.clone().into()),
+            };
+            let binding = serde_json::json!({ "type": ty, "value": value });
+            ((index + 1).to_string(), binding)
+        })
+        .collect();
+    serde_json::Value::Object(bindings)
+}
+
+/// Sends statements to one account's SQL API endpoint.
+pub struct SqlApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    auth: KeyPairAuth,
+    context: StatementContext,
+    /// Handles of statements that are still running
+    running: Mutex<HashSet<String>>,
+}
+
+/// Keeps a handle in `running` until the statement finishes or its caller
+/// stops waiting for it.
+struct RunningStatement<'a> {
+    running: &'a Mutex<HashSet<String>>,
+    handle: String,
+}
+
+impl Drop for RunningStatement<'_> {
+    fn drop(&mut self) {
+        self.running.lock().unwrap().remove(&self.handle);
+    }
+}
+
+impl SqlApiClient {
+    pub fn new(base_url: &str, auth: KeyPairAuth, context: StatementContext) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth,
+            context,
+            running: Mutex::default(),
+        }
+    }
+
+    /// Run `sql` and wait for its result, reporting failures against `object`.
+    pub async fn execute(&self, object: &str, sql: &str) -> Result<StatementResult, BackendError> {
+        self.execute_with_params(object, sql, &[]).await
+    }
+
+    /// Run `sql` with `params` bound to its `?` placeholders.
+    pub async fn execute_with_params(
+        &self,
+        object: &str,
+        sql: &str,
+        params: &[SqlParam],
+    ) -> Result<StatementResult, BackendError> {
+        let mut body = serde_json::json!({
+            "statement": sql,
+            "database": self.context.database,
+            "warehouse": self.context.warehouse,
+        });
+        if let Some(role) = &self.context.role {
+            body["role"] = role.clone().into();
+        }
+        if !params.is_empty() {
+            body["bindings"] = bindings(params);
+        }
+
+        let url = format!(
+            "{}/api/v2/statements?requestId={}&async=true",
+            self.base_url,
+            uuid::Uuid::new_v4()
+        );
+        let request = self.http.post(url).json(&body);
+        let mut response = self.send(object, request).await?;
+
+        // Still running: poll the statement until it finishes
+        let mut interval = INITIAL_POLL_INTERVAL;
+        let mut registered = None;
+        while response.0 == StatusCode::ACCEPTED {
+            let handle = response.1.statement_handle.clone().ok_or_else(|| {
+                BackendError::execution_failed(object, "Snowflake returned no statement handle")
+            })?;
+            if registered.is_none() {
+                self.running.lock().unwrap().insert(handle.clone());
+                registered = Some(RunningStatement {
+                    running: &self.running,
+                    handle: handle.clone(),
+                });
+            }
+            tokio::time::sleep(interval).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+            response = self
+                .send(object, self.statement_request(&handle, None))
+                .await?;
+        }
+
+        let response = response.1;
+        let Some(metadata) = response.result_set_meta_data else {
+            return Ok(StatementResult::default());
+        };
+
+        let mut rows = response.data;
+        if metadata.partition_info.len() > 1 {
+            let handle = response.statement_handle.ok_or_else(|| {
+                BackendError::execution_failed(object, "Snowflake returned no statement handle")
+            })?;
+            for partition in 1..metadata.partition_info.len() {
+                let request = self.statement_request(&handle, Some(partition));
+                let (_, page) = self.send(object, request).await?;
+                rows.extend(page.data);
+            }
+        }
+
+        Ok(StatementResult {
+            columns: metadata.row_type,
+            rows,
+        })
+    }
+
+    /// Cancel every statement that is still running.
+    ///
+    /// Statements that finish before the request arrives are left as they are.
+    pub async fn cancel_running(&self) -> Result<(), BackendError> {
+        let handles: Vec<String> = self.running.lock().unwrap().iter().cloned().collect();
+        for handle in handles {
+            let url = format!("{}/api/v2/statements/{}/cancel", self.base_url, handle);
+            match self.send("cancel", self.http.post(url)).await {
+                Ok(_) | Err(BackendError::ExecutionFailed { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn statement_request(&self, handle: &str, partition: Option<usize>) -> reqwest::RequestBuilder {
+        let url = format!("{}/api/v2/statements/{}", self.base_url, handle);
+        let request = self.http.get(url);
+        match partition {
+            Some(partition) => request.query(&[("partition", partition)]),
+            None => request,
+        }
+    }
+
+    /// Send an authenticated request, turning error statuses into backend errors.
+    async fn send(
+        &self,
+        object: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<(StatusCode, StatementResponse), BackendError> {
+        let response = request
+            .bearer_auth(self.auth.token())
+            .header("X-Snowflake-Authorization-Token-Type", "KEYPAIR_JWT")
+            .header("Accept", "application/json")
+            .header("User-Agent", concat!("smelt/", env!("CARGO_PKG_VERSION")))
+            .send()
+            .await
+            .map_err(|e| BackendError::connection_failed(e.to_string()))?;
+
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| BackendError::connection_failed(e.to_string()))?;
+        let parsed = serde_json::from_str::<StatementResponse>(&text);
+
+        if status.is_success() {
+            let parsed = parsed.map_err(|e| {
+                BackendError::execution_failed(
+                    object,
+                    format!("Unexpected Snowflake response: {}", e),
+                )
+            })?;
+            return Ok((status, parsed));
+        }
+
+        let message = parsed
+            .ok()
+            .and_then(|r| r.message)
+            .unwrap_or_else(|| text.trim().to_string());
+        Err(match status {
+            // Not retried: a rejected key won't start working on its own
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => BackendError::ConfigurationError {
+                message: format!("Snowflake authentication failed: {}", message),
+            },
+            StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => BackendError::connection_failed(format!(
+                "Snowflake temporarily unavailable ({}): {}",
+                status.as_u16(),
+                message
+            )),
+            _ => BackendError::execution_failed(object, message),
+        })
+    }
+}
@@ -0,0 +1,809 @@
+//! Snowflake backend implementation for smelt.
+//!
+//! Statements run through the Snowflake SQL API (REST) with key-pair (JWT)
+//! authentication, and JSON result sets are converted to Arrow record batches.
+//! Relations are fully qualified as `database.schema.name`, in the target's
+//! database unless a relation names another; the target's database,
+//! warehouse, and (optional) role are sent with every statement.
+//!
+//! Snowflake upper-cases unquoted identifiers, so table and schema lookups
+//! compare upper-cased names and column names come back in lower case.
+
+mod auth;
+mod client;
+mod result;
+
+pub use auth::KeyPairAuth;
+
+use arrow::array::RecordBatch;
+use async_trait::async_trait;
+use client::{SqlApiClient, StatementContext};
+use result::{normalize_identifier, to_record_batch};
+use smelt_backend::{
+    Backend, BackendCapabilities, BackendError, ColumnInfo, Materialization, PartitionSpec,
+    RelationName, SqlDialect, SqlParam,
+};
+use std::path::PathBuf;
+
+/// Connection settings for a Snowflake target.
+#[derive(Debug, Clone)]
+pub struct SnowflakeConfig {
+    /// Account identifier, e.g. `myorg-myaccount` or `xy12345.us-east-2.aws`.
+    pub account: String,
+    pub user: String,
+    pub database: String,
+    pub warehouse: String,
+    pub role: Option<String>,
+    /// Unencrypted PKCS#8 or PKCS#1 PEM private key registered for `user`.
+    pub private_key_path: PathBuf,
+    base_url: Option<String>,
+}
+
+impl SnowflakeConfig {
+    pub fn new(
+        account: impl Into<String>,
+        user: impl Into<String>,
+        database: impl Into<String>,
+        warehouse: impl Into<String>,
+        private_key_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            account: account.into(),
+            user: user.into(),
+            database: database.into(),
+            warehouse: warehouse.into(),
+            role: None,
+            private_key_path: private_key_path.into(),
+            base_url: None,
+        }
+    }
+
+    /// Run statements with this role instead of the user's default role.
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    /// Send requests to this URL instead of `https://<account>.snowflakecomputing.com`,
+    /// e.g. a PrivateLink endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// The SQL API endpoint for this account.
+    pub fn base_url(&self) -> String {
+        self.base_url.clone().unwrap_or_else(|| {
+            // Account URLs use hyphens where identifiers use underscores
+            format!(
+                "https://{}.snowflakecomputing.com",
+                self.account.to_lowercase().replace('_', "-")
+            )
+        })
+    }
+}
+
+/// Snowflake backend for smelt.
+pub struct SnowflakeBackend {
+    client: SqlApiClient,
+    database: String,
+}
+
+impl SnowflakeBackend {
+    /// Create a new Snowflake backend.
+    ///
+    /// Loads the private key and ensures the schema exists, which also checks
+    /// that the account accepts the key.
+    pub async fn new(config: &SnowflakeConfig, schema: &str) -> Result<Self, BackendError> {
+        let pem = std::fs::read_to_string(&config.private_key_path).map_err(|e| {
+            BackendError::ConfigurationError {
+                message: format!(
+                    "Failed to read Snowflake private key {:?}: {}",
+                    config.private_key_path, e
+                ),
+            }
+        })?;
+        let auth = KeyPairAuth::from_pem(&config.account, &config.user, &pem)?;
+
+        let context = StatementContext {
+            database: config.database.clone(),
+            warehouse: config.warehouse.clone(),
+            role: config.role.clone(),
+        };
+        let backend = Self {
+            client: SqlApiClient::new(&config.base_url(), auth, context),
+            database: config.database.clone(),
+        };
+        backend.ensure_schema(schema).await?;
+
+        Ok(backend)
+    }
+
+    /// Build a fully qualified name: database.schema.name, in the target's
+    /// database unless the relation names another.
+    fn qualified_name(&self, relation: &RelationName) -> String {
+        SqlDialect::Snowflake.quote_relation(&relation.or_catalog(&self.database))
+    }
+
+    /// The database holding `relation`, quoted for SQL.
+    fn database_of(&self, relation: &RelationName) -> String {
+        SqlDialect::Snowflake.quote_ident(relation.catalog.as_deref().unwrap_or(&self.database))
+    }
+
+    /// `INFORMATION_SCHEMA` conditions selecting `relation`, with their bound
+    /// values.
+    fn relation_filter(relation: &RelationName) -> (&'static str, [SqlParam; 2]) {
+        (
+            "TABLE_SCHEMA = ? AND TABLE_NAME = ?",
+            [
+                stored_identifier(&relation.schema).into(),
+                stored_identifier(&relation.name).into(),
+            ],
+        )
+    }
+
+    async fn execute_statement(&self, object: &str, sql: &str) -> Result<(), BackendError> {
+        self.client.execute(object, sql).await.map(|_| ())
+    }
+
+    async fn query(
+        &self,
+        object: &str,
+        sql: &str,
+        params: &[SqlParam],
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        let result = self.client.execute_with_params(object, sql, params).await?;
+        Ok(vec![to_record_batch(&result.columns, &result.rows)?])
+    }
+
+    /// First value of the first row, for single-value queries like `COUNT(*)`.
+    async fn query_value(
+        &self,
+        object: &str,
+        sql: &str,
+        params: &[SqlParam],
+    ) -> Result<String, BackendError> {
+        let result = self.client.execute_with_params(object, sql, params).await?;
+        result
+            .rows
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next().flatten())
+            .ok_or_else(|| BackendError::execution_failed(object, "Query returned no value"))
+    }
+}
+
+/// How Snowflake stores `ident` in `INFORMATION_SCHEMA`: upper-cased if it's
+/// written bare, exactly as given if it has to be quoted.
+fn stored_identifier(ident: &str) -> String {
+    if SqlDialect::Snowflake.quote_ident(ident) == ident {
+        ident.to_uppercase()
+    } else {
+        ident.to_string()
+    }
+}
+
+/// MERGE rows from `sql` into `table`, matching on `unique_key` and updating
+/// every other column of `columns`.
+fn merge_sql(table: &str, sql: &str, unique_key: &[String], columns: &[String]) -> String {
+    let quote = |c: &String| SqlDialect::Snowflake.quote_ident(c);
+    let unique_key: Vec<String> = unique_key.iter().map(quote).collect();
+    let columns: Vec<String> = columns.iter().map(quote).collect();
+    let on = unique_key
+        .iter()
+        .map(|k| format!("smelt_target.{} = smelt_source.{}", k, k))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let updates = columns
+        .iter()
+        .filter(|c| !unique_key.iter().any(|k| k.eq_ignore_ascii_case(c)))
+        .map(|c| format!("{} = smelt_source.{}", c, c))
+        .collect::<Vec<_>>();
+    let values = columns
+        .iter()
+        .map(|c| format!("smelt_source.{}", c))
+        .collect::<Vec<_>>();
+
+    let mut merge = format!(
+        "MERGE INTO {} AS smelt_target USING ({}) AS smelt_source ON {}",
+        table, sql, on
+    );
+    if !updates.is_empty() {
+        merge.push_str(&format!(
+            " WHEN MATCHED THEN UPDATE SET {}",
+            updates.join(", ")
+        ));
+    }
+    merge.push_str(&format!(
+        " WHEN NOT MATCHED THEN INSERT ({}) VALUES ({})",
+        columns.join(", "),
+        values.join(", ")
+    ));
+    merge
+}
+
+#[async_trait]
+impl Backend for SnowflakeBackend {
+    async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, BackendError> {
+        self.query("query", sql, &[]).await
+    }
+
+    async fn execute_sql_with_params(
+        &self,
+        sql: &str,
+        params: &[SqlParam],
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        self.query("query", sql, params).await
+    }
+
+    async fn create_table_as(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+    ) -> Result<(), BackendError> {
+        let table_name = self.qualified_name(relation);
+        let create_sql = format!("CREATE TABLE {} AS {}", table_name, sql);
+        self.execute_statement(&table_name, &create_sql).await
+    }
+
+    async fn create_view_as(&self, relation: &RelationName, sql: &str) -> Result<(), BackendError> {
+        let view_name = self.qualified_name(relation);
+        let create_sql = format!("CREATE VIEW {} AS {}", view_name, sql);
+        self.execute_statement(&view_name, &create_sql).await
+    }
+
+    /// `CREATE OR REPLACE` swaps in the new table or view only once it has
+    /// been built, so a failed build leaves the previous one in place.
+    async fn replace_relation(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        materialization: Materialization,
+    ) -> Result<(), BackendError> {
+        let relation_name = self.qualified_name(relation);
+        let kind = match materialization {
+            Materialization::Table => "TABLE",
+            Materialization::View => "VIEW",
+        };
+        let replace_sql = format!("CREATE OR REPLACE {} {} AS {}", kind, relation_name, sql);
+        self.execute_statement(&relation_name, &replace_sql).await
+    }
+
+    async fn drop_table_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let table_name = self.qualified_name(relation);
+        let drop_sql = format!("DROP TABLE IF EXISTS {}", table_name);
+        self.execute_statement(&table_name, &drop_sql).await
+    }
+
+    async fn drop_view_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let view_name = self.qualified_name(relation);
+        let drop_sql = format!("DROP VIEW IF EXISTS {}", view_name);
+        self.execute_statement(&view_name, &drop_sql).await
+    }
+
+    async fn get_row_count(&self, relation: &RelationName) -> Result<usize, BackendError> {
+        let table_name = self.qualified_name(relation);
+        let sql = format!("SELECT COUNT(*) FROM {}", table_name);
+        let count = self.query_value(&table_name, &sql, &[]).await?;
+        count.parse().map_err(|_| {
+            BackendError::execution_failed(table_name, format!("Invalid row count: {}", count))
+        })
+    }
+
+    async fn get_preview(
+        &self,
+        relation: &RelationName,
+        limit: usize,
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        let table_name = self.qualified_name(relation);
+        let sql = format!("SELECT * FROM {} LIMIT {}", table_name, limit);
+        self.query(&table_name, &sql, &[]).await
+    }
+
+    async fn table_exists(&self, relation: &RelationName) -> Result<bool, BackendError> {
+        let (filter, params) = Self::relation_filter(relation);
+        let sql = format!(
+            "SELECT COUNT(*) FROM {}.INFORMATION_SCHEMA.TABLES WHERE {}",
+            self.database_of(relation),
+            filter
+        );
+        let count = self.query_value("table_exists", &sql, &params).await?;
+        Ok(count != "0")
+    }
+
+    async fn get_table_schema(
+        &self,
+        relation: &RelationName,
+    ) -> Result<Vec<ColumnInfo>, BackendError> {
+        let (filter, params) = Self::relation_filter(relation);
+        let sql = format!(
+            "SELECT COLUMN_NAME, DATA_TYPE FROM {}.INFORMATION_SCHEMA.COLUMNS \
+             WHERE {} ORDER BY ORDINAL_POSITION",
+            self.database_of(relation),
+            filter
+        );
+        let result = self
+            .client
+            .execute_with_params(&self.qualified_name(relation), &sql, &params)
+            .await?;
+
+        Ok(result
+            .rows
+            .into_iter()
+            .filter_map(|row| {
+                let mut values = row.into_iter();
+                let name = values.next().flatten()?;
+                let data_type = values.next().flatten().unwrap_or_default();
+                Some(ColumnInfo {
+                    name: normalize_identifier(&name),
+                    data_type,
+                })
+            })
+            .collect())
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BackendError> {
+        let sql = format!(
+            "SELECT TABLE_NAME FROM {}.INFORMATION_SCHEMA.TABLES \
+             WHERE TABLE_SCHEMA = ? ORDER BY TABLE_NAME",
+            SqlDialect::Snowflake.quote_ident(&self.database)
+        );
+        let result = self
+            .client
+            .execute_with_params(schema, &sql, &[stored_identifier(schema).into()])
+            .await?;
+
+        Ok(result
+            .rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next().flatten())
+            .map(|name| normalize_identifier(&name))
+            .collect())
+    }
+
+    /// `EXPLAIN USING TEXT`; Snowflake only reports actual execution
+    /// statistics in its query profile, so there's no `EXPLAIN ANALYZE`.
+    async fn explain(&self, sql: &str, analyze: bool) -> Result<String, BackendError> {
+        if analyze {
+            return Err(BackendError::unsupported(
+                self.dialect().name(),
+                "EXPLAIN ANALYZE",
+            ));
+        }
+        let explain_sql = format!("EXPLAIN USING TEXT {}", sql);
+        self.query_value("EXPLAIN", &explain_sql, &[]).await
+    }
+
+    async fn get_grants(
+        &self,
+        relation: &RelationName,
+    ) -> Result<Vec<(String, String)>, BackendError> {
+        let (filter, params) = Self::relation_filter(relation);
+        let sql = format!(
+            "SELECT PRIVILEGE_TYPE, GRANTEE FROM {}.INFORMATION_SCHEMA.TABLE_PRIVILEGES \
+             WHERE {} AND GRANTEE <> GRANTOR",
+            self.database_of(relation),
+            filter
+        );
+        let object = self.qualified_name(relation);
+        let result = self
+            .client
+            .execute_with_params(&object, &sql, &params)
+            .await?;
+
+        Ok(result
+            .rows
+            .into_iter()
+            .filter_map(|row| {
+                let mut values = row.into_iter();
+                Some((values.next()??, values.next()??))
+            })
+            .collect())
+    }
+
+    async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError> {
+        let dialect = SqlDialect::Snowflake;
+        let sql = format!(
+            "CREATE SCHEMA IF NOT EXISTS {}.{}",
+            dialect.quote_ident(&self.database),
+            dialect.quote_ident(schema)
+        );
+        self.execute_statement("schema", &sql).await
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::Snowflake
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::snowflake()
+    }
+
+    async fn version(&self) -> Result<String, BackendError> {
+        self.query_value("version", "SELECT CURRENT_VERSION()", &[])
+            .await
+    }
+
+    async fn cancel(&self) -> Result<(), BackendError> {
+        self.client.cancel_running().await
+    }
+
+    async fn delete_partitions(
+        &self,
+        relation: &RelationName,
+        partition: &PartitionSpec,
+    ) -> Result<(), BackendError> {
+        let table_name = self.qualified_name(relation);
+
+        let (placeholders, params) = partition.bound_values();
+        let delete_sql = format!(
+            "DELETE FROM {} WHERE {} IN ({})",
+            table_name,
+            SqlDialect::Snowflake.quote_ident(&partition.column),
+            placeholders
+        );
+        self.client
+            .execute_with_params(&table_name, &delete_sql, &params)
+            .await
+            .map(|_| ())
+    }
+
+    async fn insert_into_from_query(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+    ) -> Result<(), BackendError> {
+        let table_name = self.qualified_name(relation);
+        let insert_sql = format!("INSERT INTO {} {}", table_name, sql);
+        self.execute_statement(&table_name, &insert_sql).await
+    }
+
+    /// Native MERGE: rows matching `unique_key` are updated in place and the
+    /// rest inserted. The column list comes from the existing table.
+    async fn merge_into_from_query(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        unique_key: &[String],
+    ) -> Result<(), BackendError> {
+        let table_name = self.qualified_name(relation);
+        let columns: Vec<String> = self
+            .get_table_schema(relation)
+            .await?
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        if columns.is_empty() {
+            return Err(BackendError::not_found(&relation.schema, &relation.name));
+        }
+
+        let merge = merge_sql(&table_name, sql, unique_key, &columns);
+        self.execute_statement(&table_name, &merge).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A recorded request: request line plus body.
+    type Requests = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Serve canned SQL API responses on localhost, one connection per request.
+    async fn mock_server(
+        respond: impl Fn(&str, &str) -> (u16, String) + Send + Sync + 'static,
+    ) -> (String, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests: Requests = Arc::default();
+        let recorded = Arc::clone(&requests);
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buffer.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buffer).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| {
+                                let (name, value) = l.split_once(':')?;
+                                name.eq_ignore_ascii_case("content-length")
+                                    .then(|| value.trim().parse::<usize>().ok())?
+                            })
+                            .unwrap_or(0);
+                        if body.len() >= length {
+                            break (head.to_string(), body.to_string());
+                        }
+                    }
+                };
+
+                assert!(head.contains("authorization: Bearer "));
+                assert!(head.contains("x-snowflake-authorization-token-type: KEYPAIR_JWT"));
+                let request_line = head.lines().next().unwrap().to_string();
+                let (status, response) = respond(&request_line, &body);
+                recorded.lock().unwrap().push((request_line, body));
+
+                let reply = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    response.len(),
+                    response
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+
+        (format!("http://{}", address), requests)
+    }
+
+    fn result(columns: &[(&str, &str)], rows: &[&[&str]], partitions: usize) -> String {
+        serde_json::json!({
+            "statementHandle": "h1",
+            "resultSetMetaData": {
+                "rowType": columns
+                    .iter()
+                    .map(|(name, ty)| serde_json::json!({
+                        "name": name, "type": ty, "precision": 18, "scale": 0
+                    }))
+                    .collect::<Vec<_>>(),
+                "partitionInfo": vec![serde_json::json!({}); partitions],
+            },
+            "data": rows,
+        })
+        .to_string()
+    }
+
+    async fn backend(base_url: &str) -> SnowflakeBackend {
+        let key_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/rsa_key.p8");
+        let config = SnowflakeConfig::new("acct", "smelt", "ANALYTICS", "COMPUTE_WH", key_path)
+            .with_role("TRANSFORMER")
+            .with_base_url(base_url);
+        SnowflakeBackend::new(&config, "staging").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_query_polls_and_fetches_partitions() {
+        let (url, requests) = mock_server(|request_line, body| {
+            if request_line.starts_with("POST") && body.contains("SELECT id") {
+                (202, r#"{"statementHandle": "h1", "code": "333334"}"#.into())
+            } else if request_line.contains("partition=1") {
+                (200, r#"{"data": [["2"], ["3"]]}"#.into())
+            } else if request_line.starts_with("GET") {
+                (200, result(&[("ID", "fixed")], &[&["1"]], 2))
+            } else {
+                (200, result(&[("status", "text")], &[&["ok"]], 1))
+            }
+        })
+        .await;
+        let backend = backend(&url).await;
+
+        let batches = backend.execute_sql("SELECT id FROM t").await.unwrap();
+        assert_eq!(batches[0].num_rows(), 3);
+        assert_eq!(batches[0].schema().field(0).name(), "id");
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0]
+            .1
+            .contains("CREATE SCHEMA IF NOT EXISTS ANALYTICS.staging"));
+        assert!(requests[0].0.contains("async=true"));
+        assert!(requests[0].1.contains(r#""role":"TRANSFORMER""#));
+        assert!(requests[0].1.contains(r#""warehouse":"COMPUTE_WH""#));
+        assert!(requests[2].0.starts_with("GET /api/v2/statements/h1 "));
+        assert!(requests[3].0.contains("/api/v2/statements/h1?partition=1"));
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_errors() {
+        let (url, requests) = mock_server(|_, body| {
+            if body.contains("INFORMATION_SCHEMA.COLUMNS") {
+                let rows: &[&[&str]] = &[&["ID", "NUMBER"], &["DAY", "TEXT"], &["AMOUNT", "NUMBER"]];
+                (200, result(&[("COLUMN_NAME", "text"), ("DATA_TYPE", "text")], rows, 1))
+            } else if body.contains("missing_table") {
+                let error = r#"{"code": "002003", "message": "SQL compilation error: Object does not exist"}"#;
+                (422, error.into())
+            } else if body.contains("SELECT COUNT(*)") {
+                (503, "Service Unavailable".into())
+            } else {
+                (200, result(&[("status", "text")], &[&["ok"]], 1))
+            }
+        })
+        .await;
+        let backend = backend(&url).await;
+
+        backend
+            .merge_into_from_query(
+                &RelationName::new("staging", "orders"),
+                "SELECT * FROM new_orders",
+                &["id".into()],
+            )
+            .await
+            .unwrap();
+        let merge = requests.lock().unwrap().last().unwrap().1.clone();
+        let merge: serde_json::Value = serde_json::from_str(&merge).unwrap();
+        assert_eq!(
+            merge["statement"],
+            "MERGE INTO ANALYTICS.staging.orders AS smelt_target \
+             USING (SELECT * FROM new_orders) AS smelt_source \
+             ON smelt_target.id = smelt_source.id \
+             WHEN MATCHED THEN UPDATE SET day = smelt_source.day, amount = smelt_source.amount \
+             WHEN NOT MATCHED THEN INSERT (id, day, amount) \
+             VALUES (smelt_source.id, smelt_source.day, smelt_source.amount)"
+        );
+
+        let partition = PartitionSpec {
+            column: "day".to_string(),
+            values: vec!["2024-01-01".to_string(), "O'Brien".to_string()],
+        };
+        backend
+            .delete_partitions(&RelationName::new("staging", "orders"), &partition)
+            .await
+            .unwrap();
+        let delete = requests.lock().unwrap().last().unwrap().1.clone();
+        let delete: serde_json::Value = serde_json::from_str(&delete).unwrap();
+        assert_eq!(
+            delete["statement"],
+            "DELETE FROM ANALYTICS.staging.orders WHERE day IN (?, ?)"
+        );
+        assert_eq!(
+            delete["bindings"],
+            serde_json::json!({
+                "1": {"type": "TEXT", "value": "2024-01-01"},
+                "2": {"type": "TEXT", "value": "O'Brien"},
+            })
+        );
+
+        let err = backend
+            .execute_sql("SELECT * FROM missing_table")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BackendError::ExecutionFailed { .. }));
+        assert!(err.to_string().contains("Object does not exist"));
+        assert!(!err.is_transient());
+
+        let err = backend
+            .get_row_count(&RelationName::new("staging", "orders"))
+            .await
+            .unwrap_err();
+        assert!(err.is_transient(), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_apply_grants() {
+        let (url, requests) = mock_server(|_, body| {
+            if body.contains("TABLE_PRIVILEGES") {
+                let rows: &[&[&str]] = &[&["SELECT", "ANALYST"], &["SELECT", "INTERN"]];
+                (
+                    200,
+                    result(&[("PRIVILEGE_TYPE", "text"), ("GRANTEE", "text")], rows, 1),
+                )
+            } else {
+                (200, result(&[("status", "text")], &[&["ok"]], 1))
+            }
+        })
+        .await;
+        let backend = backend(&url).await;
+
+        let grants = BTreeMap::from([(
+            "select".to_string(),
+            vec!["analyst".to_string(), "reporter".to_string()],
+        )]);
+        backend
+            .apply_grants(
+                &RelationName::new("staging", "orders"),
+                Materialization::Table,
+                &grants,
+            )
+            .await
+            .unwrap();
+
+        let statements: Vec<String> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, body)| {
+                let body: serde_json::Value = serde_json::from_str(body).ok()?;
+                Some(body["statement"].as_str()?.to_string())
+            })
+            .filter(|sql| sql.starts_with("GRANT") || sql.starts_with("REVOKE"))
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                "REVOKE SELECT ON TABLE staging.orders FROM INTERN",
+                "GRANT SELECT ON TABLE staging.orders TO reporter",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_statement() {
+        let cancelled = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+        let (url, requests) = mock_server(move |request_line, body| {
+            use std::sync::atomic::Ordering;
+            if request_line.starts_with("POST /api/v2/statements/h2/cancel") {
+                flag.store(true, Ordering::SeqCst);
+                (200, r#"{"statementHandle": "h2"}"#.into())
+            } else if body.contains("slow_table") {
+                (202, r#"{"statementHandle": "h2"}"#.into())
+            } else if request_line.contains("/h2") && flag.load(Ordering::SeqCst) {
+                let error = r#"{"code": "000604", "message": "SQL execution canceled"}"#;
+                (422, error.into())
+            } else if request_line.contains("/h2") {
+                (202, r#"{"statementHandle": "h2"}"#.into())
+            } else {
+                (200, result(&[("status", "text")], &[&["ok"]], 1))
+            }
+        })
+        .await;
+        let backend = backend(&url).await;
+
+        let (result, cancel) =
+            tokio::join!(backend.execute_sql("SELECT * FROM slow_table"), async {
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                backend.cancel().await
+            });
+        cancel.unwrap();
+        let err = result.unwrap_err();
+        assert!(
+            err.to_string().contains("SQL execution canceled"),
+            "{}",
+            err
+        );
+        assert!(requests
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(line, _)| line.starts_with("POST /api/v2/statements/h2/cancel")));
+
+        // Nothing left to cancel once the statement has finished
+        backend.cancel().await.unwrap();
+        assert!(!requests
+            .lock()
+            .unwrap()
+            .last()
+            .unwrap()
+            .0
+            .contains("cancel"));
+    }
+
+    #[test]
+    fn test_config() {
+        let config = SnowflakeConfig::new("MyOrg-My_Account", "u", "db", "wh", "key.p8");
+        assert_eq!(
+            config.base_url(),
+            "https://myorg-my-account.snowflakecomputing.com"
+        );
+        assert_eq!(
+            merge_sql("t", "SELECT 1 AS id", &["id".into()], &["id".into()]),
+            "MERGE INTO t AS smelt_target USING (SELECT 1 AS id) AS smelt_source \
+             ON smelt_target.id = smelt_source.id \
+             WHEN NOT MATCHED THEN INSERT (id) VALUES (smelt_source.id)"
+        );
+        assert_eq!(
+            merge_sql(
+                "t",
+                "SELECT 1",
+                &["id".into()],
+                &["id".into(), "Order Date".into()]
+            ),
+            "MERGE INTO t AS smelt_target USING (SELECT 1) AS smelt_source \
+             ON smelt_target.id = smelt_source.id \
+             WHEN MATCHED THEN UPDATE SET \"Order Date\" = smelt_source.\"Order Date\" \
+             WHEN NOT MATCHED THEN INSERT (id, \"Order Date\") \
+             VALUES (smelt_source.id, smelt_source.\"Order Date\")"
+        );
+        assert_eq!(stored_identifier("orders"), "ORDERS");
+        assert_eq!(stored_identifier("Daily Revenue"), "Daily Revenue");
+    }
+}
@@ -0,0 +1,301 @@
+//! Conversion of SQL API result sets to Arrow.
+//!
+//! The SQL API returns every value as a JSON string (or `null`) alongside a
+//! `rowType` describing each column, e.g. `fixed` with a precision and scale
+//! for NUMBER. Dates arrive as days since the epoch and timestamps as
+//! `seconds.fraction` (with a trailing time-zone offset for TIMESTAMP_TZ).
+
+use arrow::array::{
+    ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder, Float64Builder,
+    Int64Builder, RecordBatch, StringBuilder, TimestampNanosecondBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use serde::Deserialize;
+use smelt_backend::BackendError;
+use std::sync::Arc;
+
+/// Metadata for one result column.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnType {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(default)]
+    pub precision: Option<u8>,
+    #[serde(default)]
+    pub scale: Option<i8>,
+}
+
+impl ColumnType {
+    /// The Arrow type used for this column.
+    pub fn data_type(&self) -> DataType {
+        match self.type_name.as_str() {
+            "fixed" => match (self.precision.unwrap_or(38), self.scale.unwrap_or(0)) {
+                (precision, 0) if precision <= 18 => DataType::Int64,
+                (precision, scale) => DataType::Decimal128(precision.clamp(1, 38), scale),
+            },
+            "real" => DataType::Float64,
+            "boolean" => DataType::Boolean,
+            "date" => DataType::Date32,
+            "timestamp_ntz" => DataType::Timestamp(TimeUnit::Nanosecond, None),
+            "timestamp_ltz" | "timestamp_tz" => {
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+            }
+            "binary" => DataType::Binary,
+            // text, time, variant, object, array, ...
+            _ => DataType::Utf8,
+        }
+    }
+}
+
+/// Snowflake stores unquoted identifiers upper-cased; report those in lower
+/// case so they match the names used in model SQL. Quoted mixed-case names
+/// are left alone.
+pub fn normalize_identifier(name: &str) -> String {
+    if name.chars().any(|c| c.is_lowercase()) {
+        name.to_string()
+    } else {
+        name.to_lowercase()
+    }
+}
+
+/// Build a batch from `rowType` metadata and rows of string values.
+pub fn to_record_batch(
+    columns: &[ColumnType],
+    rows: &[Vec<Option<String>>],
+) -> Result<RecordBatch, BackendError> {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|c| Field::new(normalize_identifier(&c.name), c.data_type(), true))
+        .collect();
+
+    let arrays = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let values = rows
+                .iter()
+                .map(|row| row.get(index).and_then(|v| v.as_deref()));
+            build_array(&column.data_type(), values)
+                .map_err(|message| invalid_value(&column.name, &message))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .map_err(|e| BackendError::Other(e.into()))
+}
+
+fn invalid_value(column: &str, message: &str) -> BackendError {
+    BackendError::Other(anyhow::anyhow!(
+        "Unexpected value in column {}: {}",
+        column,
+        message
+    ))
+}
+
+fn build_array<'a>(
+    data_type: &DataType,
+    values: impl Iterator<Item = Option<&'a str>>,
+) -> Result<ArrayRef, String> {
+    fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+        value.parse().map_err(|_| format!("'{}'", value))
+    }
+
+    Ok(match data_type {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                builder.append_option(value.map(parse).transpose()?);
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Decimal128(precision, scale) => {
+            let mut builder = Decimal128Builder::new();
+            for value in values {
+                builder.append_option(value.map(|v| parse_decimal(v, *scale)).transpose()?);
+            }
+            Arc::new(
+                builder
+                    .finish()
+                    .with_precision_and_scale(*precision, *scale)
+                    .map_err(|e| e.to_string())?,
+            )
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                builder.append_option(value.map(parse).transpose()?);
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            for value in values {
+                builder.append_option(value.map(|v| parse(&v.to_lowercase())).transpose()?);
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Date32 => {
+            let mut builder = Date32Builder::new();
+            for value in values {
+                builder.append_option(value.map(parse).transpose()?);
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Timestamp(_, timezone) => {
+            let mut builder = TimestampNanosecondBuilder::new();
+            for value in values {
+                builder.append_option(value.map(parse_timestamp).transpose()?);
+            }
+            Arc::new(builder.finish().with_timezone_opt(timezone.clone()))
+        }
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for value in values {
+                builder.append_option(value.map(parse_hex).transpose()?);
+            }
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                builder.append_option(value);
+            }
+            Arc::new(builder.finish())
+        }
+    })
+}
+
+/// Parse `123.45` as an integer scaled by `10^scale`.
+fn parse_decimal(value: &str, scale: i8) -> Result<i128, String> {
+    let invalid = || format!("'{}'", value);
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let scale = scale.max(0) as usize;
+    if fraction.len() > scale
+        || !(whole.chars().chain(fraction.chars())).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let scaled = format!("{}{:0<width$}", whole, fraction, width = scale);
+    let magnitude: i128 = scaled.parse().map_err(|_| invalid())?;
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// Parse `seconds.fraction[ offset]` into nanoseconds since the epoch.
+fn parse_timestamp(value: &str) -> Result<i64, String> {
+    let invalid = || format!("'{}'", value);
+    let epoch = value.split_whitespace().next().ok_or_else(invalid)?;
+    let (seconds, fraction) = epoch.split_once('.').unwrap_or((epoch, ""));
+    if fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let seconds: i64 = seconds.parse().map_err(|_| invalid())?;
+    let nanos: i64 = format!("{:0<9}", fraction).parse().map_err(|_| invalid())?;
+    let nanos = if epoch.starts_with('-') {
+        -nanos
+    } else {
+        nanos
+    };
+    seconds
+        .checked_mul(1_000_000_000)
+        .and_then(|s| s.checked_add(nanos))
+        .ok_or_else(invalid)
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    if !value.is_ascii() || !value.len().is_multiple_of(2) {
+        return Err(format!("'{}'", value));
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).map_err(|_| format!("'{}'", value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Decimal128Array, Int64Array, StringArray, TimestampNanosecondArray};
+
+    fn column(name: &str, type_name: &str, precision: u8, scale: i8) -> ColumnType {
+        ColumnType {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            precision: Some(precision),
+            scale: Some(scale),
+        }
+    }
+
+    #[test]
+    fn test_to_record_batch() {
+        let columns = vec![
+            column("ID", "fixed", 18, 0),
+            column("AMOUNT", "fixed", 10, 2),
+            column("Name", "text", 0, 0),
+            column("LOADED_AT", "timestamp_ntz", 0, 9),
+        ];
+        let row = |values: [Option<&str>; 4]| values.map(|v| v.map(String::from)).to_vec();
+        let rows = vec![
+            row([
+                Some("1"),
+                Some("12.5"),
+                Some("a"),
+                Some("1704067200.000000000"),
+            ]),
+            row([Some("2"), Some("-0.07"), None, Some("1704067201.5")]),
+        ];
+
+        let batch = to_record_batch(&columns, &rows).unwrap();
+        let schema = batch.schema();
+        let names: Vec<_> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, vec!["id", "amount", "Name", "loaded_at"]);
+
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(ids.values(), &[1, 2]);
+        let amounts = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap();
+        assert_eq!(amounts.values(), &[1250, -7]);
+        let names = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(names.is_null(1));
+        let loaded_at = batch
+            .column(3)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+        assert_eq!(
+            loaded_at.values(),
+            &[1_704_067_200_000_000_000, 1_704_067_201_500_000_000]
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let columns = vec![column("ID", "fixed", 18, 0)];
+        let err = to_record_batch(&columns, &[vec![Some("abc".to_string())]]).unwrap_err();
+        assert!(err.to_string().contains("column ID"));
+
+        assert!(parse_decimal("1.234", 2).is_err());
+        assert_eq!(
+            parse_timestamp("1704067200 1440").unwrap(),
+            1_704_067_200_000_000_000
+        );
+        assert_eq!(parse_hex("00ff").unwrap(), vec![0, 255]);
+    }
+}
@@ -0,0 +1,200 @@
+//! Table formats for the tables a Spark backend creates, and the SQL each
+//! needs for creating tables and overwriting partitions.
+
+use smelt_backend::{PartitionSpec, SqlDialect};
+use std::collections::BTreeMap;
+
+/// Storage format of Spark tables, named in their `USING` clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Delta,
+    Iceberg,
+    Parquet,
+}
+
+impl TableFormat {
+    /// Whether tables in this format support MERGE and CREATE OR REPLACE TABLE.
+    pub fn is_transactional(self) -> bool {
+        matches!(self, TableFormat::Delta | TableFormat::Iceberg)
+    }
+}
+
+impl std::fmt::Display for TableFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableFormat::Delta => write!(f, "delta"),
+            TableFormat::Iceberg => write!(f, "iceberg"),
+            TableFormat::Parquet => write!(f, "parquet"),
+        }
+    }
+}
+
+impl std::str::FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "delta" => Ok(TableFormat::Delta),
+            "iceberg" => Ok(TableFormat::Iceberg),
+            "parquet" => Ok(TableFormat::Parquet),
+            _ => Err(format!(
+                "Unknown table_format: {} (expected delta, iceberg, or parquet)",
+                s
+            )),
+        }
+    }
+}
+
+/// Options for every table a Spark backend creates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableOptions {
+    /// `USING` clause; without one the catalog's default format is used
+    pub format: Option<TableFormat>,
+    /// `TBLPROPERTIES`, e.g. `delta.autoOptimize.optimizeWrite: "true"`
+    pub properties: BTreeMap<String, String>,
+}
+
+impl TableOptions {
+    /// `CREATE TABLE ... AS` for the result of `sql`, partitioned by
+    /// `partition_column` if given.
+    pub fn create_table_sql(
+        &self,
+        table: &str,
+        sql: &str,
+        partition_column: Option<&str>,
+    ) -> String {
+        let mut create = format!("CREATE TABLE {}", table);
+        if let Some(format) = self.format {
+            create.push_str(&format!(" USING {}", format));
+        }
+        if let Some(column) = partition_column {
+            create.push_str(&format!(
+                " PARTITIONED BY ({})",
+                SqlDialect::SparkSQL.quote_ident(column)
+            ));
+        }
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, value)| format!("{} = {}", string_literal(key), string_literal(value)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            create.push_str(&format!(" TBLPROPERTIES ({})", properties));
+        }
+        create.push_str(&format!(" AS {}", sql));
+        create
+    }
+
+    /// Statements replacing the rows of `table` in `partition` with the
+    /// rows of `sql` in those partitions.
+    ///
+    /// Delta replaces them with `REPLACE WHERE`. Iceberg and Parquet tables
+    /// use dynamic partition overwrite, which only replaces partitions the
+    /// query writes to, so the table must be partitioned by the partition
+    /// column; Spark stores that column last in Parquet tables, so it must
+    /// also be the model's last column there.
+    pub fn insert_overwrite_sql(
+        &self,
+        table: &str,
+        sql: &str,
+        partition: &PartitionSpec,
+    ) -> Vec<String> {
+        let values = partition
+            .values
+            .iter()
+            .map(|v| string_literal(v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let column = SqlDialect::SparkSQL.quote_ident(&partition.column);
+        let condition = format!("{} IN ({})", column, values);
+        let rows = format!("SELECT * FROM ({}) AS smelt_new WHERE {}", sql, condition);
+
+        let dynamic = "SET spark.sql.sources.partitionOverwriteMode = dynamic".to_string();
+        match self.format {
+            Some(TableFormat::Delta) => vec![format!(
+                "INSERT INTO {} REPLACE WHERE {} {}",
+                table, condition, rows
+            )],
+            Some(TableFormat::Iceberg) => {
+                vec![dynamic, format!("INSERT OVERWRITE {} {}", table, rows)]
+            }
+            Some(TableFormat::Parquet) | None => vec![
+                dynamic,
+                format!(
+                    "INSERT OVERWRITE TABLE {} PARTITION ({}) {}",
+                    table, column, rows
+                ),
+            ],
+        }
+    }
+}
+
+/// A Spark SQL string literal; backslashes escape within them.
+fn string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: TableFormat) -> TableOptions {
+        TableOptions {
+            format: Some(format),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        let mut delta = options(TableFormat::Delta);
+        delta.properties.insert(
+            "delta.autoOptimize.optimizeWrite".to_string(),
+            "true".to_string(),
+        );
+        assert_eq!(
+            delta.create_table_sql("c.s.events", "SELECT 1 AS day", Some("day")),
+            "CREATE TABLE c.s.events USING delta PARTITIONED BY (day) \
+             TBLPROPERTIES ('delta.autoOptimize.optimizeWrite' = 'true') AS SELECT 1 AS day"
+        );
+        assert_eq!(
+            TableOptions::default().create_table_sql("c.s.t", "SELECT 1", None),
+            "CREATE TABLE c.s.t AS SELECT 1"
+        );
+        assert_eq!("ICEBERG".parse(), Ok(TableFormat::Iceberg));
+        assert!("orc".parse::<TableFormat>().is_err());
+    }
+
+    #[test]
+    fn test_insert_overwrite_sql() {
+        let partition = PartitionSpec {
+            column: "day".to_string(),
+            values: vec!["2024-01-01".to_string(), "it's".to_string()],
+        };
+        let rows = "SELECT * FROM (SELECT 1) AS smelt_new WHERE day IN ('2024-01-01', 'it\\'s')";
+
+        assert_eq!(
+            options(TableFormat::Delta).insert_overwrite_sql("t", "SELECT 1", &partition),
+            vec![format!(
+                "INSERT INTO t REPLACE WHERE day IN ('2024-01-01', 'it\\'s') {}",
+                rows
+            )]
+        );
+
+        let iceberg =
+            options(TableFormat::Iceberg).insert_overwrite_sql("t", "SELECT 1", &partition);
+        assert_eq!(
+            iceberg[0],
+            "SET spark.sql.sources.partitionOverwriteMode = dynamic"
+        );
+        assert_eq!(iceberg[1], format!("INSERT OVERWRITE t {}", rows));
+
+        let parquet =
+            options(TableFormat::Parquet).insert_overwrite_sql("t", "SELECT 1", &partition);
+        assert_eq!(
+            parquet[1],
+            format!("INSERT OVERWRITE TABLE t PARTITION (day) {}", rows)
+        );
+    }
+}
@@ -14,9 +14,17 @@
 //! - Convert results to Arrow RecordBatch format
 //! - Handle Spark-specific DDL semantics (catalogs, metastore, etc.)
 
+mod format;
+
+pub use format::{TableFormat, TableOptions};
+
 use arrow::array::RecordBatch;
 use async_trait::async_trait;
-use smelt_backend::{Backend, BackendCapabilities, BackendError, PartitionSpec, SqlDialect};
+use smelt_backend::{
+    Backend, BackendCapabilities, BackendError, Materialization, PartitionSpec, RelationName,
+    SqlDialect,
+};
+use std::collections::BTreeMap;
 
 /// Spark Connect backend for smelt (stub implementation).
 ///
@@ -27,6 +35,7 @@ pub struct SparkBackend {
     connect_url: String,
     catalog: String,
     schema: String,
+    table_options: TableOptions,
 }
 
 impl SparkBackend {
@@ -55,13 +64,20 @@ impl SparkBackend {
             connect_url: connect_url.to_string(),
             catalog: catalog.to_string(),
             schema: schema.to_string(),
+            table_options: TableOptions::default(),
         })
     }
 
-    /// Build a fully qualified table name: catalog.schema.table
-    #[allow(dead_code)]
-    fn qualified_name(&self, schema: &str, name: &str) -> String {
-        format!("{}.{}.{}", self.catalog, schema, name)
+    /// Create tables with the given format and table properties.
+    pub fn with_table_options(mut self, table_options: TableOptions) -> Self {
+        self.table_options = table_options;
+        self
+    }
+
+    /// Build a fully qualified table name: catalog.schema.table, in the
+    /// target's catalog unless the relation names another.
+    fn qualified_name(&self, relation: &RelationName) -> String {
+        SqlDialect::SparkSQL.quote_relation(&relation.or_catalog(&self.catalog))
     }
 }
 
@@ -82,30 +98,48 @@ impl Backend for SparkBackend {
 
     async fn create_table_as(
         &self,
-        schema: &str,
-        name: &str,
-        _sql: &str,
+        relation: &RelationName,
+        sql: &str,
     ) -> Result<(), BackendError> {
         // TODO: Implement table creation
         // Note: Spark may not support CREATE OR REPLACE TABLE in all versions
         // Use DROP IF EXISTS + CREATE TABLE pattern
-        let table_name = self.qualified_name(schema, name);
+        let create_sql =
+            self.table_options
+                .create_table_sql(&self.qualified_name(relation), sql, None);
 
         Err(BackendError::Other(anyhow::anyhow!(
-            "Spark backend stub: would create table {}",
-            table_name
+            "Spark backend stub: would run {}",
+            create_sql
+        )))
+    }
+
+    async fn create_incremental_table_as(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        partition_column: &str,
+    ) -> Result<(), BackendError> {
+        let create_sql = self.table_options.create_table_sql(
+            &self.qualified_name(relation),
+            sql,
+            Some(partition_column),
+        );
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would run {}",
+            create_sql
         )))
     }
 
     async fn create_view_as(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         _sql: &str,
     ) -> Result<(), BackendError> {
         // TODO: Implement view creation
         // Spark supports CREATE OR REPLACE VIEW
-        let view_name = self.qualified_name(schema, name);
+        let view_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would create view {}",
@@ -113,8 +147,8 @@ impl Backend for SparkBackend {
         )))
     }
 
-    async fn drop_table_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
-        let table_name = self.qualified_name(schema, name);
+    async fn drop_table_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let table_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would drop table {}",
@@ -122,8 +156,8 @@ impl Backend for SparkBackend {
         )))
     }
 
-    async fn drop_view_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
-        let view_name = self.qualified_name(schema, name);
+    async fn drop_view_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let view_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would drop view {}",
@@ -131,8 +165,8 @@ impl Backend for SparkBackend {
         )))
     }
 
-    async fn get_row_count(&self, schema: &str, name: &str) -> Result<usize, BackendError> {
-        let table_name = self.qualified_name(schema, name);
+    async fn get_row_count(&self, relation: &RelationName) -> Result<usize, BackendError> {
+        let table_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would count rows in {}",
@@ -142,11 +176,10 @@ impl Backend for SparkBackend {
 
     async fn get_preview(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         _limit: usize,
     ) -> Result<Vec<RecordBatch>, BackendError> {
-        let table_name = self.qualified_name(schema, name);
+        let table_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would preview {}",
@@ -154,8 +187,8 @@ impl Backend for SparkBackend {
         )))
     }
 
-    async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, BackendError> {
-        let table_name = self.qualified_name(schema, name);
+    async fn table_exists(&self, relation: &RelationName) -> Result<bool, BackendError> {
+        let table_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would check if {} exists",
@@ -163,6 +196,63 @@ impl Backend for SparkBackend {
         )))
     }
 
+    async fn export_parquet(
+        &self,
+        relation: &RelationName,
+        _sql: &str,
+        location: &str,
+    ) -> Result<(), BackendError> {
+        // TODO: Write the query result as an external Parquet table
+        // Example pseudo-code:
+        // self.session.sql(&format!(
+        //     "CREATE OR REPLACE TABLE {} USING PARQUET LOCATION '{}' AS {}",
+        //     table_name, location, sql
+        // )).await?;
+        let table_name = self.qualified_name(relation);
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would write {} as Parquet to {}",
+            table_name,
+            location
+        )))
+    }
+
+    async fn set_comments(
+        &self,
+        relation: &RelationName,
+        _materialization: Materialization,
+        _description: Option<&str>,
+        _columns: &[(String, String)],
+    ) -> Result<(), BackendError> {
+        // TODO: Spark comments columns with ALTER TABLE rather than COMMENT ON
+        // Example pseudo-code:
+        // COMMENT ON TABLE {table_name} IS '{description}'
+        // ALTER TABLE {table_name} ALTER COLUMN {column} COMMENT '{description}'
+        let table_name = self.qualified_name(relation);
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would set comments on {}",
+            table_name
+        )))
+    }
+
+    async fn apply_grants(
+        &self,
+        relation: &RelationName,
+        _materialization: Materialization,
+        _grants: &BTreeMap<String, Vec<String>>,
+    ) -> Result<(), BackendError> {
+        // TODO: Diff against SHOW GRANTS ON TABLE, then GRANT/REVOKE
+        // Example pseudo-code:
+        // GRANT SELECT ON TABLE {table_name} TO `analysts`
+        let table_name = self.qualified_name(relation);
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would apply grants on {}",
+            table_name
+        )))
+    }
+
     async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError> {
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would create schema {}.{}",
@@ -176,16 +266,33 @@ impl Backend for SparkBackend {
     }
 
     fn capabilities(&self) -> BackendCapabilities {
-        BackendCapabilities::spark()
+        let mut capabilities = BackendCapabilities::spark();
+        // Without a format the catalog's default decides; assume it's Delta
+        if let Some(format) = self.table_options.format {
+            capabilities.supports_merge = format.is_transactional();
+            capabilities.supports_create_or_replace_table = format.is_transactional();
+        }
+        capabilities
+    }
+
+    async fn cancel(&self) -> Result<(), BackendError> {
+        // TODO: Interrupt running jobs via the Spark Connect Interrupt RPC
+        // Example pseudo-code:
+        // self.session.interrupt_all().await?;
+        // Ok(())
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would interrupt running jobs on {}",
+            self.connect_url
+        )))
     }
 
     async fn delete_partitions(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         _partition: &PartitionSpec,
     ) -> Result<(), BackendError> {
-        let table_name = self.qualified_name(schema, name);
+        let table_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would delete partitions from {}",
@@ -195,17 +302,32 @@ impl Backend for SparkBackend {
 
     async fn insert_into_from_query(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         _sql: &str,
     ) -> Result<(), BackendError> {
-        let table_name = self.qualified_name(schema, name);
+        let table_name = self.qualified_name(relation);
 
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would insert into {}",
             table_name
         )))
     }
+
+    async fn insert_overwrite_partitions(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        partition: &PartitionSpec,
+    ) -> Result<(), BackendError> {
+        let statements =
+            self.table_options
+                .insert_overwrite_sql(&self.qualified_name(relation), sql, partition);
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would run {}",
+            statements.join("; ")
+        )))
+    }
 }
 
 #[cfg(test)]
@@ -234,4 +356,25 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("stub"));
     }
+
+    #[tokio::test]
+    async fn test_table_format() {
+        let backend = SparkBackend::new("sc://localhost:15002", "spark_catalog", "default")
+            .await
+            .unwrap()
+            .with_table_options(TableOptions {
+                format: Some(TableFormat::Parquet),
+                properties: BTreeMap::new(),
+            });
+        assert!(!backend.capabilities().supports_merge);
+
+        let relation = RelationName::new("analytics", "events");
+        let err = backend
+            .create_incremental_table_as(&relation, "SELECT 1 AS day", "day")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(
+            "CREATE TABLE spark_catalog.analytics.events USING parquet PARTITIONED BY (day)"
+        ));
+    }
 }
@@ -0,0 +1,1087 @@
+//! SQLite backend implementation for smelt.
+//!
+//! SQLite has no schemas, so each schema other than `main` is emulated with an
+//! attached database stored next to the main database file: with a target
+//! database of `target/dev.sqlite`, the `staging` schema lives in
+//! `target/dev.staging.sqlite` and is queried as `staging.<table>`. Schema
+//! files found next to the database are attached again when it is reopened.
+//!
+//! SQLite only lets a view reference objects in its own database, so a view
+//! model can't select from a model or source in a different schema; materialize
+//! it as a table instead.
+
+use anyhow::Context;
+use arrow::array::{ArrayRef, BinaryArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{Field, Schema};
+use async_trait::async_trait;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, InterruptHandle};
+use smelt_backend::{
+    Backend, BackendCapabilities, BackendError, ColumnInfo, Materialization, PartitionSpec,
+    RelationName, SqlDialect, SqlParam,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Extension of the main database and attached schema files.
+const SCHEMA_FILE_EXTENSION: &str = "sqlite";
+
+/// SQLite backend for smelt.
+///
+/// Wraps a SQLite connection and implements the Backend trait.
+/// SQLite operations are synchronous, so they're wrapped in spawn_blocking.
+/// Uses Arc<Mutex<Connection>> since Connection is not Sync.
+pub struct SqliteBackend {
+    connection: Arc<Mutex<Connection>>,
+    /// Interrupts the statement running on `connection` without taking its lock
+    interrupt_handle: InterruptHandle,
+    database_path: PathBuf,
+}
+
+impl SqliteBackend {
+    /// Create a new SQLite backend.
+    ///
+    /// Opens or creates a database file at the given path, attaches any schema
+    /// databases created by earlier runs, and ensures the schema exists.
+    pub async fn new(database_path: &Path, schema: &str) -> Result<Self, BackendError> {
+        let database_path = database_path.to_owned();
+        let path_for_init = database_path.clone();
+
+        // Run blocking SQLite operations in spawn_blocking
+        let (connection, interrupt_handle) = tokio::task::spawn_blocking(move || {
+            // Create parent directory if needed
+            if let Some(parent) = path_for_init.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+            }
+
+            let connection = Connection::open(&path_for_init)
+                .with_context(|| format!("Failed to open SQLite database: {:?}", path_for_init))?;
+
+            for (schema, path) in existing_schema_files(&path_for_init)? {
+                attach(&connection, &schema, &path)
+                    .with_context(|| format!("Failed to attach schema database: {:?}", path))?;
+            }
+
+            let interrupt_handle = connection.get_interrupt_handle();
+            Ok::<_, anyhow::Error>((Arc::new(Mutex::new(connection)), interrupt_handle))
+        })
+        .await
+        .map_err(|e| BackendError::connection_failed(e.to_string()))?
+        .map_err(|e| BackendError::connection_failed(e.to_string()))?;
+
+        let backend = Self {
+            connection,
+            interrupt_handle,
+            database_path,
+        };
+        backend.ensure_schema(schema).await?;
+
+        Ok(backend)
+    }
+
+    /// Path of the database file backing `schema`.
+    pub fn schema_path(&self, schema: &str) -> PathBuf {
+        if schema == "main" {
+            self.database_path.clone()
+        } else {
+            schema_file(&self.database_path, schema)
+        }
+    }
+
+    /// Run a closure against the connection on the blocking thread pool.
+    async fn with_connection<T, F>(&self, f: F) -> Result<T, BackendError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, BackendError> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+
+        tokio::task::spawn_blocking(move || {
+            let conn = connection.lock().unwrap();
+            f(&conn)
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
+    /// Execute a statement that returns no rows, reporting errors against `object`.
+    async fn execute_statement(&self, object: String, sql: String) -> Result<(), BackendError> {
+        self.with_connection(move |conn| {
+            conn.execute_batch(&sql)
+                .map_err(|e| BackendError::execution_failed(object, e.to_string()))
+        })
+        .await
+    }
+}
+
+/// `schema.name` for a relation, quoted for SQL. SQLite has no level above its (attached)
+/// schemas, so a relation in a catalog can't be addressed.
+fn qualified_name(relation: &RelationName) -> Result<String, BackendError> {
+    match &relation.catalog {
+        Some(catalog) => Err(BackendError::ConfigurationError {
+            message: format!(
+                "SQLite has no catalogs; can't address {} in catalog '{}'",
+                relation, catalog
+            ),
+        }),
+        None => Ok(SqlDialect::SQLite.quote_relation(relation)),
+    }
+}
+
+/// Path of the attached database holding `schema`, e.g. `dev.staging.sqlite`.
+fn schema_file(database_path: &Path, schema: &str) -> PathBuf {
+    let stem = database_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    database_path.with_file_name(format!("{}.{}.{}", stem, schema, SCHEMA_FILE_EXTENSION))
+}
+
+/// Schema databases left next to `database_path` by earlier runs.
+fn existing_schema_files(database_path: &Path) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let (Some(parent), Some(stem)) = (database_path.parent(), database_path.file_stem()) else {
+        return Ok(Vec::new());
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    let prefix = format!("{}.", stem.to_string_lossy());
+    let suffix = format!(".{}", SCHEMA_FILE_EXTENSION);
+
+    let mut schemas = Vec::new();
+    for entry in std::fs::read_dir(parent)
+        .with_context(|| format!("Failed to read directory: {:?}", parent))?
+    {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let schema = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(&suffix));
+        if let Some(schema) = schema {
+            if is_valid_schema_name(schema) && schema != "main" && schema != "temp" {
+                schemas.push((schema.to_string(), path));
+            }
+        }
+    }
+    schemas.sort();
+
+    Ok(schemas)
+}
+
+/// Schema names become file names and unquoted identifiers, so keep them simple.
+fn is_valid_schema_name(schema: &str) -> bool {
+    !schema.is_empty()
+        && schema
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn attach(conn: &Connection, schema: &str, path: &Path) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "ATTACH DATABASE ?1 AS {}",
+            SqlDialect::SQLite.quote_ident(schema)
+        ),
+        [path.to_string_lossy()],
+    )
+    .map(|_| ())
+}
+
+fn is_attached(conn: &Connection, schema: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_database_list WHERE name = ?1",
+        [schema],
+        |row| row.get(0),
+    )
+}
+
+/// Run a query and convert its rows to Arrow.
+///
+/// SQLite values are dynamically typed, so each column's Arrow type is
+/// inferred from its values: integers become Int64, numbers with any real
+/// value Float64, blobs Binary, and anything else (including all-NULL columns)
+/// Utf8. Statements that return no columns are executed and yield no batches.
+fn query_batches(
+    conn: &Connection,
+    sql: &str,
+    params: &[Value],
+) -> rusqlite::Result<Vec<RecordBatch>> {
+    let mut stmt = conn.prepare(sql)?;
+    if stmt.column_count() == 0 {
+        stmt.execute(params_from_iter(params))?;
+        return Ok(Vec::new());
+    }
+
+    let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut columns: Vec<Vec<Value>> = vec![Vec::new(); names.len()];
+    let mut rows = stmt.query(params_from_iter(params))?;
+    while let Some(row) = rows.next()? {
+        for (index, column) in columns.iter_mut().enumerate() {
+            column.push(row.get(index)?);
+        }
+    }
+
+    let (fields, arrays): (Vec<Field>, Vec<ArrayRef>) = names
+        .into_iter()
+        .zip(columns)
+        .map(|(name, values)| {
+            let array = to_array(values);
+            (Field::new(name, array.data_type().clone(), true), array)
+        })
+        .unzip();
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    Ok(vec![batch])
+}
+
+/// Convert bound parameters to SQLite values; booleans are stored as 0 or 1.
+fn to_values(params: &[SqlParam]) -> Vec<Value> {
+    params
+        .iter()
+        .map(|param| match param {
+            SqlParam::Null => Value::Null,
+            SqlParam::Bool(b) => Value::Integer(*b as i64),
+            SqlParam::Int(i) => Value::Integer(*i),
+            SqlParam::Float(f) => Value::Real(*f),
+            SqlParam::Text(s) => Value::Text(s.clone()),
+        })
+        .collect()
+}
+
+fn to_array(values: Vec<Value>) -> ArrayRef {
+    let is = |f: fn(&Value) -> bool| values.iter().all(|v| matches!(v, Value::Null) || f(v));
+    let any_value = values.iter().any(|v| !matches!(v, Value::Null));
+
+    if any_value && is(|v| matches!(v, Value::Integer(_))) {
+        Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Integer(i) => Some(*i),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        )
+    } else if any_value && is(|v| matches!(v, Value::Integer(_) | Value::Real(_))) {
+        Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Integer(i) => Some(*i as f64),
+                    Value::Real(f) => Some(*f),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        )
+    } else if any_value && is(|v| matches!(v, Value::Blob(_))) {
+        Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Blob(b) => Some(b.as_slice()),
+                    _ => None,
+                })
+                .collect::<BinaryArray>(),
+        )
+    } else {
+        Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Null => None,
+                    Value::Integer(i) => Some(i.to_string()),
+                    Value::Real(f) => Some(f.to_string()),
+                    Value::Text(s) => Some(s.clone()),
+                    Value::Blob(b) => Some(String::from_utf8_lossy(b).into_owned()),
+                })
+                .collect::<StringArray>(),
+        )
+    }
+}
+
+#[async_trait]
+impl Backend for SqliteBackend {
+    async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, BackendError> {
+        let sql = sql.to_string();
+
+        self.with_connection(move |conn| {
+            query_batches(conn, &sql, &[])
+                .map_err(|e| BackendError::execution_failed("query", e.to_string()))
+        })
+        .await
+    }
+
+    async fn execute_sql_with_params(
+        &self,
+        sql: &str,
+        params: &[SqlParam],
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        let sql = sql.to_string();
+        let values = to_values(params);
+
+        self.with_connection(move |conn| {
+            query_batches(conn, &sql, &values)
+                .map_err(|e| BackendError::execution_failed("query", e.to_string()))
+        })
+        .await
+    }
+
+    async fn create_table_as(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+    ) -> Result<(), BackendError> {
+        let table_name = qualified_name(relation)?;
+        let create_sql = format!("CREATE TABLE {} AS {}", table_name, sql);
+        self.execute_statement(table_name, create_sql).await
+    }
+
+    async fn create_view_as(&self, relation: &RelationName, sql: &str) -> Result<(), BackendError> {
+        let view_name = qualified_name(relation)?;
+        let create_sql = format!("CREATE VIEW {} AS {}", view_name, sql);
+        self.execute_statement(view_name, create_sql).await
+    }
+
+    /// Drop and create in one transaction, so a failed build leaves the
+    /// previous table or view in place.
+    async fn replace_relation(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        materialization: Materialization,
+    ) -> Result<(), BackendError> {
+        let relation_name = qualified_name(relation)?;
+        let kind = match materialization {
+            Materialization::Table => "TABLE",
+            Materialization::View => "VIEW",
+        };
+        let replace_sql = format!(
+            "DROP {kind} IF EXISTS {relation};\nCREATE {kind} {relation} AS {sql}",
+            kind = kind,
+            relation = relation_name,
+            sql = sql,
+        );
+
+        self.with_connection(move |conn| {
+            let replace = || {
+                conn.execute_batch("BEGIN")?;
+                conn.execute_batch(&replace_sql)?;
+                // Views aren't checked until queried; preparing one catches bad SQL
+                conn.prepare(&format!("SELECT * FROM {} LIMIT 0", relation_name))?;
+                conn.execute_batch("COMMIT")
+            };
+            replace().map_err(|e| {
+                if !conn.is_autocommit() {
+                    let _ = conn.execute_batch("ROLLBACK");
+                }
+                BackendError::execution_failed(relation_name, e.to_string())
+            })
+        })
+        .await
+    }
+
+    async fn drop_table_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let table_name = qualified_name(relation)?;
+        let drop_sql = format!("DROP TABLE IF EXISTS {}", table_name);
+        self.execute_statement(table_name, drop_sql).await
+    }
+
+    async fn drop_view_if_exists(&self, relation: &RelationName) -> Result<(), BackendError> {
+        let view_name = qualified_name(relation)?;
+        let drop_sql = format!("DROP VIEW IF EXISTS {}", view_name);
+        self.execute_statement(view_name, drop_sql).await
+    }
+
+    async fn get_row_count(&self, relation: &RelationName) -> Result<usize, BackendError> {
+        let table_name = qualified_name(relation)?;
+        let sql = format!("SELECT COUNT(*) FROM {}", table_name);
+
+        self.with_connection(move |conn| {
+            conn.query_row(&sql, [], |row| row.get::<_, i64>(0))
+                .map(|count| count as usize)
+                .map_err(|e| BackendError::execution_failed(table_name, e.to_string()))
+        })
+        .await
+    }
+
+    async fn get_preview(
+        &self,
+        relation: &RelationName,
+        limit: usize,
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        let table_name = qualified_name(relation)?;
+        let sql = format!("SELECT * FROM {} LIMIT {}", table_name, limit);
+
+        self.with_connection(move |conn| {
+            query_batches(conn, &sql, &[])
+                .map_err(|e| BackendError::execution_failed(table_name, e.to_string()))
+        })
+        .await
+    }
+
+    async fn table_exists(&self, relation: &RelationName) -> Result<bool, BackendError> {
+        qualified_name(relation)?;
+        let schema = relation.schema.clone();
+        let name = relation.name.clone();
+
+        self.with_connection(move |conn| {
+            let exists = || -> rusqlite::Result<bool> {
+                if !is_attached(conn, &schema)? {
+                    return Ok(false);
+                }
+                conn.query_row(
+                    &format!(
+                        "SELECT COUNT(*) > 0 FROM {}.sqlite_master \
+                         WHERE type IN ('table', 'view') AND name = ?1",
+                        SqlDialect::SQLite.quote_ident(&schema)
+                    ),
+                    [&name],
+                    |row| row.get(0),
+                )
+            };
+            exists().map_err(|e| BackendError::execution_failed(name.clone(), e.to_string()))
+        })
+        .await
+    }
+
+    /// Columns from `pragma_table_info`, with SQLite's declared type names.
+    ///
+    /// Columns computed by a view have no declared type and are reported with
+    /// an empty type name.
+    async fn get_table_schema(
+        &self,
+        relation: &RelationName,
+    ) -> Result<Vec<ColumnInfo>, BackendError> {
+        qualified_name(relation)?;
+        let schema = relation.schema.clone();
+        let name = relation.name.clone();
+
+        self.with_connection(move |conn| {
+            let columns = || -> rusqlite::Result<Vec<ColumnInfo>> {
+                if !is_attached(conn, &schema)? {
+                    return Ok(Vec::new());
+                }
+                let mut stmt =
+                    conn.prepare("SELECT name, type FROM pragma_table_info(?1, ?2) ORDER BY cid")?;
+                let rows = stmt.query_map([&name, &schema], |row| {
+                    Ok(ColumnInfo {
+                        name: row.get(0)?,
+                        data_type: row.get(1)?,
+                    })
+                })?;
+                rows.collect()
+            };
+            columns().map_err(|e| BackendError::execution_failed(name.clone(), e.to_string()))
+        })
+        .await
+    }
+
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BackendError> {
+        let schema = schema.to_string();
+
+        self.with_connection(move |conn| {
+            let tables = || -> rusqlite::Result<Vec<String>> {
+                if !is_attached(conn, &schema)? {
+                    return Ok(Vec::new());
+                }
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT name FROM {}.sqlite_master \
+                     WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' \
+                     ORDER BY name",
+                    SqlDialect::SQLite.quote_ident(&schema)
+                ))?;
+                let rows = stmt.query_map([], |row| row.get(0))?;
+                rows.collect()
+            };
+            tables().map_err(|e| BackendError::execution_failed(schema.clone(), e.to_string()))
+        })
+        .await
+    }
+
+    /// `EXPLAIN QUERY PLAN`, indented to show the plan's tree.
+    ///
+    /// SQLite has no `EXPLAIN ANALYZE`.
+    async fn explain(&self, sql: &str, analyze: bool) -> Result<String, BackendError> {
+        if analyze {
+            return Err(BackendError::unsupported(
+                self.dialect().name(),
+                "EXPLAIN ANALYZE",
+            ));
+        }
+        let explain_sql = format!("EXPLAIN QUERY PLAN {}", sql);
+
+        self.with_connection(move |conn| {
+            let plan = || -> rusqlite::Result<String> {
+                let mut stmt = conn.prepare(&explain_sql)?;
+                let rows = stmt.query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get(3)?))
+                })?;
+
+                // Rows come parent first, so each parent's depth is known
+                let mut depths = HashMap::new();
+                let mut lines = Vec::new();
+                for row in rows {
+                    let (id, parent, detail): (i64, i64, String) = row?;
+                    let depth = depths.get(&parent).map_or(0, |depth| depth + 1);
+                    depths.insert(id, depth);
+                    lines.push(format!("{}{}", "  ".repeat(depth), detail));
+                }
+                Ok(lines.join("\n"))
+            };
+            plan().map_err(|e| BackendError::execution_failed("EXPLAIN", e.to_string()))
+        })
+        .await
+    }
+
+    /// SQLite has no catalogs above its schemas (attached databases).
+    async fn ensure_catalog_schema(
+        &self,
+        _catalog: &str,
+        _schema: &str,
+    ) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(
+            self.dialect().name(),
+            "databases other than the target's",
+        ))
+    }
+
+    /// Attach the database file for `schema`, creating it if needed.
+    async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError> {
+        if !is_valid_schema_name(schema) {
+            return Err(BackendError::ConfigurationError {
+                message: format!(
+                    "SQLite schema '{}' must contain only letters, digits, and '_'",
+                    schema
+                ),
+            });
+        }
+        if schema == "main" || schema == "temp" {
+            return Ok(());
+        }
+
+        let schema = schema.to_string();
+        let path = self.schema_path(&schema);
+
+        self.with_connection(move |conn| {
+            let result = match is_attached(conn, &schema) {
+                Ok(true) => Ok(()),
+                Ok(false) => attach(conn, &schema, &path),
+                Err(e) => Err(e),
+            };
+            result.map_err(|e| BackendError::execution_failed("schema", e.to_string()))
+        })
+        .await
+    }
+
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::SQLite
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::sqlite()
+    }
+
+    async fn version(&self) -> Result<String, BackendError> {
+        self.with_connection(|conn| {
+            conn.query_row("SELECT sqlite_version()", [], |row| row.get(0))
+                .map_err(|e| BackendError::execution_failed("version", e.to_string()))
+        })
+        .await
+    }
+
+    async fn cancel(&self) -> Result<(), BackendError> {
+        self.interrupt_handle.interrupt();
+        Ok(())
+    }
+
+    async fn delete_partitions(
+        &self,
+        relation: &RelationName,
+        partition: &PartitionSpec,
+    ) -> Result<(), BackendError> {
+        let table_name = qualified_name(relation)?;
+
+        let (placeholders, params) = partition.bound_values();
+        let delete_sql = format!(
+            "DELETE FROM {} WHERE {} IN ({})",
+            table_name,
+            SqlDialect::SQLite.quote_ident(&partition.column),
+            placeholders
+        );
+        let values = to_values(&params);
+
+        self.with_connection(move |conn| {
+            conn.execute(&delete_sql, params_from_iter(values))
+                .map(|_| ())
+                .map_err(|e| BackendError::execution_failed(table_name, e.to_string()))
+        })
+        .await
+    }
+
+    async fn insert_into_from_query(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+    ) -> Result<(), BackendError> {
+        let table_name = qualified_name(relation)?;
+        let insert_sql = format!("INSERT INTO {} {}", table_name, sql);
+        self.execute_statement(table_name, insert_sql).await
+    }
+
+    /// SQLite has no MERGE, so matching rows are deleted and the new rows
+    /// inserted in one transaction.
+    async fn merge_into_from_query(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        unique_key: &[String],
+    ) -> Result<(), BackendError> {
+        let table_name = qualified_name(relation)?;
+        let name = SqlDialect::SQLite.quote_ident(&relation.name);
+        let key_match = unique_key
+            .iter()
+            .map(|k| SqlDialect::SQLite.quote_ident(k))
+            .map(|k| format!("{}.{} = smelt_merge.{}", name, k, k))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        // Stage the new rows once, then replace matching rows in one transaction
+        let merge_sql = format!(
+            "BEGIN;
+             DROP TABLE IF EXISTS temp.smelt_merge;
+             CREATE TEMP TABLE smelt_merge AS {sql};
+             DELETE FROM {table} WHERE EXISTS (SELECT 1 FROM smelt_merge WHERE {key_match});
+             INSERT INTO {table} SELECT * FROM smelt_merge;
+             DROP TABLE temp.smelt_merge;
+             COMMIT;",
+            sql = sql,
+            table = table_name,
+            key_match = key_match,
+        );
+
+        self.with_connection(move |conn| {
+            conn.execute_batch(&merge_sql).map_err(|e| {
+                if !conn.is_autocommit() {
+                    let _ = conn.execute_batch("ROLLBACK");
+                }
+                BackendError::execution_failed(table_name, e.to_string())
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::DataType;
+    use smelt_backend::{IncrementalStrategy, MaterializationStrategy};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_backend_creation() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.sqlite");
+
+        let backend = SqliteBackend::new(&db_path, "main").await.unwrap();
+        assert!(db_path.exists());
+        assert_eq!(backend.dialect(), SqlDialect::SQLite);
+        assert!(!backend.capabilities().supports_qualify);
+    }
+
+    #[tokio::test]
+    async fn test_execute_model_table_and_view() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.sqlite");
+
+        let backend = SqliteBackend::new(&db_path, "main").await.unwrap();
+
+        let result = backend
+            .execute_model(
+                &RelationName::new("main", "numbers"),
+                "SELECT 1 AS id, 'one' AS name UNION ALL SELECT 2, 'two'",
+                Materialization::Table,
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 2);
+        let preview = result.preview.unwrap();
+        assert_eq!(preview[0].schema().field(0).data_type(), &DataType::Int64);
+        assert_eq!(preview[0].schema().field(1).data_type(), &DataType::Utf8);
+
+        let result = backend
+            .execute_model(
+                &RelationName::new("main", "big_numbers"),
+                "SELECT * FROM main.numbers WHERE id > 1",
+                Materialization::View,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 1);
+        assert!(backend
+            .table_exists(&RelationName::new("main", "big_numbers"))
+            .await
+            .unwrap());
+        assert!(!backend
+            .table_exists(&RelationName::new("main", "missing"))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_build_keeps_previous_relation() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.sqlite");
+        let backend = SqliteBackend::new(&db_path, "main").await.unwrap();
+
+        for materialization in [Materialization::Table, Materialization::View] {
+            let sql = "SELECT 1 AS id UNION ALL SELECT 2";
+            backend
+                .execute_model(
+                    &RelationName::new("main", "model"),
+                    sql,
+                    materialization,
+                    false,
+                )
+                .await
+                .unwrap();
+
+            let err = backend
+                .execute_model(
+                    &RelationName::new("main", "model"),
+                    "SELECT * FROM missing",
+                    materialization,
+                    false,
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(err, BackendError::ExecutionFailed { .. }));
+            assert_eq!(
+                backend
+                    .get_row_count(&RelationName::new("main", "model"))
+                    .await
+                    .unwrap(),
+                2
+            );
+
+            // The failed build's transaction was rolled back
+            let result = backend
+                .execute_model(
+                    &RelationName::new("main", "model"),
+                    "SELECT 3 AS id",
+                    materialization,
+                    false,
+                )
+                .await
+                .unwrap();
+            assert_eq!(result.row_count, 1);
+
+            match materialization {
+                Materialization::Table => {
+                    backend
+                        .drop_table_if_exists(&RelationName::new("main", "model"))
+                        .await
+                }
+                Materialization::View => {
+                    backend
+                        .drop_view_if_exists(&RelationName::new("main", "model"))
+                        .await
+                }
+            }
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schemas_are_attached_databases() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("dev.sqlite");
+
+        {
+            let backend = SqliteBackend::new(&db_path, "analytics").await.unwrap();
+            assert_eq!(
+                backend.schema_path("analytics"),
+                temp_dir.path().join("dev.analytics.sqlite")
+            );
+            backend
+                .execute_sql("CREATE TABLE analytics.events (id INTEGER, score REAL)")
+                .await
+                .unwrap();
+            backend
+                .insert_into_from_query(&RelationName::new("analytics", "events"), "SELECT 1, 2.5")
+                .await
+                .unwrap();
+            assert!(!backend
+                .table_exists(&RelationName::new("staging", "events"))
+                .await
+                .unwrap());
+            assert!(backend.ensure_schema("bad-name").await.is_err());
+            let in_catalog = RelationName::new("analytics", "events").with_catalog("lake");
+            assert!(matches!(
+                backend.table_exists(&in_catalog).await,
+                Err(BackendError::ConfigurationError { .. })
+            ));
+        }
+        assert!(temp_dir.path().join("dev.analytics.sqlite").exists());
+
+        // Reopening attaches the schema again
+        let backend = SqliteBackend::new(&db_path, "main").await.unwrap();
+        assert!(backend
+            .table_exists(&RelationName::new("analytics", "events"))
+            .await
+            .unwrap());
+        let columns = backend
+            .get_table_schema(&RelationName::new("analytics", "events"))
+            .await
+            .unwrap();
+        let columns: Vec<_> = columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.as_str()))
+            .collect();
+        assert_eq!(columns, vec![("id", "INTEGER"), ("score", "REAL")]);
+        assert!(backend
+            .get_table_schema(&RelationName::new("missing", "events"))
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            backend.list_tables("analytics").await.unwrap(),
+            vec!["events"]
+        );
+        assert!(backend.list_tables("missing").await.unwrap().is_empty());
+
+        let batches = backend
+            .execute_sql("SELECT score FROM analytics.events")
+            .await
+            .unwrap();
+        let score = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap()
+            .value(0);
+        assert_eq!(score, 2.5);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.sqlite");
+
+        let backend = SqliteBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                &RelationName::new("main", "orders"),
+                "SELECT 1 AS id, '2024-01-01' AS day, 10 AS amount \
+                 UNION ALL SELECT 2, '2024-01-02', 20",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let partition = PartitionSpec {
+            column: "day".to_string(),
+            values: vec!["2024-01-02".to_string()],
+        };
+        let orders = RelationName::new("main", "orders");
+        let run = |strategy| {
+            backend.execute_model_incremental(
+                &orders,
+                "SELECT 2 AS id, '2024-01-02' AS day, 25 AS amount \
+                 UNION ALL SELECT 3, '2024-01-02', 30",
+                Materialization::Table,
+                MaterializationStrategy::Incremental {
+                    partition: partition.clone(),
+                    strategy,
+                },
+                false,
+            )
+        };
+        let total = || async {
+            let batches = backend
+                .execute_sql("SELECT SUM(amount) FROM main.orders")
+                .await
+                .unwrap();
+            batches[0]
+                .column(0)
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .value(0)
+        };
+
+        let result = run(IncrementalStrategy::Merge {
+            unique_key: vec!["id".to_string()],
+        })
+        .await
+        .unwrap();
+        assert_eq!(result.row_count, 3);
+        assert_eq!(total().await, 10 + 25 + 30);
+
+        let result = run(IncrementalStrategy::DeleteInsert).await.unwrap();
+        assert_eq!(result.row_count, 3);
+        assert_eq!(total().await, 10 + 25 + 30);
+
+        let err = run(IncrementalStrategy::InsertOverwrite).await.unwrap_err();
+        assert!(matches!(err, BackendError::UnsupportedFeature { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_execute_sql_with_params() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = SqliteBackend::new(&temp_dir.path().join("dev.sqlite"), "main")
+            .await
+            .unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.names (id INTEGER, name TEXT)")
+            .await
+            .unwrap();
+        backend
+            .execute_sql_with_params(
+                "INSERT INTO main.names VALUES (?, ?), (?, ?)",
+                &[
+                    SqlParam::Int(1),
+                    "O'Brien".into(),
+                    SqlParam::Int(2),
+                    SqlParam::Null,
+                ],
+            )
+            .await
+            .unwrap();
+
+        let batches = backend
+            .execute_sql_with_params(
+                "SELECT id FROM main.names WHERE name = ?",
+                &["O'Brien".into()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+
+        let partition = PartitionSpec {
+            column: "name".to_string(),
+            values: vec!["O'Brien".to_string()],
+        };
+        backend
+            .delete_partitions(&RelationName::new("main", "names"), &partition)
+            .await
+            .unwrap();
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "names"))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = SqliteBackend::new(&temp_dir.path().join("dev.sqlite"), "main")
+            .await
+            .unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.orders (id INTEGER, customer_id INTEGER)")
+            .await
+            .unwrap();
+
+        let sql = "SELECT * FROM main.orders WHERE id IN (SELECT customer_id FROM main.orders)";
+        let plan = backend.explain(sql, false).await.unwrap();
+        assert!(
+            plan.lines().next().unwrap().starts_with("SCAN "),
+            "{}",
+            plan
+        );
+        assert!(plan.lines().any(|line| line.starts_with("  ")), "{}", plan);
+
+        let err = backend.explain(sql, true).await.unwrap_err();
+        assert!(matches!(err, BackendError::UnsupportedFeature { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_load_record_batches() {
+        use arrow::datatypes::DataType;
+
+        let temp_dir = TempDir::new().unwrap();
+        let backend = SqliteBackend::new(&temp_dir.path().join("dev.sqlite"), "main")
+            .await
+            .unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.events (id INTEGER, score REAL, name TEXT)")
+            .await
+            .unwrap();
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("score", DataType::Float64, true),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let ids: arrow::array::Int32Array = (0..3000).collect();
+        let scores: Float64Array = (0..3000).map(|i| Some(i as f64 / 2.0)).collect();
+        let names: StringArray = (0..3000)
+            .map(|i| (i % 2 == 0).then(|| "O'Brien".to_string()))
+            .collect();
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(ids), Arc::new(scores), Arc::new(names)],
+        )
+        .unwrap();
+
+        // More rows than fit in one statement's parameters
+        backend
+            .load_record_batches(&RelationName::new("main", "events"), &[batch])
+            .await
+            .unwrap();
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "events"))
+                .await
+                .unwrap(),
+            3000
+        );
+
+        let batches = backend
+            .execute_sql("SELECT COUNT(*) FROM main.events WHERE name = 'O''Brien'")
+            .await
+            .unwrap();
+        let quoted = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap()
+            .value(0);
+        assert_eq!(quoted, 1500);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_interrupts_running_statement() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.sqlite");
+        let backend = SqliteBackend::new(&db_path, "main").await.unwrap();
+        assert!(backend.capabilities().supports_cancel);
+
+        let endless = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) \
+                       SELECT COUNT(*) FROM n";
+        let (result, cancelled) = tokio::join!(backend.execute_sql(endless), async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            backend.cancel().await
+        });
+        cancelled.unwrap();
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("interrupted"), "{}", err);
+
+        // The connection is usable again afterwards
+        backend.execute_sql("SELECT 1").await.unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 //! SQL dialect definitions and backend capabilities.
 
+use crate::types::RelationName;
+
 /// SQL dialect used by a backend.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SqlDialect {
@@ -9,6 +11,10 @@ pub enum SqlDialect {
     SparkSQL,
     /// PostgreSQL dialect
     PostgreSQL,
+    /// SQLite dialect
+    SQLite,
+    /// Snowflake SQL dialect
+    Snowflake,
 }
 
 impl SqlDialect {
@@ -18,10 +24,80 @@ impl SqlDialect {
             SqlDialect::DuckDB => "DuckDB",
             SqlDialect::SparkSQL => "Spark SQL",
             SqlDialect::PostgreSQL => "PostgreSQL",
+            SqlDialect::SQLite => "SQLite",
+            SqlDialect::Snowflake => "Snowflake",
+        }
+    }
+
+    /// Quote a schema, table, or column name for this dialect if it isn't a
+    /// plain identifier (letters, digits, and `_`, not starting with a digit,
+    /// and not a reserved word).
+    ///
+    /// Plain identifiers are left bare so the engine's case folding still
+    /// matches unquoted references in model SQL (Snowflake upper-cases them).
+    /// PostgreSQL folds to lower case, so names with capitals are quoted there.
+    pub fn quote_ident(&self, ident: &str) -> String {
+        let folds_capitals =
+            *self == SqlDialect::PostgreSQL && ident.chars().any(|c| c.is_ascii_uppercase());
+        if is_plain_identifier(ident) && !folds_capitals {
+            return ident.to_string();
+        }
+
+        let quote = match self {
+            SqlDialect::SparkSQL => '`',
+            _ => '"',
+        };
+        let escaped = ident.replace(quote, &format!("{}{}", quote, quote));
+        format!("{}{}{}", quote, escaped, quote)
+    }
+
+    /// Capabilities of a typical backend speaking this dialect, for compiling
+    /// without a connection; a connected backend's `capabilities()` can differ
+    /// (e.g. Spark's depend on its table format).
+    pub fn capabilities(&self) -> BackendCapabilities {
+        match self {
+            SqlDialect::DuckDB => BackendCapabilities::duckdb(),
+            SqlDialect::SparkSQL => BackendCapabilities::spark(),
+            SqlDialect::PostgreSQL => BackendCapabilities::postgresql(),
+            SqlDialect::SQLite => BackendCapabilities::sqlite(),
+            SqlDialect::Snowflake => BackendCapabilities::snowflake(),
+        }
+    }
+
+    /// A relation's `[catalog.]schema.name`, each part quoted as needed.
+    pub fn quote_relation(&self, relation: &RelationName) -> String {
+        let mut parts = Vec::with_capacity(3);
+        if let Some(catalog) = &relation.catalog {
+            parts.push(self.quote_ident(catalog));
         }
+        parts.push(self.quote_ident(&relation.schema));
+        parts.push(self.quote_ident(&relation.name));
+        parts.join(".")
     }
 }
 
+/// Words reserved in at least one supported dialect, which can't be used as
+/// bare identifiers, separated by whitespace.
+const RESERVED_WORDS: &str =
+    "all alter and any as asc between both by case cast check column constraint create \
+     cross current_date current_time current_timestamp current_user default delete desc \
+     distinct drop else end except exists false fetch for foreign from full grant group \
+     having in inner insert intersect into is join lateral leading left like limit natural \
+     not null offset on or order outer pivot primary qualify references right select set \
+     table then to trailing true union unique unpivot update user using values view when \
+     where window with";
+
+fn is_plain_identifier(ident: &str) -> bool {
+    let mut chars = ident.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED_WORDS
+            .split_whitespace()
+            .any(|word| word.eq_ignore_ascii_case(ident))
+}
+
 /// Capabilities of a backend.
 ///
 /// Used to determine what SQL features can be used directly vs. need rewriting.
@@ -39,6 +115,9 @@ pub struct BackendCapabilities {
     /// Supports MERGE statement (upsert)
     pub supports_merge: bool,
 
+    /// Supports INSERT OVERWRITE of individual partitions
+    pub supports_insert_overwrite: bool,
+
     /// Supports PIVOT/UNPIVOT natively
     pub supports_pivot: bool,
 
@@ -48,11 +127,23 @@ pub struct BackendCapabilities {
     /// Supports || for string concatenation
     pub supports_concat_operator: bool,
 
+    /// Supports `expr::type` casts (otherwise rewritten to CAST(expr AS type))
+    pub supports_double_colon_cast: bool,
+
     /// Supports arrays with [a, b, c] syntax
     pub supports_array_literal: bool,
 
     /// Supports transactional DDL (can rollback CREATE TABLE)
     pub supports_transactional_ddl: bool,
+
+    /// Can interrupt a running statement (see `Backend::cancel`)
+    pub supports_cancel: bool,
+
+    /// Stores comments on tables, views, and columns (see `Backend::set_comments`)
+    pub supports_comments: bool,
+
+    /// Has GRANT/REVOKE privileges on tables and views (see `Backend::apply_grants`)
+    pub supports_grants: bool,
 }
 
 impl BackendCapabilities {
@@ -63,11 +154,16 @@ impl BackendCapabilities {
             supports_create_or_replace_table: true,
             supports_create_or_replace_view: true,
             supports_merge: true,
+            supports_insert_overwrite: false,
             supports_pivot: true,
             supports_date_literal: true,
             supports_concat_operator: true,
+            supports_double_colon_cast: true,
             supports_array_literal: true,
             supports_transactional_ddl: true,
+            supports_cancel: false, // Not exposed by the duckdb crate
+            supports_comments: true,
+            supports_grants: false,
         }
     }
 
@@ -78,11 +174,16 @@ impl BackendCapabilities {
             supports_create_or_replace_table: false, // DROP + CREATE
             supports_create_or_replace_view: true,
             supports_merge: true, // Delta Lake only
+            supports_insert_overwrite: true,
             supports_pivot: true,
             supports_date_literal: false, // Uses DATE('YYYY-MM-DD') function
             supports_concat_operator: true,
-            supports_array_literal: false, // Uses ARRAY(a, b, c)
+            supports_double_colon_cast: false, // Spark 4+ only
+            supports_array_literal: false,     // Uses ARRAY(a, b, c)
             supports_transactional_ddl: false,
+            supports_cancel: true,   // Spark Connect Interrupt
+            supports_comments: true, // COMMENT ON TABLE, ALTER COLUMN ... COMMENT
+            supports_grants: true,   // Unity Catalog / Hive table ACLs
         }
     }
 
@@ -92,12 +193,57 @@ impl BackendCapabilities {
             supports_qualify: false,                 // Requires subquery rewrite
             supports_create_or_replace_table: false, // DROP + CREATE
             supports_create_or_replace_view: true,
-            supports_merge: true,  // PostgreSQL 15+
+            supports_merge: true, // PostgreSQL 15+
+            supports_insert_overwrite: false,
             supports_pivot: false, // Requires crosstab extension
             supports_date_literal: true,
             supports_concat_operator: true,
+            supports_double_colon_cast: true,
             supports_array_literal: false, // Uses ARRAY[a, b, c]
             supports_transactional_ddl: true,
+            supports_cancel: true, // pg_cancel_backend
+            supports_comments: true,
+            supports_grants: true,
+        }
+    }
+
+    /// Capabilities for SQLite
+    pub fn sqlite() -> Self {
+        Self {
+            supports_qualify: false,                 // Requires subquery rewrite
+            supports_create_or_replace_table: false, // DROP + CREATE
+            supports_create_or_replace_view: false,  // DROP + CREATE
+            supports_merge: true,                    // Emulated with DELETE + INSERT
+            supports_insert_overwrite: false,
+            supports_pivot: false,
+            supports_date_literal: false, // Dates are TEXT; use 'YYYY-MM-DD'
+            supports_concat_operator: true,
+            supports_double_colon_cast: false,
+            supports_array_literal: false,
+            supports_transactional_ddl: true,
+            supports_cancel: true,
+            supports_comments: false,
+            supports_grants: false,
+        }
+    }
+
+    /// Capabilities for Snowflake
+    pub fn snowflake() -> Self {
+        Self {
+            supports_qualify: true,
+            supports_create_or_replace_table: true,
+            supports_create_or_replace_view: true,
+            supports_merge: true,
+            supports_insert_overwrite: false, // INSERT OVERWRITE truncates the whole table
+            supports_pivot: true,
+            supports_date_literal: true,
+            supports_concat_operator: true,
+            supports_double_colon_cast: true,
+            supports_array_literal: true,
+            supports_transactional_ddl: false, // DDL commits implicitly
+            supports_cancel: true,
+            supports_comments: true,
+            supports_grants: true,
         }
     }
 }
@@ -25,6 +25,10 @@ pub enum BackendError {
     #[error("Feature not supported by {dialect}: {feature}")]
     UnsupportedFeature { dialect: String, feature: String },
 
+    /// Another process holds the database's lock.
+    #[error("Another run is in progress on {path} ({holder} holds its lock)")]
+    Locked { path: String, holder: String },
+
     /// Configuration error.
     #[error("Configuration error: {message}")]
     ConfigurationError { message: String },
@@ -34,7 +38,53 @@ pub enum BackendError {
     Other(#[from] anyhow::Error),
 }
 
+/// Error message fragments that indicate a failure worth retrying.
+const TRANSIENT_MESSAGES: &[&str] = &[
+    "could not set lock",
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection refused",
+    "temporarily unavailable",
+];
+
+/// Error message fragments that indicate a query names a table or view that
+/// doesn't exist (DuckDB, SQLite, Spark, Snowflake).
+const MISSING_RELATION_MESSAGES: &[&str] = &[
+    "table with name",
+    "no such table",
+    "table_or_view_not_found",
+    "does not exist or not authorized",
+];
+
 impl BackendError {
+    /// Whether the error is likely transient (lost connection, lock contention,
+    /// timeout) so the operation may succeed if retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::ConnectionFailed { .. } => true,
+            Self::ExecutionFailed { message, .. } => {
+                let message = message.to_lowercase();
+                TRANSIENT_MESSAGES.iter().any(|m| message.contains(m))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the error is a query naming a table or view that doesn't exist.
+    pub fn is_missing_relation(&self) -> bool {
+        match self {
+            Self::NotFound { .. } => true,
+            Self::ExecutionFailed { message, .. } => {
+                let message = message.to_lowercase();
+                MISSING_RELATION_MESSAGES
+                    .iter()
+                    .any(|m| message.contains(m))
+            }
+            _ => false,
+        }
+    }
+
     /// Create a connection failed error.
     pub fn connection_failed(message: impl Into<String>) -> Self {
         Self::ConnectionFailed {
@@ -5,14 +5,23 @@
 
 mod dialect;
 mod error;
+mod stream;
 mod types;
 
 pub use dialect::{BackendCapabilities, SqlDialect};
 pub use error::BackendError;
-pub use types::{ExecutionResult, Materialization, MaterializationStrategy, PartitionSpec};
+pub use stream::{collect_limited, RecordBatchStream};
+pub use types::{
+    ColumnInfo, ExecutionResult, IncrementalStrategy, Materialization, MaterializationStrategy,
+    PartitionSpec, QueryStats, RelationName, SqlParam,
+};
 
-use arrow::array::RecordBatch;
+use arrow::array::{Array, AsArray, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Float64Type, Int64Type};
+use arrow::util::display::array_value_to_string;
 use async_trait::async_trait;
+use futures::StreamExt;
+use std::collections::BTreeMap;
 
 /// Abstract interface for smelt execution backends.
 ///
@@ -26,86 +35,447 @@ pub trait Backend: Send + Sync {
     /// Execute a SQL query and return results.
     async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, BackendError>;
 
-    /// Create a table from a SQL query.
-    async fn create_table_as(
+    /// Execute a SQL query and stream its results batch by batch.
+    ///
+    /// The default implementation collects the whole result with
+    /// [`Backend::execute_sql`]; backends that can produce batches
+    /// incrementally should override it so large results aren't held in memory.
+    async fn execute_sql_stream(&self, sql: &str) -> Result<RecordBatchStream, BackendError> {
+        let batches = self.execute_sql(sql).await?;
+        Ok(futures::stream::iter(batches.into_iter().map(Ok)).boxed())
+    }
+
+    /// Execute a SQL query with values bound to its `?` placeholders, in order.
+    ///
+    /// Values are passed to the engine rather than spliced into the SQL, so
+    /// they never need quoting. The default implementation reports the
+    /// feature as unsupported.
+    async fn execute_sql_with_params(
         &self,
-        schema: &str,
-        name: &str,
-        sql: &str,
-    ) -> Result<(), BackendError>;
+        _sql: &str,
+        _params: &[SqlParam],
+    ) -> Result<Vec<RecordBatch>, BackendError> {
+        Err(BackendError::unsupported(
+            self.dialect().name(),
+            "bound parameters",
+        ))
+    }
 
-    /// Create a view from a SQL query.
-    async fn create_view_as(&self, schema: &str, name: &str, sql: &str)
+    /// Create a table from a SQL query.
+    async fn create_table_as(&self, relation: &RelationName, sql: &str)
         -> Result<(), BackendError>;
 
+    /// Create a view from a SQL query.
+    async fn create_view_as(&self, relation: &RelationName, sql: &str) -> Result<(), BackendError>;
+
     /// Drop a table if it exists.
-    async fn drop_table_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError>;
+    async fn drop_table_if_exists(&self, relation: &RelationName) -> Result<(), BackendError>;
 
     /// Drop a view if it exists.
-    async fn drop_view_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError>;
+    async fn drop_view_if_exists(&self, relation: &RelationName) -> Result<(), BackendError>;
 
     /// Get the row count of a table or view.
-    async fn get_row_count(&self, schema: &str, name: &str) -> Result<usize, BackendError>;
+    async fn get_row_count(&self, relation: &RelationName) -> Result<usize, BackendError>;
 
     /// Get a preview of a table or view (first N rows).
     async fn get_preview(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         limit: usize,
     ) -> Result<Vec<RecordBatch>, BackendError>;
 
     /// Check if a table exists.
-    async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, BackendError>;
+    async fn table_exists(&self, relation: &RelationName) -> Result<bool, BackendError>;
+
+    /// Get the columns of a table or view, in order.
+    ///
+    /// The default implementation queries `information_schema.columns`; it
+    /// returns an empty list if the relation doesn't exist.
+    async fn get_table_schema(
+        &self,
+        relation: &RelationName,
+    ) -> Result<Vec<ColumnInfo>, BackendError> {
+        let (filter, params) = information_schema_filter(relation);
+        let sql = format!(
+            "SELECT column_name, data_type FROM information_schema.columns \
+             WHERE {} ORDER BY ordinal_position",
+            filter
+        );
+
+        let mut columns = Vec::new();
+        for batch in self.execute_sql_with_params(&sql, &params).await? {
+            let column_names = string_column(&batch, 0)?;
+            let data_types = string_column(&batch, 1)?;
+            for row in 0..batch.num_rows() {
+                columns.push(ColumnInfo {
+                    name: column_names.value(row).to_string(),
+                    data_type: data_types.value(row).to_string(),
+                });
+            }
+        }
+
+        Ok(columns)
+    }
+
+    /// List the tables and views in a schema, sorted by name.
+    ///
+    /// The default implementation queries `information_schema.tables`; it
+    /// returns an empty list if the schema doesn't exist.
+    async fn list_tables(&self, schema: &str) -> Result<Vec<String>, BackendError> {
+        let sql = "SELECT table_name FROM information_schema.tables \
+                   WHERE table_schema = ? ORDER BY table_name";
+
+        let mut tables = Vec::new();
+        for batch in self.execute_sql_with_params(sql, &[schema.into()]).await? {
+            let names = string_column(&batch, 0)?;
+            for row in 0..batch.num_rows() {
+                tables.push(names.value(row).to_string());
+            }
+        }
+
+        Ok(tables)
+    }
+
+    /// Get the engine's query plan for a SQL query as text.
+    ///
+    /// With `analyze` the query is run (but nothing is materialized) and the
+    /// plan includes actual timings and row counts. The default implementation
+    /// runs `EXPLAIN [ANALYZE]` and joins the last column of its result.
+    async fn explain(&self, sql: &str, analyze: bool) -> Result<String, BackendError> {
+        let explain_sql = format!("EXPLAIN {}{}", if analyze { "ANALYZE " } else { "" }, sql);
+
+        let mut lines = Vec::new();
+        for batch in self.execute_sql(&explain_sql).await? {
+            if batch.num_columns() == 0 {
+                continue;
+            }
+            let plan = string_column(&batch, batch.num_columns() - 1)?;
+            for row in 0..batch.num_rows() {
+                lines.push(plan.value(row).trim_end().to_string());
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+
+    /// Append Arrow record batches to an existing table.
+    ///
+    /// Columns are matched by position. The default implementation sends
+    /// `INSERT ... VALUES` statements with the values bound as parameters;
+    /// backends with a native bulk-load path should override it.
+    async fn load_record_batches(
+        &self,
+        relation: &RelationName,
+        batches: &[RecordBatch],
+    ) -> Result<(), BackendError> {
+        let table = self.dialect().quote_relation(relation);
+        for batch in batches {
+            let num_columns = batch.num_columns().max(1);
+            let rows_per_statement = (MAX_BOUND_PARAMS / num_columns).max(1);
+            let row_placeholders = format!("({})", vec!["?"; num_columns].join(", "));
+
+            for start in (0..batch.num_rows()).step_by(rows_per_statement) {
+                let chunk = batch.slice(start, rows_per_statement.min(batch.num_rows() - start));
+                let params = row_params(&chunk)?;
+                let sql = format!(
+                    "INSERT INTO {} VALUES {}",
+                    table,
+                    vec![row_placeholders.as_str(); chunk.num_rows()].join(", ")
+                );
+                self.execute_sql_with_params(&sql, &params).await?;
+            }
+        }
+
+        Ok(())
+    }
 
     /// Ensure a schema exists, creating it if necessary.
     async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError>;
 
+    /// Ensure a schema exists in another catalog, creating it if necessary.
+    ///
+    /// The catalog itself must already exist (or, in DuckDB, be attached).
+    /// The default implementation runs `CREATE SCHEMA IF NOT EXISTS`.
+    async fn ensure_catalog_schema(&self, catalog: &str, schema: &str) -> Result<(), BackendError> {
+        let dialect = self.dialect();
+        let sql = format!(
+            "CREATE SCHEMA IF NOT EXISTS {}.{}",
+            dialect.quote_ident(catalog),
+            dialect.quote_ident(schema)
+        );
+        self.execute_sql(&sql).await?;
+        Ok(())
+    }
+
     /// Get the SQL dialect this backend uses.
     fn dialect(&self) -> SqlDialect;
 
     /// Get the capabilities of this backend.
     fn capabilities(&self) -> BackendCapabilities;
 
-    /// Execute a model (drop + create as table or view).
+    /// The database engine's version, e.g. for `smelt debug`.
     ///
-    /// This is a convenience method that combines drop + create operations.
-    async fn execute_model(
+    /// The default implementation returns the result of `SELECT version()`.
+    async fn version(&self) -> Result<String, BackendError> {
+        let batches = self.execute_sql("SELECT version()").await?;
+        match batches
+            .iter()
+            .find(|b| b.num_rows() > 0 && b.num_columns() > 0)
+        {
+            Some(batch) => Ok(string_column(batch, 0)?.value(0).to_string()),
+            None => Err(BackendError::Other(anyhow::anyhow!(
+                "version() returned no rows"
+            ))),
+        }
+    }
+
+    /// Statistics collected while building `relation` since the last call.
+    ///
+    /// Called after a model is built to fill in [`ExecutionResult::stats`].
+    /// The default implementation reports nothing; backends that can profile
+    /// their statements record them per relation and return them here.
+    fn take_build_stats(&self, _relation: &RelationName) -> QueryStats {
+        QueryStats::default()
+    }
+
+    /// Interrupt every statement currently running on this backend, e.g. on
+    /// Ctrl-C or when a model exceeds its timeout.
+    ///
+    /// Interrupted calls return an error. The default implementation does
+    /// nothing, leaving running statements to finish; backends that can
+    /// interrupt them set `supports_cancel` in their capabilities.
+    async fn cancel(&self) -> Result<(), BackendError> {
+        Ok(())
+    }
+
+    /// Rename a table within its schema.
+    async fn rename_table(&self, relation: &RelationName, to: &str) -> Result<(), BackendError> {
+        let dialect = self.dialect();
+        let sql = format!(
+            "ALTER TABLE {} RENAME TO {}",
+            dialect.quote_relation(relation),
+            dialect.quote_ident(to)
+        );
+        self.execute_sql(&sql).await.map(|_| ())
+    }
+
+    /// Replace a model's table or view with the result of `sql`.
+    ///
+    /// A failed build must leave the previous relation in place. The default
+    /// implementation, for backends without transactional DDL, builds a table
+    /// under a temporary name and renames it over the old one; a view is
+    /// created under a temporary name first to check its SQL. Backends that
+    /// can drop and create in one transaction, or replace atomically, override it.
+    async fn replace_relation(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         sql: &str,
         materialization: Materialization,
-        show_preview: bool,
-    ) -> Result<ExecutionResult, BackendError> {
-        let start = std::time::Instant::now();
+    ) -> Result<(), BackendError> {
+        let staging = relation.sibling(format!("{}{}", relation.name, STAGING_SUFFIX));
 
         match materialization {
             Materialization::Table => {
-                self.drop_table_if_exists(schema, name).await?;
-                self.create_table_as(schema, name, sql).await?;
+                self.drop_table_if_exists(&staging).await?;
+                if let Err(e) = self.create_table_as(&staging, sql).await {
+                    let _ = self.drop_table_if_exists(&staging).await;
+                    return Err(e);
+                }
+                self.drop_table_if_exists(relation).await?;
+                self.rename_table(&staging, &relation.name).await
             }
             Materialization::View => {
-                self.drop_view_if_exists(schema, name).await?;
-                self.create_view_as(schema, name, sql).await?;
+                self.drop_view_if_exists(&staging).await?;
+                self.create_view_as(&staging, sql).await?;
+                self.drop_view_if_exists(&staging).await?;
+                self.drop_view_if_exists(relation).await?;
+                self.create_view_as(relation, sql).await
             }
         }
+    }
 
-        let duration = start.elapsed();
-        let row_count = self.get_row_count(schema, name).await?;
+    /// Store documentation on a built table or view as catalog comments.
+    ///
+    /// `columns` pairs column names with their descriptions. The default
+    /// implementation runs `COMMENT ON` statements, and does nothing on
+    /// backends without `supports_comments`.
+    async fn set_comments(
+        &self,
+        relation: &RelationName,
+        materialization: Materialization,
+        description: Option<&str>,
+        columns: &[(String, String)],
+    ) -> Result<(), BackendError> {
+        if !self.capabilities().supports_comments {
+            return Ok(());
+        }
+        let dialect = self.dialect();
+        let table = dialect.quote_relation(relation);
+        let kind = match materialization {
+            Materialization::Table => "TABLE",
+            Materialization::View => "VIEW",
+        };
 
-        let preview = if show_preview {
-            Some(self.get_preview(schema, name, 10).await?)
-        } else {
-            None
+        if let Some(description) = description {
+            let sql = format!(
+                "COMMENT ON {} {} IS {}",
+                kind,
+                table,
+                string_literal(description)
+            );
+            self.execute_sql(&sql).await?;
+        }
+        for (column, description) in columns {
+            let sql = format!(
+                "COMMENT ON COLUMN {}.{} IS {}",
+                table,
+                dialect.quote_ident(column),
+                string_literal(description)
+            );
+            self.execute_sql(&sql).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Privileges granted on a table or view to other roles, as
+    /// `(privilege, grantee)` pairs.
+    ///
+    /// The default implementation queries `information_schema.table_privileges`,
+    /// leaving out the privileges an owner holds on its own relation.
+    async fn get_grants(
+        &self,
+        relation: &RelationName,
+    ) -> Result<Vec<(String, String)>, BackendError> {
+        let (filter, params) = information_schema_filter(relation);
+        let sql = format!(
+            "SELECT privilege_type, grantee FROM information_schema.table_privileges \
+             WHERE {} AND grantee <> grantor",
+            filter
+        );
+
+        let mut grants = Vec::new();
+        for batch in self.execute_sql_with_params(&sql, &params).await? {
+            let privileges = string_column(&batch, 0)?;
+            let grantees = string_column(&batch, 1)?;
+            for row in 0..batch.num_rows() {
+                grants.push((
+                    privileges.value(row).to_string(),
+                    grantees.value(row).to_string(),
+                ));
+            }
+        }
+
+        Ok(grants)
+    }
+
+    /// Make the grants on a table or view match `grants`, a map from
+    /// privilege to grantees.
+    ///
+    /// Only the privileges in `grants` are managed: holders of one that aren't
+    /// listed have it revoked, and listed grantees that don't hold it are
+    /// granted it. Fails as unsupported on backends without `supports_grants`.
+    async fn apply_grants(
+        &self,
+        relation: &RelationName,
+        materialization: Materialization,
+        grants: &BTreeMap<String, Vec<String>>,
+    ) -> Result<(), BackendError> {
+        if !self.capabilities().supports_grants {
+            return Err(BackendError::unsupported(self.dialect().name(), "grants"));
+        }
+        let kind = match materialization {
+            Materialization::Table => "TABLE",
+            Materialization::View => "VIEW",
         };
+        let dialect = self.dialect();
+        let table = dialect.quote_relation(relation);
+        let current = self.get_grants(relation).await?;
 
-        Ok(ExecutionResult {
-            model_name: name.to_string(),
-            duration,
-            row_count,
-            preview,
-        })
+        for (privilege, grantees) in grants {
+            let privilege = privilege.to_uppercase();
+            let holders: Vec<&str> = current
+                .iter()
+                .filter(|(p, _)| p.eq_ignore_ascii_case(&privilege))
+                .map(|(_, grantee)| grantee.as_str())
+                .collect();
+
+            for holder in &holders {
+                if !grantees.iter().any(|g| g.eq_ignore_ascii_case(holder)) {
+                    let sql = format!(
+                        "REVOKE {} ON {} {} FROM {}",
+                        privilege,
+                        kind,
+                        table,
+                        dialect.quote_ident(holder)
+                    );
+                    self.execute_sql(&sql).await?;
+                }
+            }
+            for grantee in grantees {
+                if !holders.iter().any(|h| h.eq_ignore_ascii_case(grantee)) {
+                    let sql = format!(
+                        "GRANT {} ON {} {} TO {}",
+                        privilege,
+                        kind,
+                        table,
+                        dialect.quote_ident(grantee)
+                    );
+                    self.execute_sql(&sql).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Execute a model (replace its table or view, then count its rows).
+    async fn execute_model(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        materialization: Materialization,
+        show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        let start = std::time::Instant::now();
+
+        self.replace_relation(relation, sql, materialization)
+            .await?;
+
+        build_result(self, relation, start, show_preview).await
+    }
+
+    /// Write the result of `sql` to Parquet at `location` and replace the
+    /// model's view with one that reads it.
+    ///
+    /// `location` is a path or URL the engine can write to. The default
+    /// implementation reports the feature as unsupported.
+    async fn export_parquet(
+        &self,
+        _relation: &RelationName,
+        _sql: &str,
+        _location: &str,
+    ) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(
+            self.dialect().name(),
+            "external Parquet models",
+        ))
+    }
+
+    /// Execute an external model (export it to Parquet, then count its rows
+    /// through its view).
+    async fn execute_model_external(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        location: &str,
+        show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        let start = std::time::Instant::now();
+
+        self.export_parquet(relation, sql, location).await?;
+
+        build_result(self, relation, start, show_preview).await
     }
 
     /// Execute a model with incremental materialization support.
@@ -113,8 +483,7 @@ pub trait Backend: Send + Sync {
     /// This extends execute_model() with partition-aware incremental updates.
     async fn execute_model_incremental(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         sql: &str,
         materialization: Materialization,
         strategy: MaterializationStrategy,
@@ -123,56 +492,246 @@ pub trait Backend: Send + Sync {
         let start = std::time::Instant::now();
 
         match (materialization, strategy) {
-            (Materialization::View, _) => {
-                self.drop_view_if_exists(schema, name).await?;
-                self.create_view_as(schema, name, sql).await?;
-            }
-            (Materialization::Table, MaterializationStrategy::FullRefresh) => {
-                self.drop_table_if_exists(schema, name).await?;
-                self.create_table_as(schema, name, sql).await?;
+            (materialization @ Materialization::View, _)
+            | (materialization, MaterializationStrategy::FullRefresh) => {
+                self.replace_relation(relation, sql, materialization)
+                    .await?;
             }
-            (Materialization::Table, MaterializationStrategy::Incremental { partition }) => {
-                let table_exists = self.table_exists(schema, name).await?;
+            (
+                Materialization::Table,
+                MaterializationStrategy::Incremental {
+                    partition,
+                    strategy,
+                },
+            ) => {
+                self.check_incremental_strategy(&strategy)?;
+                let table_exists = self.table_exists(relation).await?;
 
                 if !table_exists {
-                    self.create_table_as(schema, name, sql).await?;
+                    self.create_incremental_table_as(relation, sql, &partition.column)
+                        .await?;
                 } else {
-                    self.delete_partitions(schema, name, &partition).await?;
-                    self.insert_into_from_query(schema, name, sql).await?;
+                    match strategy {
+                        IncrementalStrategy::DeleteInsert => {
+                            self.delete_partitions(relation, &partition).await?;
+                            self.insert_into_from_query(relation, sql).await?;
+                        }
+                        IncrementalStrategy::Merge { unique_key } => {
+                            self.merge_into_from_query(relation, sql, &unique_key)
+                                .await?;
+                        }
+                        IncrementalStrategy::InsertOverwrite => {
+                            self.insert_overwrite_partitions(relation, sql, &partition)
+                                .await?;
+                        }
+                    }
                 }
             }
         }
 
-        let duration = start.elapsed();
-        let row_count = self.get_row_count(schema, name).await?;
+        build_result(self, relation, start, show_preview).await
+    }
 
-        let preview = if show_preview {
-            Some(self.get_preview(schema, name, 10).await?)
-        } else {
-            None
+    /// Create an incremental model's table on its first build.
+    ///
+    /// The default implementation is [`Backend::create_table_as`]; backends
+    /// whose partition overwrites need the table physically partitioned by
+    /// `partition_column` override it.
+    async fn create_incremental_table_as(
+        &self,
+        relation: &RelationName,
+        sql: &str,
+        _partition_column: &str,
+    ) -> Result<(), BackendError> {
+        self.create_table_as(relation, sql).await
+    }
+
+    /// Check that this backend can run an incremental strategy.
+    fn check_incremental_strategy(
+        &self,
+        strategy: &IncrementalStrategy,
+    ) -> Result<(), BackendError> {
+        let capabilities = self.capabilities();
+        let supported = match strategy {
+            IncrementalStrategy::DeleteInsert => true,
+            IncrementalStrategy::Merge { unique_key } => {
+                if unique_key.is_empty() {
+                    return Err(BackendError::ConfigurationError {
+                        message: "incremental_strategy 'merge' requires a unique_key".to_string(),
+                    });
+                }
+                capabilities.supports_merge
+            }
+            IncrementalStrategy::InsertOverwrite => capabilities.supports_insert_overwrite,
         };
 
-        Ok(ExecutionResult {
-            model_name: name.to_string(),
-            duration,
-            row_count,
-            preview,
-        })
+        if supported {
+            Ok(())
+        } else {
+            Err(BackendError::unsupported(
+                self.dialect().name(),
+                format!("incremental_strategy '{}'", strategy),
+            ))
+        }
     }
 
     /// Delete rows matching partition values.
     async fn delete_partitions(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         partition: &PartitionSpec,
     ) -> Result<(), BackendError>;
 
     /// Insert data from a SELECT query into an existing table.
     async fn insert_into_from_query(
         &self,
-        schema: &str,
-        name: &str,
+        relation: &RelationName,
         sql: &str,
     ) -> Result<(), BackendError>;
+
+    /// Upsert rows from a SELECT query, matching existing rows on `unique_key`.
+    async fn merge_into_from_query(
+        &self,
+        _relation: &RelationName,
+        _sql: &str,
+        _unique_key: &[String],
+    ) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(
+            self.dialect().name(),
+            "incremental_strategy 'merge'",
+        ))
+    }
+
+    /// Replace the given partitions with the rows from a SELECT query.
+    async fn insert_overwrite_partitions(
+        &self,
+        _relation: &RelationName,
+        _sql: &str,
+        _partition: &PartitionSpec,
+    ) -> Result<(), BackendError> {
+        Err(BackendError::unsupported(
+            self.dialect().name(),
+            "incremental_strategy 'insert_overwrite'",
+        ))
+    }
+}
+
+/// The result of a model built since `start`: its row count, optional
+/// preview, and the backend's statistics for the build.
+async fn build_result<B: Backend + ?Sized>(
+    backend: &B,
+    relation: &RelationName,
+    start: std::time::Instant,
+    show_preview: bool,
+) -> Result<ExecutionResult, BackendError> {
+    let duration = start.elapsed();
+    let stats = backend.take_build_stats(relation);
+    let row_count = backend.get_row_count(relation).await?;
+
+    let preview = if show_preview {
+        Some(backend.get_preview(relation, 10).await?)
+    } else {
+        None
+    };
+
+    Ok(ExecutionResult {
+        model_name: relation.name.clone(),
+        duration,
+        row_count,
+        preview,
+        stats,
+    })
+}
+
+/// Appended to a model's name while [`Backend::replace_relation`] builds it.
+pub const STAGING_SUFFIX: &str = "__smelt_new";
+
+/// Most values bound to one statement by [`Backend::load_record_batches`].
+const MAX_BOUND_PARAMS: usize = 5000;
+
+/// The values of a batch as parameters, row by row.
+///
+/// Integers, floats, and booleans keep their type; everything else (strings,
+/// dates, timestamps, ...) is bound as its text form for the engine to cast.
+fn row_params(batch: &RecordBatch) -> Result<Vec<SqlParam>, BackendError> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|column| match column.data_type() {
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32 => arrow::compute::cast(column, &DataType::Int64),
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+                arrow::compute::cast(column, &DataType::Float64)
+            }
+            _ => Ok(column.clone()),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| BackendError::Other(e.into()))?;
+
+    let mut params = Vec::with_capacity(batch.num_rows() * columns.len());
+    for row in 0..batch.num_rows() {
+        for column in &columns {
+            let param = if column.is_null(row) {
+                SqlParam::Null
+            } else {
+                match column.data_type() {
+                    DataType::Int64 => SqlParam::Int(column.as_primitive::<Int64Type>().value(row)),
+                    DataType::Float64 => {
+                        SqlParam::Float(column.as_primitive::<Float64Type>().value(row))
+                    }
+                    DataType::Boolean => SqlParam::Bool(column.as_boolean().value(row)),
+                    _ => SqlParam::Text(
+                        array_value_to_string(column, row)
+                            .map_err(|e| BackendError::Other(e.into()))?,
+                    ),
+                }
+            };
+            params.push(param);
+        }
+    }
+
+    Ok(params)
+}
+
+/// `information_schema` conditions selecting `relation`, with their bound
+/// values. A relation without a catalog is looked up in `current_database()`.
+fn information_schema_filter(relation: &RelationName) -> (String, Vec<SqlParam>) {
+    let mut params = vec![
+        SqlParam::from(relation.schema.as_str()),
+        SqlParam::from(relation.name.as_str()),
+    ];
+    let catalog = match &relation.catalog {
+        Some(catalog) => {
+            params.push(SqlParam::from(catalog.as_str()));
+            "?"
+        }
+        None => "current_database()",
+    };
+    let filter = format!(
+        "table_schema = ? AND table_name = ? AND table_catalog = {}",
+        catalog
+    );
+    (filter, params)
+}
+
+/// Quote text as a SQL string literal, for statements that can't take
+/// bound parameters (such as `COMMENT ON`).
+fn string_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Read a column of a result batch as strings.
+fn string_column(batch: &RecordBatch, index: usize) -> Result<StringArray, BackendError> {
+    let column = arrow::compute::cast(batch.column(index), &DataType::Utf8)
+        .map_err(|e| BackendError::Other(e.into()))?;
+    column
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .cloned()
+        .ok_or_else(|| BackendError::Other(anyhow::anyhow!("Expected a string column")))
 }
@@ -0,0 +1,34 @@
+//! Streaming query results.
+
+use crate::BackendError;
+use arrow::array::RecordBatch;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+
+/// Record batches produced incrementally by [`crate::Backend::execute_sql_stream`].
+///
+/// Dropping the stream stops the query.
+pub type RecordBatchStream = BoxStream<'static, Result<RecordBatch, BackendError>>;
+
+/// Read at most `limit` rows from a stream, then drop it.
+///
+/// The batch that crosses the limit is sliced, so only `limit` rows are kept.
+pub async fn collect_limited(
+    mut stream: RecordBatchStream,
+    limit: usize,
+) -> Result<Vec<RecordBatch>, BackendError> {
+    let mut batches = Vec::new();
+    let mut remaining = limit;
+
+    while remaining > 0 {
+        let Some(batch) = stream.next().await else {
+            break;
+        };
+        let batch = batch?;
+        let rows = batch.num_rows().min(remaining);
+        remaining -= rows;
+        batches.push(batch.slice(0, rows));
+    }
+
+    Ok(batches)
+}
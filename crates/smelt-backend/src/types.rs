@@ -17,6 +17,142 @@ pub struct ExecutionResult {
 
     /// Optional preview of the first few rows.
     pub preview: Option<Vec<RecordBatch>>,
+
+    /// Statistics the backend reported for the statements that built the model.
+    pub stats: QueryStats,
+}
+
+/// Execution statistics reported by a backend.
+///
+/// Every field is optional: backends fill in what their engine exposes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// Time spent executing in the engine, as opposed to wall time.
+    pub backend_time: Option<Duration>,
+
+    /// Rows read by table scans.
+    pub rows_scanned: Option<u64>,
+
+    /// Bytes read from storage.
+    pub bytes_scanned: Option<u64>,
+
+    /// Bytes written to storage.
+    pub bytes_written: Option<u64>,
+
+    /// Peak memory used while executing.
+    pub peak_memory_bytes: Option<u64>,
+}
+
+impl QueryStats {
+    /// Whether the backend reported nothing.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Combine the statistics of two statements: times and counts are added,
+    /// peak memory is the larger of the two.
+    pub fn merge(self, other: QueryStats) -> QueryStats {
+        fn add<T: std::ops::Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            }
+        }
+
+        QueryStats {
+            backend_time: add(self.backend_time, other.backend_time),
+            rows_scanned: add(self.rows_scanned, other.rows_scanned),
+            bytes_scanned: add(self.bytes_scanned, other.bytes_scanned),
+            bytes_written: add(self.bytes_written, other.bytes_written),
+            peak_memory_bytes: self.peak_memory_bytes.max(other.peak_memory_bytes),
+        }
+    }
+}
+
+impl std::fmt::Display for QueryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(time) = self.backend_time {
+            parts.push(format!("{:?} in backend", time));
+        }
+        if let Some(rows) = self.rows_scanned {
+            parts.push(format!("{} rows scanned", rows));
+        }
+        if let Some(bytes) = self.bytes_scanned {
+            parts.push(format!("{} bytes scanned", bytes));
+        }
+        if let Some(bytes) = self.bytes_written {
+            parts.push(format!("{} bytes written", bytes));
+        }
+        if let Some(bytes) = self.peak_memory_bytes {
+            parts.push(format!("{} bytes peak memory", bytes));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// The name of a table or view: `[catalog.]schema.name`.
+///
+/// `catalog` is the top level of the engine's namespace: the database in
+/// Snowflake, the catalog in Spark, or an attached database in DuckDB.
+/// Without one, the backend uses the connection's default catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RelationName {
+    pub catalog: Option<String>,
+    pub schema: String,
+    pub name: String,
+}
+
+impl RelationName {
+    /// A relation in the default catalog.
+    pub fn new(schema: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            catalog: None,
+            schema: schema.into(),
+            name: name.into(),
+        }
+    }
+
+    /// The same relation in `catalog`.
+    pub fn with_catalog(mut self, catalog: impl Into<String>) -> Self {
+        self.catalog = Some(catalog.into());
+        self
+    }
+
+    /// The same relation in `catalog` if it doesn't name one already.
+    pub fn or_catalog(&self, catalog: &str) -> Self {
+        let mut relation = self.clone();
+        relation.catalog.get_or_insert_with(|| catalog.to_string());
+        relation
+    }
+
+    /// Another relation in the same catalog and schema, e.g. a staging table.
+    pub fn sibling(&self, name: impl Into<String>) -> Self {
+        Self {
+            catalog: self.catalog.clone(),
+            schema: self.schema.clone(),
+            name: name.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for RelationName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(catalog) = &self.catalog {
+            write!(f, "{}.", catalog)?;
+        }
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
+
+/// A column of a table or view, as reported by the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    /// Column name.
+    pub name: String,
+
+    /// Backend-specific type name (e.g., "BIGINT", "VARCHAR").
+    pub data_type: String,
 }
 
 /// How a model should be materialized.
@@ -57,10 +193,25 @@ pub struct PartitionSpec {
     /// Column name used for partitioning (e.g., "date", "event_date")
     pub column: String,
 
-    /// Partition values to update (e.g., vec!["2024-01-01", "2024-01-02"])
+    /// Partition values to update, formatted for the partition grain
+    /// (e.g., vec!["2024-01-01", "2024-01-02"], or "2024-01-01 03:00:00" for hours)
     pub values: Vec<String>,
 }
 
+impl PartitionSpec {
+    /// Placeholders for an `IN (...)` list of the partition values, e.g.
+    /// `"?, ?"`, with the values to bind to them.
+    pub fn bound_values(&self) -> (String, Vec<SqlParam>) {
+        let placeholders = vec!["?"; self.values.len()].join(", ");
+        let params = self
+            .values
+            .iter()
+            .map(|v| SqlParam::from(v.as_str()))
+            .collect();
+        (placeholders, params)
+    }
+}
+
 /// Materialization strategy for tables.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum MaterializationStrategy {
@@ -68,6 +219,75 @@ pub enum MaterializationStrategy {
     #[default]
     FullRefresh,
 
-    /// Incremental: DELETE by partition + INSERT
-    Incremental { partition: PartitionSpec },
+    /// Incremental: update only the given partitions using `strategy`
+    Incremental {
+        partition: PartitionSpec,
+        strategy: IncrementalStrategy,
+    },
+}
+
+/// How an incremental model's new rows are written into the existing table.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum IncrementalStrategy {
+    /// DELETE rows in the partitions, then INSERT the new rows
+    #[default]
+    DeleteInsert,
+
+    /// Upsert rows matched on `unique_key` (requires MERGE support)
+    Merge { unique_key: Vec<String> },
+
+    /// Atomically replace the partitions (requires INSERT OVERWRITE support)
+    InsertOverwrite,
+}
+
+impl std::fmt::Display for IncrementalStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncrementalStrategy::DeleteInsert => write!(f, "delete+insert"),
+            IncrementalStrategy::Merge { .. } => write!(f, "merge"),
+            IncrementalStrategy::InsertOverwrite => write!(f, "insert_overwrite"),
+        }
+    }
+}
+
+/// A value bound to a `?` placeholder by [`Backend::execute_sql_with_params`].
+///
+/// [`Backend::execute_sql_with_params`]: crate::Backend::execute_sql_with_params
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlParam {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<&str> for SqlParam {
+    fn from(value: &str) -> Self {
+        SqlParam::Text(value.to_string())
+    }
+}
+
+impl From<String> for SqlParam {
+    fn from(value: String) -> Self {
+        SqlParam::Text(value)
+    }
+}
+
+impl From<i64> for SqlParam {
+    fn from(value: i64) -> Self {
+        SqlParam::Int(value)
+    }
+}
+
+impl From<f64> for SqlParam {
+    fn from(value: f64) -> Self {
+        SqlParam::Float(value)
+    }
+}
+
+impl From<bool> for SqlParam {
+    fn from(value: bool) -> Self {
+        SqlParam::Bool(value)
+    }
 }
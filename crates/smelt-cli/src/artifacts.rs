@@ -0,0 +1,971 @@
+//! Machine-readable run artifacts.
+//!
+//! `smelt compile` and `smelt run` write `target/manifest.json`, describing the
+//! project graph and compiled SQL. `smelt run` also writes
+//! `target/run_results.json` with the status, timing, and row count of every
+//! model in the run, and the row counts it records are the baseline the next
+//! run's row count warnings compare against. Both are intended for CI tooling and external
+//! orchestration, and the manifest doubles as the baseline for state-based
+//! selection.
+//!
+//! `smelt run` also keeps `target/cache/<target>.json`, recording what each
+//! model was last built from so unchanged models can be skipped.
+//!
+//! `smelt run --explain` writes each model's query plan to
+//! `target/plans/<model>.txt` instead of running it.
+
+use crate::compiler::SqlCompiler;
+use crate::config::{Config, Materialization};
+use crate::discovery::ModelFile;
+use crate::graph::DependencyGraph;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use smelt_backend::{ExecutionResult, QueryStats};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const RUN_RESULTS_FILE: &str = "run_results.json";
+
+/// Directory artifacts are written to, relative to the project root.
+pub fn artifacts_dir(project_root: &Path) -> PathBuf {
+    project_root.join("target")
+}
+
+/// Metadata shared by every artifact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactMetadata {
+    pub smelt_version: String,
+    /// RFC 3339 timestamp of when the artifact was generated
+    pub generated_at: String,
+    pub project_name: String,
+    pub target: String,
+}
+
+impl ArtifactMetadata {
+    pub fn new(project_name: &str, target: &str) -> Self {
+        Self {
+            smelt_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            project_name: project_name.to_string(),
+            target: target.to_string(),
+        }
+    }
+}
+
+/// Snapshot of the project graph and compiled SQL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub metadata: ArtifactMetadata,
+    /// Models keyed by name
+    pub nodes: BTreeMap<String, ManifestNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestNode {
+    pub name: String,
+    /// Path relative to the project root
+    pub path: PathBuf,
+    /// SHA-256 of the raw model file contents
+    pub checksum: String,
+    /// SHA-256 of the smelt.yml settings the model is built with (empty in
+    /// manifests from older versions)
+    #[serde(default)]
+    pub config_checksum: String,
+    pub compiled_sql: String,
+    pub materialization: Materialization,
+    /// Schema the model is built in (empty in manifests from older versions)
+    #[serde(default)]
+    pub schema: String,
+    /// Database the model is built in, if not the target's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    /// Upstream models and sources referenced by this model
+    pub depends_on: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Manifest {
+    /// Build a manifest by compiling every model in the graph.
+    pub fn build(
+        graph: &DependencyGraph,
+        compiler: &SqlCompiler,
+        config: &Config,
+        project_root: &Path,
+        target: &str,
+        schema: &str,
+    ) -> Result<Self> {
+        let mut nodes = BTreeMap::new();
+
+        for model in graph.models().values() {
+            let compiled = compiler
+                .compile(model, schema)
+                .with_context(|| format!("Failed to compile model: {}", model.name))?;
+
+            let mut depends_on: Vec<String> =
+                model.refs.iter().map(|r| r.model_name.clone()).collect();
+            depends_on.sort();
+            depends_on.dedup();
+
+            let tags = config.get_model_tags(model);
+
+            nodes.insert(
+                model.name.clone(),
+                ManifestNode {
+                    name: model.name.clone(),
+                    path: model
+                        .path
+                        .strip_prefix(project_root)
+                        .unwrap_or(&model.path)
+                        .to_path_buf(),
+                    checksum: checksum(&model.content),
+                    config_checksum: config_checksum(config, model, target),
+                    compiled_sql: compiled.sql,
+                    materialization: compiled.materialization,
+                    schema: config
+                        .get_model_schema(model)
+                        .unwrap_or_else(|| schema.to_string()),
+                    database: config.get_model_database(model).map(str::to_string),
+                    depends_on,
+                    tags,
+                },
+            );
+        }
+
+        Ok(Self {
+            metadata: ArtifactMetadata::new(&config.name, target),
+            nodes,
+        })
+    }
+
+    /// Schemas to resolve refs to unselected models in, for `--defer`.
+    ///
+    /// Every model in `graph` outside `selected` that this manifest records as
+    /// built is deferred to the schema it was built in. Models added since the
+    /// manifest was written, and ephemeral models, still resolve as usual.
+    pub fn deferred_schemas(
+        &self,
+        graph: &DependencyGraph,
+        selected: &[String],
+    ) -> Result<HashMap<String, String>> {
+        let mut deferred = HashMap::new();
+
+        for name in graph.models().keys() {
+            if selected.contains(name) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(name) else {
+                continue;
+            };
+            if node.materialization == Materialization::Ephemeral {
+                continue;
+            }
+            if node.schema.is_empty() {
+                return Err(anyhow!(
+                    "State manifest doesn't record the schema of '{}'; regenerate it with this version of smelt to use --defer",
+                    name
+                ));
+            }
+            deferred.insert(name.clone(), node.schema.clone());
+        }
+
+        Ok(deferred)
+    }
+
+    /// Load the manifest used for `--state` comparison.
+    ///
+    /// `path` may be the manifest file itself or a directory containing
+    /// `manifest.json` (e.g. the `target/` of a previous run).
+    pub fn load_state(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            Self::load(&path.join(MANIFEST_FILE))
+        } else {
+            Self::load(path)
+        }
+    }
+
+    /// Load a manifest written by a previous run.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {:?}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid manifest {:?}", path))
+    }
+}
+
+/// Hex-encoded SHA-256 of a model's source.
+pub fn checksum(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Hex-encoded SHA-256 of the smelt.yml settings `model` is built with in
+/// `target`: everything but its SQL that can change what the build leaves in
+/// the warehouse.
+pub fn config_checksum(config: &Config, model: &ModelFile, target: &str) -> String {
+    let name = model.name.as_str();
+    let settings = serde_json::json!({
+        "materialization": config.get_model_materialization(model),
+        "schema": config.get_model_schema(model),
+        "database": config.get_model_database(model),
+        "incremental": config.get_incremental_with_metadata(name, model.metadata.as_deref()),
+        "hooks": config.get_hooks(name),
+        "grants": config.get_grants(name, target),
+        "contract": config.get_contract(name),
+        "location": config.models.get(name).and_then(|m| m.location.as_ref()),
+        "program": config.get_program(name),
+    });
+    checksum(&settings.to_string())
+}
+
+/// Outcome of a single model in a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Success,
+    Error,
+    /// Not executed because of an earlier failure (see `message`)
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeResult {
+    pub name: String,
+    pub status: RunStatus,
+    pub execution_time_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub row_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Statistics reported by the backend, when it reports any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ExecutionStats>,
+    /// Row count recorded for the model by the previous run, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_row_count: Option<usize>,
+}
+
+/// Backend-reported statistics for a model build.
+///
+/// `backend_time_secs` is time spent executing inside the backend, as opposed
+/// to `execution_time_secs` which is wall time as seen by smelt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_time_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows_scanned: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_scanned: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_written: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_memory_bytes: Option<u64>,
+}
+
+impl ExecutionStats {
+    /// Convert backend statistics, or `None` if the backend reported nothing.
+    pub fn from_query_stats(stats: &QueryStats) -> Option<Self> {
+        if stats.is_empty() {
+            return None;
+        }
+        Some(Self {
+            backend_time_secs: stats.backend_time.map(|t| t.as_secs_f64()),
+            rows_scanned: stats.rows_scanned,
+            bytes_scanned: stats.bytes_scanned,
+            bytes_written: stats.bytes_written,
+            peak_memory_bytes: stats.peak_memory_bytes,
+        })
+    }
+}
+
+impl NodeResult {
+    pub fn success(result: &ExecutionResult) -> Self {
+        Self {
+            name: result.model_name.clone(),
+            status: RunStatus::Success,
+            execution_time_secs: result.duration.as_secs_f64(),
+            row_count: Some(result.row_count),
+            message: None,
+            stats: ExecutionStats::from_query_stats(&result.stats),
+            previous_row_count: None,
+        }
+    }
+
+    pub fn error(name: &str, duration: Duration, error: &anyhow::Error) -> Self {
+        Self {
+            name: name.to_string(),
+            status: RunStatus::Error,
+            execution_time_secs: duration.as_secs_f64(),
+            row_count: None,
+            message: Some(error.root_cause().to_string()),
+            stats: None,
+            previous_row_count: None,
+        }
+    }
+
+    /// A model that wasn't rebuilt because nothing it's built from changed.
+    pub fn cached(name: &str, build: &CachedBuild) -> Self {
+        Self {
+            name: name.to_string(),
+            status: RunStatus::Success,
+            execution_time_secs: 0.0,
+            row_count: build.row_count,
+            message: Some(format!("unchanged since {}", build.built_at)),
+            stats: None,
+            previous_row_count: None,
+        }
+    }
+
+    pub fn skipped(name: &str, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: RunStatus::Skipped,
+            execution_time_secs: 0.0,
+            row_count: None,
+            message: Some(reason.into()),
+            stats: None,
+            previous_row_count: None,
+        }
+    }
+}
+
+/// Per-model results of a `smelt run`, in execution order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunResults {
+    pub metadata: ArtifactMetadata,
+    pub elapsed_secs: f64,
+    pub results: Vec<NodeResult>,
+}
+
+impl RunResults {
+    pub fn new(metadata: ArtifactMetadata, elapsed: Duration, results: Vec<NodeResult>) -> Self {
+        Self {
+            metadata,
+            elapsed_secs: elapsed.as_secs_f64(),
+            results,
+        }
+    }
+
+    /// Load the run results written by a previous run.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read run results {:?}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("Invalid run results {:?}", path))
+    }
+
+    /// Row counts of the models that built successfully.
+    pub fn row_counts(&self) -> HashMap<String, usize> {
+        self.results
+            .iter()
+            .filter(|r| r.status == RunStatus::Success)
+            .filter_map(|r| Some((r.name.clone(), r.row_count?)))
+            .collect()
+    }
+}
+
+/// Row counts from the last run against `target` recorded in `dir`.
+///
+/// Empty if there's no previous run, it was against another target, or its
+/// results can't be read: the next run simply has nothing to compare with.
+pub fn previous_row_counts(dir: &Path, target: &str) -> HashMap<String, usize> {
+    match RunResults::load(&dir.join(RUN_RESULTS_FILE)) {
+        Ok(previous) if previous.metadata.target == target => previous.row_counts(),
+        _ => HashMap::new(),
+    }
+}
+
+/// File in [`artifacts_dir`] that compiled SQL is cached in between invocations.
+pub const COMPILE_CACHE_FILE: &str = "compile_cache.json";
+
+/// Compiled SQL from earlier invocations, one entry per model.
+///
+/// An entry is reused while its key still matches; see
+/// [`SqlCompiler::with_cache`](crate::compiler::SqlCompiler::with_cache) for
+/// what goes into the key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompileCache {
+    pub models: BTreeMap<String, CachedCompile>,
+    /// Whether entries changed since the cache was loaded
+    #[serde(skip)]
+    changed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedCompile {
+    pub key: String,
+    pub sql: String,
+}
+
+impl CompileCache {
+    /// The cache in `dir`, or an empty one if there's none or it can't be read.
+    pub fn load(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(COMPILE_CACHE_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the cache to `dir` if any entry changed since it was loaded.
+    pub fn write(&mut self, dir: &Path) -> Result<()> {
+        if self.changed {
+            write_artifact(dir, COMPILE_CACHE_FILE, self)?;
+            self.changed = false;
+        }
+        Ok(())
+    }
+
+    /// The SQL `model` was compiled to, if it was compiled with `key`.
+    pub fn get(&self, model: &str, key: &str) -> Option<&str> {
+        self.models
+            .get(model)
+            .filter(|cached| cached.key == key)
+            .map(|cached| cached.sql.as_str())
+    }
+
+    pub fn insert(&mut self, model: &str, key: String, sql: String) {
+        let cached = CachedCompile { key, sql };
+        if self.models.get(model) != Some(&cached) {
+            self.models.insert(model.to_string(), cached);
+            self.changed = true;
+        }
+    }
+}
+
+/// Directory build caches are written to, one file per target.
+pub fn cache_dir(project_root: &Path) -> PathBuf {
+    artifacts_dir(project_root).join("cache")
+}
+
+/// What each model in a target was last built from.
+///
+/// A model whose compiled SQL and upstream builds match its entry needn't be
+/// rebuilt. Upstream models enter the key through their `built_at`, so
+/// rebuilding one invalidates everything downstream of it. Sources aren't
+/// tracked: after their data changes, run with `--no-cache`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildCache {
+    pub metadata: ArtifactMetadata,
+    pub models: BTreeMap<String, CachedBuild>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedBuild {
+    /// Hash of the compiled SQL, materialization, and upstream builds
+    pub cache_key: String,
+    /// RFC 3339 timestamp of the build
+    pub built_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_count: Option<usize>,
+}
+
+impl BuildCache {
+    pub fn new(project_name: &str, target: &str) -> Self {
+        Self {
+            metadata: ArtifactMetadata::new(project_name, target),
+            models: BTreeMap::new(),
+        }
+    }
+
+    fn file_name(target: &str) -> String {
+        format!("{}.json", target)
+    }
+
+    /// The cache for `target` in `dir`, or an empty one if there's none or it
+    /// can't be read.
+    pub fn load(dir: &Path, project_name: &str, target: &str) -> Self {
+        std::fs::read_to_string(dir.join(Self::file_name(target)))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_else(|| Self::new(project_name, target))
+    }
+
+    pub fn write(&mut self, dir: &Path) -> Result<PathBuf> {
+        self.metadata.generated_at = chrono::Utc::now().to_rfc3339();
+        write_artifact(dir, &Self::file_name(&self.metadata.target), self)
+    }
+
+    /// Forget every build in `target`, e.g. after `smelt seed` reloads tables
+    /// models read from.
+    pub fn clear(dir: &Path, target: &str) -> Result<()> {
+        let path = dir.join(Self::file_name(target));
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to remove {:?}", path))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The key `name` would be built with now, or `None` if an upstream model
+    /// has no recorded build.
+    ///
+    /// Ephemeral models are inlined rather than built, so their own upstream
+    /// models stand in for them.
+    pub fn cache_key(&self, manifest: &Manifest, name: &str) -> Option<String> {
+        let node = manifest.nodes.get(name)?;
+        let mut upstream = BTreeMap::new();
+        self.upstream_builds(manifest, node, &mut upstream)?;
+
+        // The model file's checksum covers descriptions set as comments, and
+        // the config checksum its hooks, grants, and contract
+        let mut hasher = Sha256::new();
+        hasher.update(node.compiled_sql.as_bytes());
+        hasher.update(format!("\0{}\0{}", node.checksum, node.config_checksum));
+        hasher.update(format!("\0{:?}\0{}", node.materialization, node.schema));
+        if let Some(database) = &node.database {
+            hasher.update(format!("\0{}", database));
+        }
+        for (upstream, built_at) in upstream {
+            hasher.update(format!("\0{}={}", upstream, built_at));
+        }
+        Some(
+            hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        )
+    }
+
+    fn upstream_builds<'a>(
+        &'a self,
+        manifest: &'a Manifest,
+        node: &'a ManifestNode,
+        builds: &mut BTreeMap<&'a str, &'a str>,
+    ) -> Option<()> {
+        for dependency in &node.depends_on {
+            // Anything that isn't a model is a source
+            let Some(upstream) = manifest.nodes.get(dependency) else {
+                continue;
+            };
+            if upstream.materialization == Materialization::Ephemeral {
+                self.upstream_builds(manifest, upstream, builds)?;
+            } else {
+                let build = self.models.get(dependency)?;
+                builds.insert(dependency, &build.built_at);
+            }
+        }
+        Some(())
+    }
+
+    /// The build to reuse for `name`, if it was last built with `cache_key`.
+    pub fn hit(&self, name: &str, cache_key: &str) -> Option<&CachedBuild> {
+        self.models
+            .get(name)
+            .filter(|build| build.cache_key == cache_key)
+    }
+
+    /// Record that `name` was just built with `cache_key`.
+    pub fn record(&mut self, name: &str, cache_key: String, row_count: Option<usize>) {
+        self.models.insert(
+            name.to_string(),
+            CachedBuild {
+                cache_key,
+                built_at: chrono::Utc::now().to_rfc3339(),
+                row_count,
+            },
+        );
+    }
+
+    /// Forget `name`, e.g. after a failed build may have left it half-built.
+    pub fn invalidate(&mut self, name: &str) {
+        self.models.remove(name);
+    }
+}
+
+/// A change in a model's row count since the previous run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowCountChange {
+    pub previous: usize,
+    pub current: usize,
+    /// Relative to `previous`; infinite when a model that was empty has rows
+    pub percent: f64,
+}
+
+impl RowCountChange {
+    pub fn new(previous: usize, current: usize) -> Self {
+        let percent = if previous == current {
+            0.0
+        } else if previous == 0 {
+            f64::INFINITY
+        } else {
+            (current as f64 - previous as f64) / previous as f64 * 100.0
+        };
+        Self {
+            previous,
+            current,
+            percent,
+        }
+    }
+
+    /// Whether the count moved by more than `threshold_percent` either way.
+    pub fn exceeds(&self, threshold_percent: f64) -> bool {
+        self.percent.abs() > threshold_percent
+    }
+}
+
+impl std::fmt::Display for RowCountChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "row count changed from {} to {}",
+            self.previous, self.current
+        )?;
+        if self.percent.is_finite() {
+            write!(f, " ({:+.1}%)", self.percent)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialize an artifact as pretty-printed JSON to `<dir>/<file_name>`.
+pub fn write_artifact<T: Serialize>(dir: &Path, file_name: &str, artifact: &T) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create artifact directory {:?}", dir))?;
+
+    let path = dir.join(file_name);
+    let json = serde_json::to_string_pretty(artifact)?;
+    std::fs::write(&path, json).with_context(|| format!("Failed to write {:?}", path))?;
+
+    Ok(path)
+}
+
+/// Directory query plans are written to by `smelt run --explain`.
+pub fn plans_dir(project_root: &Path) -> PathBuf {
+    artifacts_dir(project_root).join("plans")
+}
+
+/// Write a model's query plan to `<dir>/<model>.txt`, returning the written path.
+pub fn write_plan(dir: &Path, model_name: &str, plan: &str) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create plan directory {:?}", dir))?;
+
+    let path = dir.join(format!("{}.txt", model_name));
+    let mut contents = plan.trim_end().to_string();
+    contents.push('\n');
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::RefInfo;
+    use rowan::TextRange;
+
+    fn make_model(name: &str, content: &str, deps: Vec<&str>) -> ModelFile {
+        ModelFile {
+            name: name.to_string(),
+            path: PathBuf::from("/project/models").join(format!("{}.sql", name)),
+            content: content.to_string(),
+            refs: deps
+                .into_iter()
+                .map(|dep| RefInfo {
+                    model_name: dep.to_string(),
+                    has_named_params: false,
+                    range: TextRange::default(),
+                })
+                .collect(),
+            parse_errors: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let sql = "SELECT * FROM smelt.ref('a')";
+        let parse = smelt_parser::parse(sql);
+        let file = smelt_parser::File::cast(parse.syntax()).unwrap();
+        let mut b = make_model("b", sql, vec![]);
+        b.refs = file
+            .refs()
+            .map(|r| RefInfo {
+                model_name: r.model_name().unwrap(),
+                has_named_params: false,
+                range: r.range(),
+            })
+            .collect();
+
+        let models = vec![make_model("a", "SELECT 1 AS id", vec![]), b];
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let config = Config::for_test("test");
+        let compiler = SqlCompiler::new(config.clone());
+
+        let manifest = Manifest::build(
+            &graph,
+            &compiler,
+            &config,
+            Path::new("/project"),
+            "dev",
+            "main",
+        )
+        .unwrap();
+
+        let node = &manifest.nodes["b"];
+        assert_eq!(node.path, PathBuf::from("models/b.sql"));
+        assert_eq!(node.compiled_sql, "SELECT * FROM main.a");
+        assert_eq!(node.depends_on, vec!["a"]);
+        assert_eq!(node.checksum, checksum(sql));
+        assert_eq!(node.schema, "main");
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = write_artifact(temp_dir.path(), MANIFEST_FILE, &manifest).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap(), manifest);
+    }
+
+    #[test]
+    fn test_deferred_schemas() {
+        let models = vec![
+            make_model("a", "SELECT 1", vec![]),
+            make_model("b", "SELECT 1", vec!["a"]),
+            make_model("c", "SELECT 1", vec!["b"]),
+            make_model("d", "SELECT 1", vec!["c"]),
+        ];
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let config = Config::for_test("test");
+        let mut state = Manifest::build(
+            &graph,
+            &SqlCompiler::new(config.clone()),
+            &config,
+            Path::new("/project"),
+            "prod",
+            "production",
+        )
+        .unwrap();
+        // `b` is ephemeral in production and `d` is new since then
+        state.nodes.get_mut("b").unwrap().materialization = Materialization::Ephemeral;
+        state.nodes.remove("d");
+
+        let deferred = state.deferred_schemas(&graph, &["c".to_string()]).unwrap();
+        assert_eq!(
+            deferred,
+            HashMap::from([("a".to_string(), "production".to_string())])
+        );
+
+        // Manifests without schemas can't be deferred to
+        state.nodes.get_mut("a").unwrap().schema.clear();
+        assert!(state.deferred_schemas(&graph, &["c".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_run_results_serialization() {
+        let results = RunResults::new(
+            ArtifactMetadata::new("test", "dev"),
+            Duration::from_millis(1500),
+            vec![
+                NodeResult::success(&ExecutionResult {
+                    model_name: "a".to_string(),
+                    duration: Duration::from_millis(500),
+                    row_count: 42,
+                    preview: None,
+                    stats: QueryStats {
+                        backend_time: Some(Duration::from_millis(250)),
+                        rows_scanned: Some(1000),
+                        ..QueryStats::default()
+                    },
+                }),
+                NodeResult::error("b", Duration::from_secs(1), &anyhow::anyhow!("boom")),
+                NodeResult::skipped("c", "upstream model 'b' failed"),
+            ],
+        );
+
+        let json: serde_json::Value = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["elapsed_secs"], 1.5);
+        assert_eq!(json["results"][0]["status"], "success");
+        assert_eq!(json["results"][0]["row_count"], 42);
+        assert_eq!(json["results"][0]["stats"]["backend_time_secs"], 0.25);
+        assert_eq!(json["results"][0]["stats"]["rows_scanned"], 1000);
+        assert!(json["results"][0]["stats"].get("bytes_written").is_none());
+        assert!(json["results"][1].get("stats").is_none());
+        assert_eq!(json["results"][1]["status"], "error");
+        assert_eq!(json["results"][1]["message"], "boom");
+        assert_eq!(json["results"][2]["status"], "skipped");
+        assert_eq!(json["results"][2]["message"], "upstream model 'b' failed");
+        assert!(json["results"][2].get("row_count").is_none());
+    }
+
+    #[test]
+    fn test_previous_row_counts() {
+        let success = |name: &str, row_count| {
+            NodeResult::success(&ExecutionResult {
+                model_name: name.to_string(),
+                duration: Duration::ZERO,
+                row_count,
+                preview: None,
+                stats: QueryStats::default(),
+            })
+        };
+        let results = RunResults::new(
+            ArtifactMetadata::new("test", "dev"),
+            Duration::ZERO,
+            vec![
+                success("orders", 1200),
+                NodeResult::error("users", Duration::ZERO, &anyhow::anyhow!("boom")),
+            ],
+        );
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(previous_row_counts(temp_dir.path(), "dev").is_empty());
+
+        write_artifact(temp_dir.path(), RUN_RESULTS_FILE, &results).unwrap();
+        assert_eq!(
+            previous_row_counts(temp_dir.path(), "dev"),
+            HashMap::from([("orders".to_string(), 1200)])
+        );
+        assert!(previous_row_counts(temp_dir.path(), "prod").is_empty());
+    }
+
+    #[test]
+    fn test_build_cache() {
+        let node = |name: &str, materialization, depends_on: &[&str]| ManifestNode {
+            name: name.to_string(),
+            path: PathBuf::from(format!("models/{}.sql", name)),
+            checksum: String::new(),
+            config_checksum: String::new(),
+            compiled_sql: format!("SELECT * FROM {}", depends_on.join(", ")),
+            materialization,
+            schema: "main".to_string(),
+            database: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            tags: Vec::new(),
+        };
+        let mut manifest = Manifest {
+            metadata: ArtifactMetadata::new("test", "dev"),
+            nodes: BTreeMap::new(),
+        };
+        for node in [
+            node("stg", Materialization::View, &["raw.orders"]),
+            node("eph", Materialization::Ephemeral, &["stg"]),
+            node("mart", Materialization::Table, &["eph"]),
+        ] {
+            manifest.nodes.insert(node.name.clone(), node);
+        }
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut cache = BuildCache::load(temp_dir.path(), "test", "dev");
+        assert!(cache.models.is_empty());
+
+        // Sources don't need a build; models do, through ephemeral models
+        let stg_key = cache.cache_key(&manifest, "stg").unwrap();
+        assert_eq!(cache.cache_key(&manifest, "mart"), None);
+        cache.record("stg", stg_key.clone(), Some(3));
+        let mart_key = cache.cache_key(&manifest, "mart").unwrap();
+        cache.record("mart", mart_key.clone(), Some(1));
+
+        assert_eq!(
+            cache.cache_key(&manifest, "mart").as_deref(),
+            Some(&*mart_key)
+        );
+        assert_eq!(cache.hit("mart", &mart_key).unwrap().row_count, Some(1));
+
+        // Rebuilding an upstream model, or changing the SQL, misses
+        std::thread::sleep(Duration::from_millis(2));
+        cache.record("stg", stg_key.clone(), Some(3));
+        assert_ne!(cache.cache_key(&manifest, "mart").unwrap(), mart_key);
+        manifest.nodes.get_mut("stg").unwrap().database = Some("lake".to_string());
+        assert!(cache
+            .hit("stg", &cache.cache_key(&manifest, "stg").unwrap())
+            .is_none());
+        manifest.nodes.get_mut("stg").unwrap().database = None;
+        manifest.nodes.get_mut("stg").unwrap().compiled_sql = "SELECT 1".to_string();
+        assert!(cache
+            .hit("stg", &cache.cache_key(&manifest, "stg").unwrap())
+            .is_none());
+
+        cache.write(temp_dir.path()).unwrap();
+        assert_eq!(BuildCache::load(temp_dir.path(), "test", "dev"), cache);
+        assert!(BuildCache::load(temp_dir.path(), "test", "prod")
+            .models
+            .is_empty());
+
+        BuildCache::clear(temp_dir.path(), "dev").unwrap();
+        BuildCache::clear(temp_dir.path(), "dev").unwrap();
+        assert!(BuildCache::load(temp_dir.path(), "test", "dev")
+            .models
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cache_key_covers_config() {
+        let model = make_model("orders", "SELECT 1 AS id", vec![]);
+        let node = |config: &Config| ManifestNode {
+            name: model.name.clone(),
+            path: PathBuf::from("models/orders.sql"),
+            checksum: checksum(&model.content),
+            config_checksum: config_checksum(config, &model, "dev"),
+            compiled_sql: model.content.clone(),
+            materialization: Materialization::Table,
+            schema: "main".to_string(),
+            database: None,
+            depends_on: Vec::new(),
+            tags: Vec::new(),
+        };
+        let manifest = |config: &Config| Manifest {
+            metadata: ArtifactMetadata::new("test", "dev"),
+            nodes: BTreeMap::from([("orders".to_string(), node(config))]),
+        };
+
+        let mut config = Config::for_test("test");
+        let mut cache = BuildCache::new("test", "dev");
+        let key = cache.cache_key(&manifest(&config), "orders").unwrap();
+        cache.record("orders", key, None);
+
+        // Adding a post-hook in smelt.yml misses, as does a grant
+        config.hooks.post.push("ANALYZE {{ this }}".to_string());
+        let key = cache.cache_key(&manifest(&config), "orders").unwrap();
+        assert!(cache.hit("orders", &key).is_none());
+        cache.record("orders", key.clone(), None);
+        assert!(cache.hit("orders", &key).is_some());
+
+        config.models.insert(
+            "orders".to_string(),
+            serde_yaml::from_str("grants:\n  select: [analyst]\n").unwrap(),
+        );
+        let key = cache.cache_key(&manifest(&config), "orders").unwrap();
+        assert!(cache.hit("orders", &key).is_none());
+    }
+
+    #[test]
+    fn test_row_count_change() {
+        let drop = RowCountChange::new(1200, 180);
+        assert_eq!(drop.percent, -85.0);
+        assert!(drop.exceeds(50.0));
+        assert!(!drop.exceeds(90.0));
+        assert_eq!(
+            drop.to_string(),
+            "row count changed from 1200 to 180 (-85.0%)"
+        );
+
+        assert!(!RowCountChange::new(100, 110).exceeds(10.0));
+        assert!(!RowCountChange::new(0, 0).exceeds(0.0));
+
+        let from_empty = RowCountChange::new(0, 5);
+        assert!(from_empty.exceeds(1000.0));
+        assert_eq!(from_empty.to_string(), "row count changed from 0 to 5");
+    }
+
+    #[test]
+    fn test_write_plan() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = plans_dir(temp_dir.path());
+
+        let path = write_plan(&dir, "orders", "SEQ_SCAN orders\n\n").unwrap();
+        assert_eq!(path, temp_dir.path().join("target/plans/orders.txt"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "SEQ_SCAN orders\n");
+    }
+
+    #[test]
+    fn test_checksum_is_stable() {
+        assert_eq!(
+            checksum(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(checksum("SELECT 1"), checksum("SELECT 2"));
+    }
+}
@@ -1,8 +1,16 @@
+use crate::artifacts::CompileCache;
 use crate::config::{Config, Materialization};
-use crate::discovery::ModelFile;
+use crate::discovery::{ref_model_name, ModelFile};
 use crate::errors::{extract_snippet, text_range_to_line_col, CliError};
-use anyhow::{anyhow, Result};
+use crate::metadata::{extract_file_metadata, FileMetadata};
+use crate::rewrite::rewrite_query;
+use anyhow::{anyhow, Context, Result};
 use rowan::TextRange;
+use sha2::{Digest, Sha256};
+use smelt_backend::{Backend, BackendCapabilities, RelationName, SqlDialect};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 #[derive(Debug, Clone)]
 pub struct CompiledModel {
@@ -11,37 +19,199 @@ pub struct CompiledModel {
     pub materialization: Materialization,
 }
 
-/// Replace smelt.ref() calls with qualified table names using AST-based ranges.
+/// Replace smelt.ref() and smelt.source() calls with qualified table names using AST-based ranges.
 ///
 /// This function performs byte-exact replacements using TextRange positions from the parser.
-/// Refs are processed from end to start to avoid offset shifting.
-fn replace_refs_with_ranges(
+/// Refs are replaced with `resolve(model_name)`; sources are resolved to the `source.table`
+/// name they name. Replacements are processed from end to start to avoid offset shifting.
+fn replace_refs_with(
     sql: &str,
     refs: &[(String, TextRange)], // (model_name, range)
-    schema: &str,
+    resolve: impl Fn(&str) -> String,
 ) -> String {
+    let mut replacements: Vec<(TextRange, String)> = refs
+        .iter()
+        .map(|(model_name, range)| (*range, resolve(model_name)))
+        .collect();
+    replacements.extend(source_replacements(sql));
+
     // Sort by position (descending) to avoid offset shifting
-    let mut sorted: Vec<_> = refs.iter().collect();
-    sorted.sort_by(|a, b| b.1.start().cmp(&a.1.start()));
+    replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start()));
 
     let mut result = sql.to_string();
-    for (model_name, range) in sorted {
+    for (range, replacement) in replacements {
         let start = usize::from(range.start());
         let end = usize::from(range.end());
-        let replacement = format!("{}.{}", schema, model_name);
         result.replace_range(start..end, &replacement);
     }
 
     result
 }
 
+/// Find smelt.source() calls and the qualified table names they resolve to.
+fn source_replacements(sql: &str) -> Vec<(TextRange, String)> {
+    let parse = smelt_parser::parse(sql);
+    let Some(file) = smelt_parser::File::cast(parse.syntax()) else {
+        return Vec::new();
+    };
+
+    file.sources()
+        .filter_map(|source_call| Some((source_call.range(), source_call.qualified_name()?)))
+        .collect()
+}
+
+/// Directory compiled SQL is written to, relative to the project root.
+pub fn compiled_dir(project_root: &Path) -> PathBuf {
+    project_root.join("target").join("compiled")
+}
+
+/// Write a compiled model to `<dir>/<model>.sql`, returning the written path.
+pub fn write_compiled_model(dir: &Path, model: &CompiledModel) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory {:?}", dir))?;
+
+    let path = dir.join(format!("{}.sql", model.name));
+    let mut contents = model.sql.trim_end().to_string();
+    contents.push('\n');
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write compiled SQL to {:?}", path))?;
+
+    Ok(path)
+}
+
+/// Name of the CTE an ephemeral model is inlined as.
+pub fn ephemeral_cte_name(model_name: &str) -> String {
+    format!("__smelt_cte__{}", model_name)
+}
+
 pub struct SqlCompiler {
     config: Config,
+    /// Ephemeral models, inlined as CTEs into the models that ref them
+    ephemeral: HashMap<String, ModelFile>,
+    /// Models built outside the target schema
+    schemas: HashMap<String, String>,
+    /// Models built outside the target's database
+    databases: HashMap<String, String>,
+    /// Unselected models resolved to a production schema (`--defer`)
+    deferred: HashMap<String, String>,
+    /// What the target backend supports; syntax it lacks is rewritten
+    capabilities: BackendCapabilities,
+    /// Compiled SQL reused across invocations, if enabled
+    cache: Option<Mutex<CompileCache>>,
 }
 
 impl SqlCompiler {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            ephemeral: HashMap::new(),
+            schemas: HashMap::new(),
+            databases: HashMap::new(),
+            deferred: HashMap::new(),
+            capabilities: SqlDialect::DuckDB.capabilities(),
+            cache: None,
+        }
+    }
+
+    /// Compile for a connected backend, rewriting syntax it doesn't support
+    /// (QUALIFY, `::` casts) into forms it does.
+    pub fn with_backend(self, backend: &dyn Backend) -> Self {
+        self.with_capabilities(backend.capabilities())
+    }
+
+    /// Compile for a backend with these capabilities, e.g. a dialect's
+    /// typical ones when compiling without a connection.
+    pub fn with_capabilities(mut self, capabilities: BackendCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Register the project's models so refs to ephemeral models can be inlined
+    /// and refs to models with their own schema or database resolve to it.
+    pub fn with_models<'a>(mut self, models: impl IntoIterator<Item = &'a ModelFile>) -> Self {
+        for model in models {
+            if self.materialization(model) == Materialization::Ephemeral {
+                self.ephemeral.insert(model.name.clone(), model.clone());
+            }
+            if let Some(schema) = self.config.get_model_schema(model) {
+                self.schemas.insert(model.name.clone(), schema);
+            }
+            if let Some(database) = self.config.get_model_database(model) {
+                self.databases
+                    .insert(model.name.clone(), database.to_string());
+            }
+        }
+        self
+    }
+
+    /// Resolve refs to these models (name to schema) in another environment's
+    /// schema rather than the target's; see [`Manifest::deferred_schemas`].
+    ///
+    /// [`Manifest::deferred_schemas`]: crate::artifacts::Manifest::deferred_schemas
+    pub fn with_deferred(mut self, deferred: HashMap<String, String>) -> Self {
+        self.deferred = deferred;
+        self
+    }
+
+    /// Reuse SQL compiled by earlier invocations from `cache`, and record what
+    /// [`Self::compile`] produces in it; see [`Self::write_cache`].
+    ///
+    /// An entry is keyed by a hash of the model's name and SQL, the schema and
+    /// backend it's compiled for, and what each of its refs resolves to,
+    /// including the SQL of inlined ephemeral models. Refs cover the config
+    /// compilation reads: the schemas, databases, and deferral of upstream
+    /// models.
+    pub fn with_cache(mut self, cache: CompileCache) -> Self {
+        self.cache = Some(Mutex::new(cache));
+        self
+    }
+
+    /// Write the compile cache to `dir` if anything new was compiled.
+    pub fn write_cache(&self, dir: &Path) -> Result<()> {
+        match &self.cache {
+            Some(cache) => cache.lock().unwrap().write(dir),
+            None => Ok(()),
+        }
+    }
+
+    /// The schema refs to a model resolve to: its deferred schema, the schema
+    /// it's built in, or `default` (the target's schema).
+    pub fn schema_for<'a>(&'a self, model_name: &str, default: &'a str) -> &'a str {
+        self.deferred
+            .get(model_name)
+            .or_else(|| self.schemas.get(model_name))
+            .map(String::as_str)
+            .unwrap_or(default)
+    }
+
+    /// The relation a model is built as and refs to it resolve to: in
+    /// [`Self::schema_for`], and in its own database if it has one. Package
+    /// models are named without their package.
+    pub fn relation_for(&self, model_name: &str, default_schema: &str) -> RelationName {
+        let table = self
+            .config
+            .package_of(model_name)
+            .map_or(model_name, |(_, table)| table);
+        let relation = RelationName::new(self.schema_for(model_name, default_schema), table);
+        match self.databases.get(model_name) {
+            Some(database) => relation.with_catalog(database),
+            None => relation,
+        }
+    }
+
+    /// Whether a registered model is ephemeral (and so never materialized).
+    pub fn is_ephemeral(&self, model_name: &str) -> bool {
+        self.ephemeral.contains_key(model_name)
+    }
+
+    /// Whether a model is built by a program rather than from SQL.
+    pub fn is_program(&self, model_name: &str) -> bool {
+        self.config.get_program(model_name).is_some()
+    }
+
+    /// Get materialization: SQL metadata > smelt.yml > default
+    fn materialization(&self, model: &ModelFile) -> Materialization {
+        self.config.get_model_materialization(model)
     }
 
     /// Compile a model's SQL by replacing smelt.ref() calls with table references
@@ -70,22 +240,84 @@ impl SqlCompiler {
             .map(|r| (r.model_name.clone(), r.range))
             .collect();
 
-        // Use AST-based replacement with precise byte offsets
-        let compiled_sql = replace_refs_with_ranges(&model.content, &refs, schema);
-
-        // Get materialization: SQL metadata > smelt.yml > default
-        let materialization = self.config.get_materialization_with_metadata(
-            &model.name,
-            model.metadata.as_ref().map(|b| b.as_ref()),
-        );
-
+        let compile = || self.rewrite(&self.resolve_refs(&model.content, &refs, schema));
+        let sql = match &self.cache {
+            Some(cache) => {
+                let key = self.cache_key(model, &refs, schema);
+                let cached = cache
+                    .lock()
+                    .unwrap()
+                    .get(&model.name, &key)
+                    .map(str::to_string);
+                match cached {
+                    Some(sql) => sql,
+                    None => {
+                        let sql = compile()?;
+                        cache.lock().unwrap().insert(&model.name, key, sql.clone());
+                        sql
+                    }
+                }
+            }
+            None => compile()?,
+        };
         Ok(CompiledModel {
             name: model.name.clone(),
-            sql: compiled_sql,
-            materialization,
+            sql,
+            materialization: self.materialization(model),
         })
     }
 
+    /// Key of a model's compiled SQL in the compile cache; see [`Self::with_cache`].
+    fn cache_key(&self, model: &ModelFile, refs: &[(String, TextRange)], schema: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "{}\0{}\0{}\0{:?}\0",
+            env!("CARGO_PKG_VERSION"),
+            model.name,
+            schema,
+            self.capabilities
+        ));
+        hasher.update(model.content.as_bytes());
+
+        let mut visiting = HashSet::new();
+        let mut inlined = Vec::new();
+        for (model_name, _) in refs {
+            hasher.update(format!(
+                "\0{}={}",
+                model_name,
+                self.resolve_ref(model_name, schema)
+            ));
+            self.collect_ephemeral(model_name, &mut visiting, &mut inlined);
+        }
+        for ephemeral in inlined {
+            hasher.update(format!("\0{}\0", ephemeral.name));
+            hasher.update(ephemeral.content.as_bytes());
+            for r in &ephemeral.refs {
+                hasher.update(format!(
+                    "\0{}={}",
+                    r.model_name,
+                    self.resolve_ref(&r.model_name, schema)
+                ));
+            }
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// What a ref to `model_name` compiles to: its CTE if it's ephemeral, or
+    /// its relation.
+    fn resolve_ref(&self, model_name: &str, schema: &str) -> String {
+        if self.is_ephemeral(model_name) {
+            ephemeral_cte_name(model_name)
+        } else {
+            self.relation_for(model_name, schema).to_string()
+        }
+    }
+
     /// Compile a model with custom SQL (e.g., for transformed queries).
     /// This is used for incremental processing where the SQL has been transformed.
     pub fn compile_with_sql(
@@ -101,38 +333,159 @@ impl SqlCompiler {
             .ok_or_else(|| anyhow!("Failed to parse transformed SQL"))?;
 
         // Extract refs with their ranges from transformed SQL
+        let package = self
+            .config
+            .package_of(&model.name)
+            .map(|(package, _)| package);
         let refs: Vec<(String, TextRange)> = file
             .refs()
             .filter_map(|ref_call| {
-                let name = ref_call.model_name()?;
+                let name = ref_model_name(&ref_call, package)?;
                 let range = ref_call.range();
                 Some((name, range))
             })
             .collect();
 
-        // Use AST-based replacement with precise byte offsets
-        let compiled_sql = replace_refs_with_ranges(sql, &refs, schema);
-
-        // Get materialization: SQL metadata > smelt.yml > default
-        let materialization = self.config.get_materialization_with_metadata(
-            &model.name,
-            model.metadata.as_ref().map(|b| b.as_ref()),
-        );
-
+        let sql = self.resolve_refs(sql, &refs, schema);
         Ok(CompiledModel {
             name: model.name.clone(),
-            sql: compiled_sql,
-            materialization,
+            sql: self.rewrite(&sql)?,
+            materialization: self.materialization(model),
         })
     }
+
+    /// Replace refs using AST-based byte offsets, inlining ephemeral models as CTEs.
+    pub(crate) fn resolve_refs(
+        &self,
+        sql: &str,
+        refs: &[(String, TextRange)],
+        schema: &str,
+    ) -> String {
+        let resolve = |model_name: &str| self.resolve_ref(model_name, schema);
+        let compiled_sql = replace_refs_with(sql, refs, resolve);
+
+        let mut visiting = HashSet::new();
+        let mut inlined = Vec::new();
+        for (model_name, _) in refs {
+            self.collect_ephemeral(model_name, &mut visiting, &mut inlined);
+        }
+        if inlined.is_empty() {
+            return compiled_sql;
+        }
+
+        let ctes: Vec<String> = inlined
+            .iter()
+            .map(|model| {
+                let refs: Vec<(String, TextRange)> = model
+                    .refs
+                    .iter()
+                    .map(|r| (r.model_name.clone(), r.range))
+                    .collect();
+                let body = replace_refs_with(&model.content, &refs, resolve);
+                let (_, body) = split_frontmatter(&body);
+                format!(
+                    "{} AS (\n{}\n)",
+                    ephemeral_cte_name(&model.name),
+                    body.trim().trim_end_matches(';').trim_end()
+                )
+            })
+            .collect();
+
+        let (frontmatter, body) = split_frontmatter(&compiled_sql);
+        let body = match leading_with(body) {
+            // Prepend to the model's own CTEs
+            Some(end) => format!(
+                "{}\n{},\n{}",
+                &body[..end],
+                ctes.join(",\n"),
+                body[end..].trim_start()
+            ),
+            None => format!("WITH {}\n{}", ctes.join(",\n"), body.trim_start()),
+        };
+        format!("{}{}", frontmatter, body)
+    }
+
+    /// Rewrite syntax the backend lacks in compiled SQL, leaving any
+    /// frontmatter as it is.
+    pub(crate) fn rewrite(&self, sql: &str) -> Result<String> {
+        let (frontmatter, body) = split_frontmatter(sql);
+        Ok(format!(
+            "{}{}",
+            frontmatter,
+            rewrite_query(body, &self.capabilities)?
+        ))
+    }
+
+    /// Add `model_name` (if ephemeral) to `inlined` after the ephemeral models it refs.
+    fn collect_ephemeral<'a>(
+        &'a self,
+        model_name: &str,
+        visiting: &mut HashSet<&'a str>,
+        inlined: &mut Vec<&'a ModelFile>,
+    ) {
+        let Some(model) = self.ephemeral.get(model_name) else {
+            return;
+        };
+        // Already inlined, or a cycle (reported by the dependency graph)
+        if !visiting.insert(&model.name) {
+            return;
+        }
+        for r in &model.refs {
+            self.collect_ephemeral(&r.model_name, visiting, inlined);
+        }
+        inlined.push(model);
+    }
+}
+
+/// Split off a single-model YAML frontmatter block, if any.
+pub(crate) fn split_frontmatter(sql: &str) -> (&str, &str) {
+    match extract_file_metadata(sql) {
+        Ok(FileMetadata::Single { sql_offset, .. }) if sql_offset <= sql.len() => {
+            sql.split_at(sql_offset)
+        }
+        _ => ("", sql),
+    }
+}
+
+/// If the statement starts with `WITH` (after comments), the offset just past
+/// `WITH` / `WITH RECURSIVE`.
+fn leading_with(sql: &str) -> Option<usize> {
+    let mut offset = 0;
+    loop {
+        let rest = &sql[offset..];
+        let trimmed = rest.trim_start();
+        offset += rest.len() - trimmed.len();
+
+        if trimmed.starts_with("--") {
+            offset += trimmed.find('\n').unwrap_or(trimmed.len());
+        } else if trimmed.starts_with("/*") {
+            offset += trimmed.find("*/").map_or(trimmed.len(), |end| end + 2);
+        } else {
+            break;
+        }
+    }
+
+    let keyword = |offset: usize, word: &str| {
+        let rest = &sql[offset..];
+        let matched = rest.len() >= word.len()
+            && rest[..word.len()].eq_ignore_ascii_case(word)
+            && rest[word.len()..]
+                .chars()
+                .next()
+                .is_some_and(char::is_whitespace);
+        matched.then_some(offset + word.len())
+    };
+
+    let end = keyword(offset, "WITH")?;
+    let recursive_start = end + (sql[end..].len() - sql[end..].trim_start().len());
+    Some(keyword(recursive_start, "RECURSIVE").unwrap_or(end))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{ModelConfig, Target};
+    use crate::config::{Hooks, ModelConfig};
     use crate::discovery::RefInfo;
-    use std::collections::HashMap;
 
     /// Helper function to parse SQL and extract refs with real TextRange values
     fn extract_refs_from_sql(sql: &str) -> Vec<RefInfo> {
@@ -141,7 +494,7 @@ mod tests {
             file.refs()
                 .filter_map(|ref_call| {
                     Some(RefInfo {
-                        model_name: ref_call.model_name()?,
+                        model_name: ref_call.qualified_name()?,
                         has_named_params: ref_call.named_params().count() > 0,
                         range: ref_call.range(),
                     })
@@ -152,29 +505,6 @@ mod tests {
         }
     }
 
-    fn make_test_config() -> Config {
-        let mut targets = HashMap::new();
-        targets.insert(
-            "dev".to_string(),
-            Target {
-                target_type: "duckdb".to_string(),
-                database: Some("test.duckdb".to_string()),
-                schema: "main".to_string(),
-                connect_url: None,
-                catalog: None,
-            },
-        );
-
-        Config {
-            name: "test".to_string(),
-            version: 1,
-            model_paths: vec!["models".to_string()],
-            targets,
-            default_materialization: Materialization::View,
-            models: HashMap::new(),
-        }
-    }
-
     #[test]
     fn test_simple_ref_replacement() {
         let sql = r#"
@@ -194,7 +524,7 @@ GROUP BY user_id
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let compiled = compiler.compile(&model, "main").unwrap();
@@ -220,7 +550,7 @@ JOIN smelt.ref('model_b') b ON a.id = b.id
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let compiled = compiler.compile(&model, "main").unwrap();
@@ -246,7 +576,7 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let result = compiler.compile(&model, "main");
@@ -268,12 +598,23 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
             metadata: None,
         };
 
-        let mut config = make_test_config();
+        let mut config = Config::for_test("test");
         config.models.insert(
             "test_model".to_string(),
             ModelConfig {
                 materialization: Some(Materialization::Table),
                 incremental: None,
+                hooks: Hooks::default(),
+                retries: None,
+                timeout_seconds: None,
+                row_count_warn_percent: None,
+                contract: None,
+                tags: Vec::new(),
+                schema: None,
+                database: None,
+                location: None,
+                grants: Default::default(),
+                program: None,
             },
         );
 
@@ -296,7 +637,7 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let compiled = compiler.compile(&model, "main").unwrap();
@@ -318,7 +659,7 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let compiled = compiler.compile(&model, "main").unwrap();
@@ -344,7 +685,7 @@ JOIN smelt.ref('model_a') b ON a.parent_id = b.id
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let compiled = compiler.compile(&model, "main").unwrap();
@@ -373,7 +714,7 @@ WHERE event_type = 'click'
             metadata: None,
         };
 
-        let config = make_test_config();
+        let config = Config::for_test("test");
         let compiler = SqlCompiler::new(config);
 
         let compiled = compiler.compile(&model, "main").unwrap();
@@ -384,4 +725,301 @@ WHERE event_type = 'click'
         assert!(compiled.sql.contains("WHERE event_type = 'click'"));
         assert!(!compiled.sql.contains("smelt.ref"));
     }
+
+    #[test]
+    fn test_source_replacement() {
+        let sql = r#"
+SELECT u.user_id, e.event_type
+FROM smelt.source('raw.users') u
+JOIN smelt.ref('events') e ON u.user_id = e.user_id
+"#;
+
+        let model = ModelFile {
+            name: "test".to_string(),
+            path: "models/test.sql".into(),
+            content: sql.to_string(),
+            refs: extract_refs_from_sql(sql),
+            parse_errors: Vec::new(),
+            metadata: None,
+        };
+
+        let config = Config::for_test("test");
+        let compiler = SqlCompiler::new(config);
+
+        let compiled = compiler.compile(&model, "main").unwrap();
+
+        assert!(compiled.sql.contains("FROM raw.users u"));
+        assert!(compiled.sql.contains("JOIN main.events e"));
+        assert!(!compiled.sql.contains("smelt."));
+    }
+
+    #[test]
+    fn test_write_compiled_model() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = compiled_dir(temp_dir.path());
+
+        let model = CompiledModel {
+            name: "user_stats".to_string(),
+            sql: "SELECT 1\n\n".to_string(),
+            materialization: Materialization::View,
+        };
+
+        let path = write_compiled_model(&dir, &model).unwrap();
+
+        assert_eq!(path, temp_dir.path().join("target/compiled/user_stats.sql"));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "SELECT 1\n");
+    }
+
+    fn make_model(name: &str, sql: &str) -> ModelFile {
+        ModelFile {
+            name: name.to_string(),
+            path: format!("models/{}.sql", name).into(),
+            content: sql.to_string(),
+            refs: extract_refs_from_sql(sql),
+            parse_errors: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_compile_for_backend_capabilities() {
+        let model = make_model(
+            "latest_orders",
+            "SELECT id, ts::DATE AS day FROM smelt.ref('orders') QUALIFY ROW_NUMBER() OVER (PARTITION BY id ORDER BY ts DESC) = 1",
+        );
+
+        let duckdb = SqlCompiler::new(Config::for_test("test"));
+        assert_eq!(
+            duckdb.compile(&model, "main").unwrap().sql,
+            "SELECT id, ts::DATE AS day FROM main.orders QUALIFY ROW_NUMBER() OVER (PARTITION BY id ORDER BY ts DESC) = 1"
+        );
+
+        let sqlite = SqlCompiler::new(Config::for_test("test"))
+            .with_capabilities(SqlDialect::SQLite.capabilities());
+        assert_eq!(
+            sqlite.compile(&model, "main").unwrap().sql,
+            "SELECT id, day FROM (\nSELECT id, CAST(ts AS DATE) AS day, ROW_NUMBER() OVER (PARTITION BY id ORDER BY ts DESC) = 1 AS __smelt_qualify FROM main.orders\n) AS smelt_qualified WHERE __smelt_qualify"
+        );
+    }
+
+    #[test]
+    fn test_ephemeral_models_inlined_as_ctes() {
+        let mut config = Config::for_test("test");
+        for name in ["stg_users", "active_users"] {
+            config.models.insert(
+                name.to_string(),
+                serde_yaml::from_str("materialized: ephemeral").unwrap(),
+            );
+        }
+
+        let models = vec![
+            make_model("raw_users", "SELECT 1 AS id"),
+            make_model("stg_users", "SELECT * FROM smelt.ref('raw_users');"),
+            make_model(
+                "active_users",
+                "SELECT * FROM smelt.ref('stg_users') WHERE active",
+            ),
+            make_model(
+                "report",
+                "-- Active user report\nSELECT a.id FROM smelt.ref('active_users') a JOIN smelt.ref('stg_users') s ON a.id = s.id",
+            ),
+        ];
+        let compiler = SqlCompiler::new(config).with_models(&models);
+        assert!(compiler.is_ephemeral("stg_users"));
+        assert!(!compiler.is_ephemeral("report"));
+
+        let compiled = compiler.compile(&models[3], "main").unwrap();
+        assert_eq!(
+            compiled.sql,
+            "WITH __smelt_cte__stg_users AS (\nSELECT * FROM main.raw_users\n),\n\
+             __smelt_cte__active_users AS (\nSELECT * FROM __smelt_cte__stg_users WHERE active\n)\n\
+             -- Active user report\nSELECT a.id FROM __smelt_cte__active_users a JOIN __smelt_cte__stg_users s ON a.id = s.id"
+        );
+    }
+
+    #[test]
+    fn test_ephemeral_merged_into_existing_with() {
+        let mut config = Config::for_test("test");
+        config.models.insert(
+            "base".to_string(),
+            serde_yaml::from_str("materialization: ephemeral").unwrap(),
+        );
+
+        let models = vec![
+            make_model("base", "SELECT 1 AS id"),
+            make_model(
+                "downstream",
+                "-- header\nwith ids AS (SELECT id FROM smelt.ref('base'))\nSELECT * FROM ids",
+            ),
+        ];
+        let compiler = SqlCompiler::new(config).with_models(&models);
+
+        let compiled = compiler.compile(&models[1], "main").unwrap();
+        assert_eq!(
+            compiled.sql,
+            "-- header\nwith\n__smelt_cte__base AS (\nSELECT 1 AS id\n),\n\
+             ids AS (SELECT id FROM __smelt_cte__base)\nSELECT * FROM ids"
+        );
+    }
+
+    #[test]
+    fn test_deferred_refs() {
+        let mut config = Config::for_test("test");
+        config.models.insert(
+            "stg_users".to_string(),
+            serde_yaml::from_str("schema: staging").unwrap(),
+        );
+
+        let models = vec![
+            make_model("raw_users", "SELECT 1 AS id"),
+            make_model("stg_users", "SELECT * FROM smelt.ref('raw_users')"),
+            make_model(
+                "report",
+                "SELECT * FROM smelt.ref('stg_users') JOIN smelt.ref('raw_users') USING (id)",
+            ),
+        ];
+        let compiler = SqlCompiler::new(config)
+            .with_models(&models)
+            .with_deferred(HashMap::from([(
+                "stg_users".to_string(),
+                "prod_staging".to_string(),
+            )]));
+
+        let compiled = compiler.compile(&models[2], "dev").unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM prod_staging.stg_users JOIN dev.raw_users USING (id)"
+        );
+    }
+
+    #[test]
+    fn test_compile_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let models = vec![
+            make_model("users", "SELECT 1 AS id"),
+            make_model("report", "SELECT * FROM smelt.ref('users')"),
+        ];
+        let compiler = SqlCompiler::new(Config::for_test("test"))
+            .with_models(&models)
+            .with_cache(CompileCache::load(temp_dir.path()));
+        compiler.compile(&models[1], "dev").unwrap();
+        compiler.write_cache(temp_dir.path()).unwrap();
+
+        // Tamper with the cached SQL to see whether it's reused
+        let mut cache = CompileCache::load(temp_dir.path());
+        assert_eq!(cache.models["report"].sql, "SELECT * FROM dev.users");
+        cache.models.get_mut("report").unwrap().sql = "cached".to_string();
+        let compile = |config: Config, models: &[ModelFile]| {
+            SqlCompiler::new(config)
+                .with_models(models)
+                .with_cache(cache.clone())
+                .compile(&models[1], "dev")
+                .unwrap()
+                .sql
+        };
+        assert_eq!(compile(Config::for_test("test"), &models), "cached");
+
+        // Moving an upstream model to another schema changes the key
+        let mut config = Config::for_test("test");
+        config.models.insert(
+            "users".to_string(),
+            serde_yaml::from_str("schema: staging").unwrap(),
+        );
+        assert_eq!(compile(config, &models), "SELECT * FROM staging.users");
+
+        // As does editing the model
+        let edited = vec![
+            models[0].clone(),
+            make_model("report", "SELECT id FROM smelt.ref('users')"),
+        ];
+        assert_eq!(
+            compile(Config::for_test("test"), &edited),
+            "SELECT id FROM dev.users"
+        );
+    }
+
+    #[test]
+    fn test_refs_to_other_databases() {
+        let mut config = Config::for_test("test");
+        config.models.insert(
+            "events".to_string(),
+            serde_yaml::from_str("database: lake\nschema: raw").unwrap(),
+        );
+
+        let mut sessions = make_model("sessions", "SELECT 1 AS id");
+        sessions.metadata = Some(Box::new(
+            serde_yaml::from_str("database: warehouse").unwrap(),
+        ));
+        let models = vec![
+            make_model("events", "SELECT 1 AS id"),
+            sessions,
+            make_model(
+                "report",
+                "SELECT * FROM smelt.ref('events') JOIN smelt.ref('sessions') USING (id)",
+            ),
+        ];
+        let compiler = SqlCompiler::new(config).with_models(&models);
+
+        let compiled = compiler.compile(&models[2], "dev").unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM lake.raw.events JOIN warehouse.dev.sessions USING (id)"
+        );
+        assert_eq!(
+            compiler.relation_for("report", "dev"),
+            RelationName::new("dev", "report")
+        );
+    }
+
+    #[test]
+    fn test_refs_to_package_models() {
+        let mut config = Config::for_test("test");
+        config.packages.insert(
+            "shared".to_string(),
+            serde_yaml::from_str("path: ../shared\ndatabase: lake").unwrap(),
+        );
+
+        let mut orders = make_model(
+            "shared.orders",
+            "SELECT * FROM smelt.ref('customers') WHERE region = 'emea'",
+        );
+        orders.refs[0].model_name = "shared.customers".to_string();
+        let models = vec![
+            make_model("shared.customers", "SELECT 1 AS id"),
+            orders,
+            make_model("report", "SELECT * FROM smelt.ref('shared', 'orders')"),
+        ];
+        let compiler = SqlCompiler::new(config).with_models(&models);
+
+        let compiled = compiler.compile(&models[2], "dev").unwrap();
+        assert_eq!(compiled.sql, "SELECT * FROM lake.shared.orders");
+        assert_eq!(
+            compiler.relation_for("shared.orders", "dev"),
+            RelationName::new("shared", "orders").with_catalog("lake")
+        );
+
+        // Refs in rewritten SQL resolve within the package too
+        let compiled = compiler
+            .compile_with_sql(&models[1], "dev", &models[1].content)
+            .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM lake.shared.customers WHERE region = 'emea'"
+        );
+    }
+
+    #[test]
+    fn test_leading_with() {
+        assert_eq!(
+            leading_with("WITH a AS (SELECT 1) SELECT * FROM a"),
+            Some(4)
+        );
+        assert_eq!(
+            leading_with("-- c\n  with recursive t AS (SELECT 1)"),
+            Some(21)
+        );
+        assert_eq!(leading_with("/* x */ WITH a AS (SELECT 1)"), Some(12));
+        assert_eq!(leading_with("SELECT 1 AS with_col"), None);
+        assert_eq!(leading_with("WITHOUT"), None);
+    }
 }
@@ -1,13 +1,23 @@
+use crate::discovery::ModelFile;
 use crate::errors::CliError;
+use crate::lint::LintConfig;
+use crate::notify::Notification;
+use crate::template::{render, Vars};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use smelt_backend::SqlDialect;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Materialization {
     Table,
     View,
+    /// Never created in the database; inlined as a CTE into downstream models
+    Ephemeral,
+    /// Written to Parquet at the model's `location`, with a view over the files
+    External,
 }
 
 impl<'de> Deserialize<'de> for Materialization {
@@ -19,8 +29,10 @@ impl<'de> Deserialize<'de> for Materialization {
         match s.to_lowercase().as_str() {
             "table" => Ok(Materialization::Table),
             "view" => Ok(Materialization::View),
+            "ephemeral" => Ok(Materialization::Ephemeral),
+            "external" => Ok(Materialization::External),
             _ => Err(serde::de::Error::custom(format!(
-                "Invalid materialization type: {}. Must be 'table' or 'view'",
+                "Invalid materialization type: {}. Must be 'table', 'view', 'ephemeral', or 'external'",
                 s
             ))),
         }
@@ -35,6 +47,8 @@ impl Serialize for Materialization {
         match self {
             Materialization::Table => serializer.serialize_str("table"),
             Materialization::View => serializer.serialize_str("view"),
+            Materialization::Ephemeral => serializer.serialize_str("ephemeral"),
+            Materialization::External => serializer.serialize_str("external"),
         }
     }
 }
@@ -45,17 +59,68 @@ pub struct Config {
     pub version: u32,
     #[serde(default = "default_model_paths")]
     pub model_paths: Vec<String>,
+    #[serde(default = "default_seed_paths")]
+    pub seed_paths: Vec<String>,
+    #[serde(default = "default_operation_paths")]
+    pub operation_paths: Vec<String>,
     pub targets: HashMap<String, Target>,
     #[serde(default = "default_materialization")]
     pub default_materialization: Materialization,
     #[serde(default)]
     pub models: HashMap<String, ModelConfig>,
+    /// Hooks run around every model
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Times to retry a model after a transient backend error
+    #[serde(default)]
+    pub retries: u32,
+    /// Cancel a model's statements if it runs longer than this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// Warn when a model's row count changes by more than this percentage
+    /// since the previous run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_count_warn_percent: Option<f64>,
+    /// Append each model's outcome to `smelt_run_history` in the target schema
+    #[serde(default = "default_run_history")]
+    pub run_history: bool,
+    /// Values for `{{ var() }}` in model SQL; `--vars` overrides these
+    #[serde(default, skip_serializing_if = "Vars::is_empty")]
+    pub vars: Vars,
+    /// Model defaults keyed by directory relative to the project root (e.g. `models/staging`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub groups: HashMap<String, GroupConfig>,
+    /// Webhooks called when `smelt run` finishes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notifications: Vec<Notification>,
+    /// Rules for `smelt lint`
+    #[serde(default, skip_serializing_if = "LintConfig::is_empty")]
+    pub lint: LintConfig,
+    /// Other smelt projects whose models are built with this one, by package name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, PackageConfig>,
+    /// The active target's `schema_template`, with everything but `{schema}`
+    /// filled in; set by [`Config::use_target`]
+    #[serde(skip)]
+    pub schema_template: Option<String>,
 }
 
 fn default_model_paths() -> Vec<String> {
     vec!["models".to_string()]
 }
 
+fn default_seed_paths() -> Vec<String> {
+    vec!["seeds".to_string()]
+}
+
+fn default_operation_paths() -> Vec<String> {
+    vec!["operations".to_string()]
+}
+
+fn default_run_history() -> bool {
+    true
+}
+
 fn default_materialization() -> Materialization {
     Materialization::View
 }
@@ -64,96 +129,512 @@ fn default_materialization() -> Materialization {
 pub struct Target {
     #[serde(rename = "type")]
     pub target_type: String,
-    // DuckDB fields
+    // DuckDB and SQLite fields (Snowflake: the database name)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub database: Option<String>,
     pub schema: String,
+    /// Name for every schema built in, e.g. `dev_{user}_{schema}`, so
+    /// developers can share a warehouse: `{schema}` is the target's or the
+    /// model's own schema, `{user}` is `$SMELT_USER` (or `$USER`), `{target}`
+    /// the target's name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_template: Option<String>,
     // Spark fields
     #[serde(skip_serializing_if = "Option::is_none")]
     pub connect_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub catalog: Option<String>,
+    /// Format of the tables created: `delta`, `iceberg`, or `parquet`
+    /// (the catalog's default if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table_format: Option<String>,
+    /// `TBLPROPERTIES` set on every table created
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub table_properties: BTreeMap<String, String>,
+    /// DuckDB: more database files to attach, by name, so models can be
+    /// built in them with `database:`; paths are relative to the project
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attach: BTreeMap<String, String>,
+    // Snowflake fields
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warehouse: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key_path: Option<String>,
+    /// Privileges granted on every model built in this target, e.g. `{select: [analyst]}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub grants: BTreeMap<String, Vec<String>>,
 }
 
 impl Target {
     /// Get the backend type from the target_type field.
     pub fn backend_type(&self) -> BackendType {
+        // Default to DuckDB for backward compatibility
+        self.known_backend_type().unwrap_or(BackendType::DuckDB)
+    }
+
+    /// The backend type, or `None` if `type` isn't a supported backend.
+    pub fn known_backend_type(&self) -> Option<BackendType> {
         match self.target_type.to_lowercase().as_str() {
-            "duckdb" => BackendType::DuckDB,
-            "spark" => BackendType::Spark,
-            _ => BackendType::DuckDB, // Default to DuckDB for backward compatibility
+            "duckdb" => Some(BackendType::DuckDB),
+            "spark" => Some(BackendType::Spark),
+            "sqlite" => Some(BackendType::SQLite),
+            "snowflake" => Some(BackendType::Snowflake),
+            _ => None,
         }
     }
 }
 
+/// Fill in a `schema_template`'s `{user}` and `{target}`, leaving `{schema}`.
+fn fill_schema_template(template: &str, target: &str) -> Result<String> {
+    let mut filled = template.replace("{target}", target);
+    if filled.contains("{user}") {
+        let user = ["SMELT_USER", "USER", "USERNAME"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.trim().is_empty()))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "schema_template of target '{}' uses {{user}}, but SMELT_USER isn't set",
+                    target
+                )
+            })?;
+        // Keep the schema a plain identifier
+        let user: String = user
+            .trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        filled = filled.replace("{user}", &user);
+    }
+    let unfilled = filled.replace("{schema}", "");
+    if let Some(start) = unfilled.find('{') {
+        let placeholder = unfilled[start..].split_inclusive('}').next().unwrap_or("{");
+        anyhow::bail!(
+            "Unknown placeholder {} in schema_template of target '{}'; expected {{schema}}, {{user}} or {{target}}",
+            placeholder,
+            target
+        );
+    }
+    Ok(filled)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BackendType {
     DuckDB,
     Spark,
+    SQLite,
+    Snowflake,
+}
+
+impl BackendType {
+    /// The SQL dialect the backend speaks.
+    pub fn dialect(&self) -> SqlDialect {
+        match self {
+            BackendType::DuckDB => SqlDialect::DuckDB,
+            BackendType::Spark => SqlDialect::SparkSQL,
+            BackendType::SQLite => SqlDialect::SQLite,
+            BackendType::Snowflake => SqlDialect::Snowflake,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ModelConfig {
-    #[serde(default)]
+    #[serde(default, alias = "materialized")]
     pub materialization: Option<Materialization>,
     #[serde(default)]
     pub incremental: Option<IncrementalConfig>,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Overrides the project-level `retries`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Overrides the project-level `timeout_seconds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// Overrides the project-level `row_count_warn_percent`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_count_warn_percent: Option<f64>,
+    /// Columns the model must produce, checked after it is built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<ModelContract>,
+    /// Added to tags from the model's frontmatter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Schema to build the model in, instead of the target's schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Database (catalog) to build the model in, instead of the target's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    /// Where an external model's Parquet file is written (a path or URL)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Privileges granted on the model after it is built; replaces the
+    /// target's grantees for the same privilege
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub grants: BTreeMap<String, Vec<String>>,
+    /// Build the model by running a program instead of SQL; the model needs
+    /// no .sql file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<ProgramConfig>,
+}
+
+/// A command that builds a model, declared under a model's `program:` in smelt.yml.
+///
+/// The command runs from the project root after the models and sources in
+/// `depends_on`, with them exported as Arrow IPC files to `$SMELT_INPUT_DIR`.
+/// Whatever it writes to stdout is loaded into the model's table.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ProgramConfig {
+    /// Program and arguments, e.g. `[python, scripts/score.py]`
+    pub command: Vec<String>,
+    /// Models and sources (`schema.table`) the program reads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// What the program writes to stdout
+    #[serde(default)]
+    pub format: ProgramFormat,
+    /// Extra environment variables for the program
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgramFormat {
+    /// An Arrow IPC stream
+    #[default]
+    Arrow,
+    /// A Parquet file
+    Parquet,
+}
+
+/// A smelt project declared under `packages:` in smelt.yml.
+///
+/// Its models are named `<package>.<model>` and built in their own schema.
+/// A package is either a local `path` or a `git` repository that `smelt deps`
+/// clones into `packages/<name>`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PackageConfig {
+    /// The package's project directory, relative to this project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// URL of a git repository holding the package's project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Branch, tag, or commit of `git` to check out (default: the remote's HEAD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Schema its models are built in (default: the package name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Database (catalog) its models are built in, instead of the target's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+}
+
+/// Defaults for every model under a directory, set in smelt.yml `groups:`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct GroupConfig {
+    #[serde(
+        default,
+        alias = "materialized",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub materialization: Option<Materialization>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Expected output columns of a model.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ModelContract {
+    pub columns: Vec<ContractColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContractColumn {
+    pub name: String,
+    /// Expected type; if omitted only the column's presence is checked
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+}
+
+/// SQL statements executed before and after a model is materialized.
+///
+/// `{{ this }}` in a hook is replaced with the model's qualified name.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Hooks {
+    #[serde(default)]
+    pub pre: Vec<String>,
+    #[serde(default)]
+    pub post: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct IncrementalConfig {
     pub enabled: bool,
-    /// Column in source data to filter on (for WHERE injection)
+    /// Column in source data to filter on (for WHERE injection); defaults
+    /// to the one declared on the sources the model reads
+    #[serde(default)]
     pub event_time_column: String,
-    /// Column in output to delete by (for DELETE+INSERT)
+    /// Column in output to delete by (for DELETE+INSERT); defaults to the
+    /// one declared on the sources the model reads
+    #[serde(default)]
     pub partition_column: String,
+    /// How new rows are written into the existing table
+    #[serde(default)]
+    pub incremental_strategy: IncrementalStrategy,
+    /// Columns identifying a row, required by the `merge` strategy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unique_key: Vec<String>,
+    /// Size of each partition in `partition_column`
+    #[serde(default)]
+    pub partition_granularity: PartitionGranularity,
+}
+
+/// Time grain of an incremental model's partitions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionGranularity {
+    Hour,
+    #[default]
+    Day,
+    /// ISO weeks, starting on Monday
+    Week,
+    Month,
+}
+
+impl std::fmt::Display for PartitionGranularity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartitionGranularity::Hour => write!(f, "hour"),
+            PartitionGranularity::Day => write!(f, "day"),
+            PartitionGranularity::Week => write!(f, "week"),
+            PartitionGranularity::Month => write!(f, "month"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum IncrementalStrategy {
+    /// Delete the partitions being updated, then insert the new rows
+    #[default]
+    #[serde(rename = "delete+insert")]
+    DeleteInsert,
+    /// Upsert rows matched on `unique_key`
+    #[serde(rename = "merge")]
+    Merge,
+    /// Replace the partitions being updated in a single statement
+    #[serde(rename = "insert_overwrite")]
+    InsertOverwrite,
+}
+
+impl std::fmt::Display for IncrementalStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncrementalStrategy::DeleteInsert => write!(f, "delete+insert"),
+            IncrementalStrategy::Merge => write!(f, "merge"),
+            IncrementalStrategy::InsertOverwrite => write!(f, "insert_overwrite"),
+        }
+    }
 }
 
 impl Config {
     pub fn load(project_dir: &Path) -> Result<Self> {
+        Self::load_with_vars(project_dir, &Vars::new())
+    }
+
+    /// Load smelt.yml with `--vars` overrides.
+    ///
+    /// `env_var()` and `var()` in smelt.yml are rendered before parsing; there
+    /// `var()` only sees `cli_vars`, since the `vars:` block isn't parsed yet.
+    /// The returned config's `vars` are the project vars overlaid with `cli_vars`.
+    pub fn load_with_vars(project_dir: &Path, cli_vars: &Vars) -> Result<Self> {
         let config_path = project_dir.join("smelt.yml");
-        let content =
-            std::fs::read_to_string(&config_path).map_err(|e| CliError::ConfigLoadError {
-                path: config_path.clone(),
-                source: e.into(),
-            })?;
+        let load_error = |source: anyhow::Error| CliError::ConfigLoadError {
+            path: config_path.clone(),
+            source,
+        };
 
-        serde_yaml::from_str(&content).map_err(|e| {
-            CliError::ConfigLoadError {
-                path: config_path,
-                source: e.into(),
-            }
-            .into()
-        })
+        let content = std::fs::read_to_string(&config_path).map_err(|e| load_error(e.into()))?;
+        let content = render(&content, cli_vars).map_err(|e| load_error(e.into()))?;
+
+        let mut config: Config =
+            serde_yaml::from_str(&content).map_err(|e| load_error(e.into()))?;
+        config
+            .vars
+            .extend(cli_vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        Ok(config)
+    }
+
+    /// Name the schemas built in with target `name` after its
+    /// `schema_template`: the target's own schema, and every model's.
+    pub fn use_target(&mut self, name: &str) -> Result<()> {
+        let Some(target) = self.targets.get_mut(name) else {
+            return Ok(());
+        };
+        let Some(template) = &target.schema_template else {
+            return Ok(());
+        };
+        let template = fill_schema_template(template, name)?;
+        target.schema = template.replace("{schema}", &target.schema);
+        self.schema_template = Some(template);
+        Ok(())
+    }
+
+    /// `schema` named after the active target's `schema_template`.
+    pub fn schema_name(&self, schema: &str) -> String {
+        match &self.schema_template {
+            Some(template) => template.replace("{schema}", schema),
+            None => schema.to_string(),
+        }
     }
 
     /// Get materialization for a model
     ///
-    /// **Precedence**: SQL file metadata > smelt.yml model config > default_materialization
+    /// **Precedence**: SQL file metadata > smelt.yml model config > default_materialization.
+    /// Program models are always tables.
     pub fn get_materialization(&self, model_name: &str) -> Materialization {
+        if self.get_program(model_name).is_some() {
+            return Materialization::Table;
+        }
         self.models
             .get(model_name)
             .and_then(|m| m.materialization.clone())
             .unwrap_or_else(|| self.default_materialization.clone())
     }
 
-    /// Get materialization with SQL metadata precedence
+    /// Get materialization for a discovered model
     ///
-    /// **Precedence**: SQL file metadata > smelt.yml model config > default_materialization
-    pub fn get_materialization_with_metadata(
-        &self,
-        model_name: &str,
-        sql_metadata: Option<&crate::metadata::ModelMetadata>,
-    ) -> Materialization {
-        // Check SQL metadata first
-        if let Some(metadata) = sql_metadata {
-            if let Some(materialization) = &metadata.materialization {
-                return materialization.clone();
+    /// **Precedence**: SQL file metadata > smelt.yml model config > group > default_materialization.
+    /// Program models are always tables.
+    pub fn get_model_materialization(&self, model: &ModelFile) -> Materialization {
+        if self.get_program(&model.name).is_some() {
+            return Materialization::Table;
+        }
+        model
+            .metadata
+            .as_ref()
+            .and_then(|m| m.materialization.clone())
+            .or_else(|| {
+                self.models
+                    .get(&model.name)
+                    .and_then(|m| m.materialization.clone())
+            })
+            .or_else(|| {
+                self.group_for(&model.path)
+                    .and_then(|g| g.materialization.clone())
+            })
+            .unwrap_or_else(|| self.default_materialization.clone())
+    }
+
+    /// Get a model's tags: SQL file metadata, then smelt.yml model config, then group tags
+    pub fn get_model_tags(&self, model: &ModelFile) -> Vec<String> {
+        let metadata_tags = model.metadata.iter().flat_map(|m| m.tags.iter());
+        let config_tags = self
+            .models
+            .get(&model.name)
+            .into_iter()
+            .flat_map(|m| m.tags.iter());
+        let group_tags = self
+            .group_for(&model.path)
+            .into_iter()
+            .flat_map(|g| g.tags.iter());
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in metadata_tags.chain(config_tags).chain(group_tags) {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
             }
         }
+        tags
+    }
 
-        // Fall back to smelt.yml
-        self.get_materialization(model_name)
+    /// Get the schema a model is built in, if it overrides the target's schema
+    ///
+    /// **Precedence**: SQL file metadata > smelt.yml model config > group > package,
+    /// then named after the target's `schema_template`
+    pub fn get_model_schema(&self, model: &ModelFile) -> Option<String> {
+        let schema = model
+            .metadata
+            .as_ref()
+            .and_then(|m| m.schema.as_deref())
+            .or_else(|| {
+                self.models
+                    .get(&model.name)
+                    .and_then(|m| m.schema.as_deref())
+            })
+            .or_else(|| {
+                self.group_for(&model.path)
+                    .and_then(|g| g.schema.as_deref())
+            })
+            .or_else(|| {
+                let (package, _) = self.package_of(&model.name)?;
+                Some(self.packages[package].schema.as_deref().unwrap_or(package))
+            })?;
+        Some(self.schema_name(schema))
+    }
+
+    /// Get the database a model is built in, if it overrides the target's
+    ///
+    /// **Precedence**: SQL file metadata > smelt.yml model config > group > package
+    pub fn get_model_database<'a>(&'a self, model: &'a ModelFile) -> Option<&'a str> {
+        model
+            .metadata
+            .as_ref()
+            .and_then(|m| m.database.as_deref())
+            .or_else(|| {
+                self.models
+                    .get(&model.name)
+                    .and_then(|m| m.database.as_deref())
+            })
+            .or_else(|| {
+                self.group_for(&model.path)
+                    .and_then(|g| g.database.as_deref())
+            })
+            .or_else(|| {
+                let (package, _) = self.package_of(&model.name)?;
+                self.packages[package].database.as_deref()
+            })
+    }
+
+    /// Split a package model's name into its package and model name
+    pub fn package_of<'a>(&self, model_name: &'a str) -> Option<(&'a str, &'a str)> {
+        model_name
+            .split_once('.')
+            .filter(|(package, _)| self.packages.contains_key(*package))
+    }
+
+    /// The most specific group whose directory contains `path`
+    pub fn group_for(&self, path: &Path) -> Option<&GroupConfig> {
+        self.groups
+            .iter()
+            .filter(|(dir, _)| {
+                let dir = Path::new(dir.trim_end_matches('/'));
+                path.parent()
+                    .is_some_and(|parent| parent.ancestors().any(|a| a.ends_with(dir)))
+            })
+            .max_by_key(|(dir, _)| Path::new(dir).components().count())
+            .map(|(_, group)| group)
     }
 
     /// Get incremental config for a model if enabled
@@ -186,6 +667,155 @@ impl Config {
         // Fall back to smelt.yml
         self.get_incremental(model_name)
     }
+
+    /// Get a model's incremental config, filling an unset `event_time_column`
+    /// or `partition_column` from the sources it reads, when they agree on one
+    ///
+    /// **Precedence**: SQL file metadata > smelt.yml model config > sources.yml
+    pub fn get_model_incremental<'a>(
+        &'a self,
+        model: &'a ModelFile,
+        sources: Option<&SourceConfig>,
+    ) -> Option<Cow<'a, IncrementalConfig>> {
+        let incremental =
+            self.get_incremental_with_metadata(&model.name, model.metadata.as_deref())?;
+        let Some(sources) = sources.filter(|_| {
+            incremental.event_time_column.is_empty() || incremental.partition_column.is_empty()
+        }) else {
+            return Some(Cow::Borrowed(incremental));
+        };
+
+        let tables: Vec<&SourceTable> = model
+            .source_tables()
+            .iter()
+            .filter_map(|name| sources.get_table(name))
+            .collect();
+        let declared = |column: fn(&SourceTable) -> Option<&String>| {
+            let mut columns = tables.iter().filter_map(|table| column(table));
+            let first = columns.next()?;
+            columns.all(|c| c == first).then(|| first.clone())
+        };
+
+        let mut incremental = incremental.clone();
+        if incremental.event_time_column.is_empty() {
+            if let Some(column) = declared(|t| t.event_time_column.as_ref()) {
+                incremental.event_time_column = column;
+            }
+        }
+        if incremental.partition_column.is_empty() {
+            if let Some(column) = declared(|t| t.partition_column.as_ref()) {
+                incremental.partition_column = column;
+            }
+        }
+        Some(Cow::Owned(incremental))
+    }
+
+    /// Get the hooks to run around a model
+    ///
+    /// Project pre-hooks run before model pre-hooks; model post-hooks run
+    /// before project post-hooks.
+    pub fn get_hooks(&self, model_name: &str) -> Hooks {
+        let model_hooks = self.models.get(model_name).map(|m| &m.hooks);
+
+        let mut pre = self.hooks.pre.clone();
+        let mut post = Vec::new();
+        if let Some(hooks) = model_hooks {
+            pre.extend(hooks.pre.iter().cloned());
+            post.extend(hooks.post.iter().cloned());
+        }
+        post.extend(self.hooks.post.iter().cloned());
+
+        Hooks { pre, post }
+    }
+
+    /// Get where an external model's Parquet file is written
+    ///
+    /// **Precedence**: smelt.yml model `location` > `target/external/<model>.parquet`.
+    /// Relative paths are resolved against the project root; URLs such as
+    /// `s3://bucket/orders.parquet` are used as is.
+    pub fn get_external_location(&self, model_name: &str, project_root: &Path) -> String {
+        match self
+            .models
+            .get(model_name)
+            .and_then(|m| m.location.as_deref())
+        {
+            Some(location) if location.contains("://") => location.to_string(),
+            Some(location) => project_root.join(location).to_string_lossy().into_owned(),
+            None => project_root
+                .join("target")
+                .join("external")
+                .join(format!("{}.parquet", model_name))
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+
+    /// Get the program that builds a model, if it isn't built from SQL
+    pub fn get_program(&self, model_name: &str) -> Option<&ProgramConfig> {
+        self.models.get(model_name).and_then(|m| m.program.as_ref())
+    }
+
+    /// Program models, by name
+    pub fn programs(&self) -> impl Iterator<Item = (&str, &ProgramConfig)> {
+        self.models
+            .iter()
+            .filter_map(|(name, m)| Some((name.as_str(), m.program.as_ref()?)))
+    }
+
+    /// Get the contract a model's output columns must satisfy
+    pub fn get_contract(&self, model_name: &str) -> Option<&ModelContract> {
+        self.models
+            .get(model_name)
+            .and_then(|m| m.contract.as_ref())
+    }
+
+    /// Get the privileges to grant on a model, keyed by privilege
+    ///
+    /// **Precedence**: smelt.yml model `grants` > target `grants`, per privilege.
+    /// An empty grantee list revokes the privilege from everyone.
+    pub fn get_grants(&self, model_name: &str, target_name: &str) -> BTreeMap<String, Vec<String>> {
+        let mut grants = self
+            .targets
+            .get(target_name)
+            .map(|target| target.grants.clone())
+            .unwrap_or_default();
+        if let Some(model) = self.models.get(model_name) {
+            grants.extend(model.grants.clone());
+        }
+        grants
+    }
+
+    /// Get the number of retries for a model after transient errors
+    ///
+    /// **Precedence**: smelt.yml model config > project `retries`
+    pub fn get_retries(&self, model_name: &str) -> u32 {
+        self.models
+            .get(model_name)
+            .and_then(|m| m.retries)
+            .unwrap_or(self.retries)
+    }
+
+    /// Get how long a model may run before its statements are cancelled
+    ///
+    /// **Precedence**: smelt.yml model config > project `timeout_seconds`
+    pub fn get_timeout(&self, model_name: &str) -> Option<std::time::Duration> {
+        self.models
+            .get(model_name)
+            .and_then(|m| m.timeout_seconds)
+            .or(self.timeout_seconds)
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// Get how far (in percent) a model's row count may move from the
+    /// previous run's before smelt warns
+    ///
+    /// **Precedence**: smelt.yml model config > project `row_count_warn_percent`
+    pub fn get_row_count_warn_percent(&self, model_name: &str) -> Option<f64> {
+        self.models
+            .get(model_name)
+            .and_then(|m| m.row_count_warn_percent)
+            .or(self.row_count_warn_percent)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -197,6 +827,12 @@ pub struct SourceConfig {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SourceSchema {
     pub tables: HashMap<String, SourceTable>,
+    /// Default `loaded_at_field` for tables in this schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loaded_at_field: Option<String>,
+    /// Default freshness thresholds for tables in this schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freshness: Option<FreshnessConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -204,6 +840,54 @@ pub struct SourceTable {
     #[serde(default)]
     pub description: String,
     pub columns: Vec<SourceColumn>,
+    /// Timestamp column recording when each row was loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loaded_at_field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freshness: Option<FreshnessConfig>,
+    /// Event time column of incremental models reading this table, unless
+    /// they set their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time_column: Option<String>,
+    /// Partition column of incremental models reading this table, unless
+    /// they set their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_column: Option<String>,
+}
+
+/// How stale a source may get before `smelt source freshness` warns or fails.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FreshnessConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_after: Option<FreshnessThreshold>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_after: Option<FreshnessThreshold>,
+}
+
+/// A freshness threshold such as `{count: 12, period: hour}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FreshnessThreshold {
+    pub count: u64,
+    pub period: FreshnessPeriod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FreshnessPeriod {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl FreshnessThreshold {
+    pub fn duration(&self) -> std::time::Duration {
+        let secs_per_period = match self.period {
+            FreshnessPeriod::Minute => 60,
+            FreshnessPeriod::Hour => 60 * 60,
+            FreshnessPeriod::Day => 24 * 60 * 60,
+        };
+        std::time::Duration::from_secs(self.count.saturating_mul(secs_per_period))
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -216,6 +900,22 @@ pub struct SourceColumn {
 }
 
 impl SourceConfig {
+    /// Add the schemas and tables of `other`; tables already declared here win
+    pub fn merge(&mut self, other: SourceConfig) {
+        for (schema_name, schema) in other.sources {
+            match self.sources.get_mut(&schema_name) {
+                Some(existing) => {
+                    for (table_name, table) in schema.tables {
+                        existing.tables.entry(table_name).or_insert(table);
+                    }
+                }
+                None => {
+                    self.sources.insert(schema_name, schema);
+                }
+            }
+        }
+    }
+
     pub fn load(project_dir: &Path) -> Result<Self> {
         let sources_path = project_dir.join("sources.yml");
         let content =
@@ -233,6 +933,50 @@ impl SourceConfig {
         })
     }
 
+    /// Get the loaded-at column and thresholds for a source table, if it has both
+    ///
+    /// **Precedence**: table settings > schema settings
+    pub fn get_freshness(&self, schema: &str, table: &str) -> Option<(&str, &FreshnessConfig)> {
+        let source_schema = self.sources.get(schema)?;
+        let source_table = source_schema.tables.get(table)?;
+
+        let loaded_at_field = source_table
+            .loaded_at_field
+            .as_ref()
+            .or(source_schema.loaded_at_field.as_ref())?;
+        let freshness = source_table
+            .freshness
+            .as_ref()
+            .or(source_schema.freshness.as_ref())?;
+
+        Some((loaded_at_field, freshness))
+    }
+
+    /// A source table by qualified name; like refs, an unqualified name
+    /// matches a table in any source schema
+    pub fn get_table(&self, name: &str) -> Option<&SourceTable> {
+        match name.split_once('.') {
+            Some((schema, table)) => self.sources.get(schema)?.tables.get(table),
+            None => self
+                .sources
+                .values()
+                .find_map(|schema| schema.tables.get(name)),
+        }
+    }
+
+    /// The sources `model` reads that declare an event time column, named as
+    /// in its SQL
+    pub fn event_time_sources(&self, model: &ModelFile) -> Vec<String> {
+        model
+            .source_tables()
+            .into_iter()
+            .filter(|name| {
+                self.get_table(name)
+                    .is_some_and(|table| table.event_time_column.is_some())
+            })
+            .collect()
+    }
+
     /// Get full source name (schema.table format)
     pub fn get_source_names(&self) -> Vec<String> {
         let mut names = Vec::new();
@@ -245,6 +989,73 @@ impl SourceConfig {
     }
 }
 
+/// Dashboards, reports, and other consumers of models outside the warehouse,
+/// declared in exposures.yml.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ExposureConfig {
+    pub version: u32,
+    #[serde(default)]
+    pub exposures: BTreeMap<String, Exposure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Exposure {
+    #[serde(rename = "type")]
+    pub exposure_type: ExposureType,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Models and sources (`schema.table`) the exposure reads
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExposureType {
+    Dashboard,
+    Report,
+    Notebook,
+    /// A machine learning model or job
+    Ml,
+    Application,
+    Analysis,
+}
+
+impl std::fmt::Display for ExposureType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExposureType::Dashboard => write!(f, "dashboard"),
+            ExposureType::Report => write!(f, "report"),
+            ExposureType::Notebook => write!(f, "notebook"),
+            ExposureType::Ml => write!(f, "ml"),
+            ExposureType::Application => write!(f, "application"),
+            ExposureType::Analysis => write!(f, "analysis"),
+        }
+    }
+}
+
+impl ExposureConfig {
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let exposures_path = project_dir.join("exposures.yml");
+        let content =
+            std::fs::read_to_string(&exposures_path).map_err(|e| CliError::ConfigLoadError {
+                path: exposures_path.clone(),
+                source: e.into(),
+            })?;
+
+        serde_yaml::from_str(&content).map_err(|e| {
+            CliError::ConfigLoadError {
+                path: exposures_path,
+                source: e.into(),
+            }
+            .into()
+        })
+    }
+}
+
 /// Find the smelt project root by looking for smelt.yml or models/ directory
 pub fn find_project_root(start_dir: &Path) -> Result<PathBuf> {
     let mut current = start_dir.to_path_buf();
@@ -272,6 +1083,19 @@ pub fn find_project_root(start_dir: &Path) -> Result<PathBuf> {
     Err(CliError::ProjectRootNotFound.into())
 }
 
+#[cfg(test)]
+impl Config {
+    /// A project called `name` with a single DuckDB `dev` target in schema
+    /// `main` and defaults for everything else, for tests.
+    pub(crate) fn for_test(name: &str) -> Self {
+        serde_yaml::from_str(&format!(
+            "name: {}\nversion: 1\ntargets:\n  dev:\n    type: duckdb\n    schema: main\n",
+            name
+        ))
+        .unwrap()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -291,6 +1115,8 @@ models:
     materialization: table
   model2:
     materialization: view
+  model3:
+    materialized: ephemeral
 "#;
 
         let config: Config = serde_yaml::from_str(yaml).unwrap();
@@ -303,6 +1129,88 @@ models:
             config.models.get("model2").unwrap().materialization,
             Some(Materialization::View)
         );
+        assert_eq!(
+            config.models.get("model3").unwrap().materialization,
+            Some(Materialization::Ephemeral)
+        );
+    }
+
+    #[test]
+    fn test_external_location() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    database: test.duckdb
+    schema: main
+models:
+  events:
+    materialized: external
+    location: lake/events.parquet
+  remote:
+    materialized: external
+    location: s3://bucket/remote.parquet
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let root = Path::new("/project");
+        assert_eq!(
+            config.models["events"].materialization,
+            Some(Materialization::External)
+        );
+        assert_eq!(
+            config.get_external_location("events", root),
+            "/project/lake/events.parquet"
+        );
+        assert_eq!(
+            config.get_external_location("remote", root),
+            "s3://bucket/remote.parquet"
+        );
+        assert_eq!(
+            config.get_external_location("other", root),
+            "/project/target/external/other.parquet"
+        );
+    }
+
+    #[test]
+    fn test_grants() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+  prod:
+    type: snowflake
+    schema: analytics
+    grants:
+      select: [analyst, reporter]
+models:
+  orders:
+    grants:
+      select: [finance]
+      insert: [loader]
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.get_grants("users", "dev").is_empty());
+        assert_eq!(
+            config.get_grants("users", "prod"),
+            BTreeMap::from([(
+                "select".to_string(),
+                vec!["analyst".to_string(), "reporter".to_string()]
+            )])
+        );
+        assert_eq!(
+            config.get_grants("orders", "prod"),
+            BTreeMap::from([
+                ("insert".to_string(), vec!["loader".to_string()]),
+                ("select".to_string(), vec!["finance".to_string()]),
+            ])
+        );
     }
 
     #[test]
@@ -320,4 +1228,502 @@ targets:
         let config: Config = serde_yaml::from_str(yaml).unwrap();
         assert_eq!(config.default_materialization, Materialization::View);
     }
+
+    #[test]
+    fn test_hooks_ordering() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+hooks:
+  pre: ["SET threads = 4"]
+  post: ["ANALYZE"]
+models:
+  model1:
+    hooks:
+      pre: ["DELETE FROM staging.model1_tmp"]
+      post: ["GRANT SELECT ON {{ this }} TO analyst"]
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+
+        let hooks = config.get_hooks("model1");
+        assert_eq!(
+            hooks.pre,
+            vec!["SET threads = 4", "DELETE FROM staging.model1_tmp"]
+        );
+        assert_eq!(
+            hooks.post,
+            vec!["GRANT SELECT ON {{ this }} TO analyst", "ANALYZE"]
+        );
+
+        let hooks = config.get_hooks("other");
+        assert_eq!(hooks.pre, vec!["SET threads = 4"]);
+        assert_eq!(hooks.post, vec!["ANALYZE"]);
+    }
+
+    #[test]
+    fn test_load_with_vars() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("smelt.yml"),
+            r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: "{{ var('schema', 'main') }}"
+hooks:
+  post: ["ANALYZE {{ this }}"]
+vars:
+  region: emea
+  days: 7
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load(temp_dir.path()).unwrap();
+        assert_eq!(config.targets["dev"].schema, "main");
+        assert_eq!(config.hooks.post, vec!["ANALYZE {{ this }}"]);
+
+        let cli_vars = crate::template::parse_vars("{schema: analytics, region: apac}").unwrap();
+        let config = Config::load_with_vars(temp_dir.path(), &cli_vars).unwrap();
+        assert_eq!(config.targets["dev"].schema, "analytics");
+        assert_eq!(config.vars["region"], "apac");
+        assert_eq!(config.vars["days"], 7);
+    }
+
+    #[test]
+    fn test_source_freshness_precedence() {
+        let yaml = r#"
+version: 1
+sources:
+  raw:
+    loaded_at_field: _loaded_at
+    freshness:
+      warn_after: {count: 12, period: hour}
+    tables:
+      events:
+        columns: []
+        freshness:
+          warn_after: {count: 30, period: minute}
+          error_after: {count: 1, period: day}
+      users:
+        columns: []
+        loaded_at_field: updated_at
+  lookup:
+    tables:
+      countries:
+        columns: []
+"#;
+
+        let sources: SourceConfig = serde_yaml::from_str(yaml).unwrap();
+
+        let (field, freshness) = sources.get_freshness("raw", "events").unwrap();
+        assert_eq!(field, "_loaded_at");
+        assert_eq!(
+            freshness.warn_after.unwrap().duration(),
+            std::time::Duration::from_secs(30 * 60)
+        );
+        assert_eq!(
+            freshness.error_after.unwrap().duration(),
+            std::time::Duration::from_secs(24 * 60 * 60)
+        );
+
+        let (field, freshness) = sources.get_freshness("raw", "users").unwrap();
+        assert_eq!(field, "updated_at");
+        assert_eq!(
+            freshness.warn_after.unwrap().duration(),
+            std::time::Duration::from_secs(12 * 60 * 60)
+        );
+        assert!(freshness.error_after.is_none());
+
+        assert!(sources.get_freshness("lookup", "countries").is_none());
+    }
+
+    #[test]
+    fn test_retries_precedence() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+retries: 2
+models:
+  flaky:
+    retries: 5
+  strict:
+    retries: 0
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.get_retries("flaky"), 5);
+        assert_eq!(config.get_retries("strict"), 0);
+        assert_eq!(config.get_retries("other"), 2);
+    }
+
+    #[test]
+    fn test_timeout_precedence() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+timeout_seconds: 600
+models:
+  slow:
+    timeout_seconds: 3600
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            config.get_timeout("slow"),
+            Some(std::time::Duration::from_secs(3600))
+        );
+        assert_eq!(
+            config.get_timeout("other"),
+            Some(std::time::Duration::from_secs(600))
+        );
+
+        let config = Config {
+            timeout_seconds: None,
+            ..config
+        };
+        assert_eq!(config.get_timeout("other"), None);
+    }
+
+    #[test]
+    fn test_row_count_warn_percent_precedence() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+row_count_warn_percent: 25
+models:
+  volatile:
+    row_count_warn_percent: 80.5
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.get_row_count_warn_percent("volatile"), Some(80.5));
+        assert_eq!(config.get_row_count_warn_percent("other"), Some(25.0));
+
+        let config = Config {
+            row_count_warn_percent: None,
+            ..config
+        };
+        assert_eq!(config.get_row_count_warn_percent("other"), None);
+    }
+
+    #[test]
+    fn test_incremental_strategy() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+models:
+  events:
+    incremental:
+      enabled: true
+      event_time_column: event_ts
+      partition_column: event_date
+  users:
+    incremental:
+      enabled: true
+      event_time_column: updated_at
+      partition_column: updated_date
+      incremental_strategy: merge
+      unique_key: [user_id]
+      partition_granularity: hour
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let events = config.get_incremental("events").unwrap();
+        assert_eq!(
+            events.incremental_strategy,
+            IncrementalStrategy::DeleteInsert
+        );
+        assert_eq!(events.partition_granularity, PartitionGranularity::Day);
+
+        let users = config.get_incremental("users").unwrap();
+        assert_eq!(users.incremental_strategy, IncrementalStrategy::Merge);
+        assert_eq!(users.unique_key, vec!["user_id".to_string()]);
+        assert_eq!(users.partition_granularity, PartitionGranularity::Hour);
+
+        let invalid = yaml.replace("merge", "upsert");
+        assert!(serde_yaml::from_str::<Config>(&invalid).is_err());
+    }
+
+    fn model(name: &str, dir: &str, metadata: Option<&str>) -> ModelFile {
+        ModelFile {
+            name: name.to_string(),
+            path: Path::new("/project")
+                .join(dir)
+                .join(format!("{}.sql", name)),
+            content: String::new(),
+            refs: Vec::new(),
+            parse_errors: Vec::new(),
+            metadata: metadata.map(|yaml| Box::new(serde_yaml::from_str(yaml).unwrap())),
+        }
+    }
+
+    #[test]
+    fn test_group_defaults() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+default_materialization: view
+models:
+  stg_orders:
+    materialized: table
+    tags: [orders]
+    schema: raw_staging
+groups:
+  models/staging:
+    schema: staging
+    materialized: ephemeral
+    tags: [staging]
+  models/staging/legacy:
+    materialization: view
+    database: archive
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let stg_users = model("stg_users", "models/staging", None);
+        assert_eq!(
+            config.get_model_materialization(&stg_users),
+            Materialization::Ephemeral
+        );
+        assert_eq!(
+            config.get_model_schema(&stg_users).as_deref(),
+            Some("staging")
+        );
+        assert_eq!(config.get_model_tags(&stg_users), vec!["staging"]);
+
+        // Model config overrides the group, SQL metadata overrides both
+        let stg_orders = model("stg_orders", "models/staging", Some("tags: [core, orders]"));
+        assert_eq!(
+            config.get_model_materialization(&stg_orders),
+            Materialization::Table
+        );
+        assert_eq!(
+            config.get_model_schema(&stg_orders).as_deref(),
+            Some("raw_staging")
+        );
+        assert_eq!(
+            config.get_model_tags(&stg_orders),
+            vec!["core", "orders", "staging"]
+        );
+        let stg_orders = model("stg_orders", "models/staging", Some("schema: orders"));
+        assert_eq!(
+            config.get_model_schema(&stg_orders).as_deref(),
+            Some("orders")
+        );
+        let stg_orders = model("stg_orders", "models/staging", Some("materialized: view"));
+        assert_eq!(
+            config.get_model_materialization(&stg_orders),
+            Materialization::View
+        );
+
+        // The most specific group wins
+        let old = model("old", "models/staging/legacy", None);
+        assert_eq!(
+            config.get_model_materialization(&old),
+            Materialization::View
+        );
+        assert!(config.get_model_tags(&old).is_empty());
+        assert_eq!(config.get_model_database(&old), Some("archive"));
+        assert_eq!(config.get_model_database(&stg_users), None);
+
+        // Sibling directories with a shared prefix don't match
+        let other = model("other", "models/staging_old", None);
+        assert!(config.group_for(&other.path).is_none());
+        assert_eq!(config.get_model_schema(&other).as_deref(), None);
+    }
+
+    #[test]
+    fn test_package_defaults() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+models:
+  billing.invoices:
+    schema: finance
+packages:
+  shared:
+    path: ../shared
+  billing:
+    path: vendor/billing
+    schema: billing_models
+    database: lake
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            config.package_of("shared.orders"),
+            Some(("shared", "orders"))
+        );
+        assert_eq!(config.package_of("raw.orders"), None);
+        assert_eq!(config.package_of("orders"), None);
+
+        // Package models are built in a schema named after the package by default
+        let orders = model("shared.orders", "../shared/models", None);
+        assert_eq!(config.get_model_schema(&orders).as_deref(), Some("shared"));
+        assert_eq!(config.get_model_database(&orders), None);
+
+        let invoices = model("billing.invoices", "vendor/billing/models", None);
+        assert_eq!(
+            config.get_model_schema(&invoices).as_deref(),
+            Some("finance")
+        );
+        assert_eq!(config.get_model_database(&invoices), Some("lake"));
+        let payments = model("billing.payments", "vendor/billing/models", None);
+        assert_eq!(
+            config.get_model_schema(&payments).as_deref(),
+            Some("billing_models")
+        );
+    }
+
+    #[test]
+    fn test_incremental_columns_from_sources() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+models:
+  events_by_day:
+    incremental:
+      enabled: true
+  orders_by_day:
+    incremental:
+      enabled: true
+      event_time_column: updated_at
+  joined:
+    incremental:
+      enabled: true
+"#,
+        )
+        .unwrap();
+        let sources: SourceConfig = serde_yaml::from_str(
+            r#"
+version: 1
+sources:
+  raw:
+    tables:
+      events:
+        columns: []
+        event_time_column: event_time
+        partition_column: event_date
+      orders:
+        columns: []
+        event_time_column: created_at
+"#,
+        )
+        .unwrap();
+        let with_sql = |name: &str, sql: &str| ModelFile {
+            content: sql.to_string(),
+            ..model(name, "models", None)
+        };
+
+        let events = with_sql("events_by_day", "SELECT * FROM smelt.source('raw.events')");
+        let incremental = config
+            .get_model_incremental(&events, Some(&sources))
+            .unwrap();
+        assert_eq!(incremental.event_time_column, "event_time");
+        assert_eq!(incremental.partition_column, "event_date");
+        let incremental = config.get_model_incremental(&events, None).unwrap();
+        assert_eq!(incremental.event_time_column, "");
+
+        // The model's own setting wins; unqualified sources match any schema
+        let orders = with_sql("orders_by_day", "SELECT * FROM smelt.source('orders')");
+        let incremental = config
+            .get_model_incremental(&orders, Some(&sources))
+            .unwrap();
+        assert_eq!(incremental.event_time_column, "updated_at");
+        assert_eq!(incremental.partition_column, "");
+
+        // Sources that disagree leave it unset
+        let joined = with_sql(
+            "joined",
+            "SELECT * FROM smelt.source('raw.events') e JOIN smelt.source('raw.orders') o ON e.id = o.id",
+        );
+        let incremental = config
+            .get_model_incremental(&joined, Some(&sources))
+            .unwrap();
+        assert_eq!(incremental.event_time_column, "");
+        assert_eq!(incremental.partition_column, "event_date");
+    }
+
+    #[test]
+    fn test_schema_template() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+    schema_template: "{target}_{user}_{schema}"
+  prod:
+    type: duckdb
+    schema: main
+  typo:
+    type: duckdb
+    schema: main
+    schema_template: "dev_{usr}_{schema}"
+models:
+  stg_users:
+    schema: staging
+"#;
+        std::env::set_var("SMELT_USER", "Jane.Doe");
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let stg_users = model("stg_users", "models", None);
+        let users = model("users", "models", None);
+
+        let mut dev = config.clone();
+        dev.use_target("dev").unwrap();
+        assert_eq!(dev.targets["dev"].schema, "dev_jane_doe_main");
+        assert_eq!(
+            dev.get_model_schema(&stg_users).as_deref(),
+            Some("dev_jane_doe_staging")
+        );
+        assert_eq!(dev.get_model_schema(&users), None);
+
+        let mut prod = config.clone();
+        prod.use_target("prod").unwrap();
+        assert_eq!(prod.targets["prod"].schema, "main");
+        assert_eq!(
+            prod.get_model_schema(&stg_users).as_deref(),
+            Some("staging")
+        );
+
+        let err = config.clone().use_target("typo").unwrap_err();
+        assert!(err.to_string().contains("{usr}"), "{}", err);
+    }
 }
@@ -0,0 +1,241 @@
+//! Model contracts: declared output columns checked against what was built.
+//!
+//! A contract in smelt.yml lists the columns (and optionally types) a model
+//! must produce. After the model is materialized its columns are read back
+//! from the backend and compared. With `--dry-run`, column names inferred from
+//! the model's SQL are checked instead, without touching the database.
+
+use crate::config::ModelContract;
+use crate::discovery::ModelFile;
+use smelt_backend::ColumnInfo;
+use smelt_db::{ColumnSource, Database, Inputs, Schema};
+use std::fmt;
+use std::sync::Arc;
+
+/// One difference between a contract and a model's actual columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractViolation {
+    /// Declared in the contract but not produced by the model
+    Missing {
+        column: String,
+        expected_type: Option<String>,
+    },
+    /// Produced by the model but not declared in the contract
+    Unexpected {
+        column: String,
+        data_type: Option<String>,
+    },
+    TypeMismatch {
+        column: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for ContractViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let with_type = |column: &str, data_type: &Option<String>| match data_type {
+            Some(data_type) => format!("{} {}", column, data_type),
+            None => column.to_string(),
+        };
+
+        match self {
+            ContractViolation::Missing {
+                column,
+                expected_type,
+            } => write!(f, "- {} (missing)", with_type(column, expected_type)),
+            ContractViolation::Unexpected { column, data_type } => {
+                write!(f, "+ {} (not in contract)", with_type(column, data_type))
+            }
+            ContractViolation::TypeMismatch {
+                column,
+                expected,
+                actual,
+            } => write!(f, "~ {}: expected {}, got {}", column, expected, actual),
+        }
+    }
+}
+
+/// Compare a model's columns, as reported by the backend, against its contract.
+pub fn check_contract(contract: &ModelContract, columns: &[ColumnInfo]) -> Vec<ContractViolation> {
+    let actual: Vec<(&str, Option<&str>)> = columns
+        .iter()
+        .map(|c| (c.name.as_str(), Some(c.data_type.as_str())))
+        .collect();
+    diff(contract, &actual)
+}
+
+/// Compare column names (without types) against a contract.
+pub fn check_contract_names(
+    contract: &ModelContract,
+    columns: &[String],
+) -> Vec<ContractViolation> {
+    let actual: Vec<(&str, Option<&str>)> = columns.iter().map(|c| (c.as_str(), None)).collect();
+    diff(contract, &actual)
+}
+
+/// Output column names inferred from a model's SQL, or `None` if they can't be
+/// determined statically (e.g. `SELECT *`).
+pub fn inferred_columns(model: &ModelFile) -> Option<Vec<String>> {
+    let mut db = Database::default();
+    db.set_sources_yaml(Arc::new(String::new()));
+    db.set_file_text(model.path.clone(), Arc::new(model.content.clone()));
+    db.set_all_files(Arc::new(vec![model.path.clone()]));
+
+    let schema = db.model_schema(model.path.clone());
+    let inferable = !schema.columns.is_empty()
+        && schema.columns.iter().all(|c| {
+            !matches!(
+                c.source,
+                ColumnSource::Wildcard { .. } | ColumnSource::Unknown
+            )
+        });
+
+    inferable.then(|| schema.columns.iter().map(|c| c.name.clone()).collect())
+}
+
+fn diff(contract: &ModelContract, actual: &[(&str, Option<&str>)]) -> Vec<ContractViolation> {
+    let find = |name: &str| actual.iter().find(|(c, _)| c.eq_ignore_ascii_case(name));
+    let mut violations = Vec::new();
+
+    for expected in &contract.columns {
+        match find(&expected.name) {
+            None => violations.push(ContractViolation::Missing {
+                column: expected.name.clone(),
+                expected_type: expected.data_type.clone(),
+            }),
+            Some((_, Some(actual_type))) => {
+                if let Some(expected_type) = &expected.data_type {
+                    if normalize_type(expected_type) != normalize_type(actual_type) {
+                        violations.push(ContractViolation::TypeMismatch {
+                            column: expected.name.clone(),
+                            expected: expected_type.clone(),
+                            actual: actual_type.to_string(),
+                        });
+                    }
+                }
+            }
+            Some((_, None)) => {}
+        }
+    }
+
+    for (column, data_type) in actual {
+        if !contract
+            .columns
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case(column))
+        {
+            violations.push(ContractViolation::Unexpected {
+                column: column.to_string(),
+                data_type: data_type.map(str::to_string),
+            });
+        }
+    }
+
+    violations
+}
+
+/// Map type names that differ between dialects onto one spelling.
+fn normalize_type(data_type: &str) -> String {
+    let upper = data_type.trim().to_ascii_uppercase();
+    let normalized = match upper.as_str() {
+        "INT" | "INT4" | "INTEGER" => "INTEGER",
+        "INT8" | "BIGINT" | "LONG" => "BIGINT",
+        "INT2" | "SMALLINT" | "SHORT" => "SMALLINT",
+        "STRING" | "TEXT" | "VARCHAR" | "CHARACTER VARYING" => "VARCHAR",
+        "FLOAT8" | "DOUBLE" | "DOUBLE PRECISION" => "DOUBLE",
+        "FLOAT4" | "FLOAT" | "REAL" => "FLOAT",
+        "BOOL" | "BOOLEAN" => "BOOLEAN",
+        other => other,
+    };
+    normalized.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract(yaml: &str) -> ModelContract {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn column(name: &str, data_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_check_contract() {
+        let contract = contract(
+            r#"
+columns:
+  - {name: id, type: int8}
+  - {name: email, type: text}
+  - {name: signup_date, type: date}
+  - {name: plan}
+"#,
+        );
+        let columns = vec![
+            column("id", "BIGINT"),
+            column("EMAIL", "VARCHAR"),
+            column("signup_date", "TIMESTAMP"),
+            column("referrer", "VARCHAR"),
+        ];
+
+        let violations = check_contract(&contract, &columns);
+        assert_eq!(
+            violations,
+            vec![
+                ContractViolation::TypeMismatch {
+                    column: "signup_date".to_string(),
+                    expected: "date".to_string(),
+                    actual: "TIMESTAMP".to_string(),
+                },
+                ContractViolation::Missing {
+                    column: "plan".to_string(),
+                    expected_type: None,
+                },
+                ContractViolation::Unexpected {
+                    column: "referrer".to_string(),
+                    data_type: Some("VARCHAR".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "~ signup_date: expected date, got TIMESTAMP",
+                "- plan (missing)",
+                "+ referrer VARCHAR (not in contract)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inferred_columns() {
+        let model = |sql: &str| ModelFile {
+            name: "users".to_string(),
+            path: "models/users.sql".into(),
+            content: sql.to_string(),
+            refs: Vec::new(),
+            parse_errors: Vec::new(),
+            metadata: None,
+        };
+
+        let columns =
+            inferred_columns(&model("SELECT id, lower(email) AS email FROM raw.users")).unwrap();
+        assert_eq!(columns, vec!["id", "email"]);
+        assert!(check_contract_names(
+            &contract("columns: [{name: id, type: bigint}, {name: email}]"),
+            &columns
+        )
+        .is_empty());
+
+        assert_eq!(inferred_columns(&model("SELECT * FROM raw.users")), None);
+    }
+}
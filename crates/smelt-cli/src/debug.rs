@@ -0,0 +1,325 @@
+//! Connection diagnostics for `smelt debug`.
+//!
+//! The target from smelt.yml is checked for missing fields before connecting;
+//! once connected, a trivial query is round-tripped and a probe table is
+//! created and dropped in the target schema. Failures carry a hint for the
+//! common misconfiguration behind them.
+
+use crate::config::{BackendType, Target};
+use smelt_backend::{Backend, BackendCapabilities, RelationName};
+use std::path::Path;
+use std::time::Instant;
+
+/// Table created (and dropped) to check that the target schema is writable.
+pub const PROBE_TABLE: &str = "smelt_debug_probe";
+
+/// The outcome of one diagnostic check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What was found, or the error
+    pub detail: String,
+    /// How to fix a failed check
+    pub hint: Option<&'static str>,
+}
+
+impl Check {
+    pub fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    pub fn fail(name: &'static str, backend_type: BackendType, error: impl ToString) -> Self {
+        let detail = error.to_string();
+        Self {
+            name,
+            passed: false,
+            hint: hint(backend_type, &detail),
+            detail,
+        }
+    }
+}
+
+/// Problems with a target's fields that would stop smelt from connecting.
+pub fn target_problems(target: &Target, project_dir: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    let Some(backend_type) = target.known_backend_type() else {
+        problems.push(format!(
+            "Unknown target type '{}'; expected duckdb, sqlite, snowflake, or spark",
+            target.target_type
+        ));
+        return problems;
+    };
+
+    if target.schema.trim().is_empty() {
+        problems.push("'schema' is empty".to_string());
+    }
+
+    let required: &[(&str, &Option<String>)] = match backend_type {
+        BackendType::DuckDB | BackendType::SQLite => &[("database", &target.database)],
+        BackendType::Spark => &[("connect_url", &target.connect_url)],
+        BackendType::Snowflake => &[
+            ("account", &target.account),
+            ("user", &target.user),
+            ("database", &target.database),
+            ("warehouse", &target.warehouse),
+            ("private_key_path", &target.private_key_path),
+        ],
+    };
+    for (field, value) in required {
+        if value.is_none() {
+            problems.push(format!("Missing required field '{}'", field));
+        }
+    }
+
+    if let Some(format) = &target.table_format {
+        if backend_type != BackendType::Spark {
+            problems.push("'table_format' only applies to Spark targets".to_string());
+        } else if !["delta", "iceberg", "parquet"].contains(&format.to_lowercase().as_str()) {
+            problems.push(format!(
+                "Unknown table_format '{}'; expected delta, iceberg, or parquet",
+                format
+            ));
+        }
+    }
+
+    if let Some(key_path) = &target.private_key_path {
+        let key_path = project_dir.join(key_path);
+        if backend_type == BackendType::Snowflake && !key_path.is_file() {
+            problems.push(format!(
+                "Private key {} doesn't exist (private_key_path is relative to the project)",
+                key_path.display()
+            ));
+        }
+    }
+
+    problems
+}
+
+/// A suggested fix for a connection or query error, if it's a familiar one.
+pub fn hint(backend_type: BackendType, error: &str) -> Option<&'static str> {
+    let error = error.to_lowercase();
+    let mentions = |needles: &[&str]| needles.iter().any(|n| error.contains(n));
+
+    if mentions(&["rebuild with --features"]) {
+        Some("This smelt binary was built without the target's backend; rebuild it with the feature named above")
+    } else if mentions(&["another run is in progress"]) {
+        Some("Another smelt process is using the database; wait for it to finish, or pass --wait to queue behind it")
+    } else if mentions(&["could not set lock", "conflicting lock"]) {
+        Some("Another process has the DuckDB file open; close it (or wait for the other smelt run) and retry")
+    } else if mentions(&["readonly", "read-only"]) {
+        Some("The database file is read-only; check its permissions or point --database at a writable copy")
+    } else if mentions(&["jwt", "390144", "authentication", "401 unauthorized"]) {
+        Some("Key-pair authentication failed; check 'account' and 'user', and that the user's RSA_PUBLIC_KEY matches private_key_path")
+    } else if mentions(&["private key", "pkcs"]) {
+        Some("private_key_path must be an unencrypted PKCS#8 PEM key (openssl pkcs8 -topk8 -nocrypt)")
+    } else if mentions(&["warehouse"]) {
+        Some("Check that 'warehouse' exists and that the role has USAGE on it")
+    } else if mentions(&[
+        "permission denied",
+        "insufficient privileges",
+        "not authorized",
+        "access denied",
+    ]) {
+        Some(match backend_type {
+            BackendType::Snowflake => "The role can't create objects here; grant it USAGE on the database and CREATE TABLE/VIEW on the schema, or set 'role'",
+            _ => "The user can't create objects here; grant it CREATE on the schema or pick a schema it owns",
+        })
+    } else if mentions(&[
+        "connection refused",
+        "dns error",
+        "failed to lookup",
+        "timed out",
+        "error sending request",
+    ]) {
+        Some(match backend_type {
+            BackendType::Spark => "Can't reach the Spark Connect server; check 'connect_url' and that the server is running",
+            _ => "Can't reach the server; check 'account' and your network or proxy settings",
+        })
+    } else if mentions(&["does not exist", "not found", "unable to open"]) {
+        Some(match backend_type {
+            BackendType::DuckDB | BackendType::SQLite => "Check that the directory for 'database' exists (paths are relative to the project)",
+            _ => "The database or schema doesn't exist, or the role can't see it; check 'database', 'schema', and 'role'",
+        })
+    } else {
+        None
+    }
+}
+
+/// Each capability by name, in declaration order.
+pub fn capability_flags(capabilities: &BackendCapabilities) -> Vec<(&'static str, bool)> {
+    let BackendCapabilities {
+        supports_qualify,
+        supports_create_or_replace_table,
+        supports_create_or_replace_view,
+        supports_merge,
+        supports_insert_overwrite,
+        supports_pivot,
+        supports_date_literal,
+        supports_concat_operator,
+        supports_double_colon_cast,
+        supports_array_literal,
+        supports_transactional_ddl,
+        supports_cancel,
+        supports_comments,
+        supports_grants,
+    } = capabilities.clone();
+
+    vec![
+        ("QUALIFY", supports_qualify),
+        ("CREATE OR REPLACE TABLE", supports_create_or_replace_table),
+        ("CREATE OR REPLACE VIEW", supports_create_or_replace_view),
+        ("MERGE", supports_merge),
+        ("INSERT OVERWRITE", supports_insert_overwrite),
+        ("PIVOT", supports_pivot),
+        ("DATE literals", supports_date_literal),
+        ("|| concatenation", supports_concat_operator),
+        (":: casts", supports_double_colon_cast),
+        ("array literals", supports_array_literal),
+        ("transactional DDL", supports_transactional_ddl),
+        ("cancel", supports_cancel),
+        ("comments", supports_comments),
+        ("grants", supports_grants),
+    ]
+}
+
+/// Run the checks that need a connection, against the target schema.
+pub async fn run_checks(
+    backend: &dyn Backend,
+    backend_type: BackendType,
+    schema: &str,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let start = Instant::now();
+    checks.push(match backend.execute_sql("SELECT 1 AS ok").await {
+        Ok(batches) if batches.iter().map(|b| b.num_rows()).sum::<usize>() == 1 => Check::pass(
+            "query",
+            format!("SELECT 1 round-trip in {:.0?}", start.elapsed()),
+        ),
+        Ok(_) => Check::fail("query", backend_type, "SELECT 1 didn't return one row"),
+        Err(e) => Check::fail("query", backend_type, e),
+    });
+
+    checks.push(match backend.version().await {
+        Ok(version) => Check::pass("version", version),
+        Err(e) => Check::fail("version", backend_type, e),
+    });
+
+    checks.push(match backend.ensure_schema(schema).await {
+        Ok(()) => Check::pass("schema", format!("{} exists or was created", schema)),
+        Err(e) => Check::fail("schema", backend_type, e),
+    });
+
+    let probe = RelationName::new(schema, PROBE_TABLE);
+    let created = backend.create_table_as(&probe, "SELECT 1 AS ok").await;
+    let dropped = backend.drop_table_if_exists(&probe).await;
+    checks.push(match created.and(dropped) {
+        Ok(()) => Check::pass("create table", format!("created and dropped {}", probe)),
+        Err(e) => Check::fail("create table", backend_type, e),
+    });
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_backend_duckdb::DuckDbBackend;
+    use tempfile::TempDir;
+
+    fn target(yaml: &str) -> Target {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_target_problems() {
+        let dir = Path::new(".");
+        assert!(
+            target_problems(&target("{type: duckdb, database: x.db, schema: main}"), dir)
+                .is_empty()
+        );
+        assert_eq!(
+            target_problems(&target("{type: postgres, schema: main}"), dir),
+            vec!["Unknown target type 'postgres'; expected duckdb, sqlite, snowflake, or spark"]
+        );
+
+        let problems = target_problems(
+            &target("{type: snowflake, account: a, user: u, schema: marts, private_key_path: missing.p8}"),
+            dir,
+        );
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[0].contains("'database'"));
+        assert!(problems[1].contains("'warehouse'"));
+        assert!(problems[2].contains("missing.p8"));
+
+        assert_eq!(
+            target_problems(
+                &target("{type: spark, connect_url: 'sc://x', schema: s, table_format: orc}"),
+                dir
+            ),
+            vec!["Unknown table_format 'orc'; expected delta, iceberg, or parquet"]
+        );
+    }
+
+    #[test]
+    fn test_hint() {
+        let duckdb = BackendType::DuckDB;
+        assert!(hint(
+            duckdb,
+            "IO Error: Could not set lock on file \"dev.duckdb\""
+        )
+        .unwrap()
+        .contains("Another process"));
+        assert!(hint(
+            duckdb,
+            "Another run is in progress on dev.duckdb (process 42 holds its lock)"
+        )
+        .unwrap()
+        .contains("--wait"));
+        assert!(hint(
+            BackendType::Snowflake,
+            "390144 (08004): JWT token is invalid"
+        )
+        .unwrap()
+        .contains("RSA_PUBLIC_KEY"));
+        assert!(hint(
+            BackendType::Snowflake,
+            "Insufficient privileges to operate on schema"
+        )
+        .unwrap()
+        .contains("role"));
+        assert!(
+            hint(BackendType::Spark, "tcp connect error: Connection refused")
+                .unwrap()
+                .contains("connect_url")
+        );
+        assert_eq!(
+            hint(duckdb, "Parser Error: syntax error at end of input"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_checks() {
+        let dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&dir.path().join("debug.duckdb"), "main")
+            .await
+            .unwrap();
+
+        let checks = run_checks(&backend, BackendType::DuckDB, "analytics").await;
+        let names: Vec<_> = checks.iter().map(|c| c.name).collect();
+        assert_eq!(names, ["query", "version", "schema", "create table"]);
+        assert!(checks.iter().all(|c| c.passed), "{:?}", checks);
+        assert!(checks[1].detail.starts_with('v'));
+        assert!(!backend
+            .table_exists(&RelationName::new("analytics", PROBE_TABLE))
+            .await
+            .unwrap());
+    }
+}
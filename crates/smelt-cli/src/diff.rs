@@ -0,0 +1,305 @@
+//! Comparing a model's relation across two targets for `smelt diff`.
+//!
+//! Row counts and columns come from each backend; rows are compared by
+//! streaming both relations and hashing every row in Rust, so the targets can
+//! be different engines. Values are compared by their text form: a DECIMAL
+//! `10.50` on one side and a DOUBLE `10.5` on the other count as different.
+
+use anyhow::{Context, Result};
+use arrow::array::{Array, RecordBatch};
+use arrow::util::display::array_value_to_string;
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use smelt_backend::{Backend, ColumnInfo, RelationName};
+use std::collections::HashMap;
+
+/// Column differences between the two relations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    /// Columns in both (matched case-insensitively), as named on each side, in A's order
+    pub common: Vec<(String, String)>,
+    pub only_in_a: Vec<ColumnInfo>,
+    pub only_in_b: Vec<ColumnInfo>,
+    /// Common columns whose types differ: name, type in A, type in B
+    pub type_changes: Vec<(String, String, String)>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.type_changes.is_empty()
+    }
+}
+
+/// Row differences over the common columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowDiff {
+    /// Order-independent checksum of each side's rows
+    pub checksum_a: u64,
+    pub checksum_b: u64,
+    /// Rows with no identical row on the other side (duplicates count separately)
+    pub only_in_a: usize,
+    pub only_in_b: usize,
+    /// Some of those rows, with values separated by ` | `
+    pub sample_a: Vec<String>,
+    pub sample_b: Vec<String>,
+}
+
+/// Result of [`diff_relations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelDiff {
+    pub row_count_a: usize,
+    pub row_count_b: usize,
+    pub schema: SchemaDiff,
+    /// `None` when the relations share no columns
+    pub rows: Option<RowDiff>,
+}
+
+impl ModelDiff {
+    pub fn is_identical(&self) -> bool {
+        self.row_count_a == self.row_count_b
+            && self.schema.is_empty()
+            && self
+                .rows
+                .as_ref()
+                .is_some_and(|rows| rows.only_in_a == 0 && rows.only_in_b == 0)
+    }
+}
+
+/// Match columns by name, ignoring case (Snowflake upper-cases unquoted names).
+pub fn diff_schemas(a: &[ColumnInfo], b: &[ColumnInfo]) -> SchemaDiff {
+    let find = |columns: &[ColumnInfo], name: &str| {
+        columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .cloned()
+    };
+
+    let mut diff = SchemaDiff::default();
+    for column in a {
+        match find(b, &column.name) {
+            Some(other) => {
+                if !column.data_type.eq_ignore_ascii_case(&other.data_type) {
+                    diff.type_changes.push((
+                        column.name.clone(),
+                        column.data_type.clone(),
+                        other.data_type,
+                    ));
+                }
+                diff.common.push((column.name.clone(), other.name));
+            }
+            None => diff.only_in_a.push(column.clone()),
+        }
+    }
+    diff.only_in_b = b
+        .iter()
+        .filter(|column| find(a, &column.name).is_none())
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Compare `relation_a` in backend `a` with `relation_b` in backend `b`,
+/// keeping up to `sample` unmatched rows from each side.
+pub async fn diff_relations(
+    a: &dyn Backend,
+    relation_a: &RelationName,
+    b: &dyn Backend,
+    relation_b: &RelationName,
+    sample: usize,
+) -> Result<ModelDiff> {
+    let row_count_a = a
+        .get_row_count(relation_a)
+        .await
+        .with_context(|| format!("Failed to count rows of {}", relation_a))?;
+    let row_count_b = b
+        .get_row_count(relation_b)
+        .await
+        .with_context(|| format!("Failed to count rows of {}", relation_b))?;
+
+    let columns_a = a
+        .get_table_schema(relation_a)
+        .await
+        .with_context(|| format!("Failed to read columns of {}", relation_a))?;
+    let columns_b = b
+        .get_table_schema(relation_b)
+        .await
+        .with_context(|| format!("Failed to read columns of {}", relation_b))?;
+    let schema = diff_schemas(&columns_a, &columns_b);
+
+    let rows = if schema.common.is_empty() {
+        None
+    } else {
+        let (names_a, names_b): (Vec<_>, Vec<_>) = schema.common.iter().cloned().unzip();
+        Some(diff_rows((a, relation_a, &names_a), (b, relation_b, &names_b), sample).await?)
+    };
+
+    Ok(ModelDiff {
+        row_count_a,
+        row_count_b,
+        schema,
+        rows,
+    })
+}
+
+type Side<'a> = (&'a dyn Backend, &'a RelationName, &'a [String]);
+
+async fn diff_rows(a: Side<'_>, b: Side<'_>, sample: usize) -> Result<RowDiff> {
+    let mut diff = RowDiff::default();
+
+    // Count A's rows by hash, then cancel them out with B's. A row of B that
+    // takes its count below zero has no match left in A.
+    let mut counts: HashMap<u64, i64> = HashMap::new();
+    for_each_row(a, |hash, _| {
+        diff.checksum_a = diff.checksum_a.wrapping_add(hash);
+        *counts.entry(hash).or_default() += 1;
+        None
+    })
+    .await?;
+    for_each_row(b, |hash, text| {
+        diff.checksum_b = diff.checksum_b.wrapping_add(hash);
+        let count = counts.entry(hash).or_default();
+        *count -= 1;
+        if *count < 0 {
+            diff.only_in_b += 1;
+            if diff.sample_b.len() < sample {
+                diff.sample_b.push(text());
+            }
+        }
+        None
+    })
+    .await?;
+
+    counts.retain(|_, count| *count > 0);
+    diff.only_in_a = counts.values().map(|count| *count as usize).sum();
+    if diff.only_in_a > 0 && sample > 0 {
+        // Read A again for examples of its unmatched rows
+        for_each_row(a, |hash, text| {
+            let count = counts.get_mut(&hash).filter(|count| **count > 0)?;
+            *count -= 1;
+            diff.sample_a.push(text());
+            (diff.sample_a.len() >= sample).then_some(())
+        })
+        .await?;
+    }
+
+    Ok(diff)
+}
+
+/// Stream a relation's columns, calling `each` with every row's hash and a
+/// way to render it. Stops early once `each` returns `Some`.
+async fn for_each_row(
+    (backend, relation, columns): Side<'_>,
+    mut each: impl FnMut(u64, &dyn Fn() -> String) -> Option<()>,
+) -> Result<()> {
+    let dialect = backend.dialect();
+    let sql = format!(
+        "SELECT {} FROM {}",
+        columns
+            .iter()
+            .map(|column| dialect.quote_ident(column))
+            .collect::<Vec<_>>()
+            .join(", "),
+        dialect.quote_relation(relation)
+    );
+
+    let mut stream = backend
+        .execute_sql_stream(&sql)
+        .await
+        .with_context(|| format!("Failed to read {}", relation))?;
+    while let Some(batch) = stream.next().await {
+        let batch = batch.with_context(|| format!("Failed to read {}", relation))?;
+        for row in 0..batch.num_rows() {
+            let hash = row_hash(&batch, row)?;
+            if each(hash, &|| row_text(&batch, row)).is_some() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hash of a row's values; NULL and each value's length are included so
+/// `('a', 'bc')`, `('ab', 'c')`, and `(NULL, 'abc')` all differ.
+fn row_hash(batch: &RecordBatch, row: usize) -> Result<u64> {
+    let mut hasher = Sha256::new();
+    for column in batch.columns() {
+        if column.is_null(row) {
+            hasher.update([0]);
+        } else {
+            let value = array_value_to_string(column, row)?;
+            hasher.update([1]);
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        }
+    }
+    let digest = hasher.finalize();
+    Ok(u64::from_le_bytes(digest[..8].try_into().unwrap()))
+}
+
+fn row_text(batch: &RecordBatch, row: usize) -> String {
+    batch
+        .columns()
+        .iter()
+        .map(|column| {
+            if column.is_null(row) {
+                "NULL".to_string()
+            } else {
+                array_value_to_string(column, row).unwrap_or_else(|_| "?".to_string())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_backend_duckdb::DuckDbBackend;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_diff_relations() {
+        let temp_dir = TempDir::new().unwrap();
+        let dev = DuckDbBackend::new(&temp_dir.path().join("dev.duckdb"), "main")
+            .await
+            .unwrap();
+        let prod = DuckDbBackend::new(&temp_dir.path().join("prod.duckdb"), "main")
+            .await
+            .unwrap();
+        dev.execute_sql(
+            "CREATE TABLE orders AS SELECT * FROM (VALUES \
+             (1, 'a', 10), (2, 'b', 20), (2, 'b', 20), (3, NULL, 30)) t(id, name, amount)",
+        )
+        .await
+        .unwrap();
+        prod.execute_sql(
+            "CREATE TABLE orders AS SELECT * FROM (VALUES \
+             (1, 'a', 10, true), (2, 'b', 20, true), (3, 'c', 30, false)) t(ID, name, amount, flag)",
+        )
+        .await
+        .unwrap();
+
+        let relation = RelationName::new("main", "orders");
+        let diff = diff_relations(&dev, &relation, &prod, &relation, 10)
+            .await
+            .unwrap();
+
+        assert!(!diff.is_identical());
+        assert_eq!((diff.row_count_a, diff.row_count_b), (4, 3));
+        assert_eq!(diff.schema.only_in_b.len(), 1);
+        assert_eq!(diff.schema.only_in_b[0].name, "flag");
+        assert_eq!(diff.schema.common[0], ("id".to_string(), "ID".to_string()));
+        assert!(diff.schema.type_changes.is_empty());
+
+        let rows = diff.rows.unwrap();
+        assert_eq!((rows.only_in_a, rows.only_in_b), (2, 1));
+        assert_eq!(rows.sample_a, vec!["2 | b | 20", "3 | NULL | 30"]);
+        assert_eq!(rows.sample_b, vec!["3 | c | 30"]);
+        assert_ne!(rows.checksum_a, rows.checksum_b);
+
+        let same = diff_relations(&dev, &relation, &dev, &relation, 10)
+            .await
+            .unwrap();
+        assert!(same.is_identical());
+    }
+}
@@ -1,10 +1,13 @@
 use anyhow::{anyhow, Context, Result};
 use rowan::TextRange;
-use smelt_parser::File as AstFile;
+use smelt_parser::{File as AstFile, RefCall};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::config::ProgramConfig;
 use crate::metadata::{extract_file_metadata, FileMetadata, ModelMetadata};
+use crate::package::Package;
+use crate::template::{render, Vars};
 
 #[derive(Debug, Clone)]
 pub struct ModelFile {
@@ -17,6 +20,17 @@ pub struct ModelFile {
     pub metadata: Option<Box<ModelMetadata>>,
 }
 
+impl ModelFile {
+    /// Qualified names of the tables the model reads with `smelt.source()`.
+    pub fn source_tables(&self) -> Vec<String> {
+        let parse = smelt_parser::parse(&self.content);
+        let Some(file) = AstFile::cast(parse.syntax()) else {
+            return Vec::new();
+        };
+        file.sources().filter_map(|s| s.qualified_name()).collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RefInfo {
     pub model_name: String,
@@ -27,6 +41,11 @@ pub struct RefInfo {
 pub struct ModelDiscovery {
     project_root: PathBuf,
     model_paths: Vec<String>,
+    vars: Vars,
+    /// Program models and what they depend on
+    programs: Vec<(String, Vec<String>)>,
+    /// Other projects whose models are discovered as `<package>.<model>`
+    packages: Vec<Package>,
 }
 
 impl ModelDiscovery {
@@ -34,14 +53,80 @@ impl ModelDiscovery {
         Self {
             project_root,
             model_paths,
+            vars: Vars::new(),
+            programs: Vec::new(),
+            packages: Vec::new(),
         }
     }
 
+    /// Variables for rendering `{{ var() }}` in model files
+    pub fn with_vars(mut self, vars: Vars) -> Self {
+        self.vars = vars;
+        self
+    }
+
+    /// Models built by programs declared in smelt.yml, which have no .sql file
+    pub fn with_programs<'a>(
+        mut self,
+        programs: impl IntoIterator<Item = (&'a str, &'a ProgramConfig)>,
+    ) -> Self {
+        self.programs = programs
+            .into_iter()
+            .map(|(name, program)| (name.to_string(), program.depends_on.clone()))
+            .collect();
+        self.programs.sort();
+        self
+    }
+
+    /// Packages whose models are discovered along with the project's
+    pub fn with_packages(mut self, packages: &[Package]) -> Self {
+        self.packages = packages.to_vec();
+        self
+    }
+
     pub fn discover_models(&self) -> Result<Vec<ModelFile>> {
         let mut models = Vec::new();
+        self.discover_in(&self.project_root, &self.model_paths, None, &mut models)?;
+        for package in &self.packages {
+            self.discover_in(
+                &package.root,
+                &package.model_paths,
+                Some(&package.name),
+                &mut models,
+            )?;
+        }
+
+        for (name, depends_on) in &self.programs {
+            if let Some(model) = models.iter().find(|m| &m.name == name) {
+                return Err(anyhow!(
+                    "Model '{}' has a program in smelt.yml and SQL in {:?}; remove one of them",
+                    name,
+                    model.path
+                ));
+            }
+            models.push(self.program_model(name, depends_on));
+        }
+
+        if models.is_empty() {
+            return Err(anyhow!(
+                "No models found in model paths: {}",
+                self.model_paths.join(", ")
+            ));
+        }
 
-        for model_path in &self.model_paths {
-            let search_path = self.project_root.join(model_path);
+        Ok(models)
+    }
+
+    /// Parse every .sql file under `model_paths` of `root` into `models`
+    fn discover_in(
+        &self,
+        root: &Path,
+        model_paths: &[String],
+        package: Option<&str>,
+        models: &mut Vec<ModelFile>,
+    ) -> Result<()> {
+        for model_path in model_paths {
+            let search_path = root.join(model_path);
 
             if !search_path.exists() {
                 continue;
@@ -56,26 +141,20 @@ impl ModelDiscovery {
                 let path = entry.path();
 
                 if path.extension().and_then(|s| s.to_str()) == Some("sql") {
-                    let model = self.parse_model_file(path)?;
+                    let model = self.parse_model_file(path, package)?;
                     models.push(model);
                 }
             }
         }
-
-        if models.is_empty() {
-            return Err(anyhow!(
-                "No models found in model paths: {}",
-                self.model_paths.join(", ")
-            ));
-        }
-
-        Ok(models)
+        Ok(())
     }
 
-    fn parse_model_file(&self, path: &Path) -> Result<ModelFile> {
+    fn parse_model_file(&self, path: &Path, package: Option<&str>) -> Result<ModelFile> {
         // Read file content
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read model file: {:?}", path))?;
+        let content = render(&content, &self.vars)
+            .with_context(|| format!("Failed to render model file: {:?}", path))?;
 
         // Extract metadata from YAML frontmatter
         let file_metadata = extract_file_metadata(&content).ok();
@@ -107,13 +186,17 @@ impl ModelDiscovery {
                     .map(|s| s.to_string())
             })
             .ok_or_else(|| anyhow!("Cannot determine model name from {:?}", path))?;
+        let name = match package {
+            Some(package) => format!("{}.{}", package, name),
+            None => name,
+        };
 
         // Parse using smelt-parser
         let parse = smelt_parser::parse(&content);
 
         // Extract refs using AST
         let refs = if let Some(file) = AstFile::cast(parse.syntax()) {
-            extract_refs(&file)
+            extract_refs(&file, package)
         } else {
             Vec::new()
         };
@@ -127,12 +210,42 @@ impl ModelDiscovery {
             metadata: model_metadata,
         })
     }
+
+    /// A program model, declared in smelt.yml, with a ref for each dependency
+    fn program_model(&self, name: &str, depends_on: &[String]) -> ModelFile {
+        ModelFile {
+            name: name.to_string(),
+            path: self.project_root.join("smelt.yml"),
+            content: String::new(),
+            refs: depends_on
+                .iter()
+                .map(|dependency| RefInfo {
+                    model_name: dependency.clone(),
+                    has_named_params: false,
+                    range: TextRange::default(),
+                })
+                .collect(),
+            parse_errors: Vec::new(),
+            metadata: None,
+        }
+    }
 }
 
-fn extract_refs(file: &AstFile) -> Vec<RefInfo> {
+/// The model a ref names: `package.model` for refs into a package, including
+/// plain refs made from inside `package`.
+pub(crate) fn ref_model_name(ref_call: &RefCall, package: Option<&str>) -> Option<String> {
+    match package {
+        Some(package) if ref_call.package_name().is_none() => {
+            Some(format!("{}.{}", package, ref_call.model_name()?))
+        }
+        _ => ref_call.qualified_name(),
+    }
+}
+
+fn extract_refs(file: &AstFile, package: Option<&str>) -> Vec<RefInfo> {
     file.refs()
         .filter_map(|ref_call| {
-            let model_name = ref_call.model_name()?;
+            let model_name = ref_model_name(&ref_call, package)?;
             let has_params = ref_call.named_params().count() > 0;
             let range = ref_call.range();
 
@@ -161,7 +274,7 @@ GROUP BY user_id
 
         let parse = smelt_parser::parse(sql);
         let file = AstFile::cast(parse.syntax()).unwrap();
-        let refs = extract_refs(&file);
+        let refs = extract_refs(&file, None);
 
         assert_eq!(refs.len(), 1);
         assert_eq!(refs[0].model_name, "raw_events");
@@ -177,7 +290,7 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
 
         let parse = smelt_parser::parse(sql);
         let file = AstFile::cast(parse.syntax()).unwrap();
-        let refs = extract_refs(&file);
+        let refs = extract_refs(&file, None);
 
         assert_eq!(refs.len(), 1);
         assert_eq!(refs[0].model_name, "raw_events");
@@ -196,10 +309,110 @@ INNER JOIN smelt.ref('model_b') b ON a.id = b.id
 
         let parse = smelt_parser::parse(sql);
         let file = AstFile::cast(parse.syntax()).unwrap();
-        let refs = extract_refs(&file);
+        let refs = extract_refs(&file, None);
 
         assert_eq!(refs.len(), 2);
         assert_eq!(refs[0].model_name, "model_a");
         assert_eq!(refs[1].model_name, "model_b");
     }
+
+    #[test]
+    fn test_discover_renders_vars() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let models_dir = temp_dir.path().join("models");
+        std::fs::create_dir_all(&models_dir).unwrap();
+        std::fs::write(
+            models_dir.join("regional.sql"),
+            "SELECT * FROM smelt.ref('events') WHERE region = '{{ var('region') }}'",
+        )
+        .unwrap();
+
+        let vars = crate::template::parse_vars("{region: emea}").unwrap();
+        let models = ModelDiscovery::new(temp_dir.path().to_path_buf(), vec!["models".into()])
+            .with_vars(vars)
+            .discover_models()
+            .unwrap();
+
+        assert_eq!(
+            models[0].content,
+            "SELECT * FROM smelt.ref('events') WHERE region = 'emea'"
+        );
+        assert_eq!(models[0].refs[0].model_name, "events");
+
+        let err = ModelDiscovery::new(temp_dir.path().to_path_buf(), vec!["models".into()])
+            .discover_models()
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("Variable 'region' is not defined"));
+    }
+
+    #[test]
+    fn test_discover_program_models() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let models_dir = temp_dir.path().join("models");
+        std::fs::create_dir_all(&models_dir).unwrap();
+        std::fs::write(models_dir.join("orders.sql"), "SELECT 1 AS id").unwrap();
+
+        let program: ProgramConfig =
+            serde_yaml::from_str("command: [python, score.py]\ndepends_on: [orders, raw.events]")
+                .unwrap();
+        let models = ModelDiscovery::new(temp_dir.path().to_path_buf(), vec!["models".into()])
+            .with_programs([("scores", &program)])
+            .discover_models()
+            .unwrap();
+
+        assert_eq!(models.len(), 2);
+        let scores = models.iter().find(|m| m.name == "scores").unwrap();
+        assert!(scores.content.is_empty());
+        let refs: Vec<_> = scores.refs.iter().map(|r| r.model_name.as_str()).collect();
+        assert_eq!(refs, vec!["orders", "raw.events"]);
+
+        let err = ModelDiscovery::new(temp_dir.path().to_path_buf(), vec!["models".into()])
+            .with_programs([("orders", &program)])
+            .discover_models()
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("has a program in smelt.yml and SQL"));
+    }
+
+    #[test]
+    fn test_discover_package_models() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let models_dir = temp_dir.path().join("project/models");
+        std::fs::create_dir_all(&models_dir).unwrap();
+        std::fs::write(
+            models_dir.join("orders.sql"),
+            "SELECT * FROM smelt.ref('shared', 'customers') JOIN smelt.ref('customers') USING (id)",
+        )
+        .unwrap();
+        let package_dir = temp_dir.path().join("shared/marts");
+        std::fs::create_dir_all(&package_dir).unwrap();
+        std::fs::write(
+            package_dir.join("customers.sql"),
+            "SELECT * FROM smelt.ref('accounts')",
+        )
+        .unwrap();
+
+        let package = Package {
+            name: "shared".to_string(),
+            root: temp_dir.path().join("shared"),
+            model_paths: vec!["marts".to_string()],
+            operation_paths: Vec::new(),
+        };
+        let mut models =
+            ModelDiscovery::new(temp_dir.path().join("project"), vec!["models".into()])
+                .with_packages(&[package])
+                .discover_models()
+                .unwrap();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let names: Vec<_> = models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["orders", "shared.customers"]);
+        let refs = |model: &ModelFile| -> Vec<String> {
+            model.refs.iter().map(|r| r.model_name.clone()).collect()
+        };
+        // Plain refs inside a package stay in the package
+        assert_eq!(refs(&models[0]), vec!["shared.customers", "customers"]);
+        assert_eq!(refs(&models[1]), vec!["shared.accounts"]);
+    }
 }
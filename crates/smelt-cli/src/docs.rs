@@ -0,0 +1,890 @@
+//! Documentation generation for `smelt docs generate`.
+//!
+//! Builds a [`DocsBundle`] describing every model (description, columns,
+//! lineage, dependencies), source and exposure, then writes it as `docs.json` and/or a
+//! self-contained static `index.html`. Column schemas come from the same
+//! smelt-db schema queries that power LSP hover, so the site always agrees
+//! with what the editor shows. With `--catalog`, column types are read from
+//! the built relations in the target backend.
+
+use crate::config::{Config, ExposureType, Materialization, SourceConfig};
+use crate::graph::DependencyGraph;
+use crate::metadata::{extract_file_metadata, FileMetadata};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use smelt_backend::{Backend, RelationName};
+use smelt_db::{ColumnSource, Database, Inputs, ModelSchema, Schema};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Everything needed to render the docs site.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocsBundle {
+    pub project_name: String,
+    pub generated_at: String,
+    pub models: Vec<ModelDoc>,
+    pub sources: Vec<SourceDoc>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exposures: Vec<ExposureDoc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDoc {
+    pub name: String,
+    /// Path relative to the project root
+    pub path: PathBuf,
+    /// From `description:` in frontmatter, or the leading `--` comment block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub materialization: Materialization,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub depends_on: Vec<String>,
+    pub referenced_by: Vec<String>,
+    /// Exposures that read this model, directly or through downstream models
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exposures: Vec<String>,
+    pub columns: Vec<ColumnDoc>,
+    pub sql: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnDoc {
+    pub name: String,
+    pub expression: String,
+    /// Upstream column or table this column is read from, if traceable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<String>,
+    /// Type reported by the backend, when docs are generated with `--catalog`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// From `columns:` in frontmatter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceDoc {
+    /// Qualified `schema.table` name
+    pub name: String,
+    pub description: String,
+    pub columns: Vec<SourceColumnDoc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureDoc {
+    pub name: String,
+    pub exposure_type: ExposureType,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceColumnDoc {
+    pub name: String,
+    pub column_type: String,
+    pub description: String,
+}
+
+impl DocsBundle {
+    /// Collect documentation for every model in the graph and every source.
+    pub fn build(
+        graph: &DependencyGraph,
+        config: &Config,
+        sources: Option<&SourceConfig>,
+        project_root: &Path,
+    ) -> Self {
+        // Load models into a smelt-db database to get column schemas
+        let mut db = Database::default();
+        let sources_yaml =
+            std::fs::read_to_string(project_root.join("sources.yml")).unwrap_or_default();
+        db.set_sources_yaml(Arc::new(sources_yaml));
+        db.set_packages(Arc::new(
+            graph.packages().iter().map(|p| p.db_package()).collect(),
+        ));
+
+        // Program models have no SQL, so their columns are unknown
+        let is_sql = |path: &Path| path.extension().is_some_and(|ext| ext == "sql");
+
+        let mut paths = Vec::new();
+        for model in graph.models().values().filter(|m| is_sql(&m.path)) {
+            db.set_file_text(model.path.clone(), Arc::new(model.content.clone()));
+            paths.push(model.path.clone());
+        }
+        db.set_all_files(Arc::new(paths));
+
+        let mut referenced_by: HashMap<&str, Vec<String>> = HashMap::new();
+        for model in graph.models().values() {
+            for r in &model.refs {
+                referenced_by
+                    .entry(r.model_name.as_str())
+                    .or_default()
+                    .push(model.name.clone());
+            }
+        }
+
+        let mut models: Vec<ModelDoc> = graph
+            .models()
+            .values()
+            .map(|model| {
+                let metadata = model.metadata.as_deref();
+
+                let mut depends_on: Vec<String> =
+                    model.refs.iter().map(|r| r.model_name.clone()).collect();
+                depends_on.sort();
+                depends_on.dedup();
+
+                let mut dependents = referenced_by
+                    .get(model.name.as_str())
+                    .cloned()
+                    .unwrap_or_default();
+                dependents.sort();
+                dependents.dedup();
+
+                let schema = if is_sql(&model.path) {
+                    db.model_schema(model.path.clone())
+                } else {
+                    Arc::new(ModelSchema::empty())
+                };
+                let columns = schema
+                    .columns
+                    .iter()
+                    .map(|col| ColumnDoc {
+                        name: col.name.clone(),
+                        expression: col.expression.clone(),
+                        lineage: column_lineage(&col.source),
+                        data_type: None,
+                        description: metadata
+                            .and_then(|m| m.columns.iter().find(|c| c.name == col.name))
+                            .map(|c| c.description.clone())
+                            .filter(|d| !d.is_empty()),
+                    })
+                    .collect();
+
+                ModelDoc {
+                    name: model.name.clone(),
+                    path: model
+                        .path
+                        .strip_prefix(project_root)
+                        .unwrap_or(&model.path)
+                        .to_path_buf(),
+                    description: metadata
+                        .and_then(|m| m.description.clone())
+                        .or_else(|| leading_comment(&model.content)),
+                    materialization: config.get_model_materialization(model),
+                    tags: config.get_model_tags(model),
+                    owner: metadata.and_then(|m| m.owner.clone()),
+                    depends_on,
+                    referenced_by: dependents,
+                    exposures: graph
+                        .downstream_exposures(&model.name)
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                    columns,
+                    sql: model.content.clone(),
+                }
+            })
+            .collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut source_docs = Vec::new();
+        if let Some(sources) = sources {
+            let schemas: BTreeMap<_, _> = sources.sources.iter().collect();
+            for (schema_name, schema) in schemas {
+                let tables: BTreeMap<_, _> = schema.tables.iter().collect();
+                for (table_name, table) in tables {
+                    source_docs.push(SourceDoc {
+                        name: format!("{}.{}", schema_name, table_name),
+                        description: table.description.clone(),
+                        columns: table
+                            .columns
+                            .iter()
+                            .map(|c| SourceColumnDoc {
+                                name: c.name.clone(),
+                                column_type: c.column_type.clone(),
+                                description: c.description.clone(),
+                            })
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        let exposures = graph
+            .exposures()
+            .iter()
+            .map(|(name, exposure)| ExposureDoc {
+                name: name.clone(),
+                exposure_type: exposure.exposure_type,
+                description: exposure.description.clone(),
+                owner: exposure.owner.clone(),
+                url: exposure.url.clone(),
+                depends_on: exposure.depends_on.clone(),
+            })
+            .collect();
+
+        Self {
+            project_name: config.name.clone(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            models,
+            sources: source_docs,
+            exposures,
+        }
+    }
+    /// Fill in column types from the relations the backend has built.
+    ///
+    /// Models are looked up as the relation given by `relation_for`; models that
+    /// haven't been built are left as they are. Source columns without a
+    /// declared type take the backend's type, and sources that declare no
+    /// columns get the backend's column list. Returns the number of models and
+    /// sources found in the backend.
+    pub async fn add_catalog_types(
+        &mut self,
+        backend: &dyn Backend,
+        relation_for: impl Fn(&str) -> RelationName,
+    ) -> Result<usize> {
+        let mut found = 0;
+
+        for model in &mut self.models {
+            let relation = relation_for(&model.name);
+            let columns = backend
+                .get_table_schema(&relation)
+                .await
+                .with_context(|| format!("Failed to read columns of {}", relation))?;
+            if columns.is_empty() {
+                continue;
+            }
+            found += 1;
+            for column in &mut model.columns {
+                column.data_type = columns
+                    .iter()
+                    .find(|c| c.name.eq_ignore_ascii_case(&column.name))
+                    .map(|c| c.data_type.clone());
+            }
+        }
+
+        for source in &mut self.sources {
+            let Some((schema, table)) = source.name.split_once('.') else {
+                continue;
+            };
+            let columns = backend
+                .get_table_schema(&RelationName::new(schema, table))
+                .await
+                .with_context(|| format!("Failed to read columns of {}", source.name))?;
+            if columns.is_empty() {
+                continue;
+            }
+            found += 1;
+            if source.columns.is_empty() {
+                source.columns = columns
+                    .into_iter()
+                    .map(|c| SourceColumnDoc {
+                        name: c.name,
+                        column_type: c.data_type,
+                        description: String::new(),
+                    })
+                    .collect();
+                continue;
+            }
+            for column in &mut source.columns {
+                if column.column_type.is_empty() {
+                    if let Some(c) = columns
+                        .iter()
+                        .find(|c| c.name.eq_ignore_ascii_case(&column.name))
+                    {
+                        column.column_type = c.data_type.clone();
+                    }
+                }
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+fn column_lineage(source: &ColumnSource) -> Option<String> {
+    match source {
+        ColumnSource::FromModel {
+            model_name,
+            column_name,
+        } => Some(format!("{}.{}", model_name, column_name)),
+        ColumnSource::Wildcard { model_name } => Some(format!("{}.*", model_name)),
+        ColumnSource::ExternalTable { table_name } => Some(table_name.clone()),
+        ColumnSource::Computed | ColumnSource::Unknown => None,
+    }
+}
+
+/// Extract the `--` comment block at the top of a model (after any frontmatter).
+pub fn leading_comment(content: &str) -> Option<String> {
+    let body = match extract_file_metadata(content) {
+        Ok(FileMetadata::Single { sql_offset, .. }) => &content[sql_offset..],
+        _ => content,
+    };
+
+    let lines: Vec<&str> = body
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| line.starts_with("--"))
+        .map(|line| line.trim_start_matches('-').trim())
+        .collect();
+
+    let description = lines.join("\n").trim().to_string();
+    (!description.is_empty()).then_some(description)
+}
+
+/// Write `docs.json` to `dir`.
+pub fn write_docs_json(bundle: &DocsBundle, dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create docs directory {:?}", dir))?;
+    let path = dir.join("docs.json");
+    std::fs::write(&path, serde_json::to_string_pretty(bundle)?)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(path)
+}
+
+/// Write the static site (`index.html`) to `dir`.
+pub fn write_docs_site(bundle: &DocsBundle, dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create docs directory {:?}", dir))?;
+    let path = dir.join("index.html");
+    std::fs::write(&path, render_html(bundle))
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(path)
+}
+
+const NODE_WIDTH: usize = 180;
+const NODE_HEIGHT: usize = 32;
+const LAYER_GAP: usize = 80;
+const ROW_GAP: usize = 16;
+
+const STYLE: &str = "
+body { font-family: -apple-system, BlinkMacSystemFont, sans-serif; margin: 0; display: flex; color: #222; }
+nav { width: 240px; height: 100vh; overflow-y: auto; position: sticky; top: 0; background: #f6f8fa; padding: 16px; box-sizing: border-box; }
+nav h3 { margin-top: 16px; font-size: 13px; text-transform: uppercase; color: #666; }
+nav a { display: block; padding: 2px 0; color: #0366d6; text-decoration: none; }
+main { flex: 1; padding: 24px 40px; min-width: 0; }
+section { border-top: 1px solid #e1e4e8; padding: 16px 0; }
+.description { white-space: pre-line; }
+.meta { color: #666; font-size: 14px; }
+.tag { background: #e1ecf4; border-radius: 3px; padding: 1px 6px; margin-right: 4px; font-size: 12px; }
+table { border-collapse: collapse; margin: 8px 0; }
+th, td { border: 1px solid #e1e4e8; padding: 4px 10px; text-align: left; font-size: 14px; }
+code, pre { font-family: SFMono-Regular, Menlo, monospace; font-size: 13px; }
+pre { background: #f6f8fa; padding: 12px; overflow-x: auto; }
+.dag { overflow-x: auto; border: 1px solid #e1e4e8; }
+.dag rect { fill: #fff; stroke: #0366d6; rx: 4; }
+.dag a:hover rect { fill: #e1ecf4; }
+.dag text { font-size: 12px; dominant-baseline: middle; text-anchor: middle; }
+.dag path { fill: none; stroke: #999; marker-end: url(#arrow); }
+";
+
+/// Render the docs bundle as a single self-contained HTML page.
+pub fn render_html(bundle: &DocsBundle) -> String {
+    let mut html = String::new();
+    let title = escape(&bundle.project_name);
+
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} docs</title>\n<style>{}</style>\n</head>\n<body>",
+        title, STYLE
+    );
+
+    // Navigation
+    html.push_str("<nav>\n");
+    let _ = writeln!(html, "<h2>{}</h2>", title);
+    html.push_str("<a href=\"#dag\">Lineage graph</a>\n<h3>Models</h3>\n");
+    for model in &bundle.models {
+        let _ = writeln!(html, "<a href=\"#model-{0}\">{0}</a>", escape(&model.name));
+    }
+    if !bundle.sources.is_empty() {
+        html.push_str("<h3>Sources</h3>\n");
+        for source in &bundle.sources {
+            let _ = writeln!(
+                html,
+                "<a href=\"#source-{0}\">{0}</a>",
+                escape(&source.name)
+            );
+        }
+    }
+    if !bundle.exposures.is_empty() {
+        html.push_str("<h3>Exposures</h3>\n");
+        for exposure in &bundle.exposures {
+            let _ = writeln!(
+                html,
+                "<a href=\"#exposure-{0}\">{0}</a>",
+                escape(&exposure.name)
+            );
+        }
+    }
+    html.push_str("</nav>\n<main>\n");
+
+    let _ = writeln!(
+        html,
+        "<h1>{}</h1>\n<p class=\"meta\">Generated {}</p>",
+        title,
+        escape(&bundle.generated_at)
+    );
+    let _ = writeln!(
+        html,
+        "<section id=\"dag\">\n<h2>Lineage graph</h2>\n<div class=\"dag\">{}</div>\n</section>",
+        render_dag(&bundle.models)
+    );
+
+    for model in &bundle.models {
+        render_model(&mut html, model);
+    }
+    for source in &bundle.sources {
+        render_source(&mut html, source);
+    }
+    for exposure in &bundle.exposures {
+        render_exposure(&mut html, exposure);
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+fn render_model(html: &mut String, model: &ModelDoc) {
+    let name = escape(&model.name);
+    let _ = writeln!(html, "<section id=\"model-{0}\">\n<h2>{0}</h2>", name);
+
+    let mut meta = format!(
+        "<code>{}</code> · {}",
+        escape(&model.path.to_string_lossy()),
+        match model.materialization {
+            Materialization::Table => "table",
+            Materialization::View => "view",
+            Materialization::Ephemeral => "ephemeral",
+            Materialization::External => "external",
+        }
+    );
+    if let Some(owner) = &model.owner {
+        let _ = write!(meta, " · owner: {}", escape(owner));
+    }
+    let _ = writeln!(html, "<p class=\"meta\">{}</p>", meta);
+
+    if !model.tags.is_empty() {
+        html.push_str("<p>");
+        for tag in &model.tags {
+            let _ = write!(html, "<span class=\"tag\">{}</span>", escape(tag));
+        }
+        html.push_str("</p>\n");
+    }
+
+    if let Some(description) = &model.description {
+        let _ = writeln!(html, "<p class=\"description\">{}</p>", escape(description));
+    }
+
+    let links = |names: &[String]| {
+        names
+            .iter()
+            .map(|n| format!("<a href=\"#model-{0}\">{0}</a>", escape(n)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if !model.depends_on.is_empty() {
+        let _ = writeln!(html, "<p>Depends on: {}</p>", links(&model.depends_on));
+    }
+    if !model.referenced_by.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p>Referenced by: {}</p>",
+            links(&model.referenced_by)
+        );
+    }
+    if !model.exposures.is_empty() {
+        let exposures = model
+            .exposures
+            .iter()
+            .map(|n| format!("<a href=\"#exposure-{0}\">{0}</a>", escape(n)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(html, "<p>Exposures: {}</p>", exposures);
+    }
+
+    if !model.columns.is_empty() {
+        let typed = model.columns.iter().any(|c| c.data_type.is_some());
+        html.push_str("<table>\n<tr><th>Column</th>");
+        if typed {
+            html.push_str("<th>Type</th>");
+        }
+        html.push_str("<th>Expression</th><th>Lineage</th></tr>\n");
+        for column in &model.columns {
+            let _ = write!(html, "<tr><td>{}", escape(&column.name));
+            if let Some(description) = &column.description {
+                let _ = write!(
+                    html,
+                    "<br><span class=\"meta\">{}</span>",
+                    escape(description)
+                );
+            }
+            html.push_str("</td>");
+            if typed {
+                let _ = write!(
+                    html,
+                    "<td><code>{}</code></td>",
+                    column.data_type.as_deref().map(escape).unwrap_or_default()
+                );
+            }
+            let _ = writeln!(
+                html,
+                "<td><code>{}</code></td><td>{}</td></tr>",
+                escape(&column.expression),
+                column.lineage.as_deref().map(escape).unwrap_or_default()
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    let _ = writeln!(
+        html,
+        "<details>\n<summary>SQL</summary>\n<pre>{}</pre>\n</details>\n</section>",
+        escape(&model.sql)
+    );
+}
+
+fn render_source(html: &mut String, source: &SourceDoc) {
+    let _ = writeln!(
+        html,
+        "<section id=\"source-{0}\">\n<h2>{0} <span class=\"meta\">(source)</span></h2>",
+        escape(&source.name)
+    );
+    if !source.description.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"description\">{}</p>",
+            escape(&source.description)
+        );
+    }
+    html.push_str("<table>\n<tr><th>Column</th><th>Type</th><th>Description</th></tr>\n");
+    for column in &source.columns {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td><code>{}</code></td><td>{}</td></tr>",
+            escape(&column.name),
+            escape(&column.column_type),
+            escape(&column.description)
+        );
+    }
+    html.push_str("</table>\n</section>\n");
+}
+
+fn render_exposure(html: &mut String, exposure: &ExposureDoc) {
+    let _ = writeln!(
+        html,
+        "<section id=\"exposure-{0}\">\n<h2>{0} <span class=\"meta\">({1})</span></h2>",
+        escape(&exposure.name),
+        exposure.exposure_type
+    );
+    let mut meta = Vec::new();
+    if let Some(owner) = &exposure.owner {
+        meta.push(format!("owner: {}", escape(owner)));
+    }
+    if let Some(url) = &exposure.url {
+        meta.push(format!("<a href=\"{0}\">{0}</a>", escape(url)));
+    }
+    if !meta.is_empty() {
+        let _ = writeln!(html, "<p class=\"meta\">{}</p>", meta.join(" · "));
+    }
+    if !exposure.description.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"description\">{}</p>",
+            escape(&exposure.description)
+        );
+    }
+    // Sources have their own sections, keyed by the qualified name
+    let links = exposure
+        .depends_on
+        .iter()
+        .map(|n| {
+            let kind = if n.contains('.') { "source" } else { "model" };
+            format!("<a href=\"#{0}-{1}\">{1}</a>", kind, escape(n))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let _ = writeln!(html, "<p>Depends on: {}</p>\n</section>", links);
+}
+
+/// Render the model DAG as an SVG, one column per dependency depth.
+/// Nodes link to the model's section.
+fn render_dag(models: &[ModelDoc]) -> String {
+    let by_name: HashMap<&str, &ModelDoc> = models.iter().map(|m| (m.name.as_str(), m)).collect();
+
+    // Depth = longest path from a root, so every edge points rightwards
+    fn depth<'a>(
+        name: &'a str,
+        by_name: &HashMap<&'a str, &'a ModelDoc>,
+        memo: &mut HashMap<&'a str, usize>,
+        visiting: &mut Vec<&'a str>,
+    ) -> usize {
+        if let Some(&d) = memo.get(name) {
+            return d;
+        }
+        if visiting.contains(&name) {
+            // Cycles are reported by graph validation; don't recurse forever here
+            return 0;
+        }
+        visiting.push(name);
+        let d = by_name
+            .get(name)
+            .map(|m| {
+                m.depends_on
+                    .iter()
+                    .filter(|dep| by_name.contains_key(dep.as_str()))
+                    .map(|dep| depth(dep, by_name, memo, visiting) + 1)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .unwrap_or(0);
+        visiting.pop();
+        memo.insert(name, d);
+        d
+    }
+
+    let mut memo = HashMap::new();
+    let mut layers: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for model in models {
+        let d = depth(&model.name, &by_name, &mut memo, &mut Vec::new());
+        layers.entry(d).or_default().push(&model.name);
+    }
+
+    let mut positions: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (layer, names) in &layers {
+        for (row, name) in names.iter().enumerate() {
+            positions.insert(
+                name,
+                (
+                    layer * (NODE_WIDTH + LAYER_GAP) + 10,
+                    row * (NODE_HEIGHT + ROW_GAP) + 10,
+                ),
+            );
+        }
+    }
+
+    let width = layers.len() * (NODE_WIDTH + LAYER_GAP) + 20;
+    let height = layers.values().map(Vec::len).max().unwrap_or(0) * (NODE_HEIGHT + ROW_GAP) + 20;
+
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">\n<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"#999\"/></marker></defs>\n",
+        width, height
+    );
+
+    for model in models {
+        let Some(&(x2, y2)) = positions.get(model.name.as_str()) else {
+            continue;
+        };
+        for dep in &model.depends_on {
+            if let Some(&(x1, y1)) = positions.get(dep.as_str()) {
+                let (sx, sy) = (x1 + NODE_WIDTH, y1 + NODE_HEIGHT / 2);
+                let (ex, ey) = (x2, y2 + NODE_HEIGHT / 2);
+                let mid = (sx + ex) / 2;
+                let _ = writeln!(
+                    svg,
+                    "<path d=\"M{sx},{sy} C{mid},{sy} {mid},{ey} {ex},{ey}\"/>"
+                );
+            }
+        }
+    }
+
+    for model in models {
+        let (x, y) = positions[model.name.as_str()];
+        let name = escape(&model.name);
+        let _ = writeln!(
+            svg,
+            "<a href=\"#model-{name}\"><title>{name}</title><rect x=\"{x}\" y=\"{y}\" width=\"{NODE_WIDTH}\" height=\"{NODE_HEIGHT}\"/><text x=\"{}\" y=\"{}\">{name}</text></a>",
+            x + NODE_WIDTH / 2,
+            y + NODE_HEIGHT / 2
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{ModelFile, RefInfo};
+    use smelt_backend_duckdb::DuckDbBackend;
+    use tempfile::TempDir;
+
+    fn make_model(name: &str, content: &str) -> ModelFile {
+        let parse = smelt_parser::parse(content);
+        let file = smelt_parser::File::cast(parse.syntax()).unwrap();
+        let refs = file
+            .refs()
+            .filter_map(|r| {
+                Some(RefInfo {
+                    model_name: r.model_name()?,
+                    has_named_params: false,
+                    range: r.range(),
+                })
+            })
+            .collect();
+
+        ModelFile {
+            name: name.to_string(),
+            path: PathBuf::from("/project/models").join(format!("{}.sql", name)),
+            content: content.to_string(),
+            refs,
+            parse_errors: Vec::new(),
+            metadata: extract_file_metadata(content).ok().and_then(|m| match m {
+                FileMetadata::Single { metadata, .. } => Some(metadata),
+                _ => None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_leading_comment() {
+        assert_eq!(
+            leading_comment("\n-- Daily revenue\n-- by user\nSELECT 1").as_deref(),
+            Some("Daily revenue\nby user")
+        );
+        assert_eq!(
+            leading_comment("---\nmaterialization: table\n---\n-- After frontmatter\nSELECT 1")
+                .as_deref(),
+            Some("After frontmatter")
+        );
+        assert_eq!(leading_comment("SELECT 1 -- trailing"), None);
+    }
+
+    #[test]
+    fn test_build_bundle() {
+        let mut users = make_model("users", "-- All users\nSELECT id, name FROM raw.users");
+        users.metadata = Some(Box::new(
+            serde_yaml::from_str("columns: [{name: id, description: User id}]").unwrap(),
+        ));
+        let models = vec![
+            users,
+            make_model(
+                "user_names",
+                "SELECT u.name AS user_name FROM smelt.ref('users') u",
+            ),
+            make_model(
+                "orders",
+                "---\ndescription: Every order\ntags: [core]\n---\n-- Ignored comment\nSELECT 1",
+            ),
+        ];
+        let exposures = serde_yaml::from_str(
+            "version: 1\nexposures:\n  user_dashboard:\n    type: dashboard\n    \
+             url: https://bi.example.com/users\n    depends_on: [user_names]\n",
+        )
+        .unwrap();
+        let graph = DependencyGraph::build(models, None)
+            .unwrap()
+            .with_exposures(Some(&exposures));
+        let bundle = DocsBundle::build(
+            &graph,
+            &Config::for_test("shop"),
+            None,
+            Path::new("/project"),
+        );
+
+        let names: Vec<_> = bundle.models.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["orders", "user_names", "users"]);
+
+        let orders = &bundle.models[0];
+        assert_eq!(orders.description.as_deref(), Some("Every order"));
+        assert_eq!(orders.tags, vec!["core"]);
+
+        let user_names = &bundle.models[1];
+        assert_eq!(user_names.depends_on, vec!["users"]);
+        assert_eq!(user_names.columns[0].name, "user_name");
+
+        let users = &bundle.models[2];
+        assert_eq!(users.description.as_deref(), Some("All users"));
+        assert_eq!(users.referenced_by, vec!["user_names"]);
+        assert_eq!(users.exposures, vec!["user_dashboard"]);
+        assert!(orders.exposures.is_empty());
+        assert_eq!(bundle.exposures[0].depends_on, vec!["user_names"]);
+        assert_eq!(users.path, PathBuf::from("models/users.sql"));
+        assert_eq!(
+            users
+                .columns
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["id", "name"]
+        );
+        assert_eq!(users.columns[0].description.as_deref(), Some("User id"));
+        assert_eq!(users.columns[1].description, None);
+
+        let html = render_html(&bundle);
+        assert!(html.contains("<section id=\"model-users\">"));
+        assert!(html.contains("<a href=\"#model-user_names\">"));
+        assert!(html.contains("<path d="));
+        assert!(html.contains("<span class=\"meta\">User id</span>"));
+        assert!(html.contains("<section id=\"exposure-user_dashboard\">"));
+        assert!(html.contains("<a href=\"https://bi.example.com/users\">"));
+    }
+
+    #[tokio::test]
+    async fn test_add_catalog_types() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.users AS SELECT 1::BIGINT AS id, 'a' AS name")
+            .await
+            .unwrap();
+
+        let models = vec![
+            make_model("users", "SELECT id, name FROM raw.users"),
+            make_model("orders", "SELECT 1 AS id"),
+        ];
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let mut bundle = DocsBundle::build(
+            &graph,
+            &Config::for_test("shop"),
+            None,
+            Path::new("/project"),
+        );
+
+        let found = bundle
+            .add_catalog_types(&backend, |model| RelationName::new("main", model))
+            .await
+            .unwrap();
+        assert_eq!(found, 1);
+
+        let users = bundle.models.iter().find(|m| m.name == "users").unwrap();
+        let types: Vec<_> = users
+            .columns
+            .iter()
+            .map(|c| c.data_type.as_deref())
+            .collect();
+        assert_eq!(types, vec![Some("BIGINT"), Some("VARCHAR")]);
+
+        let orders = bundle.models.iter().find(|m| m.name == "orders").unwrap();
+        assert_eq!(orders.columns[0].data_type, None);
+
+        assert!(render_html(&bundle).contains("<th>Type</th>"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a < b && \"c\""),
+            "a &lt; b &amp;&amp; &quot;c&quot;"
+        );
+    }
+}
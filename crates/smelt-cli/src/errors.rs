@@ -1,4 +1,6 @@
+use crate::template::TemplateError;
 use rowan::TextRange;
+use serde::Serialize;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -28,6 +30,38 @@ pub enum CliError {
         source: anyhow::Error,
     },
 
+    #[error("Model '{model}' has SQL the target rejects:\n  {message}{}", line.as_ref().map(|(n, sql)| format!("\n\n  --> compiled SQL line {}\n   | {}", n, sql)).unwrap_or_default())]
+    InvalidSql {
+        model: String,
+        message: String,
+        /// 1-based line of the compiled SQL the error points at, and its text
+        line: Option<(usize, String)>,
+    },
+
+    #[error("Model '{model}' timed out after {timeout_seconds}s\n\nHint: Raise its `timeout_seconds` in smelt.yml")]
+    ModelTimeout { model: String, timeout_seconds: u64 },
+
+    #[error("Model '{model}' was cancelled")]
+    Cancelled { model: String },
+
+    /// Ctrl+C during a run; running statements were cancelled
+    #[error("Interrupted after {succeeded} succeeded ({} failed or cancelled, {} not run)", failed.len(), skipped.len())]
+    Interrupted {
+        failed: Vec<String>,
+        skipped: Vec<String>,
+        succeeded: usize,
+    },
+
+    #[error("Statement {index} of operation '{operation}' failed:\n  {source}\n\nSQL:\n{sql}")]
+    OperationError {
+        operation: String,
+        /// 1-based position of the statement
+        index: usize,
+        sql: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
     #[error("Dependency resolution failed:\n  {message}")]
     DependencyError { message: String },
 
@@ -45,6 +79,48 @@ pub enum CliError {
     #[error("Source tables not found in database:\n  {}\n\nHint: Create source tables manually or use 'smelt seed' command", missing.join("\n  "))]
     SourceTablesNotFound { missing: Vec<String> },
 
+    #[error("Incremental strategies the target doesn't support:\n  {}\n\nHint: Use delete+insert for these models, or run them against a backend that supports the strategy", models.join("\n  "))]
+    UnsupportedStrategies { models: Vec<String> },
+
+    #[error("`timeout_seconds` can't be enforced on this target, which can't cancel running statements:\n  {}\n\nHint: Remove `timeout_seconds` for these models, or run them against a backend that supports cancellation", models.join("\n  "))]
+    UnsupportedTimeouts { models: Vec<String> },
+
+    #[error("`notifications` in smelt.yml can't be used with `smelt run --watch`, which rebuilds models until stopped instead of finishing a run\n\nHint: Run without --watch, or remove `notifications` while developing locally")]
+    NotificationsWithWatch,
+
+    #[error("{kind} {index} for model '{model}' failed:\n  {source}\n\nSQL:\n{sql}")]
+    HookError {
+        model: String,
+        /// "pre-hook" or "post-hook"
+        kind: &'static str,
+        /// 1-based position of the hook
+        index: usize,
+        sql: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("Model '{model}' violates its contract:\n  {}\n\nHint: Update the model's SQL or its `contract` in smelt.yml", violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n  "))]
+    ContractViolation {
+        model: String,
+        violations: Vec<crate::contract::ContractViolation>,
+    },
+
+    #[error("Seed '{seed}' failed to load:\n  {source}\n\nHint: Use --full-refresh if the CSV columns have changed")]
+    SeedError {
+        seed: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    #[error("Program for model '{model}' failed:\n  {source}\n\nCommand: {command}")]
+    ProgramError {
+        model: String,
+        command: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
     #[error("Model '{model}' uses named parameters which are not yet supported\n\n  --> {file}:{line}:{col}\n   |\n{snippet}\n   |\n   = note: Named parameters will be supported in a future release\n   = help: For now, use: FROM smelt.ref('model_name') without parameters")]
     NamedParametersNotSupported {
         model: String,
@@ -53,6 +129,124 @@ pub enum CliError {
         col: u32,
         snippet: String,
     },
+
+    #[error("{} of {} models failed: {}", failed.len(), failed.len() + skipped.len() + succeeded, failed.join(", "))]
+    RunFailed {
+        failed: Vec<String>,
+        skipped: Vec<String>,
+        succeeded: usize,
+    },
+
+    #[error("Backfill of '{model}' failed for {} of {total} chunks ({succeeded} succeeded); resume with --from {resume_from}", failed.len())]
+    BackfillFailed {
+        model: String,
+        /// Failed chunks as `start → end`
+        failed: Vec<String>,
+        succeeded: usize,
+        total: usize,
+        /// Start of the earliest failed chunk
+        resume_from: String,
+    },
+
+    /// Contract, SQL, or source freshness checks that ran but didn't pass
+    #[error("{message}")]
+    ChecksFailed { message: String },
+
+    #[error("Validation found {errors} error(s) and {warnings} warning(s)")]
+    ValidationFailed { errors: usize, warnings: usize },
+
+    #[error("Lint found {errors} error(s) and {warnings} warning(s)")]
+    LintFailed { errors: usize, warnings: usize },
+
+    #[error("Model '{model}' differs between targets '{target_a}' and '{target_b}'")]
+    DiffFound {
+        model: String,
+        target_a: String,
+        target_b: String,
+    },
+}
+
+impl CliError {
+    pub fn outcome(&self) -> Outcome {
+        match self {
+            CliError::CompilationError { .. }
+            | CliError::DependencyError { .. }
+            | CliError::ParseError { .. }
+            | CliError::CircularDependency { .. }
+            | CliError::NamedParametersNotSupported { .. }
+            | CliError::InvalidSql { .. }
+            | CliError::ValidationFailed { .. }
+            | CliError::LintFailed { .. } => Outcome::CompileError,
+            CliError::ExecutionError { .. }
+            | CliError::HookError { .. }
+            | CliError::ProgramError { .. }
+            | CliError::ModelTimeout { .. } => Outcome::ExecutionError,
+            CliError::Cancelled { .. } | CliError::Interrupted { .. } => Outcome::Interrupted,
+            CliError::RunFailed { succeeded: 0, .. } => Outcome::ExecutionError,
+            CliError::RunFailed { .. } => Outcome::PartialSuccess,
+            CliError::BackfillFailed { succeeded: 0, .. } => Outcome::ExecutionError,
+            CliError::BackfillFailed { .. } => Outcome::PartialSuccess,
+            CliError::ContractViolation { .. }
+            | CliError::ChecksFailed { .. }
+            | CliError::DiffFound { .. } => Outcome::TestFailure,
+            CliError::ProjectRootNotFound
+            | CliError::ConfigLoadError { .. }
+            | CliError::OperationError { .. }
+            | CliError::SourceTablesNotFound { .. }
+            | CliError::UnsupportedStrategies { .. }
+            | CliError::UnsupportedTimeouts { .. }
+            | CliError::NotificationsWithWatch
+            | CliError::SeedError { .. } => Outcome::Error,
+        }
+    }
+}
+
+/// How a command ended, reported as its exit code so CI can branch on it.
+///
+/// Clap exits with 2 for invalid arguments, so that code isn't used here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    /// Configuration, connection, and other errors
+    Error,
+    /// Models failed to parse, compile, or resolve their dependencies
+    CompileError,
+    /// No model that ran succeeded
+    ExecutionError,
+    /// Contracts or source freshness checks failed, or `smelt diff` found differences
+    TestFailure,
+    /// Some models succeeded while others failed or were skipped
+    PartialSuccess,
+    /// Stopped by Ctrl+C
+    Interrupted,
+}
+
+impl Outcome {
+    /// Classify a command's result by the outermost [`CliError`] in its chain.
+    pub fn of(result: &anyhow::Result<()>) -> Self {
+        match result {
+            Ok(()) => Outcome::Success,
+            Err(e) => match e.downcast_ref::<CliError>() {
+                Some(error) => error.outcome(),
+                None if e.downcast_ref::<TemplateError>().is_some() => Outcome::CompileError,
+                None => Outcome::Error,
+            },
+        }
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Outcome::Success => 0,
+            Outcome::Error => 1,
+            Outcome::CompileError => 3,
+            Outcome::ExecutionError => 4,
+            Outcome::TestFailure => 5,
+            Outcome::PartialSuccess => 6,
+            // 128 + SIGINT, as shells report a process killed by Ctrl+C
+            Outcome::Interrupted => 130,
+        }
+    }
 }
 
 /// Helper to convert TextRange to line/column for error messages
@@ -95,3 +289,73 @@ pub fn extract_snippet(text: &str, range: TextRange, context_lines: usize) -> St
 
     snippet_lines.join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_outcome() {
+        let execution_error = || -> anyhow::Error {
+            CliError::ExecutionError {
+                model: "orders".to_string(),
+                sql: "SELECT 1".to_string(),
+                source: anyhow::anyhow!("table not found"),
+            }
+            .into()
+        };
+        let run_failed = |succeeded| CliError::RunFailed {
+            failed: vec!["orders".to_string()],
+            skipped: vec!["revenue".to_string()],
+            succeeded,
+        };
+
+        assert_eq!(Outcome::of(&Ok(())), Outcome::Success);
+        assert_eq!(
+            Outcome::of(&Err(anyhow::anyhow!("connection refused"))),
+            Outcome::Error
+        );
+        assert_eq!(
+            Outcome::of(&Err(execution_error())),
+            Outcome::ExecutionError
+        );
+
+        // The outermost CliError wins
+        let partial = execution_error().context(run_failed(2));
+        assert_eq!(partial.to_string(), "1 of 4 models failed: orders");
+        assert_eq!(Outcome::of(&Err(partial)), Outcome::PartialSuccess);
+        let all_failed: anyhow::Result<()> = Err(execution_error().context(run_failed(0)));
+        assert_eq!(Outcome::of(&all_failed), Outcome::ExecutionError);
+
+        let cycle: anyhow::Result<()> = Err(CliError::CircularDependency {
+            models: "a, b".to_string(),
+        }
+        .into());
+        // ...even through plain-string context
+        let cycle = cycle.with_context(|| "Failed to build dependency graph");
+        assert_eq!(Outcome::of(&cycle), Outcome::CompileError);
+
+        let template: anyhow::Result<()> = Err(TemplateError::UndefinedVar("x".to_string()).into());
+        assert_eq!(
+            Outcome::of(&template.context("Failed to render model")),
+            Outcome::CompileError
+        );
+        assert_eq!(Outcome::TestFailure.exit_code(), 5);
+
+        let cancelled = anyhow::Error::from(CliError::Cancelled {
+            model: "orders".to_string(),
+        });
+        let interrupted: anyhow::Result<()> = Err(cancelled.context(CliError::Interrupted {
+            failed: vec!["orders".to_string()],
+            skipped: vec!["revenue".to_string()],
+            succeeded: 2,
+        }));
+        assert_eq!(
+            interrupted.as_ref().unwrap_err().to_string(),
+            "Interrupted after 2 succeeded (1 failed or cancelled, 1 not run)"
+        );
+        assert_eq!(Outcome::of(&interrupted), Outcome::Interrupted);
+        assert_eq!(Outcome::Interrupted.exit_code(), 130);
+    }
+}
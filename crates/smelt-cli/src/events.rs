@@ -0,0 +1,215 @@
+//! Structured run events for `smelt run --log-format json`.
+//!
+//! Each event is written to stdout as a single JSON line with an `event` tag
+//! and an RFC 3339 `timestamp`, so orchestrators can follow a run as it
+//! happens rather than waiting for `run_results.json`.
+
+use crate::artifacts::{NodeResult, RunStatus};
+use crate::errors::{CliError, Outcome};
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    RunStart {
+        project: String,
+        target: String,
+        model_count: usize,
+    },
+    ModelStart {
+        model: String,
+        incremental: bool,
+    },
+    ModelSuccess {
+        model: String,
+        execution_time_secs: f64,
+        row_count: usize,
+    },
+    ModelError {
+        model: String,
+        execution_time_secs: f64,
+        message: String,
+    },
+    /// A transient error; the model will be run again after `delay_secs`
+    ModelRetry {
+        model: String,
+        attempt: u32,
+        delay_secs: f64,
+        message: String,
+    },
+    /// Not executed because of an earlier failure
+    ModelSkipped {
+        model: String,
+        reason: String,
+    },
+    RunEnd {
+        elapsed_secs: f64,
+        success: usize,
+        error: usize,
+        skipped: usize,
+    },
+    /// Always the last event: how the command ended and its exit code
+    RunSummary {
+        outcome: Outcome,
+        exit_code: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        failed: Vec<String>,
+        skipped: Vec<String>,
+    },
+}
+
+impl RunEvent {
+    /// The completion event for a model's result.
+    pub fn from_result(result: &NodeResult) -> Self {
+        let model = result.name.clone();
+        match result.status {
+            RunStatus::Success => RunEvent::ModelSuccess {
+                model,
+                execution_time_secs: result.execution_time_secs,
+                row_count: result.row_count.unwrap_or(0),
+            },
+            RunStatus::Error => RunEvent::ModelError {
+                model,
+                execution_time_secs: result.execution_time_secs,
+                message: result.message.clone().unwrap_or_default(),
+            },
+            RunStatus::Skipped => RunEvent::ModelSkipped {
+                model,
+                reason: result.message.clone().unwrap_or_default(),
+            },
+        }
+    }
+
+    /// The end-of-run event summarizing all model results.
+    pub fn run_end(results: &[NodeResult], elapsed: Duration) -> Self {
+        let count = |status| results.iter().filter(|r| r.status == status).count();
+        RunEvent::RunEnd {
+            elapsed_secs: elapsed.as_secs_f64(),
+            success: count(RunStatus::Success),
+            error: count(RunStatus::Error),
+            skipped: count(RunStatus::Skipped),
+        }
+    }
+
+    /// The final summary of a command's result.
+    pub fn run_summary(result: &anyhow::Result<()>) -> Self {
+        let outcome = Outcome::of(result);
+        let error = result.as_ref().err();
+        let (failed, skipped) = match error.and_then(|e| e.downcast_ref::<CliError>()) {
+            Some(
+                CliError::RunFailed {
+                    failed, skipped, ..
+                }
+                | CliError::Interrupted {
+                    failed, skipped, ..
+                },
+            ) => (failed.clone(), skipped.clone()),
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        RunEvent::RunSummary {
+            outcome,
+            exit_code: outcome.exit_code(),
+            message: error.map(ToString::to_string),
+            failed,
+            skipped,
+        }
+    }
+
+    /// Serialize as a single timestamped JSON line.
+    pub fn to_json_line(&self) -> String {
+        #[derive(Serialize)]
+        struct Line<'a> {
+            timestamp: String,
+            #[serde(flatten)]
+            event: &'a RunEvent,
+        }
+
+        serde_json::to_string(&Line {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            event: self,
+        })
+        .expect("run events always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_lines() {
+        let results = vec![
+            NodeResult {
+                name: "users".to_string(),
+                status: RunStatus::Success,
+                execution_time_secs: 0.5,
+                row_count: Some(42),
+                message: None,
+                stats: None,
+                previous_row_count: None,
+            },
+            NodeResult::error(
+                "orders",
+                Duration::from_secs(1),
+                &anyhow::anyhow!("table not found"),
+            ),
+            NodeResult::skipped("revenue", "upstream model 'orders' failed"),
+        ];
+
+        let line: serde_json::Value =
+            serde_json::from_str(&RunEvent::from_result(&results[0]).to_json_line()).unwrap();
+        assert_eq!(line["event"], "model_success");
+        assert_eq!(line["model"], "users");
+        assert_eq!(line["row_count"], 42);
+        assert!(line["timestamp"].is_string());
+
+        let line: serde_json::Value =
+            serde_json::from_str(&RunEvent::from_result(&results[1]).to_json_line()).unwrap();
+        assert_eq!(line["event"], "model_error");
+        assert_eq!(line["message"], "table not found");
+
+        assert_eq!(
+            RunEvent::run_end(&results, Duration::from_secs(2)),
+            RunEvent::RunEnd {
+                elapsed_secs: 2.0,
+                success: 1,
+                error: 1,
+                skipped: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let result = Err(
+            anyhow::anyhow!("table not found").context(CliError::RunFailed {
+                failed: vec!["orders".to_string()],
+                skipped: vec!["revenue".to_string()],
+                succeeded: 1,
+            }),
+        );
+
+        let line: serde_json::Value =
+            serde_json::from_str(&RunEvent::run_summary(&result).to_json_line()).unwrap();
+        assert_eq!(line["event"], "run_summary");
+        assert_eq!(line["outcome"], "partial_success");
+        assert_eq!(line["exit_code"], 6);
+        assert_eq!(line["failed"], serde_json::json!(["orders"]));
+        assert_eq!(line["skipped"], serde_json::json!(["revenue"]));
+        assert_eq!(line["message"], "1 of 3 models failed: orders");
+
+        assert_eq!(
+            RunEvent::run_summary(&Ok(())),
+            RunEvent::RunSummary {
+                outcome: Outcome::Success,
+                exit_code: 0,
+                message: None,
+                failed: Vec::new(),
+                skipped: Vec::new(),
+            }
+        );
+    }
+}
@@ -1,32 +1,64 @@
+use crate::artifacts::{checksum, NodeResult, RunStatus};
 use crate::compiler::CompiledModel;
-use crate::config::SourceConfig;
+use crate::config::{IncrementalConfig, IncrementalStrategy, ModelContract, SourceConfig};
+use crate::contract::check_contract;
+use crate::discovery::ModelFile;
+use crate::docs::leading_comment;
 use crate::errors::CliError;
-use anyhow::Result;
+use crate::rewrite::replace_statements;
+use crate::seed::sql_type_for;
+use anyhow::{Context, Result};
+use arrow::array::TimestampMicrosecondArray;
+use arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
 use smelt_backend::{
-    Backend, ExecutionResult, Materialization, MaterializationStrategy, PartitionSpec,
+    Backend, BackendError, ExecutionResult, IncrementalStrategy as BackendIncrementalStrategy,
+    Materialization, MaterializationStrategy, PartitionSpec, RelationName,
 };
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Longest wait between retries of a model.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How long a cancelled statement gets to return before it is abandoned.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Set once a cancelled statement has been left running in the background.
+static ABANDONED: AtomicBool = AtomicBool::new(false);
 
 /// Execute a compiled model using any Backend implementation.
 pub async fn execute_model(
     backend: &dyn Backend,
     compiled: &CompiledModel,
-    schema: &str,
+    relation: &RelationName,
     show_results: bool,
 ) -> Result<ExecutionResult> {
     // Convert CLI Materialization to Backend Materialization
     let materialization = match compiled.materialization {
         crate::config::Materialization::Table => Materialization::Table,
         crate::config::Materialization::View => Materialization::View,
+        crate::config::Materialization::Ephemeral => {
+            return Err(anyhow::anyhow!(
+                "{} is ephemeral; it is inlined into downstream models, not materialized",
+                compiled.name
+            ))
+        }
+        crate::config::Materialization::External => {
+            return Err(anyhow::anyhow!(
+                "{} is external; it is written to Parquet, not materialized as a table or view",
+                compiled.name
+            ))
+        }
     };
 
     backend
-        .execute_model(
-            schema,
-            &compiled.name,
-            &compiled.sql,
-            materialization,
-            show_results,
-        )
+        .execute_model(relation, &compiled.sql, materialization, show_results)
         .await
         .map_err(|e| {
             CliError::ExecutionError {
@@ -38,17 +70,93 @@ pub async fn execute_model(
         })
 }
 
-/// Execute a compiled model incrementally using DELETE+INSERT pattern.
+/// Execute a compiled external model, writing its output to Parquet at
+/// `location` and creating a view over it.
+pub async fn execute_model_external(
+    backend: &dyn Backend,
+    compiled: &CompiledModel,
+    relation: &RelationName,
+    location: &str,
+    show_results: bool,
+) -> Result<ExecutionResult> {
+    backend
+        .execute_model_external(relation, &compiled.sql, location, show_results)
+        .await
+        .map_err(|e| {
+            CliError::ExecutionError {
+                model: compiled.name.clone(),
+                sql: compiled.sql.clone(),
+                source: e.into(),
+            }
+            .into()
+        })
+}
+
+/// What the backend made of a model's compiled SQL without running it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlCheck {
+    Valid,
+    /// The SQL reads a table or view that doesn't exist yet, such as an
+    /// upstream model that hasn't been built, so it couldn't be checked
+    Unchecked(String),
+}
+
+/// Ask the backend to plan a compiled model's SQL (`EXPLAIN`), catching
+/// syntax and dialect errors before a real run.
+///
+/// Fails with [`CliError::InvalidSql`], pointing at the offending line when
+/// the backend's message names one.
+pub async fn check_sql(backend: &dyn Backend, compiled: &CompiledModel) -> Result<SqlCheck> {
+    match backend.explain(&compiled.sql, false).await {
+        Ok(_) => Ok(SqlCheck::Valid),
+        Err(e) if e.is_missing_relation() => {
+            let message = e.to_string();
+            let reason = message.lines().next().unwrap_or_default();
+            Ok(SqlCheck::Unchecked(reason.to_string()))
+        }
+        Err(e) => {
+            let message = e.to_string();
+            let line = error_line(&message).and_then(|n| {
+                let sql = compiled.sql.lines().nth(n.checked_sub(1)?)?;
+                Some((n, sql.trim_end().to_string()))
+            });
+            Err(CliError::InvalidSql {
+                model: compiled.name.clone(),
+                message,
+                line,
+            }
+            .into())
+        }
+    }
+}
+
+/// The 1-based line a backend error message points at, as in DuckDB's
+/// `LINE 3:`, Snowflake's `line 3 at position 7`, or Spark's `(line 3, pos 7)`.
+fn error_line(message: &str) -> Option<usize> {
+    let lower = message.to_lowercase();
+    lower.match_indices("line ").find_map(|(i, m)| {
+        let digits: String = lower[i + m.len()..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        digits.parse().ok().filter(|&n| n > 0)
+    })
+}
+
+/// Execute a compiled model incrementally using the model's incremental strategy.
 ///
 /// This function:
-/// 1. Deletes existing rows for the specified partitions
-/// 2. Inserts new rows from the (filtered) SQL query
-/// 3. Auto-creates the table on first run if it doesn't exist
+/// 1. Writes new rows from the (filtered) SQL query into the specified partitions,
+///    by delete+insert, merge on `unique_key`, or insert overwrite
+/// 2. Auto-creates the table on first run if it doesn't exist
+///
+/// Fails if the backend doesn't support the strategy.
 pub async fn execute_model_incremental(
     backend: &dyn Backend,
     compiled: &CompiledModel,
-    schema: &str,
+    relation: &RelationName,
     partition: PartitionSpec,
+    incremental: &IncrementalConfig,
     show_results: bool,
 ) -> Result<ExecutionResult> {
     // Views can't be incremental - warn and use full refresh
@@ -60,15 +168,27 @@ pub async fn execute_model_incremental(
             "  Warning: {} is a view, using full refresh (views cannot be incremental)",
             compiled.name
         );
-        return execute_model(backend, compiled, schema, show_results).await;
+        return execute_model(backend, compiled, relation, show_results).await;
     }
 
-    let strategy = MaterializationStrategy::Incremental { partition };
+    if matches!(
+        compiled.materialization,
+        crate::config::Materialization::External
+    ) {
+        return Err(anyhow::anyhow!(
+            "{} is external; external models are rewritten in full and can't be incremental",
+            compiled.name
+        ));
+    }
+
+    let strategy = MaterializationStrategy::Incremental {
+        partition,
+        strategy: backend_strategy(incremental),
+    };
 
     backend
         .execute_model_incremental(
-            schema,
-            &compiled.name,
+            relation,
             &compiled.sql,
             Materialization::Table,
             strategy,
@@ -85,22 +205,430 @@ pub async fn execute_model_incremental(
         })
 }
 
+/// Whether a model failure was caused by a transient backend error.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<BackendError>())
+        .any(BackendError::is_transient)
+}
+
+/// Exponential backoff before retry `attempt` (1-based): 1s, 2s, 4s, ... capped at 30s.
+pub fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.saturating_sub(1).min(16)).min(MAX_RETRY_DELAY)
+}
+
+/// Run a model's backend work until it finishes, `cancel` fires, or `timeout`
+/// elapses.
+///
+/// When stopped early, the backend's running statements are cancelled and
+/// given a moment to return; backends that can't interrupt them (see
+/// `supports_cancel`) leave them running in the background.
+pub async fn run_cancellable<T>(
+    backend: &dyn Backend,
+    model: &str,
+    timeout: Option<Duration>,
+    cancel: &CancellationToken,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    if cancel.is_cancelled() {
+        return Err(CliError::Cancelled {
+            model: model.to_string(),
+        }
+        .into());
+    }
+
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let mut work = std::pin::pin!(work);
+    let error = tokio::select! {
+        result = &mut work => return result,
+        _ = cancel.cancelled() => CliError::Cancelled {
+            model: model.to_string(),
+        },
+        _ = deadline => CliError::ModelTimeout {
+            model: model.to_string(),
+            timeout_seconds: timeout.unwrap_or_default().as_secs(),
+        },
+    };
+
+    let cancelled = match backend.cancel().await {
+        Ok(()) => backend.capabilities().supports_cancel,
+        Err(e) => {
+            eprintln!(
+                "  Warning: failed to cancel statements for {}: {}",
+                model, e
+            );
+            false
+        }
+    };
+    // Let the interrupted statement unwind so the connection is free again
+    if !cancelled
+        || tokio::time::timeout(CANCEL_GRACE_PERIOD, work)
+            .await
+            .is_err()
+    {
+        ABANDONED.store(true, Ordering::Relaxed);
+    }
+    Err(error.into())
+}
+
+/// Whether [`run_cancellable`] has left a statement running in the
+/// background, in which case the process shouldn't wait for it on exit.
+pub fn abandoned_statements() -> bool {
+    ABANDONED.load(Ordering::Relaxed)
+}
+
+/// Which side of model execution a hook runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    Pre,
+    Post,
+}
+
+impl HookKind {
+    fn label(self) -> &'static str {
+        match self {
+            HookKind::Pre => "pre-hook",
+            HookKind::Post => "post-hook",
+        }
+    }
+}
+
+/// Run hook statements in order, stopping at the first failure.
+///
+/// `{{ this }}` is replaced with the model's relation, quoted for the backend. Returns the number of hooks run.
+pub async fn run_hooks(
+    backend: &dyn Backend,
+    relation: &RelationName,
+    hooks: &[String],
+    kind: HookKind,
+) -> Result<usize> {
+    let this = backend.dialect().quote_relation(relation);
+
+    let capabilities = backend.capabilities();
+    for (i, hook) in hooks.iter().enumerate() {
+        for sql in replace_statements(&render_hook(hook, &this), &capabilities) {
+            backend
+                .execute_sql(&sql)
+                .await
+                .map_err(|e| CliError::HookError {
+                    model: relation.name.clone(),
+                    kind: kind.label(),
+                    index: i + 1,
+                    sql: sql.clone(),
+                    source: e.into(),
+                })?;
+        }
+    }
+
+    Ok(hooks.len())
+}
+
+fn render_hook(hook: &str, this: &str) -> String {
+    hook.replace("{{ this }}", this).replace("{{this}}", this)
+}
+
+/// Store a model's description and column descriptions as comments on its
+/// table or view, where the backend supports comments.
+///
+/// The description comes from frontmatter `description:` or the model's
+/// leading `--` comment block; column descriptions from frontmatter
+/// `columns:`. Returns the number of comments set.
+pub async fn apply_comments(
+    backend: &dyn Backend,
+    model: &ModelFile,
+    relation: &RelationName,
+    materialization: &crate::config::Materialization,
+) -> Result<usize> {
+    let materialization = match materialization {
+        crate::config::Materialization::Table => Materialization::Table,
+        crate::config::Materialization::View | crate::config::Materialization::External => {
+            Materialization::View
+        }
+        crate::config::Materialization::Ephemeral => return Ok(0),
+    };
+    if !backend.capabilities().supports_comments {
+        return Ok(0);
+    }
+
+    let metadata = model.metadata.as_deref();
+    let description = metadata
+        .and_then(|m| m.description.clone())
+        .or_else(|| leading_comment(&model.content));
+    let columns: Vec<(String, String)> = metadata
+        .map(|m| m.columns.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter(|c| !c.description.is_empty())
+        .map(|c| (c.name.clone(), c.description.clone()))
+        .collect();
+
+    backend
+        .set_comments(relation, materialization, description.as_deref(), &columns)
+        .await
+        .with_context(|| format!("Failed to set comments on {}", relation))?;
+
+    Ok(usize::from(description.is_some()) + columns.len())
+}
+
+/// Make the grants on a built model match its `grants:` config.
+///
+/// Fails as unsupported on backends without grants; callers check
+/// `supports_grants` first to skip them with a warning instead.
+pub async fn apply_grants(
+    backend: &dyn Backend,
+    relation: &RelationName,
+    materialization: &crate::config::Materialization,
+    grants: &BTreeMap<String, Vec<String>>,
+) -> Result<()> {
+    let materialization = match materialization {
+        crate::config::Materialization::Table => Materialization::Table,
+        crate::config::Materialization::View | crate::config::Materialization::External => {
+            Materialization::View
+        }
+        crate::config::Materialization::Ephemeral => return Ok(()),
+    };
+
+    backend
+        .apply_grants(relation, materialization, grants)
+        .await
+        .with_context(|| format!("Failed to apply grants on {}", relation))
+}
+
+/// Check a materialized model's columns against its contract.
+pub async fn enforce_contract(
+    backend: &dyn Backend,
+    relation: &RelationName,
+    contract: &ModelContract,
+) -> Result<()> {
+    let columns = backend
+        .get_table_schema(relation)
+        .await
+        .with_context(|| format!("Failed to read columns of {}", relation))?;
+
+    let violations = check_contract(contract, &columns);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ContractViolation {
+            model: relation.name.clone(),
+            violations,
+        }
+        .into())
+    }
+}
+
+/// Table in the target schema that each run appends its models' outcomes to.
+pub const RUN_HISTORY_TABLE: &str = "smelt_run_history";
+
+/// A model's row in [`RUN_HISTORY_TABLE`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunHistoryEntry {
+    pub model: String,
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+    pub row_count: Option<usize>,
+    pub status: RunStatus,
+    /// SHA-256 of the compiled SQL, to tell builds of changed code apart
+    pub sql_hash: Option<String>,
+}
+
+impl RunHistoryEntry {
+    pub fn new(result: &NodeResult, started_at: DateTime<Utc>, compiled_sql: Option<&str>) -> Self {
+        Self {
+            model: result.name.clone(),
+            started_at,
+            duration: Duration::from_secs_f64(result.execution_time_secs),
+            row_count: result.row_count,
+            status: result.status,
+            sql_hash: compiled_sql.map(checksum),
+        }
+    }
+}
+
+/// An identifier for a run, unique enough to group its history rows: the
+/// start time followed by a short hash of the time and process id.
+pub fn new_run_id() -> String {
+    let now = Utc::now();
+    let salt = format!("{:?}{}", now.timestamp_nanos_opt(), std::process::id());
+    format!("{}-{}", now.format("%Y%m%dT%H%M%SZ"), &checksum(&salt)[..8])
+}
+
+fn run_history_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("run_id", DataType::Utf8, false),
+        Field::new("model", DataType::Utf8, false),
+        Field::new(
+            "started_at",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        ),
+        Field::new("duration_secs", DataType::Float64, false),
+        Field::new("row_count", DataType::Int64, true),
+        Field::new("status", DataType::Utf8, false),
+        Field::new("sql_hash", DataType::Utf8, true),
+    ]))
+}
+
+/// Append a run's entries to [`RUN_HISTORY_TABLE`] in `schema`, creating the
+/// table on first use.
+pub async fn record_run_history(
+    backend: &dyn Backend,
+    schema: &str,
+    run_id: &str,
+    entries: &[RunHistoryEntry],
+) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let dialect = backend.dialect();
+    let relation = RelationName::new(schema, RUN_HISTORY_TABLE);
+    let history_schema = run_history_schema();
+
+    backend.ensure_schema(schema).await?;
+    if !backend.table_exists(&relation).await? {
+        let columns = history_schema
+            .fields()
+            .iter()
+            .map(|f| {
+                format!(
+                    "{} {}",
+                    dialect.quote_ident(f.name()),
+                    sql_type_for(f.data_type(), dialect)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let create_sql = format!(
+            "CREATE TABLE {} ({})",
+            dialect.quote_relation(&relation),
+            columns
+        );
+        backend.execute_sql(&create_sql).await?;
+    }
+
+    let status = |status: RunStatus| match status {
+        RunStatus::Success => "success",
+        RunStatus::Error => "error",
+        RunStatus::Skipped => "skipped",
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec![run_id; entries.len()])),
+        Arc::new(StringArray::from_iter_values(
+            entries.iter().map(|e| e.model.as_str()),
+        )),
+        Arc::new(TimestampMicrosecondArray::from_iter_values(
+            entries.iter().map(|e| e.started_at.timestamp_micros()),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            entries.iter().map(|e| e.duration.as_secs_f64()),
+        )),
+        Arc::new(Int64Array::from_iter(
+            entries.iter().map(|e| e.row_count.map(|n| n as i64)),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            entries.iter().map(|e| status(e.status)),
+        )),
+        Arc::new(StringArray::from_iter(
+            entries.iter().map(|e| e.sql_hash.as_deref()),
+        )),
+    ];
+    let batch = RecordBatch::try_new(history_schema, columns)?;
+
+    backend
+        .load_record_batches(&relation, &[batch])
+        .await
+        .with_context(|| format!("Failed to record run history in {}", relation))
+}
+
+/// The backend's form of a model's incremental strategy.
+fn backend_strategy(incremental: &IncrementalConfig) -> BackendIncrementalStrategy {
+    match incremental.incremental_strategy {
+        IncrementalStrategy::DeleteInsert => BackendIncrementalStrategy::DeleteInsert,
+        IncrementalStrategy::Merge => BackendIncrementalStrategy::Merge {
+            unique_key: incremental.unique_key.clone(),
+        },
+        IncrementalStrategy::InsertOverwrite => BackendIncrementalStrategy::InsertOverwrite,
+    }
+}
+
+/// Check that the backend supports each incremental model's strategy (e.g.
+/// insert_overwrite only where partitions can be overwritten), so a run fails
+/// before building anything rather than partway through.
+pub fn check_incremental_strategies<'a>(
+    backend: &dyn Backend,
+    models: impl IntoIterator<Item = (&'a str, &'a IncrementalConfig)>,
+) -> Result<()> {
+    let unsupported: Vec<String> = models
+        .into_iter()
+        .filter_map(|(model, incremental)| {
+            let error = backend
+                .check_incremental_strategy(&backend_strategy(incremental))
+                .err()?;
+            Some(format!("{}: {}", model, error))
+        })
+        .collect();
+
+    if !unsupported.is_empty() {
+        return Err(CliError::UnsupportedStrategies {
+            models: unsupported,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Check that the backend can cancel running statements if any model has a
+/// timeout. Otherwise (DuckDB) a timed-out statement would keep running in the
+/// background, so the run fails before building anything instead.
+pub fn check_timeouts<'a>(
+    backend: &dyn Backend,
+    models: impl IntoIterator<Item = (&'a str, Option<Duration>)>,
+) -> Result<()> {
+    if backend.capabilities().supports_cancel {
+        return Ok(());
+    }
+    let unsupported: Vec<String> = models
+        .into_iter()
+        .filter_map(|(model, timeout)| {
+            Some(format!(
+                "{}: timeout_seconds: {}",
+                model,
+                timeout?.as_secs()
+            ))
+        })
+        .collect();
+
+    if !unsupported.is_empty() {
+        return Err(CliError::UnsupportedTimeouts {
+            models: unsupported,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
 /// Validate that all source tables exist in the backend.
 pub async fn validate_sources(backend: &dyn Backend, sources: &SourceConfig) -> Result<()> {
     let mut missing = Vec::new();
 
     for (schema_name, schema) in &sources.sources {
-        for table_name in schema.tables.keys() {
-            let exists = backend
-                .table_exists(schema_name, table_name)
-                .await
-                .unwrap_or(false);
+        let existing = backend.list_tables(schema_name).await.unwrap_or_default();
 
-            if !exists {
+        for table_name in schema.tables.keys() {
+            if !existing.iter().any(|t| t.eq_ignore_ascii_case(table_name)) {
                 missing.push(format!("{}.{}", schema_name, table_name));
             }
         }
     }
+    missing.sort();
 
     if !missing.is_empty() {
         return Err(CliError::SourceTablesNotFound { missing }.into());
@@ -137,7 +665,8 @@ mod tests {
             materialization: crate::config::Materialization::Table,
         };
 
-        let result = execute_model(&backend, &compiled, "main", false)
+        let relation = RelationName::new("main", &compiled.name);
+        let result = execute_model(&backend, &compiled, &relation, false)
             .await
             .unwrap();
 
@@ -159,7 +688,8 @@ mod tests {
             materialization: crate::config::Materialization::View,
         };
 
-        let result = execute_model(&backend, &compiled, "main", false)
+        let relation = RelationName::new("main", &compiled.name);
+        let result = execute_model(&backend, &compiled, &relation, false)
             .await
             .unwrap();
 
@@ -180,7 +710,8 @@ mod tests {
             materialization: crate::config::Materialization::Table,
         };
 
-        let result = execute_model(&backend, &compiled, "main", true)
+        let relation = RelationName::new("main", &compiled.name);
+        let result = execute_model(&backend, &compiled, &relation, true)
             .await
             .unwrap();
 
@@ -191,4 +722,329 @@ mod tests {
         let total_rows: usize = batches.iter().map(|b| b.num_rows()).sum();
         assert_eq!(total_rows, 3);
     }
+
+    #[tokio::test]
+    async fn test_run_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+
+        backend
+            .execute_sql("CREATE TABLE main.audit (model VARCHAR)")
+            .await
+            .unwrap();
+
+        let hooks = vec![
+            "CREATE TABLE {{ this }} AS SELECT 1 AS id".to_string(),
+            "INSERT INTO main.audit VALUES ('{{this}}')".to_string(),
+        ];
+        let hooked = RelationName::new("main", "hooked");
+        let count = run_hooks(&backend, &hooked, &hooks, HookKind::Post)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(backend.table_exists(&hooked).await.unwrap());
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "audit"))
+                .await
+                .unwrap(),
+            1
+        );
+
+        let failing = vec!["SELECT 1".to_string(), "SELECT * FROM missing".to_string()];
+        let err = run_hooks(&backend, &hooked, &failing, HookKind::Pre)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("pre-hook 2 for model 'hooked' failed"));
+        assert!(err.contains("SELECT * FROM missing"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_comments() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_sql("CREATE TABLE main.orders AS SELECT 1 AS id, 2 AS amount")
+            .await
+            .unwrap();
+
+        let model = ModelFile {
+            name: "orders".to_string(),
+            path: temp_dir.path().join("models/orders.sql"),
+            content: "-- Every order's total\nSELECT 1 AS id, 2 AS amount".to_string(),
+            refs: Vec::new(),
+            parse_errors: Vec::new(),
+            metadata: Some(Box::new(
+                serde_yaml::from_str("columns: [{name: id, description: \"Order's id\"}]").unwrap(),
+            )),
+        };
+        let comments = apply_comments(
+            &backend,
+            &model,
+            &RelationName::new("main", "orders"),
+            &crate::config::Materialization::Table,
+        )
+        .await
+        .unwrap();
+        assert_eq!(comments, 2);
+
+        let batches = backend
+            .execute_sql(
+                "SELECT t.comment, c.comment FROM duckdb_tables() t \
+                 JOIN duckdb_columns() c USING (table_name) \
+                 WHERE table_name = 'orders' AND c.column_name = 'id'",
+            )
+            .await
+            .unwrap();
+        let value = |column| {
+            arrow::util::display::array_value_to_string(batches[0].column(column), 0).unwrap()
+        };
+        assert_eq!(value(0), "Every order's total");
+        assert_eq!(value(1), "Order's id");
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let error = |e: BackendError| -> anyhow::Error {
+            CliError::ExecutionError {
+                model: "m".to_string(),
+                sql: "SELECT 1".to_string(),
+                source: e.into(),
+            }
+            .into()
+        };
+
+        assert!(is_transient(&error(BackendError::connection_failed(
+            "broken pipe"
+        ))));
+        assert!(is_transient(&error(BackendError::execution_failed(
+            "m",
+            "IO Error: Could not set lock on file"
+        ))));
+        assert!(!is_transient(&error(BackendError::execution_failed(
+            "m",
+            "Catalog Error: Table with name missing does not exist!"
+        ))));
+        assert!(!is_transient(&anyhow::anyhow!("compile failed")));
+    }
+
+    #[tokio::test]
+    async fn test_check_sql() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let compiled = |sql: &str| CompiledModel {
+            name: "m".to_string(),
+            sql: sql.to_string(),
+            materialization: crate::config::Materialization::Table,
+        };
+
+        assert_eq!(
+            check_sql(&backend, &compiled("SELECT 1 AS id"))
+                .await
+                .unwrap(),
+            SqlCheck::Valid
+        );
+        assert!(matches!(
+            check_sql(&backend, &compiled("SELECT * FROM main.upstream"))
+                .await
+                .unwrap(),
+            SqlCheck::Unchecked(_)
+        ));
+
+        let err = check_sql(&backend, &compiled("SELECT 1 AS id\nFORM main.upstream"))
+            .await
+            .unwrap_err();
+        match err.downcast_ref::<CliError>() {
+            Some(CliError::InvalidSql { model, line, .. }) => {
+                assert_eq!(model, "m");
+                assert_eq!(line, &Some((2, "FORM main.upstream".to_string())));
+            }
+            other => panic!("expected InvalidSql, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_line() {
+        assert_eq!(
+            error_line("Parser Error: syntax error at or near \"FORM\"\nLINE 2: FORM x"),
+            Some(2)
+        );
+        assert_eq!(
+            error_line("SQL compilation error: syntax error line 3 at position 7"),
+            Some(3)
+        );
+        assert_eq!(error_line("Binder Error: no such column"), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_cancellable() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.sqlite");
+        let backend = smelt_backend_sqlite::SqliteBackend::new(&db_path, "main")
+            .await
+            .unwrap();
+        let endless = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) \
+                       SELECT COUNT(*) FROM n";
+        let work = || async { Ok(backend.execute_sql(endless).await?) };
+        let cancel = CancellationToken::new();
+
+        let timeout = Some(Duration::from_millis(100));
+        let err = run_cancellable(&backend, "slow", timeout, &cancel, work())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CliError>(),
+            Some(CliError::ModelTimeout { model, .. }) if model == "slow"
+        ));
+        assert!(!is_transient(&err), "timeouts aren't retried");
+
+        let (result, ()) = tokio::join!(
+            run_cancellable(&backend, "slow", None, &cancel, work()),
+            async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                cancel.cancel();
+            }
+        );
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<CliError>(),
+            Some(CliError::Cancelled { .. })
+        ));
+
+        // Once cancelled, later models don't start
+        let ran = std::cell::Cell::new(false);
+        let work = async {
+            ran.set(true);
+            Ok(())
+        };
+        assert!(run_cancellable(&backend, "next", None, &cancel, work)
+            .await
+            .is_err());
+        assert!(!ran.get());
+
+        let result = run_cancellable(&backend, "fast", None, &CancellationToken::new(), async {
+            Ok(backend.execute_sql("SELECT 1").await?)
+        })
+        .await;
+        assert!(result.is_ok(), "the connection is usable after cancelling");
+    }
+
+    #[tokio::test]
+    async fn test_record_run_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let duckdb = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let sqlite =
+            smelt_backend_sqlite::SqliteBackend::new(&temp_dir.path().join("test.sqlite"), "main")
+                .await
+                .unwrap();
+
+        let success = NodeResult::success(&ExecutionResult {
+            model_name: "orders".to_string(),
+            duration: Duration::from_millis(1500),
+            row_count: 42,
+            preview: None,
+            stats: Default::default(),
+        });
+        let skipped = NodeResult::skipped("revenue", "upstream model 'users' failed");
+        let started_at = Utc::now();
+        let entries = vec![
+            RunHistoryEntry::new(&success, started_at, Some("SELECT 1")),
+            RunHistoryEntry::new(&skipped, started_at, None),
+        ];
+
+        let backends: [&dyn Backend; 2] = [&duckdb, &sqlite];
+        for backend in backends {
+            for run_id in ["run-1", "run-2"] {
+                record_run_history(backend, "audit", run_id, &entries)
+                    .await
+                    .unwrap();
+            }
+
+            let batches = backend
+                .execute_sql(
+                    "SELECT run_id, model, status, row_count, sql_hash, duration_secs \
+                     FROM audit.smelt_run_history ORDER BY run_id, model",
+                )
+                .await
+                .unwrap();
+            let rows = arrow::util::pretty::pretty_format_batches(&batches)
+                .unwrap()
+                .to_string();
+            let dialect = backend.dialect().name();
+            assert_eq!(rows.matches("orders").count(), 2, "{}: {}", dialect, rows);
+            assert_eq!(rows.matches("skipped").count(), 2, "{}: {}", dialect, rows);
+            assert!(rows.contains("run-2"), "{}: {}", dialect, rows);
+            assert!(
+                rows.contains(&checksum("SELECT 1")),
+                "{}: {}",
+                dialect,
+                rows
+            );
+            assert!(rows.contains("1.5"), "{}: {}", dialect, rows);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_incremental_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let incremental = |yaml: &str| -> IncrementalConfig { serde_yaml::from_str(yaml).unwrap() };
+        let daily = incremental("{enabled: true, event_time_column: ts, partition_column: day}");
+        let overwrite = incremental(
+            "{enabled: true, event_time_column: ts, partition_column: day, incremental_strategy: insert_overwrite}",
+        );
+
+        check_incremental_strategies(&backend, [("daily", &daily)]).unwrap();
+
+        let err = check_incremental_strategies(
+            &backend,
+            [("daily", &daily), ("overwritten", &overwrite)],
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("overwritten: Feature not supported by DuckDB"),
+            "{}",
+            message
+        );
+        assert!(!message.contains("daily"));
+    }
+
+    #[tokio::test]
+    async fn test_check_timeouts() {
+        let temp_dir = TempDir::new().unwrap();
+        let duckdb = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let sqlite =
+            smelt_backend_sqlite::SqliteBackend::new(&temp_dir.path().join("test.sqlite"), "main")
+                .await
+                .unwrap();
+        let models = [("fast", None), ("slow", Some(Duration::from_secs(600)))];
+
+        check_timeouts(&duckdb, [("fast", None)]).unwrap();
+        check_timeouts(&sqlite, models).unwrap();
+        let err = check_timeouts(&duckdb, models).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CliError>(),
+            Some(CliError::UnsupportedTimeouts { models }) if models == &["slow: timeout_seconds: 600"]
+        ));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
 }
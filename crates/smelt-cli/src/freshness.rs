@@ -0,0 +1,315 @@
+//! Source freshness checks for `smelt source freshness`.
+//!
+//! Each source table with a `loaded_at_field` and `freshness` thresholds in
+//! sources.yml is probed for its latest load time; the age of that timestamp
+//! is compared against `warn_after` / `error_after`. Results are also written
+//! to `target/sources.json`.
+
+use crate::artifacts::ArtifactMetadata;
+use crate::config::{FreshnessConfig, FreshnessThreshold, SourceConfig};
+use anyhow::{anyhow, Context, Result};
+use arrow::array::{Array, Float64Array, RecordBatch, StringArray};
+use arrow::compute::cast;
+use arrow::datatypes::DataType;
+use serde::Serialize;
+use smelt_backend::{Backend, SqlDialect};
+use std::time::Duration;
+
+pub const SOURCES_FILE: &str = "sources.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreshnessStatus {
+    Pass,
+    Warn,
+    Error,
+    /// The probe query itself failed
+    RuntimeError,
+}
+
+/// Freshness of a single source table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceFreshness {
+    /// `schema.table`
+    pub source: String,
+    pub loaded_at_field: String,
+    pub status: FreshnessStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_loaded_at: Option<String>,
+    /// Seconds since `max_loaded_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Freshness of every checked source, written to `target/sources.json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FreshnessResults {
+    pub metadata: ArtifactMetadata,
+    pub results: Vec<SourceFreshness>,
+}
+
+/// Probe returning the latest load time (as text) and its age in seconds.
+pub fn freshness_query(dialect: SqlDialect, table: &str, loaded_at_field: &str) -> String {
+    let max = format!("MAX({})", loaded_at_field);
+    let (age, text_type, float_type) = match dialect {
+        SqlDialect::DuckDB => (
+            format!("epoch_ms(current_timestamp) / 1000.0 - epoch({})", max),
+            "VARCHAR",
+            "DOUBLE",
+        ),
+        SqlDialect::SparkSQL => (
+            format!(
+                "unix_timestamp(current_timestamp()) - unix_timestamp({})",
+                max
+            ),
+            "STRING",
+            "DOUBLE",
+        ),
+        SqlDialect::PostgreSQL => (
+            format!("EXTRACT(EPOCH FROM (now() - {}))", max),
+            "TEXT",
+            "DOUBLE PRECISION",
+        ),
+        SqlDialect::SQLite => (
+            format!("(julianday('now') - julianday({})) * 86400.0", max),
+            "TEXT",
+            "REAL",
+        ),
+        SqlDialect::Snowflake => (
+            format!(
+                "DATEDIFF('millisecond', {}, CURRENT_TIMESTAMP()) / 1000.0",
+                max
+            ),
+            "VARCHAR",
+            "DOUBLE",
+        ),
+    };
+
+    format!(
+        "SELECT CAST({max} AS {text_type}) AS max_loaded_at, CAST({age} AS {float_type}) AS age_secs FROM {table}"
+    )
+}
+
+/// Compare a source's age against its thresholds.
+pub fn evaluate(age: Duration, freshness: &FreshnessConfig) -> FreshnessStatus {
+    let exceeds =
+        |threshold: Option<FreshnessThreshold>| threshold.is_some_and(|t| age > t.duration());
+
+    if exceeds(freshness.error_after) {
+        FreshnessStatus::Error
+    } else if exceeds(freshness.warn_after) {
+        FreshnessStatus::Warn
+    } else {
+        FreshnessStatus::Pass
+    }
+}
+
+/// Format an age as its two largest units, e.g. `3h 12m` or `2d 4h`.
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Check every source table that has freshness configured, sorted by name.
+pub async fn check_source_freshness(
+    backend: &dyn Backend,
+    sources: &SourceConfig,
+) -> Vec<SourceFreshness> {
+    let mut tables: Vec<(&str, &str)> = sources
+        .sources
+        .iter()
+        .flat_map(|(schema, s)| s.tables.keys().map(move |t| (schema.as_str(), t.as_str())))
+        .collect();
+    tables.sort();
+
+    let mut results = Vec::new();
+    for (schema, table) in tables {
+        let Some((loaded_at_field, freshness)) = sources.get_freshness(schema, table) else {
+            continue;
+        };
+        let source = format!("{}.{}", schema, table);
+        let sql = freshness_query(backend.dialect(), &source, loaded_at_field);
+
+        let mut result = SourceFreshness {
+            source,
+            loaded_at_field: loaded_at_field.to_string(),
+            status: FreshnessStatus::RuntimeError,
+            max_loaded_at: None,
+            age_secs: None,
+            message: None,
+        };
+
+        match probe(backend, &sql).await {
+            Ok(Some((max_loaded_at, age_secs))) => {
+                result.status = evaluate(Duration::from_secs_f64(age_secs.max(0.0)), freshness);
+                result.max_loaded_at = Some(max_loaded_at);
+                result.age_secs = Some(age_secs);
+            }
+            Ok(None) => {
+                result.status = FreshnessStatus::Error;
+                result.message = Some(format!("{} has no rows", result.source));
+            }
+            Err(e) => result.message = Some(format!("{:#}", e)),
+        }
+
+        results.push(result);
+    }
+
+    results
+}
+
+/// Run a freshness probe; `None` if the table is empty.
+async fn probe(backend: &dyn Backend, sql: &str) -> Result<Option<(String, f64)>> {
+    let batches = backend
+        .execute_sql(sql)
+        .await
+        .with_context(|| format!("Freshness query failed: {}", sql))?;
+
+    let Some(batch) = batches.iter().find(|b| b.num_rows() > 0) else {
+        return Ok(None);
+    };
+    let max_loaded_at = column_as::<StringArray>(batch, 0, &DataType::Utf8)?;
+    let age_secs = column_as::<Float64Array>(batch, 1, &DataType::Float64)?;
+
+    if max_loaded_at.is_null(0) || age_secs.is_null(0) {
+        return Ok(None);
+    }
+    Ok(Some((
+        max_loaded_at.value(0).to_string(),
+        age_secs.value(0),
+    )))
+}
+
+fn column_as<T: Array + Clone + 'static>(
+    batch: &RecordBatch,
+    index: usize,
+    data_type: &DataType,
+) -> Result<T> {
+    let column = cast(batch.column(index), data_type)?;
+    column
+        .as_any()
+        .downcast_ref::<T>()
+        .cloned()
+        .ok_or_else(|| anyhow!("Unexpected type for freshness column {}", index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FreshnessPeriod;
+    use smelt_backend_duckdb::DuckDbBackend;
+
+    fn thresholds() -> FreshnessConfig {
+        FreshnessConfig {
+            warn_after: Some(FreshnessThreshold {
+                count: 1,
+                period: FreshnessPeriod::Hour,
+            }),
+            error_after: Some(FreshnessThreshold {
+                count: 1,
+                period: FreshnessPeriod::Day,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let freshness = thresholds();
+        assert_eq!(
+            evaluate(Duration::from_secs(60), &freshness),
+            FreshnessStatus::Pass
+        );
+        assert_eq!(
+            evaluate(Duration::from_secs(2 * 60 * 60), &freshness),
+            FreshnessStatus::Warn
+        );
+        assert_eq!(
+            evaluate(Duration::from_secs(2 * 24 * 60 * 60), &freshness),
+            FreshnessStatus::Error
+        );
+        assert_eq!(
+            evaluate(
+                Duration::from_secs(u32::MAX as u64),
+                &FreshnessConfig::default()
+            ),
+            FreshnessStatus::Pass
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(5 * 60 + 3)), "5m 3s");
+        assert_eq!(
+            format_age(Duration::from_secs(3 * 3_600 + 12 * 60)),
+            "3h 12m"
+        );
+        assert_eq!(
+            format_age(Duration::from_secs(2 * 86_400 + 4 * 3_600)),
+            "2d 4h"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_source_freshness() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        for sql in [
+            "CREATE SCHEMA raw",
+            "CREATE TABLE raw.fresh AS SELECT make_timestamp(epoch_ms(current_timestamp) * 1000) AS loaded_at",
+            "CREATE TABLE raw.stale AS SELECT make_timestamp(epoch_ms(current_timestamp) * 1000) - INTERVAL 3 HOUR AS loaded_at",
+            "CREATE TABLE raw.empty (loaded_at TIMESTAMP)",
+        ] {
+            backend.execute_sql(sql).await.unwrap();
+        }
+
+        let sources: SourceConfig = serde_yaml::from_str(
+            r#"
+version: 1
+sources:
+  raw:
+    loaded_at_field: loaded_at
+    freshness:
+      warn_after: {count: 1, period: hour}
+      error_after: {count: 1, period: day}
+    tables:
+      fresh: {columns: []}
+      stale: {columns: []}
+      empty: {columns: []}
+      missing: {columns: []}
+"#,
+        )
+        .unwrap();
+
+        let results = check_source_freshness(&backend, &sources).await;
+        let statuses: Vec<_> = results
+            .iter()
+            .map(|r| (r.source.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("raw.empty", FreshnessStatus::Error),
+                ("raw.fresh", FreshnessStatus::Pass),
+                ("raw.missing", FreshnessStatus::RuntimeError),
+                ("raw.stale", FreshnessStatus::Warn),
+            ]
+        );
+        assert!(results[1].max_loaded_at.is_some());
+        assert!(results[3].age_secs.unwrap() >= 3.0 * 60.0 * 60.0 - 60.0);
+    }
+}
@@ -1,8 +1,9 @@
-use crate::config::SourceConfig;
+use crate::config::{Exposure, ExposureConfig, SourceConfig};
 use crate::discovery::ModelFile;
 use crate::errors::CliError;
+use crate::package::Package;
 use anyhow::{anyhow, Result};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 pub struct DependencyGraph {
     /// model_name -> dependencies (model names it references)
@@ -11,6 +12,10 @@ pub struct DependencyGraph {
     models: HashMap<String, ModelFile>,
     /// External sources (from sources.yml)
     sources: HashSet<String>,
+    /// Consumers of models outside the warehouse (from exposures.yml)
+    exposures: BTreeMap<String, Exposure>,
+    /// Packages the graph's `<package>.<model>` models come from
+    packages: Vec<Package>,
 }
 
 impl DependencyGraph {
@@ -40,33 +45,87 @@ impl DependencyGraph {
             dependencies,
             models: models_map,
             sources: source_set,
+            exposures: BTreeMap::new(),
+            packages: Vec::new(),
         })
     }
 
+    /// Add the exposures that depend on the graph's models.
+    pub fn with_exposures(mut self, exposures: Option<&ExposureConfig>) -> Self {
+        self.exposures = exposures
+            .map(|config| config.exposures.clone())
+            .unwrap_or_default();
+        self
+    }
+
+    /// Record the packages the graph's package models were discovered in.
+    pub fn with_packages(mut self, packages: &[Package]) -> Self {
+        self.packages = packages.to_vec();
+        self
+    }
+
     /// Validate all references exist (either as models or sources)
     pub fn validate(&self) -> Result<()> {
-        let mut errors = Vec::new();
+        let mut errors: Vec<String> = self
+            .undefined_refs()
+            .into_iter()
+            .map(|(model_name, dep)| {
+                format!(
+                    "Model '{}' references undefined model/source '{}'",
+                    model_name, dep
+                )
+            })
+            .collect();
+        errors.extend(
+            self.undefined_exposure_refs()
+                .into_iter()
+                .map(|(exposure, dep)| {
+                    format!(
+                        "Exposure '{}' depends on undefined model/source '{}'",
+                        exposure, dep
+                    )
+                }),
+        );
+
+        if !errors.is_empty() {
+            return Err(CliError::DependencyError {
+                message: errors.join("\n  "),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// `(model, reference)` pairs whose reference is neither a model nor a source, sorted
+    pub fn undefined_refs(&self) -> Vec<(String, String)> {
+        let mut undefined = Vec::new();
 
         for (model_name, deps) in &self.dependencies {
             for dep in deps {
                 // Check if dependency exists as a model or source
                 if !self.models.contains_key(dep) && !self.is_source(dep) {
-                    errors.push(format!(
-                        "Model '{}' references undefined model/source '{}'",
-                        model_name, dep
-                    ));
+                    undefined.push((model_name.clone(), dep.clone()));
                 }
             }
         }
 
-        if !errors.is_empty() {
-            return Err(CliError::DependencyError {
-                message: errors.join("\n  "),
-            }
-            .into());
-        }
+        undefined.sort();
+        undefined
+    }
 
-        Ok(())
+    /// `(exposure, dependency)` pairs whose dependency is neither a model nor a source, sorted
+    pub fn undefined_exposure_refs(&self) -> Vec<(String, String)> {
+        self.exposures
+            .iter()
+            .flat_map(|(name, exposure)| {
+                exposure
+                    .depends_on
+                    .iter()
+                    .filter(|dep| !self.models.contains_key(*dep) && !self.is_source(dep))
+                    .map(move |dep| (name.clone(), dep.clone()))
+            })
+            .collect()
     }
 
     fn is_source(&self, name: &str) -> bool {
@@ -151,6 +210,60 @@ impl DependencyGraph {
     pub fn models(&self) -> &HashMap<String, ModelFile> {
         &self.models
     }
+
+    pub fn exposures(&self) -> &BTreeMap<String, Exposure> {
+        &self.exposures
+    }
+
+    pub fn packages(&self) -> &[Package] {
+        &self.packages
+    }
+
+    /// Exposures that read the given model or source, directly or through
+    /// downstream models, sorted by name
+    pub fn downstream_exposures(&self, name: &str) -> Vec<&str> {
+        let mut affected = self.downstream(name);
+        affected.insert(name.to_string());
+        self.exposures
+            .iter()
+            .filter(|(_, exposure)| exposure.depends_on.iter().any(|dep| affected.contains(dep)))
+            .map(|(exposure, _)| exposure.as_str())
+            .collect()
+    }
+
+    /// All models the given model transitively depends on (excluding itself and sources)
+    pub fn upstream(&self, name: &str) -> HashSet<String> {
+        let mut visited = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::from([name]);
+
+        while let Some(current) = queue.pop_front() {
+            for dep in self.dependencies.get(current).into_iter().flatten() {
+                if self.models.contains_key(dep) && visited.insert(dep.clone()) {
+                    queue.push_back(dep);
+                }
+            }
+        }
+
+        visited.remove(name);
+        visited
+    }
+
+    /// All models that transitively depend on the given model (excluding itself)
+    pub fn downstream(&self, name: &str) -> HashSet<String> {
+        let mut visited = HashSet::new();
+        let mut queue: VecDeque<String> = VecDeque::from([name.to_string()]);
+
+        while let Some(current) = queue.pop_front() {
+            for (model_name, deps) in &self.dependencies {
+                if deps.contains(&current) && visited.insert(model_name.clone()) {
+                    queue.push_back(model_name.clone());
+                }
+            }
+        }
+
+        visited.remove(name);
+        visited
+    }
 }
 
 #[cfg(test)]
@@ -239,6 +352,30 @@ mod tests {
         assert!(err_msg.contains("Circular dependency"));
     }
 
+    #[test]
+    fn test_upstream_and_downstream() {
+        // A -> B -> C, A -> D
+        let models = vec![
+            make_model("A", vec![]),
+            make_model("B", vec!["A"]),
+            make_model("C", vec!["B"]),
+            make_model("D", vec!["A"]),
+        ];
+
+        let graph = DependencyGraph::build(models, None).unwrap();
+
+        let upstream = graph.upstream("C");
+        assert_eq!(upstream, HashSet::from(["A".to_string(), "B".to_string()]));
+
+        let downstream = graph.downstream("A");
+        assert_eq!(
+            downstream,
+            HashSet::from(["B".to_string(), "C".to_string(), "D".to_string()])
+        );
+
+        assert!(graph.downstream("C").is_empty());
+    }
+
     #[test]
     fn test_undefined_reference() {
         let models = vec![make_model("A", vec!["nonexistent"])];
@@ -269,9 +406,20 @@ mod tests {
                     column_type: "INTEGER".to_string(),
                     description: String::new(),
                 }],
+                loaded_at_field: None,
+                freshness: None,
+                event_time_column: None,
+                partition_column: None,
+            },
+        );
+        sources.insert(
+            "source".to_string(),
+            SourceSchema {
+                tables,
+                loaded_at_field: None,
+                freshness: None,
             },
         );
-        sources.insert("source".to_string(), SourceSchema { tables });
 
         let source_config = SourceConfig {
             version: 1,
@@ -281,4 +429,40 @@ mod tests {
         let graph = DependencyGraph::build(models, Some(&source_config)).unwrap();
         assert!(graph.validate().is_ok());
     }
+
+    #[test]
+    fn test_exposures() {
+        let models = vec![
+            make_model("revenue", vec!["orders"]),
+            make_model("orders", vec![]),
+            make_model("users", vec![]),
+        ];
+        let exposures: ExposureConfig = serde_yaml::from_str(
+            r#"
+version: 1
+exposures:
+  weekly_kpis:
+    type: dashboard
+    owner: finance@example.com
+    depends_on: [revenue]
+  churn:
+    type: ml
+    depends_on: [users, missing]
+"#,
+        )
+        .unwrap();
+
+        let graph = DependencyGraph::build(models, None)
+            .unwrap()
+            .with_exposures(Some(&exposures));
+
+        assert_eq!(graph.downstream_exposures("orders"), vec!["weekly_kpis"]);
+        assert_eq!(graph.downstream_exposures("revenue"), vec!["weekly_kpis"]);
+        assert_eq!(graph.downstream_exposures("users"), vec!["churn"]);
+
+        let err = graph.validate().unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Exposure 'churn' depends on undefined model/source 'missing'"));
+    }
 }
@@ -0,0 +1,239 @@
+//! Project scaffolding for `smelt init`.
+//!
+//! A new project gets a DuckDB `dev` target, a seed standing in for raw data,
+//! and a staging + mart model pair, so `smelt seed && smelt run` works
+//! straight away:
+//!
+//! ```text
+//! my_project/
+//! ├── smelt.yml
+//! ├── sources.yml
+//! ├── seeds/raw_orders.csv
+//! ├── models/staging/stg_orders.sql
+//! ├── models/marts/customer_orders.sql
+//! └── tests/
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+const SOURCES_YML: &str = r#"version: 1
+
+# Tables models read with smelt.source('schema.table'). `smelt seed` loads
+# seeds/raw_orders.csv into the target schema, so it is declared under `main`.
+sources:
+  main:
+    tables:
+      raw_orders:
+        description: Example orders loaded from seeds/raw_orders.csv
+        columns:
+          - name: order_id
+            type: INTEGER
+          - name: customer_id
+            type: INTEGER
+          - name: order_date
+            type: DATE
+          - name: amount
+            type: DOUBLE
+          - name: status
+            type: VARCHAR
+"#;
+
+const RAW_ORDERS_CSV: &str = "order_id,customer_id,order_date,amount,status
+1,100,2024-01-03,25.50,completed
+2,101,2024-01-04,12.00,completed
+3,100,2024-01-09,40.25,completed
+4,102,2024-01-11,8.75,returned
+5,101,2024-01-15,60.00,completed
+6,103,2024-01-18,19.99,pending
+";
+
+const STG_ORDERS_SQL: &str = "-- Staging models clean up raw data: rename, cast, and filter.
+SELECT
+    order_id,
+    customer_id,
+    order_date,
+    amount,
+    status
+FROM smelt.source('main.raw_orders')
+WHERE status != 'returned'
+";
+
+const CUSTOMER_ORDERS_SQL: &str = "-- Marts build on staging models with smelt.ref().
+SELECT
+    customer_id,
+    COUNT(*) AS order_count,
+    SUM(amount) AS total_amount,
+    MIN(order_date) AS first_order_date,
+    MAX(order_date) AS last_order_date
+FROM smelt.ref('stg_orders')
+GROUP BY customer_id
+";
+
+const GITIGNORE: &str = "target/\npackages/\n";
+
+/// Paths (relative to the project root) and contents of a new project.
+pub fn scaffold_files(name: &str) -> Vec<(PathBuf, String)> {
+    vec![
+        ("smelt.yml".into(), smelt_yml(name)),
+        ("sources.yml".into(), SOURCES_YML.to_string()),
+        ("seeds/raw_orders.csv".into(), RAW_ORDERS_CSV.to_string()),
+        (
+            "models/staging/stg_orders.sql".into(),
+            STG_ORDERS_SQL.to_string(),
+        ),
+        (
+            "models/marts/customer_orders.sql".into(),
+            CUSTOMER_ORDERS_SQL.to_string(),
+        ),
+        ("tests/.gitkeep".into(), String::new()),
+        (".gitignore".into(), GITIGNORE.to_string()),
+    ]
+}
+
+fn smelt_yml(name: &str) -> String {
+    format!(
+        r#"name: {name}
+version: 1
+
+model_paths:
+  - models
+
+targets:
+  dev:
+    type: duckdb
+    database: target/dev.duckdb
+    schema: main
+
+default_materialization: view
+
+# Defaults for every model under a directory
+groups:
+  models/staging:
+    materialization: view
+    tags: [staging]
+  models/marts:
+    materialization: table
+    tags: [marts]
+"#
+    )
+}
+
+/// Create a new project called `name` in `dir`, returning the files written.
+///
+/// `dir` may already exist as long as it is empty; nothing is overwritten.
+pub fn init_project(dir: &Path, name: &str) -> Result<Vec<PathBuf>> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(anyhow!(
+            "Invalid project name '{}': use letters, digits, '_' and '-'",
+            name
+        ));
+    }
+
+    if dir.exists() {
+        let mut entries =
+            std::fs::read_dir(dir).with_context(|| format!("Failed to read {:?}", dir))?;
+        if entries.next().is_some() {
+            return Err(anyhow!(
+                "Cannot create project in {:?}: directory is not empty",
+                dir
+            ));
+        }
+    }
+
+    let mut written = Vec::new();
+    for (relative, contents) in scaffold_files(name) {
+        let path = dir.join(&relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+        written.push(relative);
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Materialization, SourceConfig};
+    use crate::discovery::ModelDiscovery;
+    use crate::graph::DependencyGraph;
+    use crate::seed::{discover_seeds, load_seed};
+    use crate::SqlCompiler;
+    use smelt_backend::{Backend, RelationName};
+    use smelt_backend_duckdb::DuckDbBackend;
+
+    #[tokio::test]
+    async fn test_init_project_runs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("demo");
+
+        let written = init_project(&dir, "demo").unwrap();
+        assert!(written.contains(&PathBuf::from("smelt.yml")));
+        assert!(dir.join("tests").is_dir());
+
+        let config = Config::load(&dir).unwrap();
+        assert_eq!(config.name, "demo");
+        let sources = SourceConfig::load(&dir).unwrap();
+        let models = ModelDiscovery::new(dir.clone(), config.model_paths.clone())
+            .discover_models()
+            .unwrap();
+        let graph = DependencyGraph::build(models, Some(&sources)).unwrap();
+        graph.validate().unwrap();
+
+        let order = graph.execution_order().unwrap();
+        assert_eq!(order, vec!["stg_orders", "customer_orders"]);
+
+        // Seed the raw data and build both models
+        let backend = DuckDbBackend::new(&dir.join("target/dev.duckdb"), "main")
+            .await
+            .unwrap();
+        for seed in discover_seeds(&dir, &config.seed_paths).unwrap() {
+            load_seed(&backend, &seed, "main", false).await.unwrap();
+        }
+        let compiler = SqlCompiler::new(config).with_models(graph.models().values());
+        for name in &order {
+            let compiled = compiler
+                .compile(&graph.models()[name.as_str()], "main")
+                .unwrap();
+            let expected = if name == "customer_orders" {
+                Materialization::Table
+            } else {
+                Materialization::View
+            };
+            assert_eq!(compiled.materialization, expected);
+            let sql = compiled.sql.trim().trim_end_matches(';');
+            backend
+                .execute_sql(&format!("CREATE TABLE main.{} AS {}", name, sql))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "customer_orders"))
+                .await
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_init_project_refuses_non_empty_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "keep me").unwrap();
+
+        let err = init_project(temp_dir.path(), "demo").unwrap_err();
+        assert!(err.to_string().contains("directory is not empty"));
+        assert!(!temp_dir.path().join("smelt.yml").exists());
+
+        let err = init_project(&temp_dir.path().join("x"), "my project").unwrap_err();
+        assert!(err.to_string().contains("Invalid project name"));
+    }
+}
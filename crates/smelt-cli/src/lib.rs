@@ -1,18 +1,86 @@
+pub mod artifacts;
 pub mod compiler;
 pub mod config;
+pub mod contract;
+pub mod debug;
+pub mod diff;
 pub mod discovery;
+pub mod docs;
 pub mod errors;
+pub mod events;
 pub mod executor;
+pub mod freshness;
 pub mod graph;
+pub mod init;
+pub mod lineage;
+pub mod lint;
+pub mod list;
 pub mod metadata;
+pub mod notify;
+pub mod operation;
+pub mod package;
+pub mod partition;
+pub mod program;
+pub mod progress;
+pub mod query;
+pub mod rewrite;
+pub mod seed;
+pub mod selection;
+pub mod template;
 pub mod transformer;
+pub mod validate;
+pub mod watch;
 
-pub use compiler::{CompiledModel, SqlCompiler};
+pub use artifacts::{
+    artifacts_dir, cache_dir, plans_dir, previous_row_counts, write_artifact, write_plan,
+    ArtifactMetadata, BuildCache, CachedBuild, CachedCompile, CompileCache, Manifest, ManifestNode,
+    NodeResult, RowCountChange, RunResults, RunStatus, COMPILE_CACHE_FILE, MANIFEST_FILE,
+    RUN_RESULTS_FILE,
+};
+pub use compiler::{compiled_dir, write_compiled_model, CompiledModel, SqlCompiler};
 pub use config::{
-    find_project_root, BackendType, Config, IncrementalConfig, Materialization, SourceConfig,
+    find_project_root, BackendType, Config, Exposure, ExposureConfig, ExposureType,
+    IncrementalConfig, Materialization, ModelContract, PackageConfig, PartitionGranularity,
+    ProgramConfig, ProgramFormat, SourceConfig,
 };
+pub use contract::{check_contract, check_contract_names, inferred_columns, ContractViolation};
+pub use diff::{diff_relations, diff_schemas, ModelDiff, RowDiff, SchemaDiff};
 pub use discovery::{ModelDiscovery, ModelFile, RefInfo};
-pub use errors::CliError;
+pub use docs::{write_docs_json, write_docs_site, DocsBundle};
+pub use errors::{CliError, Outcome};
+pub use events::RunEvent;
+pub use freshness::{
+    check_source_freshness, format_age, FreshnessResults, FreshnessStatus, SourceFreshness,
+    SOURCES_FILE,
+};
 pub use graph::DependencyGraph;
+pub use init::{init_project, scaffold_files};
+pub use lineage::{render_dot, render_tree, Direction, Lineage, LineageNode, LineageTarget};
+pub use lint::{fix_source, lint_source, KeywordCase, LintConfig, LintLevel, LintRule, Violation};
+pub use list::{list_resources, Resource, ResourceType};
 pub use metadata::{extract_file_metadata, FileMetadata, MetadataError, ModelMetadata};
-pub use transformer::{inject_time_filter, TimeRange, TransformError};
+pub use notify::{send_notifications, Notification, NotificationFormat, NotifyOn, RunNotification};
+pub use operation::{
+    discover_operations, find_operation, parse_args, render_operation, run_operation,
+    split_statements, OperationFile, OperationResult,
+};
+pub use package::{
+    install_package, load_packages, merge_package_sources, InstallStatus, Package, PACKAGES_DIR,
+};
+pub use partition::{
+    align_time_range, is_aligned, parse_chunk, parse_event_time, parse_time_range,
+    partition_values, split_time_range,
+};
+pub use program::run_program;
+pub use progress::{ModelBar, RunProgress};
+pub use query::{compile_query, empty_query, limit_query, statement_complete};
+pub use rewrite::{replace_statements, rewrite_query, RewriteError};
+pub use seed::{discover_seeds, load_seed, SeedFile, SeedResult};
+pub use selection::{select_models, Selector, SelectorMethod, StateSelector};
+pub use template::{parse_vars, render, TemplateError, Vars};
+pub use transformer::{inject_time_filter, FilteredQuery, TimeRange, TransformError};
+pub use validate::{event_time_problem, validate_project, Issue, Severity};
+pub use watch::{
+    affected_models, changed_models, model_checksums, scan_model_files,
+    POLL_INTERVAL as WATCH_POLL_INTERVAL,
+};
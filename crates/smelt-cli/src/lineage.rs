@@ -0,0 +1,524 @@
+//! Model and column lineage for `smelt lineage`.
+//!
+//! Model lineage follows `smelt.ref()` calls. Column lineage follows the
+//! output schemas smelt-db infers for each model: a column selected directly
+//! from an upstream model (or passed through by `SELECT *`) links to that
+//! model's column, while computed and external columns end the trace.
+
+use crate::compiler::split_frontmatter;
+use crate::graph::DependencyGraph;
+use anyhow::{anyhow, Result};
+use smelt_db::{ColumnSource, Database, Inputs, ModelSchema, Schema};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+/// What to trace: a model, or one of its columns (`model.column`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineageTarget {
+    pub model: String,
+    pub column: Option<String>,
+}
+
+impl LineageTarget {
+    pub fn parse(target: &str) -> Self {
+        match target.split_once('.') {
+            Some((model, column)) => Self {
+                model: model.to_string(),
+                column: Some(column.to_string()),
+            },
+            None => Self {
+                model: target.to_string(),
+                column: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upstream,
+    Downstream,
+}
+
+/// A model, column, or end of a trace, with what it feeds from (upstream) or into (downstream).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineageNode {
+    pub label: String,
+    pub children: Vec<LineageNode>,
+}
+
+impl LineageNode {
+    fn leaf(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Inferred schemas and direct dependencies of every model in a project.
+pub struct Lineage {
+    schemas: BTreeMap<String, Arc<ModelSchema>>,
+    depends_on: BTreeMap<String, Vec<String>>,
+    /// Labels of the exposures that read each model
+    exposures: BTreeMap<String, Vec<String>>,
+}
+
+impl Lineage {
+    pub fn build(graph: &DependencyGraph, project_root: &Path) -> Self {
+        let mut db = Database::default();
+        let sources_yaml =
+            std::fs::read_to_string(project_root.join("sources.yml")).unwrap_or_default();
+        db.set_sources_yaml(Arc::new(sources_yaml));
+        db.set_packages(Arc::new(
+            graph.packages().iter().map(|p| p.db_package()).collect(),
+        ));
+
+        // Program models have no SQL, so their columns are unknown
+        let is_sql = |path: &Path| path.extension().is_some_and(|ext| ext == "sql");
+
+        let mut paths = Vec::new();
+        for model in graph.models().values().filter(|m| is_sql(&m.path)) {
+            let (_, sql) = split_frontmatter(&model.content);
+            db.set_file_text(model.path.clone(), Arc::new(sql.to_string()));
+            paths.push(model.path.clone());
+        }
+        db.set_all_files(Arc::new(paths));
+
+        let mut schemas = BTreeMap::new();
+        let mut depends_on = BTreeMap::new();
+        for model in graph.models().values() {
+            let schema = if is_sql(&model.path) {
+                db.model_schema(model.path.clone())
+            } else {
+                Arc::new(ModelSchema::empty())
+            };
+            schemas.insert(model.name.clone(), schema);
+
+            let mut refs: Vec<String> = model.refs.iter().map(|r| r.model_name.clone()).collect();
+            refs.sort();
+            refs.dedup();
+            depends_on.insert(model.name.clone(), refs);
+        }
+
+        let mut exposures: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, exposure) in graph.exposures() {
+            for dependency in &exposure.depends_on {
+                exposures
+                    .entry(dependency.clone())
+                    .or_default()
+                    .push(format!("{} ({})", name, exposure.exposure_type));
+            }
+        }
+
+        Self {
+            schemas,
+            depends_on,
+            exposures,
+        }
+    }
+
+    /// Trace a model or column in one direction.
+    pub fn trace(&self, target: &LineageTarget, direction: Direction) -> Result<LineageNode> {
+        if !self.schemas.contains_key(&target.model) {
+            return Err(anyhow!("Model '{}' not found", target.model));
+        }
+
+        let mut expanded = HashSet::new();
+        match &target.column {
+            None => Ok(self.model_node(&target.model, direction, &mut expanded)),
+            Some(column) => {
+                if !self.provides(&target.model, column) {
+                    return Err(anyhow!(
+                        "Column '{}' not found in model '{}' (columns: {})",
+                        column,
+                        target.model,
+                        self.schemas[&target.model].column_names().join(", ")
+                    ));
+                }
+                Ok(self.column_node(&target.model, column, direction, &mut expanded))
+            }
+        }
+    }
+
+    fn model_node(
+        &self,
+        model: &str,
+        direction: Direction,
+        expanded: &mut HashSet<String>,
+    ) -> LineageNode {
+        if !expanded.insert(model.to_string()) {
+            return LineageNode::leaf(format!("{} (see above)", model));
+        }
+
+        let next: Vec<String> = match direction {
+            Direction::Upstream => self.depends_on.get(model).cloned().unwrap_or_default(),
+            Direction::Downstream => self
+                .depends_on
+                .iter()
+                .filter(|(_, refs)| refs.iter().any(|r| r == model))
+                .map(|(name, _)| name.clone())
+                .collect(),
+        };
+
+        let mut children: Vec<LineageNode> = next
+            .iter()
+            .map(|name| self.model_node(name, direction, expanded))
+            .collect();
+        if direction == Direction::Downstream {
+            // Exposures end the trace: nothing in the project reads them
+            children.extend(
+                self.exposures
+                    .get(model)
+                    .into_iter()
+                    .flatten()
+                    .map(LineageNode::leaf),
+            );
+        }
+
+        LineageNode {
+            label: model.to_string(),
+            children,
+        }
+    }
+
+    fn column_node(
+        &self,
+        model: &str,
+        column: &str,
+        direction: Direction,
+        expanded: &mut HashSet<String>,
+    ) -> LineageNode {
+        let label = format!("{}.{}", model, column);
+        if !expanded.insert(label.to_ascii_lowercase()) {
+            return LineageNode::leaf(format!("{} (see above)", label));
+        }
+
+        let children = match direction {
+            Direction::Upstream => self.upstream_columns(model, column, expanded),
+            Direction::Downstream => self.downstream_columns(model, column, expanded),
+        };
+        LineageNode { label, children }
+    }
+
+    fn upstream_columns(
+        &self,
+        model: &str,
+        column: &str,
+        expanded: &mut HashSet<String>,
+    ) -> Vec<LineageNode> {
+        let Some(schema) = self.schemas.get(model) else {
+            return Vec::new();
+        };
+
+        if let Some(col) = find_column(schema, column) {
+            return match &col.source {
+                ColumnSource::FromModel {
+                    model_name,
+                    column_name,
+                } if self.schemas.contains_key(model_name) => {
+                    vec![self.column_node(model_name, column_name, Direction::Upstream, expanded)]
+                }
+                ColumnSource::FromModel {
+                    model_name,
+                    column_name,
+                } => vec![LineageNode::leaf(format!(
+                    "{}.{} (not a model)",
+                    model_name, column_name
+                ))],
+                ColumnSource::ExternalTable { .. } => {
+                    vec![LineageNode::leaf("(external table)")]
+                }
+                ColumnSource::Computed => {
+                    vec![LineageNode::leaf(format!(
+                        "(computed: {})",
+                        col.expression.trim()
+                    ))]
+                }
+                ColumnSource::Wildcard { .. } | ColumnSource::Unknown => {
+                    vec![LineageNode::leaf("(unknown)")]
+                }
+            };
+        }
+
+        // Not selected by name, so passed through by `SELECT *`
+        wildcard_sources(schema)
+            .filter(|upstream| self.provides(upstream, column))
+            .map(|upstream| self.column_node(upstream, column, Direction::Upstream, expanded))
+            .collect()
+    }
+
+    fn downstream_columns(
+        &self,
+        model: &str,
+        column: &str,
+        expanded: &mut HashSet<String>,
+    ) -> Vec<LineageNode> {
+        let mut children = Vec::new();
+
+        for (name, schema) in &self.schemas {
+            let selected = schema.columns.iter().filter(|c| {
+                matches!(
+                    &c.source,
+                    ColumnSource::FromModel { model_name, column_name }
+                        if model_name == model && column_name.eq_ignore_ascii_case(column)
+                )
+            });
+            for downstream in selected {
+                children.push(self.column_node(
+                    name,
+                    &downstream.name,
+                    Direction::Downstream,
+                    expanded,
+                ));
+            }
+
+            let passed_through = find_column(schema, column).is_none()
+                && wildcard_sources(schema).any(|upstream| upstream == model);
+            if passed_through {
+                children.push(self.column_node(name, column, Direction::Downstream, expanded));
+            }
+        }
+
+        children
+    }
+
+    /// Columns inferred for a model, empty if it's unknown or couldn't be parsed.
+    pub fn column_names(&self, model: &str) -> Vec<&str> {
+        self.schemas
+            .get(model)
+            .map(|schema| schema.column_names())
+            .unwrap_or_default()
+    }
+
+    /// Whether a model outputs `column`, by name or through `SELECT *`.
+    pub fn provides(&self, model: &str, column: &str) -> bool {
+        let Some(schema) = self.schemas.get(model) else {
+            return false;
+        };
+        find_column(schema, column).is_some()
+            || wildcard_sources(schema).any(|upstream| {
+                // Without an upstream schema, assume the column comes through
+                !self.schemas.contains_key(upstream) || self.provides(upstream, column)
+            })
+    }
+}
+
+fn find_column<'a>(schema: &'a ModelSchema, name: &str) -> Option<&'a smelt_db::Column> {
+    schema
+        .columns
+        .iter()
+        .find(|c| c.name != "*" && c.name.eq_ignore_ascii_case(name))
+}
+
+fn wildcard_sources(schema: &ModelSchema) -> impl Iterator<Item = &str> {
+    schema.columns.iter().filter_map(|c| match &c.source {
+        ColumnSource::Wildcard { model_name } => Some(model_name.as_str()),
+        _ => None,
+    })
+}
+
+/// Render a lineage tree with box-drawing branches.
+pub fn render_tree(root: &LineageNode) -> String {
+    fn render_children(out: &mut String, node: &LineageNode, prefix: &str) {
+        for (i, child) in node.children.iter().enumerate() {
+            let last = i + 1 == node.children.len();
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                prefix,
+                if last { "└──" } else { "├──" },
+                child.label
+            );
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            render_children(out, child, &prefix);
+        }
+    }
+
+    let mut out = format!("{}\n", root.label);
+    render_children(&mut out, root, "");
+    out
+}
+
+/// Render upstream and downstream trees as a Graphviz digraph, edges pointing
+/// in the direction data flows.
+pub fn render_dot(upstream: Option<&LineageNode>, downstream: Option<&LineageNode>) -> String {
+    fn edges(node: &LineageNode, direction: Direction, out: &mut Vec<String>) {
+        for child in &node.children {
+            let (from, to) = match direction {
+                Direction::Upstream => (&child.label, &node.label),
+                Direction::Downstream => (&node.label, &child.label),
+            };
+            let edge = format!("  {} -> {};", quote(from), quote(to));
+            if !out.contains(&edge) {
+                out.push(edge);
+            }
+            edges(child, direction, out);
+        }
+    }
+
+    fn quote(label: &str) -> String {
+        format!(
+            "\"{}\"",
+            label
+                .trim_end_matches(" (see above)")
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        )
+    }
+
+    let mut lines = Vec::new();
+    if let Some(root) = upstream {
+        edges(root, Direction::Upstream, &mut lines);
+    }
+    if let Some(root) = downstream {
+        edges(root, Direction::Downstream, &mut lines);
+    }
+    let root = upstream.or(downstream).map(|n| quote(&n.label));
+
+    let mut dot = String::from("digraph lineage {\n  rankdir=LR;\n  node [shape=box];\n");
+    if let Some(root) = root {
+        let _ = writeln!(dot, "  {} [style=bold];", root);
+    }
+    for line in lines {
+        dot.push_str(&line);
+        dot.push('\n');
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{ModelFile, RefInfo};
+    use smelt_parser::File;
+
+    fn make_model(name: &str, sql: &str) -> ModelFile {
+        let parse = smelt_parser::parse(sql);
+        let refs = File::cast(parse.syntax())
+            .map(|file| {
+                file.refs()
+                    .filter_map(|r| {
+                        Some(RefInfo {
+                            model_name: r.model_name()?,
+                            has_named_params: false,
+                            range: r.range(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        ModelFile {
+            name: name.to_string(),
+            path: format!("/project/models/{}.sql", name).into(),
+            content: sql.to_string(),
+            refs,
+            parse_errors: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    /// raw_users -> stg_users (SELECT *) -> users -> report -> (weekly exposure)
+    fn make_lineage() -> Lineage {
+        let models = vec![
+            make_model("raw_users", "SELECT id, email, plan FROM source.users"),
+            make_model("stg_users", "SELECT * FROM smelt.ref('raw_users')"),
+            make_model(
+                "users",
+                "SELECT id, email AS contact, UPPER(plan) AS plan FROM smelt.ref('stg_users')",
+            ),
+            make_model("report", "SELECT contact FROM smelt.ref('users')"),
+        ];
+        let exposures = serde_yaml::from_str(
+            "version: 1\nexposures:\n  weekly:\n    type: dashboard\n    depends_on: [report]\n",
+        )
+        .unwrap();
+        let graph = DependencyGraph::build(models, None)
+            .unwrap()
+            .with_exposures(Some(&exposures));
+        Lineage::build(&graph, Path::new("/project"))
+    }
+
+    #[test]
+    fn test_lineage_target() {
+        assert_eq!(
+            LineageTarget::parse("users.email"),
+            LineageTarget {
+                model: "users".to_string(),
+                column: Some("email".to_string()),
+            }
+        );
+        assert_eq!(LineageTarget::parse("users").column, None);
+    }
+
+    #[test]
+    fn test_column_lineage() {
+        let lineage = make_lineage();
+
+        let upstream = lineage
+            .trace(&LineageTarget::parse("report.contact"), Direction::Upstream)
+            .unwrap();
+        assert_eq!(
+            render_tree(&upstream),
+            "report.contact\n\
+             └── users.contact\n    \
+                 └── stg_users.email\n        \
+                     └── raw_users.email\n            \
+                         └── (external table)\n"
+        );
+
+        let upstream = lineage
+            .trace(&LineageTarget::parse("users.plan"), Direction::Upstream)
+            .unwrap();
+        assert_eq!(upstream.children[0].label, "(computed: UPPER(plan))");
+
+        let downstream = lineage
+            .trace(
+                &LineageTarget::parse("raw_users.email"),
+                Direction::Downstream,
+            )
+            .unwrap();
+        assert_eq!(
+            render_tree(&downstream),
+            "raw_users.email\n\
+             └── stg_users.email\n    \
+                 └── users.contact\n        \
+                     └── report.contact\n"
+        );
+
+        assert!(lineage
+            .trace(&LineageTarget::parse("users.missing"), Direction::Upstream)
+            .is_err());
+        assert!(lineage
+            .trace(&LineageTarget::parse("nope"), Direction::Upstream)
+            .is_err());
+    }
+
+    #[test]
+    fn test_model_lineage_and_dot() {
+        let lineage = make_lineage();
+        let target = LineageTarget::parse("users");
+
+        let upstream = lineage.trace(&target, Direction::Upstream).unwrap();
+        let downstream = lineage.trace(&target, Direction::Downstream).unwrap();
+        assert_eq!(
+            render_tree(&upstream),
+            "users\n└── stg_users\n    └── raw_users\n"
+        );
+        assert_eq!(
+            render_tree(&downstream),
+            "users\n└── report\n    └── weekly (dashboard)\n"
+        );
+
+        assert_eq!(
+            render_dot(Some(&upstream), Some(&downstream)),
+            "digraph lineage {\n  rankdir=LR;\n  node [shape=box];\n  \"users\" [style=bold];\n  \
+             \"stg_users\" -> \"users\";\n  \"raw_users\" -> \"stg_users\";\n  \"users\" -> \"report\";\n  \
+             \"report\" -> \"weekly (dashboard)\";\n}\n"
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! SQL style checks for `smelt lint`.
+//!
+//! Rules run over the CST of each model file as written (before `{{ var() }}`
+//! rendering), so positions and fixes line up with the source. Only fixes
+//! that cannot change what a query returns are offered: keyword case, and
+//! spelling an implicit cross join (`FROM a, b`) as `CROSS JOIN`.
+
+use crate::metadata::{extract_file_metadata, FileMetadata};
+use serde::{Deserialize, Serialize};
+use smelt_parser::parse;
+use smelt_parser::syntax_kind::{SyntaxNode, SyntaxToken};
+use smelt_parser::SyntaxKind;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+/// Longest line allowed when `line_length` is enabled without `max_line_length`.
+const DEFAULT_MAX_LINE_LENGTH: usize = 120;
+
+/// Most passes [`fix_source`] makes; each pass can expose further fixes.
+const MAX_FIX_PASSES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// `SELECT *` or `t.*` in a select list
+    SelectStar,
+    /// A computed select item without an alias
+    ExpressionAlias,
+    /// Tables joined with a comma instead of `JOIN`
+    ImplicitCrossJoin,
+    /// Keywords not written in the configured case
+    KeywordCase,
+    /// Lines longer than `max_line_length`
+    LineLength,
+}
+
+impl LintRule {
+    pub fn name(self) -> &'static str {
+        match self {
+            LintRule::SelectStar => "select_star",
+            LintRule::ExpressionAlias => "expression_alias",
+            LintRule::ImplicitCrossJoin => "implicit_cross_join",
+            LintRule::KeywordCase => "keyword_case",
+            LintRule::LineLength => "line_length",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Off,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCase {
+    #[default]
+    Upper,
+    Lower,
+}
+
+/// The `lint:` section of smelt.yml.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LintConfig {
+    /// Case to write keywords in; setting it enables `keyword_case`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword_case: Option<KeywordCase>,
+    /// Longest allowed line in characters; setting it enables `line_length`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_line_length: Option<usize>,
+    /// Level of each rule; unlisted rules are warnings
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<LintRule, LintLevel>,
+}
+
+impl LintConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// How `rule` is reported. `keyword_case` and `line_length` are off
+    /// unless their option is set or the rule is listed under `rules`.
+    pub fn level(&self, rule: LintRule) -> LintLevel {
+        let enabled = match rule {
+            LintRule::KeywordCase => self.keyword_case.is_some(),
+            LintRule::LineLength => self.max_line_length.is_some(),
+            _ => true,
+        };
+        match self.rules.get(&rule) {
+            Some(level) => *level,
+            None if enabled => LintLevel::Warning,
+            None => LintLevel::Off,
+        }
+    }
+}
+
+/// A replacement of a byte range of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// A rule violation found by [`lint_source`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: LintRule,
+    /// `Warning` or `Error`
+    pub level: LintLevel,
+    /// 1-based line in the file
+    pub line: usize,
+    /// 1-based column (in characters)
+    pub column: usize,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+impl Violation {
+    pub fn is_error(&self) -> bool {
+        self.level == LintLevel::Error
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.level {
+            LintLevel::Error => "error",
+            _ => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.line,
+            self.column,
+            label,
+            self.rule.name(),
+            self.message
+        )?;
+        if self.fix.is_some() {
+            write!(f, " (fixable)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the model file `source` against the enabled rules, in source order.
+pub fn lint_source(source: &str, config: &LintConfig) -> Vec<Violation> {
+    let mut found = Vec::new();
+    for section in sql_sections(source) {
+        lint_section(source, section, config, &mut found);
+    }
+
+    let mut violations: Vec<Violation> = found
+        .into_iter()
+        .filter_map(|(offset, rule, message, fix)| {
+            let level = config.level(rule);
+            if level == LintLevel::Off {
+                return None;
+            }
+            let (line, column) = position(source, offset);
+            Some(Violation {
+                rule,
+                level,
+                line,
+                column,
+                message,
+                fix,
+            })
+        })
+        .collect();
+    violations.sort_by_key(|v| (v.line, v.column));
+    violations
+}
+
+/// Apply every fix [`lint_source`] offers, repeatedly until none are left.
+/// Returns the fixed source and the number of fixes applied.
+pub fn fix_source(source: &str, config: &LintConfig) -> (String, usize) {
+    let mut fixed = source.to_string();
+    let mut applied = 0;
+
+    for _ in 0..MAX_FIX_PASSES {
+        let mut fixes: Vec<Fix> = lint_source(&fixed, config)
+            .into_iter()
+            .filter_map(|v| v.fix)
+            .collect();
+        if fixes.is_empty() {
+            break;
+        }
+        applied += fixes.len();
+        // Fixes never overlap, so applying from the end keeps offsets valid
+        fixes.sort_by_key(|fix| std::cmp::Reverse(fix.range.start));
+        for fix in fixes {
+            fixed.replace_range(fix.range, &fix.replacement);
+        }
+    }
+
+    (fixed, applied)
+}
+
+type Found = (usize, LintRule, String, Option<Fix>);
+
+/// Byte ranges of the SQL in a model file, skipping YAML frontmatter.
+fn sql_sections(source: &str) -> Vec<Range<usize>> {
+    let start = match extract_file_metadata(source) {
+        Ok(FileMetadata::Single { sql_offset, .. }) => sql_offset.min(source.len()),
+        Ok(FileMetadata::Multi { models }) => {
+            return models
+                .into_iter()
+                .map(|section| section.sql_range)
+                .collect();
+        }
+        _ => 0,
+    };
+    let sql = start..source.len();
+    vec![sql]
+}
+
+fn lint_section(source: &str, section: Range<usize>, config: &LintConfig, found: &mut Vec<Found>) {
+    let base = section.start;
+    let sql = &source[section];
+    let root = parse(sql).syntax();
+    let offset = |token: &SyntaxToken| base + usize::from(token.text_range().start());
+
+    for node in root.descendants() {
+        if node.kind() != SyntaxKind::SELECT_ITEM {
+            continue;
+        }
+        let Some(last) = last_code_token(&node) else {
+            continue;
+        };
+        if last.kind() == SyntaxKind::STAR {
+            found.push((
+                offset(&last),
+                LintRule::SelectStar,
+                "Select columns explicitly instead of *".to_string(),
+                None,
+            ));
+        } else if let Some(expression) = unaliased_expression(&node) {
+            let start = first_code_token(&expression).map_or(base, |token| offset(&token));
+            found.push((
+                start,
+                LintRule::ExpressionAlias,
+                format!(
+                    "Add an alias to `{}`",
+                    summarize(&expression.text().to_string())
+                ),
+                None,
+            ));
+        }
+    }
+
+    let keyword_case = config.keyword_case.unwrap_or_default();
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        if token.kind() == SyntaxKind::COMMA && is_join_comma(&token) {
+            let start = offset(&token);
+            found.push((
+                start,
+                LintRule::ImplicitCrossJoin,
+                "Join with CROSS JOIN instead of a comma".to_string(),
+                Some(Fix {
+                    range: start..start + 1,
+                    replacement: match config.keyword_case {
+                        Some(KeywordCase::Lower) => " cross join",
+                        _ => " CROSS JOIN",
+                    }
+                    .to_string(),
+                }),
+            ));
+        } else if token.kind().is_keyword() && !used_as_identifier(&token) {
+            let text = token.text();
+            let expected = match keyword_case {
+                KeywordCase::Upper => text.to_uppercase(),
+                KeywordCase::Lower => text.to_lowercase(),
+            };
+            if text != expected {
+                let start = offset(&token);
+                found.push((
+                    start,
+                    LintRule::KeywordCase,
+                    format!("Write `{}` as `{}`", text, expected),
+                    Some(Fix {
+                        range: start..start + text.len(),
+                        replacement: expected,
+                    }),
+                ));
+            }
+        }
+    }
+
+    let max = config.max_line_length.unwrap_or(DEFAULT_MAX_LINE_LENGTH);
+    let mut line_start = base;
+    for line in sql.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\r', '\n']);
+        let length = text.chars().count();
+        if length > max {
+            let over = text.char_indices().nth(max).map_or(0, |(i, _)| i);
+            found.push((
+                line_start + over,
+                LintRule::LineLength,
+                format!("Line is {} characters long (max {})", length, max),
+                None,
+            ));
+        }
+        line_start += line.len();
+    }
+}
+
+/// The expression of a select item that computes a value but has no alias.
+/// Plain column references keep their name, and select lists whose names
+/// don't matter (scalar subqueries, `EXISTS`, later `UNION` branches) are skipped.
+fn unaliased_expression(item: &SyntaxNode) -> Option<SyntaxNode> {
+    let has_alias = item
+        .children_with_tokens()
+        .any(|element| matches!(element.kind(), SyntaxKind::AS_KW | SyntaxKind::IDENT));
+    if has_alias || !names_columns(item) {
+        return None;
+    }
+
+    let expression = item
+        .children()
+        .find(|child| child.kind() == SyntaxKind::EXPRESSION)?;
+    let mut tokens = code_tokens(&expression).peekable();
+    tokens.peek()?;
+    let column_ref =
+        tokens.all(|token| matches!(token.kind(), SyntaxKind::IDENT | SyntaxKind::DOT));
+    (!column_ref).then_some(expression)
+}
+
+/// Whether the column names of the select holding `item` are visible: it is
+/// the model's query, or a subquery in FROM or a CTE.
+fn names_columns(item: &SyntaxNode) -> bool {
+    let Some(select) = item
+        .ancestors()
+        .find(|node| node.kind() == SyntaxKind::SELECT_STMT)
+    else {
+        return false;
+    };
+    let Some(parent) = select.parent() else {
+        return false;
+    };
+    match parent.kind() {
+        SyntaxKind::FILE => true,
+        SyntaxKind::SUBQUERY => parent
+            .parent()
+            .is_some_and(|p| matches!(p.kind(), SyntaxKind::TABLE_REF | SyntaxKind::CTE)),
+        _ => false,
+    }
+}
+
+/// Whether a comma separates tables in FROM. The parser stops the FROM clause
+/// at such a comma, so it is recognized by directly following a table.
+fn is_join_comma(comma: &SyntaxToken) -> bool {
+    if comma.parent().map(|p| p.kind()) == Some(SyntaxKind::FROM_CLAUSE) {
+        return true;
+    }
+    let Some(previous) = prev_code_token(comma) else {
+        return false;
+    };
+    previous.parent_ancestors().any(|node| {
+        node.kind() == SyntaxKind::TABLE_REF
+            && node.parent().is_some_and(|p| {
+                matches!(p.kind(), SyntaxKind::FROM_CLAUSE | SyntaxKind::JOIN_CLAUSE)
+            })
+            && last_code_token(&node).as_ref() == Some(&previous)
+    })
+}
+
+/// Keywords after `.` or `AS`, or before `.`, are column or table names.
+fn used_as_identifier(token: &SyntaxToken) -> bool {
+    prev_code_token(token).is_some_and(|t| matches!(t.kind(), SyntaxKind::DOT | SyntaxKind::AS_KW))
+        || next_code_token(token).is_some_and(|t| t.kind() == SyntaxKind::DOT)
+}
+
+fn code_tokens(node: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+}
+
+fn first_code_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    code_tokens(node).next()
+}
+
+fn last_code_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    code_tokens(node).last()
+}
+
+fn prev_code_token(token: &SyntaxToken) -> Option<SyntaxToken> {
+    std::iter::successors(token.prev_token(), |t| t.prev_token()).find(|t| !t.kind().is_trivia())
+}
+
+fn next_code_token(token: &SyntaxToken) -> Option<SyntaxToken> {
+    std::iter::successors(token.next_token(), |t| t.next_token()).find(|t| !t.kind().is_trivia())
+}
+
+/// An expression on one line, shortened for messages.
+fn summarize(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > 40 {
+        format!("{}...", text.chars().take(37).collect::<String>())
+    } else {
+        text
+    }
+}
+
+/// 1-based line and column of a byte offset.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, source[line_start..offset].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str, config: &LintConfig) -> Vec<(LintRule, usize, usize)> {
+        lint_source(source, config)
+            .into_iter()
+            .map(|v| (v.rule, v.line, v.column))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_rules() {
+        let sql = "SELECT count(*), amount * 2, id, o.status, lower(name) AS name, o.*\n\
+                   FROM smelt.ref('orders') o\n\
+                   WHERE EXISTS (SELECT 1 FROM smelt.ref('users'))\n\
+                   UNION ALL\n\
+                   SELECT a, c + 1, * FROM (SELECT max(x) FROM t) s";
+
+        assert_eq!(
+            rules(sql, &LintConfig::default()),
+            vec![
+                (LintRule::ExpressionAlias, 1, 8),
+                (LintRule::ExpressionAlias, 1, 18),
+                (LintRule::SelectStar, 1, 67),
+                (LintRule::SelectStar, 5, 18),
+                (LintRule::ExpressionAlias, 5, 33),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_levels() {
+        let config: LintConfig =
+            serde_yaml::from_str("rules:\n  select_star: error\n  expression_alias: off\n")
+                .unwrap();
+        let violations = lint_source("SELECT *, a + 1 FROM t", &config);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].is_error());
+        assert_eq!(
+            violations[0].to_string(),
+            "1:8: error[select_star]: Select columns explicitly instead of *"
+        );
+
+        assert!(serde_yaml::from_str::<LintConfig>("rules:\n  no_such_rule: off\n").is_err());
+    }
+
+    #[test]
+    fn test_keyword_case_and_line_length() {
+        let config = LintConfig {
+            keyword_case: Some(KeywordCase::Upper),
+            max_line_length: Some(30),
+            ..Default::default()
+        };
+        let sql = "---\nmaterialization: table\n---\n\
+                   select id, t.last, x AS first\nfrom smelt.ref('events') t where id > 1000000000000000";
+
+        let violations = lint_source(sql, &config);
+        let found: Vec<_> = violations
+            .iter()
+            .map(|v| (v.rule, v.line, v.column, v.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (LintRule::KeywordCase, 4, 1, "Write `select` as `SELECT`"),
+                (LintRule::KeywordCase, 5, 1, "Write `from` as `FROM`"),
+                (LintRule::KeywordCase, 5, 28, "Write `where` as `WHERE`"),
+                (
+                    LintRule::LineLength,
+                    5,
+                    31,
+                    "Line is 54 characters long (max 30)"
+                ),
+            ]
+        );
+
+        // keyword_case and line_length are off unless configured
+        assert!(lint_source(sql, &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_fix_source() {
+        let config = LintConfig {
+            keyword_case: Some(KeywordCase::Lower),
+            ..Default::default()
+        };
+        let sql = "SELECT a.id -- FROM a, b\nFROM a, b, c WHERE a.id = b.id";
+
+        let (fixed, applied) = fix_source(sql, &config);
+        assert_eq!(
+            fixed,
+            "select a.id -- FROM a, b\nfrom a cross join b cross join c where a.id = b.id"
+        );
+        assert_eq!(applied, 5);
+        assert!(lint_source(&fixed, &config).is_empty());
+    }
+}
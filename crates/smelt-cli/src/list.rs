@@ -0,0 +1,205 @@
+//! Resource listing for `smelt ls`.
+
+use crate::config::{Config, ExposureType, Materialization, SourceConfig};
+use crate::graph::DependencyGraph;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Kinds of project resources that can be listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceType {
+    Model,
+    Source,
+    Exposure,
+}
+
+/// A single listed resource.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Resource {
+    pub resource_type: ResourceType,
+    pub name: String,
+    /// Path relative to the project root
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub materialization: Option<Materialization>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exposure_type: Option<ExposureType>,
+}
+
+/// List models (restricted to `selected` if given), sources, and exposures, each sorted by name.
+///
+/// With a selection, only exposures that read a selected model are listed, so
+/// `--select state:modified+` shows the exposures a change affects.
+pub fn list_resources(
+    graph: &DependencyGraph,
+    config: &Config,
+    sources: Option<&SourceConfig>,
+    project_root: &Path,
+    selected: Option<&HashSet<String>>,
+) -> Vec<Resource> {
+    let mut models: Vec<Resource> = graph
+        .models()
+        .values()
+        .filter(|model| selected.is_none_or(|s| s.contains(&model.name)))
+        .map(|model| Resource {
+            resource_type: ResourceType::Model,
+            name: model.name.clone(),
+            path: model
+                .path
+                .strip_prefix(project_root)
+                .unwrap_or(&model.path)
+                .to_path_buf(),
+            tags: config.get_model_tags(model),
+            materialization: Some(config.get_model_materialization(model)),
+            exposure_type: None,
+        })
+        .collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut source_list: Vec<Resource> = sources
+        .into_iter()
+        .flat_map(|config| config.sources.iter())
+        .flat_map(|(schema_name, schema)| {
+            schema.tables.keys().map(move |table| Resource {
+                resource_type: ResourceType::Source,
+                name: format!("{}.{}", schema_name, table),
+                path: PathBuf::from("sources.yml"),
+                tags: Vec::new(),
+                materialization: None,
+                exposure_type: None,
+            })
+        })
+        .collect();
+    source_list.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let exposures = graph
+        .exposures()
+        .iter()
+        .filter(|(_, exposure)| {
+            selected.is_none_or(|s| exposure.depends_on.iter().any(|dep| s.contains(dep)))
+        })
+        .map(|(name, exposure)| Resource {
+            resource_type: ResourceType::Exposure,
+            name: name.clone(),
+            path: PathBuf::from("exposures.yml"),
+            tags: Vec::new(),
+            materialization: None,
+            exposure_type: Some(exposure.exposure_type),
+        });
+
+    models.extend(source_list);
+    models.extend(exposures);
+    models
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SourceColumn, SourceSchema, SourceTable};
+    use crate::discovery::ModelFile;
+    use crate::metadata::ModelMetadata;
+    use std::collections::HashMap;
+
+    fn make_model(name: &str, tags: Vec<&str>) -> ModelFile {
+        ModelFile {
+            name: name.to_string(),
+            path: PathBuf::from("/project/models").join(format!("{}.sql", name)),
+            content: "SELECT 1".to_string(),
+            refs: Vec::new(),
+            parse_errors: Vec::new(),
+            metadata: Some(Box::new(ModelMetadata {
+                tags: tags.into_iter().map(String::from).collect(),
+                materialization: Some(Materialization::Table),
+                ..Default::default()
+            })),
+        }
+    }
+
+    #[test]
+    fn test_list_resources() {
+        let exposures = serde_yaml::from_str(
+            "version: 1\nexposures:\n  kpis:\n    type: dashboard\n    depends_on: [a]\n",
+        )
+        .unwrap();
+        let graph = DependencyGraph::build(
+            vec![make_model("b", vec![]), make_model("a", vec!["daily"])],
+            None,
+        )
+        .unwrap()
+        .with_exposures(Some(&exposures));
+
+        let config = Config::for_test("test");
+
+        let mut tables = HashMap::new();
+        tables.insert(
+            "events".to_string(),
+            SourceTable {
+                description: String::new(),
+                columns: vec![SourceColumn {
+                    name: "id".to_string(),
+                    column_type: "INTEGER".to_string(),
+                    description: String::new(),
+                }],
+                loaded_at_field: None,
+                freshness: None,
+                event_time_column: None,
+                partition_column: None,
+            },
+        );
+        let mut schemas = HashMap::new();
+        schemas.insert(
+            "raw".to_string(),
+            SourceSchema {
+                tables,
+                loaded_at_field: None,
+                freshness: None,
+            },
+        );
+        let sources = SourceConfig {
+            version: 1,
+            sources: schemas,
+        };
+
+        let resources =
+            list_resources(&graph, &config, Some(&sources), Path::new("/project"), None);
+        let names: Vec<_> = resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "raw.events", "kpis"]);
+        assert_eq!(resources[0].path, PathBuf::from("models/a.sql"));
+        assert_eq!(resources[0].tags, vec!["daily"]);
+        assert_eq!(resources[0].materialization, Some(Materialization::Table));
+        assert_eq!(resources[2].resource_type, ResourceType::Source);
+        assert_eq!(resources[3].exposure_type, Some(ExposureType::Dashboard));
+
+        let selected = HashSet::from(["b".to_string()]);
+        let resources = list_resources(
+            &graph,
+            &config,
+            None,
+            Path::new("/project"),
+            Some(&selected),
+        );
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].name, "b");
+
+        // Exposures are listed when they read a selected model
+        let selected = HashSet::from(["a".to_string()]);
+        let resources = list_resources(
+            &graph,
+            &config,
+            None,
+            Path::new("/project"),
+            Some(&selected),
+        );
+        let names: Vec<_> = resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "kpis"]);
+
+        let json = serde_json::to_value(&resources).unwrap();
+        assert_eq!(json[0]["resource_type"], "model");
+        assert_eq!(json[0]["materialization"], "table");
+        assert_eq!(json[1]["exposure_type"], "dashboard");
+    }
+}
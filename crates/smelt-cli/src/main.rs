@@ -1,17 +1,45 @@
 use anyhow::{Context, Result};
 use arrow::util::pretty;
-use chrono::{Duration, NaiveDate};
-use clap::{Parser, Subcommand};
-use smelt_backend::{Backend, PartitionSpec};
-use smelt_backend_duckdb::DuckDbBackend;
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use smelt_backend::{collect_limited, Backend, ExecutionResult, PartitionSpec, RelationName};
+use smelt_cli::config::{IncrementalStrategy, Materialization, Target};
+use smelt_cli::executor::{HookKind, RunHistoryEntry, SqlCheck};
 use smelt_cli::{
-    executor, find_project_root, inject_time_filter, BackendType, Config, DependencyGraph,
-    ModelDiscovery, SourceConfig, SqlCompiler, TimeRange,
+    affected_models, align_time_range, artifacts_dir, cache_dir, changed_models,
+    check_contract_names, check_source_freshness, compile_query, compiled_dir, diff_relations,
+    discover_seeds, empty_query, event_time_problem, executor, find_operation, find_project_root,
+    fix_source, format_age, inferred_columns, init_project, inject_time_filter, install_package,
+    is_aligned, limit_query, lint_source, list_resources, load_packages, load_seed,
+    merge_package_sources, model_checksums, parse_args, parse_chunk, parse_time_range, parse_vars,
+    partition_values, plans_dir, previous_row_counts, render_dot, render_operation, render_tree,
+    run_program, scan_model_files, select_models, send_notifications, split_time_range,
+    statement_complete, validate_project, write_artifact, write_compiled_model, write_docs_json,
+    write_docs_site, write_plan, ArtifactMetadata, BackendType, BuildCache, CachedBuild, CliError,
+    CompileCache, Config, DependencyGraph, Direction, DocsBundle, ExposureConfig, FreshnessResults,
+    FreshnessStatus, InstallStatus, Lineage, LineageTarget, Manifest, ModelDiscovery, ModelFile,
+    NodeResult, Outcome, Package, Resource, ResourceType, RowCountChange, RunEvent,
+    RunNotification, RunProgress, RunResults, RunStatus, SourceConfig, SqlCompiler, TimeRange,
+    MANIFEST_FILE, RUN_RESULTS_FILE, SOURCES_FILE, WATCH_POLL_INTERVAL,
 };
-use std::path::PathBuf;
-
+use smelt_parser::ast::text_range_to_range;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "duckdb")]
+use smelt_backend_duckdb::{DuckDbBackend, LockWait};
+#[cfg(feature = "snowflake")]
+use smelt_backend_snowflake::{SnowflakeBackend, SnowflakeConfig};
 #[cfg(feature = "spark")]
 use smelt_backend_spark::SparkBackend;
+#[cfg(feature = "sqlite")]
+use smelt_backend_sqlite::SqliteBackend;
 
 #[derive(Parser)]
 #[command(name = "smelt")]
@@ -19,21 +47,88 @@ use smelt_backend_spark::SparkBackend;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Wait for another run using the same DuckDB database to finish instead
+    /// of failing; `--wait=SECONDS` gives up after that long
+    #[arg(long, global = true, value_name = "SECONDS", num_args = 0..=1, require_equals = true)]
+    wait: Option<Option<u64>>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Create a new project with a sample DuckDB target, seed, and models
+    Init(InitArgs),
+
     /// Run models and materialize them in the target database
     Run(RunArgs),
+
+    /// Rebuild an incremental model over a historical time range, chunk by chunk
+    Backfill(BackfillArgs),
+
+    /// Compile models to SQL in target/compiled/ without executing them
+    Compile(CompileArgs),
+
+    /// Load CSV files from the seeds directory into the target schema
+    Seed(SeedArgs),
+
+    /// Check configuration and models for errors without connecting to a target
+    Validate(ValidateArgs),
+
+    /// Check model SQL against the style rules in smelt.yml's `lint:` section
+    Lint(LintArgs),
+
+    /// List models and sources
+    Ls(LsArgs),
+
+    /// Run ad-hoc SQL (with smelt.ref()/smelt.source()) against a target, or open a shell
+    Query(QueryArgs),
+
+    /// Preview a model's rows
+    Show(ShowArgs),
+
+    /// Compare a model's row count, columns, and rows between two targets
+    Diff(DiffArgs),
+
+    /// Print the upstream and downstream lineage of a model or column (`model.column`)
+    Lineage(LineageArgs),
+
+    /// Run a SQL operation from the operations directory outside the model DAG
+    RunOperation(RunOperationArgs),
+
+    /// Check the connection to a target and the rights smelt needs on its schema
+    Debug(DebugArgs),
+
+    /// Fetch the packages declared in smelt.yml, cloning git packages into packages/
+    Deps(DepsArgs),
+
+    /// Generate project documentation
+    #[command(subcommand)]
+    Docs(DocsCommands),
+
+    /// Inspect declared sources
+    #[command(subcommand)]
+    Source(SourceCommands),
 }
 
-#[derive(Parser)]
+#[derive(Subcommand)]
+enum SourceCommands {
+    /// Check each source's latest load time against its freshness thresholds
+    Freshness(SourceFreshnessArgs),
+}
+
+#[derive(Subcommand)]
+enum DocsCommands {
+    /// Render model docs, column schemas, and the lineage graph to target/docs/
+    Generate(DocsGenerateArgs),
+}
+
+#[derive(Parser, Default)]
 struct RunArgs {
     /// Path to smelt project root
     #[arg(long, default_value = ".")]
     project_dir: PathBuf,
 
-    /// DuckDB database file path
+    /// Database file path (DuckDB and SQLite targets)
     #[arg(long)]
     database: Option<PathBuf>,
 
@@ -41,6 +136,10 @@ struct RunArgs {
     #[arg(long, default_value = "dev")]
     target: String,
 
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
     /// Display query results after execution
     #[arg(long)]
     show_results: bool,
@@ -53,354 +152,3165 @@ struct RunArgs {
     #[arg(long)]
     dry_run: bool,
 
-    /// Start of event time range for incremental models (ISO 8601: YYYY-MM-DD)
+    /// With --dry-run, also have the target plan each model's compiled SQL
+    /// (EXPLAIN) to catch syntax and dialect errors
+    #[arg(long, requires = "dry_run")]
+    check_sql: bool,
+
+    /// Write each model's query plan to target/plans/ instead of executing it
+    #[arg(long, conflicts_with = "dry_run")]
+    explain: bool,
+
+    /// With --explain, run each query to report actual row counts and timings
+    /// (EXPLAIN ANALYZE); nothing is materialized
+    #[arg(long, requires = "explain")]
+    analyze: bool,
+
+    /// Start of event time range for incremental models (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)
     #[arg(long = "event-time-start", requires = "event_time_end")]
     event_time_start: Option<String>,
 
-    /// End of event time range for incremental models (exclusive, ISO 8601: YYYY-MM-DD)
+    /// End of event time range for incremental models (exclusive; YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)
     #[arg(long = "event-time-end", requires = "event_time_start")]
     event_time_end: Option<String>,
-}
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-
-    match cli.command {
-        Commands::Run(args) => run(args).await,
-    }
-}
-
-async fn run(args: RunArgs) -> Result<()> {
-    // 1. Find project root
-    let project_dir = find_project_root(&args.project_dir)
-        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
-
-    println!("Project directory: {}", project_dir.display());
-
-    // 2. Load configuration
-    let config =
-        Config::load(&project_dir).with_context(|| "Failed to load smelt.yml configuration")?;
-
-    println!("Project: {} (version {})", config.name, config.version);
 
-    // Get target config
-    let target_config = config.targets.get(&args.target).ok_or_else(|| {
-        anyhow::anyhow!(
-            "Target '{}' not found in smelt.yml. Available targets: {}",
-            args.target,
-            config
-                .targets
-                .keys()
-                .cloned()
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
-    })?;
+    /// Rebuild incremental models from scratch instead of updating partitions
+    #[arg(long)]
+    full_refresh: bool,
 
-    // Load source configuration (optional)
-    let sources = SourceConfig::load(&project_dir).ok();
+    /// Build every model with no rows, to check the whole DAG's SQL and
+    /// schemas without moving data; incremental models are rebuilt empty
+    #[arg(long, conflicts_with_all = ["dry_run", "explain", "watch", "event_time_start"])]
+    empty: bool,
 
-    if let Some(ref source_config) = sources {
-        let source_count: usize = source_config.sources.values().map(|s| s.tables.len()).sum();
-        println!("Loaded {} source tables", source_count);
-    }
+    /// Rebuild models even if their SQL and upstream models are unchanged
+    /// since they were last built
+    #[arg(long)]
+    no_cache: bool,
 
-    // 3. Discover models
-    let discovery = ModelDiscovery::new(project_dir.clone(), config.model_paths.clone());
-    let models = discovery
-        .discover_models()
-        .with_context(|| "Failed to discover models")?;
+    /// Only run the selected models (e.g. `my_model+`, `+my_model`, `tag:daily`, `models/staging/*`)
+    #[arg(long, short = 's', num_args = 1..)]
+    select: Vec<String>,
 
-    println!("Found {} models", models.len());
+    /// Skip the selected models (same syntax as --select)
+    #[arg(long, num_args = 1..)]
+    exclude: Vec<String>,
 
-    // Report any parse errors
-    for model in &models {
-        if !model.parse_errors.is_empty() {
-            eprintln!("\nWarning: Parse errors in {}:", model.name);
-            for error in &model.parse_errors {
-                eprintln!("  - {} at {:?}", error.message, error.range);
-            }
-        }
-    }
+    /// Previous manifest.json (or the directory containing it) for `state:` selectors
+    #[arg(long)]
+    state: Option<PathBuf>,
 
-    // 4. Build dependency graph
-    let graph = DependencyGraph::build(models, sources.as_ref())
-        .with_context(|| "Failed to build dependency graph")?;
+    /// Resolve refs to unselected models to where the --state manifest built them,
+    /// so they needn't exist in this target
+    #[arg(long, requires = "state")]
+    defer: bool,
 
-    graph
-        .validate()
-        .with_context(|| "Dependency validation failed")?;
+    /// Keep running and re-run changed models (and their downstreams) when files change
+    #[arg(long, conflicts_with_all = ["dry_run", "explain", "defer"])]
+    watch: bool,
 
-    // 5. Determine execution order
-    let execution_order = graph
-        .execution_order()
-        .with_context(|| "Failed to determine execution order")?;
+    /// Stop the run at the first failing model (the default)
+    #[arg(long, conflicts_with = "keep_going")]
+    fail_fast: bool,
 
-    println!(
-        "\nExecution order: {}",
-        execution_order
-            .iter()
-            .enumerate()
-            .map(|(i, name)| format!("{}. {}", i + 1, name))
-            .collect::<Vec<_>>()
-            .join(" → ")
-    );
+    /// After a failure, keep running models that don't depend on the failed model
+    #[arg(long)]
+    keep_going: bool,
 
-    if args.dry_run {
-        println!("\n[DRY RUN] Skipping execution");
-        return Ok(());
-    }
+    /// Show a live display of running models (only when stdout is a terminal)
+    #[arg(long)]
+    progress: bool,
 
-    // 6. Create backend based on target type
-    let backend: Box<dyn Backend> = match target_config.backend_type() {
-        BackendType::DuckDB => {
-            let database = target_config
-                .database
-                .as_ref()
-                .ok_or_else(|| anyhow::anyhow!("DuckDB target requires 'database' field"))?;
+    /// Log format; `json` writes one event per line to stdout and moves other output to stderr
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
 
-            let db_path = args.database.unwrap_or_else(|| project_dir.join(database));
-            println!("\nBackend: DuckDB");
-            println!("Database: {}", db_path.display());
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable progress output
+    #[default]
+    Text,
+    /// Structured JSON events (model_start, model_success, model_error, ...)
+    Json,
+}
 
-            Box::new(
-                DuckDbBackend::new(&db_path, &target_config.schema)
-                    .await
-                    .with_context(|| format!("Failed to initialize DuckDB at {:?}", db_path))?,
-            )
-        }
-        BackendType::Spark => {
-            #[cfg(feature = "spark")]
-            {
-                let connect_url = target_config
-                    .connect_url
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("Spark target requires 'connect_url' field"))?;
+/// Set by `--log-format json` so stdout carries only JSON events.
+static JSON_LOGS: AtomicBool = AtomicBool::new(false);
 
-                let default_catalog = "spark_catalog".to_string();
-                let catalog = target_config.catalog.as_ref().unwrap_or(&default_catalog);
+/// Set from `--wait`: absent, present, or present with a timeout in seconds.
+static WAIT_FOR_LOCK: OnceLock<Option<Option<u64>>> = OnceLock::new();
 
-                println!("\nBackend: Spark");
-                println!("Connect URL: {}", connect_url);
-                println!("Catalog: {}", catalog);
+/// Set by `--progress` when stdout is a terminal.
+static PROGRESS: OnceLock<RunProgress> = OnceLock::new();
 
-                Box::new(
-                    SparkBackend::new(connect_url, catalog, &target_config.schema)
-                        .await
-                        .with_context(|| {
-                            format!("Failed to connect to Spark at {}", connect_url)
-                        })?,
-                )
-            }
-            #[cfg(not(feature = "spark"))]
-            {
-                return Err(anyhow::anyhow!(
-                    "Spark backend not available. Rebuild with --features spark"
-                ));
-            }
+/// Print human-readable output: stdout normally (above any progress bars),
+/// stderr when logging JSON.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if JSON_LOGS.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else if let Some(progress) = PROGRESS.get() {
+            progress.println(format!($($arg)*))
+        } else {
+            println!($($arg)*)
         }
     };
+}
 
-    // 7. Validate sources exist (if sources.yml present)
-    if let Some(ref source_config) = sources {
-        executor::validate_sources(backend.as_ref(), source_config)
-            .await
-            .with_context(|| "Source validation failed")?;
+/// Write a structured event to stdout when logging JSON.
+fn emit(event: RunEvent) {
+    if JSON_LOGS.load(Ordering::Relaxed) {
+        println!("{}", event.to_json_line());
     }
+}
 
-    // 8. Parse time range if provided (for incremental processing)
-    let time_range = match (&args.event_time_start, &args.event_time_end) {
-        (Some(start), Some(end)) => {
-            // Validate date format
-            NaiveDate::parse_from_str(start, "%Y-%m-%d").with_context(|| {
-                format!("Invalid start date format: {}. Expected YYYY-MM-DD", start)
-            })?;
-            NaiveDate::parse_from_str(end, "%Y-%m-%d").with_context(|| {
-                format!("Invalid end date format: {}. Expected YYYY-MM-DD", end)
-            })?;
-
-            println!("\nTime range: {} to {} (exclusive)", start, end);
-            Some(TimeRange {
-                start: start.clone(),
-                end: end.clone(),
-            })
-        }
-        _ => None,
-    };
-
-    // 9. Compile and execute each model
-    let compiler = SqlCompiler::new(config.clone());
+/// Everything needed to execute models against a target.
+struct RunContext<'a> {
+    args: &'a RunArgs,
+    config: &'a Config,
+    packages: &'a [Package],
+    sources: Option<&'a SourceConfig>,
+    project_dir: &'a Path,
+    schema: &'a str,
+    backend: &'a dyn Backend,
+    time_range: Option<&'a TimeRange>,
+    /// Schemas of unselected models ref'd from the `--state` manifest (`--defer`)
+    deferred: &'a HashMap<String, String>,
+    /// Cancelled on Ctrl+C
+    cancel: &'a CancellationToken,
+}
 
-    println!("\n{}", "=".repeat(60));
-    println!("Executing models...");
-    println!("{}", "=".repeat(60));
+#[derive(Parser)]
+struct BackfillArgs {
+    /// Incremental model to backfill
+    #[arg(long)]
+    model: String,
 
-    let mut results = Vec::new();
+    /// Start of the range (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)
+    #[arg(long)]
+    from: String,
 
-    for model_name in &execution_order {
-        let model = graph.get_model(model_name)?;
+    /// End of the range (exclusive)
+    #[arg(long)]
+    to: String,
 
-        // Check if this model should be run incrementally
-        // SQL metadata takes precedence over smelt.yml
-        let inc_config = config
-            .get_incremental_with_metadata(model_name, model.metadata.as_ref().map(|b| b.as_ref()));
-        let is_incremental = time_range.is_some() && inc_config.is_some();
+    /// Size of each chunk as hours, days, or weeks (e.g. `12h`, `7d`, `2w`);
+    /// rounded up to whole partitions
+    #[arg(long, default_value = "1d")]
+    chunk: String,
 
-        if is_incremental {
-            let range = time_range.as_ref().unwrap();
-            let inc = inc_config.unwrap();
+    /// Chunks to run at once after the first; merge models only run one at a time
+    #[arg(long, default_value_t = 1)]
+    parallel: usize,
 
-            println!("\n▶ Running model: {} (incremental)", model_name);
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
 
-            // Transform SQL to filter by time range
-            let transformed_sql = inject_time_filter(&model.content, &inc.event_time_column, range)
-                .with_context(|| format!("Failed to transform SQL for model: {}", model_name))?;
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
 
-            // Compile with transformed SQL
-            let compiled = compiler
-                .compile_with_sql(model, &target_config.schema, &transformed_sql)
-                .with_context(|| format!("Failed to compile model: {}", model_name))?;
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
 
-            if args.verbose {
-                println!("\n  Transformed SQL:");
-                println!("  {}", "─".repeat(58));
-                for line in compiled.sql.lines() {
-                    println!("  {}", line);
-                }
-                println!("  {}", "─".repeat(58));
-            }
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
 
-            // Generate partition values for DELETE
-            let partition_values = generate_partition_dates(&range.start, &range.end)?;
-            println!(
-                "  Partitions to update: {} ({} days)",
-                if partition_values.len() <= 3 {
-                    partition_values.join(", ")
-                } else {
-                    format!(
-                        "{}, ..., {}",
-                        partition_values.first().unwrap(),
-                        partition_values.last().unwrap()
-                    )
-                },
-                partition_values.len()
-            );
+    /// Show compiled SQL for each chunk
+    #[arg(long, short)]
+    verbose: bool,
+}
 
-            let partition = PartitionSpec {
-                column: inc.partition_column.clone(),
-                values: partition_values,
-            };
+#[derive(Parser)]
+struct CompileArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
 
-            // Execute incrementally
-            let result = executor::execute_model_incremental(
-                backend.as_ref(),
-                &compiled,
-                &target_config.schema,
-                partition,
-                args.show_results,
-            )
-            .await
-            .with_context(|| format!("Failed to execute model: {}", model_name))?;
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
 
-            println!(
-                "  ✓ {} ({} rows, {:?})",
-                result.model_name, result.row_count, result.duration
-            );
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
 
-            // Show preview if requested
-            if let Some(ref batches) = result.preview {
-                println!("\n  Preview:");
-                pretty::print_batches(batches).with_context(|| "Failed to print result preview")?;
-                println!();
-            }
+    /// Only compile the selected models (same syntax as `smelt run --select`)
+    #[arg(long, short = 's', num_args = 1..)]
+    select: Vec<String>,
 
-            results.push(result);
-        } else {
-            // Standard full refresh path
-            if time_range.is_some() && inc_config.is_none() {
-                println!(
-                    "\n▶ Running model: {} (full refresh - not configured for incremental)",
-                    model_name
-                );
-            } else {
-                println!("\n▶ Running model: {}", model_name);
-            }
+    /// Skip the selected models
+    #[arg(long, num_args = 1..)]
+    exclude: Vec<String>,
 
-            // Compile
-            let compiled = compiler
-                .compile(model, &target_config.schema)
-                .with_context(|| format!("Failed to compile model: {}", model_name))?;
+    /// Previous manifest.json (or the directory containing it) for `state:` selectors
+    #[arg(long)]
+    state: Option<PathBuf>,
+}
 
-            if args.verbose {
-                println!("\n  Compiled SQL:");
-                println!("  {}", "─".repeat(58));
-                for line in compiled.sql.lines() {
-                    println!("  {}", line);
-                }
-                println!("  {}", "─".repeat(58));
-            }
+#[derive(Parser)]
+struct SeedArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
 
-            // Execute
-            let result = executor::execute_model(
-                backend.as_ref(),
-                &compiled,
-                &target_config.schema,
-                args.show_results,
-            )
-            .await
-            .with_context(|| format!("Failed to execute model: {}", model_name))?;
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
 
-            println!(
-                "  ✓ {} ({} rows, {:?})",
-                result.model_name, result.row_count, result.duration
-            );
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
 
-            // Show preview if requested
-            if let Some(ref batches) = result.preview {
-                println!("\n  Preview:");
-                pretty::print_batches(batches).with_context(|| "Failed to print result preview")?;
-                println!();
-            }
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
 
-            results.push(result);
-        }
-    }
+    /// Drop and recreate seed tables instead of truncating and reloading
+    #[arg(long)]
+    full_refresh: bool,
+}
 
-    // 9. Summary
-    println!("\n{}", "=".repeat(60));
-    println!("Summary");
-    println!("{}", "=".repeat(60));
-    println!("✓ Executed {} models successfully", results.len());
+#[derive(Parser)]
+struct InitArgs {
+    /// Project name, used in smelt.yml
+    name: String,
 
-    let total_duration: std::time::Duration = results.iter().map(|r| r.duration).sum();
-    println!("  Total time: {:?}", total_duration);
+    /// Directory to create the project in (defaults to ./<name>)
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct ValidateArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+}
+
+#[derive(Parser)]
+struct LintArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Rewrite model files to fix the violations that can be fixed safely
+    #[arg(long)]
+    fix: bool,
+}
+
+#[derive(Parser)]
+struct LsArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Only list the selected models (same syntax as `smelt run --select`)
+    #[arg(long, short = 's', num_args = 1..)]
+    select: Vec<String>,
+
+    /// Skip the selected models
+    #[arg(long, num_args = 1..)]
+    exclude: Vec<String>,
+
+    /// Previous manifest.json (or the directory containing it) for `state:` selectors
+    #[arg(long)]
+    state: Option<PathBuf>,
+
+    /// Only list resources of this type
+    #[arg(long, value_enum)]
+    resource_type: Option<LsResourceType>,
+
+    /// Output format
+    #[arg(long, short, value_enum, default_value_t = LsOutput::Text)]
+    output: LsOutput,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LsResourceType {
+    Model,
+    Source,
+    Exposure,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LsOutput {
+    /// One resource per line
+    Text,
+    /// JSON array for scripting
+    Json,
+}
+
+#[derive(Parser)]
+struct QueryArgs {
+    /// SQL to run; starts an interactive shell if omitted
+    sql: Option<String>,
+
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Maximum number of rows to print per statement
+    #[arg(long, default_value_t = 1000)]
+    limit: usize,
+
+    /// Show the compiled SQL before running it
+    #[arg(long, short)]
+    verbose: bool,
+}
+
+#[derive(Parser)]
+struct LineageArgs {
+    /// Model or column to trace, e.g. `users` or `users.email`
+    target: String,
+
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Which way to trace
+    #[arg(long, value_enum, default_value_t = LineageDirection::Both)]
+    direction: LineageDirection,
+
+    /// Output format
+    #[arg(long, short, value_enum, default_value_t = LineageOutput::Text)]
+    output: LineageOutput,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LineageDirection {
+    Upstream,
+    Downstream,
+    Both,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LineageOutput {
+    /// Indented trees
+    Text,
+    /// Graphviz digraph, e.g. for `dot -Tsvg`
+    Dot,
+}
+
+#[derive(Parser)]
+struct RunOperationArgs {
+    /// Operation to run (the name of a .sql file in operations/)
+    name: String,
+
+    /// Arguments for the operation's `{{ var() }}` as a YAML mapping, e.g. `{model: users}`
+    #[arg(long)]
+    args: Option<String>,
+
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Show each compiled statement before running it
+    #[arg(long, short)]
+    verbose: bool,
+}
+
+#[derive(Parser)]
+struct DepsArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+}
+
+#[derive(Parser)]
+struct DebugArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+}
+
+#[derive(Parser)]
+struct ShowArgs {
+    /// Model to preview
+    model: String,
+
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Maximum number of rows to show
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+
+    /// Run the compiled SELECT even if the model is already materialized
+    #[arg(long)]
+    inline: bool,
+
+    /// Show the compiled SQL
+    #[arg(long, short)]
+    verbose: bool,
+}
+
+#[derive(Parser)]
+struct DiffArgs {
+    /// Model to compare
+    model: String,
+
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// First target from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target_a: String,
+
+    /// Second target from smelt.yml
+    #[arg(long)]
+    target_b: String,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Maximum number of differing rows to show from each target
+    #[arg(long, default_value_t = 10)]
+    limit: usize,
+}
+
+#[derive(Parser)]
+struct SourceFreshnessArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Database file path (DuckDB and SQLite targets)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+}
+
+#[derive(Parser)]
+struct DocsGenerateArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Variables for `{{ var() }}` as a YAML mapping, e.g. `{schema: dev, days: 7}`
+    #[arg(long)]
+    vars: Option<String>,
+
+    /// Output directory (defaults to target/docs under the project root)
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = DocsFormat::Html)]
+    format: DocsFormat,
+
+    /// Read column types of built models and sources from the target backend
+    #[arg(long)]
+    catalog: bool,
+
+    /// Database file path (DuckDB and SQLite targets), used with --catalog
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Target environment from smelt.yml, used with --catalog
+    #[arg(long, default_value = "dev")]
+    target: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DocsFormat {
+    /// Static HTML site (index.html) plus docs.json
+    Html,
+    /// JSON bundle only (docs.json)
+    Json,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let _ = WAIT_FOR_LOCK.set(cli.wait);
+    let is_run = matches!(cli.command, Commands::Run(_));
+
+    let result = match cli.command {
+        Commands::Init(args) => init(args),
+        Commands::Run(args) => run(args).await,
+        Commands::Backfill(args) => backfill(args).await,
+        Commands::Compile(args) => compile(args),
+        Commands::Seed(args) => seed(args).await,
+        Commands::Validate(args) => validate(args),
+        Commands::Lint(args) => lint(args),
+        Commands::Ls(args) => ls(args),
+        Commands::Query(args) => query(args).await,
+        Commands::Show(args) => show(args).await,
+        Commands::Diff(args) => diff(args).await,
+        Commands::Lineage(args) => lineage(args),
+        Commands::RunOperation(args) => run_operation(args).await,
+        Commands::Debug(args) => debug(args).await,
+        Commands::Deps(args) => deps(args),
+        Commands::Docs(DocsCommands::Generate(args)) => docs_generate(args).await,
+        Commands::Source(SourceCommands::Freshness(args)) => source_freshness(args).await,
+    };
+
+    if is_run {
+        emit(RunEvent::run_summary(&result));
+    }
+    if let Err(e) = &result {
+        eprintln!("Error: {:?}", e);
+    }
+    let outcome = Outcome::of(&result);
+    if outcome == Outcome::Interrupted || executor::abandoned_statements() {
+        // Exit without waiting for statements the backend couldn't interrupt
+        std::process::exit(outcome.exit_code().into());
+    }
+    ExitCode::from(outcome.exit_code())
+}
+
+/// A token cancelled on the first Ctrl+C; a second Ctrl+C exits immediately.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("\nInterrupted; cancelling running statements (Ctrl+C again to exit now)");
+            token.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(Outcome::Interrupted.exit_code().into());
+            }
+        }
+    });
+    cancel
+}
+
+async fn run(args: RunArgs) -> Result<()> {
+    let started = Instant::now();
+    JSON_LOGS.store(args.log_format == LogFormat::Json, Ordering::Relaxed);
+    if args.progress && args.log_format == LogFormat::Text {
+        if let Some(progress) = RunProgress::stdout() {
+            let _ = PROGRESS.set(progress);
+        }
+    }
+
+    // 1. Find project root
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+
+    say!("Project directory: {}", project_dir.display());
+
+    // 2. Load configuration
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+
+    say!("Project: {} (version {})", config.name, config.version);
+
+    if config.notifications.is_empty() || args.dry_run || args.explain {
+        return run_project(&args, &project_dir, &config).await;
+    }
+    if args.watch {
+        return Err(CliError::NotificationsWithWatch.into());
+    }
+
+    let result = run_project(&args, &project_dir, &config).await;
+    let run = RunNotification::new(&config.name, &args.target, started.elapsed(), &result);
+    for e in send_notifications(&config.notifications, &run).await {
+        eprintln!("  ⚠ {:#}", e);
+    }
+    result
+}
+
+/// Build the project's selected models once loaded, or watch them with
+/// `--watch`.
+async fn run_project(args: &RunArgs, project_dir: &Path, config: &Config) -> Result<()> {
+    // Get target config
+    let target_config = get_target(config, &args.target)?;
+
+    // Load source configuration (optional)
+    let packages = load_packages(config, project_dir)?;
+    let sources = load_sources(project_dir, &packages)?;
+
+    if let Some(ref source_config) = sources {
+        let source_count: usize = source_config.sources.values().map(|s| s.tables.len()).sum();
+        say!("Loaded {} source tables", source_count);
+    }
+
+    // 3-4. Discover models and build dependency graph
+    let graph = build_graph(project_dir, config, &packages, sources.as_ref())?;
+
+    // 5. Determine execution order
+    let mut execution_order = graph
+        .execution_order()
+        .with_context(|| "Failed to determine execution order")?;
+
+    let state = load_state(args.state.as_deref())?;
+    if !args.select.is_empty() || !args.exclude.is_empty() {
+        let selected = select_models(
+            &graph,
+            config,
+            project_dir,
+            &args.select,
+            &args.exclude,
+            state.as_ref(),
+        )
+        .with_context(|| "Failed to resolve model selection")?;
+        execution_order.retain(|name| selected.contains(name));
+
+        say!(
+            "Selected {} of {} models",
+            execution_order.len(),
+            graph.models().len()
+        );
+    }
+
+    say!(
+        "\nExecution order: {}",
+        execution_order
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{}. {}", i + 1, name))
+            .collect::<Vec<_>>()
+            .join(" → ")
+    );
+
+    let deferred = match &state {
+        Some(state) if args.defer => {
+            let deferred = state.deferred_schemas(&graph, &execution_order)?;
+            say!(
+                "Deferring {} unselected models to the state manifest's {} target",
+                deferred.len(),
+                state.metadata.target
+            );
+            deferred
+        }
+        _ => HashMap::new(),
+    };
+
+    if args.dry_run {
+        check_contracts(config, &graph, &execution_order)?;
+        if args.check_sql {
+            let backend = create_backend(target_config, args.database.clone(), project_dir).await?;
+            let compiler = SqlCompiler::new(config.clone())
+                .with_models(graph.models().values())
+                .with_deferred(deferred)
+                .with_backend(backend.as_ref());
+            check_models_sql(
+                backend.as_ref(),
+                &compiler,
+                &graph,
+                &execution_order,
+                &target_config.schema,
+            )
+            .await?;
+        }
+        say!("\n[DRY RUN] Skipping execution");
+        return Ok(());
+    }
+
+    // 6. Create backend based on target type
+    let backend = create_backend(target_config, args.database.clone(), project_dir).await?;
+    check_timeouts(backend.as_ref(), config, &execution_order)?;
+
+    // 7. Validate sources exist (if sources.yml present)
+    if let Some(ref source_config) = sources {
+        executor::validate_sources(backend.as_ref(), source_config)
+            .await
+            .with_context(|| "Source validation failed")?;
+    }
+
+    // 8. Parse time range if provided (for incremental processing)
+    let time_range = match (&args.event_time_start, &args.event_time_end) {
+        (Some(start), Some(end)) => {
+            let range = parse_time_range(start, end)?;
+            say!("\nTime range: {} to {} (exclusive)", start, end);
+            Some(range)
+        }
+        _ => None,
+    };
+    if time_range.is_some() && !args.full_refresh && !args.empty {
+        let incremental = execution_order.iter().filter_map(|name| {
+            let model = graph.models().get(name)?;
+            let incremental = config
+                .get_incremental_with_metadata(name, model.metadata.as_ref().map(|b| b.as_ref()))?;
+            Some((name.as_str(), incremental))
+        });
+        executor::check_incremental_strategies(backend.as_ref(), incremental)?;
+        check_event_time_columns(
+            project_dir,
+            config,
+            &graph,
+            sources.as_ref(),
+            &execution_order,
+        )?;
+    }
+
+    // 9. Compile and execute each model
+    let cancel = cancel_on_ctrl_c();
+    let ctx = RunContext {
+        args,
+        config,
+        packages: &packages,
+        sources: sources.as_ref(),
+        project_dir,
+        schema: &target_config.schema,
+        backend: backend.as_ref(),
+        time_range: time_range.as_ref(),
+        deferred: &deferred,
+        cancel: &cancel,
+    };
+
+    if args.explain {
+        return explain_models(&ctx, &graph, &execution_order).await;
+    }
+
+    if args.watch {
+        if let Err(e) = execute_models(&ctx, &graph, &execution_order).await {
+            eprintln!("\n✗ {:#}", e);
+        }
+        return watch(&ctx, sources.as_ref(), graph).await;
+    }
+
+    execute_models(&ctx, &graph, &execution_order).await
+}
+
+/// Check contracts against the column names inferred from each model's SQL.
+///
+/// Column types (and models whose columns can't be inferred) are only checked
+/// when the model is built.
+fn check_contracts(
+    config: &Config,
+    graph: &DependencyGraph,
+    execution_order: &[String],
+) -> Result<()> {
+    let mut violated = Vec::new();
+
+    for model_name in execution_order {
+        let Some(contract) = config.get_contract(model_name) else {
+            continue;
+        };
+        let model = graph.get_model(model_name)?;
+        if config.get_program(model_name).is_some() {
+            say!(
+                "  ? {}: built by a program; contract will be checked at run time",
+                model_name
+            );
+            continue;
+        }
+
+        let Some(columns) = inferred_columns(model) else {
+            say!(
+                "  ? {}: columns can't be inferred from SQL; contract will be checked at run time",
+                model_name
+            );
+            continue;
+        };
+
+        let violations = check_contract_names(contract, &columns);
+        if violations.is_empty() {
+            say!("  ✓ {} satisfies its contract", model_name);
+        } else {
+            eprintln!(
+                "\n✗ {}",
+                CliError::ContractViolation {
+                    model: model_name.clone(),
+                    violations,
+                }
+            );
+            violated.push(model_name.as_str());
+        }
+    }
+
+    if violated.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ChecksFailed {
+            message: format!("Contract violated by: {}", violated.join(", ")),
+        }
+        .into())
+    }
+}
+
+/// Have the target plan each model's compiled SQL without running it.
+///
+/// Nothing is built, so models reading upstream models that don't exist in the
+/// target yet can't be checked; they're reported but don't fail the check.
+async fn check_models_sql(
+    backend: &dyn Backend,
+    compiler: &SqlCompiler,
+    graph: &DependencyGraph,
+    execution_order: &[String],
+    schema: &str,
+) -> Result<()> {
+    say!(
+        "\nChecking SQL against the {} target...",
+        backend.dialect().name()
+    );
+
+    let mut invalid = Vec::new();
+    let mut unchecked = 0;
+    for model_name in execution_order {
+        // Ephemeral models are checked as part of their downstream models
+        if compiler.is_ephemeral(model_name) {
+            continue;
+        }
+        if compiler.is_program(model_name) {
+            say!("  - {}: built by a program, not SQL", model_name);
+            continue;
+        }
+        let model = graph.get_model(model_name)?;
+        let compiled = compiler
+            .compile(model, schema)
+            .with_context(|| format!("Failed to compile model: {}", model_name))?;
+
+        match executor::check_sql(backend, &compiled).await {
+            Ok(SqlCheck::Valid) => say!("  ✓ {}", model_name),
+            Ok(SqlCheck::Unchecked(reason)) => {
+                say!(
+                    "  ? {}: not checked until its inputs exist ({})",
+                    model_name,
+                    reason
+                );
+                unchecked += 1;
+            }
+            Err(e) => {
+                eprintln!("\n✗ {}", e);
+                invalid.push(model_name.as_str());
+            }
+        }
+    }
+
+    if unchecked > 0 {
+        say!(
+            "\n{} models read tables that don't exist yet; run upstream models or use --defer to check them",
+            unchecked
+        );
+    }
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ChecksFailed {
+            message: format!("SQL rejected by the target: {}", invalid.join(", ")),
+        }
+        .into())
+    }
+}
+
+/// Execute models in order, write run artifacts, and print a summary.
+///
+/// Models that can't run because of an earlier failure are recorded as skipped
+/// (see [`skip_reason`]).
+async fn execute_models(
+    ctx: &RunContext<'_>,
+    graph: &DependencyGraph,
+    execution_order: &[String],
+) -> Result<()> {
+    let artifacts = artifacts_dir(ctx.project_dir);
+    let compiler = SqlCompiler::new(ctx.config.clone())
+        .with_models(graph.models().values())
+        .with_deferred(ctx.deferred.clone())
+        .with_backend(ctx.backend)
+        .with_cache(CompileCache::load(&artifacts));
+    let manifest = Manifest::build(
+        graph,
+        &compiler,
+        ctx.config,
+        ctx.project_dir,
+        &ctx.args.target,
+        ctx.schema,
+    )?;
+    compiler.write_cache(&artifacts)?;
+
+    // Ephemeral models are inlined into their downstream models, never run
+    let execution_order: Vec<&String> = execution_order
+        .iter()
+        .filter(|name| !compiler.is_ephemeral(name))
+        .collect();
+
+    say!("\n{}", "=".repeat(60));
+    say!("Executing models...");
+    say!("{}", "=".repeat(60));
+
+    emit(RunEvent::RunStart {
+        project: ctx.config.name.clone(),
+        target: ctx.args.target.clone(),
+        model_count: execution_order.len(),
+    });
+
+    let previous_row_counts = previous_row_counts(&artifacts, &ctx.args.target);
+
+    let run_started = Instant::now();
+    let mut results = Vec::new();
+    let mut node_results = Vec::new();
+    let mut started_at = Vec::new();
+    let mut failures: Vec<(String, anyhow::Error)> = Vec::new();
+
+    let cache_dir = cache_dir(ctx.project_dir);
+    let mut cache = BuildCache::load(&cache_dir, &ctx.config.name, &ctx.args.target);
+    let mut cached = 0;
+
+    for model_name in execution_order {
+        started_at.push(chrono::Utc::now());
+        if let Some(reason) = skip_reason(ctx, graph, model_name, &failures) {
+            let node_result = NodeResult::skipped(model_name, reason);
+            emit(RunEvent::from_result(&node_result));
+            node_results.push(node_result);
+            continue;
+        }
+
+        let model = graph.get_model(model_name)?;
+        let cache_key = cache.cache_key(&manifest, model_name);
+        if let Some(build) = cache_hit(ctx, &compiler, &cache, model, cache_key.as_deref()).await {
+            say!("\n↺ {} unchanged since {}", model_name, build.built_at);
+            let mut node_result = NodeResult::cached(model_name, build);
+            node_result.previous_row_count = previous_row_counts.get(model_name).copied();
+            emit(RunEvent::from_result(&node_result));
+            node_results.push(node_result);
+            cached += 1;
+            continue;
+        }
+
+        let started = Instant::now();
+
+        let bar = PROGRESS.get().map(|progress| progress.start(model_name));
+        let result = run_model_with_retries(ctx, &compiler, model).await;
+        drop(bar);
+
+        let node_result = match result {
+            Ok(result) if ctx.args.empty => {
+                // An empty build has no row count to compare or reuse
+                cache.invalidate(model_name);
+                let mut node_result = NodeResult::success(&result);
+                node_result.row_count = None;
+                results.push(result);
+                node_result
+            }
+            Ok(result) => {
+                let mut node_result = NodeResult::success(&result);
+                node_result.previous_row_count = previous_row_counts.get(model_name).copied();
+                if let (Some(previous), Some(threshold)) = (
+                    node_result.previous_row_count,
+                    ctx.config.get_row_count_warn_percent(model_name),
+                ) {
+                    let change = RowCountChange::new(previous, result.row_count);
+                    if change.exceeds(threshold) {
+                        say!(
+                            "  ⚠ {}: {}, more than the {}% allowed",
+                            model_name,
+                            change,
+                            threshold
+                        );
+                    }
+                }
+                match cache_key {
+                    Some(key) => cache.record(model_name, key, Some(result.row_count)),
+                    None => cache.invalidate(model_name),
+                }
+                results.push(result);
+                node_result
+            }
+            Err(e) => {
+                cache.invalidate(model_name);
+                eprintln!("  ✗ {} failed: {}", model_name, e.root_cause());
+                let node_result = NodeResult::error(model_name, started.elapsed(), &e);
+                failures.push((model_name.clone(), e));
+                node_result
+            }
+        };
+        emit(RunEvent::from_result(&node_result));
+        node_results.push(node_result);
+    }
+
+    // 10. Write artifacts
+    let metadata = ArtifactMetadata::new(&ctx.config.name, &ctx.args.target);
+    emit(RunEvent::run_end(&node_results, run_started.elapsed()));
+    let run_results = RunResults::new(metadata, run_started.elapsed(), node_results);
+    write_artifact(&artifacts, MANIFEST_FILE, &manifest)?;
+    write_artifact(&artifacts, RUN_RESULTS_FILE, &run_results)?;
+    cache.write(&cache_dir)?;
+
+    if ctx.config.run_history {
+        let entries: Vec<RunHistoryEntry> = run_results
+            .results
+            .iter()
+            .zip(started_at)
+            .map(|(result, started_at)| {
+                let compiled_sql = manifest.nodes.get(&result.name);
+                RunHistoryEntry::new(
+                    result,
+                    started_at,
+                    compiled_sql.map(|n| n.compiled_sql.as_str()),
+                )
+            })
+            .collect();
+        let run_id = executor::new_run_id();
+        if let Err(e) =
+            executor::record_run_history(ctx.backend, ctx.schema, &run_id, &entries).await
+        {
+            eprintln!("  ⚠ {:#}", e);
+        }
+    }
+
+    // 11. Summary
+    say!("\n{}", "=".repeat(60));
+    say!("Summary");
+    say!("{}", "=".repeat(60));
+    say!("✓ Executed {} models successfully", results.len());
+
+    if cached > 0 {
+        say!(
+            "↺ Reused {} unchanged models (--no-cache to rebuild them)",
+            cached
+        );
+    }
+
+    let total_duration: std::time::Duration = results.iter().map(|r| r.duration).sum();
+    say!("  Total time: {:?}", total_duration);
+
+    if !failures.is_empty() {
+        say!("\n✗ Failed ({}):", failures.len());
+        for (name, e) in &failures {
+            say!("  - {}: {}", name, e.root_cause());
+        }
+    }
+
+    let skipped: Vec<_> = run_results
+        .results
+        .iter()
+        .filter(|r| r.status == RunStatus::Skipped)
+        .collect();
+    if !skipped.is_empty() {
+        say!("\n⊘ Skipped ({}):", skipped.len());
+        for result in &skipped {
+            say!(
+                "  - {} ({})",
+                result.name,
+                result.message.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    say!("\n  Artifacts written to {}", artifacts.display());
+
+    let failed = failures.iter().map(|(name, _)| name.clone()).collect();
+    let skipped = skipped.iter().map(|r| r.name.clone()).collect();
+    if ctx.cancel.is_cancelled() {
+        return Err(CliError::Interrupted {
+            failed,
+            skipped,
+            succeeded: results.len(),
+        }
+        .into());
+    }
+
+    let run_failed = CliError::RunFailed {
+        failed,
+        skipped,
+        succeeded: results.len(),
+    };
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0).1.context(run_failed)),
+        _ => Err(run_failed.into()),
+    }
+}
+
+/// Write the backend's query plan for each model to target/plans/ instead of
+/// executing it.
+///
+/// Plans are made against the database as it is, so a model whose upstream
+/// models haven't been built yet fails to explain; the other models are still
+/// explained.
+async fn explain_models(
+    ctx: &RunContext<'_>,
+    graph: &DependencyGraph,
+    execution_order: &[String],
+) -> Result<()> {
+    let compiler = SqlCompiler::new(ctx.config.clone())
+        .with_models(graph.models().values())
+        .with_deferred(ctx.deferred.clone())
+        .with_backend(ctx.backend);
+    let output_dir = plans_dir(ctx.project_dir);
+
+    say!("\n{}", "=".repeat(60));
+    say!(
+        "Explaining models{}...",
+        if ctx.args.analyze { " (analyze)" } else { "" }
+    );
+    say!("{}", "=".repeat(60));
+
+    let mut explained = 0;
+    let mut failed = Vec::new();
+    for model_name in execution_order {
+        // Ephemeral models are explained as part of their downstream models,
+        // and program models have no SQL to explain
+        if compiler.is_ephemeral(model_name) || compiler.is_program(model_name) {
+            continue;
+        }
+        let model = graph.get_model(model_name)?;
+
+        let result = explain_model(ctx, &compiler, model)
+            .await
+            .and_then(|plan| write_plan(&output_dir, model_name, &plan));
+        match result {
+            Ok(path) => {
+                say!(
+                    "  ✓ {} → {}",
+                    model_name,
+                    path.strip_prefix(ctx.project_dir)
+                        .unwrap_or(&path)
+                        .display()
+                );
+                explained += 1;
+            }
+            Err(e) => {
+                eprintln!("  ✗ {}: {}", model_name, e.root_cause());
+                failed.push(model_name.as_str());
+            }
+        }
+    }
+
+    say!(
+        "\n✓ Wrote plans for {} models to {}",
+        explained,
+        output_dir.display()
+    );
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ChecksFailed {
+            message: format!("Couldn't explain: {}", failed.join(", ")),
+        }
+        .into())
+    }
+}
+
+/// Compile a model as `smelt run` would and ask the backend for its plan.
+async fn explain_model(
+    ctx: &RunContext<'_>,
+    compiler: &SqlCompiler,
+    model: &ModelFile,
+) -> Result<String> {
+    let RunContext {
+        args,
+        config,
+        schema,
+        backend,
+        time_range,
+        ..
+    } = *ctx;
+    let model_name = &model.name;
+
+    let inc_config = config
+        .get_model_incremental(model, ctx.sources)
+        .filter(|_| !args.full_refresh);
+    let compiled = match (time_range, inc_config) {
+        (Some(range), Some(inc)) => {
+            let range = &align_time_range(range, inc.partition_granularity)?;
+            let time_sources = ctx
+                .sources
+                .map(|sources| sources.event_time_sources(model))
+                .unwrap_or_default();
+            let filtered =
+                inject_time_filter(&model.content, &inc.event_time_column, &time_sources, range)
+                    .with_context(|| {
+                        format!("Failed to transform SQL for model: {}", model_name)
+                    })?;
+            compiler.compile_with_sql(model, schema, &filtered.sql)
+        }
+        _ => compiler.compile(model, schema),
+    }
+    .with_context(|| format!("Failed to compile model: {}", model_name))?;
+
+    if args.verbose {
+        print_sql("Compiled SQL", &compiled.sql);
+    }
+
+    backend
+        .explain(&compiled.sql, args.analyze)
+        .await
+        .with_context(|| format!("Failed to explain model: {}", model_name))
+}
+
+/// The earlier build of `model` to reuse instead of running it, if there's
+/// one built from the same SQL and upstream builds that still exists.
+///
+/// Incremental runs over a time range, full refreshes, empty runs, external
+/// models, and program models always run.
+async fn cache_hit<'a>(
+    ctx: &RunContext<'_>,
+    compiler: &SqlCompiler,
+    cache: &'a BuildCache,
+    model: &ModelFile,
+    cache_key: Option<&str>,
+) -> Option<&'a CachedBuild> {
+    let RunContext { args, config, .. } = *ctx;
+    if args.no_cache || args.full_refresh || args.empty {
+        return None;
+    }
+    let incremental = config
+        .get_incremental_with_metadata(&model.name, model.metadata.as_ref().map(|b| b.as_ref()))
+        .is_some();
+    if (incremental && ctx.time_range.is_some())
+        || config.get_model_materialization(model) == Materialization::External
+        || config.get_program(&model.name).is_some()
+    {
+        return None;
+    }
+
+    let build = cache.hit(&model.name, cache_key?)?;
+    let relation = compiler.relation_for(&model.name, ctx.schema);
+    match ctx.backend.table_exists(&relation).await {
+        Ok(true) => Some(build),
+        _ => None,
+    }
+}
+
+/// Why a model should be skipped given the failures so far, if it should be.
+///
+/// With `--keep-going` only models downstream of a failure are skipped;
+/// otherwise the first failure stops the run. Ctrl+C always stops it.
+fn skip_reason(
+    ctx: &RunContext<'_>,
+    graph: &DependencyGraph,
+    model_name: &str,
+    failures: &[(String, anyhow::Error)],
+) -> Option<String> {
+    if ctx.cancel.is_cancelled() {
+        return Some("run interrupted".to_string());
+    }
+
+    if !ctx.args.keep_going {
+        return failures
+            .first()
+            .map(|(failed, _)| format!("run stopped after '{}' failed", failed));
+    }
+
+    let upstream = graph.upstream(model_name);
+    failures
+        .iter()
+        .find(|(failed, _)| upstream.contains(failed))
+        .map(|(failed, _)| format!("upstream model '{}' failed", failed))
+}
+
+/// Fail if SQL models in `models` have a timeout the backend can't enforce;
+/// programs are stopped by killing their process instead.
+fn check_timeouts(backend: &dyn Backend, config: &Config, models: &[String]) -> Result<()> {
+    executor::check_timeouts(
+        backend,
+        models
+            .iter()
+            .filter(|name| config.get_program(name).is_none())
+            .map(|name| (name.as_str(), config.get_timeout(name))),
+    )
+}
+
+/// Run a model, retrying with backoff after transient backend errors.
+///
+/// Each attempt is cancelled on Ctrl+C or after the model's `timeout_seconds`.
+async fn run_model_with_retries(
+    ctx: &RunContext<'_>,
+    compiler: &SqlCompiler,
+    model: &ModelFile,
+) -> Result<ExecutionResult> {
+    let retries = ctx.config.get_retries(&model.name);
+    let timeout = ctx.config.get_timeout(&model.name);
+    let mut attempt = 0;
+
+    loop {
+        let work = run_model(ctx, compiler, model);
+        match executor::run_cancellable(ctx.backend, &model.name, timeout, ctx.cancel, work).await {
+            Err(e) if attempt < retries && executor::is_transient(&e) => {
+                attempt += 1;
+                let delay = executor::retry_delay(attempt);
+                say!(
+                    "  ↻ Transient error: {} (retry {}/{} in {:?})",
+                    e.root_cause(),
+                    attempt,
+                    retries,
+                    delay
+                );
+                emit(RunEvent::ModelRetry {
+                    model: model.name.clone(),
+                    attempt,
+                    delay_secs: delay.as_secs_f64(),
+                    message: e.root_cause().to_string(),
+                });
+                tokio::select! {
+                    _ = ctx.cancel.cancelled() => return Err(e),
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Run an incremental model over `--from`..`--to` one chunk at a time.
+///
+/// The first chunk always runs alone, since it may create the table. With
+/// `--parallel`, later chunks then run concurrently; they replace disjoint
+/// partitions, which is only safe for strategies that don't match on keys.
+async fn backfill(args: BackfillArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = build_graph(&project_dir, &config, &packages, sources.as_ref())?;
+
+    let model = graph.get_model(&args.model)?;
+    let incremental = config
+        .get_model_incremental(model, sources.as_ref())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Model '{}' isn't configured for incremental runs, so it can't be backfilled",
+                model.name
+            )
+        })?;
+    if args.parallel > 1 && incremental.incremental_strategy == IncrementalStrategy::Merge {
+        return Err(anyhow::anyhow!(
+            "--parallel isn't supported for '{}': merge chunks may update the same keys",
+            model.name
+        ));
+    }
+
+    let range = parse_time_range(&args.from, &args.to)?;
+    let chunk = parse_chunk(&args.chunk)?;
+    let granularity = incremental.partition_granularity;
+    let chunks = split_time_range(&range, chunk, granularity)?;
+    let label = |chunk: &TimeRange| format!("{} → {}", chunk.start, chunk.end);
+
+    say!(
+        "Backfilling {} from {} to {} in {} chunks of {} ({} partitions)",
+        model.name,
+        chunks[0].start,
+        chunks[chunks.len() - 1].end,
+        chunks.len(),
+        args.chunk,
+        granularity
+    );
+
+    let backend = create_backend(target_config, args.database.clone(), &project_dir).await?;
+    check_timeouts(backend.as_ref(), &config, std::slice::from_ref(&model.name))?;
+    executor::check_incremental_strategies(
+        backend.as_ref(),
+        [(model.name.as_str(), incremental.as_ref())],
+    )?;
+    check_event_time_columns(
+        &project_dir,
+        &config,
+        &graph,
+        sources.as_ref(),
+        std::slice::from_ref(&model.name),
+    )?;
+    if let Some(ref source_config) = sources {
+        executor::validate_sources(backend.as_ref(), source_config)
+            .await
+            .with_context(|| "Source validation failed")?;
+    }
+
+    let run_args = RunArgs {
+        project_dir: project_dir.clone(),
+        database: args.database.clone(),
+        target: args.target.clone(),
+        vars: args.vars.clone(),
+        verbose: args.verbose,
+        ..Default::default()
+    };
+    let deferred = HashMap::new();
+    let compiler = SqlCompiler::new(config.clone())
+        .with_models(graph.models().values())
+        .with_backend(backend.as_ref());
+    let total = chunks.len();
+    let cancel = cancel_on_ctrl_c();
+
+    let run_chunk = |index: usize| {
+        let chunk = &chunks[index];
+        let ctx = RunContext {
+            args: &run_args,
+            config: &config,
+            packages: &packages,
+            sources: sources.as_ref(),
+            project_dir: &project_dir,
+            schema: &target_config.schema,
+            backend: backend.as_ref(),
+            time_range: Some(chunk),
+            deferred: &deferred,
+            cancel: &cancel,
+        };
+        let compiler = &compiler;
+        async move {
+            say!(
+                "\n[{}/{}] {} → {}",
+                index + 1,
+                total,
+                chunk.start,
+                chunk.end
+            );
+            (index, run_model_with_retries(&ctx, compiler, model).await)
+        }
+    };
+
+    let started = Instant::now();
+    let mut results = vec![run_chunk(0).await];
+    if results[0].1.is_ok() {
+        if args.parallel > 1 {
+            let rest: Vec<_> = futures::stream::iter(1..total)
+                .map(run_chunk)
+                .buffer_unordered(args.parallel)
+                .collect()
+                .await;
+            results.extend(rest);
+        } else {
+            for index in 1..total {
+                let result = run_chunk(index).await;
+                let failed = result.1.is_err();
+                results.push(result);
+                if failed {
+                    break;
+                }
+            }
+        }
+    }
+    results.sort_by_key(|(index, _)| *index);
+
+    let failures: Vec<usize> = results
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .map(|(index, _)| *index)
+        .collect();
+    let succeeded = results.len() - failures.len();
+
+    say!("\n{}", "=".repeat(60));
+    say!("Summary");
+    say!("{}", "=".repeat(60));
+    say!(
+        "✓ {} of {} chunks succeeded in {:?}",
+        succeeded,
+        total,
+        started.elapsed()
+    );
+
+    let Some(&first_failed) = failures.first() else {
+        return Ok(());
+    };
+
+    say!("\n✗ Failed ({}):", failures.len());
+    for &index in &failures {
+        if let (_, Err(e)) = &results[index] {
+            say!("  - {}: {}", label(&chunks[index]), e.root_cause());
+        }
+    }
+    if results.len() < total {
+        say!("\n⊘ Not run: {} chunks", total - results.len());
+    }
+    say!(
+        "\nResume with: smelt backfill --model {} --from '{}' --to '{}' --chunk {}",
+        model.name,
+        chunks[first_failed].start,
+        args.to,
+        args.chunk
+    );
+
+    let failed = failures
+        .iter()
+        .map(|&index| label(&chunks[index]))
+        .collect();
+    if cancel.is_cancelled() {
+        return Err(CliError::Interrupted {
+            failed,
+            skipped: chunks[results.len()..].iter().map(label).collect(),
+            succeeded,
+        }
+        .into());
+    }
+
+    let backfill_failed = CliError::BackfillFailed {
+        model: model.name.clone(),
+        failed,
+        succeeded,
+        total,
+        resume_from: chunks[first_failed].start.clone(),
+    };
+    match failures.as_slice() {
+        [index] => Err(results
+            .swap_remove(*index)
+            .1
+            .unwrap_err()
+            .context(backfill_failed)),
+        _ => Err(backfill_failed.into()),
+    }
+}
+
+/// Poll the model directories and re-run changed models and their downstreams until Ctrl+C.
+async fn watch(
+    ctx: &RunContext<'_>,
+    sources: Option<&SourceConfig>,
+    graph: DependencyGraph,
+) -> Result<()> {
+    let model_paths = &ctx.config.model_paths;
+    let mut files = scan_model_files(ctx.project_dir, model_paths);
+    let mut checksums = model_checksums(&graph);
+
+    say!(
+        "\nWatching {} for changes (Ctrl+C to stop)...",
+        model_paths.join(", ")
+    );
+
+    loop {
+        tokio::select! {
+            _ = ctx.cancel.cancelled() => {
+                say!("\nStopping watch mode");
+                return Ok(());
+            }
+            _ = tokio::time::sleep(WATCH_POLL_INTERVAL) => {}
+        }
+
+        let current_files = scan_model_files(ctx.project_dir, model_paths);
+        if current_files == files {
+            continue;
+        }
+        files = current_files;
+
+        // Rediscover so refs and metadata reflect the edited files
+        let graph = match build_graph(ctx.project_dir, ctx.config, ctx.packages, sources) {
+            Ok(graph) => graph,
+            Err(e) => {
+                eprintln!("\n✗ {:#}", e);
+                continue;
+            }
+        };
+
+        let current_checksums = model_checksums(&graph);
+        let changed = changed_models(&checksums, &current_checksums);
+        checksums = current_checksums;
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        let affected = affected_models(&graph, &changed);
+        let mut execution_order = match graph.execution_order() {
+            Ok(order) => order,
+            Err(e) => {
+                eprintln!("\n✗ {:#}", e);
+                continue;
+            }
+        };
+        execution_order.retain(|name| affected.contains(name));
+
+        say!(
+            "\n↻ Changed: {} ({} models to run)",
+            changed.join(", "),
+            execution_order.len()
+        );
+
+        if let Err(e) = execute_models(ctx, &graph, &execution_order).await {
+            eprintln!("\n✗ {:#}", e);
+        }
+    }
+}
+
+/// Compile and execute a single model, incrementally if a time range is given
+/// and the model is configured for it.
+async fn run_model(
+    ctx: &RunContext<'_>,
+    compiler: &SqlCompiler,
+    model: &ModelFile,
+) -> Result<ExecutionResult> {
+    let RunContext {
+        args,
+        config,
+        schema,
+        backend,
+        time_range,
+        ..
+    } = *ctx;
+    let model_name = &model.name;
+    let target_schema = schema;
+    let relation = &compiler.relation_for(model_name, target_schema);
+    match &relation.catalog {
+        Some(catalog) => backend
+            .ensure_catalog_schema(catalog, &relation.schema)
+            .await
+            .with_context(|| format!("Failed to create schema {}.{}", catalog, relation.schema))?,
+        None if relation.schema != target_schema => {
+            backend
+                .ensure_schema(&relation.schema)
+                .await
+                .with_context(|| format!("Failed to create schema {}", relation.schema))?
+        }
+        None => {}
+    }
+
+    // Check if this model should be run incrementally
+    // SQL metadata takes precedence over smelt.yml
+    let inc_config = config.get_model_incremental(model, ctx.sources);
+    let full_refresh = args.full_refresh && inc_config.is_some();
+    let inc_config = inc_config.filter(|_| !args.full_refresh && !args.empty);
+
+    emit(RunEvent::ModelStart {
+        model: model_name.clone(),
+        incremental: time_range.is_some() && inc_config.is_some(),
+    });
+
+    let program = config.get_program(model_name);
+    match (time_range, &inc_config) {
+        _ if program.is_some() => say!("\n▶ Running model: {} (program)", model_name),
+        (Some(_), Some(inc)) => say!(
+            "\n▶ Running model: {} (incremental, {})",
+            model_name,
+            inc.incremental_strategy
+        ),
+        _ if args.empty => say!("\n▶ Running model: {} (empty)", model_name),
+        _ if full_refresh => say!("\n▶ Running model: {} (full refresh)", model_name),
+        (Some(_), None) => say!(
+            "\n▶ Running model: {} (full refresh - not configured for incremental)",
+            model_name
+        ),
+        (None, _) => say!("\n▶ Running model: {}", model_name),
+    }
+
+    let hooks = config.get_hooks(model_name);
+    let pre_hooks = executor::run_hooks(backend, relation, &hooks.pre, HookKind::Pre).await?;
+    if pre_hooks > 0 {
+        say!("  ✓ {} pre-hooks", pre_hooks);
+    }
+
+    let result = match (program, time_range, inc_config) {
+        (Some(program), _, _) => {
+            let inputs = program_inputs(ctx, compiler, &program.depends_on)?;
+            if args.verbose {
+                say!("  Command: {}", program.command.join(" "));
+            }
+            run_program(
+                backend,
+                model_name,
+                program,
+                ctx.project_dir,
+                &inputs,
+                relation,
+            )
+            .await?
+        }
+        (None, Some(range), Some(inc)) => {
+            // Replace whole partitions, widening the range to their boundaries
+            let granularity = inc.partition_granularity;
+            let range = &align_time_range(range, granularity)?;
+            if !is_aligned(time_range.unwrap(), granularity)? {
+                say!(
+                    "  Time range widened to whole {}s: {} to {}",
+                    granularity,
+                    range.start,
+                    range.end
+                );
+            }
+
+            // Transform SQL to filter by time range
+            let time_sources = ctx
+                .sources
+                .map(|sources| sources.event_time_sources(model))
+                .unwrap_or_default();
+            let filtered =
+                inject_time_filter(&model.content, &inc.event_time_column, &time_sources, range)
+                    .with_context(|| {
+                        format!("Failed to transform SQL for model: {}", model_name)
+                    })?;
+
+            // Compile with transformed SQL
+            let compiled = compiler
+                .compile_with_sql(model, target_schema, &filtered.sql)
+                .with_context(|| format!("Failed to compile model: {}", model_name))?;
+
+            if args.verbose {
+                for filter in &filtered.filters {
+                    let position = text_range_to_range(&filtered.sql, *filter).start;
+                    say!(
+                        "  Time filter at {}:{}: {}",
+                        position.line + 1,
+                        position.column + 1,
+                        &filtered.sql[*filter]
+                    );
+                }
+                print_sql("Transformed SQL", &compiled.sql);
+            }
+
+            // Generate partition values for DELETE
+            let partition_values = partition_values(range, granularity)?;
+            say!(
+                "  Partitions to update: {} ({} {}s)",
+                if partition_values.len() <= 3 {
+                    partition_values.join(", ")
+                } else {
+                    format!(
+                        "{}, ..., {}",
+                        partition_values.first().unwrap(),
+                        partition_values.last().unwrap()
+                    )
+                },
+                partition_values.len(),
+                granularity
+            );
+
+            let partition = PartitionSpec {
+                column: inc.partition_column.clone(),
+                values: partition_values,
+            };
+
+            // Execute incrementally
+            executor::execute_model_incremental(
+                backend,
+                &compiled,
+                relation,
+                partition,
+                &inc,
+                args.show_results,
+            )
+            .await
+            .with_context(|| format!("Failed to execute model: {}", model_name))?
+        }
+        (None, _, _) => {
+            // Standard full refresh path
+            // Compile
+            let mut compiled = compiler
+                .compile(model, target_schema)
+                .with_context(|| format!("Failed to compile model: {}", model_name))?;
+            if args.empty {
+                compiled.sql = empty_query(&compiled.sql);
+            }
+
+            if args.verbose {
+                print_sql("Compiled SQL", &compiled.sql);
+            }
+
+            // Execute
+            if compiled.materialization == Materialization::External {
+                let location = config.get_external_location(model_name, ctx.project_dir);
+                say!("  Writing Parquet to {}", location);
+                executor::execute_model_external(
+                    backend,
+                    &compiled,
+                    relation,
+                    &location,
+                    args.show_results,
+                )
+                .await
+            } else {
+                executor::execute_model(backend, &compiled, relation, args.show_results).await
+            }
+            .with_context(|| format!("Failed to execute model: {}", model_name))?
+        }
+    };
+
+    say!(
+        "  ✓ {} ({} rows, {:?})",
+        result.model_name,
+        result.row_count,
+        result.duration
+    );
+    if args.verbose && !result.stats.is_empty() {
+        say!("    {}", result.stats);
+    }
+
+    if let Some(contract) = config.get_contract(model_name) {
+        executor::enforce_contract(backend, relation, contract).await?;
+        say!("  ✓ contract ({} columns)", contract.columns.len());
+    }
+
+    let materialization = config.get_model_materialization(model);
+    let comments = executor::apply_comments(backend, model, relation, &materialization).await?;
+    if comments > 0 {
+        say!("  ✓ {} comments", comments);
+    }
+
+    let grants = config.get_grants(model_name, &args.target);
+    if !grants.is_empty() {
+        if backend.capabilities().supports_grants {
+            executor::apply_grants(backend, relation, &materialization, &grants).await?;
+            say!(
+                "  ✓ grants ({})",
+                grants.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        } else {
+            eprintln!(
+                "  Warning: {} doesn't support grants; skipping grants on {}",
+                backend.dialect().name(),
+                model_name
+            );
+        }
+    }
+
+    let post_hooks = executor::run_hooks(backend, relation, &hooks.post, HookKind::Post).await?;
+    if post_hooks > 0 {
+        say!("  ✓ {} post-hooks", post_hooks);
+    }
+
+    // Show preview if requested
+    if let Some(ref batches) = result.preview {
+        say!("\n  Preview:");
+        let table =
+            pretty::pretty_format_batches(batches).with_context(|| "Failed to format preview")?;
+        say!("{}\n", table);
+    }
+
+    Ok(result)
+}
+
+/// The relations a program model reads, by the name they're exported as:
+/// models by name, and sources as `schema.table`.
+fn program_inputs(
+    ctx: &RunContext<'_>,
+    compiler: &SqlCompiler,
+    depends_on: &[String],
+) -> Result<Vec<(String, RelationName)>> {
+    depends_on
+        .iter()
+        .map(|dependency| {
+            let relation = match dependency.split_once('.') {
+                Some((schema, table)) if ctx.config.package_of(dependency).is_none() => {
+                    RelationName::new(schema, table)
+                }
+                _ if compiler.is_ephemeral(dependency) => anyhow::bail!(
+                    "'{}' is ephemeral, so there is no table to pass to the program",
+                    dependency
+                ),
+                _ => compiler.relation_for(dependency, ctx.schema),
+            };
+            Ok((dependency.clone(), relation))
+        })
+        .collect()
+}
+
+fn print_sql(label: &str, sql: &str) {
+    say!("\n  {}:", label);
+    say!("  {}", "─".repeat(58));
+    for line in sql.lines() {
+        say!("  {}", line);
+    }
+    say!("  {}", "─".repeat(58));
+}
+
+fn compile(args: CompileArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+
+    println!("Project directory: {}", project_dir.display());
+
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+
+    let graph = build_graph(&project_dir, &config, &packages, sources.as_ref())?;
+    let mut execution_order = graph
+        .execution_order()
+        .with_context(|| "Failed to determine execution order")?;
+
+    let output_dir = compiled_dir(&project_dir);
+    let selecting = !args.select.is_empty() || !args.exclude.is_empty();
+
+    if selecting {
+        let state = load_state(args.state.as_deref())?;
+        let selected = select_models(
+            &graph,
+            &config,
+            &project_dir,
+            &args.select,
+            &args.exclude,
+            state.as_ref(),
+        )
+        .with_context(|| "Failed to resolve model selection")?;
+        execution_order.retain(|name| selected.contains(name));
+    } else if output_dir.exists() {
+        // Full compile: clear out SQL for models that no longer exist
+        std::fs::remove_dir_all(&output_dir)
+            .with_context(|| format!("Failed to clean {:?}", output_dir))?;
+    }
+
+    let artifacts = artifacts_dir(&project_dir);
+    let compiler = SqlCompiler::new(config.clone())
+        .with_models(graph.models().values())
+        .with_capabilities(target_config.backend_type().dialect().capabilities())
+        .with_cache(CompileCache::load(&artifacts));
+
+    for model_name in &execution_order {
+        if compiler.is_program(model_name) {
+            println!(
+                "  - {} is built by a program; no SQL to compile",
+                model_name
+            );
+            continue;
+        }
+        let model = graph.get_model(model_name)?;
+        let compiled = compiler.compile(model, &target_config.schema)?;
+        let path = write_compiled_model(&output_dir, &compiled)?;
+
+        println!(
+            "  ✓ {} → {}",
+            model_name,
+            path.strip_prefix(&project_dir).unwrap_or(&path).display()
+        );
+    }
+
+    let manifest = Manifest::build(
+        &graph,
+        &compiler,
+        &config,
+        &project_dir,
+        &args.target,
+        &target_config.schema,
+    )?;
+    write_artifact(&artifacts, MANIFEST_FILE, &manifest)?;
+    compiler.write_cache(&artifacts)?;
+
+    println!(
+        "\n✓ Compiled {} models to {}",
+        execution_order.len(),
+        output_dir.display()
+    );
+
+    Ok(())
+}
+
+fn validate(args: ValidateArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let cli_vars = args
+        .vars
+        .as_deref()
+        .map(parse_vars)
+        .transpose()?
+        .unwrap_or_default();
+
+    let issues = validate_project(&project_dir, &cli_vars);
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    let errors = issues.iter().filter(|i| i.is_error()).count();
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        return Err(CliError::ValidationFailed { errors, warnings }.into());
+    }
+
+    if warnings > 0 {
+        println!("\n✓ No errors ({} warning(s))", warnings);
+    } else {
+        println!("✓ Project is valid");
+    }
+    Ok(())
+}
+
+fn lint(args: LintArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_config(&project_dir, args.vars.as_deref())?;
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    let mut fixed = 0;
+    let mut fixed_files = 0;
+    for path in scan_model_files(&project_dir, &config.model_paths).into_keys() {
+        let mut source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read model file: {:?}", path))?;
+        if args.fix {
+            let (fixed_source, applied) = fix_source(&source, &config.lint);
+            if applied > 0 {
+                std::fs::write(&path, &fixed_source)
+                    .with_context(|| format!("Failed to write model file: {:?}", path))?;
+                source = fixed_source;
+                fixed += applied;
+                fixed_files += 1;
+            }
+        }
+
+        let relative = path.strip_prefix(&project_dir).unwrap_or(&path);
+        for violation in lint_source(&source, &config.lint) {
+            println!("{}:{}", relative.display(), violation);
+            if violation.is_error() {
+                errors += 1;
+            } else {
+                warnings += 1;
+            }
+        }
+    }
+
+    if fixed > 0 {
+        println!("\nFixed {} issue(s) in {} file(s)", fixed, fixed_files);
+    }
+    if errors > 0 {
+        return Err(CliError::LintFailed { errors, warnings }.into());
+    }
+
+    if warnings > 0 {
+        println!("\n✓ No lint errors ({} warning(s))", warnings);
+    } else {
+        println!("✓ No lint issues");
+    }
+    Ok(())
+}
+
+fn ls(args: LsArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_config(&project_dir, args.vars.as_deref())?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = discover_graph(&project_dir, &config, &packages, sources.as_ref())?;
+
+    // Selectors only apply to models, so a selection hides sources (and
+    // exposures that read none of the selected models)
+    let selecting = !args.select.is_empty() || !args.exclude.is_empty();
+    let selected = if selecting {
+        let state = load_state(args.state.as_deref())?;
+        Some(
+            select_models(
+                &graph,
+                &config,
+                &project_dir,
+                &args.select,
+                &args.exclude,
+                state.as_ref(),
+            )
+            .with_context(|| "Failed to resolve model selection")?,
+        )
+    } else {
+        None
+    };
+    let listed_sources = if selecting { None } else { sources.as_ref() };
+
+    let resources: Vec<Resource> = list_resources(
+        &graph,
+        &config,
+        listed_sources,
+        &project_dir,
+        selected.as_ref(),
+    )
+    .into_iter()
+    .filter(|r| match args.resource_type {
+        Some(LsResourceType::Model) => r.resource_type == ResourceType::Model,
+        Some(LsResourceType::Source) => r.resource_type == ResourceType::Source,
+        Some(LsResourceType::Exposure) => r.resource_type == ResourceType::Exposure,
+        None => true,
+    })
+    .collect();
+
+    match args.output {
+        LsOutput::Json => println!("{}", serde_json::to_string_pretty(&resources)?),
+        LsOutput::Text => {
+            let width = resources.iter().map(|r| r.name.len()).max().unwrap_or(0);
+            for resource in &resources {
+                let kind = match resource.resource_type {
+                    ResourceType::Model => "model",
+                    ResourceType::Source => "source",
+                    ResourceType::Exposure => "exposure",
+                };
+                let materialization = match (&resource.materialization, resource.exposure_type) {
+                    (Some(Materialization::Table), _) => "table".to_string(),
+                    (Some(Materialization::View), _) => "view".to_string(),
+                    (Some(Materialization::Ephemeral), _) => "ephemeral".to_string(),
+                    (Some(Materialization::External), _) => "external".to_string(),
+                    (None, Some(exposure_type)) => exposure_type.to_string(),
+                    (None, None) => String::new(),
+                };
+                let tags = if resource.tags.is_empty() {
+                    String::new()
+                } else {
+                    format!("  [{}]", resource.tags.join(", "))
+                };
+                println!(
+                    "{:<8}  {:<width$}  {:<11}  {}{}",
+                    kind,
+                    resource.name,
+                    materialization,
+                    resource.path.display(),
+                    tags,
+                    width = width
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn query(args: QueryArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = discover_graph(&project_dir, &config, &packages, sources.as_ref())?;
+    let backend = create_backend(target_config, args.database.clone(), &project_dir).await?;
+    let compiler = SqlCompiler::new(config.clone())
+        .with_models(graph.models().values())
+        .with_backend(backend.as_ref());
+
+    if let Some(ref sql) = args.sql {
+        return run_query(
+            backend.as_ref(),
+            &graph,
+            &compiler,
+            &target_config.schema,
+            sql,
+            &args,
+        )
+        .await;
+    }
+
+    println!("\nEnter SQL terminated by ';' (.quit to exit)");
+
+    let stdin = std::io::stdin();
+    let mut buffer = String::new();
+    loop {
+        print!(
+            "{}",
+            if buffer.is_empty() {
+                "smelt> "
+            } else {
+                "  ...> "
+            }
+        );
+        std::io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+
+        if buffer.is_empty() && matches!(line.trim(), ".quit" | ".exit") {
+            return Ok(());
+        }
+
+        buffer.push_str(&line);
+        if buffer.trim().is_empty() {
+            buffer.clear();
+            continue;
+        }
+        if !statement_complete(&buffer) {
+            continue;
+        }
+
+        // Errors are reported but don't end the session
+        if let Err(e) = run_query(
+            backend.as_ref(),
+            &graph,
+            &compiler,
+            &target_config.schema,
+            &buffer,
+            &args,
+        )
+        .await
+        {
+            eprintln!("✗ {:#}", e);
+        }
+        buffer.clear();
+    }
+}
+
+/// Compile and execute one ad-hoc statement, printing its results as a table.
+async fn run_query(
+    backend: &dyn Backend,
+    graph: &DependencyGraph,
+    compiler: &SqlCompiler,
+    schema: &str,
+    sql: &str,
+    args: &QueryArgs,
+) -> Result<()> {
+    let compiled = compile_query(sql, None, schema, graph, compiler)?;
+
+    if args.verbose {
+        print_sql("Compiled SQL", &compiled);
+    }
+
+    let started = Instant::now();
+    let stream = backend
+        .execute_sql_stream(&compiled)
+        .await
+        .with_context(|| "Query failed")?;
+    // One extra row tells us whether the output was cut off
+    let mut batches = collect_limited(stream, args.limit.saturating_add(1))
+        .await
+        .with_context(|| "Query failed")?;
+    let mut row_count: usize = batches.iter().map(|b| b.num_rows()).sum();
+    let truncated = row_count > args.limit;
+    if truncated {
+        if let Some(last) = batches.pop() {
+            batches.push(last.slice(0, last.num_rows() - 1));
+        }
+        row_count -= 1;
+    }
+
+    pretty::print_batches(&batches).with_context(|| "Failed to print query results")?;
+    if truncated {
+        println!(
+            "(first {} rows, {:?}; raise --limit to see more)",
+            row_count,
+            started.elapsed()
+        );
+    } else {
+        println!("({} rows, {:?})", row_count, started.elapsed());
+    }
+
+    Ok(())
+}
+
+fn lineage(args: LineageArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_config(&project_dir, args.vars.as_deref())?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = discover_graph(&project_dir, &config, &packages, sources.as_ref())?;
+
+    let lineage = Lineage::build(&graph, &project_dir);
+    let target = LineageTarget::parse(&args.target);
+    let trace = |direction, wanted: bool| {
+        wanted
+            .then(|| lineage.trace(&target, direction))
+            .transpose()
+    };
+    let upstream = trace(
+        Direction::Upstream,
+        args.direction != LineageDirection::Downstream,
+    )?;
+    let downstream = trace(
+        Direction::Downstream,
+        args.direction != LineageDirection::Upstream,
+    )?;
+
+    match args.output {
+        LineageOutput::Dot => print!("{}", render_dot(upstream.as_ref(), downstream.as_ref())),
+        LineageOutput::Text => {
+            if let Some(tree) = &upstream {
+                println!("Upstream:\n{}", render_tree(tree));
+            }
+            if let Some(tree) = &downstream {
+                println!("Downstream:\n{}", render_tree(tree));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_operation(args: RunOperationArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = discover_graph(&project_dir, &config, &packages, sources.as_ref())?;
+    let compiler = SqlCompiler::new(config.clone())
+        .with_models(graph.models().values())
+        .with_capabilities(target_config.backend_type().dialect().capabilities());
+
+    let operation = find_operation(&project_dir, &config.operation_paths, &packages, &args.name)?;
+    let op_args = args.args.as_deref().map(parse_args).transpose()?;
+    let statements = render_operation(&operation, &config.vars, &op_args.unwrap_or_default())?
+        .iter()
+        .map(|sql| {
+            compile_query(
+                sql,
+                operation.package.as_deref(),
+                &target_config.schema,
+                &graph,
+                &compiler,
+            )
+        })
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("Failed to compile operation '{}'", operation.name))?;
+
+    if args.verbose {
+        for (i, sql) in statements.iter().enumerate() {
+            print_sql(&format!("Statement {}", i + 1), sql);
+        }
+    }
+
+    let backend = create_backend(target_config, args.database, &project_dir).await?;
+
+    println!("\n▶ Running operation: {}", operation.name);
+    let result = smelt_cli::run_operation(backend.as_ref(), &operation.name, &statements).await?;
+
+    if result.batches.iter().any(|b| b.num_rows() > 0) {
+        pretty::print_batches(&result.batches)
+            .with_context(|| "Failed to print operation results")?;
+    }
+    println!(
+        "  ✓ {} ({} statements, {:?})",
+        operation.name, result.statement_count, result.duration
+    );
+
+    Ok(())
+}
+
+async fn debug(args: DebugArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+
+    println!("Project: {} ({})", config.name, project_dir.display());
+    println!(
+        "Target: {} (type {}, schema {})",
+        args.target, target_config.target_type, target_config.schema
+    );
+
+    let problems = smelt_cli::debug::target_problems(target_config, &project_dir);
+    for problem in &problems {
+        println!("  ✗ config: {}", problem);
+    }
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "Target '{}' has {} configuration problem(s); fix them in smelt.yml",
+            args.target,
+            problems.len()
+        );
+    }
+    println!("  ✓ config");
+
+    let backend_type = target_config.backend_type();
+    let backend = match create_backend(target_config, args.database, &project_dir).await {
+        Ok(backend) => backend,
+        Err(e) => {
+            let error = format!("{:#}", e);
+            println!("  ✗ connect: {}", error);
+            if let Some(hint) = smelt_cli::debug::hint(backend_type, &error) {
+                println!("    Hint: {}", hint);
+            }
+            return Err(e);
+        }
+    };
+    println!("  ✓ connect ({})", backend.dialect().name());
+
+    let checks =
+        smelt_cli::debug::run_checks(backend.as_ref(), backend_type, &target_config.schema).await;
+    for check in &checks {
+        let icon = if check.passed { "✓" } else { "✗" };
+        println!("  {} {}: {}", icon, check.name, check.detail);
+        if let Some(hint) = check.hint {
+            println!("    Hint: {}", hint);
+        }
+    }
+
+    println!("\nCapabilities:");
+    for (name, supported) in smelt_cli::debug::capability_flags(&backend.capabilities()) {
+        println!("  {} {}", if supported { "✓" } else { "-" }, name);
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, checks.len());
+    }
+    println!("\n✓ All checks passed");
+    Ok(())
+}
+
+async fn show(args: ShowArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = discover_graph(&project_dir, &config, &packages, sources.as_ref())?;
+
+    let model = graph.get_model(&args.model)?;
+    let compiler = SqlCompiler::new(config.clone())
+        .with_models(graph.models().values())
+        .with_capabilities(target_config.backend_type().dialect().capabilities());
+    let compiled = compiler
+        .compile(model, &target_config.schema)
+        .with_context(|| format!("Failed to compile model: {}", model.name))?;
+    let relation = compiler.relation_for(&model.name, &target_config.schema);
+
+    if args.verbose {
+        print_sql("Compiled SQL", &compiled.sql);
+    }
+
+    let backend = create_backend(target_config, args.database, &project_dir).await?;
+
+    let materialized = !args.inline
+        && backend
+            .table_exists(&relation)
+            .await
+            .with_context(|| format!("Failed to look up {}", relation))?;
+
+    let batches = if materialized {
+        println!("\nPreviewing {}", relation);
+        backend.get_preview(&relation, args.limit).await
+    } else {
+        println!("\nPreviewing compiled SQL for {}", model.name);
+        match backend
+            .execute_sql_stream(&limit_query(&compiled.sql, args.limit))
+            .await
+        {
+            Ok(stream) => collect_limited(stream, args.limit).await,
+            Err(e) => Err(e),
+        }
+    }
+    .with_context(|| format!("Failed to preview model: {}", model.name))?;
+
+    pretty::print_batches(&batches).with_context(|| "Failed to print result preview")?;
+    let row_count: usize = batches.iter().map(|b| b.num_rows()).sum();
+    println!("({} rows, limit {})", row_count, args.limit);
 
     Ok(())
 }
 
-/// Generate partition date values from a time range.
-/// Returns a list of date strings in YYYY-MM-DD format.
-fn generate_partition_dates(start: &str, end: &str) -> Result<Vec<String>> {
-    let start_date = NaiveDate::parse_from_str(start, "%Y-%m-%d")
-        .with_context(|| format!("Invalid start date: {}", start))?;
-    let end_date = NaiveDate::parse_from_str(end, "%Y-%m-%d")
-        .with_context(|| format!("Invalid end date: {}", end))?;
+async fn diff(args: DiffArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_config(&project_dir, args.vars.as_deref())?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = discover_graph(&project_dir, &config, &packages, sources.as_ref())?;
+    let model = graph.get_model(&args.model)?;
+
+    let (backend_a, relation_a) =
+        built_relation(&config, &graph, &model.name, &args.target_a, &project_dir).await?;
+    let (backend_b, relation_b) =
+        built_relation(&config, &graph, &model.name, &args.target_b, &project_dir).await?;
+
+    let (a, b) = (&args.target_a, &args.target_b);
+    println!(
+        "\nComparing {}: {} ({}) vs {} ({})",
+        model.name, relation_a, a, relation_b, b
+    );
+    let diff = diff_relations(
+        backend_a.as_ref(),
+        &relation_a,
+        backend_b.as_ref(),
+        &relation_b,
+        args.limit,
+    )
+    .await?;
+
+    println!(
+        "\nRows: {} ({}) vs {} ({})",
+        diff.row_count_a, a, diff.row_count_b, b
+    );
 
-    if start_date >= end_date {
-        return Err(anyhow::anyhow!(
-            "Start date ({}) must be before end date ({})",
-            start,
-            end
-        ));
+    if diff.schema.is_empty() {
+        println!("Columns: {} in both", diff.schema.common.len());
+    } else {
+        println!("Columns:");
+        for column in &diff.schema.only_in_a {
+            println!("  only in {}: {} {}", a, column.name, column.data_type);
+        }
+        for column in &diff.schema.only_in_b {
+            println!("  only in {}: {} {}", b, column.name, column.data_type);
+        }
+        for (name, type_a, type_b) in &diff.schema.type_changes {
+            println!("  {}: {} ({}) vs {} ({})", name, type_a, a, type_b, b);
+        }
+    }
+
+    if let Some(rows) = &diff.rows {
+        println!(
+            "Checksum over {} common column(s): {:016x} ({}) vs {:016x} ({})",
+            diff.schema.common.len(),
+            rows.checksum_a,
+            a,
+            rows.checksum_b,
+            b
+        );
+        for (target, count, sample) in [
+            (a, rows.only_in_a, &rows.sample_a),
+            (b, rows.only_in_b, &rows.sample_b),
+        ] {
+            if count > 0 {
+                println!("\n{} row(s) only in {}:", count, target);
+                for row in sample {
+                    println!("  {}", row);
+                }
+                if count > sample.len() {
+                    println!("  ... and {} more", count - sample.len());
+                }
+            }
+        }
+    } else {
+        println!("No common columns to compare rows on");
+    }
+
+    if !diff.is_identical() {
+        return Err(CliError::DiffFound {
+            model: model.name.clone(),
+            target_a: a.clone(),
+            target_b: b.clone(),
+        }
+        .into());
+    }
+    println!("\n✓ {} is identical in {} and {}", model.name, a, b);
+    Ok(())
+}
+
+/// Connect to `target` and find where it built `model`.
+async fn built_relation(
+    config: &Config,
+    graph: &DependencyGraph,
+    model: &str,
+    target: &str,
+    project_dir: &Path,
+) -> Result<(Box<dyn Backend>, RelationName)> {
+    // Each target can name schemas differently through its schema_template
+    let mut config = config.clone();
+    config.use_target(target)?;
+    let target_config = get_target(&config, target)?;
+    let compiler = SqlCompiler::new(config.clone()).with_models(graph.models().values());
+    if compiler.is_ephemeral(model) {
+        anyhow::bail!("Model '{}' is ephemeral and never built", model);
+    }
+    let relation = compiler.relation_for(model, &target_config.schema);
+
+    let backend = create_backend(target_config, None, project_dir).await?;
+    let exists = backend
+        .table_exists(&relation)
+        .await
+        .with_context(|| format!("Failed to look up {}", relation))?;
+    if !exists {
+        anyhow::bail!(
+            "Model '{}' has not been built in target '{}' ({} not found)",
+            model,
+            target,
+            relation
+        );
+    }
+    Ok((backend, relation))
+}
+
+async fn docs_generate(args: DocsGenerateArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+
+    println!("Project directory: {}", project_dir.display());
+
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let packages = load_packages(&config, &project_dir)?;
+    let sources = load_sources(&project_dir, &packages)?;
+    let graph = build_graph(&project_dir, &config, &packages, sources.as_ref())?;
+
+    let mut bundle = DocsBundle::build(&graph, &config, sources.as_ref(), &project_dir);
+
+    if args.catalog {
+        let target_config = get_target(&config, &args.target)?;
+        let backend = create_backend(target_config, args.database, &project_dir).await?;
+        let compiler = SqlCompiler::new(config.clone()).with_models(graph.models().values());
+        let found = bundle
+            .add_catalog_types(backend.as_ref(), |model| {
+                compiler.relation_for(model, &target_config.schema)
+            })
+            .await?;
+        println!("  ✓ Read column types for {} relations", found);
+    }
+    let output_dir = args
+        .output_dir
+        .unwrap_or_else(|| artifacts_dir(&project_dir).join("docs"));
+
+    let json_path = write_docs_json(&bundle, &output_dir)?;
+    println!("  ✓ {}", json_path.display());
+
+    if matches!(args.format, DocsFormat::Html) {
+        let site_path = write_docs_site(&bundle, &output_dir)?;
+        println!("  ✓ {}", site_path.display());
+    }
+
+    println!(
+        "\n✓ Documented {} models and {} sources",
+        bundle.models.len(),
+        bundle.sources.len()
+    );
+
+    Ok(())
+}
+
+async fn source_freshness(args: SourceFreshnessArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+
+    println!("Project directory: {}", project_dir.display());
+
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+    let sources = SourceConfig::load(&project_dir).with_context(|| "Failed to load sources.yml")?;
+
+    let backend = create_backend(target_config, args.database, &project_dir).await?;
+
+    println!("\n{}", "=".repeat(60));
+    println!("Checking source freshness...");
+    println!("{}", "=".repeat(60));
+
+    let results = check_source_freshness(backend.as_ref(), &sources).await;
+    if results.is_empty() {
+        println!("\nNo sources have both loaded_at_field and freshness configured");
+        return Ok(());
+    }
+
+    for result in &results {
+        let (icon, label) = match result.status {
+            FreshnessStatus::Pass => ("✓", "pass"),
+            FreshnessStatus::Warn => ("!", "warn"),
+            FreshnessStatus::Error => ("✗", "error"),
+            FreshnessStatus::RuntimeError => ("✗", "runtime error"),
+        };
+        match (&result.max_loaded_at, result.age_secs) {
+            (Some(max_loaded_at), Some(age_secs)) => println!(
+                "  {} {} [{}] last loaded {} ({} ago)",
+                icon,
+                result.source,
+                label,
+                max_loaded_at,
+                format_age(std::time::Duration::from_secs_f64(age_secs.max(0.0)))
+            ),
+            _ => println!(
+                "  {} {} [{}] {}",
+                icon,
+                result.source,
+                label,
+                result.message.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+
+    let artifacts = artifacts_dir(&project_dir);
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let (passed, warned) = (count(FreshnessStatus::Pass), count(FreshnessStatus::Warn));
+    let failed = results.len() - passed - warned;
+
+    write_artifact(
+        &artifacts,
+        SOURCES_FILE,
+        &FreshnessResults {
+            metadata: ArtifactMetadata::new(&config.name, &args.target),
+            results,
+        },
+    )?;
+
+    println!("\n{}", "=".repeat(60));
+    println!("Summary");
+    println!("{}", "=".repeat(60));
+    println!("{} passed, {} warned, {} failed", passed, warned, failed);
+    println!(
+        "  Results written to {}",
+        artifacts.join(SOURCES_FILE).display()
+    );
+
+    if failed > 0 {
+        return Err(CliError::ChecksFailed {
+            message: format!("{} sources are stale or could not be checked", failed),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+fn init(args: InitArgs) -> Result<()> {
+    let dir = args.path.unwrap_or_else(|| PathBuf::from(&args.name));
+
+    let files = init_project(&dir, &args.name)?;
+
+    println!("Created project '{}' in {}", args.name, dir.display());
+    for file in &files {
+        println!("  {}", file.display());
+    }
+    println!("\nNext steps:");
+    println!("  cd {}", dir.display());
+    println!("  smelt seed");
+    println!("  smelt run");
+
+    Ok(())
+}
+
+fn deps(args: DepsArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = load_config(&project_dir, args.vars.as_deref())?;
+
+    if config.packages.is_empty() {
+        println!("No packages declared in smelt.yml");
+        return Ok(());
+    }
+
+    for (name, package) in &config.packages {
+        match install_package(name, package, &project_dir)? {
+            InstallStatus::Local(path) => println!("  {} {} (local)", name, path.display()),
+            InstallStatus::Cloned { commit } => {
+                println!("  {} cloned at {}", name, short_commit(&commit))
+            }
+            InstallStatus::UpToDate { commit } => {
+                println!("  {} up to date at {}", name, short_commit(&commit))
+            }
+            InstallStatus::Updated { from, to } => println!(
+                "  {} updated {} -> {}",
+                name,
+                short_commit(&from),
+                short_commit(&to)
+            ),
+        }
+    }
+    println!("✓ {} packages ready", config.packages.len());
+
+    Ok(())
+}
+
+fn short_commit(commit: &str) -> &str {
+    &commit[..commit.len().min(10)]
+}
+
+async fn seed(args: SeedArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+
+    println!("Project directory: {}", project_dir.display());
+
+    let config = load_target_config(&project_dir, args.vars.as_deref(), &args.target)?;
+    let target_config = get_target(&config, &args.target)?;
+
+    let seeds = discover_seeds(&project_dir, &config.seed_paths)
+        .with_context(|| "Failed to discover seeds")?;
+
+    if seeds.is_empty() {
+        println!(
+            "No seed files found in seed paths: {}",
+            config.seed_paths.join(", ")
+        );
+        return Ok(());
+    }
+
+    println!("Found {} seeds", seeds.len());
+
+    let backend = create_backend(target_config, args.database, &project_dir).await?;
+
+    println!("\n{}", "=".repeat(60));
+    println!("Loading seeds...");
+    println!("{}", "=".repeat(60));
+
+    let mut results = Vec::new();
+    for seed_file in &seeds {
+        println!("\n▶ Loading seed: {}", seed_file.name);
+
+        let result = load_seed(
+            backend.as_ref(),
+            seed_file,
+            &target_config.schema,
+            args.full_refresh,
+        )
+        .await?;
+
+        println!(
+            "  ✓ {} ({} rows, {:?}{})",
+            result.name,
+            result.row_count,
+            result.duration,
+            if result.recreated { ", created" } else { "" }
+        );
+
+        results.push(result);
+    }
+
+    println!("\n{}", "=".repeat(60));
+    println!("Summary");
+    println!("{}", "=".repeat(60));
+    println!("✓ Loaded {} seeds successfully", results.len());
+
+    // Models may read the reloaded seeds, which the build cache doesn't track
+    BuildCache::clear(&cache_dir(&project_dir), &args.target)?;
+
+    Ok(())
+}
+
+/// Check the event time columns incremental models among `models` filter on,
+/// with the input schemas smelt-db infers: fail if one has none, and warn if
+/// one isn't a column of the model's inputs.
+fn check_event_time_columns(
+    project_dir: &Path,
+    config: &Config,
+    graph: &DependencyGraph,
+    sources: Option<&SourceConfig>,
+    models: &[String],
+) -> Result<()> {
+    let incremental: Vec<_> = models
+        .iter()
+        .filter_map(|name| {
+            let model = graph.models().get(name)?;
+            Some((model, config.get_model_incremental(model, sources)?))
+        })
+        .collect();
+    if incremental.is_empty() {
+        return Ok(());
+    }
+
+    let lineage = Lineage::build(graph, project_dir);
+    for (model, inc) in incremental {
+        let Some(problem) = event_time_problem(model, &inc, graph, &lineage, sources) else {
+            continue;
+        };
+        if inc.event_time_column.trim().is_empty() {
+            return Err(anyhow::anyhow!(problem));
+        }
+        say!("  ⚠ {}", problem);
     }
+    Ok(())
+}
+
+/// Load smelt.yml, applying `--vars` overrides
+fn load_config(project_dir: &Path, vars: Option<&str>) -> Result<Config> {
+    let cli_vars = vars.map(parse_vars).transpose()?.unwrap_or_default();
+    Config::load_with_vars(project_dir, &cli_vars)
+        .with_context(|| "Failed to load smelt.yml configuration")
+}
+
+/// Load smelt.yml for a target, naming schemas after its `schema_template`
+fn load_target_config(project_dir: &Path, vars: Option<&str>, target: &str) -> Result<Config> {
+    let mut config = load_config(project_dir, vars)?;
+    config.use_target(target)?;
+    Ok(config)
+}
+
+/// Load the `--state` manifest, if one was given
+fn load_state(path: Option<&Path>) -> Result<Option<Manifest>> {
+    path.map(|path| {
+        Manifest::load_state(path)
+            .with_context(|| format!("Failed to load state manifest from {:?}", path))
+    })
+    .transpose()
+}
+
+/// Load sources.yml, if any, with the sources declared by packages added
+fn load_sources(project_dir: &Path, packages: &[Package]) -> Result<Option<SourceConfig>> {
+    let mut sources = SourceConfig::load(project_dir).ok();
+    merge_package_sources(&mut sources, packages)?;
+    Ok(sources)
+}
+
+/// Discover models and build the dependency graph without reporting or validating
+fn discover_graph(
+    project_dir: &Path,
+    config: &Config,
+    packages: &[Package],
+    sources: Option<&SourceConfig>,
+) -> Result<DependencyGraph> {
+    let discovery = ModelDiscovery::new(project_dir.to_path_buf(), config.model_paths.clone())
+        .with_vars(config.vars.clone())
+        .with_programs(config.programs())
+        .with_packages(packages);
+    let models = discovery
+        .discover_models()
+        .with_context(|| "Failed to discover models")?;
+    let exposures = ExposureConfig::load(project_dir).ok();
+    DependencyGraph::build(models, sources)
+        .map(|graph| {
+            graph
+                .with_exposures(exposures.as_ref())
+                .with_packages(packages)
+        })
+        .with_context(|| "Failed to build dependency graph")
+}
+
+/// Discover models, report parse errors, and build a validated dependency graph
+fn build_graph(
+    project_dir: &Path,
+    config: &Config,
+    packages: &[Package],
+    sources: Option<&SourceConfig>,
+) -> Result<DependencyGraph> {
+    let discovery = ModelDiscovery::new(project_dir.to_path_buf(), config.model_paths.clone())
+        .with_vars(config.vars.clone())
+        .with_programs(config.programs())
+        .with_packages(packages);
+    let models = discovery
+        .discover_models()
+        .with_context(|| "Failed to discover models")?;
+
+    say!("Found {} models", models.len());
 
-    let mut dates = Vec::new();
-    let mut current = start_date;
-    while current < end_date {
-        dates.push(current.format("%Y-%m-%d").to_string());
-        current += Duration::days(1);
+    // Report any parse errors
+    for model in &models {
+        if !model.parse_errors.is_empty() {
+            eprintln!("\nWarning: Parse errors in {}:", model.name);
+            for error in &model.parse_errors {
+                eprintln!("  - {} at {:?}", error.message, error.range);
+            }
+        }
     }
 
-    Ok(dates)
+    let exposures = ExposureConfig::load(project_dir).ok();
+    let graph = DependencyGraph::build(models, sources)
+        .with_context(|| "Failed to build dependency graph")?
+        .with_exposures(exposures.as_ref())
+        .with_packages(packages);
+
+    graph
+        .validate()
+        .with_context(|| "Dependency validation failed")?;
+
+    Ok(graph)
+}
+
+fn get_target<'a>(config: &'a Config, name: &str) -> Result<&'a Target> {
+    config.targets.get(name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Target '{}' not found in smelt.yml. Available targets: {}",
+            name,
+            config
+                .targets
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+/// Create the backend for a target.
+///
+/// `database_override` replaces the DuckDB or SQLite database path from smelt.yml.
+async fn create_backend(
+    target_config: &Target,
+    database_override: Option<PathBuf>,
+    project_dir: &Path,
+) -> Result<Box<dyn Backend>> {
+    let backend: Box<dyn Backend> = match target_config.backend_type() {
+        BackendType::DuckDB => {
+            #[cfg(feature = "duckdb")]
+            {
+                let database = target_config
+                    .database
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("DuckDB target requires 'database' field"))?;
+
+                let db_path = database_override.unwrap_or_else(|| project_dir.join(database));
+                say!("\nBackend: DuckDB");
+                say!("Database: {}", db_path.display());
+
+                let wait = match WAIT_FOR_LOCK.get().copied().flatten() {
+                    None => LockWait::NoWait,
+                    Some(None) => LockWait::Forever,
+                    Some(Some(secs)) => LockWait::Timeout(std::time::Duration::from_secs(secs)),
+                };
+                let backend = DuckDbBackend::with_lock_wait(&db_path, &target_config.schema, wait)
+                    .await
+                    .map_err(|e| {
+                        let context = match (&e, wait) {
+                            (smelt_backend::BackendError::Locked { .. }, LockWait::NoWait) => {
+                                "Database is in use; pass --wait to wait for the other run to finish"
+                                    .to_string()
+                            }
+                            _ => format!("Failed to initialize DuckDB at {:?}", db_path),
+                        };
+                        anyhow::Error::new(e).context(context)
+                    })?;
+                for (name, path) in &target_config.attach {
+                    let path = project_dir.join(path);
+                    let sql = format!(
+                        "ATTACH IF NOT EXISTS '{}' AS {}",
+                        path.display().to_string().replace('\'', "''"),
+                        backend.dialect().quote_ident(name)
+                    );
+                    backend
+                        .execute_sql(&sql)
+                        .await
+                        .with_context(|| format!("Failed to attach {:?} as {}", path, name))?;
+                }
+                Box::new(backend)
+            }
+            #[cfg(not(feature = "duckdb"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "DuckDB backend not available. Rebuild with --features duckdb"
+                ));
+            }
+        }
+        BackendType::SQLite => {
+            #[cfg(feature = "sqlite")]
+            {
+                let database = target_config
+                    .database
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("SQLite target requires 'database' field"))?;
+
+                let db_path = database_override.unwrap_or_else(|| project_dir.join(database));
+                say!("\nBackend: SQLite");
+                say!("Database: {}", db_path.display());
+
+                Box::new(
+                    SqliteBackend::new(&db_path, &target_config.schema)
+                        .await
+                        .with_context(|| format!("Failed to initialize SQLite at {:?}", db_path))?,
+                )
+            }
+            #[cfg(not(feature = "sqlite"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "SQLite backend not available. Rebuild with --features sqlite"
+                ));
+            }
+        }
+        BackendType::Snowflake => {
+            #[cfg(feature = "snowflake")]
+            {
+                let required = |value: &Option<String>, field: &str| {
+                    value.clone().ok_or_else(|| {
+                        anyhow::anyhow!("Snowflake target requires '{}' field", field)
+                    })
+                };
+                let account = required(&target_config.account, "account")?;
+                let private_key_path =
+                    required(&target_config.private_key_path, "private_key_path")?;
+                let mut config = SnowflakeConfig::new(
+                    &account,
+                    required(&target_config.user, "user")?,
+                    required(&target_config.database, "database")?,
+                    required(&target_config.warehouse, "warehouse")?,
+                    project_dir.join(private_key_path),
+                );
+                if let Some(role) = &target_config.role {
+                    config = config.with_role(role);
+                }
+
+                say!("\nBackend: Snowflake");
+                say!("Account: {}", account);
+                say!(
+                    "Database: {} (warehouse {})",
+                    config.database,
+                    config.warehouse
+                );
+
+                Box::new(
+                    SnowflakeBackend::new(&config, &target_config.schema)
+                        .await
+                        .with_context(|| {
+                            format!("Failed to connect to Snowflake account {}", account)
+                        })?,
+                )
+            }
+            #[cfg(not(feature = "snowflake"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "Snowflake backend not available. Rebuild with --features snowflake"
+                ));
+            }
+        }
+        BackendType::Spark => {
+            #[cfg(feature = "spark")]
+            {
+                let connect_url = target_config
+                    .connect_url
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("Spark target requires 'connect_url' field"))?;
+
+                let default_catalog = "spark_catalog".to_string();
+                let catalog = target_config.catalog.as_ref().unwrap_or(&default_catalog);
+
+                say!("\nBackend: Spark");
+                say!("Connect URL: {}", connect_url);
+                say!("Catalog: {}", catalog);
+
+                let table_options = smelt_backend_spark::TableOptions {
+                    format: target_config
+                        .table_format
+                        .as_deref()
+                        .map(str::parse)
+                        .transpose()
+                        .map_err(anyhow::Error::msg)?,
+                    properties: target_config.table_properties.clone(),
+                };
+
+                Box::new(
+                    SparkBackend::new(connect_url, catalog, &target_config.schema)
+                        .await
+                        .with_context(|| format!("Failed to connect to Spark at {}", connect_url))?
+                        .with_table_options(table_options),
+                )
+            }
+            #[cfg(not(feature = "spark"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "Spark backend not available. Rebuild with --features spark"
+                ));
+            }
+        }
+    };
+
+    Ok(backend)
 }
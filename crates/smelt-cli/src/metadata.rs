@@ -36,8 +36,8 @@ pub struct ModelMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
 
-    /// Materialization strategy (table or view)
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Materialization strategy (table, view, or ephemeral)
+    #[serde(alias = "materialized", skip_serializing_if = "Option::is_none")]
     pub materialization: Option<Materialization>,
 
     /// Incremental configuration
@@ -48,6 +48,14 @@ pub struct ModelMetadata {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
 
+    /// Schema to build the model in, instead of the target's schema
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+
+    /// Database (catalog) to build the model in, instead of the target's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+
     /// Model owner (team/person)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner: Option<String>,
@@ -56,6 +64,10 @@ pub struct ModelMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 
+    /// Descriptions of the model's output columns
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<ColumnMetadata>,
+
     /// Backend-specific hints (forward compatibility)
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub backend_hints: HashMap<String, serde_yaml::Value>,
@@ -65,6 +77,14 @@ pub struct ModelMetadata {
     pub custom: HashMap<String, serde_yaml::Value>,
 }
 
+/// Documentation for one output column of a model
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct ColumnMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
 /// Complete file metadata (single or multi-model)
 #[derive(Debug, Clone, PartialEq)]
 pub enum FileMetadata {
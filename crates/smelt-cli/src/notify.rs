@@ -0,0 +1,257 @@
+//! Run notifications for `smelt run`.
+//!
+//! Each entry under `notifications:` in smelt.yml is an HTTP webhook that
+//! receives a POST when a run finishes: the `run_summary` event as JSON, or
+//! a Slack-compatible `{"text": ...}` message. A notification that can't be
+//! delivered is reported but never changes the run's exit code.
+
+use crate::errors::Outcome;
+use crate::events::RunEvent;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long to wait for a webhook to respond.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationFormat {
+    /// The run summary as JSON
+    #[default]
+    Json,
+    /// `{"text": ...}`, as accepted by Slack incoming webhooks
+    Slack,
+}
+
+/// A webhook in smelt.yml `notifications:`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Notification {
+    /// URL to POST to; use `{{ env_var('...') }}` to keep it out of smelt.yml
+    pub url: String,
+    /// When to send it; defaults to after every run
+    #[serde(default = "default_on")]
+    pub on: Vec<NotifyOn>,
+    #[serde(default)]
+    pub format: NotificationFormat,
+}
+
+fn default_on() -> Vec<NotifyOn> {
+    vec![NotifyOn::Success, NotifyOn::Failure]
+}
+
+/// What a notification reports about a finished run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunNotification {
+    pub project: String,
+    pub target: String,
+    pub elapsed_secs: f64,
+    /// The run's [`RunEvent::RunSummary`]
+    #[serde(flatten)]
+    pub summary: RunEvent,
+}
+
+impl RunNotification {
+    pub fn new(project: &str, target: &str, elapsed: Duration, result: &Result<()>) -> Self {
+        Self {
+            project: project.to_string(),
+            target: target.to_string(),
+            elapsed_secs: elapsed.as_secs_f64(),
+            summary: RunEvent::run_summary(result),
+        }
+    }
+
+    fn succeeded(&self) -> bool {
+        matches!(
+            self.summary,
+            RunEvent::RunSummary {
+                outcome: Outcome::Success,
+                ..
+            }
+        )
+    }
+
+    /// A one-message summary for chat.
+    pub fn text(&self) -> String {
+        let RunEvent::RunSummary {
+            message,
+            failed,
+            skipped,
+            ..
+        } = &self.summary
+        else {
+            return String::new();
+        };
+
+        let mut text = if self.succeeded() {
+            format!(
+                "✓ smelt run succeeded for {} (target {}) in {:.1}s",
+                self.project, self.target, self.elapsed_secs
+            )
+        } else {
+            format!(
+                "✗ smelt run failed for {} (target {}) after {:.1}s",
+                self.project, self.target, self.elapsed_secs
+            )
+        };
+        if let Some(message) = message {
+            text.push_str(&format!("\n{}", message));
+        }
+        if !failed.is_empty() {
+            text.push_str(&format!("\nFailed: {}", failed.join(", ")));
+        }
+        if !skipped.is_empty() {
+            text.push_str(&format!("\nSkipped: {}", skipped.join(", ")));
+        }
+        text
+    }
+}
+
+impl Notification {
+    /// Whether this notification is sent for `run`.
+    pub fn wants(&self, run: &RunNotification) -> bool {
+        let on = if run.succeeded() {
+            NotifyOn::Success
+        } else {
+            NotifyOn::Failure
+        };
+        self.on.contains(&on)
+    }
+
+    pub fn payload(&self, run: &RunNotification) -> serde_json::Value {
+        match self.format {
+            NotificationFormat::Json => serde_json::to_value(run).unwrap_or_default(),
+            NotificationFormat::Slack => serde_json::json!({ "text": run.text() }),
+        }
+    }
+
+    /// The URL without its path, which for most webhooks holds a secret.
+    pub fn host(&self) -> &str {
+        let start = self.url.find("://").map_or(0, |i| i + 3);
+        let end = self.url[start..]
+            .find('/')
+            .map_or(self.url.len(), |i| start + i);
+        &self.url[..end]
+    }
+}
+
+/// POST `run` to every notification that wants it, returning the failures.
+pub async fn send_notifications(
+    notifications: &[Notification],
+    run: &RunNotification,
+) -> Vec<anyhow::Error> {
+    let client = reqwest::Client::new();
+    let mut failures = Vec::new();
+
+    for notification in notifications.iter().filter(|n| n.wants(run)) {
+        if let Err(e) = send(&client, notification, run).await {
+            failures.push(e);
+        }
+    }
+    failures
+}
+
+async fn send(
+    client: &reqwest::Client,
+    notification: &Notification,
+    run: &RunNotification,
+) -> Result<()> {
+    client
+        .post(&notification.url)
+        .timeout(TIMEOUT)
+        .json(&notification.payload(run))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to notify {}", notification.host()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CliError;
+
+    #[test]
+    fn test_notification_payloads() {
+        let notification: Notification = serde_yaml::from_str(
+            "url: https://hooks.slack.com/services/T000/B000/secret\non: [failure]\nformat: slack\n",
+        )
+        .unwrap();
+        assert_eq!(notification.host(), "https://hooks.slack.com");
+
+        let succeeded = RunNotification::new("shop", "prod", Duration::from_secs(3), &Ok(()));
+        assert!(!notification.wants(&succeeded));
+
+        let failed = RunNotification::new(
+            "shop",
+            "prod",
+            Duration::from_millis(12_340),
+            &Err(CliError::RunFailed {
+                failed: vec!["orders".to_string()],
+                skipped: vec!["revenue".to_string()],
+                succeeded: 2,
+            }
+            .into()),
+        );
+        assert!(notification.wants(&failed));
+        let text = notification.payload(&failed)["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(text.starts_with("✗ smelt run failed for shop (target prod) after 12.3s\n"));
+        assert!(text.ends_with("\nFailed: orders\nSkipped: revenue"));
+
+        let json: Notification = serde_yaml::from_str("url: http://localhost:9000/hook").unwrap();
+        assert!(json.wants(&succeeded));
+        let payload = json.payload(&failed);
+        assert_eq!(payload["event"], "run_summary");
+        assert_eq!(payload["project"], "shop");
+        assert_eq!(payload["outcome"], "partial_success");
+        assert_eq!(payload["failed"][0], "orders");
+    }
+
+    #[tokio::test]
+    async fn test_send_notifications() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&request).contains("}") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let notifications = vec![Notification {
+            url,
+            on: default_on(),
+            format: NotificationFormat::Slack,
+        }];
+        let run = RunNotification::new("shop", "dev", Duration::from_secs(1), &Ok(()));
+        let failures = send_notifications(&notifications, &run).await;
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(
+            request.contains(r#"{"text":"✓ smelt run succeeded for shop (target dev) in 1.0s"}"#)
+        );
+        assert_eq!(failures.len(), 1);
+        assert!(format!("{:#}", failures[0]).contains("500"));
+    }
+}
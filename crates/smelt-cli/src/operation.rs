@@ -0,0 +1,322 @@
+//! Project operations for `smelt run-operation`.
+//!
+//! An operation is a `.sql` file under one of the `operation_paths`
+//! (`operations/` by default) holding statements that run outside the model
+//! DAG: grants, vacuums, partition maintenance. Before running, `{{ var() }}`
+//! is rendered with `--args` overlaid on the project's vars, and `smelt.ref()`
+//! resolves like it does in `smelt query`:
+//!
+//! ```text
+//! -- operations/grant_select.sql
+//! GRANT SELECT ON smelt.ref('{{ var('model') }}') TO {{ var('role', 'analyst') }};
+//! ```
+//!
+//! Operations in a package's operation paths are named `<package>.<operation>`,
+//! and their plain refs name models in the same package.
+
+use crate::errors::CliError;
+use crate::package::Package;
+use crate::rewrite::replace_statements;
+use crate::template::{render, Vars};
+use anyhow::{anyhow, Context, Result};
+use arrow::array::RecordBatch;
+use smelt_backend::Backend;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// A `.sql` file discovered in one of the operation paths.
+#[derive(Debug, Clone)]
+pub struct OperationFile {
+    /// Operation name: the file stem, prefixed with `<package>.` for package operations
+    pub name: String,
+    pub path: PathBuf,
+    /// Package the operation comes from, if any
+    pub package: Option<String>,
+}
+
+/// Outcome of running an operation.
+#[derive(Debug)]
+pub struct OperationResult {
+    pub statement_count: usize,
+    pub duration: Duration,
+    /// Rows returned by the last statement
+    pub batches: Vec<RecordBatch>,
+}
+
+/// Find all `.sql` files under the configured operation paths and those of
+/// `packages`, sorted by name.
+pub fn discover_operations(
+    project_root: &Path,
+    operation_paths: &[String],
+    packages: &[Package],
+) -> Result<Vec<OperationFile>> {
+    let mut operations = Vec::new();
+    discover_in(project_root, operation_paths, None, &mut operations)?;
+    for package in packages {
+        discover_in(
+            &package.root,
+            &package.operation_paths,
+            Some(&package.name),
+            &mut operations,
+        )?;
+    }
+
+    operations.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(operations)
+}
+
+/// Add the `.sql` files under `operation_paths` of `root` to `operations`.
+fn discover_in(
+    root: &Path,
+    operation_paths: &[String],
+    package: Option<&str>,
+    operations: &mut Vec<OperationFile>,
+) -> Result<()> {
+    for operation_path in operation_paths {
+        let search_path = root.join(operation_path);
+
+        if !search_path.exists() {
+            continue;
+        }
+
+        for entry in WalkDir::new(&search_path)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            if path.extension().and_then(|s| s.to_str()) == Some("sql") {
+                let stem = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .ok_or_else(|| anyhow!("Cannot determine operation name from {:?}", path))?;
+
+                operations.push(OperationFile {
+                    name: match package {
+                        Some(package) => format!("{}.{}", package, stem),
+                        None => stem.to_string(),
+                    },
+                    path: path.to_path_buf(),
+                    package: package.map(str::to_string),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Find the operation called `name`.
+pub fn find_operation(
+    project_root: &Path,
+    operation_paths: &[String],
+    packages: &[Package],
+    name: &str,
+) -> Result<OperationFile> {
+    let operations = discover_operations(project_root, operation_paths, packages)?;
+    let available: Vec<&str> = operations.iter().map(|o| o.name.as_str()).collect();
+
+    match operations.iter().find(|o| o.name == name) {
+        Some(operation) => Ok(operation.clone()),
+        None if available.is_empty() => Err(anyhow!(
+            "Operation '{}' not found: no .sql files in operation paths ({})",
+            name,
+            operation_paths.join(", ")
+        )),
+        None => Err(anyhow!(
+            "Operation '{}' not found. Available operations: {}",
+            name,
+            available.join(", ")
+        )),
+    }
+}
+
+/// Parse `--args`, a YAML (or JSON) mapping such as `{model: users, days: 30}`.
+pub fn parse_args(yaml: &str) -> Result<Vars> {
+    serde_yaml::from_str(yaml).map_err(|e| {
+        anyhow!(
+            "--args must be a YAML mapping, e.g. '{{model: users}}': {}",
+            e
+        )
+    })
+}
+
+/// Read an operation and render it into statements, with `args` taking
+/// precedence over the project's `vars`.
+pub fn render_operation(
+    operation: &OperationFile,
+    vars: &Vars,
+    args: &Vars,
+) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(&operation.path)
+        .with_context(|| format!("Failed to read operation {:?}", operation.path))?;
+
+    let mut vars = vars.clone();
+    vars.extend(args.iter().map(|(k, v)| (k.clone(), v.clone())));
+    let sql = render(&content, &vars)
+        .with_context(|| format!("Failed to render operation '{}'", operation.name))?;
+
+    Ok(split_statements(&sql))
+}
+
+/// Split SQL on `;`, ignoring semicolons in quotes and comments.
+///
+/// Statements that are empty or only comments are dropped.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    let mut chars = sql.char_indices().peekable();
+
+    let mut push = |statement: &str, has_code: bool| {
+        if has_code {
+            statements.push(statement.trim().to_string());
+        }
+    };
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                has_code = true;
+                for (_, next) in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek().is_some_and(|(_, next)| *next == '-') => {
+                for (_, next) in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().is_some_and(|(_, next)| *next == '*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, next) in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            ';' => {
+                push(&sql[start..i], has_code);
+                start = i + 1;
+                has_code = false;
+            }
+            c if !c.is_whitespace() => has_code = true,
+            _ => {}
+        }
+    }
+    push(&sql[start..], has_code);
+
+    statements
+}
+
+/// Run an operation's statements in order, stopping at the first failure.
+pub async fn run_operation(
+    backend: &dyn Backend,
+    name: &str,
+    statements: &[String],
+) -> Result<OperationResult> {
+    let started = Instant::now();
+    let mut batches = Vec::new();
+
+    let capabilities = backend.capabilities();
+    for (i, statement) in statements.iter().enumerate() {
+        for sql in replace_statements(statement, &capabilities) {
+            batches = backend
+                .execute_sql(&sql)
+                .await
+                .map_err(|e| CliError::OperationError {
+                    operation: name.to_string(),
+                    index: i + 1,
+                    sql: sql.clone(),
+                    source: e.into(),
+                })?;
+        }
+    }
+
+    Ok(OperationResult {
+        statement_count: statements.len(),
+        duration: started.elapsed(),
+        batches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_backend::RelationName;
+    use smelt_backend_duckdb::DuckDbBackend;
+
+    #[test]
+    fn test_split_statements() {
+        let sql = "-- Drop old partitions; keep a week\n\
+                   DELETE FROM events WHERE note = 'a;b';\n\
+                   /* vacuum; analyze */ VACUUM;\n\
+                   ANALYZE events\n\
+                   ; -- done";
+        assert_eq!(
+            split_statements(sql),
+            vec![
+                "-- Drop old partitions; keep a week\nDELETE FROM events WHERE note = 'a;b'",
+                "/* vacuum; analyze */ VACUUM",
+                "ANALYZE events",
+            ]
+        );
+        assert!(split_statements("  -- nothing to do\n").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_operation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let operations_dir = temp_dir.path().join("operations");
+        std::fs::create_dir_all(&operations_dir).unwrap();
+        std::fs::write(
+            operations_dir.join("purge.sql"),
+            "CREATE TABLE main.events AS SELECT range AS day FROM range(10);\n\
+             DELETE FROM main.events WHERE day < {{ var('keep_from', '0') }};\n\
+             SELECT COUNT(*) AS remaining FROM main.events;",
+        )
+        .unwrap();
+
+        let paths = vec!["operations".to_string()];
+        let operation = find_operation(temp_dir.path(), &paths, &[], "purge").unwrap();
+        let err = find_operation(temp_dir.path(), &paths, &[], "vacuum").unwrap_err();
+        assert!(err.to_string().contains("Available operations: purge"));
+
+        let vars = parse_args("{keep_from: 2}").unwrap();
+        let args = parse_args("{keep_from: 7}").unwrap();
+        let statements = render_operation(&operation, &vars, &args).unwrap();
+        assert_eq!(statements.len(), 3);
+        assert!(statements[1].ends_with("WHERE day < 7"));
+
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let result = run_operation(&backend, "purge", &statements).await.unwrap();
+        assert_eq!(result.statement_count, 3);
+        assert_eq!(
+            backend
+                .get_row_count(&RelationName::new("main", "events"))
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            result.batches.iter().map(|b| b.num_rows()).sum::<usize>(),
+            1
+        );
+
+        let err = run_operation(&backend, "purge", &statements)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Statement 1 of operation 'purge' failed"));
+    }
+}
@@ -0,0 +1,450 @@
+//! Packages: other smelt projects whose models are built with this one.
+//!
+//! A package is declared under `packages:` in smelt.yml with either the path
+//! to its project directory or a git URL and revision; `smelt deps` clones git
+//! packages into `packages/<name>`. Its models are discovered from the model
+//! paths in its own smelt.yml and named `<package>.<model>`; this project refs
+//! them with `smelt.ref('<package>', '<model>')`. Inside a package, a plain
+//! `smelt.ref('<model>')` names a model in the same package. The sources in a
+//! package's sources.yml are added to this project's, and its operations run
+//! with `smelt run-operation <package>.<operation>`.
+
+use crate::config::{Config, PackageConfig, SourceConfig};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory, relative to the project root, that `smelt deps` clones into
+pub const PACKAGES_DIR: &str = "packages";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    /// Directory containing the package's smelt.yml
+    pub root: PathBuf,
+    /// The package's model paths, relative to its root
+    pub model_paths: Vec<String>,
+    /// The package's operation paths, relative to its root
+    pub operation_paths: Vec<String>,
+}
+
+impl Package {
+    /// The package as smelt-db sees it, for resolving refs in its files
+    pub fn db_package(&self) -> smelt_db::Package {
+        smelt_db::Package {
+            name: self.name.clone(),
+            root: self.root.clone(),
+        }
+    }
+}
+
+/// What `smelt deps` did for a package.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallStatus {
+    /// A local package, read in place
+    Local(PathBuf),
+    /// A git package, now checked out at this commit
+    Cloned { commit: String },
+    /// A git package that was already checked out at this commit
+    UpToDate { commit: String },
+    /// A git package moved from one commit to another
+    Updated { from: String, to: String },
+}
+
+/// Where a package's project lives: its `path`, or `packages/<name>` for git.
+fn package_dir(name: &str, package: &PackageConfig, project_root: &Path) -> Result<PathBuf> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!(
+            "Invalid package name '{}': use only letters, digits, and underscores",
+            name
+        );
+    }
+    match (&package.path, &package.git) {
+        (Some(path), None) => {
+            if package.rev.is_some() {
+                bail!("Package '{}': `rev` only applies to git packages", name);
+            }
+            Ok(project_root.join(path))
+        }
+        (None, Some(_)) => Ok(project_root.join(PACKAGES_DIR).join(name)),
+        (Some(_), Some(_)) => bail!("Package '{}' sets both `path` and `git`", name),
+        (None, None) => bail!("Package '{}' needs a `path` or a `git` URL", name),
+    }
+}
+
+/// Load the smelt.yml of every package `config` declares, sorted by name.
+pub fn load_packages(config: &Config, project_root: &Path) -> Result<Vec<Package>> {
+    config
+        .packages
+        .iter()
+        .map(|(name, package)| {
+            let dir = package_dir(name, package, project_root)?;
+            let root = match dir.canonicalize() {
+                Ok(root) => root,
+                Err(_) if package.git.is_some() => {
+                    bail!("Package '{}' is not installed; run `smelt deps`", name)
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Package '{}' not found at {:?}", name, dir))
+                }
+            };
+            let package_config = Config::load(&root)
+                .with_context(|| format!("Failed to load package '{}'", name))?;
+            Ok(Package {
+                name: name.clone(),
+                root,
+                model_paths: package_config.model_paths,
+                operation_paths: package_config.operation_paths,
+            })
+        })
+        .collect()
+}
+
+/// Fetch a declared package: clone or update a git package in `packages/`
+/// and check out its `rev`, or check that a local package exists.
+pub fn install_package(
+    name: &str,
+    package: &PackageConfig,
+    project_root: &Path,
+) -> Result<InstallStatus> {
+    let dir = package_dir(name, package, project_root)?;
+    let Some(url) = &package.git else {
+        Config::load(&dir).with_context(|| format!("Package '{}' not found at {:?}", name, dir))?;
+        return Ok(InstallStatus::Local(dir));
+    };
+
+    // A checkout of another repository is replaced, not updated
+    let previous = if dir.join(".git").exists()
+        && git(&dir, &["remote", "get-url", "origin"]).ok().as_deref() == Some(url.as_str())
+    {
+        git(&dir, &["fetch", "--quiet", "--tags", "origin"])
+            .with_context(|| format!("Failed to fetch package '{}'", name))?;
+        Some(git(&dir, &["rev-parse", "HEAD"])?)
+    } else {
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {:?}", dir))?;
+        }
+        std::fs::create_dir_all(project_root.join(PACKAGES_DIR))?;
+        git(
+            project_root,
+            &["clone", "--quiet", url, &dir.to_string_lossy()],
+        )
+        .with_context(|| format!("Failed to clone package '{}' from {}", name, url))?;
+        None
+    };
+
+    // Prefer the remote branch, so a branch rev picks up new commits
+    let rev = package.rev.as_deref().unwrap_or("HEAD");
+    let commit = [
+        format!("origin/{}^{{commit}}", rev),
+        format!("{}^{{commit}}", rev),
+    ]
+    .iter()
+    .find_map(|candidate| git(&dir, &["rev-parse", "--verify", "--quiet", candidate]).ok())
+    .with_context(|| {
+        format!(
+            "Package '{}': revision '{}' not found in {}",
+            name, rev, url
+        )
+    })?;
+    git(&dir, &["checkout", "--quiet", "--detach", &commit])
+        .with_context(|| format!("Failed to check out '{}' for package '{}'", rev, name))?;
+    Config::load(&dir).with_context(|| format!("Failed to load package '{}'", name))?;
+
+    Ok(match previous {
+        None => InstallStatus::Cloned { commit },
+        Some(from) if from == commit => InstallStatus::UpToDate { commit },
+        Some(from) => InstallStatus::Updated { from, to: commit },
+    })
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Add the sources each package declares in its sources.yml to `sources`;
+/// tables this project declares itself win.
+pub fn merge_package_sources(
+    sources: &mut Option<SourceConfig>,
+    packages: &[Package],
+) -> Result<()> {
+    for package in packages {
+        if !package.root.join("sources.yml").exists() {
+            continue;
+        }
+        let package_sources = SourceConfig::load(&package.root)
+            .with_context(|| format!("Failed to load sources of package '{}'", package.name))?;
+        match sources {
+            Some(sources) => sources.merge(package_sources),
+            None => *sources = Some(package_sources),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::SqlCompiler;
+    use crate::discovery::ModelDiscovery;
+    use crate::graph::DependencyGraph;
+    use crate::operation::{discover_operations, find_operation, parse_args, render_operation};
+    use crate::query::compile_query;
+    use crate::template::Vars;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let shared = temp_dir.path().join("shared");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::write(
+            shared.join("smelt.yml"),
+            "name: shared\nversion: 1\nmodel_paths: [marts]\ntargets: {}\n",
+        )
+        .unwrap();
+
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let config: Config = serde_yaml::from_str(
+            "name: project\nversion: 1\ntargets: {}\npackages:\n  shared:\n    path: ../shared\n",
+        )
+        .unwrap();
+        let packages = load_packages(&config, &project).unwrap();
+        assert_eq!(
+            packages,
+            vec![Package {
+                name: "shared".to_string(),
+                root: shared.canonicalize().unwrap(),
+                model_paths: vec!["marts".to_string()],
+                operation_paths: vec!["operations".to_string()],
+            }]
+        );
+
+        let config: Config = serde_yaml::from_str(
+            "name: project\nversion: 1\ntargets: {}\npackages:\n  missing:\n    path: ../missing\n",
+        )
+        .unwrap();
+        let err = load_packages(&config, &project).unwrap_err();
+        assert!(err.to_string().contains("Package 'missing' not found"));
+
+        let config: Config = serde_yaml::from_str(
+            "name: project\nversion: 1\ntargets: {}\npackages:\n  remote:\n    git: https://example.com/remote.git\n",
+        )
+        .unwrap();
+        let err = load_packages(&config, &project).unwrap_err();
+        assert!(err.to_string().contains("run `smelt deps`"));
+    }
+
+    #[test]
+    fn test_install_git_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        let commit = |message: &str| {
+            git(&repo, &["add", "-A"]).unwrap();
+            git(
+                &repo,
+                &[
+                    "-c",
+                    "user.name=smelt",
+                    "-c",
+                    "user.email=smelt@example.com",
+                    "commit",
+                    "--quiet",
+                    "-m",
+                    message,
+                ],
+            )
+            .unwrap();
+            git(&repo, &["rev-parse", "HEAD"]).unwrap()
+        };
+        git(&repo, &["init", "--quiet"]).unwrap();
+        std::fs::write(
+            repo.join("smelt.yml"),
+            "name: shared\nversion: 1\ntargets: {}\n",
+        )
+        .unwrap();
+        let first = commit("first");
+        git(&repo, &["tag", "v1"]).unwrap();
+        std::fs::write(repo.join("sources.yml"), "version: 1\nsources: {}\n").unwrap();
+        let second = commit("second");
+
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let mut package = PackageConfig {
+            git: Some(repo.to_string_lossy().into_owned()),
+            rev: Some("v1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            install_package("shared", &package, &project).unwrap(),
+            InstallStatus::Cloned {
+                commit: first.clone()
+            }
+        );
+        assert!(!project.join("packages/shared/sources.yml").exists());
+        assert_eq!(
+            install_package("shared", &package, &project).unwrap(),
+            InstallStatus::UpToDate {
+                commit: first.clone()
+            }
+        );
+
+        // Without a rev, the remote's default branch is checked out
+        package.rev = None;
+        assert_eq!(
+            install_package("shared", &package, &project).unwrap(),
+            InstallStatus::Updated {
+                from: first,
+                to: second
+            }
+        );
+        assert!(project.join("packages/shared/sources.yml").exists());
+
+        let mut config: Config =
+            serde_yaml::from_str("name: project\nversion: 1\ntargets: {}\n").unwrap();
+        config.packages.insert("shared".to_string(), package);
+        let packages = load_packages(&config, &project).unwrap();
+        assert_eq!(
+            packages[0].root,
+            project.join("packages/shared").canonicalize().unwrap()
+        );
+
+        package = PackageConfig {
+            path: Some("../repo".into()),
+            git: Some("https://example.com/repo.git".to_string()),
+            ..Default::default()
+        };
+        let err = install_package("shared", &package, &project).unwrap_err();
+        assert!(err.to_string().contains("both `path` and `git`"));
+    }
+
+    #[test]
+    fn test_package_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let shared = temp_dir.path().join("shared");
+        std::fs::create_dir_all(shared.join("models")).unwrap();
+        std::fs::create_dir_all(shared.join("operations")).unwrap();
+        std::fs::write(
+            shared.join("smelt.yml"),
+            "name: shared\nversion: 1\ntargets: {}\n",
+        )
+        .unwrap();
+        std::fs::write(shared.join("models/customers.sql"), "SELECT 1 AS id").unwrap();
+        std::fs::write(
+            shared.join("operations/count_rows.sql"),
+            "SELECT COUNT(*) FROM smelt.ref('customers') WHERE id > {{ var('min_id') }};",
+        )
+        .unwrap();
+
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(project.join("operations")).unwrap();
+        std::fs::write(project.join("operations/vacuum.sql"), "VACUUM;").unwrap();
+        let config: Config = serde_yaml::from_str(
+            "name: project\nversion: 1\ntargets: {}\npackages:\n  shared:\n    path: ../shared\n",
+        )
+        .unwrap();
+        let packages = load_packages(&config, &project).unwrap();
+
+        let names: Vec<String> = discover_operations(&project, &config.operation_paths, &packages)
+            .unwrap()
+            .into_iter()
+            .map(|operation| operation.name)
+            .collect();
+        assert_eq!(names, vec!["shared.count_rows", "vacuum"]);
+
+        // Plain refs in a package operation name the package's models
+        let operation = find_operation(
+            &project,
+            &config.operation_paths,
+            &packages,
+            "shared.count_rows",
+        )
+        .unwrap();
+        assert_eq!(operation.package.as_deref(), Some("shared"));
+        let models = ModelDiscovery::new(project.clone(), config.model_paths.clone())
+            .with_packages(&packages)
+            .discover_models()
+            .unwrap();
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let compiler = SqlCompiler::new(config).with_models(graph.models().values());
+        let statements = render_operation(
+            &operation,
+            &Vars::new(),
+            &parse_args("{min_id: 0}").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            compile_query(
+                &statements[0],
+                operation.package.as_deref(),
+                "main",
+                &graph,
+                &compiler
+            )
+            .unwrap(),
+            "SELECT COUNT(*) FROM shared.customers WHERE id > 0"
+        );
+    }
+
+    #[test]
+    fn test_merge_package_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("sources.yml"),
+            r#"
+version: 1
+sources:
+  raw:
+    tables:
+      users:
+        description: from the package
+        columns: []
+      events:
+        columns: []
+  stripe:
+    tables:
+      charges:
+        columns: []
+"#,
+        )
+        .unwrap();
+        let package = Package {
+            name: "shared".to_string(),
+            root: temp_dir.path().to_path_buf(),
+            model_paths: vec![],
+            operation_paths: vec![],
+        };
+
+        let mut sources: Option<SourceConfig> = Some(
+            serde_yaml::from_str(
+                "version: 1\nsources:\n  raw:\n    tables:\n      users:\n        description: ours\n        columns: []\n",
+            )
+            .unwrap(),
+        );
+        merge_package_sources(&mut sources, std::slice::from_ref(&package)).unwrap();
+        let sources = sources.unwrap();
+        assert_eq!(sources.sources["raw"].tables["users"].description, "ours");
+        assert!(sources.sources["raw"].tables.contains_key("events"));
+        assert!(sources.sources["stripe"].tables.contains_key("charges"));
+
+        let mut sources = None;
+        merge_package_sources(&mut sources, &[package]).unwrap();
+        assert_eq!(sources.unwrap().sources.len(), 2);
+    }
+}
@@ -0,0 +1,332 @@
+//! Partition values for incremental runs.
+//!
+//! An incremental model replaces whole partitions of its `partition_granularity`
+//! (hour, day, week, or month). The `--event-time-start`/`--event-time-end`
+//! range is widened to partition boundaries first, so rows outside the
+//! requested range but inside a replaced partition are rebuilt rather than lost.
+//!
+//! Daily, weekly, and monthly partition values are dates (`2024-01-15`; weeks
+//! start on Monday, months on the 1st); hourly values are timestamps
+//! (`2024-01-15 03:00:00`).
+//!
+//! `smelt backfill` splits a long range into chunks of whole partitions with
+//! [`split_time_range`], so each chunk replaces a disjoint set of partitions.
+
+use crate::config::PartitionGranularity;
+use crate::transformer::TimeRange;
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+const TIMESTAMP_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// Parse an event time: `YYYY-MM-DD` or `YYYY-MM-DD HH:MM[:SS]` (or with a `T`).
+pub fn parse_event_time(value: &str) -> Result<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN));
+    }
+    TIMESTAMP_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid event time: {}. Expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS",
+                value
+            )
+        })
+}
+
+/// Check an event time range, returning it as given.
+pub fn parse_time_range(start: &str, end: &str) -> Result<TimeRange> {
+    if parse_event_time(start)? >= parse_event_time(end)? {
+        return Err(anyhow!(
+            "Start time ({}) must be before end time ({})",
+            start,
+            end
+        ));
+    }
+    Ok(TimeRange {
+        start: start.to_string(),
+        end: end.to_string(),
+    })
+}
+
+/// Widen a range to whole partitions, formatted like the partition values.
+pub fn align_time_range(range: &TimeRange, granularity: PartitionGranularity) -> Result<TimeRange> {
+    let (start, end) = aligned_bounds(range, granularity)?;
+    Ok(TimeRange {
+        start: format_partition(start, granularity),
+        end: format_partition(end, granularity),
+    })
+}
+
+/// Whether a range already starts and ends on partition boundaries.
+pub fn is_aligned(range: &TimeRange, granularity: PartitionGranularity) -> Result<bool> {
+    let (start, end) = aligned_bounds(range, granularity)?;
+    Ok(start == parse_event_time(&range.start)? && end == parse_event_time(&range.end)?)
+}
+
+/// The partitions a range covers, after widening it to whole partitions.
+pub fn partition_values(
+    range: &TimeRange,
+    granularity: PartitionGranularity,
+) -> Result<Vec<String>> {
+    let (start, end) = aligned_bounds(range, granularity)?;
+
+    let mut values = Vec::new();
+    let mut current = start;
+    while current < end {
+        values.push(format_partition(current, granularity));
+        current = next_partition(current, granularity);
+    }
+
+    Ok(values)
+}
+
+/// Parse a backfill chunk size: a count of hours, days, or weeks (`12h`, `7d`, `2w`).
+pub fn parse_chunk(spec: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow!(
+            "Invalid chunk size: {}. Expected a number of hours, days, or weeks, e.g. 12h, 7d, 2w",
+            spec
+        )
+    };
+
+    let (unit_at, _) = spec.char_indices().last().ok_or_else(invalid)?;
+    let (count, unit) = spec.split_at(unit_at);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    if count <= 0 {
+        return Err(invalid());
+    }
+
+    match unit {
+        "h" => Ok(Duration::hours(count)),
+        "d" => Ok(Duration::days(count)),
+        "w" => Ok(Duration::weeks(count)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Split a range into consecutive chunks of about `chunk` each.
+///
+/// The range is widened to whole partitions and every chunk boundary is
+/// rounded up to the next partition boundary, so chunks never share a
+/// partition (and are at least one partition long).
+pub fn split_time_range(
+    range: &TimeRange,
+    chunk: Duration,
+    granularity: PartitionGranularity,
+) -> Result<Vec<TimeRange>> {
+    let (start, end) = aligned_bounds(range, granularity)?;
+
+    let mut chunks = Vec::new();
+    let mut chunk_start = start;
+    while chunk_start < end {
+        let chunk_end = partition_ceil(chunk_start + chunk, granularity).min(end);
+        chunks.push(TimeRange {
+            start: format_partition(chunk_start, granularity),
+            end: format_partition(chunk_end, granularity),
+        });
+        chunk_start = chunk_end;
+    }
+
+    Ok(chunks)
+}
+
+fn aligned_bounds(
+    range: &TimeRange,
+    granularity: PartitionGranularity,
+) -> Result<(NaiveDateTime, NaiveDateTime)> {
+    let start = parse_event_time(&range.start)?;
+    let end = parse_event_time(&range.end)?;
+    if start >= end {
+        return Err(anyhow!(
+            "Start time ({}) must be before end time ({})",
+            range.start,
+            range.end
+        ));
+    }
+
+    Ok((
+        partition_start(start, granularity),
+        partition_ceil(end, granularity),
+    ))
+}
+
+/// `time` if it's a partition boundary, otherwise the start of the next partition.
+fn partition_ceil(time: NaiveDateTime, granularity: PartitionGranularity) -> NaiveDateTime {
+    match partition_start(time, granularity) {
+        floor if floor == time => time,
+        floor => next_partition(floor, granularity),
+    }
+}
+
+/// Start of the partition containing `time`.
+fn partition_start(time: NaiveDateTime, granularity: PartitionGranularity) -> NaiveDateTime {
+    let date = time.date();
+    match granularity {
+        PartitionGranularity::Hour => date
+            .and_hms_opt(time.hour(), 0, 0)
+            .expect("hour of a valid time is valid"),
+        PartitionGranularity::Day => date.and_time(NaiveTime::MIN),
+        PartitionGranularity::Week => {
+            let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+            monday.and_time(NaiveTime::MIN)
+        }
+        PartitionGranularity::Month => date
+            .with_day(1)
+            .expect("every month has a first day")
+            .and_time(NaiveTime::MIN),
+    }
+}
+
+fn next_partition(start: NaiveDateTime, granularity: PartitionGranularity) -> NaiveDateTime {
+    match granularity {
+        PartitionGranularity::Hour => start + Duration::hours(1),
+        PartitionGranularity::Day => start + Duration::days(1),
+        PartitionGranularity::Week => start + Duration::weeks(1),
+        PartitionGranularity::Month => start + Months::new(1),
+    }
+}
+
+fn format_partition(time: NaiveDateTime, granularity: PartitionGranularity) -> String {
+    match granularity {
+        PartitionGranularity::Hour => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        _ => time.format("%Y-%m-%d").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: &str, end: &str) -> TimeRange {
+        TimeRange {
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+
+    #[test]
+    fn test_daily_partitions() {
+        let values = partition_values(
+            &range("2024-01-30", "2024-02-02"),
+            PartitionGranularity::Day,
+        )
+        .unwrap();
+        assert_eq!(values, vec!["2024-01-30", "2024-01-31", "2024-02-01"]);
+    }
+
+    #[test]
+    fn test_hourly_partitions() {
+        let range = range("2024-01-15 22:30", "2024-01-16T01:00:00");
+
+        let values = partition_values(&range, PartitionGranularity::Hour).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                "2024-01-15 22:00:00",
+                "2024-01-15 23:00:00",
+                "2024-01-16 00:00:00"
+            ]
+        );
+
+        let aligned = align_time_range(&range, PartitionGranularity::Hour).unwrap();
+        assert_eq!(aligned.start, "2024-01-15 22:00:00");
+        assert_eq!(aligned.end, "2024-01-16 01:00:00");
+        assert!(!is_aligned(&range, PartitionGranularity::Hour).unwrap());
+        assert!(is_aligned(&aligned, PartitionGranularity::Hour).unwrap());
+    }
+
+    #[test]
+    fn test_weekly_and_monthly_partitions_widen_range() {
+        // Wednesday to the following Tuesday spans two ISO weeks
+        let weeks = range("2024-01-17", "2024-01-23");
+        assert_eq!(
+            partition_values(&weeks, PartitionGranularity::Week).unwrap(),
+            vec!["2024-01-15", "2024-01-22"]
+        );
+        let aligned = align_time_range(&weeks, PartitionGranularity::Week).unwrap();
+        assert_eq!(
+            (aligned.start.as_str(), aligned.end.as_str()),
+            ("2024-01-15", "2024-01-29")
+        );
+
+        let months = range("2024-01-31", "2024-03-01");
+        assert_eq!(
+            partition_values(&months, PartitionGranularity::Month).unwrap(),
+            vec!["2024-01-01", "2024-02-01"]
+        );
+    }
+
+    #[test]
+    fn test_split_time_range() {
+        let ranges = |chunks: Vec<TimeRange>| -> Vec<(String, String)> {
+            chunks.into_iter().map(|c| (c.start, c.end)).collect()
+        };
+
+        let days = range("2024-01-01", "2024-01-18");
+        let chunks = split_time_range(&days, parse_chunk("7d").unwrap(), PartitionGranularity::Day);
+        assert_eq!(
+            ranges(chunks.unwrap()),
+            vec![
+                ("2024-01-01".into(), "2024-01-08".into()),
+                ("2024-01-08".into(), "2024-01-15".into()),
+                ("2024-01-15".into(), "2024-01-18".into()),
+            ]
+        );
+
+        // Chunk boundaries round up to whole months
+        let months = range("2024-01-10", "2024-03-05");
+        let chunks = split_time_range(
+            &months,
+            parse_chunk("2w").unwrap(),
+            PartitionGranularity::Month,
+        );
+        assert_eq!(
+            ranges(chunks.unwrap()),
+            vec![
+                ("2024-01-01".into(), "2024-02-01".into()),
+                ("2024-02-01".into(), "2024-03-01".into()),
+                ("2024-03-01".into(), "2024-04-01".into()),
+            ]
+        );
+
+        let hours = range("2024-01-01 00:00", "2024-01-01 05:00");
+        let chunks = split_time_range(
+            &hours,
+            parse_chunk("2h").unwrap(),
+            PartitionGranularity::Hour,
+        );
+        assert_eq!(chunks.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_parse_chunk() {
+        assert_eq!(parse_chunk("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_chunk("7d").unwrap(), Duration::days(7));
+        assert_eq!(parse_chunk("2w").unwrap(), Duration::weeks(2));
+        for invalid in ["", "d", "0d", "-1d", "7", "1mo", "1.5d", "7é"] {
+            assert!(
+                parse_chunk(invalid).is_err(),
+                "{:?} should be invalid",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_time_range() {
+        assert!(parse_time_range("2024-01-15", "2024-01-15 06:00").is_ok());
+
+        let err = parse_time_range("2024-01-18", "2024-01-15").unwrap_err();
+        assert!(err.to_string().contains("must be before"));
+
+        let err = parse_time_range("2024/01/15", "2024-01-18").unwrap_err();
+        assert!(err.to_string().contains("Invalid event time: 2024/01/15"));
+    }
+}
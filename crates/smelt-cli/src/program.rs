@@ -0,0 +1,315 @@
+//! Program models: tables built by running a command instead of SQL.
+//!
+//! A model with a `program:` in smelt.yml runs its command from the project
+//! root once its upstream models are built. Each model or source it depends on
+//! is exported to `$SMELT_INPUT_DIR/<name>.arrow` (an Arrow IPC file), and the
+//! program writes its result to stdout as an Arrow IPC stream or a Parquet
+//! file. The result replaces the model's table on every run.
+
+use crate::config::{ProgramConfig, ProgramFormat};
+use crate::errors::CliError;
+use crate::seed::create_table_sql;
+use anyhow::{anyhow, bail, Context, Result};
+use arrow::array::RecordBatch;
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::FileWriter;
+use futures::StreamExt;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use smelt_backend::{Backend, ExecutionResult, QueryStats, RelationName};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Directory a program model's inputs are exported to, relative to the project root.
+pub fn input_dir(project_root: &Path, model_name: &str) -> PathBuf {
+    project_root
+        .join("target")
+        .join("programs")
+        .join(model_name)
+}
+
+/// Run `program` and load its output into `relation`.
+///
+/// `inputs` are the relations the program depends on, by the name they are
+/// exported as. The exported files are removed after a successful run and left
+/// in place after a failure, so the command can be rerun by hand.
+pub async fn run_program(
+    backend: &dyn Backend,
+    model_name: &str,
+    program: &ProgramConfig,
+    project_root: &Path,
+    inputs: &[(String, RelationName)],
+    relation: &RelationName,
+) -> Result<ExecutionResult> {
+    run_program_inner(backend, model_name, program, project_root, inputs, relation)
+        .await
+        .map_err(|e| {
+            CliError::ProgramError {
+                model: model_name.to_string(),
+                command: program.command.join(" "),
+                source: e,
+            }
+            .into()
+        })
+}
+
+async fn run_program_inner(
+    backend: &dyn Backend,
+    model_name: &str,
+    program: &ProgramConfig,
+    project_root: &Path,
+    inputs: &[(String, RelationName)],
+    relation: &RelationName,
+) -> Result<ExecutionResult> {
+    let start = Instant::now();
+    let (command, args) = program
+        .command
+        .split_first()
+        .ok_or_else(|| anyhow!("`command` is empty"))?;
+
+    let dir = input_dir(project_root, model_name);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .with_context(|| format!("Failed to clear input directory {:?}", dir))?;
+    }
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create input directory {:?}", dir))?;
+    for (name, input) in inputs {
+        export_relation(backend, input, &dir.join(format!("{}.arrow", name))).await?;
+    }
+
+    let output = tokio::process::Command::new(command)
+        .args(args)
+        .current_dir(project_root)
+        .env("SMELT_MODEL", model_name)
+        .env("SMELT_SCHEMA", &relation.schema)
+        .env("SMELT_INPUT_DIR", &dir)
+        .envs(&program.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("Failed to start {}", command))?;
+    if !output.status.success() {
+        bail!("{} exited with {}", command, output.status);
+    }
+
+    let (schema, batches) = read_output(output.stdout, program.format)?;
+    let dialect = backend.dialect();
+    backend.drop_table_if_exists(relation).await?;
+    backend
+        .execute_sql(&create_table_sql(
+            &dialect.quote_relation(relation),
+            &schema,
+            dialect,
+        ))
+        .await?;
+    backend.load_record_batches(relation, &batches).await?;
+    let row_count = backend.get_row_count(relation).await?;
+
+    std::fs::remove_dir_all(&dir)
+        .with_context(|| format!("Failed to remove input directory {:?}", dir))?;
+
+    Ok(ExecutionResult {
+        model_name: model_name.to_string(),
+        duration: start.elapsed(),
+        row_count,
+        preview: None,
+        stats: QueryStats::default(),
+    })
+}
+
+/// Write every row of `relation` to an Arrow IPC file at `path`.
+///
+/// A relation with no rows is written with no columns, since its schema only
+/// arrives with the first batch.
+async fn export_relation(
+    backend: &dyn Backend,
+    relation: &RelationName,
+    path: &Path,
+) -> Result<()> {
+    let sql = format!(
+        "SELECT * FROM {}",
+        backend.dialect().quote_relation(relation)
+    );
+    let mut stream = backend
+        .execute_sql_stream(&sql)
+        .await
+        .with_context(|| format!("Failed to read {}", relation))?;
+
+    let create = || File::create(path).with_context(|| format!("Failed to create {:?}", path));
+    let mut writer = None;
+    while let Some(batch) = stream.next().await {
+        let batch = batch.with_context(|| format!("Failed to read {}", relation))?;
+        if writer.is_none() {
+            writer = Some(FileWriter::try_new(create()?, &batch.schema())?);
+        }
+        if let Some(writer) = &mut writer {
+            writer.write(&batch)?;
+        }
+    }
+
+    let mut writer = match writer {
+        Some(writer) => writer,
+        None => FileWriter::try_new(create()?, &Schema::empty())?,
+    };
+    writer
+        .finish()
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// Decode a program's stdout.
+fn read_output(stdout: Vec<u8>, format: ProgramFormat) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    match format {
+        ProgramFormat::Arrow => {
+            let reader = StreamReader::try_new(std::io::Cursor::new(stdout), None)
+                .context("stdout is not an Arrow IPC stream")?;
+            let schema = reader.schema();
+            let batches = reader
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to read the Arrow IPC stream on stdout")?;
+            Ok((schema, batches))
+        }
+        ProgramFormat::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(stdout))
+                .context("stdout is not a Parquet file")?;
+            let schema = Arc::clone(builder.schema());
+            let batches = builder
+                .build()?
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to read the Parquet file on stdout")?;
+            Ok((schema, batches))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field};
+    use arrow::ipc::writer::StreamWriter;
+    use parquet::arrow::ArrowWriter;
+    use smelt_backend_duckdb::DuckDbBackend;
+    use tempfile::TempDir;
+
+    fn scores() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("customer_id", DataType::Int64, false),
+            Field::new("segment", DataType::Utf8, true),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec![Some("vip"), None, Some("new")])),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn program(script: &str, format: ProgramFormat) -> ProgramConfig {
+        ProgramConfig {
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            depends_on: vec!["orders".to_string()],
+            format,
+            env: [("SCORES".to_string(), "scores".to_string())].into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_program() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let backend = DuckDbBackend::new(&root.join("dev.duckdb"), "main")
+            .await
+            .unwrap();
+        backend
+            .execute_sql("CREATE TABLE orders AS SELECT 1 AS id")
+            .await
+            .unwrap();
+
+        let mut writer = StreamWriter::try_new(
+            File::create(root.join("scores.arrows")).unwrap(),
+            &scores().schema(),
+        )
+        .unwrap();
+        writer.write(&scores()).unwrap();
+        writer.finish().unwrap();
+        let mut writer = ArrowWriter::try_new(
+            File::create(root.join("scores.parquet")).unwrap(),
+            scores().schema(),
+            None,
+        )
+        .unwrap();
+        writer.write(&scores()).unwrap();
+        writer.close().unwrap();
+
+        // The input is exported before the program runs, and removed after
+        let inputs = [("orders".to_string(), RelationName::new("main", "orders"))];
+        let relation = RelationName::new("main", "customer_scores");
+        let arrow = program(
+            r#"test -s "$SMELT_INPUT_DIR/orders.arrow" && cat "$SCORES.arrows""#,
+            ProgramFormat::Arrow,
+        );
+        let result = run_program(
+            &backend,
+            "customer_scores",
+            &arrow,
+            root,
+            &inputs,
+            &relation,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.row_count, 3);
+        assert!(!input_dir(root, "customer_scores").exists());
+
+        let parquet = program(r#"cat "$SCORES.parquet""#, ProgramFormat::Parquet);
+        run_program(
+            &backend,
+            "customer_scores",
+            &parquet,
+            root,
+            &inputs,
+            &relation,
+        )
+        .await
+        .unwrap();
+        let batches = backend
+            .execute_sql("SELECT segment FROM main.customer_scores WHERE customer_id = 3")
+            .await
+            .unwrap();
+        let segment = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(segment.value(0), "new");
+
+        // A failing program leaves its inputs behind and the table as it was
+        let failing = program("echo boom >&2; exit 3", ProgramFormat::Arrow);
+        let err = run_program(
+            &backend,
+            "customer_scores",
+            &failing,
+            root,
+            &inputs,
+            &relation,
+        )
+        .await
+        .unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("Program for model 'customer_scores' failed"));
+        assert!(message.contains("exit status: 3"));
+        assert!(input_dir(root, "customer_scores")
+            .join("orders.arrow")
+            .exists());
+        assert_eq!(backend.get_row_count(&relation).await.unwrap(), 3);
+    }
+}
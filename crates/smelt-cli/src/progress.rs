@@ -0,0 +1,82 @@
+//! Live progress display for `smelt run --progress`.
+//!
+//! Each running model gets a spinner with its elapsed time. Bars are drawn on
+//! stdout, so other output has to go through [`RunProgress::println`] to
+//! appear above them. Handles are `Send + Sync`, so models running
+//! concurrently can each hold their own bar.
+//!
+//! The display is only used when stdout is a terminal; otherwise runs keep
+//! printing plain lines.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::IsTerminal;
+use std::time::Duration;
+
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct RunProgress {
+    bars: MultiProgress,
+    style: ProgressStyle,
+}
+
+impl RunProgress {
+    /// A display on stdout, or `None` if stdout isn't a terminal.
+    pub fn stdout() -> Option<Self> {
+        std::io::stdout()
+            .is_terminal()
+            .then(|| Self::new(ProgressDrawTarget::stdout()))
+    }
+
+    pub fn new(target: ProgressDrawTarget) -> Self {
+        Self {
+            bars: MultiProgress::with_draw_target(target),
+            style: ProgressStyle::with_template("  {spinner} {msg} [{elapsed}]")
+                .expect("progress template is valid"),
+        }
+    }
+
+    /// Show a bar for a model until the returned handle is dropped.
+    pub fn start(&self, model: &str) -> ModelBar {
+        let bar = self.bars.add(ProgressBar::new_spinner());
+        bar.set_style(self.style.clone());
+        bar.set_message(model.to_string());
+        bar.enable_steady_tick(TICK_INTERVAL);
+        ModelBar(bar)
+    }
+
+    /// Print a line above the bars.
+    pub fn println(&self, line: impl AsRef<str>) {
+        // Only fails if the terminal has gone away, in which case nothing can be shown anyway
+        let _ = self.bars.println(line);
+    }
+}
+
+/// A running model's bar, cleared when dropped.
+pub struct ModelBar(ProgressBar);
+
+impl Drop for ModelBar {
+    fn drop(&mut self) {
+        self.0.finish_and_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_bars() {
+        let progress = RunProgress::new(ProgressDrawTarget::hidden());
+
+        let orders = progress.start("orders");
+        let users =
+            std::thread::scope(|scope| scope.spawn(|| progress.start("users")).join().unwrap());
+        assert_eq!(orders.0.message(), "orders");
+        assert_eq!(users.0.message(), "users");
+
+        let bar = orders.0.clone();
+        drop(orders);
+        assert!(bar.is_finished());
+        assert!(!users.0.is_finished());
+    }
+}
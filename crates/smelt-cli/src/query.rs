@@ -0,0 +1,185 @@
+//! Ad-hoc SQL compilation for `smelt query`.
+//!
+//! Queries may use `smelt.ref()` and `smelt.source()` like models do; refs are
+//! checked against the project's models so typos fail before hitting the backend.
+
+use crate::compiler::SqlCompiler;
+use crate::discovery::ref_model_name;
+use crate::graph::DependencyGraph;
+use anyhow::{anyhow, Result};
+use rowan::TextRange;
+
+/// Compile ad-hoc SQL, resolving refs to `schema.model` and sources to their tables.
+///
+/// Refs are resolved by `compiler`, so models built in their own schema (and
+/// ephemeral models) resolve as they do in compiled models. SQL from a
+/// `package` (its operations) resolves plain refs to the package's models.
+pub fn compile_query(
+    sql: &str,
+    package: Option<&str>,
+    schema: &str,
+    graph: &DependencyGraph,
+    compiler: &SqlCompiler,
+) -> Result<String> {
+    let parse = smelt_parser::parse(sql);
+    let file =
+        smelt_parser::File::cast(parse.syntax()).ok_or_else(|| anyhow!("Failed to parse query"))?;
+
+    let mut refs: Vec<(String, TextRange)> = Vec::new();
+    for ref_call in file.refs() {
+        let Some(name) = ref_model_name(&ref_call, package) else {
+            continue;
+        };
+        if ref_call.named_params().count() > 0 {
+            return Err(anyhow!(
+                "smelt.ref('{}') uses named parameters, which are not supported in queries",
+                name
+            ));
+        }
+        if !graph.models().contains_key(&name) {
+            return Err(anyhow!("Query references undefined model '{}'", name));
+        }
+        refs.push((name, ref_call.range()));
+    }
+
+    compiler.rewrite(&compiler.resolve_refs(sql, &refs, schema))
+}
+
+/// Wrap a SELECT so at most `limit` rows are returned.
+pub fn limit_query(sql: &str, limit: usize) -> String {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    format!(
+        "SELECT * FROM (\n{}\n) AS smelt_preview LIMIT {}",
+        sql, limit
+    )
+}
+
+/// Wrap a SELECT so it returns no rows but keeps its columns.
+pub fn empty_query(sql: &str) -> String {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    format!("SELECT * FROM (\n{}\n) AS smelt_empty WHERE false", sql)
+}
+
+/// Whether the shell input buffer holds a complete statement (ends with `;`).
+pub fn statement_complete(buffer: &str) -> bool {
+    buffer.trim_end().ends_with(';')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::ModelFile;
+
+    fn make_graph(names: &[&str]) -> DependencyGraph {
+        let models = names
+            .iter()
+            .map(|name| ModelFile {
+                name: name.to_string(),
+                path: format!("models/{}.sql", name).into(),
+                content: "SELECT 1".to_string(),
+                refs: Vec::new(),
+                parse_errors: Vec::new(),
+                metadata: None,
+            })
+            .collect();
+        DependencyGraph::build(models, None).unwrap()
+    }
+
+    fn make_compiler(graph: &DependencyGraph, yaml: &str) -> SqlCompiler {
+        let config =
+            serde_yaml::from_str(&format!("name: test\nversion: 1\ntargets: {{}}\n{}", yaml))
+                .unwrap();
+        SqlCompiler::new(config).with_models(graph.models().values())
+    }
+
+    #[test]
+    fn test_compile_query_resolves_refs() {
+        let graph = make_graph(&["users", "orders"]);
+        let sql = "SELECT u.id, COUNT(*) FROM smelt.ref('users') u JOIN smelt.ref('orders') o ON u.id = o.user_id GROUP BY u.id";
+
+        let compiled =
+            compile_query(sql, None, "analytics", &graph, &make_compiler(&graph, "")).unwrap();
+
+        assert!(compiled.contains("FROM analytics.users u"));
+        assert!(compiled.contains("JOIN analytics.orders o"));
+        assert!(!compiled.contains("smelt.ref"));
+    }
+
+    #[test]
+    fn test_compile_query_rejects_unknown_model() {
+        let graph = make_graph(&["users"]);
+
+        let compiler = make_compiler(&graph, "");
+        let err = compile_query(
+            "SELECT * FROM smelt.ref('userz')",
+            None,
+            "main",
+            &graph,
+            &compiler,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("undefined model 'userz'"));
+    }
+
+    #[test]
+    fn test_compile_query_uses_model_schema() {
+        let graph = make_graph(&["users", "orders"]);
+        let compiler = make_compiler(&graph, "models:\n  users:\n    schema: crm\n");
+
+        let compiled = compile_query(
+            "SELECT * FROM smelt.ref('users') JOIN smelt.ref('orders') USING (id)",
+            None,
+            "analytics",
+            &graph,
+            &compiler,
+        )
+        .unwrap();
+        assert_eq!(
+            compiled,
+            "SELECT * FROM crm.users JOIN analytics.orders USING (id)"
+        );
+    }
+
+    #[test]
+    fn test_compile_query_resolves_package_refs() {
+        let graph = make_graph(&["users", "shared.users"]);
+        let compiler = make_compiler(&graph, "packages:\n  shared:\n    path: ../shared\n");
+
+        let compiled = compile_query(
+            "SELECT * FROM smelt.ref('users') JOIN smelt.ref('shared', 'users') USING (id)",
+            None,
+            "analytics",
+            &graph,
+            &compiler,
+        )
+        .unwrap();
+        assert_eq!(
+            compiled,
+            "SELECT * FROM analytics.users JOIN shared.users USING (id)"
+        );
+    }
+
+    #[test]
+    fn test_limit_query() {
+        assert_eq!(
+            limit_query("SELECT * FROM main.users;\n", 20),
+            "SELECT * FROM (\nSELECT * FROM main.users\n) AS smelt_preview LIMIT 20"
+        );
+    }
+
+    #[test]
+    fn test_empty_query() {
+        assert_eq!(
+            empty_query("SELECT * FROM main.users;\n"),
+            "SELECT * FROM (\nSELECT * FROM main.users\n) AS smelt_empty WHERE false"
+        );
+    }
+
+    #[test]
+    fn test_statement_complete() {
+        assert!(statement_complete("SELECT 1;"));
+        assert!(statement_complete("SELECT 1;  \n"));
+        assert!(!statement_complete("SELECT *\nFROM smelt.ref('users')"));
+        assert!(!statement_complete(""));
+    }
+}
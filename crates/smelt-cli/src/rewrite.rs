@@ -0,0 +1,380 @@
+//! Rewriting SQL into the syntax variants a backend supports.
+//!
+//! Models may use `QUALIFY` and `expr::type` casts wherever they run: for
+//! backends without them the compiler rewrites QUALIFY into a filtered
+//! subquery and `::` into `CAST(expr AS type)`. Hooks and operations may use
+//! `CREATE OR REPLACE TABLE/VIEW`, which becomes `DROP ... IF EXISTS` followed
+//! by `CREATE` where it's unsupported.
+
+use rowan::NodeOrToken;
+use smelt_backend::BackendCapabilities;
+use smelt_parser::syntax_kind::{SyntaxElement, SyntaxNode};
+use smelt_parser::{CastExpr, SyntaxKind};
+use thiserror::Error;
+
+/// Column a rewritten QUALIFY condition is selected as, then filtered on.
+const QUALIFY_COLUMN: &str = "__smelt_qualify";
+
+/// Errors from rewriting SQL for a backend
+#[derive(Debug, Error)]
+pub enum RewriteError {
+    #[error("QUALIFY isn't supported by this backend and can't be rewritten: {0}")]
+    Qualify(String),
+}
+
+/// Rewrite a query's `QUALIFY` clauses and `::` casts into forms the backend
+/// supports.
+///
+/// SQL the parser doesn't fully understand is returned unchanged, leaving the
+/// backend to report any syntax it lacks.
+pub fn rewrite_query(
+    sql: &str,
+    capabilities: &BackendCapabilities,
+) -> Result<String, RewriteError> {
+    if capabilities.supports_qualify && capabilities.supports_double_colon_cast {
+        return Ok(sql.to_string());
+    }
+
+    let parse = smelt_parser::parse(sql);
+    let root = parse.syntax();
+    if !parse.errors.is_empty() || root.text() != sql {
+        return Ok(sql.to_string());
+    }
+
+    Rewriter { capabilities }.node(&root)
+}
+
+/// Statements to run for `sql`: a `CREATE OR REPLACE TABLE/VIEW` the backend
+/// lacks becomes `DROP ... IF EXISTS` then `CREATE`; anything else is run as is.
+pub fn replace_statements(sql: &str, capabilities: &BackendCapabilities) -> Vec<String> {
+    let Some(rest) = strip_keyword(sql, "CREATE")
+        .and_then(|rest| strip_keyword(rest, "OR"))
+        .and_then(|rest| strip_keyword(rest, "REPLACE"))
+    else {
+        return vec![sql.to_string()];
+    };
+
+    let (kind, supported) = if strip_keyword(rest, "TABLE").is_some() {
+        ("TABLE", capabilities.supports_create_or_replace_table)
+    } else if strip_keyword(rest, "VIEW").is_some() {
+        ("VIEW", capabilities.supports_create_or_replace_view)
+    } else {
+        return vec![sql.to_string()];
+    };
+    if supported {
+        return vec![sql.to_string()];
+    }
+
+    let create = rest.trim_start();
+    let after_kind = create[kind.len()..].trim_start();
+    let name_end = after_kind
+        .find(|c: char| c.is_whitespace() || c == '(' || c == ';')
+        .unwrap_or(after_kind.len());
+
+    vec![
+        format!("DROP {} IF EXISTS {}", kind, &after_kind[..name_end]),
+        format!("CREATE {}", create),
+    ]
+}
+
+/// The rest of `sql` after a leading `keyword` (case-insensitive), if it
+/// starts with one.
+fn strip_keyword<'a>(sql: &'a str, keyword: &str) -> Option<&'a str> {
+    let sql = sql.trim_start();
+    let rest = sql.get(keyword.len()..)?;
+    (sql[..keyword.len()].eq_ignore_ascii_case(keyword) && rest.starts_with(char::is_whitespace))
+        .then_some(rest)
+}
+
+/// The name of the column a select item outputs: its alias, or the column it
+/// selects. `None` for `*` and unaliased expressions.
+fn output_name(item: &SyntaxNode) -> Option<String> {
+    let mut alias = None;
+    let mut expression = None;
+    for element in item.children_with_tokens() {
+        match element {
+            NodeOrToken::Token(token) if token.kind() == SyntaxKind::IDENT => {
+                alias = Some(token.text().to_string())
+            }
+            NodeOrToken::Token(token) if token.kind() == SyntaxKind::STAR => return None,
+            NodeOrToken::Node(node) if node.kind() == SyntaxKind::EXPRESSION => {
+                expression = Some(node)
+            }
+            _ => {}
+        }
+    }
+    if alias.is_some() {
+        return alias;
+    }
+
+    // A column reference: `column` or `table.column`, with nothing else
+    let expression = expression?;
+    let mut name = None;
+    for element in expression.children_with_tokens() {
+        match element.kind() {
+            SyntaxKind::IDENT => name = element.into_token().map(|t| t.text().to_string()),
+            SyntaxKind::DOT => {}
+            kind if kind.is_trivia() => {}
+            _ => return None,
+        }
+    }
+    name
+}
+
+struct Rewriter<'a> {
+    capabilities: &'a BackendCapabilities,
+}
+
+impl Rewriter<'_> {
+    /// The SQL for a node, with its casts and QUALIFY clauses rewritten.
+    fn node(&self, node: &SyntaxNode) -> Result<String, RewriteError> {
+        match node.kind() {
+            SyntaxKind::CAST_EXPR
+                if !self.capabilities.supports_double_colon_cast
+                    && CastExpr::cast(node.clone()).is_some_and(|c| c.is_double_colon_cast()) =>
+            {
+                self.cast(node)
+            }
+            SyntaxKind::SELECT_STMT
+                if !self.capabilities.supports_qualify
+                    && node
+                        .children()
+                        .any(|n| n.kind() == SyntaxKind::QUALIFY_CLAUSE) =>
+            {
+                self.qualify(node)
+            }
+            _ => self.elements(node.children_with_tokens()),
+        }
+    }
+
+    fn elements(
+        &self,
+        elements: impl IntoIterator<Item = SyntaxElement>,
+    ) -> Result<String, RewriteError> {
+        let mut sql = String::new();
+        for element in elements {
+            match element {
+                NodeOrToken::Node(node) => sql.push_str(&self.node(&node)?),
+                NodeOrToken::Token(token) => sql.push_str(token.text()),
+            }
+        }
+        Ok(sql)
+    }
+
+    /// `expr::type` as `CAST(expr AS type)`.
+    fn cast(&self, node: &SyntaxNode) -> Result<String, RewriteError> {
+        let children: Vec<SyntaxElement> = node.children_with_tokens().collect();
+        let colon = children
+            .iter()
+            .position(|e| e.kind() == SyntaxKind::DOUBLE_COLON)
+            .unwrap_or(children.len());
+
+        let operand = self.elements(children[..colon].iter().cloned())?;
+        let type_spec = self.elements(children.iter().skip(colon + 1).cloned())?;
+        let trailing = &type_spec[type_spec.trim_end().len()..];
+
+        Ok(format!(
+            "CAST({} AS {}){}",
+            operand.trim(),
+            type_spec.trim(),
+            trailing
+        ))
+    }
+
+    /// `SELECT cols ... QUALIFY cond ORDER BY ... LIMIT n` as
+    /// `SELECT cols FROM (SELECT cols, cond AS __smelt_qualify ...) WHERE
+    /// __smelt_qualify ORDER BY ... LIMIT n`.
+    ///
+    /// The outer SELECT lists the inner one's columns by name, so every
+    /// selected column needs one: `*` and unaliased expressions can't be
+    /// rewritten.
+    fn qualify(&self, node: &SyntaxNode) -> Result<String, RewriteError> {
+        if node
+            .children_with_tokens()
+            .any(|e| e.kind() == SyntaxKind::UNION_KW)
+        {
+            return Err(RewriteError::Qualify(
+                "it can't be combined with UNION".to_string(),
+            ));
+        }
+
+        let items = node
+            .children()
+            .filter(|n| n.kind() == SyntaxKind::SELECT_LIST)
+            .flat_map(|list| list.children())
+            .filter(|n| n.kind() == SyntaxKind::SELECT_ITEM);
+        let mut columns = Vec::new();
+        for item in items {
+            columns.push(output_name(&item).ok_or_else(|| {
+                RewriteError::Qualify(format!(
+                    "list the columns instead of `{}`, and name expressions with AS",
+                    item.text().to_string().trim()
+                ))
+            })?);
+        }
+
+        let children: Vec<SyntaxElement> = node.children_with_tokens().collect();
+        let condition = match node
+            .children()
+            .find(|n| n.kind() == SyntaxKind::QUALIFY_CLAUSE)
+        {
+            Some(clause) => self.elements(
+                clause
+                    .children_with_tokens()
+                    .filter(|e| e.kind() != SyntaxKind::QUALIFY_KW),
+            )?,
+            None => String::new(),
+        };
+        let select = children
+            .iter()
+            .position(|e| e.kind() == SyntaxKind::SELECT_KW)
+            .unwrap_or(0);
+        let with = self.elements(children[..select].iter().cloned())?;
+
+        let mut inner = String::new();
+        let mut outer_clauses = Vec::new();
+        for element in &children[select..] {
+            match element {
+                NodeOrToken::Node(child) => match child.kind() {
+                    SyntaxKind::SELECT_LIST => {
+                        let list = self.node(child)?;
+                        inner.push_str(&format!(
+                            "{}, {} AS {}",
+                            list.trim_end(),
+                            condition.trim(),
+                            QUALIFY_COLUMN
+                        ));
+                        inner.push_str(&list[list.trim_end().len()..]);
+                    }
+                    SyntaxKind::QUALIFY_CLAUSE => {}
+                    SyntaxKind::ORDER_BY_CLAUSE | SyntaxKind::LIMIT_CLAUSE => {
+                        outer_clauses.push(self.node(child)?.trim().to_string());
+                    }
+                    _ => inner.push_str(&self.node(child)?),
+                },
+                NodeOrToken::Token(token) => inner.push_str(token.text()),
+            }
+        }
+        let mut sql = format!(
+            "{}SELECT {} FROM (\n{}\n) AS smelt_qualified WHERE {}",
+            with,
+            columns.join(", "),
+            inner.trim(),
+            QUALIFY_COLUMN
+        );
+        for clause in outer_clauses {
+            sql.push(' ');
+            sql.push_str(&clause);
+        }
+        let text = node.text().to_string();
+        sql.push_str(&text[text.trim_end().len()..]);
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_backend::SqlDialect;
+
+    const DIALECTS: [SqlDialect; 5] = [
+        SqlDialect::DuckDB,
+        SqlDialect::SparkSQL,
+        SqlDialect::PostgreSQL,
+        SqlDialect::SQLite,
+        SqlDialect::Snowflake,
+    ];
+
+    #[test]
+    fn test_rewrite_casts() {
+        let sql = "SELECT id::VARCHAR AS id, '2024-01-01'::DATE AS day, (a + b)::BIGINT::VARCHAR AS total FROM t";
+        for dialect in DIALECTS {
+            let rewritten = rewrite_query(sql, &dialect.capabilities()).unwrap();
+            if dialect.capabilities().supports_double_colon_cast {
+                assert_eq!(rewritten, sql, "{}", dialect.name());
+            } else {
+                assert_eq!(
+                    rewritten,
+                    "SELECT CAST(id AS VARCHAR) AS id, CAST('2024-01-01' AS DATE) AS day, \
+                     CAST(CAST((a + b) AS BIGINT) AS VARCHAR) AS total FROM t",
+                    "{}",
+                    dialect.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rewrite_qualify() {
+        let sql = "WITH ranked AS (SELECT * FROM events)\n\
+                   SELECT user_id, ts AS latest FROM ranked\n\
+                   WHERE ts IS NOT NULL\n\
+                   QUALIFY ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY ts DESC) = 1\n\
+                   ORDER BY user_id\n";
+        for dialect in DIALECTS {
+            let rewritten = rewrite_query(sql, &dialect.capabilities()).unwrap();
+            if dialect.capabilities().supports_qualify {
+                assert_eq!(rewritten, sql, "{}", dialect.name());
+            } else {
+                assert_eq!(
+                    rewritten,
+                    "WITH ranked AS (SELECT * FROM events)\n\
+                     SELECT user_id, latest FROM (\n\
+                     SELECT user_id, ts AS latest, ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY ts DESC) = 1 AS __smelt_qualify FROM ranked\n\
+                     WHERE ts IS NOT NULL\n\
+                     ) AS smelt_qualified WHERE __smelt_qualify ORDER BY user_id\n",
+                    "{}",
+                    dialect.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rewrite_qualify_needs_named_columns() {
+        let sqlite = SqlDialect::SQLite.capabilities();
+        let err = rewrite_query(
+            "SELECT * FROM t QUALIFY ROW_NUMBER() OVER (ORDER BY ts) = 1",
+            &sqlite,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("list the columns instead of `*`"));
+
+        // Unparseable SQL is left for the backend to reject
+        assert_eq!(
+            rewrite_query("SELECT FROM", &sqlite).unwrap(),
+            "SELECT FROM"
+        );
+    }
+
+    #[test]
+    fn test_replace_statements() {
+        let sql = "CREATE OR REPLACE TABLE main.archive AS SELECT * FROM main.orders";
+        for dialect in DIALECTS {
+            let statements = replace_statements(sql, &dialect.capabilities());
+            if dialect.capabilities().supports_create_or_replace_table {
+                assert_eq!(statements, vec![sql], "{}", dialect.name());
+            } else {
+                assert_eq!(
+                    statements,
+                    vec![
+                        "DROP TABLE IF EXISTS main.archive",
+                        "CREATE TABLE main.archive AS SELECT * FROM main.orders"
+                    ],
+                    "{}",
+                    dialect.name()
+                );
+            }
+        }
+
+        let sqlite = SqlDialect::SQLite.capabilities();
+        assert_eq!(
+            replace_statements("create or replace view v(a) as select 1", &sqlite),
+            vec!["DROP VIEW IF EXISTS v", "CREATE view v(a) as select 1"]
+        );
+        assert_eq!(
+            replace_statements("DELETE FROM main.orders", &sqlite),
+            vec!["DELETE FROM main.orders"]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Seed loading: CSV fixtures from `seeds/` loaded into the target schema.
+//!
+//! Column types are inferred from the CSV contents using Arrow's CSV reader.
+//! DuckDB targets load the file with a native `COPY ... FROM`; other backends
+//! are handed the parsed Arrow batches through `Backend::load_record_batches`.
+
+use crate::errors::CliError;
+use anyhow::{anyhow, Context, Result};
+use arrow::array::RecordBatch;
+use arrow::csv::reader::Format;
+use arrow::csv::ReaderBuilder;
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use smelt_backend::{Backend, RelationName, SqlDialect};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// Number of rows per record batch read from a seed file.
+const READ_BATCH_SIZE: usize = 8192;
+
+/// Number of records sampled for column type inference.
+const INFER_MAX_RECORDS: usize = 1000;
+
+/// A CSV file discovered in one of the seed paths.
+#[derive(Debug, Clone)]
+pub struct SeedFile {
+    /// Table name (the file stem)
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Outcome of loading a single seed.
+#[derive(Debug)]
+pub struct SeedResult {
+    pub name: String,
+    pub row_count: usize,
+    pub duration: Duration,
+    /// Whether the table was (re)created rather than truncated and reloaded
+    pub recreated: bool,
+}
+
+/// Find all `.csv` files under the configured seed paths.
+///
+/// Missing seed directories are skipped; an empty result is not an error.
+pub fn discover_seeds(project_root: &Path, seed_paths: &[String]) -> Result<Vec<SeedFile>> {
+    let mut seeds = Vec::new();
+
+    for seed_path in seed_paths {
+        let search_path = project_root.join(seed_path);
+
+        if !search_path.exists() {
+            continue;
+        }
+
+        for entry in WalkDir::new(&search_path)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+
+            if path.extension().and_then(|s| s.to_str()) == Some("csv") {
+                let name = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .map(|s| s.to_string())
+                    .ok_or_else(|| anyhow!("Cannot determine seed name from {:?}", path))?;
+
+                seeds.push(SeedFile {
+                    name,
+                    path: path.to_path_buf(),
+                });
+            }
+        }
+    }
+
+    Ok(seeds)
+}
+
+/// Infer the column names and types of a CSV file with a header row.
+pub fn infer_seed_schema(path: &Path) -> Result<Schema> {
+    let file = File::open(path).with_context(|| format!("Failed to open seed file: {:?}", path))?;
+
+    let (schema, _) = Format::default()
+        .with_header(true)
+        .infer_schema(file, Some(INFER_MAX_RECORDS))
+        .with_context(|| format!("Failed to infer column types for {:?}", path))?;
+
+    Ok(schema)
+}
+
+/// Map an inferred Arrow type to a column type for the given dialect.
+///
+/// Anything that isn't clearly numeric, boolean, or temporal is loaded as text.
+pub fn sql_type_for(data_type: &DataType, dialect: SqlDialect) -> &'static str {
+    match data_type {
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => "BIGINT",
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => "DOUBLE",
+        DataType::Boolean => "BOOLEAN",
+        DataType::Date32 | DataType::Date64 => "DATE",
+        DataType::Timestamp(_, _) => "TIMESTAMP",
+        _ => match dialect {
+            SqlDialect::SparkSQL => "STRING",
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL | SqlDialect::Snowflake => "VARCHAR",
+            SqlDialect::SQLite => "TEXT",
+        },
+    }
+}
+
+/// Load a seed into `schema.<seed name>`.
+///
+/// Without `full_refresh` an existing table is truncated and reloaded, keeping
+/// its column types. With `full_refresh` (or when the table doesn't exist yet)
+/// the table is dropped and recreated from the inferred CSV schema.
+pub async fn load_seed(
+    backend: &dyn Backend,
+    seed: &SeedFile,
+    schema: &str,
+    full_refresh: bool,
+) -> Result<SeedResult> {
+    load_seed_inner(backend, seed, schema, full_refresh)
+        .await
+        .map_err(|e| {
+            CliError::SeedError {
+                seed: seed.name.clone(),
+                source: e,
+            }
+            .into()
+        })
+}
+
+async fn load_seed_inner(
+    backend: &dyn Backend,
+    seed: &SeedFile,
+    schema: &str,
+    full_refresh: bool,
+) -> Result<SeedResult> {
+    let start = Instant::now();
+    let dialect = backend.dialect();
+    let inferred = Arc::new(infer_seed_schema(&seed.path)?);
+    let relation = RelationName::new(schema, &seed.name);
+    let table_name = dialect.quote_relation(&relation);
+
+    backend.ensure_schema(schema).await?;
+
+    let exists = backend.table_exists(&relation).await?;
+    let recreated = full_refresh || !exists;
+
+    if recreated {
+        backend.drop_table_if_exists(&relation).await?;
+        backend
+            .execute_sql(&create_table_sql(&table_name, &inferred, dialect))
+            .await?;
+    } else {
+        backend
+            .execute_sql(&format!("DELETE FROM {}", table_name))
+            .await?;
+    }
+
+    match dialect {
+        SqlDialect::DuckDB => {
+            let absolute = seed
+                .path
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve seed path: {:?}", seed.path))?;
+            let copy_sql = format!(
+                "COPY {} FROM '{}' (HEADER)",
+                table_name,
+                absolute.display().to_string().replace('\'', "''")
+            );
+            backend.execute_sql(&copy_sql).await?;
+        }
+        SqlDialect::SparkSQL
+        | SqlDialect::PostgreSQL
+        | SqlDialect::SQLite
+        | SqlDialect::Snowflake => {
+            let batches = read_seed_batches(&seed.path, inferred.clone())?;
+            backend.load_record_batches(&relation, &batches).await?;
+        }
+    }
+
+    let row_count = backend.get_row_count(&relation).await?;
+
+    Ok(SeedResult {
+        name: seed.name.clone(),
+        row_count,
+        duration: start.elapsed(),
+        recreated,
+    })
+}
+
+/// `CREATE TABLE` for columns of the given Arrow schema.
+pub(crate) fn create_table_sql(
+    table_name: &str,
+    schema: &SchemaRef,
+    dialect: SqlDialect,
+) -> String {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| {
+            format!(
+                "{} {}",
+                dialect.quote_ident(f.name()),
+                sql_type_for(f.data_type(), dialect)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!("CREATE TABLE {} ({})", table_name, columns)
+}
+
+fn read_seed_batches(path: &Path, schema: SchemaRef) -> Result<Vec<RecordBatch>> {
+    let file = File::open(path).with_context(|| format!("Failed to open seed file: {:?}", path))?;
+
+    let reader = ReaderBuilder::new(schema)
+        .with_header(true)
+        .with_batch_size(READ_BATCH_SIZE)
+        .build(file)
+        .with_context(|| format!("Failed to read seed file: {:?}", path))?;
+
+    reader
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to parse seed file: {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_backend_duckdb::DuckDbBackend;
+    use tempfile::TempDir;
+
+    fn write_seed(dir: &Path, name: &str, content: &str) -> SeedFile {
+        let seeds_dir = dir.join("seeds");
+        std::fs::create_dir_all(&seeds_dir).unwrap();
+        let path = seeds_dir.join(format!("{}.csv", name));
+        std::fs::write(&path, content).unwrap();
+        SeedFile {
+            name: name.to_string(),
+            path,
+        }
+    }
+
+    #[test]
+    fn test_discover_seeds() {
+        let temp_dir = TempDir::new().unwrap();
+        write_seed(temp_dir.path(), "countries", "code,name\nNZ,New Zealand\n");
+        write_seed(temp_dir.path(), "plans", "id,name\n1,free\n");
+        std::fs::write(temp_dir.path().join("seeds/README.md"), "not a seed").unwrap();
+
+        let seeds = discover_seeds(temp_dir.path(), &["seeds".to_string()]).unwrap();
+        let names: Vec<_> = seeds.iter().map(|s| s.name.as_str()).collect();
+
+        assert_eq!(names, vec!["countries", "plans"]);
+    }
+
+    #[test]
+    fn test_discover_seeds_missing_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let seeds = discover_seeds(temp_dir.path(), &["seeds".to_string()]).unwrap();
+        assert!(seeds.is_empty());
+    }
+
+    #[test]
+    fn test_type_inference() {
+        let temp_dir = TempDir::new().unwrap();
+        let seed = write_seed(
+            temp_dir.path(),
+            "typed",
+            "id,price,active,signup_date,name\n1,9.99,true,2024-01-15,alice\n2,5.00,false,2024-02-01,bob\n",
+        );
+
+        let schema = infer_seed_schema(&seed.path).unwrap();
+        let types: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|f| sql_type_for(f.data_type(), SqlDialect::DuckDB))
+            .collect();
+
+        assert_eq!(
+            types,
+            vec!["BIGINT", "DOUBLE", "BOOLEAN", "DATE", "VARCHAR"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_seed_and_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+
+        let seed = write_seed(temp_dir.path(), "plans", "id,name\n1,free\n2,pro\n");
+
+        let result = load_seed(&backend, &seed, "main", false).await.unwrap();
+        assert_eq!(result.row_count, 2);
+        assert!(result.recreated);
+
+        // Reload without full refresh truncates rather than duplicating rows
+        std::fs::write(&seed.path, "id,name\n1,free\n2,pro\n3,team\n").unwrap();
+        let result = load_seed(&backend, &seed, "main", false).await.unwrap();
+        assert_eq!(result.row_count, 3);
+        assert!(!result.recreated);
+    }
+
+    #[tokio::test]
+    async fn test_full_refresh_picks_up_new_columns() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+
+        let seed = write_seed(temp_dir.path(), "plans", "id,name\n1,free\n");
+        load_seed(&backend, &seed, "main", false).await.unwrap();
+
+        std::fs::write(&seed.path, "id,name,price\n1,free,0\n").unwrap();
+        let result = load_seed(&backend, &seed, "main", true).await.unwrap();
+        assert!(result.recreated);
+
+        let batches = backend
+            .execute_sql("SELECT price FROM main.plans")
+            .await
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 1);
+    }
+}
@@ -0,0 +1,652 @@
+//! Model selection syntax for `--select` / `--exclude`.
+//!
+//! Supports a dbt-style subset:
+//! - `my_model` - a single model by name
+//! - `+my_model` - the model and everything upstream of it
+//! - `my_model+` - the model and everything downstream of it
+//! - `tag:daily` - models tagged `daily`
+//! - `path:models/staging/*` (or any pattern containing `/` or `*`) - models
+//!   whose path relative to the project root matches the glob
+//! - `state:modified` / `state:new` - models whose SQL or smelt.yml settings
+//!   changed, or that were added, since the manifest passed with `--state`
+//! - `exposure:weekly_kpis` - the models an exposure reads (`+exposure:...`
+//!   for everything it needs)
+//! - `package:shared` - models from the `shared` package
+//!
+//! Space-separated selectors are unioned; comma-separated selectors within a
+//! single argument are intersected (`tag:daily,+revenue`).
+
+use crate::artifacts::{checksum, config_checksum, Manifest};
+use crate::config::Config;
+use crate::graph::DependencyGraph;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// What a selector matches before graph operators are applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorMethod {
+    /// Model name
+    Name(String),
+    /// Tag from model metadata, smelt.yml model config, or group
+    Tag(String),
+    /// Glob over the model path relative to the project root
+    Path(String),
+    /// Comparison against a previous manifest
+    State(StateSelector),
+    /// Models an exposure in exposures.yml depends on
+    Exposure(String),
+    /// Models from a package declared in smelt.yml
+    Package(String),
+}
+
+/// Which models a `state:` selector matches relative to the previous manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateSelector {
+    /// New models and models whose source changed
+    Modified,
+    /// Models that did not exist in the previous manifest
+    New,
+}
+
+/// A single parsed selector, e.g. `+tag:daily+`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub method: SelectorMethod,
+    /// Include everything upstream (`+` prefix)
+    pub upstream: bool,
+    /// Include everything downstream (`+` suffix)
+    pub downstream: bool,
+}
+
+impl Selector {
+    /// Parse a selector string.
+    pub fn parse(input: &str) -> Result<Self> {
+        let trimmed = input.trim();
+        let upstream = trimmed.starts_with('+');
+        let downstream = trimmed.len() > 1 && trimmed.ends_with('+');
+
+        let start = if upstream { 1 } else { 0 };
+        let end = if downstream {
+            trimmed.len() - 1
+        } else {
+            trimmed.len()
+        };
+        let body = trimmed.get(start..end).unwrap_or_default();
+
+        if body.is_empty() {
+            return Err(anyhow!("Invalid selector: '{}'", input));
+        }
+
+        let method = if let Some(tag) = body.strip_prefix("tag:") {
+            SelectorMethod::Tag(tag.to_string())
+        } else if let Some(state) = body.strip_prefix("state:") {
+            match state {
+                "modified" => SelectorMethod::State(StateSelector::Modified),
+                "new" => SelectorMethod::State(StateSelector::New),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid state selector: '{}'. Expected 'state:modified' or 'state:new'",
+                        input
+                    ))
+                }
+            }
+        } else if let Some(exposure) = body.strip_prefix("exposure:") {
+            SelectorMethod::Exposure(exposure.to_string())
+        } else if let Some(package) = body.strip_prefix("package:") {
+            SelectorMethod::Package(package.to_string())
+        } else if let Some(path) = body.strip_prefix("path:") {
+            SelectorMethod::Path(path.to_string())
+        } else if body.contains('/') || body.contains('*') {
+            SelectorMethod::Path(body.to_string())
+        } else {
+            SelectorMethod::Name(body.to_string())
+        };
+
+        Ok(Self {
+            method,
+            upstream,
+            downstream,
+        })
+    }
+
+    /// Resolve this selector to a set of model names.
+    ///
+    /// `state` is the previous manifest, required by `state:` selectors.
+    pub fn resolve(
+        &self,
+        graph: &DependencyGraph,
+        config: &Config,
+        project_root: &Path,
+        state: Option<&Manifest>,
+    ) -> Result<HashSet<String>> {
+        let mut matched: HashSet<String> = match &self.method {
+            SelectorMethod::Name(name) => {
+                graph.get_model(name)?;
+                HashSet::from([name.clone()])
+            }
+            SelectorMethod::Tag(tag) => graph
+                .models()
+                .values()
+                .filter(|model| config.get_model_tags(model).contains(tag))
+                .map(|model| model.name.clone())
+                .collect(),
+            SelectorMethod::Path(pattern) => graph
+                .models()
+                .values()
+                .filter(|model| {
+                    let relative = model.path.strip_prefix(project_root).unwrap_or(&model.path);
+                    glob_match(pattern, &relative.to_string_lossy())
+                })
+                .map(|model| model.name.clone())
+                .collect(),
+            SelectorMethod::State(kind) => {
+                let state = state.ok_or_else(|| {
+                    anyhow!("The 'state:' selector requires --state <path to manifest.json>")
+                })?;
+
+                graph
+                    .models()
+                    .values()
+                    .filter(|model| match (state.nodes.get(&model.name), kind) {
+                        (None, _) => true,
+                        (Some(previous), StateSelector::Modified) => {
+                            previous.checksum != checksum(&model.content)
+                                || (!previous.config_checksum.is_empty()
+                                    && previous.config_checksum
+                                        != config_checksum(config, model, &state.metadata.target))
+                        }
+                        (Some(_), StateSelector::New) => false,
+                    })
+                    .map(|model| model.name.clone())
+                    .collect()
+            }
+            SelectorMethod::Exposure(name) => {
+                let exposure = graph
+                    .exposures()
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Exposure not found: {}", name))?;
+                exposure
+                    .depends_on
+                    .iter()
+                    .filter(|dep| graph.models().contains_key(*dep))
+                    .cloned()
+                    .collect()
+            }
+            SelectorMethod::Package(package) => {
+                if !config.packages.contains_key(package) {
+                    return Err(anyhow!("Package not found: {}", package));
+                }
+                graph
+                    .models()
+                    .keys()
+                    .filter(|name| {
+                        config
+                            .package_of(name)
+                            .is_some_and(|(p, _)| p == package.as_str())
+                    })
+                    .cloned()
+                    .collect()
+            }
+        };
+
+        let seeds: Vec<String> = matched.iter().cloned().collect();
+        for name in &seeds {
+            if self.upstream {
+                matched.extend(graph.upstream(name));
+            }
+            if self.downstream {
+                matched.extend(graph.downstream(name));
+            }
+        }
+
+        Ok(matched)
+    }
+}
+
+/// Resolve `--select` and `--exclude` arguments to the set of models to run.
+///
+/// An empty `select` list selects every model. `state` is the previous
+/// manifest used to resolve `state:` selectors.
+pub fn select_models(
+    graph: &DependencyGraph,
+    config: &Config,
+    project_root: &Path,
+    select: &[String],
+    exclude: &[String],
+    state: Option<&Manifest>,
+) -> Result<HashSet<String>> {
+    let mut selected: HashSet<String> = if select.is_empty() {
+        graph.models().keys().cloned().collect()
+    } else {
+        let mut union = HashSet::new();
+        for arg in select {
+            union.extend(resolve_intersection(
+                graph,
+                config,
+                project_root,
+                arg,
+                state,
+            )?);
+        }
+        union
+    };
+
+    for arg in exclude {
+        for name in resolve_intersection(graph, config, project_root, arg, state)? {
+            selected.remove(&name);
+        }
+    }
+
+    Ok(selected)
+}
+
+/// Resolve a comma-separated selector argument as an intersection.
+fn resolve_intersection(
+    graph: &DependencyGraph,
+    config: &Config,
+    project_root: &Path,
+    arg: &str,
+    state: Option<&Manifest>,
+) -> Result<HashSet<String>> {
+    let mut result: Option<HashSet<String>> = None;
+
+    for part in arg.split(',').filter(|p| !p.trim().is_empty()) {
+        let matched = Selector::parse(part)?.resolve(graph, config, project_root, state)?;
+        result = Some(match result {
+            Some(acc) => acc.intersection(&matched).cloned().collect(),
+            None => matched,
+        });
+    }
+
+    result.ok_or_else(|| anyhow!("Empty selector"))
+}
+
+/// Match a path against a glob pattern.
+///
+/// `*` matches within a path segment, `**` matches across segments, and `?`
+/// matches a single character. A pattern without wildcards also matches any
+/// path inside it (`models/staging` selects `models/staging/orders.sql`).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    if !pattern.contains(['*', '?']) {
+        return path == pattern || path.starts_with(&format!("{}/", pattern));
+    }
+
+    glob_match_bytes(pattern.as_bytes(), path.as_bytes())
+}
+
+fn glob_match_bytes(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = pattern[2..].strip_prefix(b"/").unwrap_or(&pattern[2..]);
+            (0..=path.len()).any(|i| glob_match_bytes(rest, &path[i..]))
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            for i in 0..=path.len() {
+                if glob_match_bytes(rest, &path[i..]) {
+                    return true;
+                }
+                if path.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => {
+            !path.is_empty() && path[0] != b'/' && glob_match_bytes(&pattern[1..], &path[1..])
+        }
+        Some(c) => path.first() == Some(c) && glob_match_bytes(&pattern[1..], &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{ModelFile, RefInfo};
+    use crate::metadata::ModelMetadata;
+    use rowan::TextRange;
+
+    fn make_model(name: &str, dir: &str, deps: Vec<&str>, tags: Vec<&str>) -> ModelFile {
+        let refs = deps
+            .into_iter()
+            .map(|dep| RefInfo {
+                model_name: dep.to_string(),
+                has_named_params: false,
+                range: TextRange::default(),
+            })
+            .collect();
+
+        let metadata = (!tags.is_empty()).then(|| {
+            Box::new(ModelMetadata {
+                tags: tags.into_iter().map(String::from).collect(),
+                ..Default::default()
+            })
+        });
+
+        ModelFile {
+            name: name.to_string(),
+            path: Path::new("/project")
+                .join(dir)
+                .join(format!("{}.sql", name)),
+            content: String::new(),
+            refs,
+            parse_errors: Vec::new(),
+            metadata,
+        }
+    }
+
+    /// raw_events -> stg_events -> daily_summary -> report -> (kpis exposure)
+    ///                          \-> user_stats --------------/
+    fn make_graph() -> DependencyGraph {
+        let models = vec![
+            make_model("raw_events", "models/staging", vec![], vec![]),
+            make_model("stg_events", "models/staging", vec!["raw_events"], vec![]),
+            make_model(
+                "daily_summary",
+                "models/marts",
+                vec!["stg_events"],
+                vec!["daily"],
+            ),
+            make_model(
+                "user_stats",
+                "models/marts",
+                vec!["stg_events"],
+                vec!["daily"],
+            ),
+            make_model("report", "models/reports", vec!["daily_summary"], vec![]),
+        ];
+        let exposures = serde_yaml::from_str(
+            "version: 1\nexposures:\n  kpis:\n    type: dashboard\n    depends_on: [report, user_stats]\n",
+        )
+        .unwrap();
+        DependencyGraph::build(models, None)
+            .unwrap()
+            .with_exposures(Some(&exposures))
+    }
+
+    fn make_config() -> Config {
+        serde_yaml::from_str(
+            r#"
+name: test
+version: 1
+targets: {}
+models:
+  report:
+    tags: [finance]
+groups:
+  models/staging:
+    tags: [staging]
+"#,
+        )
+        .unwrap()
+    }
+
+    fn select(select: &[&str], exclude: &[&str]) -> Vec<String> {
+        let select: Vec<String> = select.iter().map(|s| s.to_string()).collect();
+        let exclude: Vec<String> = exclude.iter().map(|s| s.to_string()).collect();
+        let mut result: Vec<String> = select_models(
+            &make_graph(),
+            &make_config(),
+            Path::new("/project"),
+            &select,
+            &exclude,
+            None,
+        )
+        .unwrap()
+        .into_iter()
+        .collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        let selector = Selector::parse("+tag:daily+").unwrap();
+        assert_eq!(selector.method, SelectorMethod::Tag("daily".to_string()));
+        assert!(selector.upstream);
+        assert!(selector.downstream);
+
+        let selector = Selector::parse("models/staging/*").unwrap();
+        assert_eq!(
+            selector.method,
+            SelectorMethod::Path("models/staging/*".to_string())
+        );
+
+        let selector = Selector::parse("package:shared+").unwrap();
+        assert_eq!(
+            selector.method,
+            SelectorMethod::Package("shared".to_string())
+        );
+        assert!(selector.downstream);
+
+        assert!(Selector::parse("+").is_err());
+    }
+
+    #[test]
+    fn test_select_by_name_and_graph_operators() {
+        assert_eq!(select(&["stg_events"], &[]), vec!["stg_events"]);
+        assert_eq!(
+            select(&["stg_events+"], &[]),
+            vec!["daily_summary", "report", "stg_events", "user_stats"]
+        );
+        assert_eq!(
+            select(&["+daily_summary"], &[]),
+            vec!["daily_summary", "raw_events", "stg_events"]
+        );
+    }
+
+    #[test]
+    fn test_select_by_tag_and_path() {
+        assert_eq!(
+            select(&["tag:daily"], &[]),
+            vec!["daily_summary", "user_stats"]
+        );
+        assert_eq!(
+            select(&["models/staging/*"], &[]),
+            vec!["raw_events", "stg_events"]
+        );
+        assert_eq!(select(&["path:models/reports"], &[]), vec!["report"]);
+    }
+
+    #[test]
+    fn test_select_by_config_and_group_tags() {
+        assert_eq!(select(&["tag:finance"], &[]), vec!["report"]);
+        assert_eq!(
+            select(&["tag:staging"], &[]),
+            vec!["raw_events", "stg_events"]
+        );
+    }
+
+    #[test]
+    fn test_union_intersection_and_exclude() {
+        assert_eq!(
+            select(&["report", "raw_events"], &[]),
+            vec!["raw_events", "report"]
+        );
+        assert_eq!(select(&["tag:daily,+report"], &[]), vec!["daily_summary"]);
+        assert_eq!(
+            select(&[], &["tag:daily"]),
+            vec!["raw_events", "report", "stg_events"]
+        );
+        assert_eq!(
+            select(&["stg_events+"], &["report"]),
+            vec!["daily_summary", "stg_events", "user_stats"]
+        );
+    }
+
+    #[test]
+    fn test_select_by_exposure() {
+        assert_eq!(
+            select(&["exposure:kpis"], &[]),
+            vec!["report", "user_stats"]
+        );
+        assert_eq!(
+            select(&["+exposure:kpis"], &[]),
+            vec![
+                "daily_summary",
+                "raw_events",
+                "report",
+                "stg_events",
+                "user_stats"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_model_is_an_error() {
+        let result = select_models(
+            &make_graph(),
+            &make_config(),
+            Path::new("/project"),
+            &["missing".to_string()],
+            &[],
+            None,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_state_selection() {
+        use crate::artifacts::{ArtifactMetadata, ManifestNode};
+        use crate::config::Materialization;
+        use std::collections::BTreeMap;
+
+        let graph = make_graph();
+        let config = make_config();
+
+        // Previous manifest: stg_events had different SQL, user_stats and report didn't exist
+        let mut nodes = BTreeMap::new();
+        for (name, content) in [
+            ("raw_events", ""),
+            ("stg_events", "SELECT 1"),
+            ("daily_summary", ""),
+        ] {
+            nodes.insert(
+                name.to_string(),
+                ManifestNode {
+                    name: name.to_string(),
+                    path: format!("models/{}.sql", name).into(),
+                    checksum: checksum(content),
+                    config_checksum: String::new(),
+                    compiled_sql: String::new(),
+                    materialization: Materialization::View,
+                    schema: "main".to_string(),
+                    database: None,
+                    depends_on: Vec::new(),
+                    tags: Vec::new(),
+                },
+            );
+        }
+        let state = Manifest {
+            metadata: ArtifactMetadata::new("test", "dev"),
+            nodes,
+        };
+
+        let resolve = |selector: &str| {
+            let mut result: Vec<String> = select_models(
+                &graph,
+                &config,
+                Path::new("/project"),
+                &[selector.to_string()],
+                &[],
+                Some(&state),
+            )
+            .unwrap()
+            .into_iter()
+            .collect();
+            result.sort();
+            result
+        };
+
+        assert_eq!(resolve("state:new"), vec!["report", "user_stats"]);
+        assert_eq!(
+            resolve("state:modified"),
+            vec!["report", "stg_events", "user_stats"]
+        );
+        assert_eq!(
+            resolve("state:modified+"),
+            vec!["daily_summary", "report", "stg_events", "user_stats"]
+        );
+
+        // state: selectors need a manifest to compare against
+        let result = select_models(
+            &graph,
+            &config,
+            Path::new("/project"),
+            &["state:modified".to_string()],
+            &[],
+            None,
+        );
+        assert!(result.is_err());
+        assert!(Selector::parse("state:stale").is_err());
+    }
+
+    #[test]
+    fn test_state_modified_by_config() {
+        use crate::artifacts::{ArtifactMetadata, ManifestNode};
+        use std::collections::BTreeMap;
+
+        let graph = make_graph();
+        let nodes: BTreeMap<String, ManifestNode> = graph
+            .models()
+            .values()
+            .map(|model| {
+                let node = ManifestNode {
+                    name: model.name.clone(),
+                    path: model.path.clone(),
+                    checksum: checksum(&model.content),
+                    config_checksum: config_checksum(&make_config(), model, "dev"),
+                    compiled_sql: String::new(),
+                    materialization: make_config().get_model_materialization(model),
+                    schema: "main".to_string(),
+                    database: None,
+                    depends_on: Vec::new(),
+                    tags: Vec::new(),
+                };
+                (model.name.clone(), node)
+            })
+            .collect();
+        let state = Manifest {
+            metadata: ArtifactMetadata::new("test", "dev"),
+            nodes,
+        };
+        let modified = |config: &Config| {
+            select_models(
+                &graph,
+                config,
+                Path::new("/project"),
+                &["state:modified".to_string()],
+                &[],
+                Some(&state),
+            )
+            .unwrap()
+        };
+
+        assert!(modified(&make_config()).is_empty());
+
+        // Only smelt.yml changes: a post-hook on stg_events
+        let mut config = make_config();
+        config.models.insert(
+            "stg_events".to_string(),
+            serde_yaml::from_str("hooks:\n  post: [\"ANALYZE {{ this }}\"]\n").unwrap(),
+        );
+        assert_eq!(modified(&config), HashSet::from(["stg_events".to_string()]));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("models/*", "models/orders.sql"));
+        assert!(!glob_match("models/*", "models/staging/orders.sql"));
+        assert!(glob_match("models/**/*.sql", "models/staging/orders.sql"));
+        assert!(glob_match("models/**/*.sql", "models/orders.sql"));
+        assert!(glob_match("models/stg_?.sql", "models/stg_a.sql"));
+        assert!(glob_match("models/staging", "models/staging/orders.sql"));
+        assert!(!glob_match(
+            "models/staging",
+            "models/staging_old/orders.sql"
+        ));
+    }
+}
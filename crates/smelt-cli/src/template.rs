@@ -0,0 +1,191 @@
+//! `{{ env_var(...) }}` and `{{ var(...) }}` interpolation.
+//!
+//! smelt.yml and model files are rendered before they are parsed, so the same
+//! project can point at different schemas or credentials per environment:
+//!
+//! ```text
+//! schema: "{{ env_var('SMELT_SCHEMA', 'dev') }}"
+//! SELECT * FROM smelt.ref('events') WHERE region = '{{ var('region') }}'
+//! ```
+//!
+//! Other `{{ ... }}` expressions (such as `{{ this }}` in hooks) are left as-is.
+
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Template variables, from smelt.yml `vars:` and `--vars`.
+pub type Vars = BTreeMap<String, serde_yaml::Value>;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TemplateError {
+    #[error("Environment variable '{0}' is not set and env_var() has no default")]
+    MissingEnvVar(String),
+
+    #[error("Variable '{0}' is not defined; set it in smelt.yml `vars:` or pass --vars")]
+    UndefinedVar(String),
+
+    #[error("Variable '{0}' must be a string, number, or boolean")]
+    NonScalarVar(String),
+
+    #[error("Invalid template expression '{{{{ {0} }}}}': expected quoted arguments, e.g. var('key', 'default')")]
+    InvalidExpression(String),
+}
+
+/// Parse `--vars`, a YAML (or JSON) mapping such as `{schema: analytics, days: 7}`.
+pub fn parse_vars(yaml: &str) -> anyhow::Result<Vars> {
+    serde_yaml::from_str(yaml).map_err(|e| {
+        anyhow::anyhow!(
+            "--vars must be a YAML mapping, e.g. '{{schema: dev}}': {}",
+            e
+        )
+    })
+}
+
+/// Replace `env_var()` and `var()` expressions in `text`.
+pub fn render(text: &str, vars: &Vars) -> Result<String, TemplateError> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let expr = rest[start + 2..start + len].trim();
+
+        output.push_str(&rest[..start]);
+        match render_expression(expr, vars)? {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Evaluate a single expression, or `None` if it isn't `env_var()`/`var()`.
+fn render_expression(expr: &str, vars: &Vars) -> Result<Option<String>, TemplateError> {
+    let (function, args) = match expr.split_once('(') {
+        Some((function, args)) if matches!(function.trim(), "env_var" | "var") => {
+            (function.trim(), args)
+        }
+        _ => return Ok(None),
+    };
+
+    let args = args
+        .strip_suffix(')')
+        .and_then(parse_args)
+        .filter(|args| matches!(args.len(), 1 | 2))
+        .ok_or_else(|| TemplateError::InvalidExpression(expr.to_string()))?;
+    let name = &args[0];
+    let default = args.get(1).cloned();
+
+    let value = if function == "env_var" {
+        std::env::var(name)
+            .ok()
+            .or(default)
+            .ok_or_else(|| TemplateError::MissingEnvVar(name.clone()))?
+    } else {
+        match vars.get(name) {
+            Some(value) => {
+                scalar_to_string(value).ok_or_else(|| TemplateError::NonScalarVar(name.clone()))?
+            }
+            None => default.ok_or_else(|| TemplateError::UndefinedVar(name.clone()))?,
+        }
+    };
+
+    Ok(Some(value))
+}
+
+/// Parse comma-separated single- or double-quoted string literals.
+fn parse_args(args: &str) -> Option<Vec<String>> {
+    let mut parsed = Vec::new();
+    let mut rest = args.trim();
+
+    while !rest.is_empty() {
+        let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+        let end = rest[1..].find(quote)? + 1;
+        parsed.push(rest[1..end].to_string());
+
+        rest = rest[end + 1..].trim_start();
+        if let Some(next) = rest.strip_prefix(',') {
+            rest = next.trim_start();
+        } else if !rest.is_empty() {
+            return None;
+        }
+    }
+
+    Some(parsed)
+}
+
+fn scalar_to_string(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_vars() {
+        let vars = parse_vars("{region: emea, days: 7, active: true}").unwrap();
+
+        let sql = "SELECT * FROM t WHERE region = '{{ var('region') }}' AND days <= {{var(\"days\")}} AND active = {{ var('active') }}";
+        assert_eq!(
+            render(sql, &vars).unwrap(),
+            "SELECT * FROM t WHERE region = 'emea' AND days <= 7 AND active = true"
+        );
+
+        assert_eq!(
+            render("{{ var('missing', 'fallback') }}", &vars).unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            render("{{ var('missing') }}", &vars),
+            Err(TemplateError::UndefinedVar("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_render_env_var() {
+        std::env::set_var("SMELT_TEMPLATE_TEST_SCHEMA", "analytics");
+        let vars = Vars::new();
+
+        assert_eq!(
+            render("schema: {{ env_var('SMELT_TEMPLATE_TEST_SCHEMA') }}", &vars).unwrap(),
+            "schema: analytics"
+        );
+        assert_eq!(
+            render("{{ env_var('SMELT_TEMPLATE_TEST_UNSET', 'dev') }}", &vars).unwrap(),
+            "dev"
+        );
+        assert_eq!(
+            render("{{ env_var('SMELT_TEMPLATE_TEST_UNSET') }}", &vars),
+            Err(TemplateError::MissingEnvVar(
+                "SMELT_TEMPLATE_TEST_UNSET".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_render_leaves_other_expressions() {
+        let vars = Vars::new();
+
+        let hook = "GRANT SELECT ON {{ this }} TO analyst";
+        assert_eq!(render(hook, &vars).unwrap(), hook);
+        assert_eq!(
+            render("unterminated {{ var('x')", &vars).unwrap(),
+            "unterminated {{ var('x')"
+        );
+        assert!(matches!(
+            render("{{ var(region) }}", &vars),
+            Err(TemplateError::InvalidExpression(_))
+        ));
+    }
+}
@@ -1,10 +1,20 @@
 //! Query transformation for incremental materialization
 //!
-//! This module provides AST-based query transformation to inject time filters
-//! for incremental materialization. It uses the smelt-parser to find the correct
-//! insertion points and modifies the SQL string accordingly.
+//! This module injects time filters for incremental materialization by
+//! editing the model's concrete syntax tree: filters go into every SELECT
+//! that reads a time-partitioned input directly, wherever it sits (a CTE,
+//! subquery, or UNION branch), so dimension lookups elsewhere in the query
+//! keep all their rows. Everything else, comments and formatting included,
+//! is left as written.
+//!
+//! smelt-parser has no editing API: [`rewrite`](crate::rewrite) rebuilds the
+//! text of nodes it replaces, while filters are only ever inserted, so they're
+//! spliced in at byte offsets found in the tree.
 
-use smelt_parser::{parse, File};
+use crate::compiler::split_frontmatter;
+use rowan::NodeOrToken;
+use smelt_parser::syntax_kind::{SyntaxElement, SyntaxNode};
+use smelt_parser::{parse, File, FunctionCall, RefCall, SourceCall, SyntaxKind, TextRange};
 use thiserror::Error;
 
 /// Time range for filtering (inclusive start, exclusive end)
@@ -25,107 +35,265 @@ pub enum TransformError {
 
     #[error("No FROM clause found - cannot inject time filter")]
     NoFromClause,
+}
 
-    #[error(
-        "Query contains subqueries which are not yet supported for incremental transformation"
-    )]
-    SubqueryNotSupported,
+/// A query with time filters injected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilteredQuery {
+    pub sql: String,
+    /// Where each filter condition sits in `sql`
+    pub filters: Vec<TextRange>,
 }
 
 /// Transform a SQL query to filter by event time range.
 ///
-/// This function injects a WHERE clause filter to restrict the query
-/// to only process data within the specified time range.
-///
-/// # Arguments
-/// * `sql` - The original SQL query
-/// * `event_time_column` - The column name to filter on
-/// * `range` - The time range (start inclusive, end exclusive)
-///
-/// # Returns
-/// The transformed SQL with the time filter injected, or an error if
-/// the transformation cannot be safely applied.
+/// Each SELECT reading a time-partitioned input directly gets
+/// `event_time_column` restricted to the range: ANDed onto its WHERE clause
+/// (parenthesized, so an `OR` keeps its meaning) or in a new one after its FROM
+/// clause. Inputs are time-partitioned if they're among `time_sources` (the
+/// `smelt.source()` tables declaring an event time column) or are the table
+/// `event_time_column` is qualified with. A query reading none is filtered in
+/// its outermost SELECT. Frontmatter is kept as is.
 ///
 /// # Example
 /// ```ignore
 /// let sql = "SELECT * FROM users WHERE active = true";
 /// let range = TimeRange { start: "2024-01-15".into(), end: "2024-01-18".into() };
-/// let result = inject_time_filter(sql, "created_at", &range)?;
-/// // Result: "SELECT * FROM users WHERE active = true AND (created_at >= '2024-01-15' AND created_at < '2024-01-18')"
+/// let result = inject_time_filter(sql, "created_at", &[], &range)?;
+/// // result.sql: "SELECT * FROM users WHERE (active = true) AND (created_at >= '2024-01-15' AND created_at < '2024-01-18')"
 /// ```
 pub fn inject_time_filter(
     sql: &str,
     event_time_column: &str,
+    time_sources: &[String],
     range: &TimeRange,
-) -> Result<String, TransformError> {
-    // Parse the SQL to get AST
-    let parse_result = parse(sql);
-    let file = File::cast(parse_result.syntax()).ok_or(TransformError::ParseFailed)?;
-    let stmt = file.select_stmt().ok_or(TransformError::NoSelectStmt)?;
-
-    // Build the filter expression
-    // Escape single quotes in the column name (defensive)
+) -> Result<FilteredQuery, TransformError> {
+    let (frontmatter, body) = split_frontmatter(sql);
+    let syntax = parse(body).syntax();
+    File::cast(syntax.clone()).ok_or(TransformError::ParseFailed)?;
+    let root = child(&syntax, SyntaxKind::SELECT_STMT).ok_or(TransformError::NoSelectStmt)?;
+
+    // Escape single quotes (defensive)
     let safe_column = event_time_column.replace('\'', "''");
     let safe_start = range.start.replace('\'', "''");
     let safe_end = range.end.replace('\'', "''");
-
     let filter = format!(
         "{} >= '{}' AND {} < '{}'",
         safe_column, safe_start, safe_column, safe_end
     );
 
-    // Determine where to inject the filter
-    if let Some(where_clause) = stmt.where_clause() {
-        // Append to existing WHERE clause
-        let where_end = usize::from(where_clause.text_range().end());
+    let qualifier = event_time_column.rsplit('.').nth(1);
+    let mut selects: Vec<SyntaxNode> = root
+        .descendants()
+        .filter(|node| {
+            node.kind() == SyntaxKind::SELECT_STMT
+                && reads_time_input(node, time_sources, qualifier)
+        })
+        .collect();
+    if selects.is_empty() {
+        selects.push(root);
+    }
+
+    let mut inserts = Vec::new();
+    for select in &selects {
+        inserts.extend(filter_inserts(select)?);
+    }
+    inserts.sort_by_key(|insert| insert.offset);
+
+    let mut transformed = frontmatter.to_string();
+    let mut filters = Vec::new();
+    let mut copied = 0;
+    for insert in inserts {
+        transformed.push_str(&body[copied..insert.offset]);
+        copied = insert.offset;
+        transformed.push_str(insert.before);
+        if insert.filter {
+            let start = transformed.len();
+            transformed.push_str(&filter);
+            filters.push(TextRange::new(
+                (start as u32).into(),
+                (transformed.len() as u32).into(),
+            ));
+        }
+        transformed.push_str(insert.after);
+    }
+    transformed.push_str(&body[copied..]);
+
+    Ok(FilteredQuery {
+        sql: transformed,
+        filters,
+    })
+}
+
+/// Text inserted at a byte offset of the query, around the filter if `filter`.
+struct Insert {
+    offset: usize,
+    before: &'static str,
+    filter: bool,
+    after: &'static str,
+}
+
+/// Where to insert a filter into one SELECT: around its WHERE condition, or
+/// as a new WHERE clause after its FROM clause.
+fn filter_inserts(select: &SyntaxNode) -> Result<Vec<Insert>, TransformError> {
+    if let Some(where_clause) = child(select, SyntaxKind::WHERE_CLAUSE) {
+        let condition: Vec<SyntaxElement> = where_clause
+            .children_with_tokens()
+            .skip_while(|e| e.kind() != SyntaxKind::WHERE_KW)
+            .skip(1)
+            .collect();
+        let start = condition
+            .iter()
+            .find_map(|e| code_range(e).map(|(start, _)| start));
+        let end = condition
+            .iter()
+            .rev()
+            .find_map(|e| code_range(e).map(|(_, end)| end));
+        if let (Some(start), Some(end)) = (start, end) {
+            return Ok(vec![
+                Insert {
+                    offset: start,
+                    before: "(",
+                    filter: false,
+                    after: "",
+                },
+                Insert {
+                    offset: end,
+                    before: ") AND (",
+                    filter: true,
+                    after: ")",
+                },
+            ]);
+        }
+    }
+
+    let from_clause = child(select, SyntaxKind::FROM_CLAUSE).ok_or(TransformError::NoFromClause)?;
+    let (_, end) = code_range(&from_clause.into()).ok_or(TransformError::NoFromClause)?;
+    Ok(vec![Insert {
+        offset: end,
+        before: " WHERE ",
+        filter: true,
+        after: "",
+    }])
+}
 
-        // Split the SQL at the end of the WHERE clause
-        let (before, after) = sql.split_at(where_end);
+/// Whether a SELECT's own FROM clause (joins included) reads one of
+/// `time_sources`, or the table (or alias) named `qualifier`.
+fn reads_time_input(select: &SyntaxNode, time_sources: &[String], qualifier: Option<&str>) -> bool {
+    let Some(from_clause) = child(select, SyntaxKind::FROM_CLAUSE) else {
+        return false;
+    };
+    let joined = from_clause
+        .children()
+        .filter(|n| n.kind() == SyntaxKind::JOIN_CLAUSE)
+        .flat_map(|join| join.children());
+    from_clause
+        .children()
+        .chain(joined)
+        .filter(|n| n.kind() == SyntaxKind::TABLE_REF)
+        .any(|table| {
+            let call = table.children().find_map(FunctionCall::cast);
+            let source = call
+                .clone()
+                .and_then(SourceCall::from_function_call)
+                .and_then(|source| source.qualified_name());
+            if source
+                .as_ref()
+                .is_some_and(|name| time_sources.contains(name))
+            {
+                return true;
+            }
+            // A ref or source is queried by its model or table name
+            let called = call
+                .and_then(RefCall::from_function_call)
+                .and_then(|r| r.model_name())
+                .or_else(|| Some(source?.rsplit('.').next()?.to_string()));
+            qualifier.is_some_and(|qualifier| {
+                called
+                    .into_iter()
+                    .chain(table_names(&table))
+                    .any(|name| name.eq_ignore_ascii_case(qualifier))
+            })
+        })
+}
 
-        Ok(format!("{} AND ({}){}", before, filter, after))
-    } else if let Some(from_clause) = stmt.from_clause() {
-        // Insert new WHERE clause after FROM
-        let from_end = usize::from(from_clause.text_range().end());
+/// The identifiers of a table reference outside any function call: its
+/// (qualified) name and alias.
+fn table_names(table: &SyntaxNode) -> Vec<String> {
+    table
+        .children_with_tokens()
+        .filter_map(|e| e.into_token())
+        .filter(|t| t.kind() == SyntaxKind::IDENT)
+        .map(|t| t.text().to_string())
+        .collect()
+}
 
-        // Split the SQL at the end of the FROM clause
-        let (before, after) = sql.split_at(from_end);
+fn child(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxNode> {
+    node.children().find(|n| n.kind() == kind)
+}
 
-        Ok(format!("{} WHERE {}{}", before, filter, after))
-    } else {
-        Err(TransformError::NoFromClause)
+/// Byte range from the first to the last non-trivia token of an element, so
+/// edits land inside any trailing comments and whitespace.
+fn code_range(element: &SyntaxElement) -> Option<(usize, usize)> {
+    let mut tokens = match element {
+        NodeOrToken::Node(node) => node
+            .descendants_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| !t.kind().is_trivia())
+            .collect::<Vec<_>>(),
+        NodeOrToken::Token(token) if !token.kind().is_trivia() => vec![token.clone()],
+        NodeOrToken::Token(_) => Vec::new(),
     }
+    .into_iter();
+    let first = tokens.next()?;
+    let last = tokens.last().unwrap_or_else(|| first.clone());
+    Some((
+        first.text_range().start().into(),
+        last.text_range().end().into(),
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn range() -> TimeRange {
+        TimeRange {
+            start: "2024-01-15".into(),
+            end: "2024-01-18".into(),
+        }
+    }
+
+    const FILTER: &str = "event_time >= '2024-01-15' AND event_time < '2024-01-18'";
+
     #[test]
     fn test_inject_filter_no_where_clause() {
         let sql = "SELECT * FROM smelt.ref('transactions')";
-        let range = TimeRange {
-            start: "2024-01-15".into(),
-            end: "2024-01-18".into(),
-        };
 
-        let result = inject_time_filter(sql, "event_time", &range).unwrap();
+        let result = inject_time_filter(sql, "event_time", &[], &range()).unwrap();
 
-        assert!(result.contains("WHERE event_time >= '2024-01-15' AND event_time < '2024-01-18'"));
-        assert!(result.starts_with("SELECT * FROM smelt.ref('transactions')"));
+        assert_eq!(
+            result.sql,
+            format!("SELECT * FROM smelt.ref('transactions') WHERE {}", FILTER)
+        );
+        assert_eq!(result.filters.len(), 1);
+        assert_eq!(&result.sql[result.filters[0]], FILTER);
     }
 
     #[test]
     fn test_inject_filter_with_existing_where() {
-        let sql = "SELECT * FROM smelt.ref('transactions') WHERE status = 'active'";
-        let range = TimeRange {
-            start: "2024-01-15".into(),
-            end: "2024-01-18".into(),
-        };
+        let sql = "SELECT * FROM smelt.ref('transactions') WHERE status = 'active' OR refunded";
 
-        let result = inject_time_filter(sql, "event_time", &range).unwrap();
+        let result = inject_time_filter(sql, "event_time", &[], &range()).unwrap();
 
-        assert!(result.contains("WHERE status = 'active'"));
-        assert!(result.contains("AND (event_time >= '2024-01-15' AND event_time < '2024-01-18')"));
+        // The existing condition is parenthesized so the OR still applies to it alone
+        assert_eq!(
+            result.sql,
+            format!(
+                "SELECT * FROM smelt.ref('transactions') WHERE (status = 'active' OR refunded) AND ({})",
+                FILTER
+            )
+        );
     }
 
     #[test]
@@ -136,56 +304,122 @@ SELECT
     user_id,
     SUM(amount) as total_revenue
 FROM smelt.ref('transactions')
-WHERE transaction_timestamp IS NOT NULL
+WHERE transaction_timestamp IS NOT NULL -- skip drafts
 GROUP BY 1, 2
 "#;
-        let range = TimeRange {
-            start: "2024-01-15".into(),
-            end: "2024-01-18".into(),
-        };
 
-        let result = inject_time_filter(sql, "transaction_timestamp", &range).unwrap();
+        let result = inject_time_filter(sql, "transaction_timestamp", &[], &range()).unwrap();
 
-        // Should have both the original WHERE and the new filter
+        // Inserted before the trailing comment, which would otherwise swallow it
         assert!(
-            result.contains("WHERE transaction_timestamp IS NOT NULL"),
-            "Missing original WHERE. Got: {}",
-            result
+            result.sql.contains(
+                "WHERE (transaction_timestamp IS NOT NULL) AND (transaction_timestamp >= '2024-01-15' \
+                 AND transaction_timestamp < '2024-01-18') -- skip drafts\nGROUP BY 1, 2\n"
+            ),
+            "{}",
+            result.sql
         );
-        assert!(result.contains(
-            "AND (transaction_timestamp >= '2024-01-15' AND transaction_timestamp < '2024-01-18')"
-        ));
-        // GROUP BY should still be there
-        assert!(result.contains("GROUP BY 1, 2"));
     }
 
     #[test]
     fn test_no_from_clause_error() {
-        let sql = "SELECT 1 + 1";
-        let range = TimeRange {
-            start: "2024-01-15".into(),
-            end: "2024-01-18".into(),
-        };
-
-        let result = inject_time_filter(sql, "event_time", &range);
+        let result = inject_time_filter("SELECT 1 + 1", "event_time", &[], &range());
         assert!(matches!(result, Err(TransformError::NoFromClause)));
     }
 
     #[test]
     fn test_with_join() {
         let sql = "SELECT * FROM smelt.ref('orders') INNER JOIN smelt.ref('users') ON orders.user_id = users.id";
-        let range = TimeRange {
-            start: "2024-01-15".into(),
-            end: "2024-01-18".into(),
+
+        let result = inject_time_filter(sql, "orders.created_at", &[], &range()).unwrap();
+
+        assert_eq!(
+            result.sql,
+            format!(
+                "{} WHERE orders.created_at >= '2024-01-15' AND orders.created_at < '2024-01-18'",
+                sql
+            )
+        );
+    }
+
+    #[test]
+    fn test_filters_where_inputs_are_read() {
+        let sql = "---\nname: daily\n---\n\
+                   WITH recent AS (\n    SELECT * FROM smelt.source('raw.events') -- all events\n)\n\
+                   SELECT * FROM recent\n\
+                   UNION ALL\n\
+                   SELECT * FROM (SELECT * FROM smelt.source('raw.backfill') WHERE ok) b";
+        let time_sources = ["raw.events".to_string(), "raw.backfill".to_string()];
+
+        let result = inject_time_filter(sql, "event_time", &time_sources, &range()).unwrap();
+
+        assert_eq!(
+            result.sql,
+            format!(
+                "---\nname: daily\n---\n\
+                 WITH recent AS (\n    SELECT * FROM smelt.source('raw.events') WHERE {} -- all events\n)\n\
+                 SELECT * FROM recent\n\
+                 UNION ALL\n\
+                 SELECT * FROM (SELECT * FROM smelt.source('raw.backfill') WHERE (ok) AND ({})) b",
+                FILTER, FILTER
+            )
+        );
+        assert_eq!(result.filters.len(), 2);
+        for filter in &result.filters {
+            assert_eq!(&result.sql[*filter], FILTER);
+        }
+
+        // Without time-partitioned inputs, the outermost SELECT is filtered
+        let result =
+            inject_time_filter("SELECT * FROM raw.events", "event_time", &[], &range()).unwrap();
+        assert_eq!(
+            result.sql,
+            format!("SELECT * FROM raw.events WHERE {}", FILTER)
+        );
+    }
+
+    #[test]
+    fn test_dimension_ctes_are_not_filtered() {
+        let sql = "WITH u AS (SELECT * FROM smelt.ref('users')), \
+                   e AS (SELECT * FROM smelt.source('raw.events')) \
+                   SELECT * FROM e JOIN u ON e.user_id = u.id";
+        let filtered = |column: &str, time_sources: &[String]| {
+            inject_time_filter(sql, column, time_sources, &range())
+                .unwrap()
+                .sql
         };
 
-        let result = inject_time_filter(sql, "orders.created_at", &range).unwrap();
+        // Only the CTE reading the source declaring event time is filtered
+        assert_eq!(
+            filtered("event_time", &["raw.events".to_string()]),
+            format!(
+                "WITH u AS (SELECT * FROM smelt.ref('users')), \
+                 e AS (SELECT * FROM smelt.source('raw.events') WHERE {}) \
+                 SELECT * FROM e JOIN u ON e.user_id = u.id",
+                FILTER
+            )
+        );
 
-        // Should have WHERE clause injected
-        assert!(result.contains(
-            "WHERE orders.created_at >= '2024-01-15' AND orders.created_at < '2024-01-18'"
-        ));
-        // JOINs should still be there
-        assert!(result.contains("INNER JOIN"));
+        // As is the one reading the table the column is qualified with
+        assert_eq!(
+            filtered("events.event_time", &[]),
+            "WITH u AS (SELECT * FROM smelt.ref('users')), \
+             e AS (SELECT * FROM smelt.source('raw.events') \
+             WHERE events.event_time >= '2024-01-15' AND events.event_time < '2024-01-18') \
+             SELECT * FROM e JOIN u ON e.user_id = u.id"
+        );
+        assert_eq!(
+            filtered("e.event_time", &[]),
+            format!(
+                "{} WHERE e.event_time >= '2024-01-15' AND e.event_time < '2024-01-18'",
+                sql
+            )
+        );
+
+        // Otherwise only the outer query is
+        assert_eq!(
+            filtered("event_time", &[]),
+            format!("{} WHERE {}", sql, FILTER)
+        );
     }
 }
@@ -0,0 +1,549 @@
+//! Project checks for `smelt validate`.
+//!
+//! Validation loads the project without connecting to a backend and reports
+//! everything it finds rather than stopping at the first problem. Errors are
+//! things `smelt run` would fail on (or silently get wrong); warnings are
+//! hygiene issues such as config for models that don't exist.
+
+use crate::config::{Config, ExposureConfig, IncrementalConfig, IncrementalStrategy, SourceConfig};
+use crate::discovery::{ModelDiscovery, ModelFile};
+use crate::graph::DependencyGraph;
+use crate::lineage::Lineage;
+use crate::package::{load_packages, merge_package_sources};
+use crate::template::Vars;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by [`validate_project`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", label, self.message)
+    }
+}
+
+/// Check smelt.yml, sources.yml, exposures.yml, and the models of the project at `project_root`.
+pub fn validate_project(project_root: &Path, cli_vars: &Vars) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let config = match Config::load_with_vars(project_root, cli_vars) {
+        Ok(config) => config,
+        Err(e) => {
+            issues.push(Issue::error(format!("{:#}", e)));
+            return issues;
+        }
+    };
+    check_config(project_root, &config, &mut issues);
+
+    let mut sources = if project_root.join("sources.yml").exists() {
+        match SourceConfig::load(project_root) {
+            Ok(sources) => Some(sources),
+            Err(e) => {
+                issues.push(Issue::error(format!("{:#}", e)));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let exposures = if project_root.join("exposures.yml").exists() {
+        match ExposureConfig::load(project_root) {
+            Ok(exposures) => Some(exposures),
+            Err(e) => {
+                issues.push(Issue::error(format!("{:#}", e)));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let packages = load_packages(&config, project_root).unwrap_or_else(|e| {
+        issues.push(Issue::error(format!("{:#}", e)));
+        Vec::new()
+    });
+    if let Err(e) = merge_package_sources(&mut sources, &packages) {
+        issues.push(Issue::error(format!("{:#}", e)));
+    }
+
+    let models = match ModelDiscovery::new(project_root.to_path_buf(), config.model_paths.clone())
+        .with_vars(config.vars.clone())
+        .with_programs(config.programs())
+        .with_packages(&packages)
+        .discover_models()
+    {
+        Ok(models) => models,
+        Err(e) => {
+            issues.push(Issue::error(format!("{:#}", e)));
+            return issues;
+        }
+    };
+
+    for model in &models {
+        for error in &model.parse_errors {
+            issues.push(Issue::warning(format!(
+                "Parse error in {}: {} at {:?}",
+                relative(project_root, &model.path),
+                error.message,
+                error.range
+            )));
+        }
+    }
+    check_duplicate_names(project_root, &models, &mut issues);
+
+    let graph = match DependencyGraph::build(models, sources.as_ref()) {
+        Ok(graph) => graph
+            .with_exposures(exposures.as_ref())
+            .with_packages(&packages),
+        Err(e) => {
+            issues.push(Issue::error(format!("{:#}", e)));
+            return issues;
+        }
+    };
+
+    for (model, dep) in graph.undefined_refs() {
+        issues.push(Issue::error(format!(
+            "Model '{}' references undefined model/source '{}'",
+            model, dep
+        )));
+    }
+    for (exposure, dep) in graph.undefined_exposure_refs() {
+        issues.push(Issue::error(format!(
+            "Exposure '{}' depends on undefined model/source '{}'",
+            exposure, dep
+        )));
+    }
+    if let Some(sources) = &sources {
+        check_undeclared_sources(&graph, sources, &mut issues);
+    }
+    if let Err(e) = graph.execution_order() {
+        issues.push(Issue::error(e.to_string()));
+    }
+
+    let mut unknown_models: Vec<&String> = config
+        .models
+        .keys()
+        .filter(|name| !graph.models().contains_key(*name))
+        .collect();
+    unknown_models.sort();
+    for name in unknown_models {
+        issues.push(Issue::warning(format!(
+            "smelt.yml configures model '{}', which doesn't exist",
+            name
+        )));
+    }
+
+    check_incremental(project_root, &config, &graph, sources.as_ref(), &mut issues);
+
+    issues
+}
+
+fn check_config(project_root: &Path, config: &Config, issues: &mut Vec<Issue>) {
+    let mut targets: Vec<_> = config.targets.iter().collect();
+    targets.sort_by_key(|(name, _)| *name);
+    for (name, target) in targets {
+        if target.known_backend_type().is_none() {
+            issues.push(Issue::error(format!(
+                "Target '{}' has unknown type '{}' (expected duckdb, spark, sqlite, or snowflake)",
+                name, target.target_type
+            )));
+        }
+    }
+
+    let mut programs: Vec<_> = config.programs().collect();
+    programs.sort_by_key(|(name, _)| *name);
+    for (name, program) in programs {
+        if program.command.is_empty() {
+            issues.push(Issue::error(format!(
+                "Program model '{}' has an empty command",
+                name
+            )));
+        }
+    }
+
+    let mut groups: Vec<&String> = config.groups.keys().collect();
+    groups.sort();
+    for group in groups {
+        if !project_root.join(group).is_dir() {
+            issues.push(Issue::warning(format!(
+                "Group '{}' in smelt.yml doesn't match a directory",
+                group
+            )));
+        }
+    }
+}
+
+fn check_duplicate_names(project_root: &Path, models: &[ModelFile], issues: &mut Vec<Issue>) {
+    let mut paths_by_name: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for model in models {
+        paths_by_name
+            .entry(&model.name)
+            .or_default()
+            .push(relative(project_root, &model.path));
+    }
+
+    for (name, mut paths) in paths_by_name {
+        if paths.len() > 1 {
+            paths.sort();
+            issues.push(Issue::error(format!(
+                "Model name '{}' is defined by multiple files: {}",
+                name,
+                paths.join(", ")
+            )));
+        }
+    }
+}
+
+/// Tables read with `smelt.source()` that sources.yml doesn't declare.
+fn check_undeclared_sources(
+    graph: &DependencyGraph,
+    sources: &SourceConfig,
+    issues: &mut Vec<Issue>,
+) {
+    let declared = sources.get_source_names();
+    let mut models: Vec<&ModelFile> = graph.models().values().collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for model in models {
+        for source in model.source_tables() {
+            // Like refs, an unqualified name matches a table in any source schema
+            let suffix = format!(".{}", source);
+            if !declared
+                .iter()
+                .any(|name| *name == source || name.ends_with(&suffix))
+            {
+                issues.push(Issue::warning(format!(
+                    "Model '{}' reads source '{}', which isn't declared in sources.yml",
+                    model.name, source
+                )));
+            }
+        }
+    }
+}
+
+/// Incremental settings that can't work: missing columns or a merge without a key.
+fn check_incremental(
+    project_root: &Path,
+    config: &Config,
+    graph: &DependencyGraph,
+    sources: Option<&SourceConfig>,
+    issues: &mut Vec<Issue>,
+) {
+    let lineage = Lineage::build(graph, project_root);
+    let mut models: Vec<&ModelFile> = graph.models().values().collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for model in models {
+        let Some(incremental) = config.get_model_incremental(model, sources) else {
+            continue;
+        };
+        let name = &model.name;
+
+        if let Some(problem) = event_time_problem(model, &incremental, graph, &lineage, sources) {
+            issues.push(Issue::error(problem));
+        }
+
+        let column = &incremental.partition_column;
+        if column.trim().is_empty() {
+            issues.push(Issue::error(format!(
+                "Incremental model '{}' has no partition_column, and its sources don't declare one",
+                name
+            )));
+        } else if !lineage.column_names(name).is_empty() && !lineage.provides(name, column) {
+            issues.push(Issue::error(format!(
+                "Incremental model '{}': partition_column '{}' isn't one of its output columns ({})",
+                name,
+                column,
+                lineage.column_names(name).join(", ")
+            )));
+        }
+
+        if incremental.incremental_strategy == IncrementalStrategy::Merge
+            && incremental.unique_key.is_empty()
+        {
+            issues.push(Issue::error(format!(
+                "Incremental model '{}' uses the merge strategy but has no unique_key",
+                name
+            )));
+        }
+    }
+}
+
+/// Why an incremental model can't filter its inputs on its `event_time_column`:
+/// it has none, or (as far as smelt-db can infer) no input has that column.
+pub fn event_time_problem(
+    model: &ModelFile,
+    incremental: &IncrementalConfig,
+    graph: &DependencyGraph,
+    lineage: &Lineage,
+    sources: Option<&SourceConfig>,
+) -> Option<String> {
+    let column = &incremental.event_time_column;
+    if column.trim().is_empty() {
+        return Some(format!(
+            "Incremental model '{}' has no event_time_column, and its sources don't declare one",
+            model.name
+        ));
+    }
+
+    let inputs = input_columns(model, graph, lineage, sources)?;
+    if inputs.iter().any(|(_, has_column)| has_column(column)) {
+        return None;
+    }
+    Some(format!(
+        "Incremental model '{}': event_time_column '{}' isn't a column of its inputs ({})",
+        model.name,
+        column,
+        inputs
+            .iter()
+            .map(|(input, _)| input.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+type HasColumn<'a> = Box<dyn Fn(&str) -> bool + 'a>;
+
+/// A model's refs and sources, each with a check for whether it has a column.
+///
+/// `None` if there are no inputs, or any input's columns aren't known.
+fn input_columns<'a>(
+    model: &ModelFile,
+    graph: &DependencyGraph,
+    lineage: &'a Lineage,
+    sources: Option<&'a SourceConfig>,
+) -> Option<Vec<(String, HasColumn<'a>)>> {
+    let mut inputs: Vec<(String, HasColumn<'a>)> = Vec::new();
+
+    for dep in &model.refs {
+        let upstream = dep.model_name.clone();
+        if !graph.models().contains_key(&upstream) || lineage.column_names(&upstream).is_empty() {
+            return None;
+        }
+        let name = upstream.clone();
+        inputs.push((upstream, Box::new(move |c| lineage.provides(&name, c))));
+    }
+
+    for source in model.source_tables() {
+        let columns = &sources?.get_table(&source)?.columns;
+        inputs.push((
+            source,
+            Box::new(move |c| columns.iter().any(|col| col.name.eq_ignore_ascii_case(c))),
+        ));
+    }
+
+    (!inputs.is_empty()).then_some(inputs)
+}
+
+fn relative(project_root: &Path, path: &Path) -> String {
+    path.strip_prefix(project_root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn messages(issues: &[Issue]) -> Vec<String> {
+        issues.iter().map(ToString::to_string).collect()
+    }
+
+    const SOURCES: &str = "version: 1
+sources:
+  raw:
+    tables:
+      events:
+        event_time_column: event_time
+        columns:
+          - name: event_id
+            type: INTEGER
+          - name: event_time
+            type: TIMESTAMP
+";
+
+    #[test]
+    fn test_valid_project() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            root,
+            "smelt.yml",
+            "name: test
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+models:
+  daily_events:
+    incremental:
+      enabled: true
+      partition_column: event_date
+",
+        );
+        write(root, "sources.yml", SOURCES);
+        write(
+            root,
+            "models/daily_events.sql",
+            "SELECT DATE(event_time) AS event_date, COUNT(*) AS events FROM smelt.source('raw.events') GROUP BY 1",
+        );
+        write(
+            root,
+            "models/summary.sql",
+            "SELECT * FROM smelt.ref('daily_events')",
+        );
+
+        assert_eq!(
+            messages(&validate_project(root, &Vars::new())),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write(
+            temp_dir.path(),
+            "smelt.yml",
+            "name: test\nversion: 1\ntargets: {}\ndefault_materialization: tabel\n",
+        );
+
+        let issues = validate_project(temp_dir.path(), &Vars::new());
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].is_error());
+        assert!(issues[0]
+            .message
+            .contains("Invalid materialization type: tabel"));
+    }
+
+    #[test]
+    fn test_project_issues() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            root,
+            "smelt.yml",
+            "name: test
+version: 1
+targets:
+  dev:
+    type: postgres
+    schema: main
+groups:
+  models/marts:
+    materialization: table
+models:
+  old_model:
+    materialization: table
+  events_by_day:
+    incremental:
+      enabled: true
+      event_time_column: created_at
+      partition_column: day
+      incremental_strategy: merge
+",
+        );
+        write(root, "sources.yml", SOURCES);
+        write(
+            root,
+            "models/staging/events.sql",
+            "SELECT * FROM smelt.source('raw.events')",
+        );
+        write(
+            root,
+            "models/other/events.sql",
+            "SELECT * FROM smelt.source('raw.events')",
+        );
+        write(
+            root,
+            "models/clicks.sql",
+            "SELECT * FROM smelt.source('raw.clicks')",
+        );
+        write(
+            root,
+            "models/events_by_day.sql",
+            "SELECT event_id, DATE(event_time) AS event_date FROM smelt.source('raw.events')",
+        );
+        write(root, "models/a.sql", "SELECT * FROM smelt.ref('b')");
+        write(root, "models/b.sql", "SELECT * FROM smelt.ref('a')");
+        write(
+            root,
+            "models/c.sql",
+            "SELECT * FROM smelt.ref('missing_model')",
+        );
+        write(
+            root,
+            "exposures.yml",
+            "version: 1\nexposures:\n  kpis:\n    type: dashboard\n    depends_on: [c, old_dashboard_model]\n",
+        );
+
+        let issues = messages(&validate_project(root, &Vars::new()));
+        let expected = [
+            "error: Target 'dev' has unknown type 'postgres' (expected duckdb, spark, sqlite, or snowflake)",
+            "warning: Group 'models/marts' in smelt.yml doesn't match a directory",
+            "error: Model name 'events' is defined by multiple files: models/other/events.sql, models/staging/events.sql",
+            "error: Model 'c' references undefined model/source 'missing_model'",
+            "error: Exposure 'kpis' depends on undefined model/source 'old_dashboard_model'",
+            "warning: Model 'clicks' reads source 'raw.clicks', which isn't declared in sources.yml",
+            "error: Circular dependency detected involving models: ",
+            "warning: smelt.yml configures model 'old_model', which doesn't exist",
+            "error: Incremental model 'events_by_day': event_time_column 'created_at' isn't a column of its inputs (raw.events)",
+            "error: Incremental model 'events_by_day': partition_column 'day' isn't one of its output columns (event_id, event_date)",
+            "error: Incremental model 'events_by_day' uses the merge strategy but has no unique_key",
+        ];
+        for message in expected {
+            assert!(
+                issues.iter().any(|issue| issue.starts_with(message)),
+                "missing {:?} in {:#?}",
+                message,
+                issues
+            );
+        }
+        assert_eq!(issues.len(), expected.len(), "{:#?}", issues);
+    }
+}
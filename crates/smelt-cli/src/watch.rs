@@ -0,0 +1,150 @@
+//! Change detection for `smelt run --watch`.
+//!
+//! Watch mode polls the model directories for modified files, then compares
+//! model checksums against the previous cycle so only models whose SQL
+//! actually changed (plus everything downstream of them) are re-run.
+
+use crate::artifacts::checksum;
+use crate::graph::DependencyGraph;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// How often model directories are polled for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modification time of every `.sql` file under the model paths.
+///
+/// Cheap to compute, so it is used to decide whether a full rediscovery is needed.
+pub fn scan_model_files(
+    project_root: &Path,
+    model_paths: &[String],
+) -> BTreeMap<PathBuf, SystemTime> {
+    let mut files = BTreeMap::new();
+
+    for model_path in model_paths {
+        for entry in WalkDir::new(project_root.join(model_path))
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("sql") {
+                continue;
+            }
+            if let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok()) {
+                files.insert(path.to_path_buf(), modified);
+            }
+        }
+    }
+
+    files
+}
+
+/// Checksum of every model's source, keyed by model name.
+pub fn model_checksums(graph: &DependencyGraph) -> HashMap<String, String> {
+    graph
+        .models()
+        .values()
+        .map(|model| (model.name.clone(), checksum(&model.content)))
+        .collect()
+}
+
+/// Models that are new or whose checksum differs from `previous`, sorted by name.
+pub fn changed_models(
+    previous: &HashMap<String, String>,
+    current: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut changed: Vec<String> = current
+        .iter()
+        .filter(|(name, sum)| previous.get(*name) != Some(*sum))
+        .map(|(name, _)| name.clone())
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// The changed models plus everything downstream of them.
+pub fn affected_models(graph: &DependencyGraph, changed: &[String]) -> HashSet<String> {
+    let mut affected: HashSet<String> = changed.iter().cloned().collect();
+    for name in changed {
+        affected.extend(graph.downstream(name));
+    }
+    affected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{ModelFile, RefInfo};
+    use rowan::TextRange;
+
+    fn make_model(name: &str, content: &str, deps: Vec<&str>) -> ModelFile {
+        ModelFile {
+            name: name.to_string(),
+            path: format!("{}.sql", name).into(),
+            content: content.to_string(),
+            refs: deps
+                .into_iter()
+                .map(|dep| RefInfo {
+                    model_name: dep.to_string(),
+                    has_named_params: false,
+                    range: TextRange::default(),
+                })
+                .collect(),
+            parse_errors: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_changed_and_affected_models() {
+        let before = DependencyGraph::build(
+            vec![
+                make_model("a", "SELECT 1", vec![]),
+                make_model("b", "SELECT 2", vec!["a"]),
+                make_model("c", "SELECT 3", vec![]),
+            ],
+            None,
+        )
+        .unwrap();
+        let after = DependencyGraph::build(
+            vec![
+                make_model("a", "SELECT 10", vec![]),
+                make_model("b", "SELECT 2", vec!["a"]),
+                make_model("c", "SELECT 3", vec![]),
+                make_model("d", "SELECT 4", vec!["c"]),
+            ],
+            None,
+        )
+        .unwrap();
+
+        let changed = changed_models(&model_checksums(&before), &model_checksums(&after));
+        assert_eq!(changed, vec!["a", "d"]);
+
+        let affected = affected_models(&after, &changed);
+        assert_eq!(
+            affected,
+            HashSet::from(["a".to_string(), "b".to_string(), "d".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_scan_model_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let models_dir = temp_dir.path().join("models/staging");
+        std::fs::create_dir_all(&models_dir).unwrap();
+        std::fs::write(models_dir.join("a.sql"), "SELECT 1").unwrap();
+        std::fs::write(models_dir.join("notes.md"), "ignored").unwrap();
+
+        let files = scan_model_files(temp_dir.path(), &["models".to_string()]);
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            vec![&models_dir.join("a.sql")]
+        );
+
+        // Missing model paths are skipped
+        assert!(scan_model_files(temp_dir.path(), &["missing".to_string()]).is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 //! Integration test for incremental materialization
 
-use smelt_backend::{Backend, PartitionSpec};
+use smelt_backend::{Backend, PartitionSpec, RelationName};
 use smelt_backend_duckdb::DuckDbBackend;
 use tempfile::TempDir;
 
@@ -72,7 +72,9 @@ async fn test_incremental_delete_and_insert() -> anyhow::Result<()> {
     seed_database(&backend).await?;
 
     // Verify source data exists
-    let count = backend.get_row_count("raw", "transactions").await?;
+    let count = backend
+        .get_row_count(&RelationName::new("raw", "transactions"))
+        .await?;
     assert_eq!(count, 5, "Expected 5 transactions");
 
     // Create the daily_revenue table (simulating full refresh)
@@ -92,7 +94,9 @@ async fn test_incremental_delete_and_insert() -> anyhow::Result<()> {
         .await?;
 
     // Verify full table
-    let count = backend.get_row_count("main", "daily_revenue").await?;
+    let count = backend
+        .get_row_count(&RelationName::new("main", "daily_revenue"))
+        .await?;
     assert!(count > 0, "Expected rows in daily_revenue");
 
     // Test delete_partitions
@@ -102,7 +106,7 @@ async fn test_incremental_delete_and_insert() -> anyhow::Result<()> {
     };
 
     backend
-        .delete_partitions("main", "daily_revenue", &partition)
+        .delete_partitions(&RelationName::new("main", "daily_revenue"), &partition)
         .await?;
 
     // Verify rows were deleted
@@ -120,8 +124,7 @@ async fn test_incremental_delete_and_insert() -> anyhow::Result<()> {
     // Test insert_into_from_query (simulating incremental insert)
     backend
         .insert_into_from_query(
-            "main",
-            "daily_revenue",
+            &RelationName::new("main", "daily_revenue"),
             r#"
             SELECT
                 transaction_timestamp::DATE as revenue_date,
@@ -160,7 +163,7 @@ async fn test_inject_time_filter() -> anyhow::Result<()> {
         end: "2024-12-26".into(),
     };
 
-    let result = inject_time_filter(sql, "transaction_timestamp", &range)?;
+    let result = inject_time_filter(sql, "transaction_timestamp", &[], &range)?.sql;
 
     assert!(result.contains("WHERE transaction_timestamp >= '2024-12-25'"));
     assert!(result.contains("AND transaction_timestamp < '2024-12-26'"));
@@ -178,10 +181,10 @@ async fn test_inject_time_filter_with_existing_where() -> anyhow::Result<()> {
         end: "2024-12-26".into(),
     };
 
-    let result = inject_time_filter(sql, "transaction_timestamp", &range)?;
+    let result = inject_time_filter(sql, "transaction_timestamp", &[], &range)?.sql;
 
-    // Should keep original WHERE
-    assert!(result.contains("WHERE user_id = 1"));
+    // Should keep original WHERE, parenthesized
+    assert!(result.contains("WHERE (user_id = 1)"));
     // Should add AND with filter
     assert!(result.contains(
         "AND (transaction_timestamp >= '2024-12-25' AND transaction_timestamp < '2024-12-26')"
@@ -189,3 +192,160 @@ async fn test_inject_time_filter_with_existing_where() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_incremental_hourly_partitions() -> anyhow::Result<()> {
+    use smelt_cli::{align_time_range, inject_time_filter, partition_values};
+    use smelt_cli::{PartitionGranularity, TimeRange};
+
+    let temp_dir = TempDir::new()?;
+    let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main").await?;
+    seed_database(&backend).await?;
+
+    let model_sql = r#"
+        SELECT
+            date_trunc('hour', transaction_timestamp) as revenue_hour,
+            SUM(amount) as total_revenue
+        FROM raw.transactions
+        GROUP BY 1
+    "#;
+    backend
+        .execute_sql(&format!(
+            "CREATE TABLE main.hourly_revenue AS {}",
+            model_sql
+        ))
+        .await?;
+    backend
+        .execute_sql("UPDATE main.hourly_revenue SET total_revenue = 0")
+        .await?;
+
+    // Half-hour bounds widen to the 09:00-15:00 partitions
+    let requested = TimeRange {
+        start: "2024-12-25 09:30".into(),
+        end: "2024-12-25 14:30".into(),
+    };
+    let range = align_time_range(&requested, PartitionGranularity::Hour)?;
+    let values = partition_values(&requested, PartitionGranularity::Hour)?;
+    assert_eq!(values.len(), 6);
+    assert_eq!(values[0], "2024-12-25 09:00:00");
+
+    let partition = PartitionSpec {
+        column: "revenue_hour".to_string(),
+        values,
+    };
+    backend
+        .delete_partitions(&RelationName::new("main", "hourly_revenue"), &partition)
+        .await?;
+    backend
+        .insert_into_from_query(
+            &RelationName::new("main", "hourly_revenue"),
+            &inject_time_filter(model_sql, "transaction_timestamp", &[], &range)?.sql,
+        )
+        .await?;
+
+    // The 10:00 and 14:00 transactions were rebuilt; other hours were untouched
+    let result = backend
+        .execute_sql(
+            "SELECT COUNT(*), SUM(total_revenue)::BIGINT FROM main.hourly_revenue WHERE total_revenue > 0",
+        )
+        .await?;
+    let column = |i: usize| {
+        result[0]
+            .column(i)
+            .as_any()
+            .downcast_ref::<arrow::array::Int64Array>()
+            .unwrap()
+            .value(0)
+    };
+    assert_eq!(column(0), 2);
+    assert_eq!(column(1), 300);
+    assert_eq!(
+        backend
+            .get_row_count(&RelationName::new("main", "hourly_revenue"))
+            .await?,
+        5
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_incremental_merge() -> anyhow::Result<()> {
+    use smelt_backend::{IncrementalStrategy, Materialization, MaterializationStrategy};
+    use smelt_cli::{inject_time_filter, TimeRange};
+
+    let temp_dir = TempDir::new()?;
+    let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main").await?;
+    seed_database(&backend).await?;
+
+    let model_sql = r#"
+        SELECT
+            transaction_timestamp::DATE as revenue_date,
+            user_id,
+            SUM(amount) as total_revenue
+        FROM raw.transactions
+        GROUP BY 1, 2
+    "#;
+    backend
+        .execute_model(
+            &RelationName::new("main", "daily_revenue"),
+            model_sql,
+            Materialization::Table,
+            false,
+        )
+        .await?;
+
+    // A late correction and a new transaction for 2024-12-26
+    backend
+        .execute_sql("UPDATE raw.transactions SET amount = 80.00 WHERE id = 3")
+        .await?;
+    backend
+        .execute_sql(
+            "INSERT INTO raw.transactions VALUES (6, 2, 40.00, '2024-12-26 12:00:00'::TIMESTAMP)",
+        )
+        .await?;
+
+    let range = TimeRange {
+        start: "2024-12-26".into(),
+        end: "2024-12-27".into(),
+    };
+    let result = backend
+        .execute_model_incremental(
+            &RelationName::new("main", "daily_revenue"),
+            &inject_time_filter(model_sql, "transaction_timestamp", &[], &range)?.sql,
+            Materialization::Table,
+            MaterializationStrategy::Incremental {
+                partition: PartitionSpec {
+                    column: "revenue_date".to_string(),
+                    values: vec!["2024-12-26".to_string()],
+                },
+                strategy: IncrementalStrategy::Merge {
+                    unique_key: vec!["revenue_date".to_string(), "user_id".to_string()],
+                },
+            },
+            false,
+        )
+        .await?;
+
+    // User 1's row was updated in place, user 2's inserted; other days untouched
+    assert_eq!(result.row_count, 6);
+    let result = backend
+        .execute_sql(
+            "SELECT user_id::BIGINT, total_revenue::BIGINT FROM main.daily_revenue \
+             WHERE revenue_date = '2024-12-26' ORDER BY user_id",
+        )
+        .await?;
+    let column = |i: usize| {
+        result[0]
+            .column(i)
+            .as_any()
+            .downcast_ref::<arrow::array::Int64Array>()
+            .unwrap()
+            .values()
+            .to_vec()
+    };
+    assert_eq!(column(0), vec![1, 2, 3]);
+    assert_eq!(column(1), vec![80, 40, 300]);
+
+    Ok(())
+}
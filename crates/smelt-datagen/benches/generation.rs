@@ -0,0 +1,186 @@
+//! Generation throughput benchmarks.
+//!
+//! Run with `cargo bench -p smelt-datagen --features bench`; see
+//! `BENCHMARKS.md` for the baseline and how to compare against it.
+
+use anyhow::Result;
+use arrow::record_batch::RecordBatch;
+use chrono::NaiveDate;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use smelt_datagen::{
+    generate_day_seeds, DataSink, DayGenerator, ParquetOutput, StreamingOutput, VisitorPool,
+};
+use std::hint::black_box;
+use std::path::Path;
+use tempfile::TempDir;
+
+const SEED: u64 = 42;
+const SESSIONS: usize = 100_000;
+const DAYS: u32 = 10;
+
+fn start_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+}
+
+/// Counts what it's given without writing it anywhere.
+#[derive(Default)]
+struct CountingSink {
+    rows: usize,
+    bytes: usize,
+}
+
+impl CountingSink {
+    fn add(&mut self, batch: &RecordBatch) {
+        self.rows += batch.num_rows();
+        self.bytes += batch.get_array_memory_size();
+    }
+}
+
+impl DataSink for CountingSink {
+    fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.add(batch);
+        Ok(())
+    }
+
+    fn write_sessions(&mut self, _date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.add(batch);
+        Ok(())
+    }
+
+    fn write_events(&mut self, _date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.add(batch);
+        Ok(())
+    }
+
+    fn write_orders(
+        &mut self,
+        _date: NaiveDate,
+        orders: &RecordBatch,
+        items: &RecordBatch,
+    ) -> Result<()> {
+        self.add(orders);
+        self.add(items);
+        Ok(())
+    }
+
+    fn write_touchpoints(&mut self, _date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.add(batch);
+        Ok(())
+    }
+
+    fn write_expected(
+        &mut self,
+        _date: NaiveDate,
+        platforms: &RecordBatch,
+        categories: &RecordBatch,
+    ) -> Result<()> {
+        self.add(platforms);
+        self.add(categories);
+        Ok(())
+    }
+}
+
+/// One day's sessions from `DayGenerator`, without Arrow conversion.
+fn day_generator(c: &mut Criterion) {
+    let pool = VisitorPool::new(SEED, SESSIONS);
+    let day_seed = generate_day_seeds(SEED, 1)[0];
+    let per_day = SESSIONS / DAYS as usize;
+    let rows = DayGenerator::new(pool.clone(), day_seed, start_date(), per_day)
+        .generate()
+        .len();
+
+    let mut group = c.benchmark_group("day_generator");
+    group.throughput(Throughput::Elements(rows as u64));
+    group.bench_function(BenchmarkId::from_parameter(per_day), |b| {
+        b.iter(|| {
+            DayGenerator::new(pool.clone(), black_box(day_seed), start_date(), per_day).generate()
+        })
+    });
+    group.finish();
+}
+
+/// The visitor pool and every day's batches through `StreamingOutput`.
+fn streaming_output(c: &mut Criterion) {
+    let mut sink = CountingSink::default();
+    StreamingOutput::new()
+        .write_to(&mut sink, SEED, SESSIONS, DAYS, start_date())
+        .unwrap();
+
+    let mut group = c.benchmark_group("streaming_output");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(sink.rows as u64));
+    group.bench_function("rows", |b| {
+        b.iter(|| {
+            StreamingOutput::new()
+                .write_to(
+                    &mut CountingSink::default(),
+                    SEED,
+                    SESSIONS,
+                    DAYS,
+                    start_date(),
+                )
+                .unwrap()
+        })
+    });
+    group.throughput(Throughput::Bytes(sink.bytes as u64));
+    group.bench_function("arrow_bytes", |b| {
+        b.iter(|| {
+            StreamingOutput::new()
+                .write_to(
+                    &mut CountingSink::default(),
+                    SEED,
+                    SESSIONS,
+                    DAYS,
+                    start_date(),
+                )
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                dir_size(&entry.path())
+            } else {
+                entry.metadata().unwrap().len()
+            }
+        })
+        .sum()
+}
+
+/// Generating and writing Hive-partitioned Parquet with `ParquetOutput`.
+fn parquet_writer(c: &mut Criterion) {
+    let temp_dir = TempDir::new().unwrap();
+    let rows = ParquetOutput::new()
+        .write_days(temp_dir.path(), SEED, SESSIONS, DAYS, start_date(), None)
+        .unwrap();
+    let bytes = dir_size(temp_dir.path());
+
+    let mut group = c.benchmark_group("parquet_writer");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(rows as u64));
+    group.bench_function("rows", |b| {
+        b.iter(|| {
+            ParquetOutput::new()
+                .write_days(temp_dir.path(), SEED, SESSIONS, DAYS, start_date(), None)
+                .unwrap()
+        })
+    });
+    group.throughput(Throughput::Bytes(bytes));
+    group.bench_function("file_bytes", |b| {
+        b.iter(|| {
+            ParquetOutput::new()
+                .write_days(temp_dir.path(), SEED, SESSIONS, DAYS, start_date(), None)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, day_generator, streaming_output, parquet_writer);
+criterion_main!(benches);
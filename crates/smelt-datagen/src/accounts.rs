@@ -0,0 +1,201 @@
+//! Account dimension linked to visitors.
+//!
+//! Every visitor gets one account with a signup date and, for some, a churn
+//! date. When accounts are enabled a visitor only has sessions while their
+//! account is active, so joins from sessions to accounts are always consistent.
+
+use crate::gen::Gen;
+use crate::generators::{uuid_gen, weighted_choice, WeightedChoice};
+use crate::session::VisitorPool;
+use chrono::{Duration, NaiveDate};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use uuid::Uuid;
+
+/// Accounts per independently seeded block.
+const ACCOUNT_BLOCK_SIZE: usize = 1 << 16;
+/// Offset for account RNG streams, clear of the visitor block streams.
+const ACCOUNT_STREAM_BASE: u64 = 1 << 32;
+
+/// Subscription plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plan {
+    Free,
+    Basic,
+    Pro,
+    Enterprise,
+}
+
+impl Plan {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Plan::Free => "free",
+            Plan::Basic => "basic",
+            Plan::Pro => "pro",
+            Plan::Enterprise => "enterprise",
+        }
+    }
+}
+
+/// An account belonging to a visitor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    pub account_id: Uuid,
+    pub visitor_id: Uuid,
+    pub signup_date: NaiveDate,
+    pub country: &'static str,
+    pub plan: Plan,
+    pub churn_date: Option<NaiveDate>,
+}
+
+impl Account {
+    /// Whether the account can have sessions on `date`: on or after signup
+    /// and before churn.
+    pub fn is_active(&self, date: NaiveDate) -> bool {
+        self.signup_date <= date && self.churn_date.is_none_or(|churn| date < churn)
+    }
+}
+
+/// Shape of the account dimension.
+#[derive(Debug, Clone)]
+pub struct AccountConfig {
+    /// Fraction of accounts that churn before the end of the range.
+    pub churn_rate: f64,
+    /// How far before the start date the earliest signups go.
+    pub signup_lookback_days: u32,
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            churn_rate: 0.15,
+            signup_lookback_days: 365,
+        }
+    }
+}
+
+impl AccountConfig {
+    /// One account per visitor in `pool`, in pool order.
+    ///
+    /// Signups fall between `signup_lookback_days` before `start_date` and the
+    /// last day of the range; churn dates fall after signup, up to one day
+    /// past the range.
+    pub fn generate(
+        &self,
+        seed: u64,
+        pool: &VisitorPool,
+        start_date: NaiveDate,
+        num_days: u32,
+    ) -> Vec<Account> {
+        let visitors = pool.visitors();
+        let blocks: Vec<Vec<Account>> = visitors
+            .par_chunks(ACCOUNT_BLOCK_SIZE)
+            .enumerate()
+            .map(|(block, visitors)| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(ACCOUNT_STREAM_BASE + block as u64);
+                let country_g = country_gen();
+                let plan_g = plan_gen();
+
+                visitors
+                    .iter()
+                    .map(|visitor| {
+                        let first = start_date - Duration::days(self.signup_lookback_days as i64);
+                        let span = self.signup_lookback_days as i64 + num_days as i64;
+                        let signup_date = first + Duration::days(rng.gen_range(0..span.max(1)));
+                        let end = start_date + Duration::days(num_days as i64);
+                        let churn_date = if rng.gen_bool(self.churn_rate) {
+                            let days_left = (end - signup_date).num_days().max(1);
+                            Some(signup_date + Duration::days(rng.gen_range(1..=days_left)))
+                        } else {
+                            None
+                        };
+
+                        Account {
+                            account_id: uuid_gen().generate(&mut rng),
+                            visitor_id: visitor.id,
+                            signup_date,
+                            country: country_g.generate(&mut rng),
+                            plan: plan_g.generate(&mut rng),
+                            churn_date,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        blocks.concat()
+    }
+}
+
+fn country_gen() -> WeightedChoice<&'static str> {
+    weighted_choice(vec![
+        ("US", 0.35),
+        ("GB", 0.10),
+        ("IN", 0.10),
+        ("DE", 0.08),
+        ("FR", 0.07),
+        ("BR", 0.07),
+        ("CA", 0.06),
+        ("JP", 0.06),
+        ("ES", 0.06),
+        ("AU", 0.05),
+    ])
+}
+
+fn plan_gen() -> WeightedChoice<Plan> {
+    weighted_choice(vec![
+        (Plan::Free, 0.60),
+        (Plan::Basic, 0.25),
+        (Plan::Pro, 0.12),
+        (Plan::Enterprise, 0.03),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{generate_day_seeds, DayGenerator};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sessions_within_account_lifetime() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let pool = VisitorPool::new(42, 5_000);
+        let accounts = AccountConfig {
+            churn_rate: 0.5,
+            signup_lookback_days: 10,
+        }
+        .generate(42, &pool, start, 10);
+        assert_eq!(accounts.len(), pool.len());
+
+        let accounts = Arc::new(accounts);
+        let by_visitor: HashMap<_, _> = accounts.iter().map(|a| (a.visitor_id, a)).collect();
+
+        for (i, day_seed) in generate_day_seeds(42, 10).into_iter().enumerate() {
+            let date = start + Duration::days(i as i64);
+            let sessions = DayGenerator::new(pool.clone(), day_seed, date, 500)
+                .with_accounts(accounts.clone())
+                .generate();
+            assert!(!sessions.is_empty());
+            for session in sessions {
+                assert!(by_visitor[&session.visitor_id].is_active(date));
+            }
+        }
+    }
+
+    #[test]
+    fn test_churn_after_signup() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let pool = VisitorPool::new(42, 5_000);
+        let accounts = AccountConfig::default().generate(42, &pool, start, 30);
+
+        assert!(accounts.iter().any(|a| a.churn_date.is_some()));
+        for account in &accounts {
+            if let Some(churn) = account.churn_date {
+                assert!(churn > account.signup_date);
+            }
+        }
+    }
+}
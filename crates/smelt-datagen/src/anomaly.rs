@@ -0,0 +1,633 @@
+//! Controlled anomaly injection.
+//!
+//! Anomalies give data-quality tests known defects to detect. Everything is
+//! placed deterministically from the root seed and each day's seed, and uses
+//! separate RNG streams so the non-anomalous rows are unchanged.
+//!
+//! Bot personas and fraud give detection models traffic to find, with the
+//! answer in a hidden [`GROUND_TRUTH_COLUMN`]. Both are keyed by the low 64
+//! bits of ids, which id formats leave alone, so labels can be recomputed
+//! from the written rows.
+
+use crate::gen::Gen;
+use crate::generators::uuid_gen;
+use crate::session::{Platform, ProductCategory, Session, VisitSource, Visitor};
+use anyhow::{Context, Result};
+use arrow::array::{Array, ArrayRef, Int32Array, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use chrono::NaiveDate;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// RNG stream for bot visitor identities, derived from the root seed.
+/// Persona bots count down from the one below it.
+const BOT_VISITOR_STREAM: u64 = u64::MAX;
+/// RNG stream for per-day anomaly placement, derived from the day seed.
+const DAY_ANOMALY_STREAM: u64 = 1;
+/// RNG stream for fraud, derived from the root seed and session id.
+const FRAUD_STREAM: u64 = 0x800;
+
+/// Column labelling each session row `human`, `bot`, `bot:<persona>`, or
+/// `fraud`, added when [`AnomalyConfig::ground_truth`] is set.
+pub const GROUND_TRUTH_COLUMN: &str = "_ground_truth";
+
+/// Geo and device columns of datacenter bots' rows.
+const DATACENTER_COLUMNS: &[(&str, &str)] = &[
+    ("country", "US"),
+    ("region", "Virginia"),
+    ("city", "Ashburn"),
+    ("locale", "en-US"),
+    ("time_zone", "America/New_York"),
+    ("device_model", "Linux PC"),
+    (
+        "user_agent",
+        "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+         HeadlessChrome/124.0.0.0 Safari/537.36",
+    ),
+];
+
+/// A kind of bot: how many there are and what their sessions look like.
+/// Bots never purchase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BotPersona {
+    /// Ground-truth label suffix, as in `bot:<name>`.
+    pub name: String,
+    pub visitors: usize,
+    /// Sessions per bot per day.
+    pub sessions_per_day: usize,
+    pub platform: Platform,
+    pub visit_source: VisitSource,
+    /// Inclusive range of widget views per session.
+    pub widget_views: (i32, i32),
+    /// Inclusive range of product views per session.
+    pub product_views: (i32, i32),
+    /// Add every viewed product to the cart, without checking out.
+    pub adds_to_cart: bool,
+    /// Give rows one datacenter location and a headless browser's user
+    /// agent in any geo and device columns.
+    pub datacenter: bool,
+}
+
+impl BotPersona {
+    /// Very high frequency scraping from a datacenter: dozens of sessions a
+    /// day, each viewing dozens of products.
+    pub fn scraper(visitors: usize) -> Self {
+        Self {
+            name: "scraper".to_string(),
+            visitors,
+            sessions_per_day: 40,
+            platform: Platform::WebDesktop,
+            visit_source: VisitSource::Direct,
+            widget_views: (50, 200),
+            product_views: (20, 100),
+            adds_to_cart: false,
+            datacenter: true,
+        }
+    }
+
+    /// A crawler from a datacenter: a few long sessions a day that rarely
+    /// view products.
+    pub fn crawler(visitors: usize) -> Self {
+        Self {
+            name: "crawler".to_string(),
+            visitors,
+            sessions_per_day: 4,
+            platform: Platform::WebDesktop,
+            visit_source: VisitSource::Direct,
+            widget_views: (100, 400),
+            product_views: (0, 5),
+            adds_to_cart: false,
+            datacenter: true,
+        }
+    }
+
+    /// Holds stock in carts from mobile, residential-looking traffic, and
+    /// never checks out.
+    pub fn cart_hoarder(visitors: usize) -> Self {
+        Self {
+            name: "cart_hoarder".to_string(),
+            visitors,
+            sessions_per_day: 10,
+            platform: Platform::WebMobile,
+            visit_source: VisitSource::Direct,
+            widget_views: (5, 20),
+            product_views: (5, 20),
+            adds_to_cart: true,
+            datacenter: false,
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_sessions_per_day(mut self, sessions_per_day: usize) -> Self {
+        self.sessions_per_day = sessions_per_day;
+        self
+    }
+
+    fn session(&self, rng: &mut ChaCha8Rng, bot: &Visitor, date: NaiveDate) -> Session {
+        let product_views = rng.gen_range(self.product_views.0..=self.product_views.1);
+        Session {
+            visitor_id: bot.id,
+            session_id: uuid_gen().generate(rng),
+            platform: self.platform,
+            visit_source: self.visit_source,
+            visit_campaign: None,
+            widget_views: rng.gen_range(self.widget_views.0..=self.widget_views.1),
+            session_date: date,
+            product_views,
+            product_add_to_cart_count: if self.adds_to_cart { product_views } else { 0 },
+            product_checkout_count: 0,
+            product_category: ProductCategory::Electronics,
+            product_revenue: 0,
+            product_purchase_count: 0,
+        }
+    }
+}
+
+/// Sessions on `platform` are dropped between `start` and `end` inclusive.
+#[derive(Debug, Clone)]
+pub struct PlatformOutage {
+    pub platform: Platform,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// Anomalies to inject into generated sessions.
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyConfig {
+    /// Dates whose session volume is multiplied by the given factor.
+    pub traffic_spikes: Vec<(NaiveDate, f64)>,
+    pub platform_outages: Vec<PlatformOutage>,
+    /// Fraction of rows emitted twice.
+    pub duplicate_rate: f64,
+    /// Number of bot visitors, each producing `bot_sessions_per_day` sessions
+    /// every day with high view counts and no cart activity.
+    pub bot_visitors: usize,
+    pub bot_sessions_per_day: usize,
+    /// More bots, each persona with its own identities and behaviour.
+    pub bot_personas: Vec<BotPersona>,
+    /// Fraction of human sessions with purchases that are fraudulent: a
+    /// burst of several times the purchases and revenue.
+    pub fraud_rate: f64,
+    /// Label each session row in [`GROUND_TRUTH_COLUMN`].
+    pub ground_truth: bool,
+}
+
+impl AnomalyConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_traffic_spike(mut self, date: NaiveDate, multiplier: f64) -> Self {
+        self.traffic_spikes.push((date, multiplier));
+        self
+    }
+
+    pub fn with_platform_outage(
+        mut self,
+        platform: Platform,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Self {
+        self.platform_outages.push(PlatformOutage {
+            platform,
+            start,
+            end,
+        });
+        self
+    }
+
+    pub fn with_duplicate_rate(mut self, rate: f64) -> Self {
+        self.duplicate_rate = rate;
+        self
+    }
+
+    pub fn with_bot_visitors(mut self, count: usize, sessions_per_day: usize) -> Self {
+        self.bot_visitors = count;
+        self.bot_sessions_per_day = sessions_per_day;
+        self
+    }
+
+    pub fn with_bot_persona(mut self, persona: BotPersona) -> Self {
+        self.bot_personas.push(persona);
+        self
+    }
+
+    pub fn with_fraud_rate(mut self, rate: f64) -> Self {
+        self.fraud_rate = rate;
+        self
+    }
+
+    pub fn with_ground_truth(mut self) -> Self {
+        self.ground_truth = true;
+        self
+    }
+
+    /// Volume multiplier for `date` (1.0 when no spike is configured).
+    pub fn traffic_multiplier(&self, date: NaiveDate) -> f64 {
+        self.traffic_spikes
+            .iter()
+            .filter(|(d, _)| *d == date)
+            .map(|(_, m)| m)
+            .product()
+    }
+
+    /// Whether sessions on `platform` are suppressed on `date`.
+    pub fn is_outage(&self, platform: Platform, date: NaiveDate) -> bool {
+        self.platform_outages
+            .iter()
+            .any(|o| o.platform == platform && o.start <= date && date <= o.end)
+    }
+
+    /// The bot visitors for `seed`; the same on every day.
+    pub fn bots(&self, seed: u64) -> Vec<Visitor> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(BOT_VISITOR_STREAM);
+        (0..self.bot_visitors)
+            .map(|_| Visitor {
+                id: uuid_gen().generate(&mut rng),
+                platform_preference: Platform::WebDesktop,
+                return_probability: 1.0,
+            })
+            .collect()
+    }
+
+    /// Each persona's bot visitors for `seed`; the same on every day.
+    pub fn persona_bots(&self, seed: u64) -> Vec<(&BotPersona, Vec<Visitor>)> {
+        self.bot_personas
+            .iter()
+            .enumerate()
+            .map(|(i, persona)| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(BOT_VISITOR_STREAM - 1 - i as u64);
+                let bots = (0..persona.visitors)
+                    .map(|_| Visitor {
+                        id: uuid_gen().generate(&mut rng),
+                        platform_preference: persona.platform,
+                        return_probability: 1.0,
+                    })
+                    .collect();
+                (persona, bots)
+            })
+            .collect()
+    }
+
+    /// Whether a session's purchases are fraudulent, if it has any.
+    pub fn is_fraud(&self, seed: u64, session_id: Uuid) -> bool {
+        self.fraud_factor(seed, session_id).is_some()
+    }
+
+    /// How many times over a fraudulent session's purchases are inflated.
+    fn fraud_factor(&self, seed: u64, session_id: Uuid) -> Option<i32> {
+        if self.fraud_rate <= 0.0 {
+            return None;
+        }
+        let mut rng = ChaCha8Rng::seed_from_u64(seed ^ low_bits(session_id));
+        rng.set_stream(FRAUD_STREAM);
+        rng.gen_bool(self.fraud_rate.min(1.0))
+            .then(|| rng.gen_range(3..=8))
+    }
+
+    /// Apply outages, fraud, bot traffic, and duplicates to one day's sessions.
+    pub fn apply(&self, seed: u64, day_seed: u64, date: NaiveDate, sessions: &mut Vec<Session>) {
+        sessions.retain(|s| !self.is_outage(s.platform, s.session_date));
+
+        for session in sessions.iter_mut().filter(|s| s.product_purchase_count > 0) {
+            if let Some(factor) = self.fraud_factor(seed, session.session_id) {
+                commit_fraud(session, factor);
+            }
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(day_seed);
+        rng.set_stream(DAY_ANOMALY_STREAM);
+
+        for bot in self.bots(seed) {
+            if self.is_outage(bot.platform_preference, date) {
+                continue;
+            }
+            for _ in 0..self.bot_sessions_per_day {
+                sessions.push(bot_session(&mut rng, &bot, date));
+            }
+        }
+        for (persona, bots) in self.persona_bots(seed) {
+            if self.is_outage(persona.platform, date) {
+                continue;
+            }
+            for bot in &bots {
+                for _ in 0..persona.sessions_per_day {
+                    sessions.push(persona.session(&mut rng, bot, date));
+                }
+            }
+        }
+
+        if self.duplicate_rate > 0.0 {
+            let mut with_duplicates = Vec::with_capacity(sessions.len());
+            for session in sessions.drain(..) {
+                if rng.gen_bool(self.duplicate_rate) {
+                    with_duplicates.push(session.clone());
+                }
+                with_duplicates.push(session);
+            }
+            *sessions = with_duplicates;
+        }
+    }
+
+    /// `batch` of sessions with [`GROUND_TRUTH_COLUMN`] added, and datacenter
+    /// bots' geo and device columns replaced. Unchanged unless
+    /// [`Self::ground_truth`] is set.
+    pub fn label(&self, seed: u64, batch: &RecordBatch) -> Result<RecordBatch> {
+        if !self.ground_truth {
+            return Ok(batch.clone());
+        }
+        let mut bots: HashMap<u64, (String, bool)> = self
+            .bots(seed)
+            .into_iter()
+            .map(|bot| (low_bits(bot.id), ("bot".to_string(), false)))
+            .collect();
+        for (persona, persona_bots) in self.persona_bots(seed) {
+            for bot in persona_bots {
+                let label = format!("bot:{}", persona.name);
+                bots.insert(low_bits(bot.id), (label, persona.datacenter));
+            }
+        }
+
+        let visitor_ids = string_column(batch, "visitor_id")?;
+        let session_ids = string_column(batch, "session_id")?;
+        let purchases = batch
+            .column_by_name("product_purchase_count")
+            .and_then(|c| c.as_any().downcast_ref::<Int32Array>())
+            .context("Ground truth needs a product_purchase_count column")?;
+
+        let mut labels = StringBuilder::new();
+        let mut datacenter = Vec::with_capacity(batch.num_rows());
+        for row in 0..batch.num_rows() {
+            let bot = parse_id(visitor_ids, row).and_then(|id| bots.get(&low_bits(id)));
+            let label = match bot {
+                Some((label, _)) => label.as_str(),
+                None if purchases.value(row) > 0
+                    && parse_id(session_ids, row).is_some_and(|id| self.is_fraud(seed, id)) =>
+                {
+                    "fraud"
+                }
+                None => "human",
+            };
+            labels.append_value(label);
+            datacenter.push(bot.is_some_and(|(_, datacenter)| *datacenter));
+        }
+
+        let mut fields: Vec<Field> = Vec::with_capacity(batch.num_columns() + 1);
+        let mut columns: Vec<ArrayRef> = Vec::with_capacity(batch.num_columns() + 1);
+        for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+            let replacement = DATACENTER_COLUMNS
+                .iter()
+                .find(|(name, _)| name == field.name())
+                .filter(|_| field.data_type() == &DataType::Utf8);
+            let column = match replacement {
+                Some((name, value)) => {
+                    let values = string_column(batch, name)?;
+                    let replaced: StringArray = (0..values.len())
+                        .map(|row| {
+                            if datacenter[row] {
+                                Some(*value)
+                            } else {
+                                values.is_valid(row).then(|| values.value(row))
+                            }
+                        })
+                        .collect();
+                    Arc::new(replaced) as ArrayRef
+                }
+                None => column.clone(),
+            };
+            fields.push(field.as_ref().clone());
+            columns.push(column);
+        }
+        fields.push(Field::new(GROUND_TRUTH_COLUMN, DataType::Utf8, false));
+        columns.push(Arc::new(labels.finish()));
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .context("Failed to add ground truth column")
+    }
+}
+
+/// The bits of an id that id formats keep.
+fn low_bits(id: Uuid) -> u64 {
+    id.as_u64_pair().1
+}
+
+/// Turn a session's purchases into a fraudulent burst: `factor` times the
+/// purchases and revenue, with the funnel above raised to match.
+fn commit_fraud(session: &mut Session, factor: i32) {
+    session.product_purchase_count *= factor;
+    session.product_revenue *= factor;
+    session.product_checkout_count = session
+        .product_checkout_count
+        .max(session.product_purchase_count);
+    session.product_add_to_cart_count = session
+        .product_add_to_cart_count
+        .max(session.product_checkout_count);
+    session.product_views = session.product_views.max(session.product_add_to_cart_count);
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        .with_context(|| format!("Ground truth needs a string {:?} column", name))
+}
+
+fn parse_id(ids: &StringArray, row: usize) -> Option<Uuid> {
+    ids.is_valid(row)
+        .then(|| Uuid::parse_str(ids.value(row)).ok())
+        .flatten()
+}
+
+/// A bot session: direct traffic, many views, never adds to cart.
+fn bot_session(rng: &mut ChaCha8Rng, bot: &Visitor, date: NaiveDate) -> Session {
+    Session {
+        visitor_id: bot.id,
+        session_id: uuid_gen().generate(rng),
+        platform: bot.platform_preference,
+        visit_source: VisitSource::Direct,
+        visit_campaign: None,
+        widget_views: rng.gen_range(50..200),
+        session_date: date,
+        product_views: rng.gen_range(20..100),
+        product_add_to_cart_count: 0,
+        product_checkout_count: 0,
+        product_category: ProductCategory::Electronics,
+        product_revenue: 0,
+        product_purchase_count: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrich::EnrichmentConfig;
+    use crate::parquet::{session_schema, sessions_to_record_batch};
+    use crate::session::{generate_day_seeds, DayGenerator, VisitorPool};
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, n).unwrap()
+    }
+
+    fn generate(anomalies: AnomalyConfig, date: NaiveDate) -> Vec<Session> {
+        let pool = VisitorPool::new(42, 10_000);
+        let day_seed = generate_day_seeds(42, 1)[0];
+        DayGenerator::new(pool, day_seed, date, 1_000)
+            .with_anomalies(Arc::new(anomalies), 42)
+            .generate()
+    }
+
+    #[test]
+    fn test_traffic_spike_multiplies_volume() {
+        let normal = generate(AnomalyConfig::new(), day(1));
+        let spiked = generate(AnomalyConfig::new().with_traffic_spike(day(1), 3.0), day(1));
+        assert!(spiked.len() >= normal.len() * 3);
+    }
+
+    #[test]
+    fn test_platform_outage_drops_sessions() {
+        let config = AnomalyConfig::new().with_platform_outage(Platform::Ios, day(1), day(2));
+        let sessions = generate(config, day(1));
+        assert!(!sessions.is_empty());
+        assert!(sessions.iter().all(|s| s.platform != Platform::Ios));
+    }
+
+    #[test]
+    fn test_duplicates_are_exact_copies() {
+        let sessions = generate(AnomalyConfig::new().with_duplicate_rate(0.1), day(1));
+        let duplicates = sessions
+            .windows(2)
+            .filter(|w| {
+                w[0].session_id == w[1].session_id && w[0].product_category == w[1].product_category
+            })
+            .count();
+        assert!(duplicates > 0);
+    }
+
+    #[test]
+    fn test_bots_are_stable_across_days() {
+        let config = AnomalyConfig::new().with_bot_visitors(3, 50);
+        let bots: HashSet<_> = config.bots(42).iter().map(|b| b.id).collect();
+
+        for date in [day(1), day(2)] {
+            let sessions = generate(config.clone(), date);
+            let bot_rows: Vec<_> = sessions
+                .iter()
+                .filter(|s| bots.contains(&s.visitor_id))
+                .collect();
+            assert_eq!(bot_rows.len(), 150);
+            assert!(bot_rows.iter().all(|s| s.product_purchase_count == 0));
+        }
+    }
+
+    #[test]
+    fn test_anomalies_leave_other_rows_unchanged() {
+        let normal = generate(AnomalyConfig::new(), day(1));
+        let with_bots = generate(
+            AnomalyConfig::new()
+                .with_bot_visitors(2, 10)
+                .with_bot_persona(BotPersona::scraper(2)),
+            day(1),
+        );
+        assert_eq!(&with_bots[..normal.len()], &normal[..]);
+    }
+
+    fn labels(config: &AnomalyConfig, sessions: &[Session]) -> (RecordBatch, Vec<String>) {
+        let batch = sessions_to_record_batch(sessions, &Arc::new(session_schema())).unwrap();
+        let batch = EnrichmentConfig::new().apply(&batch).unwrap();
+        let labelled = config.label(42, &batch).unwrap();
+        let labels = string_column(&labelled, GROUND_TRUTH_COLUMN)
+            .unwrap()
+            .iter()
+            .map(|l| l.unwrap().to_string())
+            .collect();
+        (labelled, labels)
+    }
+
+    #[test]
+    fn test_persona_bots_are_labelled() {
+        let config = AnomalyConfig::new()
+            .with_bot_visitors(1, 5)
+            .with_bot_persona(BotPersona::scraper(2))
+            .with_bot_persona(BotPersona::cart_hoarder(1).with_name("hoarder"))
+            .with_ground_truth();
+        let sessions = generate(config.clone(), day(1));
+        let (batch, labels) = labels(&config, &sessions);
+
+        let count = |label: &str| labels.iter().filter(|l| *l == label).count();
+        assert_eq!(count("bot"), 5);
+        assert_eq!(count("bot:scraper"), 80);
+        assert_eq!(count("bot:hoarder"), 10);
+        assert_eq!(count("human") + 95, sessions.len());
+
+        let cities = string_column(&batch, "city").unwrap();
+        for (row, label) in labels.iter().enumerate() {
+            let session = &sessions[row];
+            match label.as_str() {
+                "bot:scraper" => assert_eq!(cities.value(row), "Ashburn"),
+                "bot:hoarder" => {
+                    assert_eq!(session.product_add_to_cart_count, session.product_views);
+                    assert_eq!(session.product_checkout_count, 0);
+                }
+                _ => {}
+            }
+        }
+        assert!(
+            (0..labels.len()).any(|row| labels[row] == "human" && cities.value(row) != "Ashburn")
+        );
+        let ids = |config: &AnomalyConfig| -> Vec<Uuid> {
+            config.persona_bots(42)[0].1.iter().map(|b| b.id).collect()
+        };
+        assert_eq!(ids(&config), ids(&config));
+        assert!(!ids(&config).contains(&config.bots(42)[0].id));
+    }
+
+    #[test]
+    fn test_fraud_inflates_purchases_and_is_labelled() {
+        let normal = generate(AnomalyConfig::new(), day(1));
+        let config = AnomalyConfig::new()
+            .with_fraud_rate(0.2)
+            .with_ground_truth();
+        let sessions = generate(config.clone(), day(1));
+        let (_, labels) = labels(&config, &sessions);
+        assert_eq!(sessions.len(), normal.len());
+
+        let buyers = normal
+            .iter()
+            .filter(|s| s.product_purchase_count > 0)
+            .count();
+        let mut frauds = 0;
+        for ((before, after), label) in normal.iter().zip(&sessions).zip(&labels) {
+            assert_eq!(before.session_id, after.session_id);
+            if label == "fraud" {
+                frauds += 1;
+                assert!(after.product_purchase_count >= before.product_purchase_count * 3);
+                assert!(after.product_checkout_count >= after.product_purchase_count);
+            } else {
+                assert_eq!(label, "human");
+                assert_eq!(before, after);
+            }
+        }
+        let rate = frauds as f64 / buyers as f64;
+        assert!((0.1..0.3).contains(&rate), "fraud rate {}", rate);
+    }
+
+    #[test]
+    fn test_label_without_ground_truth_is_unchanged() {
+        let config = AnomalyConfig::new().with_bot_persona(BotPersona::crawler(1));
+        let sessions = generate(config.clone(), day(1));
+        let batch = sessions_to_record_batch(&sessions, &Arc::new(session_schema())).unwrap();
+        assert_eq!(config.label(42, &batch).unwrap(), batch);
+    }
+}
@@ -0,0 +1,206 @@
+//! Marketing touchpoints leading up to conversions.
+//!
+//! Every session with purchases is a conversion, reached through a path of
+//! campaign and channel touches within a lookback window. The last touch is
+//! the converting session's own source and campaign; earlier touches lean
+//! towards awareness channels and cluster close to the conversion, so
+//! first-touch, last-touch, and position-based models disagree the way they
+//! do on real traffic.
+
+use crate::gen::Gen;
+use crate::generators::{uuid_gen, weighted_choice, WeightedChoice};
+use crate::session::{campaign_gen, Session, VisitSource};
+use chrono::{Duration, NaiveDate};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// RNG stream for touchpoints, clear of the other per-day streams.
+const ATTRIBUTION_STREAM: u64 = 0x600;
+
+/// One marketing touch on a visitor's path to a conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Touchpoint {
+    pub touchpoint_id: Uuid,
+    pub visitor_id: Uuid,
+    pub conversion_session_id: Uuid,
+    pub conversion_date: NaiveDate,
+    pub touch_date: NaiveDate,
+    /// 1-based position in the path, in date order.
+    pub position: i32,
+    /// Touches in the whole path, including the converting one.
+    pub path_length: i32,
+    pub channel: VisitSource,
+    pub campaign: Option<String>,
+}
+
+impl Touchpoint {
+    pub fn days_before_conversion(&self) -> i32 {
+        (self.conversion_date - self.touch_date).num_days() as i32
+    }
+}
+
+/// Shape of conversion paths.
+#[derive(Debug, Clone)]
+pub struct AttributionConfig {
+    /// Earlier touches fall at most this many days before the conversion.
+    pub lookback_days: i64,
+    /// Chance of each further touch before the ones already on the path.
+    pub prior_touch_rate: f64,
+    /// Longest path, including the converting touch.
+    pub max_touches: i32,
+}
+
+impl Default for AttributionConfig {
+    fn default() -> Self {
+        Self {
+            lookback_days: 30,
+            prior_touch_rate: 0.6,
+            max_touches: 8,
+        }
+    }
+}
+
+impl AttributionConfig {
+    /// Touchpoints for one day's conversions, from `day_seed`.
+    pub fn generate(&self, day_seed: u64, sessions: &[Session]) -> Vec<Touchpoint> {
+        let mut rng = ChaCha8Rng::seed_from_u64(day_seed);
+        rng.set_stream(ATTRIBUTION_STREAM);
+        let uuid_g = uuid_gen();
+        let channel_g = prior_channel_gen();
+        let campaign_g = campaign_gen();
+        let lookback = self.lookback_days.max(1);
+
+        let mut converted = HashSet::new();
+        let mut touchpoints = Vec::new();
+        for session in sessions {
+            // Sessions spanning several categories share an id; convert once.
+            if session.product_purchase_count == 0 || !converted.insert(session.session_id) {
+                continue;
+            }
+
+            let mut days_before = Vec::new();
+            while (days_before.len() as i32) < self.max_touches - 1
+                && rng.gen_bool(self.prior_touch_rate)
+            {
+                // Squaring skews touches towards the conversion.
+                let u: f64 = rng.gen();
+                days_before.push(1 + (u * u * lookback as f64) as i64 % lookback);
+            }
+            days_before.sort_unstable_by(|a, b| b.cmp(a));
+
+            let path_length = days_before.len() as i32 + 1;
+            let touch = |touchpoint_id, position, days: i64, channel, campaign| Touchpoint {
+                touchpoint_id,
+                visitor_id: session.visitor_id,
+                conversion_session_id: session.session_id,
+                conversion_date: session.session_date,
+                touch_date: session.session_date - Duration::days(days),
+                position,
+                path_length,
+                channel,
+                campaign,
+            };
+            for (i, days) in days_before.iter().enumerate() {
+                let channel = channel_g.generate(&mut rng);
+                let campaign = channel
+                    .has_campaign()
+                    .then(|| campaign_g.generate(&mut rng));
+                let id = uuid_g.generate(&mut rng);
+                touchpoints.push(touch(id, i as i32 + 1, *days, channel, campaign));
+            }
+            touchpoints.push(touch(
+                uuid_g.generate(&mut rng),
+                path_length,
+                0,
+                session.visit_source,
+                session.visit_campaign.clone(),
+            ));
+        }
+        touchpoints
+    }
+}
+
+/// Channels for touches before the converting one: more social and
+/// affiliate awareness, less direct.
+fn prior_channel_gen() -> WeightedChoice<VisitSource> {
+    weighted_choice(vec![
+        (VisitSource::Seo, 0.22),
+        (VisitSource::Social, 0.18),
+        (VisitSource::Sem, 0.15),
+        (VisitSource::OrganicSocial, 0.12),
+        (VisitSource::Affiliate, 0.10),
+        (VisitSource::Email, 0.10),
+        (VisitSource::Referral, 0.08),
+        (VisitSource::Direct, 0.05),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{generate_day_seeds, DayGenerator, VisitorPool};
+    use std::collections::HashMap;
+
+    fn day() -> (u64, Vec<Session>) {
+        let day_seed = generate_day_seeds(42, 1)[0];
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sessions =
+            DayGenerator::new(VisitorPool::new(42, 10_000), day_seed, date, 2_000).generate();
+        (day_seed, sessions)
+    }
+
+    #[test]
+    fn test_paths_end_in_the_converting_session() {
+        let (day_seed, sessions) = day();
+        let config = AttributionConfig::default();
+        let touchpoints = config.generate(day_seed, &sessions);
+
+        let conversions: HashSet<_> = sessions
+            .iter()
+            .filter(|s| s.product_purchase_count > 0)
+            .map(|s| s.session_id)
+            .collect();
+        let mut paths: HashMap<Uuid, Vec<&Touchpoint>> = HashMap::new();
+        for touch in &touchpoints {
+            paths
+                .entry(touch.conversion_session_id)
+                .or_default()
+                .push(touch);
+        }
+        assert_eq!(paths.len(), conversions.len());
+
+        for path in paths.values() {
+            assert_eq!(path.len() as i32, path[0].path_length);
+            assert!(path.len() as i32 <= config.max_touches);
+            for (i, touch) in path.iter().enumerate() {
+                assert_eq!(touch.position, i as i32 + 1);
+                assert!((0..=config.lookback_days as i32).contains(&touch.days_before_conversion()));
+            }
+            assert!(path.windows(2).all(|w| w[0].touch_date <= w[1].touch_date));
+
+            let last = path.last().unwrap();
+            let session = sessions
+                .iter()
+                .find(|s| s.session_id == last.conversion_session_id)
+                .unwrap();
+            assert_eq!(last.days_before_conversion(), 0);
+            assert_eq!(last.channel, session.visit_source);
+            assert_eq!(last.campaign, session.visit_campaign);
+        }
+        assert!(paths.values().any(|p| p.len() > 1));
+    }
+
+    #[test]
+    fn test_single_touch_paths() {
+        let (day_seed, sessions) = day();
+        let config = AttributionConfig {
+            prior_touch_rate: 0.0,
+            ..AttributionConfig::default()
+        };
+        let touchpoints = config.generate(day_seed, &sessions);
+        assert!(!touchpoints.is_empty());
+        assert!(touchpoints.iter().all(|t| t.path_length == 1));
+    }
+}
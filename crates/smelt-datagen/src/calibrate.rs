@@ -0,0 +1,480 @@
+//! Fitting the generator's distributions to an existing dataset.
+//!
+//! Reads a sessions table shaped like the generated one (from Parquet or
+//! CSV, through DuckDB) and fits the funnel rates and [`TrafficShape`], so a
+//! scenario can mimic production traffic without copying any of its rows.
+//! Only aggregates leave the source data.
+
+use crate::funnel::Funnel;
+use crate::naming::SchemaConfig;
+use crate::output::OutputOptions;
+use crate::scenario::TestDataConfig;
+use crate::session::{Platform, ProductCategory, VisitSource};
+use crate::shape::TrafficShape;
+use crate::sink::quote_ident;
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const PLATFORMS: [Platform; 4] = [
+    Platform::WebDesktop,
+    Platform::Android,
+    Platform::Ios,
+    Platform::WebMobile,
+];
+
+const VISIT_SOURCES: [VisitSource; 8] = [
+    VisitSource::Seo,
+    VisitSource::Sem,
+    VisitSource::Direct,
+    VisitSource::Referral,
+    VisitSource::Affiliate,
+    VisitSource::Email,
+    VisitSource::Social,
+    VisitSource::OrganicSocial,
+];
+
+const CATEGORIES: [ProductCategory; 6] = [
+    ProductCategory::Electronics,
+    ProductCategory::Clothing,
+    ProductCategory::Home,
+    ProductCategory::Sports,
+    ProductCategory::Beauty,
+    ProductCategory::Food,
+];
+
+/// Distributions fitted from a sessions dataset.
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Rows in the dataset.
+    pub sessions: usize,
+    pub start_date: NaiveDate,
+    pub days: u32,
+    pub funnel: Funnel,
+    pub shape: TrafficShape,
+}
+
+impl Calibration {
+    /// Fit to the sessions at `path`: a `.csv` file, a Parquet file, or a
+    /// directory of (possibly Hive-partitioned) Parquet files. Columns are
+    /// looked up by their generated names, renamed by `naming`.
+    pub fn from_path(path: &Path, naming: &SchemaConfig) -> Result<Self> {
+        let conn = duckdb::Connection::open_in_memory()?;
+        let location = path.to_string_lossy().replace('\'', "''");
+        let source = if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+        {
+            format!("read_csv_auto('{}')", location)
+        } else if path.is_dir() {
+            format!(
+                "read_parquet('{}/**/*.parquet', hive_partitioning = true)",
+                location
+            )
+        } else {
+            format!("read_parquet('{}')", location)
+        };
+        Self::fit(&conn, &source, naming)
+            .with_context(|| format!("Failed to calibrate from {:?}", path))
+    }
+
+    /// Fit to the sessions in `relation`, any DuckDB table expression.
+    pub fn fit(conn: &duckdb::Connection, relation: &str, naming: &SchemaConfig) -> Result<Self> {
+        let col = |name: &str| quote_ident(naming.column_name("sessions", name));
+        conn.execute_batch(&format!(
+            "CREATE OR REPLACE TEMP VIEW calibration_source AS SELECT \
+               CAST({} AS VARCHAR) AS session_id, CAST({} AS VARCHAR) AS visitor_id, \
+               CAST({} AS DATE) AS session_date, {} AS platform, {} AS visit_source, \
+               {} AS product_category, {} AS widget_views, {} AS product_views, \
+               {} AS add_to_cart, {} AS checkout, {} AS purchase \
+             FROM {}",
+            col("session_id"),
+            col("visitor_id"),
+            col("session_date"),
+            col("platform"),
+            col("visit_source"),
+            col("product_category"),
+            col("widget_views"),
+            col("product_views"),
+            col("product_add_to_cart_count"),
+            col("product_checkout_count"),
+            col("product_purchase_count"),
+            relation,
+        ))
+        .context("Source doesn't have the sessions columns")?;
+
+        let (rows, start_date, end_date, views, carts, checkouts, purchases): (
+            i64,
+            Option<String>,
+            Option<String>,
+            f64,
+            f64,
+            f64,
+            f64,
+        ) = conn.query_row(
+            "SELECT count(*), min(session_date)::VARCHAR, max(session_date)::VARCHAR, \
+               coalesce(sum(product_views), 0)::DOUBLE, coalesce(sum(add_to_cart), 0)::DOUBLE, \
+               coalesce(sum(checkout), 0)::DOUBLE, coalesce(sum(purchase), 0)::DOUBLE \
+             FROM calibration_source",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        )?;
+        let (Some(start_date), Some(end_date)) = (start_date, end_date) else {
+            bail!("No sessions to calibrate from");
+        };
+        let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")?;
+        let end_date = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")?;
+        let days = (end_date - start_date).num_days() as u32 + 1;
+
+        let defaults = Funnel::default();
+        let rate = |num: f64, den: f64, default: f64| {
+            if den > 0.0 {
+                (num / den).clamp(0.0, 1.0)
+            } else {
+                default
+            }
+        };
+        let funnel = Funnel::new(
+            rate(carts, views, defaults.add_to_cart),
+            rate(checkouts, carts, defaults.checkout),
+            rate(purchases, checkouts, defaults.purchase),
+        );
+
+        let defaults = TrafficShape::default();
+        let platforms = mix(
+            conn,
+            "platform",
+            "count(DISTINCT session_id)",
+            &PLATFORMS,
+            |p| p.as_str(),
+        )?
+        .unwrap_or(defaults.platforms);
+        let visit_sources = mix(
+            conn,
+            "visit_source",
+            "count(DISTINCT session_id)",
+            &VISIT_SOURCES,
+            |s| s.as_str(),
+        )?
+        .unwrap_or(defaults.visit_sources);
+        let categories = mix(conn, "product_category", "count(*)", &CATEGORIES, |c| {
+            c.as_str()
+        })?
+        .unwrap_or(defaults.categories);
+
+        // Widget views are a floored log-normal: fit the median and spread
+        // from quartiles, which the flooring and the cap barely move.
+        let (q25, q50, q75): (Option<f64>, Option<f64>, Option<f64>) = conn.query_row(
+            "SELECT quantile_cont(w, 0.25), quantile_cont(w, 0.5), quantile_cont(w, 0.75) \
+             FROM (SELECT max(widget_views)::DOUBLE + 0.5 AS w \
+                   FROM calibration_source GROUP BY session_id)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let (widget_views_median, widget_views_sigma) = match (q25, q50, q75) {
+            (Some(q25), Some(q50), Some(q75)) => (q50, (q75.ln() - q25.ln()) / 1.349),
+            _ => (defaults.widget_views_median, defaults.widget_views_sigma),
+        };
+
+        // How often visitors return also depends on how the generator fills
+        // each day, so fit the exponent by simulation: generate a sample at
+        // each candidate and keep the one whose spread of active days per
+        // visitor is closest to the source's.
+        let dispersion: Option<f64> = conn.query_row(
+            "SELECT stddev_pop(active_days) / avg(active_days) FROM \
+               (SELECT count(DISTINCT session_date)::DOUBLE AS active_days \
+                FROM calibration_source GROUP BY visitor_id)",
+            [],
+            |row| row.get(0),
+        )?;
+        let return_exponent = match dispersion {
+            Some(target) => fit_return_exponent(target, rows as usize, start_date, days)?,
+            None => defaults.return_exponent,
+        };
+
+        let shape = TrafficShape {
+            platforms,
+            visit_sources,
+            categories,
+            widget_views_median,
+            widget_views_sigma,
+            return_exponent,
+        };
+        shape.validate()?;
+
+        Ok(Self {
+            sessions: rows as usize,
+            start_date,
+            days,
+            funnel,
+            shape,
+        })
+    }
+
+    /// A scenario reproducing the fitted shape, writing Parquet to `output`.
+    pub fn to_yaml(&self, seed: u64, output: &Path) -> String {
+        fn weights<T>(items: &[(T, f64)], name: impl Fn(&T) -> &'static str) -> String {
+            let items: Vec<_> = items
+                .iter()
+                .map(|(item, weight)| format!("{}: {:.4}", name(item), weight))
+                .collect();
+            format!("{{ {} }}", items.join(", "))
+        }
+
+        let mut yaml = String::new();
+        let _ = writeln!(yaml, "seed: {}", seed);
+        let _ = writeln!(yaml, "sessions: {}", self.sessions);
+        let _ = writeln!(yaml, "start_date: {}", self.start_date);
+        let _ = writeln!(yaml, "days: {}", self.days);
+        let _ = writeln!(
+            yaml,
+            "funnel: {{ add_to_cart: {:.4}, checkout: {:.4}, purchase: {:.4} }}",
+            self.funnel.add_to_cart, self.funnel.checkout, self.funnel.purchase
+        );
+        let _ = writeln!(yaml, "shape:");
+        let _ = writeln!(
+            yaml,
+            "  platforms: {}",
+            weights(&self.shape.platforms, |p| p.as_str())
+        );
+        let _ = writeln!(
+            yaml,
+            "  visit_sources: {}",
+            weights(&self.shape.visit_sources, |s| s.as_str())
+        );
+        let _ = writeln!(
+            yaml,
+            "  categories: {}",
+            weights(&self.shape.categories, |c| c.as_str())
+        );
+        let _ = writeln!(
+            yaml,
+            "  widget_views_median: {:.4}",
+            self.shape.widget_views_median
+        );
+        let _ = writeln!(
+            yaml,
+            "  widget_views_sigma: {:.4}",
+            self.shape.widget_views_sigma
+        );
+        let _ = writeln!(yaml, "  return_exponent: {:.4}", self.shape.return_exponent);
+        let _ = writeln!(yaml, "outputs:");
+        let _ = writeln!(
+            yaml,
+            "  - {{ format: parquet, path: {:?} }}",
+            output.to_string_lossy()
+        );
+        yaml
+    }
+
+    /// The scenario [`Self::to_yaml`] describes.
+    pub fn to_config(&self, seed: u64, output: &Path) -> Result<TestDataConfig> {
+        TestDataConfig::from_yaml(&self.to_yaml(seed, output))
+    }
+}
+
+/// Most sessions generated per candidate when fitting the return exponent.
+const SIMULATED_SESSIONS: usize = 20_000;
+
+/// Candidate return exponents, a quarter to sixteen in octave steps.
+fn return_exponents() -> impl Iterator<Item = f64> {
+    (0..=6).map(|i| 0.25 * 2f64.powi(i))
+}
+
+/// The candidate exponent whose simulated dispersion of active days per
+/// visitor is closest to `target`.
+///
+/// Until the pool is large next to a day's sessions, the generator's daily
+/// top-up of random visitors swamps return visits, so this is the weakest
+/// of the fits: it tells heavy-tailed from flat traffic, not much more.
+fn fit_return_exponent(
+    target: f64,
+    sessions: usize,
+    start_date: NaiveDate,
+    days: u32,
+) -> Result<f64> {
+    let mut best = (f64::INFINITY, TrafficShape::default().return_exponent);
+    for return_exponent in return_exponents() {
+        let dispersion = active_day_dispersion(return_exponent, sessions, start_date, days)?;
+        let distance = (dispersion - target).abs();
+        if distance < best.0 {
+            best = (distance, return_exponent);
+        }
+    }
+    Ok(best.1)
+}
+
+/// Coefficient of variation of active days per visitor in a sample
+/// generated with `return_exponent`.
+fn active_day_dispersion(
+    return_exponent: f64,
+    sessions: usize,
+    start_date: NaiveDate,
+    days: u32,
+) -> Result<f64> {
+    let sessions = sessions.min(SIMULATED_SESSIONS);
+    let options = OutputOptions {
+        shape: Some(Arc::new(TrafficShape {
+            return_exponent,
+            ..TrafficShape::default()
+        })),
+        ..OutputOptions::default()
+    };
+    let mut active_days: HashMap<Uuid, f64> = HashMap::new();
+    options.for_each_day(
+        &options.visitor_pool(0, sessions, start_date),
+        0,
+        sessions,
+        days,
+        start_date,
+        |_, _, day| {
+            let visitors: HashSet<_> = day.iter().map(|s| s.visitor_id).collect();
+            for visitor_id in visitors {
+                *active_days.entry(visitor_id).or_default() += 1.0;
+            }
+            Ok(())
+        },
+    )?;
+    let n = active_days.len() as f64;
+    let mean = active_days.values().sum::<f64>() / n;
+    let variance = active_days
+        .values()
+        .map(|d| (d - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    Ok(variance.sqrt() / mean)
+}
+
+/// Share of `measure` for each known value of `column`, or `None` when no
+/// row has a known value.
+fn mix<T: Copy>(
+    conn: &duckdb::Connection,
+    column: &str,
+    measure: &str,
+    values: &[T],
+    name: impl Fn(&T) -> &'static str,
+) -> Result<Option<Vec<(T, f64)>>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST({column} AS VARCHAR), {measure}::DOUBLE FROM calibration_source GROUP BY 1"
+    ))?;
+    let counts = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, Option<String>>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<duckdb::Result<Vec<_>>>()?;
+
+    let count = |value: &T| {
+        counts
+            .iter()
+            .filter(|(key, _)| key.as_deref() == Some(name(value)))
+            .map(|(_, n)| n)
+            .sum::<f64>()
+    };
+    let total: f64 = values.iter().map(count).sum();
+    if total == 0.0 {
+        return Ok(None);
+    }
+    Ok(Some(
+        values
+            .iter()
+            .map(|value| (*value, count(value) / total))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::OutputBuilder;
+    use crate::parquet::ParquetOutput;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    fn calibrate(output: ParquetOutput) -> Calibration {
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        output
+            .write_days(temp_dir.path(), 42, 20_000, 10, start_date, None)
+            .unwrap();
+        Calibration::from_path(temp_dir.path(), &SchemaConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_recovers_generated_shape() {
+        let shape = TrafficShape {
+            platforms: vec![(Platform::Android, 0.7), (Platform::Ios, 0.3)],
+            widget_views_median: 12.0,
+            widget_views_sigma: 0.5,
+            ..TrafficShape::default()
+        };
+        let calibration = calibrate(
+            ParquetOutput::new()
+                .with_funnel(Funnel::new(0.5, 0.4, 0.9))
+                .with_shape(shape),
+        );
+
+        assert_eq!(calibration.days, 10);
+        assert!((calibration.funnel.add_to_cart - 0.5).abs() < 0.02);
+        assert!((calibration.funnel.checkout - 0.4).abs() < 0.02);
+        assert!((calibration.funnel.purchase - 0.9).abs() < 0.02);
+
+        let share = |p| {
+            calibration
+                .shape
+                .platforms
+                .iter()
+                .find(|(platform, _)| *platform == p)
+                .unwrap()
+                .1
+        };
+        assert!((share(Platform::Android) - 0.7).abs() < 0.03);
+        assert_eq!(share(Platform::WebDesktop), 0.0);
+        assert!((calibration.shape.widget_views_median - 12.0).abs() < 1.0);
+        assert!((calibration.shape.widget_views_sigma - 0.5).abs() < 0.1);
+
+        let config = calibration
+            .to_config(7, Path::new("calibrated/sessions"))
+            .unwrap();
+        assert_eq!(config.sessions, calibration.sessions);
+        assert_eq!(config.num_days(), 10);
+    }
+
+    #[test]
+    fn test_calibrates_from_csv() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sessions.csv");
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        crate::text::CsvOutput::new()
+            .write_days(File::create(&path).unwrap(), 42, 5_000, 5, start_date)
+            .unwrap();
+
+        let calibration = Calibration::from_path(&path, &SchemaConfig::default()).unwrap();
+        assert_eq!(calibration.start_date, start_date);
+        assert_eq!(calibration.days, 5);
+        assert!((calibration.funnel.add_to_cart - Funnel::default().add_to_cart).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_return_exponent_fit_is_self_consistent() {
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let target = active_day_dispersion(16.0, 10_000, start_date, 10).unwrap();
+        assert_eq!(
+            fit_return_exponent(target, 10_000, start_date, 10).unwrap(),
+            16.0
+        );
+    }
+}
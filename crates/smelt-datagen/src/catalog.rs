@@ -0,0 +1,192 @@
+//! Product catalog with heavy-tailed popularity.
+//!
+//! Each session row references a catalog item from its product category.
+//! Item popularity within a category follows a Zipf distribution, so a few
+//! items dominate and `GROUP BY product_id` shows realistic skew.
+
+use crate::session::ProductCategory;
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, Int64Builder, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Zipf};
+use std::sync::Arc;
+
+/// RNG stream for choosing items per row, clear of the other injected-data streams.
+const CATALOG_STREAM: u64 = 0x400;
+
+const CATEGORIES: [ProductCategory; 6] = [
+    ProductCategory::Electronics,
+    ProductCategory::Clothing,
+    ProductCategory::Home,
+    ProductCategory::Sports,
+    ProductCategory::Beauty,
+    ProductCategory::Food,
+];
+
+/// A catalog item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogItem {
+    pub product_id: i64,
+    pub category: ProductCategory,
+    pub name: String,
+    /// Price in cents, varied around the category's average.
+    pub price: i32,
+}
+
+/// Catalog size and skew.
+#[derive(Debug, Clone)]
+pub struct CatalogConfig {
+    /// Items per category.
+    pub items_per_category: usize,
+    /// Zipf exponent; higher means more concentrated popularity.
+    pub exponent: f64,
+}
+
+impl Default for CatalogConfig {
+    fn default() -> Self {
+        Self {
+            items_per_category: 1_000,
+            exponent: 1.1,
+        }
+    }
+}
+
+/// A generated catalog, with items grouped by category in popularity order.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    /// `by_category[c][rank]`, most popular first.
+    by_category: Vec<Vec<CatalogItem>>,
+    zipf: Zipf<f64>,
+}
+
+impl Catalog {
+    /// Generate a catalog from `seed`.
+    pub fn generate(seed: u64, config: &CatalogConfig) -> Result<Self> {
+        if config.items_per_category == 0 {
+            bail!("Catalog needs at least one item per category");
+        }
+        let zipf = Zipf::new(config.items_per_category as u64, config.exponent)
+            .map_err(|e| anyhow::anyhow!("Invalid catalog exponent: {:?}", e))?;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(CATALOG_STREAM);
+        let mut next_id = 1;
+        let by_category = CATEGORIES
+            .iter()
+            .map(|&category| {
+                (0..config.items_per_category)
+                    .map(|rank| {
+                        let product_id = next_id;
+                        next_id += 1;
+                        let price_factor = rng.gen_range(0.3..2.0);
+                        CatalogItem {
+                            product_id,
+                            category,
+                            name: format!("{} item {}", category.as_str(), rank + 1),
+                            price: (category.avg_price() as f64 * price_factor) as i32,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self { by_category, zipf })
+    }
+
+    /// All items, grouped by category.
+    pub fn items(&self) -> impl Iterator<Item = &CatalogItem> {
+        self.by_category.iter().flatten()
+    }
+
+    /// Choose an item in `category`, weighted by popularity.
+    pub fn sample(&self, rng: &mut impl Rng, category: ProductCategory) -> &CatalogItem {
+        let items = &self.by_category[CATEGORIES.iter().position(|&c| c == category).unwrap()];
+        let rank = self.zipf.sample(rng) as usize - 1;
+        &items[rank.min(items.len() - 1)]
+    }
+
+    /// Append a `product_id` column referencing an item in each row's
+    /// `product_category`, chosen deterministically from `seed`.
+    pub fn apply(&self, batch: &RecordBatch, seed: u64) -> Result<RecordBatch> {
+        let categories = batch
+            .column_by_name("product_category")
+            .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+            .context("Catalog needs a string \"product_category\" column")?;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(CATALOG_STREAM);
+        let mut product_ids = Int64Builder::new();
+        for row in 0..categories.len() {
+            let name = categories.value(row);
+            let category = CATEGORIES
+                .into_iter()
+                .find(|c| c.as_str() == name)
+                .with_context(|| format!("Unknown product category {:?}", name))?;
+            product_ids.append_value(self.sample(&mut rng, category).product_id);
+        }
+
+        let mut fields: Vec<Field> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.as_ref().clone())
+            .collect();
+        fields.push(Field::new("product_id", DataType::Int64, false));
+        let mut columns: Vec<ArrayRef> = batch.columns().to_vec();
+        columns.push(Arc::new(product_ids.finish()));
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .context("Failed to add product_id column")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_popularity_is_skewed() {
+        let catalog = Catalog::generate(42, &CatalogConfig::default()).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for _ in 0..10_000 {
+            let item = catalog.sample(&mut rng, ProductCategory::Home);
+            assert_eq!(item.category, ProductCategory::Home);
+            *counts.entry(item.product_id).or_default() += 1;
+        }
+
+        let mut counts: Vec<_> = counts.into_values().collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        let top_ten: usize = counts.iter().take(10).sum();
+        // Top 1% of items draw a large share of views
+        assert!(top_ten > 3_000, "top ten items had {} views", top_ten);
+    }
+
+    #[test]
+    fn test_product_ids_are_unique() {
+        let catalog = Catalog::generate(
+            42,
+            &CatalogConfig {
+                items_per_category: 10,
+                exponent: 1.0,
+            },
+        )
+        .unwrap();
+        let ids: Vec<_> = catalog.items().map(|i| i.product_id).collect();
+        assert_eq!(ids, (1..=60).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = CatalogConfig {
+            items_per_category: 0,
+            exponent: 1.0,
+        };
+        assert!(Catalog::generate(42, &config).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Null and malformed-value injection.
+//!
+//! Applied to Arrow batches just before they're written, so it can produce
+//! values the typed [`Session`](crate::Session) can't hold: unparseable dates,
+//! enum strings outside the known set, and negative counts or revenue.
+//! Duplicate rows are injected earlier, via
+//! [`AnomalyConfig::duplicate_rate`](crate::AnomalyConfig).
+
+use anyhow::{bail, Result};
+use arrow::array::{Array, ArrayRef, Float64Array, Int32Array, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Offset for per-column RNG streams, clear of the anomaly streams.
+const DIRTY_STREAM_BASE: u64 = 0x100;
+
+/// Malformed values for date-like string columns.
+const BAD_DATES: &[&str] = &["2024-02-30", "0000-00-00", "not-a-date", ""];
+
+/// Per-column rates of nulls and malformed values.
+#[derive(Debug, Clone, Default)]
+pub struct DirtyDataConfig {
+    pub null_rates: BTreeMap<String, f64>,
+    pub malformed_rates: BTreeMap<String, f64>,
+}
+
+impl DirtyDataConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace this fraction of `column`'s values with null.
+    pub fn with_null_rate(mut self, column: impl Into<String>, rate: f64) -> Self {
+        self.null_rates.insert(column.into(), rate);
+        self
+    }
+
+    /// Replace this fraction of `column`'s values with malformed ones.
+    ///
+    /// String columns named `*_date` get invalid dates, other string columns
+    /// get values outside their usual set, and numeric columns are negated.
+    pub fn with_malformed_rate(mut self, column: impl Into<String>, rate: f64) -> Self {
+        self.malformed_rates.insert(column.into(), rate);
+        self
+    }
+
+    /// Corrupt `batch`, placing values deterministically from `seed`.
+    ///
+    /// Columns that are configured but not in the batch are ignored, since the
+    /// same config is used for outputs with different columns (Parquet keeps
+    /// `session_date` in the partition path rather than the file).
+    pub fn apply(&self, batch: &RecordBatch, seed: u64) -> Result<RecordBatch> {
+        let schema = batch.schema();
+        let mut fields = Vec::with_capacity(schema.fields().len());
+        let mut columns = Vec::with_capacity(batch.num_columns());
+
+        for (idx, field) in schema.fields().iter().enumerate() {
+            let null_rate = self.null_rates.get(field.name()).copied().unwrap_or(0.0);
+            let malformed_rate = self
+                .malformed_rates
+                .get(field.name())
+                .copied()
+                .unwrap_or(0.0);
+            if null_rate <= 0.0 && malformed_rate <= 0.0 {
+                fields.push(field.as_ref().clone());
+                columns.push(batch.column(idx).clone());
+                continue;
+            }
+
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(DIRTY_STREAM_BASE + idx as u64);
+            let column = corrupt_column(
+                &mut rng,
+                field,
+                batch.column(idx).as_ref(),
+                null_rate,
+                malformed_rate,
+            )?;
+            fields.push(field.as_ref().clone().with_nullable(true));
+            columns.push(column);
+        }
+
+        Ok(RecordBatch::try_new(
+            Arc::new(Schema::new(fields)),
+            columns,
+        )?)
+    }
+}
+
+/// What to do with a single value.
+enum Corruption {
+    Keep,
+    Null,
+    Malformed,
+}
+
+fn pick(rng: &mut ChaCha8Rng, null_rate: f64, malformed_rate: f64) -> Corruption {
+    let r: f64 = rng.gen();
+    if r < null_rate {
+        Corruption::Null
+    } else if r < null_rate + malformed_rate {
+        Corruption::Malformed
+    } else {
+        Corruption::Keep
+    }
+}
+
+fn corrupt_column(
+    rng: &mut ChaCha8Rng,
+    field: &Field,
+    column: &dyn Array,
+    null_rate: f64,
+    malformed_rate: f64,
+) -> Result<ArrayRef> {
+    match field.data_type() {
+        DataType::Utf8 => {
+            let values = column.as_any().downcast_ref::<StringArray>().unwrap();
+            let is_date = field.name().ends_with("_date");
+            let mut builder = StringBuilder::new();
+            for value in values.iter() {
+                match (pick(rng, null_rate, malformed_rate), value) {
+                    (Corruption::Null, _) | (_, None) => builder.append_null(),
+                    (Corruption::Malformed, Some(_)) if is_date => {
+                        builder.append_value(BAD_DATES.choose(rng).unwrap())
+                    }
+                    (Corruption::Malformed, Some(v)) => {
+                        builder.append_value(malformed_string(rng, v))
+                    }
+                    (Corruption::Keep, Some(v)) => builder.append_value(v),
+                }
+            }
+            Ok(Arc::new(builder.finish()))
+        }
+        DataType::Int32 => {
+            let values = column.as_any().downcast_ref::<Int32Array>().unwrap();
+            let corrupted: Int32Array = values
+                .iter()
+                .map(|value| match pick(rng, null_rate, malformed_rate) {
+                    Corruption::Null => None,
+                    Corruption::Malformed => value.map(|v| -v.max(1)),
+                    Corruption::Keep => value,
+                })
+                .collect();
+            Ok(Arc::new(corrupted))
+        }
+        DataType::Float64 => {
+            let values = column.as_any().downcast_ref::<Float64Array>().unwrap();
+            let corrupted: Float64Array = values
+                .iter()
+                .map(|value| match pick(rng, null_rate, malformed_rate) {
+                    Corruption::Null => None,
+                    Corruption::Malformed => value.map(|v| -v.abs().max(1.0)),
+                    Corruption::Keep => value,
+                })
+                .collect();
+            Ok(Arc::new(corrupted))
+        }
+        other => bail!(
+            "Cannot inject dirty data into column {:?} of type {}",
+            field.name(),
+            other
+        ),
+    }
+}
+
+/// A variant of an enum-like string that a strict parser would reject.
+fn malformed_string(rng: &mut ChaCha8Rng, value: &str) -> String {
+    match rng.gen_range(0..3) {
+        0 => value.to_uppercase(),
+        1 => format!(" {} ", value),
+        _ => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("platform", DataType::Utf8, false),
+            Field::new("session_date", DataType::Utf8, false),
+            Field::new("product_revenue", DataType::Int32, false),
+        ]);
+        let n = 1_000;
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(vec!["ios"; n])),
+                Arc::new(StringArray::from(vec!["2024-01-01"; n])),
+                Arc::new(Int32Array::from(vec![500; n])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_null_rate() {
+        let dirty = DirtyDataConfig::new()
+            .with_null_rate("platform", 0.2)
+            .apply(&batch(), 42)
+            .unwrap();
+
+        let nulls = dirty.column(0).null_count();
+        assert!((150..250).contains(&nulls), "got {} nulls", nulls);
+        assert!(dirty.schema().field(0).is_nullable());
+        assert_eq!(dirty.column(1).null_count(), 0);
+        assert!(!dirty.schema().field(1).is_nullable());
+    }
+
+    #[test]
+    fn test_malformed_values() {
+        let dirty = DirtyDataConfig::new()
+            .with_malformed_rate("platform", 0.5)
+            .with_malformed_rate("session_date", 0.5)
+            .with_malformed_rate("product_revenue", 0.5)
+            .apply(&batch(), 42)
+            .unwrap();
+
+        let platforms = dirty
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(platforms.iter().flatten().any(|p| p != "ios"));
+
+        let dates = dirty
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(dates.iter().flatten().any(|d| BAD_DATES.contains(&d)));
+
+        let revenue = dirty
+            .column(2)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert!(revenue.iter().flatten().any(|r| r < 0));
+    }
+
+    #[test]
+    fn test_deterministic_from_seed() {
+        let config = DirtyDataConfig::new()
+            .with_null_rate("platform", 0.1)
+            .with_malformed_rate("product_revenue", 0.1);
+
+        assert_eq!(
+            config.apply(&batch(), 42).unwrap(),
+            config.apply(&batch(), 42).unwrap()
+        );
+        assert_ne!(
+            config.apply(&batch(), 42).unwrap(),
+            config.apply(&batch(), 43).unwrap()
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Geo and device enrichment.
+//!
+//! Geo fields (country, region, city, locale, time zone) are derived from the
+//! visitor id, so they're sticky per visitor; device fields (device model, user
+//! agent) are derived from the session id and platform. Both are pure functions of the
+//! ids and the seed, so sessions and the visitors dimension always agree.
+
+use crate::gen::Gen;
+use crate::generators::{weighted_choice, WeightedChoice};
+use crate::session::Platform;
+use anyhow::{Context, Result};
+use arrow::array::{ArrayRef, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use chrono_tz::{America, Asia, Australia, Europe, Tz};
+use rand::seq::SliceRandom;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// RNG streams for enrichment, clear of the other injected-data streams.
+const GEO_STREAM: u64 = 0x300;
+const DEVICE_STREAM: u64 = 0x301;
+
+/// A country with its locales and regions (each with its time zone and
+/// cities).
+pub(crate) struct Country {
+    code: &'static str,
+    locales: &'static [&'static str],
+    regions: &'static [(&'static str, Tz, &'static [&'static str])],
+}
+
+const COUNTRIES: &[(Country, f64)] = &[
+    (
+        Country {
+            code: "US",
+            locales: &["en-US", "es-US"],
+            regions: &[
+                (
+                    "California",
+                    America::Los_Angeles,
+                    &["Los Angeles", "San Francisco", "San Diego"],
+                ),
+                ("New York", America::New_York, &["New York City", "Buffalo"]),
+                ("Texas", America::Chicago, &["Houston", "Austin", "Dallas"]),
+                ("Illinois", America::Chicago, &["Chicago"]),
+            ],
+        },
+        0.35,
+    ),
+    (
+        Country {
+            code: "GB",
+            locales: &["en-GB"],
+            regions: &[
+                (
+                    "England",
+                    Europe::London,
+                    &["London", "Manchester", "Birmingham"],
+                ),
+                ("Scotland", Europe::London, &["Edinburgh", "Glasgow"]),
+            ],
+        },
+        0.10,
+    ),
+    (
+        Country {
+            code: "IN",
+            locales: &["en-IN", "hi-IN"],
+            regions: &[
+                ("Maharashtra", Asia::Kolkata, &["Mumbai", "Pune"]),
+                ("Karnataka", Asia::Kolkata, &["Bengaluru"]),
+                ("Delhi", Asia::Kolkata, &["New Delhi"]),
+            ],
+        },
+        0.10,
+    ),
+    (
+        Country {
+            code: "DE",
+            locales: &["de-DE"],
+            regions: &[
+                ("Bavaria", Europe::Berlin, &["Munich", "Nuremberg"]),
+                ("Berlin", Europe::Berlin, &["Berlin"]),
+                ("Hesse", Europe::Berlin, &["Frankfurt"]),
+            ],
+        },
+        0.08,
+    ),
+    (
+        Country {
+            code: "FR",
+            locales: &["fr-FR"],
+            regions: &[
+                ("Île-de-France", Europe::Paris, &["Paris"]),
+                ("Auvergne-Rhône-Alpes", Europe::Paris, &["Lyon"]),
+                (
+                    "Provence-Alpes-Côte d'Azur",
+                    Europe::Paris,
+                    &["Marseille", "Nice"],
+                ),
+            ],
+        },
+        0.07,
+    ),
+    (
+        Country {
+            code: "BR",
+            locales: &["pt-BR"],
+            regions: &[
+                ("São Paulo", America::Sao_Paulo, &["São Paulo", "Campinas"]),
+                ("Rio de Janeiro", America::Sao_Paulo, &["Rio de Janeiro"]),
+            ],
+        },
+        0.07,
+    ),
+    (
+        Country {
+            code: "CA",
+            locales: &["en-CA", "fr-CA"],
+            regions: &[
+                ("Ontario", America::Toronto, &["Toronto", "Ottawa"]),
+                ("Quebec", America::Toronto, &["Montreal"]),
+                ("British Columbia", America::Vancouver, &["Vancouver"]),
+            ],
+        },
+        0.06,
+    ),
+    (
+        Country {
+            code: "JP",
+            locales: &["ja-JP"],
+            regions: &[
+                ("Tokyo", Asia::Tokyo, &["Tokyo"]),
+                ("Osaka", Asia::Tokyo, &["Osaka"]),
+            ],
+        },
+        0.06,
+    ),
+    (
+        Country {
+            code: "ES",
+            locales: &["es-ES"],
+            regions: &[
+                ("Madrid", Europe::Madrid, &["Madrid"]),
+                ("Catalonia", Europe::Madrid, &["Barcelona"]),
+            ],
+        },
+        0.06,
+    ),
+    (
+        Country {
+            code: "AU",
+            locales: &["en-AU"],
+            regions: &[
+                ("New South Wales", Australia::Sydney, &["Sydney"]),
+                ("Victoria", Australia::Melbourne, &["Melbourne"]),
+            ],
+        },
+        0.05,
+    ),
+];
+
+/// A visitor's location and locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geo {
+    pub country: &'static str,
+    pub region: &'static str,
+    pub city: &'static str,
+    pub locale: &'static str,
+    pub time_zone: Tz,
+}
+
+/// A session's device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub model: &'static str,
+    pub user_agent: String,
+}
+
+/// Which enrichment columns to add.
+#[derive(Debug, Clone)]
+pub struct EnrichmentConfig {
+    pub geo: bool,
+    pub device: bool,
+    /// Country weights by ISO code; countries not listed are never chosen.
+    /// Defaults to a realistic global mix.
+    pub country_weights: Vec<(&'static str, f64)>,
+    /// Varies the mapping from ids to geo and devices.
+    pub seed: u64,
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            geo: true,
+            device: true,
+            country_weights: COUNTRIES.iter().map(|(c, w)| (c.code, *w)).collect(),
+            seed: 0,
+        }
+    }
+}
+
+impl EnrichmentConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_geo(mut self, geo: bool) -> Self {
+        self.geo = geo;
+        self
+    }
+
+    pub fn with_device(mut self, device: bool) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Restrict or reweight countries. Unknown codes are ignored, but at
+    /// least one known code must remain.
+    pub fn with_country_weights(mut self, weights: Vec<(&'static str, f64)>) -> Self {
+        self.country_weights = weights;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub(crate) fn country_gen(&self) -> WeightedChoice<&'static Country> {
+        weighted_choice(
+            self.country_weights
+                .iter()
+                .filter_map(|(code, w)| {
+                    COUNTRIES
+                        .iter()
+                        .find(|(c, _)| c.code == *code)
+                        .map(|(c, _)| (c, *w))
+                })
+                .collect(),
+        )
+    }
+
+    /// Location and locale for a visitor.
+    pub fn geo(&self, visitor_id: Uuid) -> Geo {
+        self.geo_with(&self.country_gen(), visitor_id)
+    }
+
+    pub(crate) fn geo_with(
+        &self,
+        countries: &WeightedChoice<&'static Country>,
+        visitor_id: Uuid,
+    ) -> Geo {
+        let mut rng = id_rng(self.seed, visitor_id, GEO_STREAM);
+        let country = countries.generate(&mut rng);
+        let (region, time_zone, cities) = country.regions.choose(&mut rng).unwrap();
+        Geo {
+            country: country.code,
+            region,
+            city: cities.choose(&mut rng).unwrap(),
+            locale: country.locales.choose(&mut rng).unwrap(),
+            time_zone: *time_zone,
+        }
+    }
+
+    /// Device for a session on `platform`.
+    pub fn device(&self, session_id: Uuid, platform: Platform) -> Device {
+        let mut rng = id_rng(self.seed, session_id, DEVICE_STREAM);
+        device_for(&mut rng, platform)
+    }
+
+    /// Append geo columns keyed by `visitor_id` and device columns keyed by
+    /// `session_id` and `platform`.
+    pub fn apply(&self, batch: &RecordBatch) -> Result<RecordBatch> {
+        let mut fields: Vec<Field> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.as_ref().clone())
+            .collect();
+        let mut columns: Vec<ArrayRef> = batch.columns().to_vec();
+
+        if self.geo {
+            let visitor_ids = uuid_column(batch, "visitor_id")?;
+            let countries = self.country_gen();
+            let mut builders: [StringBuilder; 5] = Default::default();
+            for id in visitor_ids {
+                let geo = self.geo_with(&countries, id);
+                builders[0].append_value(geo.country);
+                builders[1].append_value(geo.region);
+                builders[2].append_value(geo.city);
+                builders[3].append_value(geo.locale);
+                builders[4].append_value(geo.time_zone.name());
+            }
+            for (name, mut builder) in ["country", "region", "city", "locale", "time_zone"]
+                .into_iter()
+                .zip(builders)
+            {
+                fields.push(Field::new(name, DataType::Utf8, false));
+                columns.push(Arc::new(builder.finish()));
+            }
+        }
+
+        if self.device {
+            let session_ids = uuid_column(batch, "session_id")?;
+            let platforms = string_column(batch, "platform")?;
+            let mut models = StringBuilder::new();
+            let mut user_agents = StringBuilder::new();
+            for (row, id) in session_ids.into_iter().enumerate() {
+                let platform = parse_platform(platforms.value(row))?;
+                let device = self.device(id, platform);
+                models.append_value(device.model);
+                user_agents.append_value(device.user_agent);
+            }
+            fields.push(Field::new("device_model", DataType::Utf8, false));
+            columns.push(Arc::new(models.finish()));
+            fields.push(Field::new("user_agent", DataType::Utf8, false));
+            columns.push(Arc::new(user_agents.finish()));
+        }
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .context("Failed to add enrichment columns")
+    }
+}
+
+/// An RNG determined by `seed`, an id, and a stream.
+fn id_rng(seed: u64, id: Uuid, stream: u64) -> ChaCha8Rng {
+    let (hi, lo) = id.as_u64_pair();
+    let mut rng = ChaCha8Rng::seed_from_u64(seed ^ hi ^ lo.rotate_left(32));
+    rng.set_stream(stream);
+    rng
+}
+
+fn device_for(rng: &mut dyn RngCore, platform: Platform) -> Device {
+    const IPHONES: &[&str] = &["iPhone 15", "iPhone 14", "iPhone 13", "iPhone SE"];
+    const ANDROIDS: &[&str] = &["Pixel 8", "Galaxy S24", "Galaxy A54", "OnePlus 12"];
+    const DESKTOPS: &[(&str, &str)] = &[
+        ("Windows PC", "Windows NT 10.0; Win64; x64"),
+        ("Mac", "Macintosh; Intel Mac OS X 10_15_7"),
+        ("Linux PC", "X11; Linux x86_64"),
+    ];
+    const CHROME: &str = "AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0";
+
+    let iphone_ua = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) \
+                     AppleWebKit/605.1.15 (KHTML, like Gecko)";
+    match platform {
+        Platform::Ios => Device {
+            model: IPHONES.choose(rng).unwrap(),
+            user_agent: format!("{} Mobile/15E148", iphone_ua),
+        },
+        Platform::Android => {
+            let model = ANDROIDS.choose(rng).unwrap();
+            Device {
+                model,
+                user_agent: format!(
+                    "Mozilla/5.0 (Linux; Android 14; {}) {} Mobile Safari/537.36",
+                    model, CHROME
+                ),
+            }
+        }
+        Platform::WebDesktop => {
+            let (model, os) = DESKTOPS.choose(rng).unwrap();
+            Device {
+                model,
+                user_agent: format!("Mozilla/5.0 ({}) {} Safari/537.36", os, CHROME),
+            }
+        }
+        Platform::WebMobile => {
+            if rng.next_u32().is_multiple_of(2) {
+                Device {
+                    model: IPHONES.choose(rng).unwrap(),
+                    user_agent: format!("{} Version/17.4 Mobile/15E148 Safari/604.1", iphone_ua),
+                }
+            } else {
+                let model = ANDROIDS.choose(rng).unwrap();
+                Device {
+                    model,
+                    user_agent: format!(
+                        "Mozilla/5.0 (Linux; Android 14; {}) {} Mobile Safari/537.36",
+                        model, CHROME
+                    ),
+                }
+            }
+        }
+    }
+}
+
+fn parse_platform(s: &str) -> Result<Platform> {
+    [
+        Platform::WebDesktop,
+        Platform::Android,
+        Platform::Ios,
+        Platform::WebMobile,
+    ]
+    .into_iter()
+    .find(|p| p.as_str() == s)
+    .with_context(|| format!("Unknown platform {:?}", s))
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        .with_context(|| format!("Enrichment needs a string {:?} column", name))
+}
+
+fn uuid_column(batch: &RecordBatch, name: &str) -> Result<Vec<Uuid>> {
+    string_column(batch, name)?
+        .iter()
+        .map(|v| {
+            let v = v.with_context(|| format!("Null {} in batch", name))?;
+            Uuid::parse_str(v).with_context(|| format!("Invalid {}: {:?}", name, v))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geo_is_sticky_per_visitor() {
+        let config = EnrichmentConfig::new();
+        let id = Uuid::from_u128(0x1234);
+        assert_eq!(config.geo(id), config.geo(id));
+
+        let country = COUNTRIES
+            .iter()
+            .find(|(c, _)| c.code == config.geo(id).country);
+        let (country, _) = country.unwrap();
+        let geo = config.geo(id);
+        assert!(country.locales.contains(&geo.locale));
+        assert!(country
+            .regions
+            .iter()
+            .any(|(r, zone, cities)| *r == geo.region
+                && *zone == geo.time_zone
+                && cities.contains(&geo.city)));
+    }
+
+    #[test]
+    fn test_country_weights_restrict_choice() {
+        let config = EnrichmentConfig::new().with_country_weights(vec![("JP", 1.0)]);
+        for i in 0..100 {
+            assert_eq!(config.geo(Uuid::from_u128(i)).country, "JP");
+        }
+    }
+
+    #[test]
+    fn test_device_matches_platform() {
+        let config = EnrichmentConfig::new();
+        for i in 0..50 {
+            let id = Uuid::from_u128(i);
+            assert!(config
+                .device(id, Platform::Ios)
+                .user_agent
+                .contains("iPhone"));
+            let android = config.device(id, Platform::Android);
+            assert!(android.user_agent.contains(android.model));
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Event-level rows behind each session summary.
+//!
+//! Every session row expands into the events its counts describe: page views
+//! for `widget_views`, then for its category the product views, add-to-carts,
+//! checkouts, and purchases. Events share the session's `session_id` and
+//! `visitor_id`, so summing events per session reproduces the session table,
+//! and purchase revenue adds up to `product_revenue` exactly.
+//!
+//! Timestamps are UTC. With local time enabled, sessions start at a local
+//! time of day in the visitor's time zone, so models that convert back to
+//! local time can be checked, including across DST changes.
+
+use crate::enrich::EnrichmentConfig;
+use crate::gen::Gen;
+use crate::generators::{uuid_gen, weighted_choice, WeightedChoice};
+use crate::session::{ProductCategory, Session};
+use chrono::{Duration, LocalResult, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// RNG stream for events, clear of the other per-day streams.
+const EVENT_STREAM: u64 = 0x700;
+
+/// Relative chance of a session starting in each local hour: quiet overnight,
+/// building through the day to an evening peak.
+const LOCAL_HOUR_WEIGHTS: [f64; 24] = [
+    0.6, 0.35, 0.2, 0.15, 0.15, 0.25, 0.6, 1.2, 1.8, 2.2, 2.4, 2.6, 2.8, 2.7, 2.6, 2.6, 2.8, 3.2,
+    3.8, 4.5, 5.0, 4.8, 3.6, 1.6,
+];
+
+/// Kinds of event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    PageView,
+    ProductView,
+    AddToCart,
+    Checkout,
+    Purchase,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::PageView => "page_view",
+            EventType::ProductView => "product_view",
+            EventType::AddToCart => "add_to_cart",
+            EventType::Checkout => "checkout",
+            EventType::Purchase => "purchase",
+        }
+    }
+}
+
+/// One event in a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub event_id: Uuid,
+    pub session_id: Uuid,
+    pub visitor_id: Uuid,
+    pub event_timestamp: NaiveDateTime,
+    /// 1-based position within the session, in timestamp order.
+    pub sequence_number: i32,
+    pub event_type: EventType,
+    /// The category of product events; `None` for page views.
+    pub product_category: Option<ProductCategory>,
+    /// Revenue in cents, for purchases only.
+    pub revenue: Option<i32>,
+}
+
+/// Timing of generated events.
+#[derive(Debug, Clone)]
+pub struct EventsConfig {
+    /// Fewest seconds between consecutive events in a session.
+    pub min_gap_seconds: i64,
+    /// Most seconds between consecutive events in a session.
+    pub max_gap_seconds: i64,
+    /// Start sessions at a local time of day on `session_date`, in each
+    /// visitor's time zone as this geo enrichment places them, instead of
+    /// uniformly over the UTC day.
+    pub local_time: Option<EnrichmentConfig>,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            min_gap_seconds: 2,
+            max_gap_seconds: 120,
+            local_time: None,
+        }
+    }
+}
+
+/// Where a session's next event goes.
+struct Cursor {
+    timestamp: NaiveDateTime,
+    sequence_number: i32,
+}
+
+impl EventsConfig {
+    /// Time sessions by local time in the zones `geo` gives visitors; use
+    /// the same config as the geo columns so they agree.
+    pub fn with_local_time(mut self, geo: EnrichmentConfig) -> Self {
+        self.local_time = Some(geo);
+        self
+    }
+
+    /// Events for one day's sessions, from `day_seed`.
+    ///
+    /// Sessions start at a random time of day; late ones may run past
+    /// midnight but stay with their `session_date`.
+    pub fn generate(&self, day_seed: u64, sessions: &[Session]) -> Vec<Event> {
+        let mut rng = ChaCha8Rng::seed_from_u64(day_seed);
+        rng.set_stream(EVENT_STREAM);
+        let uuid_g = uuid_gen();
+        let local_time = self
+            .local_time
+            .as_ref()
+            .map(|geo| (geo.country_gen(), local_hour_gen()));
+        let min_gap = self.min_gap_seconds.max(0);
+        let max_gap = self.max_gap_seconds.max(min_gap);
+
+        let mut cursors: HashMap<Uuid, Cursor> = HashMap::new();
+        let mut events = Vec::new();
+        for session in sessions {
+            let mut page_views = 0;
+            let cursor = cursors.entry(session.session_id).or_insert_with(|| {
+                // Page views happen once per session, however many category rows it has.
+                page_views = session.widget_views;
+                let timestamp = match (&self.local_time, &local_time) {
+                    (Some(geo), Some((countries, hours))) => {
+                        let zone = geo.geo_with(countries, session.visitor_id).time_zone;
+                        let local = session
+                            .session_date
+                            .and_hms_opt(hours.generate(&mut rng), 0, 0)
+                            .unwrap()
+                            + Duration::seconds(rng.gen_range(0..3_600));
+                        local_to_utc(zone, local)
+                    }
+                    _ => {
+                        session.session_date.and_hms_opt(0, 0, 0).unwrap()
+                            + Duration::seconds(rng.gen_range(0..86_400))
+                    }
+                };
+                Cursor {
+                    timestamp,
+                    sequence_number: 0,
+                }
+            });
+
+            let counts = [
+                (EventType::PageView, page_views),
+                (EventType::ProductView, session.product_views),
+                (EventType::AddToCart, session.product_add_to_cart_count),
+                (EventType::Checkout, session.product_checkout_count),
+                (EventType::Purchase, session.product_purchase_count),
+            ];
+            for (event_type, count) in counts {
+                for i in 0..count {
+                    let revenue = (event_type == EventType::Purchase).then(|| {
+                        // Spread revenue evenly, with the remainder on the last purchase.
+                        let share = session.product_revenue / count;
+                        if i + 1 == count {
+                            session.product_revenue - share * (count - 1)
+                        } else {
+                            share
+                        }
+                    });
+                    if cursor.sequence_number > 0 {
+                        cursor.timestamp += Duration::seconds(rng.gen_range(min_gap..=max_gap));
+                    }
+                    cursor.sequence_number += 1;
+                    events.push(Event {
+                        event_id: uuid_g.generate(&mut rng),
+                        session_id: session.session_id,
+                        visitor_id: session.visitor_id,
+                        event_timestamp: cursor.timestamp,
+                        sequence_number: cursor.sequence_number,
+                        event_type,
+                        product_category: (event_type != EventType::PageView)
+                            .then_some(session.product_category),
+                        revenue,
+                    });
+                }
+            }
+        }
+        events
+    }
+}
+
+fn local_hour_gen() -> WeightedChoice<u32> {
+    weighted_choice((0..24).zip(LOCAL_HOUR_WEIGHTS).collect())
+}
+
+/// `local` wall-clock time in `zone` as UTC. Times skipped when clocks go
+/// forward are read an hour later, as a clock that missed the change would
+/// show; times repeated when they go back take the first occurrence.
+pub(crate) fn local_to_utc(zone: Tz, local: NaiveDateTime) -> NaiveDateTime {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.naive_utc(),
+        LocalResult::None => local_to_utc(zone, local + Duration::hours(1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{generate_day_seeds, DayGenerator, VisitorPool};
+    use chrono::NaiveDate;
+
+    fn day() -> (u64, Vec<Session>) {
+        let day_seed = generate_day_seeds(42, 1)[0];
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sessions =
+            DayGenerator::new(VisitorPool::new(42, 10_000), day_seed, date, 2_000).generate();
+        (day_seed, sessions)
+    }
+
+    #[test]
+    fn test_events_reproduce_session_counts() {
+        let (day_seed, sessions) = day();
+        let events = EventsConfig::default().generate(day_seed, &sessions);
+
+        let count = |event_type| events.iter().filter(|e| e.event_type == event_type).count();
+        let total = |f: fn(&Session) -> i32| sessions.iter().map(|s| f(s) as usize).sum::<usize>();
+        assert_eq!(count(EventType::ProductView), total(|s| s.product_views));
+        assert_eq!(
+            count(EventType::Purchase),
+            total(|s| s.product_purchase_count)
+        );
+
+        let revenue: i64 = events.iter().filter_map(|e| e.revenue).map(i64::from).sum();
+        let expected: i64 = sessions.iter().map(|s| s.product_revenue as i64).sum();
+        assert_eq!(revenue, expected);
+    }
+
+    #[test]
+    fn test_events_are_ordered_within_sessions() {
+        let (day_seed, sessions) = day();
+        let events = EventsConfig::default().generate(day_seed, &sessions);
+
+        let mut by_session: HashMap<Uuid, Vec<&Event>> = HashMap::new();
+        for event in &events {
+            by_session.entry(event.session_id).or_default().push(event);
+        }
+        for session_events in by_session.values() {
+            for (i, pair) in session_events.windows(2).enumerate() {
+                assert_eq!(pair[0].sequence_number, i as i32 + 1);
+                assert!(pair[0].event_timestamp < pair[1].event_timestamp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_local_to_utc_across_dst() {
+        let zone = chrono_tz::America::Los_Angeles;
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        assert_eq!(
+            local_to_utc(zone, at("2024-01-01 19:00")),
+            at("2024-01-02 03:00")
+        );
+        // 02:30 doesn't exist on the spring-forward day; it reads as 03:30 PDT.
+        assert_eq!(
+            local_to_utc(zone, at("2024-03-10 02:30")),
+            at("2024-03-10 10:30")
+        );
+        // 01:30 happens twice on the fall-back day; the first is PDT.
+        assert_eq!(
+            local_to_utc(zone, at("2024-11-03 01:30")),
+            at("2024-11-03 08:30")
+        );
+    }
+
+    #[test]
+    fn test_local_time_sessions_peak_in_the_evening() {
+        use chrono::Timelike;
+
+        // US clocks go forward on this day.
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let day_seed = generate_day_seeds(42, 1)[0];
+        let sessions =
+            DayGenerator::new(VisitorPool::new(42, 10_000), day_seed, date, 2_000).generate();
+        let geo = EnrichmentConfig::default();
+        let events = EventsConfig::default()
+            .with_local_time(geo.clone())
+            .generate(day_seed, &sessions);
+
+        let mut hours = [0; 24];
+        for event in events.iter().filter(|e| e.sequence_number == 1) {
+            let zone = geo.geo(event.visitor_id).time_zone;
+            let local = zone.from_utc_datetime(&event.event_timestamp).naive_local();
+            assert_eq!(local.date(), date);
+            hours[local.hour() as usize] += 1;
+        }
+        let evening: usize = hours[18..=22].iter().sum();
+        let morning: usize = hours[6..=10].iter().sum();
+        assert!(evening > morning * 3 / 2, "{:?}", hours);
+    }
+}
@@ -0,0 +1,111 @@
+//! Ground-truth aggregates computed during generation.
+//!
+//! Each day's totals per platform and per product category are tallied from
+//! the sessions as they're generated, so models built on the written tables
+//! can be asserted exactly against them in integration tests. They describe
+//! the clean data: rows nulled or malformed by dirty data still count here.
+
+use crate::session::Session;
+use std::collections::{BTreeMap, HashSet};
+
+/// One day's totals for a platform.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlatformTotals {
+    pub platform: &'static str,
+    /// Distinct session ids.
+    pub sessions: i64,
+    /// Distinct visitor ids.
+    pub visitors: i64,
+    /// Rows written, counting every category row and duplicate.
+    pub rows: i64,
+}
+
+/// One day's totals for a product category.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategoryTotals {
+    pub product_category: &'static str,
+    pub product_views: i64,
+    pub add_to_cart_count: i64,
+    pub checkout_count: i64,
+    pub purchase_count: i64,
+    /// Revenue in cents.
+    pub revenue: i64,
+}
+
+/// Expected aggregates for one day, ordered by platform and category name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpectedAggregates {
+    pub platforms: Vec<PlatformTotals>,
+    pub categories: Vec<CategoryTotals>,
+}
+
+impl ExpectedAggregates {
+    /// Tally one day's sessions.
+    pub fn from_sessions(sessions: &[Session]) -> Self {
+        let mut platforms: BTreeMap<&'static str, (PlatformTotals, HashSet<_>, HashSet<_>)> =
+            BTreeMap::new();
+        let mut categories: BTreeMap<&'static str, CategoryTotals> = BTreeMap::new();
+
+        for session in sessions {
+            let name = session.platform.as_str();
+            let (totals, session_ids, visitor_ids) = platforms.entry(name).or_default();
+            totals.platform = name;
+            totals.rows += 1;
+            session_ids.insert(session.session_id);
+            visitor_ids.insert(session.visitor_id);
+
+            let name = session.product_category.as_str();
+            let totals = categories.entry(name).or_default();
+            totals.product_category = name;
+            totals.product_views += session.product_views as i64;
+            totals.add_to_cart_count += session.product_add_to_cart_count as i64;
+            totals.checkout_count += session.product_checkout_count as i64;
+            totals.purchase_count += session.product_purchase_count as i64;
+            totals.revenue += session.product_revenue as i64;
+        }
+
+        Self {
+            platforms: platforms
+                .into_values()
+                .map(|(mut totals, session_ids, visitor_ids)| {
+                    totals.sessions = session_ids.len() as i64;
+                    totals.visitors = visitor_ids.len() as i64;
+                    totals
+                })
+                .collect(),
+            categories: categories.into_values().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{generate_day_seeds, DayGenerator, VisitorPool};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_totals_cover_every_row() {
+        let day_seed = generate_day_seeds(42, 1)[0];
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sessions =
+            DayGenerator::new(VisitorPool::new(42, 10_000), day_seed, date, 2_000).generate();
+        let expected = ExpectedAggregates::from_sessions(&sessions);
+
+        let rows: i64 = expected.platforms.iter().map(|p| p.rows).sum();
+        assert_eq!(rows, sessions.len() as i64);
+        let revenue: i64 = expected.categories.iter().map(|c| c.revenue).sum();
+        assert_eq!(
+            revenue,
+            sessions
+                .iter()
+                .map(|s| s.product_revenue as i64)
+                .sum::<i64>()
+        );
+        assert!(expected
+            .platforms
+            .windows(2)
+            .all(|w| w[0].platform < w[1].platform));
+        assert!(expected.platforms.iter().all(|p| p.sessions <= p.rows));
+    }
+}
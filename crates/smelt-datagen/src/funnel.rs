@@ -0,0 +1,128 @@
+//! Purchase funnel model.
+//!
+//! Each product view walks view → add to cart → checkout → purchase, dropping
+//! out at each step with a configurable probability, so cart, checkout, and
+//! purchase counts are always nested within views.
+
+use crate::gen::Gen;
+use rand::{Rng, RngCore};
+
+/// How far a single product view progressed through the funnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FunnelStep {
+    ProductView,
+    AddToCart,
+    Checkout,
+    Purchase,
+}
+
+/// Step-to-step continuation rates.
+#[derive(Debug, Clone, Copy)]
+pub struct Funnel {
+    /// Probability a view is added to the cart.
+    pub add_to_cart: f64,
+    /// Probability a cart add reaches checkout.
+    pub checkout: f64,
+    /// Probability a checkout completes as a purchase.
+    pub purchase: f64,
+}
+
+impl Default for Funnel {
+    fn default() -> Self {
+        Self {
+            add_to_cart: 0.30,
+            checkout: 0.60,
+            purchase: 0.70,
+        }
+    }
+}
+
+/// Counts of product views reaching each funnel step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunnelCounts {
+    pub add_to_cart: i32,
+    pub checkout: i32,
+    pub purchase: i32,
+}
+
+impl Funnel {
+    pub fn new(add_to_cart: f64, checkout: f64, purchase: f64) -> Self {
+        Self {
+            add_to_cart,
+            checkout,
+            purchase,
+        }
+    }
+
+    /// Walk the funnel once per product view and count how many reach each step.
+    pub fn walk(&self, rng: &mut dyn RngCore, product_views: i32) -> FunnelCounts {
+        let mut counts = FunnelCounts::default();
+        for _ in 0..product_views {
+            let step = self.generate(rng);
+            if step >= FunnelStep::AddToCart {
+                counts.add_to_cart += 1;
+            }
+            if step >= FunnelStep::Checkout {
+                counts.checkout += 1;
+            }
+            if step == FunnelStep::Purchase {
+                counts.purchase += 1;
+            }
+        }
+        counts
+    }
+}
+
+impl Gen<FunnelStep> for Funnel {
+    fn generate(&self, rng: &mut dyn RngCore) -> FunnelStep {
+        if !rng.gen_bool(self.add_to_cart) {
+            FunnelStep::ProductView
+        } else if !rng.gen_bool(self.checkout) {
+            FunnelStep::AddToCart
+        } else if !rng.gen_bool(self.purchase) {
+            FunnelStep::Checkout
+        } else {
+            FunnelStep::Purchase
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_counts_are_nested() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let funnel = Funnel::default();
+
+        for views in 0..50 {
+            let counts = funnel.walk(&mut rng, views);
+            assert!(counts.add_to_cart <= views);
+            assert!(counts.checkout <= counts.add_to_cart);
+            assert!(counts.purchase <= counts.checkout);
+        }
+    }
+
+    #[test]
+    fn test_conversion_matches_rates() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let counts = Funnel::new(0.5, 0.5, 0.5).walk(&mut rng, 100_000);
+
+        // Expect 50%, 25%, 12.5% of views
+        assert!((counts.add_to_cart - 50_000).abs() < 1_000);
+        assert!((counts.checkout - 25_000).abs() < 1_000);
+        assert!((counts.purchase - 12_500).abs() < 1_000);
+    }
+
+    #[test]
+    fn test_closed_funnel_never_purchases() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let counts = Funnel::new(1.0, 1.0, 0.0).walk(&mut rng, 1_000);
+
+        assert_eq!(counts.checkout, 1_000);
+        assert_eq!(counts.purchase, 0);
+    }
+}
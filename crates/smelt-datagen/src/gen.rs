@@ -3,11 +3,14 @@
 //! Inspired by proptest's Strategy trait, but simplified for data generation
 //! without shrinking capability.
 
-use rand::RngCore;
+use crate::generators::{bool_with_prob, optional, uuid_gen, BoolWithProb, Optional, UuidGen};
+use rand::{Rng, RngCore};
+use uuid::Uuid;
 
 /// A generator that produces values of type `T` from a random source.
 ///
-/// Generators are composable using methods like `map`, `flat_map`, and `filter`.
+/// Generators are composable using methods like `map`, `flat_map`, `filter`,
+/// and `zip`.
 pub trait Gen<T> {
     /// Generate a value using the provided random source.
     fn generate(&self, rng: &mut dyn RngCore) -> T;
@@ -50,6 +53,42 @@ pub trait Gen<T> {
             predicate,
         }
     }
+
+    /// Generate a value from this generator, then one from `other`.
+    fn zip<U, H>(self, other: H) -> Zipped<Self, H>
+    where
+        Self: Sized,
+        H: Gen<U>,
+    {
+        Zipped {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Erase the generator's type, e.g. to mix different generators of the
+    /// same values in [`crate::generators::frequency`].
+    fn boxed(self) -> BoxedGen<T>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+}
+
+/// A type-erased generator.
+pub type BoxedGen<T> = Box<dyn Gen<T>>;
+
+impl<T, G: Gen<T> + ?Sized> Gen<T> for Box<G> {
+    fn generate(&self, rng: &mut dyn RngCore) -> T {
+        (**self).generate(rng)
+    }
+}
+
+impl<T, G: Gen<T> + ?Sized> Gen<T> for &G {
+    fn generate(&self, rng: &mut dyn RngCore) -> T {
+        (**self).generate(rng)
+    }
 }
 
 /// A generator that applies a function to transform generated values.
@@ -108,3 +147,148 @@ where
         }
     }
 }
+
+/// A generator that pairs values from two generators.
+pub struct Zipped<G, H> {
+    first: G,
+    second: H,
+}
+
+impl<T, U, G, H> Gen<(T, U)> for Zipped<G, H>
+where
+    G: Gen<T>,
+    H: Gen<U>,
+{
+    fn generate(&self, rng: &mut dyn RngCore) -> (T, U) {
+        let first = self.first.generate(rng);
+        (first, self.second.generate(rng))
+    }
+}
+
+/// Types with a default generator.
+///
+/// Implement it for a record type by zipping [`any`] generators for its
+/// fields and mapping the tuple into the record, the way a derive would.
+pub trait Arbitrary: Sized {
+    type Generator: Gen<Self>;
+
+    fn arbitrary() -> Self::Generator;
+}
+
+/// The default generator for `T`.
+pub fn any<T: Arbitrary>() -> T::Generator {
+    T::arbitrary()
+}
+
+/// Generate values from rand's standard distribution: every value of an
+/// integer type, or a float in `[0, 1)`.
+pub struct Standard<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+macro_rules! standard_arbitrary {
+    ($($t:ty),*) => {$(
+        impl Gen<$t> for Standard<$t> {
+            fn generate(&self, rng: &mut dyn RngCore) -> $t {
+                rng.gen()
+            }
+        }
+
+        impl Arbitrary for $t {
+            type Generator = Standard<$t>;
+
+            fn arbitrary() -> Self::Generator {
+                Standard {
+                    _phantom: std::marker::PhantomData,
+                }
+            }
+        }
+    )*};
+}
+
+standard_arbitrary!(i32, i64, u32, u64, f64);
+
+impl Arbitrary for bool {
+    type Generator = BoolWithProb;
+
+    fn arbitrary() -> Self::Generator {
+        bool_with_prob(0.5)
+    }
+}
+
+impl Arbitrary for Uuid {
+    type Generator = UuidGen;
+
+    fn arbitrary() -> Self::Generator {
+        uuid_gen()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    type Generator = Optional<T::Generator, T>;
+
+    fn arbitrary() -> Self::Generator {
+        optional(any::<T>(), 0.5)
+    }
+}
+
+impl<A: Arbitrary, B: Arbitrary> Arbitrary for (A, B) {
+    type Generator = Zipped<A::Generator, B::Generator>;
+
+    fn arbitrary() -> Self::Generator {
+        any::<A>().zip(any::<B>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::{frequency, uniform, vec_of};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[derive(Debug, PartialEq)]
+    struct Order {
+        id: Uuid,
+        quantity: i32,
+        gift: bool,
+    }
+
+    impl Arbitrary for Order {
+        type Generator = BoxedGen<Order>;
+
+        fn arbitrary() -> Self::Generator {
+            any::<Uuid>()
+                .zip(uniform(1..10))
+                .zip(any::<bool>())
+                .map(|((id, quantity), gift)| Order { id, quantity, gift })
+                .boxed()
+        }
+    }
+
+    #[test]
+    fn test_composed_record_generator_is_deterministic() {
+        let orders = vec_of(any::<Order>(), 1..20);
+        let mut a = ChaCha8Rng::seed_from_u64(7);
+        let mut b = ChaCha8Rng::seed_from_u64(7);
+        let generated = orders.generate(&mut a);
+        assert!(!generated.is_empty() && generated.len() < 20);
+        assert!(generated.iter().all(|o| (1..10).contains(&o.quantity)));
+        assert_eq!(generated, orders.generate(&mut b));
+    }
+
+    #[test]
+    fn test_frequency_follows_weights() {
+        let gen = frequency(vec![
+            (uniform(0..10).boxed(), 9.0),
+            (uniform(100..110).boxed(), 1.0),
+        ]);
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let values: Vec<i32> = (0..10_000).map(|_| gen.generate(&mut rng)).collect();
+        let high = values.iter().filter(|v| **v >= 100).count();
+        assert!((700..1300).contains(&high), "{} high values", high);
+        assert!(values
+            .iter()
+            .all(|v| (0..10).contains(v) || (100..110).contains(v)));
+    }
+}
@@ -1,6 +1,6 @@
 //! Built-in generators for common types.
 
-use crate::gen::Gen;
+use crate::gen::{BoxedGen, Gen};
 use rand::distributions::{Distribution, WeightedIndex};
 use rand::RngCore;
 use std::ops::Range;
@@ -96,6 +96,32 @@ pub fn weighted_choice<T: Clone>(items: Vec<(T, f64)>) -> WeightedChoice<T> {
     WeightedChoice::new(items)
 }
 
+/// Generate a value from one of several generators, picked by weight.
+pub struct Frequency<T> {
+    gens: Vec<BoxedGen<T>>,
+    weights: WeightedIndex<f64>,
+}
+
+impl<T> Frequency<T> {
+    pub fn new(gens: Vec<(BoxedGen<T>, f64)>) -> Self {
+        let (gens, weights): (Vec<_>, Vec<_>) = gens.into_iter().unzip();
+        let weights = WeightedIndex::new(&weights).expect("weights must be positive");
+        Self { gens, weights }
+    }
+}
+
+impl<T> Gen<T> for Frequency<T> {
+    fn generate(&self, rng: &mut dyn RngCore) -> T {
+        let idx = self.weights.sample(rng);
+        self.gens[idx].generate(rng)
+    }
+}
+
+/// Convenience function to create a frequency generator.
+pub fn frequency<T>(gens: Vec<(BoxedGen<T>, f64)>) -> Frequency<T> {
+    Frequency::new(gens)
+}
+
 /// Generate a value uniformly selected from a slice.
 pub struct OneOf<T> {
     items: Vec<T>,
@@ -272,3 +298,30 @@ impl Gen<i32> for Geometric {
 pub fn geometric(p: f64) -> Geometric {
     Geometric::new(p)
 }
+
+/// Generate a vector of values, with a length uniformly distributed in `len`.
+pub struct VecOf<G> {
+    gen: G,
+    len: Uniform<usize>,
+}
+
+impl<G> VecOf<G> {
+    pub fn new(gen: G, len: Range<usize>) -> Self {
+        Self {
+            gen,
+            len: Uniform::new(len),
+        }
+    }
+}
+
+impl<T, G: Gen<T>> Gen<Vec<T>> for VecOf<G> {
+    fn generate(&self, rng: &mut dyn RngCore) -> Vec<T> {
+        let len = self.len.generate(rng);
+        (0..len).map(|_| self.gen.generate(rng)).collect()
+    }
+}
+
+/// Convenience function to create a vector generator.
+pub fn vec_of<G>(gen: G, len: Range<usize>) -> VecOf<G> {
+    VecOf::new(gen, len)
+}
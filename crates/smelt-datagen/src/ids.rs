@@ -0,0 +1,210 @@
+//! Key formats for generated ids.
+//!
+//! Ids are random UUIDs while generating, so every table references the same
+//! values. An [`IdFormat`] picks how they're written, to match the keys of the
+//! system under test. Time-sortable formats need each id to carry its
+//! creation time, so they're stamped right after the entity is generated and
+//! references pick up the stamped value.
+
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, Int64Builder, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Generated columns holding ids.
+const ID_COLUMNS: &[&str] = &[
+    "visitor_id",
+    "session_id",
+    "event_id",
+    "order_id",
+    "account_id",
+    "touchpoint_id",
+    "conversion_session_id",
+];
+
+/// Snowflake timestamps count milliseconds from Twitter's epoch,
+/// 2010-11-04T01:42:54.657Z.
+const SNOWFLAKE_EPOCH_MS: i64 = 1_288_834_974_657;
+
+/// Random bits below a snowflake's 41-bit timestamp.
+const SNOWFLAKE_RANDOM_BITS: u32 = 22;
+
+/// How generated ids are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdFormat {
+    /// Random hyphenated UUIDs.
+    #[default]
+    Uuid4,
+    /// Hyphenated UUIDs led by their creation time, so they sort by it.
+    Uuid7,
+    /// 32 lowercase hex digits led by the creation time, so they sort by it.
+    Hex,
+    /// 64-bit integers: milliseconds since the snowflake epoch, then 22
+    /// random bits where a real snowflake has its worker and sequence.
+    Snowflake,
+}
+
+impl FromStr for IdFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "uuid4" => IdFormat::Uuid4,
+            "uuid7" => IdFormat::Uuid7,
+            "hex" => IdFormat::Hex,
+            "snowflake" => IdFormat::Snowflake,
+            other => bail!(
+                "Unknown id format {:?}; expected uuid4, uuid7, hex or snowflake",
+                other
+            ),
+        })
+    }
+}
+
+impl IdFormat {
+    /// Whether ids carry their creation time.
+    fn is_timed(self) -> bool {
+        !matches!(self, IdFormat::Uuid4)
+    }
+
+    /// `id` with `time` in its leading 48 bits, laid out as a UUIDv7. Only
+    /// the random bits of `id` are kept, so stamping again with the same
+    /// time gives the same id. Unchanged for untimed formats.
+    pub(crate) fn stamp(self, id: Uuid, time: NaiveDateTime) -> Uuid {
+        if !self.is_timed() {
+            return id;
+        }
+        let millis = time.and_utc().timestamp_millis().max(0) as u64;
+        let mut bytes = *id.as_bytes();
+        bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+        bytes[6] = (bytes[6] & 0x0f) | 0x70;
+        Uuid::from_bytes(bytes)
+    }
+
+    /// [`Self::stamp`] at midnight on `date`.
+    pub(crate) fn stamp_date(self, id: Uuid, date: NaiveDate) -> Uuid {
+        self.stamp(id, date.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    /// A stamped id as a snowflake.
+    fn snowflake(id: Uuid) -> i64 {
+        let bits = id.as_u128();
+        let millis = (bits >> 80) as i64;
+        let random = (bits as i64) & ((1 << SNOWFLAKE_RANDOM_BITS) - 1);
+        ((millis - SNOWFLAKE_EPOCH_MS).max(0) << SNOWFLAKE_RANDOM_BITS) | random
+    }
+
+    /// `batch` with its id columns written in this format. Values that
+    /// aren't UUIDs, such as dirty data, become null.
+    pub(crate) fn apply(self, batch: &RecordBatch) -> Result<RecordBatch> {
+        if self == IdFormat::Uuid4 {
+            return Ok(batch.clone());
+        }
+        let mut fields = Vec::with_capacity(batch.num_columns());
+        let mut columns = Vec::with_capacity(batch.num_columns());
+        for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+            if !ID_COLUMNS.contains(&field.name().as_str()) || field.data_type() != &DataType::Utf8
+            {
+                fields.push(field.as_ref().clone());
+                columns.push(column.clone());
+                continue;
+            }
+            let values = column
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .with_context(|| format!("{} is not a string column", field.name()))?;
+            let ids = values
+                .iter()
+                .map(|value| value.and_then(|v| Uuid::parse_str(v).ok()));
+            let (data_type, column): (DataType, ArrayRef) = match self {
+                IdFormat::Snowflake => {
+                    let mut builder = Int64Builder::with_capacity(values.len());
+                    for id in ids {
+                        builder.append_option(id.map(Self::snowflake));
+                    }
+                    (DataType::Int64, Arc::new(builder.finish()))
+                }
+                _ => {
+                    let mut builder = StringBuilder::with_capacity(values.len(), values.len() * 36);
+                    for id in ids {
+                        builder.append_option(id.map(|id| match self {
+                            IdFormat::Hex => id.simple().to_string(),
+                            _ => id.to_string(),
+                        }));
+                    }
+                    (DataType::Utf8, Arc::new(builder.finish()))
+                }
+            };
+            let nullable = field.is_nullable() || column.null_count() > values.null_count();
+            fields.push(Field::new(field.name(), data_type, nullable));
+            columns.push(column);
+        }
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .context("Failed to format id columns")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_stamped_ids_sort_by_time() {
+        let early = IdFormat::Uuid7.stamp(Uuid::from_u128(u128::MAX), time("2024-01-01 00:00:00"));
+        let late = IdFormat::Uuid7.stamp(Uuid::from_u128(0), time("2024-01-01 00:00:01"));
+        assert!(early < late);
+        assert_eq!(early.get_version_num(), 7);
+        assert_eq!(
+            IdFormat::Uuid7.stamp(early, time("2024-01-01 00:00:00")),
+            early
+        );
+        assert!(IdFormat::snowflake(early) < IdFormat::snowflake(late));
+        assert!(IdFormat::snowflake(early) > 0);
+
+        let id = Uuid::from_u128(42);
+        assert_eq!(IdFormat::Uuid4.stamp(id, time("2024-01-01 00:00:00")), id);
+    }
+
+    #[test]
+    fn test_apply_formats_id_columns() {
+        let id = IdFormat::Hex.stamp(Uuid::from_u128(7), time("2024-01-01 00:00:00"));
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("session_id", DataType::Utf8, false),
+                Field::new("visit_source", DataType::Utf8, false),
+            ])),
+            vec![
+                Arc::new(StringArray::from(vec![id.to_string(), "bad".to_string()])),
+                Arc::new(StringArray::from(vec!["seo", "direct"])),
+            ],
+        )
+        .unwrap();
+
+        let hex = IdFormat::Hex.apply(&batch).unwrap();
+        let sessions = hex
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(sessions.value(0), id.simple().to_string());
+        assert!(sessions.is_null(1));
+        assert!(hex.schema().field(0).is_nullable());
+        assert_eq!(hex.column(1), batch.column(1));
+
+        let snowflake = IdFormat::Snowflake.apply(&batch).unwrap();
+        assert_eq!(snowflake.schema().field(0).data_type(), &DataType::Int64);
+
+        assert_eq!("uuid7".parse::<IdFormat>().unwrap(), IdFormat::Uuid7);
+        assert!("guid".parse::<IdFormat>().is_err());
+    }
+}
@@ -3,13 +3,58 @@
 //! This crate provides proptest-inspired composable generators for creating
 //! test data with deterministic output based on a seed value.
 
+pub mod accounts;
+pub mod anomaly;
+pub mod attribution;
+pub mod calibrate;
+pub mod catalog;
+pub mod dirty;
+pub mod enrich;
+pub mod events;
+pub mod expected;
+pub mod funnel;
 pub mod gen;
 pub mod generators;
+pub mod ids;
+pub mod load;
+pub mod naming;
+pub mod orders;
+mod output;
 pub mod parquet;
+pub mod properties;
+pub mod sample;
+pub mod scenario;
 pub mod session;
+pub mod shape;
+pub mod sink;
+pub mod text;
+pub mod validation;
 
-pub use gen::Gen;
+pub use accounts::{Account, AccountConfig, Plan};
+pub use anomaly::{AnomalyConfig, BotPersona, PlatformOutage, GROUND_TRUTH_COLUMN};
+pub use attribution::{AttributionConfig, Touchpoint};
+pub use calibrate::Calibration;
+pub use catalog::{Catalog, CatalogConfig, CatalogItem};
+pub use dirty::DirtyDataConfig;
+pub use enrich::{Device, EnrichmentConfig, Geo};
+pub use events::{Event, EventType, EventsConfig};
+pub use expected::{CategoryTotals, ExpectedAggregates, PlatformTotals};
+pub use funnel::{Funnel, FunnelCounts, FunnelStep};
+pub use gen::{any, Arbitrary, BoxedGen, Gen};
 pub use generators::*;
+pub use ids::IdFormat;
+pub use load::{load_into_backend, BackendLoader};
+pub use naming::SchemaConfig;
+pub use orders::{Order, OrderItem, OrderStatus, OrdersConfig};
+pub use output::{OutputBuilder, OutputOptions};
+pub use parquet::ParquetOutput;
+pub use properties::{PropertiesConfig, PropertiesLayout, PropertyDef, PropertyKind};
+pub use sample::sample_sessions;
+pub use scenario::TestDataConfig;
 pub use session::{
     generate_day_seeds, DayGenerator, Session, SessionGenerator, Visitor, VisitorPool,
 };
+pub use shape::TrafficShape;
+pub use sink::{CopyFormat, DataSink, DuckDbSink, ParquetSink, SqlSink, StreamingOutput};
+pub use text::{CsvOutput, JsonLinesOutput};
+pub use validation::{ValidationReport, Validator, Violation};
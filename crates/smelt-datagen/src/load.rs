@@ -0,0 +1,342 @@
+//! Loading generated data straight into a smelt backend.
+//!
+//! Generation runs on a blocking thread and hands each batch over a bounded
+//! channel, so at most a few days are in flight while the backend loads them
+//! through [`Backend::load_record_batches`].
+
+use crate::naming::SchemaConfig;
+use crate::sink::{with_date_column, DataSink, StreamingOutput};
+use anyhow::{anyhow, bail, Context, Result};
+use arrow::datatypes::{DataType, Schema};
+use arrow::record_batch::RecordBatch;
+use chrono::NaiveDate;
+use smelt_backend::{Backend, RelationName, SqlDialect};
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+
+/// Batches generated ahead of the backend before generation waits.
+const LOAD_BUFFER: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Table {
+    Visitors,
+    Sessions,
+    Events,
+    Orders,
+    OrderItems,
+    Touchpoints,
+    ExpectedPlatformTotals,
+    ExpectedCategoryTotals,
+}
+
+impl Table {
+    fn name(&self) -> &'static str {
+        match self {
+            Table::Visitors => "visitors",
+            Table::Sessions => "sessions",
+            Table::Events => "events",
+            Table::Orders => "orders",
+            Table::OrderItems => "order_items",
+            Table::Touchpoints => "touchpoints",
+            Table::ExpectedPlatformTotals => "expected_platform_totals",
+            Table::ExpectedCategoryTotals => "expected_category_totals",
+        }
+    }
+}
+
+/// Forwards batches to the loading task, with date columns added.
+struct ChannelSink(mpsc::Sender<(Table, RecordBatch)>);
+
+impl ChannelSink {
+    fn send(&self, table: Table, batch: RecordBatch) -> Result<()> {
+        self.0
+            .blocking_send((table, batch))
+            .map_err(|_| anyhow!("Loading stopped before generation finished"))
+    }
+}
+
+impl DataSink for ChannelSink {
+    fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.send(Table::Visitors, batch.clone())
+    }
+
+    fn write_sessions(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.send(
+            Table::Sessions,
+            with_date_column(batch, "session_date", date)?,
+        )
+    }
+
+    fn write_events(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.send(Table::Events, with_date_column(batch, "event_date", date)?)
+    }
+
+    fn write_orders(
+        &mut self,
+        date: NaiveDate,
+        orders: &RecordBatch,
+        items: &RecordBatch,
+    ) -> Result<()> {
+        self.send(Table::Orders, with_date_column(orders, "order_date", date)?)?;
+        self.send(Table::OrderItems, items.clone())
+    }
+
+    fn write_touchpoints(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.send(
+            Table::Touchpoints,
+            with_date_column(batch, "conversion_date", date)?,
+        )
+    }
+
+    fn write_expected(
+        &mut self,
+        date: NaiveDate,
+        platforms: &RecordBatch,
+        categories: &RecordBatch,
+    ) -> Result<()> {
+        self.send(
+            Table::ExpectedPlatformTotals,
+            with_date_column(platforms, "session_date", date)?,
+        )?;
+        self.send(
+            Table::ExpectedCategoryTotals,
+            with_date_column(categories, "session_date", date)?,
+        )
+    }
+}
+
+/// Generate with `output` into `visitors` and `sessions` tables in `schema`,
+/// plus order, touchpoint, and expected-aggregate tables when enabled,
+/// returning the number of sessions loaded.
+///
+/// Shorthand for [`BackendLoader`] without renaming. Must be called from
+/// within a Tokio runtime.
+pub async fn load_into_backend(
+    backend: &dyn Backend,
+    schema: &str,
+    output: &StreamingOutput,
+    seed: u64,
+    num_sessions: usize,
+    num_days: u32,
+    start_date: NaiveDate,
+) -> Result<usize> {
+    BackendLoader::new(schema)
+        .load(backend, output, seed, num_sessions, num_days, start_date)
+        .await
+}
+
+/// Loads generated tables into one schema of a backend.
+///
+/// Missing tables are created from the generated schema; existing ones are
+/// appended to, with columns matched by position.
+#[derive(Debug, Clone)]
+pub struct BackendLoader {
+    schema: String,
+    naming: SchemaConfig,
+}
+
+impl BackendLoader {
+    pub fn new(schema: impl Into<String>) -> Self {
+        Self {
+            schema: schema.into(),
+            naming: SchemaConfig::default(),
+        }
+    }
+
+    /// Rename tables and columns to fit the project's sources.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Generate with `output` and load every table, returning the number of
+    /// sessions loaded. Must be called from within a Tokio runtime.
+    pub async fn load(
+        &self,
+        backend: &dyn Backend,
+        output: &StreamingOutput,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+    ) -> Result<usize> {
+        backend.ensure_schema(&self.schema).await?;
+
+        let (tx, mut rx) = mpsc::channel(LOAD_BUFFER);
+        let output = output.clone();
+        let generate = tokio::task::spawn_blocking(move || {
+            output.write_to(
+                &mut ChannelSink(tx),
+                seed,
+                num_sessions,
+                num_days,
+                start_date,
+            )
+        });
+
+        let mut ready = HashSet::new();
+        while let Some((table, batch)) = rx.recv().await {
+            let batch = self.naming.apply(table.name(), &batch)?;
+            let relation = RelationName::new(&self.schema, self.naming.table_name(table.name()));
+            if ready.insert(table) && !backend.table_exists(&relation).await? {
+                let sql = create_table_sql(backend.dialect(), &relation, &batch.schema())?;
+                backend.execute_sql(&sql).await?;
+            }
+            backend
+                .load_record_batches(&relation, &[batch])
+                .await
+                .with_context(|| format!("Failed to load generated {}", table.name()))?;
+        }
+
+        generate.await.context("Generation task failed")?
+    }
+}
+
+fn create_table_sql(
+    dialect: SqlDialect,
+    relation: &RelationName,
+    schema: &Schema,
+) -> Result<String> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            Ok(format!(
+                "{} {}",
+                dialect.quote_ident(field.name()),
+                column_type(field.data_type(), dialect)?
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!(
+        "CREATE TABLE {} ({})",
+        dialect.quote_relation(relation),
+        columns.join(", ")
+    ))
+}
+
+fn column_type(data_type: &DataType, dialect: SqlDialect) -> Result<&'static str> {
+    Ok(match data_type {
+        DataType::Utf8 => match dialect {
+            SqlDialect::SparkSQL => "STRING",
+            SqlDialect::SQLite => "TEXT",
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL | SqlDialect::Snowflake => "VARCHAR",
+        },
+        DataType::Int32 => "INTEGER",
+        DataType::Int64 => "BIGINT",
+        DataType::Float64 => match dialect {
+            SqlDialect::PostgreSQL => "DOUBLE PRECISION",
+            _ => "DOUBLE",
+        },
+        DataType::Boolean => "BOOLEAN",
+        DataType::Date32 => "DATE",
+        DataType::Timestamp(_, None) => match dialect {
+            SqlDialect::SQLite => "TEXT",
+            SqlDialect::Snowflake => "TIMESTAMP_NTZ",
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL | SqlDialect::SparkSQL => "TIMESTAMP",
+        },
+        other => bail!("No column type for {}", other),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_backend_duckdb::DuckDbBackend;
+    use smelt_backend_sqlite::SqliteBackend;
+    use tempfile::TempDir;
+
+    fn start_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    }
+
+    async fn assert_loaded(backend: &dyn Backend) {
+        let count = load_into_backend(
+            backend,
+            "main",
+            &StreamingOutput::new(),
+            42,
+            1000,
+            5,
+            start_date(),
+        )
+        .await
+        .unwrap();
+
+        let sessions = RelationName::new("main", "sessions");
+        assert_eq!(backend.get_row_count(&sessions).await.unwrap(), count);
+        let visitors = RelationName::new("main", "visitors");
+        assert!(backend.get_row_count(&visitors).await.unwrap() > 0);
+
+        let columns = backend.get_table_schema(&sessions).await.unwrap();
+        assert_eq!(columns.last().unwrap().name, "session_date");
+    }
+
+    #[tokio::test]
+    async fn test_load_into_duckdb() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        assert_loaded(&backend).await;
+    }
+
+    #[tokio::test]
+    async fn test_load_into_sqlite() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = SqliteBackend::new(&temp_dir.path().join("test.db"), "main")
+            .await
+            .unwrap();
+        assert_loaded(&backend).await;
+    }
+
+    #[tokio::test]
+    async fn test_load_with_naming() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let naming = SchemaConfig::new()
+            .with_table_name("sessions", "raw_sessions")
+            .with_column_name("visitor_id", "anonymous_id")
+            .with_column_name("session_date", "ds");
+        let count = BackendLoader::new("raw")
+            .with_naming(naming)
+            .load(&backend, &StreamingOutput::new(), 42, 500, 2, start_date())
+            .await
+            .unwrap();
+
+        let sessions = RelationName::new("raw", "raw_sessions");
+        assert_eq!(backend.get_row_count(&sessions).await.unwrap(), count);
+        let columns: Vec<_> = backend
+            .get_table_schema(&sessions)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert!(columns.contains(&"anonymous_id".to_string()));
+        assert_eq!(columns.last().unwrap(), "ds");
+    }
+
+    #[tokio::test]
+    async fn test_appends_to_existing_tables() {
+        let temp_dir = TempDir::new().unwrap();
+        let backend = DuckDbBackend::new(&temp_dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        let output = StreamingOutput::new();
+        let first = load_into_backend(&backend, "main", &output, 1, 200, 2, start_date())
+            .await
+            .unwrap();
+        let second = load_into_backend(&backend, "main", &output, 2, 200, 2, start_date())
+            .await
+            .unwrap();
+
+        let sessions = RelationName::new("main", "sessions");
+        assert_eq!(
+            backend.get_row_count(&sessions).await.unwrap(),
+            first + second
+        );
+    }
+}
@@ -2,7 +2,10 @@
 
 use anyhow::Result;
 use chrono::NaiveDate;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use smelt_datagen::OutputBuilder;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
@@ -11,43 +14,252 @@ use std::time::Instant;
 #[command(name = "smelt-datagen")]
 #[command(about = "Deterministic data generation for smelt")]
 struct Args {
-    /// Output directory for Hive-partitioned Parquet files
+    /// Generate everything described by a YAML scenario file instead; the
+    /// other generation options are ignored
+    #[arg(long)]
+    scenario: Option<PathBuf>,
+
+    /// Fit the distributions of an existing sessions dataset (a CSV file,
+    /// or a Parquet file or directory) and print a scenario mimicking it,
+    /// writing Parquet to --output; nothing is generated
+    #[arg(long, conflicts_with = "scenario")]
+    calibrate: Option<PathBuf>,
+
+    /// Check generated Parquet (a directory per table, or a sessions
+    /// directory) against the generator's invariants and report violations;
+    /// nothing is generated
+    #[arg(long, conflicts_with_all = ["scenario", "calibrate"])]
+    validate: Option<PathBuf>,
+
+    /// Output directory for Parquet, or file for CSV/NDJSON (`-` for stdout)
     #[arg(short, long, default_value = "output")]
     output: PathBuf,
 
+    /// Output format
+    #[arg(short, long, alias = "output-format", value_enum, default_value_t = OutputFormat::Parquet)]
+    format: OutputFormat,
+
+    /// DuckDB database file to append to, created if missing (DuckDB output)
+    #[arg(long)]
+    database: Option<PathBuf>,
+
+    /// Size preset; --num-sessions and --days override it
+    #[arg(short, long, value_enum, default_value_t = Preset::Large)]
+    preset: Preset,
+
     /// Random seed for deterministic generation
     #[arg(short, long, default_value = "42")]
     seed: u64,
 
+    /// Format of generated ids: uuid4, uuid7, hex or snowflake
+    #[arg(long, default_value = "uuid4")]
+    id_format: smelt_datagen::IdFormat,
+
     /// Number of sessions to generate
-    #[arg(short, long, default_value = "100000000")]
-    num_sessions: usize,
+    #[arg(short, long)]
+    num_sessions: Option<usize>,
 
     /// Number of days to spread sessions across
-    #[arg(short, long, default_value = "30")]
-    days: u32,
+    #[arg(short, long, conflicts_with = "end_date")]
+    days: Option<u32>,
 
     /// Start date (YYYY-MM-DD)
     #[arg(long, default_value = "2024-01-01")]
     start_date: String,
 
-    /// Quiet mode (no progress output)
+    /// Last date to generate, inclusive (YYYY-MM-DD); alternative to --days
+    #[arg(long)]
+    end_date: Option<String>,
+
+    /// Only write this date's partition (YYYY-MM-DD), exactly as it appears
+    /// in the full range; for simulating daily incremental loads
+    #[arg(long)]
+    day: Option<String>,
+
+    /// Only write partitions in this inclusive range (FIRST..LAST, or a
+    /// single YYYY-MM-DD), exactly as they appear in the full range
+    #[arg(long, conflicts_with = "day")]
+    only_days: Option<String>,
+
+    /// Rewrite partitions that already exist instead of skipping them
+    /// (Parquet output skips them by default, so interrupted runs resume)
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Generate and write this many days at once (Parquet output only;
+    /// defaults to one per core)
+    #[arg(long)]
+    workers: Option<usize>,
+
+    /// Also write the visitors dimension, with each visitor's first session
+    /// date, to this directory (Parquet output only)
+    #[arg(long)]
+    visitors: Option<PathBuf>,
+
+    /// Also write each session's events as Hive-partitioned Parquet under
+    /// this directory (Parquet output only)
+    #[arg(long)]
+    events: Option<PathBuf>,
+
+    /// CSV field delimiter
+    #[arg(long, default_value = ",")]
+    delimiter: char,
+
+    /// Omit the CSV header row
+    #[arg(long)]
+    no_header: bool,
+
+    /// strftime format for session_date in CSV/NDJSON output
+    #[arg(long, default_value = smelt_datagen::text::DEFAULT_DATE_FORMAT)]
+    date_format: String,
+
+    /// Quiet mode (no progress output); implied when writing to stdout
     #[arg(short, long)]
     quiet: bool,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OutputFormat {
+    /// Hive-partitioned Parquet files, one directory per session_date
+    Parquet,
+    /// A single delimited text file
+    Csv,
+    /// A single newline-delimited JSON file
+    Ndjson,
+    /// Tables appended to in the DuckDB database given by --database
+    Duckdb,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Preset {
+    /// 10 thousand sessions over 7 days
+    Tiny,
+    /// 1 million sessions over 30 days
+    Small,
+    /// 10 million sessions over 30 days
+    Medium,
+    /// 100 million sessions over 30 days
+    Large,
+}
+
+impl Preset {
+    /// (sessions, days)
+    fn size(self) -> (usize, u32) {
+        match self {
+            Preset::Tiny => (10_000, 7),
+            Preset::Small => (1_000_000, 30),
+            Preset::Medium => (10_000_000, 30),
+            Preset::Large => (100_000_000, 30),
+        }
+    }
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|e| anyhow::anyhow!("Invalid date format: {}", e))
+}
+
+/// Parse `FIRST..LAST`, or a single date meaning just that day.
+fn parse_day_range(s: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let (first, last) = match s.split_once("..") {
+        Some((first, last)) => (parse_date(first)?, parse_date(last)?),
+        None => {
+            let day = parse_date(s)?;
+            (day, day)
+        }
+    };
+    if last < first {
+        anyhow::bail!(
+            "--only-days range ends ({}) before it starts ({})",
+            last,
+            first
+        );
+    }
+    Ok((first, last))
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let start_date = NaiveDate::parse_from_str(&args.start_date, "%Y-%m-%d")
-        .map_err(|e| anyhow::anyhow!("Invalid date format: {}", e))?;
+    if let Some(path) = &args.calibrate {
+        let calibration =
+            smelt_datagen::Calibration::from_path(path, &smelt_datagen::SchemaConfig::default())?;
+        print!("{}", calibration.to_yaml(args.seed, &args.output));
+        return Ok(());
+    }
+
+    if let Some(path) = &args.validate {
+        let report = smelt_datagen::Validator::new().validate_parquet(path)?;
+        print!("{}", report);
+        return report.ensure_ok();
+    }
+
+    if let Some(path) = &args.scenario {
+        let scenario = smelt_datagen::TestDataConfig::from_file(path)?;
+        let start_time = Instant::now();
+        let counts = scenario.generate()?;
+        if !args.quiet {
+            for (output, count) in scenario.outputs.iter().zip(counts) {
+                eprintln!("Wrote {} sessions to {:?}", count, output);
+            }
+            eprintln!("Done in {:.2}s", start_time.elapsed().as_secs_f64());
+        }
+        return Ok(());
+    }
+
+    let start_date = parse_date(&args.start_date)?;
+
+    let (preset_sessions, preset_days) = args.preset.size();
+    let num_sessions = args.num_sessions.unwrap_or(preset_sessions);
+    let days = match &args.end_date {
+        Some(end) => {
+            let end_date = parse_date(end)?;
+            if end_date < start_date {
+                anyhow::bail!(
+                    "--end-date {} is before --start-date {}",
+                    end_date,
+                    start_date
+                );
+            }
+            (end_date - start_date).num_days() as u32 + 1
+        }
+        None => args.days.unwrap_or(preset_days),
+    };
+
+    let to_stdout = args.output.as_os_str() == "-";
+    if to_stdout && matches!(args.format, OutputFormat::Parquet) {
+        anyhow::bail!("Parquet output needs a directory; use --format csv or ndjson for stdout");
+    }
+    if args.database.is_some() != matches!(args.format, OutputFormat::Duckdb) {
+        anyhow::bail!("--database is required for, and only used by, DuckDB output");
+    }
+    let only_day = args.day.as_deref().map(parse_date).transpose()?;
+    if only_day.is_some() && !matches!(args.format, OutputFormat::Parquet | OutputFormat::Duckdb) {
+        anyhow::bail!("--day is only supported for Parquet and DuckDB output");
+    }
+    let only_days = args.only_days.as_deref().map(parse_day_range).transpose()?;
+    if (args.events.is_some()
+        || args.visitors.is_some()
+        || args.workers.is_some()
+        || only_days.is_some()
+        || args.overwrite)
+        && !matches!(args.format, OutputFormat::Parquet)
+    {
+        anyhow::bail!(
+            "--events, --visitors, --workers, --only-days and --overwrite are only supported for Parquet output"
+        );
+    }
+    if args.visitors.is_some() && only_day.is_some() {
+        anyhow::bail!("--visitors needs the full range; it can't be combined with --day");
+    }
+    let quiet = args.quiet || to_stdout;
 
-    if !args.quiet {
+    if !quiet {
+        println!("Generating {} sessions over {} days", num_sessions, days);
         println!(
-            "Generating {} sessions over {} days",
-            args.num_sessions, args.days
+            "Output: {:?}",
+            args.database.as_ref().unwrap_or(&args.output)
         );
-        println!("Output: {:?}", args.output);
         println!("Seed: {}", args.seed);
         println!();
     }
@@ -77,20 +289,79 @@ fn main() -> Result<()> {
     };
 
     let progress: Option<&(dyn Fn(usize, usize) + Sync)> =
-        if args.quiet { None } else { Some(&progress_fn) };
+        if quiet { None } else { Some(&progress_fn) };
 
-    let count = smelt_datagen::parquet::write_sessions_to_parquet(
-        &args.output,
-        args.seed,
-        args.num_sessions,
-        args.days,
-        start_date,
-        progress,
-    )?;
+    let count = match args.format {
+        OutputFormat::Parquet => {
+            let mut parquet = smelt_datagen::ParquetOutput::new().with_id_format(args.id_format);
+            if let Some((first, last)) = only_days {
+                parquet = parquet.with_only_days(first, last);
+            }
+            if !args.overwrite {
+                parquet = parquet.with_skip_existing();
+            }
+            if let Some(workers) = args.workers {
+                parquet = parquet.with_workers(workers);
+            }
+            if let Some(visitors_dir) = &args.visitors {
+                parquet = parquet.with_visitors(visitors_dir);
+            }
+            if let Some(events_dir) = &args.events {
+                parquet = parquet.with_events(events_dir, smelt_datagen::EventsConfig::default());
+            }
+            match only_day {
+                Some(day) => parquet.write_one_day(
+                    &args.output,
+                    args.seed,
+                    num_sessions,
+                    days,
+                    start_date,
+                    day,
+                )?,
+                None => parquet.write_days(
+                    &args.output,
+                    args.seed,
+                    num_sessions,
+                    days,
+                    start_date,
+                    progress,
+                )?,
+            }
+        }
+        OutputFormat::Duckdb => {
+            let database = args.database.as_deref().expect("checked above");
+            let mut sink = smelt_datagen::DuckDbSink::open(database)?;
+            let streaming = smelt_datagen::StreamingOutput::new().with_id_format(args.id_format);
+            match only_day {
+                Some(day) => streaming.write_day(
+                    &mut sink,
+                    args.seed,
+                    num_sessions,
+                    days,
+                    start_date,
+                    day,
+                )?,
+                None => streaming.write_to(&mut sink, args.seed, num_sessions, days, start_date)?,
+            }
+        }
+        OutputFormat::Csv | OutputFormat::Ndjson => {
+            let writer: Box<dyn Write> = if to_stdout {
+                Box::new(io::stdout().lock())
+            } else {
+                let file = File::create(&args.output)
+                    .map_err(|e| anyhow::anyhow!("Failed to create {:?}: {}", args.output, e))?;
+                Box::new(file)
+            };
+            let mut writer = BufWriter::new(writer);
+            let count = write_text(&args, &mut writer, num_sessions, days, start_date)?;
+            writer.flush()?;
+            count
+        }
+    };
 
     let elapsed = start_time.elapsed();
 
-    if !args.quiet {
+    if !quiet {
         eprintln!();
         println!();
         println!(
@@ -103,3 +374,28 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+fn write_text(
+    args: &Args,
+    writer: impl Write,
+    num_sessions: usize,
+    days: u32,
+    start_date: NaiveDate,
+) -> Result<usize> {
+    if matches!(args.format, OutputFormat::Csv) {
+        if !args.delimiter.is_ascii() {
+            anyhow::bail!("--delimiter must be a single ASCII character");
+        }
+        smelt_datagen::CsvOutput::new()
+            .with_delimiter(args.delimiter as u8)
+            .with_header(!args.no_header)
+            .with_date_format(&args.date_format)
+            .with_id_format(args.id_format)
+            .write_days(writer, args.seed, num_sessions, days, start_date)
+    } else {
+        smelt_datagen::JsonLinesOutput::new()
+            .with_date_format(&args.date_format)
+            .with_id_format(args.id_format)
+            .write_days(writer, args.seed, num_sessions, days, start_date)
+    }
+}
@@ -0,0 +1,123 @@
+//! Renaming generated tables and columns.
+//!
+//! Generated data uses fixed names (`visitors`, `visitor_id`, `session_date`,
+//! ...). A [`SchemaConfig`] maps them onto an existing project's conventions
+//! as each destination writes, so everything upstream, including dirty data
+//! and property settings, keeps referring to the generated names.
+
+use anyhow::{bail, Context, Result};
+use arrow::datatypes::Schema;
+use arrow::record_batch::RecordBatch;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// Table and column renames, keyed by generated name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaConfig {
+    tables: BTreeMap<String, String>,
+    columns: BTreeMap<String, String>,
+}
+
+impl SchemaConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the generated table `from` as `to`.
+    pub fn with_table_name(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.tables.insert(from.into(), to.into());
+        self
+    }
+
+    /// Rename column `from` to `to` in every table, or only in one table when
+    /// `from` is qualified as `table.column`. Qualified renames win.
+    pub fn with_column_name(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.columns.insert(from.into(), to.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.columns.is_empty()
+    }
+
+    /// Written name of the generated table `table`.
+    pub fn table_name<'a>(&'a self, table: &'a str) -> &'a str {
+        self.tables.get(table).map_or(table, String::as_str)
+    }
+
+    /// Written name of `column` in the generated table `table`.
+    pub fn column_name<'a>(&'a self, table: &str, column: &'a str) -> &'a str {
+        self.columns
+            .get(&format!("{}.{}", table, column))
+            .or_else(|| self.columns.get(column))
+            .map_or(column, String::as_str)
+    }
+
+    /// `batch` from the generated table `table` with its columns renamed.
+    pub fn apply(&self, table: &str, batch: &RecordBatch) -> Result<RecordBatch> {
+        if self.columns.is_empty() {
+            return Ok(batch.clone());
+        }
+        let mut seen = HashSet::new();
+        let fields = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| {
+                let name = self.column_name(table, field.name());
+                if !seen.insert(name.to_string()) {
+                    bail!("Renaming gives {} two columns named {:?}", table, name);
+                }
+                Ok(field.as_ref().clone().with_name(name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), batch.columns().to_vec())
+            .with_context(|| format!("Failed to rename {} columns", table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{ArrayRef, Int32Array};
+    use arrow::datatypes::{DataType, Field};
+
+    fn batch(names: &[&str]) -> RecordBatch {
+        let fields: Vec<Field> = names
+            .iter()
+            .map(|name| Field::new(*name, DataType::Int32, false))
+            .collect();
+        let columns: Vec<ArrayRef> = names
+            .iter()
+            .map(|_| Arc::new(Int32Array::from(vec![1])) as ArrayRef)
+            .collect();
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap()
+    }
+
+    #[test]
+    fn test_qualified_renames_win() {
+        let naming = SchemaConfig::new()
+            .with_table_name("sessions", "fct_sessions")
+            .with_column_name("visitor_id", "anonymous_id")
+            .with_column_name("visitors.visitor_id", "id");
+
+        assert_eq!(naming.table_name("sessions"), "fct_sessions");
+        assert_eq!(naming.table_name("events"), "events");
+
+        let sessions = naming
+            .apply("sessions", &batch(&["session_id", "visitor_id"]))
+            .unwrap();
+        let visitors = naming.apply("visitors", &batch(&["visitor_id"])).unwrap();
+        assert_eq!(sessions.schema().field(1).name(), "anonymous_id");
+        assert_eq!(sessions.schema().field(0).name(), "session_id");
+        assert_eq!(visitors.schema().field(0).name(), "id");
+    }
+
+    #[test]
+    fn test_rejects_colliding_names() {
+        let naming = SchemaConfig::new().with_column_name("visitor_id", "session_id");
+        assert!(naming
+            .apply("sessions", &batch(&["session_id", "visitor_id"]))
+            .is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Orders and order line items.
+//!
+//! Every session with purchases places one order. Its purchased units are
+//! split into line items for catalog products in the session's category, so
+//! an order's total is always the sum of its lines' `quantity * unit_price`.
+//! A configurable fraction of orders is later refunded in full.
+
+use crate::catalog::Catalog;
+use crate::gen::Gen;
+use crate::generators::uuid_gen;
+use crate::session::Session;
+use chrono::{Duration, NaiveDate};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use uuid::Uuid;
+
+/// RNG stream for orders, clear of the other per-day streams.
+const ORDER_STREAM: u64 = 0x500;
+
+/// Order status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    Completed,
+    Refunded,
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Completed => "completed",
+            OrderStatus::Refunded => "refunded",
+        }
+    }
+}
+
+/// An order placed in a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub order_id: Uuid,
+    pub session_id: Uuid,
+    pub visitor_id: Uuid,
+    pub order_date: NaiveDate,
+    pub status: OrderStatus,
+    /// Sum of the order's line totals, in cents.
+    pub total: i64,
+    pub refund_date: Option<NaiveDate>,
+}
+
+/// One product line of an order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderItem {
+    pub order_id: Uuid,
+    /// 1-based position within the order.
+    pub line_number: i32,
+    pub product_id: i64,
+    pub quantity: i32,
+    /// Catalog price in cents.
+    pub unit_price: i32,
+    pub line_total: i64,
+}
+
+/// Shape of generated orders.
+#[derive(Debug, Clone)]
+pub struct OrdersConfig {
+    /// Fraction of orders refunded.
+    pub refund_rate: f64,
+    /// Most units of one product on a single line.
+    pub max_quantity: i32,
+    /// Refunds happen up to this many days after the order.
+    pub refund_window_days: i64,
+}
+
+impl Default for OrdersConfig {
+    fn default() -> Self {
+        Self {
+            refund_rate: 0.05,
+            max_quantity: 3,
+            refund_window_days: 30,
+        }
+    }
+}
+
+impl OrdersConfig {
+    /// Orders and their line items for one day's sessions, from `day_seed`.
+    pub fn generate(
+        &self,
+        catalog: &Catalog,
+        day_seed: u64,
+        sessions: &[Session],
+    ) -> (Vec<Order>, Vec<OrderItem>) {
+        let mut rng = ChaCha8Rng::seed_from_u64(day_seed);
+        rng.set_stream(ORDER_STREAM);
+        let uuid_g = uuid_gen();
+
+        let mut orders = Vec::new();
+        let mut items = Vec::new();
+        for session in sessions.iter().filter(|s| s.product_purchase_count > 0) {
+            let order_id = uuid_g.generate(&mut rng);
+            let mut remaining = session.product_purchase_count;
+            let mut total = 0;
+            let mut line_number = 0;
+            while remaining > 0 {
+                let quantity = rng.gen_range(1..=remaining.min(self.max_quantity.max(1)));
+                let product = catalog.sample(&mut rng, session.product_category);
+                let line_total = quantity as i64 * product.price as i64;
+                line_number += 1;
+                items.push(OrderItem {
+                    order_id,
+                    line_number,
+                    product_id: product.product_id,
+                    quantity,
+                    unit_price: product.price,
+                    line_total,
+                });
+                total += line_total;
+                remaining -= quantity;
+            }
+
+            let refund_date = rng.gen_bool(self.refund_rate).then(|| {
+                let days = rng.gen_range(1..=self.refund_window_days.max(1));
+                session.session_date + Duration::days(days)
+            });
+            orders.push(Order {
+                order_id,
+                session_id: session.session_id,
+                visitor_id: session.visitor_id,
+                order_date: session.session_date,
+                status: if refund_date.is_some() {
+                    OrderStatus::Refunded
+                } else {
+                    OrderStatus::Completed
+                },
+                total,
+                refund_date,
+            });
+        }
+        (orders, items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::CatalogConfig;
+    use crate::session::{generate_day_seeds, DayGenerator, VisitorPool};
+    use std::collections::HashMap;
+
+    fn day() -> (u64, Vec<Session>) {
+        let day_seed = generate_day_seeds(42, 1)[0];
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sessions =
+            DayGenerator::new(VisitorPool::new(42, 10_000), day_seed, date, 2_000).generate();
+        (day_seed, sessions)
+    }
+
+    #[test]
+    fn test_totals_match_line_items() {
+        let catalog = Catalog::generate(42, &CatalogConfig::default()).unwrap();
+        let (day_seed, sessions) = day();
+        let (orders, items) = OrdersConfig::default().generate(&catalog, day_seed, &sessions);
+
+        let buyers = sessions
+            .iter()
+            .filter(|s| s.product_purchase_count > 0)
+            .count();
+        assert_eq!(orders.len(), buyers);
+
+        let mut line_totals: HashMap<Uuid, i64> = HashMap::new();
+        for item in &items {
+            assert_eq!(
+                item.line_total,
+                item.quantity as i64 * item.unit_price as i64
+            );
+            *line_totals.entry(item.order_id).or_default() += item.line_total;
+        }
+        for order in &orders {
+            assert_eq!(line_totals[&order.order_id], order.total);
+        }
+
+        let units: i32 = items.iter().map(|i| i.quantity).sum();
+        let purchased: i32 = sessions.iter().map(|s| s.product_purchase_count).sum();
+        assert_eq!(units, purchased);
+    }
+
+    #[test]
+    fn test_refund_rate() {
+        let catalog = Catalog::generate(42, &CatalogConfig::default()).unwrap();
+        let (day_seed, sessions) = day();
+        let config = OrdersConfig {
+            refund_rate: 1.0,
+            ..OrdersConfig::default()
+        };
+        let (orders, _) = config.generate(&catalog, day_seed, &sessions);
+        assert!(!orders.is_empty());
+        for order in &orders {
+            assert_eq!(order.status, OrderStatus::Refunded);
+            assert!(order.refund_date.unwrap() > order.order_date);
+        }
+    }
+}
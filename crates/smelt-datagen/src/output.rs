@@ -0,0 +1,442 @@
+//! Generation options shared by every writer.
+
+use crate::accounts::{Account, AccountConfig};
+use crate::anomaly::AnomalyConfig;
+use crate::attribution::AttributionConfig;
+use crate::catalog::Catalog;
+use crate::dirty::DirtyDataConfig;
+use crate::enrich::EnrichmentConfig;
+use crate::events::EventsConfig;
+use crate::expected::ExpectedAggregates;
+use crate::funnel::Funnel;
+use crate::ids::IdFormat;
+use crate::orders::OrdersConfig;
+use crate::parquet::{
+    events_to_record_batch, expected_to_record_batches, order_items_to_record_batch,
+    orders_to_record_batch, touchpoints_to_record_batch, visitors_batch,
+};
+use crate::properties::PropertiesConfig;
+use crate::session::{generate_day_seeds, DayGenerator, Session, VisitorPool};
+use crate::shape::TrafficShape;
+use anyhow::{bail, Context, Result};
+use arrow::record_batch::RecordBatch;
+use chrono::NaiveDate;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Each visitor's first session date, recorded as days are generated.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FirstSeen(HashMap<Uuid, NaiveDate>);
+
+impl FirstSeen {
+    /// Note `date`'s sessions, in any day order.
+    pub fn record(&mut self, date: NaiveDate, sessions: &[Session]) {
+        for session in sessions {
+            self.0
+                .entry(session.visitor_id)
+                .and_modify(|first| *first = (*first).min(date))
+                .or_insert(date);
+        }
+    }
+
+    /// Merge another set of records into this one.
+    pub fn merge(&mut self, other: FirstSeen) {
+        for (visitor_id, date) in other.0 {
+            self.0
+                .entry(visitor_id)
+                .and_modify(|first| *first = (*first).min(date))
+                .or_insert(date);
+        }
+    }
+
+    /// The date of `visitor_id`'s first session, if it had one.
+    pub fn get(&self, visitor_id: &Uuid) -> Option<NaiveDate> {
+        self.0.get(visitor_id).copied()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    pub(crate) funnel: Option<Funnel>,
+    pub(crate) shape: Option<Arc<TrafficShape>>,
+    pub(crate) anomalies: Option<Arc<AnomalyConfig>>,
+    pub(crate) accounts: Option<AccountConfig>,
+    pub(crate) catalog: Option<Arc<Catalog>>,
+    pub(crate) enrichment: Option<EnrichmentConfig>,
+    pub(crate) properties: Option<PropertiesConfig>,
+    pub(crate) dirty_data: Option<DirtyDataConfig>,
+    pub(crate) events: Option<EventsConfig>,
+    pub(crate) orders: Option<OrdersConfig>,
+    pub(crate) attribution: Option<AttributionConfig>,
+    pub(crate) expected_aggregates: bool,
+    pub(crate) ids: IdFormat,
+}
+
+/// Builder methods for the generation options every writer shares:
+/// [`CsvOutput`](crate::CsvOutput), [`JsonLinesOutput`](crate::JsonLinesOutput),
+/// [`ParquetOutput`](crate::ParquetOutput), and
+/// [`StreamingOutput`](crate::StreamingOutput).
+pub trait OutputBuilder: Sized {
+    /// The writer's options, for the provided methods to set.
+    fn options_mut(&mut self) -> &mut OutputOptions;
+
+    /// Use these funnel continuation rates instead of the defaults.
+    fn with_funnel(mut self, funnel: Funnel) -> Self {
+        self.options_mut().funnel = Some(funnel);
+        self
+    }
+
+    /// Draw the traffic mix and session shape from `shape`.
+    fn with_shape(mut self, shape: TrafficShape) -> Self {
+        self.options_mut().shape = Some(Arc::new(shape));
+        self
+    }
+
+    /// Inject anomalies into the generated sessions.
+    fn with_anomalies(mut self, anomalies: AnomalyConfig) -> Self {
+        self.options_mut().anomalies = Some(Arc::new(anomalies));
+        self
+    }
+
+    /// Give every visitor an account and only generate sessions while it's active.
+    fn with_accounts(mut self, accounts: AccountConfig) -> Self {
+        self.options_mut().accounts = Some(accounts);
+        self
+    }
+
+    /// Reference an item from `catalog` in each row.
+    fn with_catalog(mut self, catalog: Catalog) -> Self {
+        self.options_mut().catalog = Some(Arc::new(catalog));
+        self
+    }
+
+    /// Add geo and device columns.
+    fn with_enrichment(mut self, enrichment: EnrichmentConfig) -> Self {
+        self.options_mut().enrichment = Some(enrichment);
+        self
+    }
+
+    /// Add custom property columns to each row.
+    fn with_properties(mut self, properties: PropertiesConfig) -> Self {
+        self.options_mut().properties = Some(properties);
+        self
+    }
+
+    /// Inject nulls and malformed values into the written rows.
+    fn with_dirty_data(mut self, dirty_data: DirtyDataConfig) -> Self {
+        self.options_mut().dirty_data = Some(dirty_data);
+        self
+    }
+
+    /// Write ids in `ids` format instead of random UUIDs.
+    fn with_id_format(mut self, ids: IdFormat) -> Self {
+        self.options_mut().ids = ids;
+        self
+    }
+}
+
+impl OutputOptions {
+    /// The run's visitor pool, following the shape if one is set. Visitor
+    /// ids are stamped as created at the start of the run.
+    pub(crate) fn visitor_pool(
+        &self,
+        seed: u64,
+        num_sessions: usize,
+        start_date: NaiveDate,
+    ) -> VisitorPool {
+        let pool = match &self.shape {
+            Some(shape) => VisitorPool::with_shape(seed, num_sessions, shape),
+            None => VisitorPool::new(seed, num_sessions),
+        };
+        if self.ids == IdFormat::Uuid4 {
+            return pool;
+        }
+        pool.map_ids(|id| self.ids.stamp_date(id, start_date))
+    }
+
+    /// Stamp a day's ids with their creation time: visitors, including bots,
+    /// at the start of the run and sessions on their date.
+    pub(crate) fn stamp_sessions(&self, sessions: &mut [Session], start_date: NaiveDate) {
+        if self.ids == IdFormat::Uuid4 {
+            return;
+        }
+        for session in sessions {
+            session.visitor_id = self.ids.stamp_date(session.visitor_id, start_date);
+            session.session_id = self
+                .ids
+                .stamp_date(session.session_id, session.session_date);
+        }
+    }
+
+    /// Every visitor in the pool, with geo columns when enrichment is set.
+    pub(crate) fn visitors_batch(
+        &self,
+        visitor_pool: &VisitorPool,
+        first_seen: &FirstSeen,
+    ) -> Result<RecordBatch> {
+        self.ids.apply(&visitors_batch(
+            visitor_pool,
+            first_seen,
+            self.enrichment.as_ref(),
+        )?)
+    }
+
+    /// Accounts for the run, if enabled.
+    pub(crate) fn accounts(
+        &self,
+        seed: u64,
+        visitor_pool: &VisitorPool,
+        start_date: NaiveDate,
+        num_days: u32,
+    ) -> Option<Arc<Vec<Account>>> {
+        self.accounts.as_ref().map(|config| {
+            let mut accounts = config.generate(seed, visitor_pool, start_date, num_days);
+            for account in &mut accounts {
+                account.account_id = self.ids.stamp_date(account.account_id, account.signup_date);
+            }
+            Arc::new(accounts)
+        })
+    }
+
+    /// Generator for one day, with anomalies placed from the root `seed`.
+    pub(crate) fn day_generator(
+        &self,
+        visitor_pool: VisitorPool,
+        accounts: Option<&Arc<Vec<Account>>>,
+        seed: u64,
+        day_seed: u64,
+        date: NaiveDate,
+        sessions_per_day: usize,
+    ) -> DayGenerator {
+        let mut generator = DayGenerator::new(visitor_pool, day_seed, date, sessions_per_day);
+        if let Some(funnel) = self.funnel {
+            generator = generator.with_funnel(funnel);
+        }
+        if let Some(shape) = &self.shape {
+            generator = generator.with_shape(shape.clone());
+        }
+        if let Some(anomalies) = &self.anomalies {
+            generator = generator.with_anomalies(anomalies.clone(), seed);
+        }
+        if let Some(accounts) = accounts {
+            generator = generator.with_accounts(accounts.clone());
+        }
+        generator
+    }
+
+    /// Add catalog items, enrichment, properties, and ground truth to a day's
+    /// batch, write its ids in the chosen format, then add dirty data.
+    pub(crate) fn finish_batch(
+        &self,
+        mut batch: RecordBatch,
+        seed: u64,
+        day_seed: u64,
+    ) -> Result<RecordBatch> {
+        if let Some(catalog) = &self.catalog {
+            batch = catalog.apply(&batch, day_seed)?;
+        }
+        if let Some(enrichment) = &self.enrichment {
+            batch = enrichment.apply(&batch)?;
+        }
+        if let Some(properties) = &self.properties {
+            batch = properties.apply(&batch, day_seed)?;
+        }
+        if let Some(anomalies) = &self.anomalies {
+            batch = anomalies.label(seed, &batch)?;
+        }
+        batch = self.ids.apply(&batch)?;
+        if let Some(dirty) = &self.dirty_data {
+            batch = dirty.apply(&batch, day_seed)?;
+        }
+        Ok(batch)
+    }
+
+    /// Event batch for a day's sessions, if enabled.
+    pub(crate) fn event_batch(
+        &self,
+        sessions: &[Session],
+        day_seed: u64,
+    ) -> Result<Option<RecordBatch>> {
+        let Some(events) = &self.events else {
+            return Ok(None);
+        };
+        let mut events = events.generate(day_seed, sessions);
+        for event in &mut events {
+            event.event_id = self.ids.stamp(event.event_id, event.event_timestamp);
+        }
+        self.ids.apply(&events_to_record_batch(&events)?).map(Some)
+    }
+
+    /// Orders and order item batches for a day's sessions, if enabled.
+    pub(crate) fn order_batches(
+        &self,
+        sessions: &[Session],
+        day_seed: u64,
+    ) -> Result<Option<(RecordBatch, RecordBatch)>> {
+        let Some(orders) = &self.orders else {
+            return Ok(None);
+        };
+        let catalog = self
+            .catalog
+            .as_ref()
+            .context("Orders need a catalog to price their items")?;
+        let (mut orders, mut items) = orders.generate(catalog, day_seed, sessions);
+        let mut stamped = HashMap::new();
+        for order in &mut orders {
+            let id = self.ids.stamp_date(order.order_id, order.order_date);
+            stamped.insert(order.order_id, id);
+            order.order_id = id;
+        }
+        for item in &mut items {
+            item.order_id = stamped[&item.order_id];
+        }
+        Ok(Some((
+            self.ids.apply(&orders_to_record_batch(&orders)?)?,
+            self.ids.apply(&order_items_to_record_batch(&items)?)?,
+        )))
+    }
+
+    /// Touchpoint batch for a day's conversions, if enabled.
+    pub(crate) fn touchpoint_batch(
+        &self,
+        sessions: &[Session],
+        day_seed: u64,
+    ) -> Result<Option<RecordBatch>> {
+        let Some(attribution) = &self.attribution else {
+            return Ok(None);
+        };
+        let mut touchpoints = attribution.generate(day_seed, sessions);
+        for touch in &mut touchpoints {
+            touch.touchpoint_id = self.ids.stamp_date(touch.touchpoint_id, touch.touch_date);
+        }
+        self.ids
+            .apply(&touchpoints_to_record_batch(&touchpoints)?)
+            .map(Some)
+    }
+
+    /// Expected per-platform and per-category batches for a day, if enabled.
+    pub(crate) fn expected_batches(
+        &self,
+        sessions: &[Session],
+    ) -> Result<Option<(RecordBatch, RecordBatch)>> {
+        if !self.expected_aggregates {
+            return Ok(None);
+        }
+        expected_to_record_batches(&ExpectedAggregates::from_sessions(sessions)).map(Some)
+    }
+
+    /// Sessions for just `date` within the range, with its day seed.
+    ///
+    /// Identical to that day's sessions from [`Self::for_each_day`] with the
+    /// same arguments, so days can be generated one at a time.
+    pub(crate) fn single_day(
+        &self,
+        visitor_pool: &VisitorPool,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+        date: NaiveDate,
+    ) -> Result<(u64, Vec<Session>)> {
+        let index = (date - start_date).num_days();
+        if index < 0 || index >= num_days as i64 {
+            bail!(
+                "{} is outside the {} days starting {}",
+                date,
+                num_days,
+                start_date
+            );
+        }
+        let accounts = self.accounts(seed, visitor_pool, start_date, num_days);
+        let day_seed = generate_day_seeds(seed, index as u32 + 1)[index as usize];
+        let mut sessions = self
+            .day_generator(
+                visitor_pool.clone(),
+                accounts.as_ref(),
+                seed,
+                day_seed,
+                date,
+                num_sessions / num_days as usize,
+            )
+            .generate();
+        self.stamp_sessions(&mut sessions, start_date);
+        Ok((day_seed, sessions))
+    }
+
+    /// First-seen dates over the range, from a generation pass whose
+    /// sessions are discarded.
+    pub(crate) fn first_seen(
+        &self,
+        visitor_pool: &VisitorPool,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+    ) -> Result<FirstSeen> {
+        let mut first_seen = FirstSeen::default();
+        self.for_each_day(
+            visitor_pool,
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            |date, _, sessions| {
+                first_seen.record(date, sessions);
+                Ok(())
+            },
+        )?;
+        Ok(first_seen)
+    }
+
+    /// Generate each day's sessions and hand them to `write` in date order.
+    ///
+    /// Days are generated in parallel, one batch of days per round so at most
+    /// one day per thread is held in memory while waiting to be written.
+    pub(crate) fn for_each_day(
+        &self,
+        visitor_pool: &VisitorPool,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+        mut write: impl FnMut(NaiveDate, u64, &[Session]) -> Result<()>,
+    ) -> Result<usize> {
+        let accounts = self.accounts(seed, visitor_pool, start_date, num_days);
+        let day_seeds = generate_day_seeds(seed, num_days);
+        let sessions_per_day = num_sessions / num_days as usize;
+
+        let days: Vec<_> = day_seeds
+            .into_iter()
+            .enumerate()
+            .map(|(i, day_seed)| (start_date + chrono::Duration::days(i as i64), day_seed))
+            .collect();
+
+        let mut total = 0;
+        for round in days.chunks(rayon::current_num_threads()) {
+            let generated: Vec<Vec<Session>> = round
+                .par_iter()
+                .map(|(date, day_seed)| {
+                    let mut sessions = self
+                        .day_generator(
+                            visitor_pool.clone(),
+                            accounts.as_ref(),
+                            seed,
+                            *day_seed,
+                            *date,
+                            sessions_per_day,
+                        )
+                        .generate();
+                    self.stamp_sessions(&mut sessions, start_date);
+                    sessions
+                })
+                .collect();
+            for ((date, day_seed), sessions) in round.iter().zip(&generated) {
+                write(*date, *day_seed, sessions)?;
+                total += sessions.len();
+            }
+        }
+        Ok(total)
+    }
+}
@@ -1,21 +1,37 @@
 //! Parquet writer with Hive-style partitioning.
+//!
+//! Sessions are partitioned by `session_date`; the visitors they belong to are
+//! written unpartitioned, so session data can be joined back to visitors.
 
-use crate::session::{generate_day_seeds, DayGenerator, Session, VisitorPool};
+use crate::accounts::{Account, AccountConfig};
+use crate::attribution::Touchpoint;
+use crate::catalog::Catalog;
+use crate::enrich::EnrichmentConfig;
+use crate::events::{Event, EventsConfig};
+use crate::expected::ExpectedAggregates;
+use crate::ids::IdFormat;
+use crate::naming::SchemaConfig;
+use crate::orders::{Order, OrderItem};
+use crate::output::{FirstSeen, OutputBuilder, OutputOptions};
+use crate::session::{generate_day_seeds, Session, Visitor, VisitorPool};
 use anyhow::{Context, Result};
-use arrow::array::{ArrayRef, Int32Array, StringBuilder};
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::array::{
+    ArrayRef, Date32Array, Float64Array, Int32Array, Int64Array, StringArray, StringBuilder,
+    TimestampMicrosecondArray,
+};
+use arrow::datatypes::{DataType, Date32Type, Field, Schema, TimeUnit};
 use arrow::record_batch::RecordBatch;
 use chrono::NaiveDate;
 use parquet::arrow::ArrowWriter;
 use parquet::file::properties::WriterProperties;
 use rayon::prelude::*;
 use std::fs::{self, File};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 /// Schema for session records (without session_date, which is the partition key).
-fn session_schema() -> Schema {
+pub(crate) fn session_schema() -> Schema {
     Schema::new(vec![
         Field::new("visitor_id", DataType::Utf8, false),
         Field::new("session_id", DataType::Utf8, false),
@@ -24,6 +40,8 @@ fn session_schema() -> Schema {
         Field::new("visit_campaign", DataType::Utf8, true),
         Field::new("widget_views", DataType::Int32, false),
         Field::new("product_views", DataType::Int32, false),
+        Field::new("product_add_to_cart_count", DataType::Int32, false),
+        Field::new("product_checkout_count", DataType::Int32, false),
         Field::new("product_category", DataType::Utf8, false),
         Field::new("product_revenue", DataType::Int32, false),
         Field::new("product_purchase_count", DataType::Int32, false),
@@ -36,24 +54,24 @@ pub fn write_day_to_parquet(
     date: NaiveDate,
     sessions: &[Session],
 ) -> Result<usize> {
-    if sessions.is_empty() {
-        return Ok(0);
-    }
-
-    // Create partition directory: output_dir/session_date=YYYY-MM-DD/
-    let partition_dir = output_dir.join(format!("session_date={}", date));
-    fs::create_dir_all(&partition_dir)
-        .with_context(|| format!("Failed to create partition directory: {:?}", partition_dir))?;
-
-    let file_path = partition_dir.join("data.parquet");
-    let file = File::create(&file_path)
-        .with_context(|| format!("Failed to create parquet file: {:?}", file_path))?;
+    ParquetOutput::new().write_day(output_dir, 0, date, sessions, 0)
+}
 
-    // Convert sessions to Arrow arrays
-    let schema = Arc::new(session_schema());
-    let batch = sessions_to_record_batch(sessions, &schema)?;
+/// Write a record batch to a Snappy-compressed Parquet file.
+///
+/// The file is written under a temporary name and renamed into place, so an
+/// interrupted run never leaves a truncated `file_path` behind.
+pub(crate) fn write_batch(
+    file_path: &Path,
+    schema: Arc<Schema>,
+    batch: &RecordBatch,
+) -> Result<()> {
+    let mut temp_name = file_path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = file_path.with_file_name(temp_name);
+    let file = File::create(&temp_path)
+        .with_context(|| format!("Failed to create parquet file: {:?}", temp_path))?;
 
-    // Write to Parquet
     let props = WriterProperties::builder()
         .set_compression(parquet::basic::Compression::SNAPPY)
         .build();
@@ -62,14 +80,18 @@ pub fn write_day_to_parquet(
         .context("Failed to create Parquet writer")?;
 
     writer
-        .write(&batch)
+        .write(batch)
         .context("Failed to write record batch")?;
     writer.close().context("Failed to close Parquet writer")?;
 
-    Ok(sessions.len())
+    fs::rename(&temp_path, file_path)
+        .with_context(|| format!("Failed to move parquet file into place: {:?}", file_path))
 }
 
-fn sessions_to_record_batch(sessions: &[Session], schema: &Arc<Schema>) -> Result<RecordBatch> {
+pub(crate) fn sessions_to_record_batch(
+    sessions: &[Session],
+    schema: &Arc<Schema>,
+) -> Result<RecordBatch> {
     let mut visitor_ids = StringBuilder::new();
     let mut session_ids = StringBuilder::new();
     let mut platforms = StringBuilder::new();
@@ -77,6 +99,8 @@ fn sessions_to_record_batch(sessions: &[Session], schema: &Arc<Schema>) -> Resul
     let mut visit_campaigns = StringBuilder::new();
     let mut widget_views: Vec<i32> = Vec::with_capacity(sessions.len());
     let mut product_views: Vec<i32> = Vec::with_capacity(sessions.len());
+    let mut product_add_to_cart_counts: Vec<i32> = Vec::with_capacity(sessions.len());
+    let mut product_checkout_counts: Vec<i32> = Vec::with_capacity(sessions.len());
     let mut product_categories = StringBuilder::new();
     let mut product_revenues: Vec<i32> = Vec::with_capacity(sessions.len());
     let mut product_purchase_counts: Vec<i32> = Vec::with_capacity(sessions.len());
@@ -92,6 +116,8 @@ fn sessions_to_record_batch(sessions: &[Session], schema: &Arc<Schema>) -> Resul
         }
         widget_views.push(session.widget_views);
         product_views.push(session.product_views);
+        product_add_to_cart_counts.push(session.product_add_to_cart_count);
+        product_checkout_counts.push(session.product_checkout_count);
         product_categories.append_value(session.product_category.as_str());
         product_revenues.push(session.product_revenue);
         product_purchase_counts.push(session.product_purchase_count);
@@ -105,6 +131,8 @@ fn sessions_to_record_batch(sessions: &[Session], schema: &Arc<Schema>) -> Resul
         Arc::new(visit_campaigns.finish()),
         Arc::new(Int32Array::from(widget_views)),
         Arc::new(Int32Array::from(product_views)),
+        Arc::new(Int32Array::from(product_add_to_cart_counts)),
+        Arc::new(Int32Array::from(product_checkout_counts)),
         Arc::new(product_categories.finish()),
         Arc::new(Int32Array::from(product_revenues)),
         Arc::new(Int32Array::from(product_purchase_counts)),
@@ -113,6 +141,496 @@ fn sessions_to_record_batch(sessions: &[Session], schema: &Arc<Schema>) -> Resul
     RecordBatch::try_new(schema.clone(), columns).context("Failed to create record batch")
 }
 
+/// Schema for visitor records.
+fn visitor_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("visitor_id", DataType::Utf8, false),
+        Field::new("platform_preference", DataType::Utf8, false),
+        Field::new("return_probability", DataType::Float64, false),
+        // Date of the visitor's first session in the range; null if none.
+        Field::new("first_seen", DataType::Date32, true),
+    ])
+}
+
+fn visitors_to_record_batch(
+    visitors: &[Visitor],
+    first_seen: &FirstSeen,
+    schema: &Arc<Schema>,
+) -> Result<RecordBatch> {
+    let mut visitor_ids = StringBuilder::new();
+    let mut platform_preferences = StringBuilder::new();
+    let mut return_probabilities: Vec<f64> = Vec::with_capacity(visitors.len());
+    let mut first_seen_dates: Vec<Option<i32>> = Vec::with_capacity(visitors.len());
+
+    for visitor in visitors {
+        visitor_ids.append_value(visitor.id.to_string());
+        platform_preferences.append_value(visitor.platform_preference.as_str());
+        return_probabilities.push(visitor.return_probability);
+        first_seen_dates.push(first_seen.get(&visitor.id).map(Date32Type::from_naive_date));
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(visitor_ids.finish()),
+        Arc::new(platform_preferences.finish()),
+        Arc::new(Float64Array::from(return_probabilities)),
+        Arc::new(Date32Array::from(first_seen_dates)),
+    ];
+
+    RecordBatch::try_new(schema.clone(), columns).context("Failed to create record batch")
+}
+
+/// Every visitor in `pool`, with geo columns when `enrichment` is set.
+pub(crate) fn visitors_batch(
+    pool: &VisitorPool,
+    first_seen: &FirstSeen,
+    enrichment: Option<&EnrichmentConfig>,
+) -> Result<RecordBatch> {
+    let batch = visitors_to_record_batch(pool.visitors(), first_seen, &Arc::new(visitor_schema()))?;
+    match enrichment {
+        Some(enrichment) => enrichment.clone().with_device(false).apply(&batch),
+        None => Ok(batch),
+    }
+}
+
+/// Write the visitor pool for `seed` to `output_dir/data.parquet`.
+///
+/// Uses the same pool as [`write_sessions_to_parquet`] with the same
+/// arguments, so every session's `visitor_id` is found here, and its
+/// `first_seen` is the visitor's first session date in that output. This
+/// generates the sessions again to find them; use
+/// [`ParquetOutput::with_visitors`] to write both in one pass. With
+/// `enrichment`, visitors get the same geo columns as their sessions.
+pub fn write_visitors_to_parquet(
+    output_dir: &Path,
+    seed: u64,
+    num_sessions: usize,
+    num_days: u32,
+    start_date: NaiveDate,
+    enrichment: Option<&EnrichmentConfig>,
+) -> Result<usize> {
+    let visitor_pool = VisitorPool::new(seed, num_sessions);
+    let first_seen = OutputOptions::default().first_seen(
+        &visitor_pool,
+        seed,
+        num_sessions,
+        num_days,
+        start_date,
+    )?;
+    let options = OutputOptions {
+        enrichment: enrichment.cloned(),
+        ..OutputOptions::default()
+    };
+    write_visitors(
+        output_dir,
+        &visitor_pool,
+        &first_seen,
+        &options,
+        &SchemaConfig::default(),
+    )
+}
+
+/// Write `visitor_pool` to `output_dir/data.parquet` with columns renamed.
+fn write_visitors(
+    output_dir: &Path,
+    visitor_pool: &VisitorPool,
+    first_seen: &FirstSeen,
+    options: &OutputOptions,
+    naming: &SchemaConfig,
+) -> Result<usize> {
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory: {:?}", output_dir))?;
+
+    let batch = naming.apply(
+        "visitors",
+        &options.visitors_batch(visitor_pool, first_seen)?,
+    )?;
+
+    write_batch(&output_dir.join("data.parquet"), batch.schema(), &batch)?;
+
+    Ok(visitor_pool.len())
+}
+
+/// Schema for account records.
+fn account_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("account_id", DataType::Utf8, false),
+        Field::new("visitor_id", DataType::Utf8, false),
+        Field::new("signup_date", DataType::Date32, false),
+        Field::new("country", DataType::Utf8, false),
+        Field::new("plan", DataType::Utf8, false),
+        Field::new("churn_date", DataType::Date32, true),
+    ])
+}
+
+fn accounts_to_record_batch(accounts: &[Account], schema: &Arc<Schema>) -> Result<RecordBatch> {
+    let mut account_ids = StringBuilder::new();
+    let mut visitor_ids = StringBuilder::new();
+    let mut signup_dates: Vec<i32> = Vec::with_capacity(accounts.len());
+    let mut countries = StringBuilder::new();
+    let mut plans = StringBuilder::new();
+    let mut churn_dates: Vec<Option<i32>> = Vec::with_capacity(accounts.len());
+
+    for account in accounts {
+        account_ids.append_value(account.account_id.to_string());
+        visitor_ids.append_value(account.visitor_id.to_string());
+        signup_dates.push(Date32Type::from_naive_date(account.signup_date));
+        countries.append_value(account.country);
+        plans.append_value(account.plan.as_str());
+        churn_dates.push(account.churn_date.map(Date32Type::from_naive_date));
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(account_ids.finish()),
+        Arc::new(visitor_ids.finish()),
+        Arc::new(Date32Array::from(signup_dates)),
+        Arc::new(countries.finish()),
+        Arc::new(plans.finish()),
+        Arc::new(Date32Array::from(churn_dates)),
+    ];
+
+    RecordBatch::try_new(schema.clone(), columns).context("Failed to create record batch")
+}
+
+/// Write one account per visitor to `output_dir/data.parquet`.
+///
+/// Matches the accounts [`ParquetOutput::with_accounts`] uses for the same
+/// arguments, so every session falls within its account's lifetime.
+pub fn write_accounts_to_parquet(
+    output_dir: &Path,
+    seed: u64,
+    num_sessions: usize,
+    num_days: u32,
+    start_date: NaiveDate,
+    config: &AccountConfig,
+    ids: IdFormat,
+) -> Result<usize> {
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory: {:?}", output_dir))?;
+
+    let options = OutputOptions {
+        accounts: Some(config.clone()),
+        ids,
+        ..OutputOptions::default()
+    };
+    let visitor_pool = options.visitor_pool(seed, num_sessions, start_date);
+    let accounts = options
+        .accounts(seed, &visitor_pool, start_date, num_days)
+        .expect("accounts are enabled");
+    let batch = ids.apply(&accounts_to_record_batch(
+        &accounts,
+        &Arc::new(account_schema()),
+    )?)?;
+
+    write_batch(&output_dir.join("data.parquet"), batch.schema(), &batch)?;
+
+    Ok(accounts.len())
+}
+
+/// Schema for orders (without order_date, which is the partition key).
+fn order_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("session_id", DataType::Utf8, false),
+        Field::new("visitor_id", DataType::Utf8, false),
+        Field::new("status", DataType::Utf8, false),
+        Field::new("total", DataType::Int64, false),
+        Field::new("refund_date", DataType::Date32, true),
+    ])
+}
+
+pub(crate) fn orders_to_record_batch(orders: &[Order]) -> Result<RecordBatch> {
+    let mut order_ids = StringBuilder::new();
+    let mut session_ids = StringBuilder::new();
+    let mut visitor_ids = StringBuilder::new();
+    let mut statuses = StringBuilder::new();
+    let mut totals: Vec<i64> = Vec::with_capacity(orders.len());
+    let mut refund_dates: Vec<Option<i32>> = Vec::with_capacity(orders.len());
+
+    for order in orders {
+        order_ids.append_value(order.order_id.to_string());
+        session_ids.append_value(order.session_id.to_string());
+        visitor_ids.append_value(order.visitor_id.to_string());
+        statuses.append_value(order.status.as_str());
+        totals.push(order.total);
+        refund_dates.push(order.refund_date.map(Date32Type::from_naive_date));
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(order_ids.finish()),
+        Arc::new(session_ids.finish()),
+        Arc::new(visitor_ids.finish()),
+        Arc::new(statuses.finish()),
+        Arc::new(Int64Array::from(totals)),
+        Arc::new(Date32Array::from(refund_dates)),
+    ];
+
+    RecordBatch::try_new(Arc::new(order_schema()), columns).context("Failed to create record batch")
+}
+
+/// Schema for order line items.
+fn order_item_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("line_number", DataType::Int32, false),
+        Field::new("product_id", DataType::Int64, false),
+        Field::new("quantity", DataType::Int32, false),
+        Field::new("unit_price", DataType::Int32, false),
+        Field::new("line_total", DataType::Int64, false),
+    ])
+}
+
+pub(crate) fn order_items_to_record_batch(items: &[OrderItem]) -> Result<RecordBatch> {
+    let mut order_ids = StringBuilder::new();
+    let mut line_numbers: Vec<i32> = Vec::with_capacity(items.len());
+    let mut product_ids: Vec<i64> = Vec::with_capacity(items.len());
+    let mut quantities: Vec<i32> = Vec::with_capacity(items.len());
+    let mut unit_prices: Vec<i32> = Vec::with_capacity(items.len());
+    let mut line_totals: Vec<i64> = Vec::with_capacity(items.len());
+
+    for item in items {
+        order_ids.append_value(item.order_id.to_string());
+        line_numbers.push(item.line_number);
+        product_ids.push(item.product_id);
+        quantities.push(item.quantity);
+        unit_prices.push(item.unit_price);
+        line_totals.push(item.line_total);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(order_ids.finish()),
+        Arc::new(Int32Array::from(line_numbers)),
+        Arc::new(Int64Array::from(product_ids)),
+        Arc::new(Int32Array::from(quantities)),
+        Arc::new(Int32Array::from(unit_prices)),
+        Arc::new(Int64Array::from(line_totals)),
+    ];
+
+    RecordBatch::try_new(Arc::new(order_item_schema()), columns)
+        .context("Failed to create record batch")
+}
+
+/// Schema for events (without event_date, which is the partition key).
+fn event_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("event_id", DataType::Utf8, false),
+        Field::new("session_id", DataType::Utf8, false),
+        Field::new("visitor_id", DataType::Utf8, false),
+        Field::new(
+            "event_timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        ),
+        Field::new("sequence_number", DataType::Int32, false),
+        Field::new("event_type", DataType::Utf8, false),
+        Field::new("product_category", DataType::Utf8, true),
+        Field::new("revenue", DataType::Int32, true),
+    ])
+}
+
+pub(crate) fn events_to_record_batch(events: &[Event]) -> Result<RecordBatch> {
+    let mut event_ids = StringBuilder::new();
+    let mut session_ids = StringBuilder::new();
+    let mut visitor_ids = StringBuilder::new();
+    let mut timestamps: Vec<i64> = Vec::with_capacity(events.len());
+    let mut sequence_numbers: Vec<i32> = Vec::with_capacity(events.len());
+    let mut event_types = StringBuilder::new();
+    let mut categories = StringBuilder::new();
+    let mut revenues: Vec<Option<i32>> = Vec::with_capacity(events.len());
+
+    for event in events {
+        event_ids.append_value(event.event_id.to_string());
+        session_ids.append_value(event.session_id.to_string());
+        visitor_ids.append_value(event.visitor_id.to_string());
+        timestamps.push(event.event_timestamp.and_utc().timestamp_micros());
+        sequence_numbers.push(event.sequence_number);
+        event_types.append_value(event.event_type.as_str());
+        categories.append_option(event.product_category.map(|c| c.as_str()));
+        revenues.push(event.revenue);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(event_ids.finish()),
+        Arc::new(session_ids.finish()),
+        Arc::new(visitor_ids.finish()),
+        Arc::new(TimestampMicrosecondArray::from(timestamps)),
+        Arc::new(Int32Array::from(sequence_numbers)),
+        Arc::new(event_types.finish()),
+        Arc::new(categories.finish()),
+        Arc::new(Int32Array::from(revenues)),
+    ];
+
+    RecordBatch::try_new(Arc::new(event_schema()), columns).context("Failed to create record batch")
+}
+
+/// Schema for touchpoints (without conversion_date, which is the partition key).
+fn touchpoint_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("touchpoint_id", DataType::Utf8, false),
+        Field::new("visitor_id", DataType::Utf8, false),
+        Field::new("conversion_session_id", DataType::Utf8, false),
+        Field::new("position", DataType::Int32, false),
+        Field::new("path_length", DataType::Int32, false),
+        Field::new("touch_date", DataType::Date32, false),
+        Field::new("days_before_conversion", DataType::Int32, false),
+        Field::new("channel", DataType::Utf8, false),
+        Field::new("campaign", DataType::Utf8, true),
+    ])
+}
+
+pub(crate) fn touchpoints_to_record_batch(touchpoints: &[Touchpoint]) -> Result<RecordBatch> {
+    let mut touchpoint_ids = StringBuilder::new();
+    let mut visitor_ids = StringBuilder::new();
+    let mut session_ids = StringBuilder::new();
+    let mut positions: Vec<i32> = Vec::with_capacity(touchpoints.len());
+    let mut path_lengths: Vec<i32> = Vec::with_capacity(touchpoints.len());
+    let mut touch_dates: Vec<i32> = Vec::with_capacity(touchpoints.len());
+    let mut days_before: Vec<i32> = Vec::with_capacity(touchpoints.len());
+    let mut channels = StringBuilder::new();
+    let mut campaigns = StringBuilder::new();
+
+    for touch in touchpoints {
+        touchpoint_ids.append_value(touch.touchpoint_id.to_string());
+        visitor_ids.append_value(touch.visitor_id.to_string());
+        session_ids.append_value(touch.conversion_session_id.to_string());
+        positions.push(touch.position);
+        path_lengths.push(touch.path_length);
+        touch_dates.push(Date32Type::from_naive_date(touch.touch_date));
+        days_before.push(touch.days_before_conversion());
+        channels.append_value(touch.channel.as_str());
+        campaigns.append_option(touch.campaign.as_deref());
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(touchpoint_ids.finish()),
+        Arc::new(visitor_ids.finish()),
+        Arc::new(session_ids.finish()),
+        Arc::new(Int32Array::from(positions)),
+        Arc::new(Int32Array::from(path_lengths)),
+        Arc::new(Date32Array::from(touch_dates)),
+        Arc::new(Int32Array::from(days_before)),
+        Arc::new(channels.finish()),
+        Arc::new(campaigns.finish()),
+    ];
+
+    RecordBatch::try_new(Arc::new(touchpoint_schema()), columns)
+        .context("Failed to create record batch")
+}
+
+/// Schema for expected per-platform totals (without session_date, which is
+/// the partition key).
+fn expected_platform_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("platform", DataType::Utf8, false),
+        Field::new("sessions", DataType::Int64, false),
+        Field::new("visitors", DataType::Int64, false),
+        Field::new("rows", DataType::Int64, false),
+    ])
+}
+
+/// Schema for expected per-category totals (without session_date).
+fn expected_category_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("product_category", DataType::Utf8, false),
+        Field::new("product_views", DataType::Int64, false),
+        Field::new("add_to_cart_count", DataType::Int64, false),
+        Field::new("checkout_count", DataType::Int64, false),
+        Field::new("purchase_count", DataType::Int64, false),
+        Field::new("revenue", DataType::Int64, false),
+    ])
+}
+
+/// Per-platform and per-category batches for one day's expected aggregates.
+pub(crate) fn expected_to_record_batches(
+    expected: &ExpectedAggregates,
+) -> Result<(RecordBatch, RecordBatch)> {
+    let platforms = &expected.platforms;
+    let platform_columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            platforms.iter().map(|p| p.platform),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            platforms.iter().map(|p| p.sessions),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            platforms.iter().map(|p| p.visitors),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            platforms.iter().map(|p| p.rows),
+        )),
+    ];
+
+    let categories = &expected.categories;
+    let category_columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            categories.iter().map(|c| c.product_category),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            categories.iter().map(|c| c.product_views),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            categories.iter().map(|c| c.add_to_cart_count),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            categories.iter().map(|c| c.checkout_count),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            categories.iter().map(|c| c.purchase_count),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            categories.iter().map(|c| c.revenue),
+        )),
+    ];
+
+    Ok((
+        RecordBatch::try_new(Arc::new(expected_platform_schema()), platform_columns)
+            .context("Failed to create record batch")?,
+        RecordBatch::try_new(Arc::new(expected_category_schema()), category_columns)
+            .context("Failed to create record batch")?,
+    ))
+}
+
+/// Schema for catalog items.
+fn catalog_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("product_id", DataType::Int64, false),
+        Field::new("product_category", DataType::Utf8, false),
+        Field::new("product_name", DataType::Utf8, false),
+        Field::new("price", DataType::Int32, false),
+    ])
+}
+
+/// Write every item in `catalog` to `output_dir/data.parquet`.
+pub fn write_catalog_to_parquet(output_dir: &Path, catalog: &Catalog) -> Result<usize> {
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory: {:?}", output_dir))?;
+
+    let mut product_ids = Vec::new();
+    let mut categories = StringBuilder::new();
+    let mut names = StringBuilder::new();
+    let mut prices = Vec::new();
+    for item in catalog.items() {
+        product_ids.push(item.product_id);
+        categories.append_value(item.category.as_str());
+        names.append_value(&item.name);
+        prices.push(item.price);
+    }
+
+    let count = product_ids.len();
+    let schema = Arc::new(catalog_schema());
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from(product_ids)),
+        Arc::new(categories.finish()),
+        Arc::new(names.finish()),
+        Arc::new(Int32Array::from(prices)),
+    ];
+    let batch =
+        RecordBatch::try_new(schema.clone(), columns).context("Failed to create record batch")?;
+
+    write_batch(&output_dir.join("data.parquet"), schema, &batch)?;
+
+    Ok(count)
+}
+
 /// Write sessions to Hive-partitioned Parquet files with parallel generation.
 pub fn write_sessions_to_parquet(
     output_dir: &Path,
@@ -122,55 +640,307 @@ pub fn write_sessions_to_parquet(
     start_date: NaiveDate,
     progress_callback: Option<&(dyn Fn(usize, usize) + Sync)>,
 ) -> Result<usize> {
-    // Create output directory
-    fs::create_dir_all(output_dir)
-        .with_context(|| format!("Failed to create output directory: {:?}", output_dir))?;
+    ParquetOutput::new().write_days(
+        output_dir,
+        seed,
+        num_sessions,
+        num_days,
+        start_date,
+        progress_callback,
+    )
+}
 
-    // Step 1: Generate shared visitor pool (deterministic from seed)
-    let visitor_pool = VisitorPool::new(seed, num_sessions);
+/// Writes sessions as Hive-partitioned Parquet, one file per `session_date`.
+#[derive(Debug, Clone, Default)]
+pub struct ParquetOutput {
+    naming: SchemaConfig,
+    visitors_dir: Option<PathBuf>,
+    events_dir: Option<PathBuf>,
+    workers: Option<usize>,
+    only_days: Option<(NaiveDate, NaiveDate)>,
+    skip_existing: bool,
+    pub(crate) options: OutputOptions,
+}
+
+impl OutputBuilder for ParquetOutput {
+    fn options_mut(&mut self) -> &mut OutputOptions {
+        &mut self.options
+    }
+}
+
+impl ParquetOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename columns, including the `session_date` partition key.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Generate and write days on a pool of `workers` threads instead of the
+    /// global rayon pool. Each worker holds one day's sessions at a time, so
+    /// this also bounds memory; the output doesn't depend on it.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers.max(1));
+        self
+    }
+
+    /// Only write partitions from `first` to `last` inclusive, exactly as they
+    /// appear in the full range.
+    pub fn with_only_days(mut self, first: NaiveDate, last: NaiveDate) -> Self {
+        self.only_days = Some((first, last));
+        self
+    }
+
+    /// Leave days whose sessions partition is already written alone, so an
+    /// interrupted run can be resumed with the same arguments.
+    pub fn with_skip_existing(mut self) -> Self {
+        self.skip_existing = true;
+        self
+    }
+
+    /// Also write the visitors dimension to `visitors_dir/data.parquet` from
+    /// [`Self::write_days`], with each visitor's first session date.
+    pub fn with_visitors(mut self, visitors_dir: impl Into<PathBuf>) -> Self {
+        self.visitors_dir = Some(visitors_dir.into());
+        self
+    }
+
+    /// Also write each session's events under `events_dir`, partitioned by
+    /// `event_date`.
+    pub fn with_events(mut self, events_dir: impl Into<PathBuf>, events: EventsConfig) -> Self {
+        self.events_dir = Some(events_dir.into());
+        self.options.events = Some(events);
+        self
+    }
+
+    /// Generate and write only `date`'s partition, identical to the one
+    /// [`Self::write_days`] writes with the same arguments.
+    pub fn write_one_day(
+        &self,
+        output_dir: &Path,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+        date: NaiveDate,
+    ) -> Result<usize> {
+        let visitor_pool = self.options.visitor_pool(seed, num_sessions, start_date);
+        let (day_seed, sessions) = self.options.single_day(
+            &visitor_pool,
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            date,
+        )?;
+        self.write_day(output_dir, seed, date, &sessions, day_seed)
+    }
+
+    /// Generate and write each day's partition in parallel, one day per
+    /// worker at a time.
+    pub fn write_days(
+        &self,
+        output_dir: &Path,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+        progress_callback: Option<&(dyn Fn(usize, usize) + Sync)>,
+    ) -> Result<usize> {
+        let write = || {
+            self.write_days_in_pool(
+                output_dir,
+                seed,
+                num_sessions,
+                num_days,
+                start_date,
+                progress_callback,
+            )
+        };
+        match self.workers {
+            Some(workers) => rayon::ThreadPoolBuilder::new()
+                .num_threads(workers)
+                .build()
+                .context("Failed to start worker threads")?
+                .install(write),
+            None => write(),
+        }
+    }
+
+    fn write_days_in_pool(
+        &self,
+        output_dir: &Path,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+        progress_callback: Option<&(dyn Fn(usize, usize) + Sync)>,
+    ) -> Result<usize> {
+        // Create output directory
+        fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create output directory: {:?}", output_dir))?;
 
-    // Step 2: Pre-compute per-day seeds (deterministic from seed)
-    let day_seeds = generate_day_seeds(seed, num_days);
-
-    // Step 3: Calculate sessions per day
-    let sessions_per_day = num_sessions / num_days as usize;
-
-    // Step 4: Build list of (date, seed) pairs
-    let days: Vec<_> = (0..num_days)
-        .map(|i| {
-            let date = start_date + chrono::Duration::days(i as i64);
-            (date, day_seeds[i as usize])
-        })
-        .collect();
-
-    // Step 5: Parallel generation and writing
-    let total_written = AtomicUsize::new(0);
-
-    days.par_iter()
-        .try_for_each(|(date, day_seed)| -> Result<()> {
-            // Generate sessions for this day
-            let generator =
-                DayGenerator::new(visitor_pool.clone(), *day_seed, *date, sessions_per_day);
-            let sessions = generator.generate();
-
-            // Write to parquet
-            let count = write_day_to_parquet(output_dir, *date, &sessions)?;
-
-            // Update progress
-            let new_total = total_written.fetch_add(count, Ordering::SeqCst) + count;
-            if let Some(cb) = progress_callback {
-                cb(new_total, num_sessions);
+        // Step 1: Generate shared visitor pool (deterministic from seed)
+        let visitor_pool = self.options.visitor_pool(seed, num_sessions, start_date);
+        let accounts = self
+            .options
+            .accounts(seed, &visitor_pool, start_date, num_days);
+
+        // Step 2: Pre-compute per-day seeds (deterministic from seed)
+        let day_seeds = generate_day_seeds(seed, num_days);
+
+        // Step 3: Calculate sessions per day
+        let sessions_per_day = num_sessions / num_days as usize;
+
+        // Step 4: Build list of (date, seed) pairs
+        let days: Vec<_> = (0..num_days)
+            .map(|i| {
+                let date = start_date + chrono::Duration::days(i as i64);
+                (date, day_seeds[i as usize])
+            })
+            .collect();
+
+        // Step 5: Parallel generation and writing
+        let total_written = AtomicUsize::new(0);
+
+        let first_seen = days
+            .par_iter()
+            .map(|(date, day_seed)| -> Result<FirstSeen> {
+                let write = self
+                    .only_days
+                    .is_none_or(|(first, last)| (first..=last).contains(date))
+                    && !(self.skip_existing
+                        && self
+                            .partition_dir(output_dir, *date)
+                            .join("data.parquet")
+                            .exists());
+                // Skipped days are still generated when visitors need their first sessions
+                if !write && self.visitors_dir.is_none() {
+                    return Ok(FirstSeen::default());
+                }
+
+                // Generate sessions for this day
+                let mut sessions = self
+                    .options
+                    .day_generator(
+                        visitor_pool.clone(),
+                        accounts.as_ref(),
+                        seed,
+                        *day_seed,
+                        *date,
+                        sessions_per_day,
+                    )
+                    .generate();
+                self.options.stamp_sessions(&mut sessions, start_date);
+
+                // Write to parquet
+                let count = if write {
+                    self.write_day(output_dir, seed, *date, &sessions, *day_seed)?
+                } else {
+                    0
+                };
+
+                // Update progress
+                let new_total = total_written.fetch_add(count, Ordering::SeqCst) + count;
+                if let Some(cb) = progress_callback {
+                    cb(new_total, num_sessions);
+                }
+
+                let mut first_seen = FirstSeen::default();
+                if self.visitors_dir.is_some() {
+                    first_seen.record(*date, &sessions);
+                }
+                Ok(first_seen)
+            })
+            .try_reduce(FirstSeen::default, |mut a, b| {
+                a.merge(b);
+                Ok(a)
+            })?;
+
+        // Step 6: Visitors, now every first session is known
+        if let Some(visitors_dir) = &self.visitors_dir {
+            write_visitors(
+                visitors_dir,
+                &visitor_pool,
+                &first_seen,
+                &self.options,
+                &self.naming,
+            )?;
+        }
+
+        Ok(total_written.load(Ordering::SeqCst))
+    }
+
+    /// Write one day's partition, finished with the options (seeded by the
+    /// day seed).
+    fn write_day(
+        &self,
+        output_dir: &Path,
+        seed: u64,
+        date: NaiveDate,
+        sessions: &[Session],
+        day_seed: u64,
+    ) -> Result<usize> {
+        if sessions.is_empty() {
+            return Ok(0);
+        }
+
+        // Events go first: a sessions partition marks the day as written.
+        if let Some(events_dir) = &self.events_dir {
+            if let Some(events) = self.options.event_batch(sessions, day_seed)? {
+                let partition_dir = events_dir.join(format!(
+                    "{}={}",
+                    self.naming.column_name("events", "event_date"),
+                    date
+                ));
+                fs::create_dir_all(&partition_dir).with_context(|| {
+                    format!("Failed to create partition directory: {:?}", partition_dir)
+                })?;
+                let events = self.naming.apply("events", &events)?;
+                write_batch(
+                    &partition_dir.join("data.parquet"),
+                    events.schema(),
+                    &events,
+                )?;
             }
+        }
 
-            Ok(())
+        // Create partition directory: output_dir/session_date=YYYY-MM-DD/
+        let partition_dir = self.partition_dir(output_dir, date);
+        fs::create_dir_all(&partition_dir).with_context(|| {
+            format!("Failed to create partition directory: {:?}", partition_dir)
         })?;
 
-    Ok(total_written.load(Ordering::SeqCst))
+        // Convert sessions to Arrow arrays
+        let batch = sessions_to_record_batch(sessions, &Arc::new(session_schema()))?;
+        let batch = self.options.finish_batch(batch, seed, day_seed)?;
+        let batch = self.naming.apply("sessions", &batch)?;
+
+        write_batch(&partition_dir.join("data.parquet"), batch.schema(), &batch)?;
+
+        Ok(sessions.len())
+    }
+
+    /// Sessions partition directory for `date`.
+    fn partition_dir(&self, output_dir: &Path, date: NaiveDate) -> PathBuf {
+        output_dir.join(format!(
+            "{}={}",
+            self.naming.column_name("sessions", "session_date"),
+            date
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dirty::DirtyDataConfig;
+    use arrow::array::StringArray;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
     use tempfile::TempDir;
 
     #[test]
@@ -196,6 +966,190 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_write_visitors_matches_sessions() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use std::collections::HashSet;
+
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sessions_dir = temp_dir.path().join("sessions");
+        let visitors_dir = temp_dir.path().join("visitors");
+
+        write_sessions_to_parquet(&sessions_dir, 42, 1000, 5, start_date, None).unwrap();
+        let count =
+            write_visitors_to_parquet(&visitors_dir, 42, 1000, 5, start_date, None).unwrap();
+        assert_eq!(count, 200);
+
+        let read_ids = |path: &Path, column: usize| -> HashSet<String> {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap();
+            let mut ids = HashSet::new();
+            for batch in reader {
+                let batch = batch.unwrap();
+                let array = batch
+                    .column(column)
+                    .as_any()
+                    .downcast_ref::<arrow::array::StringArray>()
+                    .unwrap();
+                ids.extend(array.iter().flatten().map(str::to_string));
+            }
+            ids
+        };
+
+        let visitor_ids = read_ids(&visitors_dir.join("data.parquet"), 0);
+        assert_eq!(visitor_ids.len(), count);
+        for i in 0..5 {
+            let date = start_date + chrono::Duration::days(i);
+            let session_visitor_ids = read_ids(
+                &sessions_dir
+                    .join(format!("session_date={}", date))
+                    .join("data.parquet"),
+                0,
+            );
+            assert!(session_visitor_ids.is_subset(&visitor_ids));
+        }
+    }
+
+    #[test]
+    fn test_dirty_data_written_to_parquet() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        ParquetOutput::new()
+            .with_dirty_data(DirtyDataConfig::new().with_null_rate("visit_source", 0.5))
+            .write_days(temp_dir.path(), 42, 1000, 5, start_date, None)
+            .unwrap();
+
+        let path = temp_dir
+            .path()
+            .join(format!("session_date={}", start_date))
+            .join("data.parquet");
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let nulls: usize = reader
+            .map(|batch| batch.unwrap().column(3).null_count())
+            .sum();
+        assert!(nulls > 0);
+    }
+
+    #[test]
+    fn test_enriched_visitors_match_sessions() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let sessions_dir = temp_dir.path().join("sessions");
+        let visitors_dir = temp_dir.path().join("visitors");
+        let enrichment = EnrichmentConfig::new();
+
+        ParquetOutput::new()
+            .with_enrichment(enrichment.clone())
+            .write_days(&sessions_dir, 42, 1000, 5, start_date, None)
+            .unwrap();
+        write_visitors_to_parquet(&visitors_dir, 42, 1000, 5, start_date, Some(&enrichment))
+            .unwrap();
+
+        let read = |path: PathBuf| -> RecordBatch {
+            let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap();
+            reader.next().unwrap().unwrap()
+        };
+        let city_by_visitor = |batch: &RecordBatch| -> HashMap<String, String> {
+            let ids = batch.column_by_name("visitor_id").unwrap();
+            let cities = batch.column_by_name("city").unwrap();
+            let ids = ids.as_any().downcast_ref::<StringArray>().unwrap();
+            let cities = cities.as_any().downcast_ref::<StringArray>().unwrap();
+            ids.iter()
+                .zip(cities.iter())
+                .map(|(id, city)| (id.unwrap().to_string(), city.unwrap().to_string()))
+                .collect()
+        };
+
+        let visitors = read(visitors_dir.join("data.parquet"));
+        assert!(visitors.column_by_name("device_model").is_none());
+        let sessions = read(
+            sessions_dir
+                .join(format!("session_date={}", start_date))
+                .join("data.parquet"),
+        );
+        assert!(sessions.column_by_name("user_agent").is_some());
+
+        let visitor_cities = city_by_visitor(&visitors);
+        for (id, city) in city_by_visitor(&sessions) {
+            assert_eq!(visitor_cities[&id], city);
+        }
+    }
+
+    #[test]
+    fn test_sessions_reference_catalog() {
+        use crate::catalog::CatalogConfig;
+        use arrow::array::Int64Array;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use std::collections::HashSet;
+
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let catalog = Catalog::generate(42, &CatalogConfig::default()).unwrap();
+
+        let count = write_catalog_to_parquet(&temp_dir.path().join("catalog"), &catalog).unwrap();
+        assert_eq!(count, 6_000);
+        ParquetOutput::new()
+            .with_catalog(catalog.clone())
+            .write_days(
+                &temp_dir.path().join("sessions"),
+                42,
+                1000,
+                5,
+                start_date,
+                None,
+            )
+            .unwrap();
+
+        let path = temp_dir
+            .path()
+            .join("sessions")
+            .join(format!("session_date={}", start_date))
+            .join("data.parquet");
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        let ids = batch.column_by_name("product_id").unwrap();
+        let ids = ids.as_any().downcast_ref::<Int64Array>().unwrap();
+
+        let known: HashSet<i64> = catalog.items().map(|i| i.product_id).collect();
+        assert!(ids.iter().all(|id| known.contains(&id.unwrap())));
+    }
+
+    #[test]
+    fn test_write_accounts() {
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let count = write_accounts_to_parquet(
+            temp_dir.path(),
+            42,
+            1000,
+            5,
+            start_date,
+            &AccountConfig::default(),
+            IdFormat::Uuid4,
+        )
+        .unwrap();
+        assert_eq!(count, 200);
+        assert!(temp_dir.path().join("data.parquet").exists());
+    }
+
     #[test]
     fn test_deterministic_parallel_output() {
         let temp_dir1 = TempDir::new().unwrap();
@@ -224,4 +1178,161 @@ mod tests {
             assert_eq!(bytes1, bytes2, "Files for {} should be identical", date);
         }
     }
+
+    #[test]
+    fn test_resume_skips_written_partitions() {
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let day = |i| start_date + chrono::Duration::days(i);
+        let partition = |i| {
+            temp_dir
+                .path()
+                .join(format!("session_date={}/data.parquet", day(i)))
+        };
+
+        // An interrupted run that only got through the middle days
+        let first = ParquetOutput::new()
+            .with_only_days(day(1), day(2))
+            .write_days(temp_dir.path(), 42, 1000, 5, start_date, None)
+            .unwrap();
+        assert!(!partition(0).exists());
+        assert!(partition(1).exists() && !partition(3).exists());
+        let written = fs::read(partition(1)).unwrap();
+
+        let rest = ParquetOutput::new()
+            .with_skip_existing()
+            .write_days(temp_dir.path(), 42, 1000, 5, start_date, None)
+            .unwrap();
+        assert_eq!(fs::read(partition(1)).unwrap(), written);
+
+        let fresh = TempDir::new().unwrap();
+        let total = write_sessions_to_parquet(fresh.path(), 42, 1000, 5, start_date, None).unwrap();
+        assert_eq!(first + rest, total);
+        for i in 0..5 {
+            let name = format!("session_date={}/data.parquet", day(i));
+            assert_eq!(
+                fs::read(temp_dir.path().join(&name)).unwrap(),
+                fs::read(fresh.path().join(&name)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_workers_do_not_change_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let write = |name: &str, output: ParquetOutput| {
+            let dir = temp_dir.path().join(name);
+            output
+                .write_days(&dir, 42, 2000, 4, start_date, None)
+                .unwrap();
+            dir
+        };
+        let single = write("single", ParquetOutput::new().with_workers(1));
+        let pooled = write("pooled", ParquetOutput::new().with_workers(3));
+
+        for i in 0..4 {
+            let partition = format!(
+                "session_date={}/data.parquet",
+                start_date + chrono::Duration::days(i)
+            );
+            assert_eq!(
+                fs::read(single.join(&partition)).unwrap(),
+                fs::read(pooled.join(&partition)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_visitors_written_with_sessions_match_standalone() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let together = temp_dir.path().join("together");
+        let standalone = temp_dir.path().join("standalone");
+
+        ParquetOutput::new()
+            .with_visitors(&together)
+            .write_days(
+                &temp_dir.path().join("sessions"),
+                42,
+                1000,
+                5,
+                start_date,
+                None,
+            )
+            .unwrap();
+        write_visitors_to_parquet(&standalone, 42, 1000, 5, start_date, None).unwrap();
+
+        let read = |dir: &Path| -> RecordBatch {
+            let file = File::open(dir.join("data.parquet")).unwrap();
+            let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+                .unwrap()
+                .build()
+                .unwrap();
+            reader.next().unwrap().unwrap()
+        };
+        let batch = read(&together);
+        assert_eq!(batch, read(&standalone));
+        let first_seen = batch.column_by_name("first_seen").unwrap();
+        assert!(first_seen.null_count() < batch.num_rows());
+    }
+
+    #[test]
+    fn test_write_events_partitions() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let temp_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let events_dir = temp_dir.path().join("events");
+
+        ParquetOutput::new()
+            .with_events(&events_dir, EventsConfig::default())
+            .write_days(
+                &temp_dir.path().join("sessions"),
+                42,
+                1000,
+                3,
+                start_date,
+                None,
+            )
+            .unwrap();
+
+        for i in 0..3 {
+            let date = start_date + chrono::Duration::days(i);
+            let path = events_dir
+                .join(format!("event_date={}", date))
+                .join("data.parquet");
+            let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap();
+            let batch = reader.next().unwrap().unwrap();
+            assert!(batch.num_rows() > 0);
+            assert_eq!(batch.schema().field(0).name(), "event_id");
+        }
+    }
+
+    #[test]
+    fn test_one_day_matches_full_range() {
+        let full_dir = TempDir::new().unwrap();
+        let day_dir = TempDir::new().unwrap();
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+        let output = ParquetOutput::new().with_accounts(AccountConfig::default());
+
+        output
+            .write_days(full_dir.path(), 42, 1000, 5, start_date, None)
+            .unwrap();
+        output
+            .write_one_day(day_dir.path(), 42, 1000, 5, start_date, date)
+            .unwrap();
+
+        let partition = PathBuf::from(format!("session_date={}", date)).join("data.parquet");
+        let full = std::fs::read(full_dir.path().join(&partition)).unwrap();
+        let day = std::fs::read(day_dir.path().join(&partition)).unwrap();
+        assert_eq!(full, day);
+        assert!(!day_dir.path().join("session_date=2024-01-03").exists());
+    }
 }
@@ -0,0 +1,371 @@
+//! Custom per-row properties.
+//!
+//! Properties are extra typed values attached to each session row — page
+//! URLs, product ids, amounts, UTM parameters — written either as their own
+//! columns or together as a single JSON `properties` column.
+
+use anyhow::{Context, Result};
+use arrow::array::{Array, ArrayRef, Float64Builder, Int64Builder, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use crate::session::ProductCategory;
+
+/// Offset for per-property RNG streams, clear of the anomaly and dirty-data streams.
+const PROPERTY_STREAM_BASE: u64 = 0x200;
+
+/// How a property's values are generated.
+#[derive(Debug, Clone)]
+pub enum PropertyKind {
+    /// One of a fixed pool of strings, e.g. page URLs.
+    OneOf(Vec<String>),
+    /// An integer in `min..=max`, e.g. product ids.
+    IntRange { min: i64, max: i64 },
+    /// A float in `min..max` rounded to two decimals, e.g. amounts.
+    FloatRange { min: f64, max: f64 },
+    /// A `utm_source=..&utm_medium=..&utm_campaign=..` query string derived
+    /// from the row's visit source and campaign; null for rows without one.
+    UtmParams,
+}
+
+/// A named property, optionally limited to some product categories.
+#[derive(Debug, Clone)]
+pub struct PropertyDef {
+    pub name: String,
+    pub kind: PropertyKind,
+    /// Categories whose rows get this property; empty means all rows.
+    pub categories: Vec<ProductCategory>,
+}
+
+/// How properties appear in the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PropertiesLayout {
+    /// One typed column per property.
+    #[default]
+    Columns,
+    /// A single `properties` column holding a JSON object.
+    Json,
+}
+
+/// Property definitions for generated rows.
+#[derive(Debug, Clone, Default)]
+pub struct PropertiesConfig {
+    pub properties: Vec<PropertyDef>,
+    pub layout: PropertiesLayout,
+}
+
+/// A generated value, before it's placed in a column.
+enum PropertyValue {
+    Str(String),
+    Int(i64),
+    Float(f64),
+}
+
+impl PropertiesConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a property for every row.
+    pub fn with_property(self, name: impl Into<String>, kind: PropertyKind) -> Self {
+        self.with_property_for(name, kind, Vec::new())
+    }
+
+    /// Add a property only for rows in `categories`.
+    pub fn with_property_for(
+        mut self,
+        name: impl Into<String>,
+        kind: PropertyKind,
+        categories: Vec<ProductCategory>,
+    ) -> Self {
+        self.properties.push(PropertyDef {
+            name: name.into(),
+            kind,
+            categories,
+        });
+        self
+    }
+
+    pub fn with_layout(mut self, layout: PropertiesLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Append property columns to a session batch, generated from `seed`.
+    pub fn apply(&self, batch: &RecordBatch, seed: u64) -> Result<RecordBatch> {
+        if self.properties.is_empty() {
+            return Ok(batch.clone());
+        }
+
+        let category = string_column(batch, "product_category")?;
+        let source = string_column(batch, "visit_source")?;
+        let campaign = string_column(batch, "visit_campaign")?;
+
+        // values[p][row]
+        let values: Vec<Vec<Option<PropertyValue>>> = self
+            .properties
+            .iter()
+            .enumerate()
+            .map(|(idx, prop)| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(PROPERTY_STREAM_BASE + idx as u64);
+                (0..batch.num_rows())
+                    .map(|row| {
+                        let applies = prop.categories.is_empty()
+                            || prop
+                                .categories
+                                .iter()
+                                .any(|c| c.as_str() == category.value(row));
+                        if !applies {
+                            return None;
+                        }
+                        let campaign = (!campaign.is_null(row)).then(|| campaign.value(row));
+                        prop.kind.generate(&mut rng, source.value(row), campaign)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut fields: Vec<Field> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.as_ref().clone())
+            .collect();
+        let mut columns: Vec<ArrayRef> = batch.columns().to_vec();
+
+        match self.layout {
+            PropertiesLayout::Columns => {
+                for (prop, values) in self.properties.iter().zip(values) {
+                    fields.push(Field::new(&prop.name, prop.kind.data_type(), true));
+                    columns.push(build_column(&prop.kind, values));
+                }
+            }
+            PropertiesLayout::Json => {
+                let mut json = StringBuilder::new();
+                for row in 0..batch.num_rows() {
+                    let mut object = String::from("{");
+                    for (prop, values) in self.properties.iter().zip(&values) {
+                        let Some(value) = &values[row] else { continue };
+                        if object.len() > 1 {
+                            object.push(',');
+                        }
+                        write_json_string(&mut object, &prop.name);
+                        object.push(':');
+                        match value {
+                            PropertyValue::Str(s) => write_json_string(&mut object, s),
+                            PropertyValue::Int(i) => write!(object, "{}", i).unwrap(),
+                            PropertyValue::Float(f) => write!(object, "{}", f).unwrap(),
+                        }
+                    }
+                    object.push('}');
+                    json.append_value(object);
+                }
+                fields.push(Field::new("properties", DataType::Utf8, false));
+                columns.push(Arc::new(json.finish()));
+            }
+        }
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .context("Failed to add property columns")
+    }
+}
+
+impl PropertyKind {
+    fn data_type(&self) -> DataType {
+        match self {
+            PropertyKind::OneOf(_) | PropertyKind::UtmParams => DataType::Utf8,
+            PropertyKind::IntRange { .. } => DataType::Int64,
+            PropertyKind::FloatRange { .. } => DataType::Float64,
+        }
+    }
+
+    fn generate(
+        &self,
+        rng: &mut ChaCha8Rng,
+        visit_source: &str,
+        visit_campaign: Option<&str>,
+    ) -> Option<PropertyValue> {
+        match self {
+            PropertyKind::OneOf(pool) => pool.choose(rng).cloned().map(PropertyValue::Str),
+            PropertyKind::IntRange { min, max } => {
+                Some(PropertyValue::Int(rng.gen_range(*min..=*max)))
+            }
+            PropertyKind::FloatRange { min, max } => {
+                let value: f64 = rng.gen_range(*min..*max);
+                Some(PropertyValue::Float((value * 100.0).round() / 100.0))
+            }
+            PropertyKind::UtmParams => visit_campaign.map(|campaign| {
+                PropertyValue::Str(format!(
+                    "utm_source={}&utm_medium={}&utm_campaign={}",
+                    visit_source,
+                    utm_medium(visit_source),
+                    campaign
+                ))
+            }),
+        }
+    }
+}
+
+/// Conventional `utm_medium` for a visit source.
+fn utm_medium(visit_source: &str) -> &'static str {
+    match visit_source {
+        "sem" => "cpc",
+        "email" => "email",
+        "social" | "organic_social" => "social",
+        "affiliate" => "affiliate",
+        _ => "referral",
+    }
+}
+
+fn string_column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a StringArray> {
+    batch
+        .column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        .with_context(|| format!("Properties need a string {:?} column", name))
+}
+
+fn build_column(kind: &PropertyKind, values: Vec<Option<PropertyValue>>) -> ArrayRef {
+    match kind.data_type() {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                match value {
+                    Some(PropertyValue::Int(i)) => builder.append_value(i),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                match value {
+                    Some(PropertyValue::Float(f)) => builder.append_value(f),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    Some(PropertyValue::Str(s)) => builder.append_value(s),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("visit_source", DataType::Utf8, false),
+            Field::new("visit_campaign", DataType::Utf8, true),
+            Field::new("product_category", DataType::Utf8, false),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(StringArray::from(vec!["sem", "direct", "email"])),
+                Arc::new(StringArray::from(vec![
+                    Some("black_friday"),
+                    None,
+                    Some("new_year"),
+                ])),
+                Arc::new(StringArray::from(vec!["electronics", "clothing", "food"])),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn config() -> PropertiesConfig {
+        PropertiesConfig::new()
+            .with_property(
+                "page_url",
+                PropertyKind::OneOf(vec!["/home".into(), "/cart".into()]),
+            )
+            .with_property_for(
+                "product_id",
+                PropertyKind::IntRange { min: 1, max: 100 },
+                vec![ProductCategory::Electronics],
+            )
+            .with_property("utm", PropertyKind::UtmParams)
+    }
+
+    #[test]
+    fn test_columns_layout() {
+        let out = config().apply(&batch(), 42).unwrap();
+        assert_eq!(out.num_columns(), 6);
+
+        let product_id = out.column_by_name("product_id").unwrap();
+        assert_eq!(product_id.data_type(), &DataType::Int64);
+        assert!(product_id.is_valid(0));
+        assert!(product_id.is_null(1));
+
+        let utm = out
+            .column_by_name("utm")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(
+            utm.value(0),
+            "utm_source=sem&utm_medium=cpc&utm_campaign=black_friday"
+        );
+        assert!(utm.is_null(1));
+    }
+
+    #[test]
+    fn test_json_layout() {
+        let out = config()
+            .with_layout(PropertiesLayout::Json)
+            .apply(&batch(), 42)
+            .unwrap();
+        let json = out
+            .column_by_name("properties")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+
+        assert!(json.value(0).contains("\"product_id\":"));
+        assert!(json.value(1).starts_with("{\"page_url\":\"/"));
+        assert!(!json.value(1).contains("product_id"));
+        assert!(!json.value(1).contains("utm"));
+    }
+
+    #[test]
+    fn test_json_string_escaping() {
+        let mut out = String::new();
+        write_json_string(&mut out, "a\"b\\c\n");
+        assert_eq!(out, r#""a\"b\\c\n""#);
+    }
+}
@@ -0,0 +1,95 @@
+//! Regenerating a few sessions without the whole dataset.
+//!
+//! Every day is generated from its own day seed, so tests can rebuild just
+//! the days they care about and assert against the exact rows a full run
+//! writes for them.
+
+use crate::output::OutputOptions;
+use crate::session::{Session, VisitorPool};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::ops::RangeInclusive;
+
+/// The first `n` sessions matching `predicate` on `dates`, in the order a full
+/// run over `num_days` from `start_date` generates them.
+///
+/// Only the days in `dates` (clipped to the range) are generated, stopping
+/// once `n` sessions match. Rows are as generated, before dirty data.
+pub fn sample_sessions(
+    seed: u64,
+    num_sessions: usize,
+    num_days: u32,
+    start_date: NaiveDate,
+    dates: RangeInclusive<NaiveDate>,
+    predicate: impl Fn(&Session) -> bool,
+    n: usize,
+) -> Result<Vec<Session>> {
+    let options = OutputOptions::default();
+    let visitor_pool = VisitorPool::new(seed, num_sessions);
+    let end_date = start_date + chrono::Duration::days(num_days as i64 - 1);
+    let first = (*dates.start()).max(start_date);
+    let last = (*dates.end()).min(end_date);
+
+    let mut sample = Vec::new();
+    for date in first.iter_days().take_while(|date| *date <= last) {
+        if sample.len() >= n {
+            break;
+        }
+        let (_, sessions) = options.single_day(
+            &visitor_pool,
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            date,
+        )?;
+        sample.extend(
+            sessions
+                .into_iter()
+                .filter(|session| predicate(session))
+                .take(n - sample.len()),
+        );
+    }
+    Ok(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Platform;
+
+    #[test]
+    fn test_sample_matches_full_run() {
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let is_ios = |s: &Session| s.platform == Platform::Ios;
+
+        let sample = sample_sessions(42, 5000, 5, start_date, day..=day, is_ios, 10).unwrap();
+        assert_eq!(sample.len(), 10);
+
+        let mut all = Vec::new();
+        let options = OutputOptions::default();
+        let visitor_pool = VisitorPool::new(42, 5000);
+        options
+            .for_each_day(&visitor_pool, 42, 5000, 5, start_date, |_, _, sessions| {
+                all.extend_from_slice(sessions);
+                Ok(())
+            })
+            .unwrap();
+        let expected: Vec<_> = all
+            .into_iter()
+            .filter(|s| s.session_date == day && is_ios(s))
+            .take(10)
+            .collect();
+        assert_eq!(sample, expected);
+
+        // Dates outside the range are ignored rather than an error.
+        let late = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        assert!(
+            sample_sessions(42, 5000, 5, start_date, day..=late, |_| true, usize::MAX)
+                .unwrap()
+                .iter()
+                .all(|s| s.session_date >= day)
+        );
+    }
+}
@@ -0,0 +1,879 @@
+//! Generation scenarios defined in YAML.
+//!
+//! A scenario pins everything needed to reproduce a data set — seed, size,
+//! date range, funnel rates, anomalies, extra columns, and where to write —
+//! so it can be version-controlled next to the models it exercises:
+//!
+//! ```yaml
+//! seed: 7
+//! sessions: 100000
+//! start_date: 2024-01-01
+//! days: 14
+//! funnel: { add_to_cart: 0.25 }
+//! shape: { platforms: { web_desktop: 0.6, ios: 0.4 }, widget_views_median: 8 }
+//! anomalies:
+//!   traffic_spikes: [{ date: 2024-01-05, multiplier: 3.0 }]
+//!   platform_outages: [{ platform: ios, start: 2024-01-08, end: 2024-01-09 }]
+//! outputs:
+//!   - { format: parquet, path: data/sessions, visitors_path: data/visitors }
+//!   - { format: csv, path: data/sessions.csv }
+//! ```
+
+use crate::accounts::AccountConfig;
+use crate::anomaly::{AnomalyConfig, BotPersona, PlatformOutage};
+use crate::attribution::AttributionConfig;
+use crate::catalog::{Catalog, CatalogConfig};
+use crate::dirty::DirtyDataConfig;
+use crate::enrich::EnrichmentConfig;
+use crate::events::EventsConfig;
+use crate::funnel::Funnel;
+use crate::ids::IdFormat;
+use crate::naming::SchemaConfig;
+use crate::orders::OrdersConfig;
+use crate::output::OutputOptions;
+use crate::parquet::ParquetOutput;
+use crate::properties::{PropertiesConfig, PropertiesLayout, PropertyDef, PropertyKind};
+use crate::session::{Platform, ProductCategory, VisitSource};
+use crate::shape::TrafficShape;
+use crate::sink::{DuckDbSink, ParquetSink, SqlSink, StreamingOutput};
+use crate::text::{CsvOutput, JsonLinesOutput, DEFAULT_DATE_FORMAT};
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A complete generation scenario.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestDataConfig {
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+    /// Total sessions across the range.
+    pub sessions: usize,
+    pub start_date: NaiveDate,
+    /// Number of days; exactly one of `days` and `end_date` is required.
+    pub days: Option<u32>,
+    /// Last day of the range, inclusive.
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub funnel: Option<FunnelSpec>,
+    #[serde(default)]
+    pub shape: Option<ShapeSpec>,
+    #[serde(default)]
+    pub anomalies: Option<AnomaliesSpec>,
+    #[serde(default)]
+    pub accounts: Option<AccountsSpec>,
+    #[serde(default)]
+    pub catalog: Option<CatalogSpec>,
+    #[serde(default)]
+    pub enrichment: Option<EnrichmentSpec>,
+    #[serde(default)]
+    pub properties: Vec<PropertySpec>,
+    #[serde(default)]
+    pub properties_layout: LayoutSpec,
+    #[serde(default)]
+    pub dirty_data: Option<DirtyDataSpec>,
+    /// Events are written by the `parquet` output when it sets
+    /// `events_path`, and by the same outputs as orders.
+    #[serde(default)]
+    pub events: Option<EventsSpec>,
+    /// Orders need a catalog, and are written by the `parquet_tables`,
+    /// `duckdb`, and `sql` outputs.
+    #[serde(default)]
+    pub orders: Option<OrdersSpec>,
+    /// Marketing touchpoints before each conversion, written by the same
+    /// outputs as orders.
+    #[serde(default)]
+    pub attribution: Option<AttributionSpec>,
+    /// Also write each day's per-platform and per-category totals, to the
+    /// same outputs as orders.
+    #[serde(default)]
+    pub expected_aggregates: bool,
+    /// Table and column renames applied by every output.
+    #[serde(default)]
+    pub naming: NamingSpec,
+    /// Format of every id column: `uuid4`, `uuid7`, `hex` or `snowflake`.
+    #[serde(default)]
+    pub ids: IdFormat,
+    pub outputs: Vec<OutputSpec>,
+}
+
+fn default_seed() -> u64 {
+    42
+}
+
+/// Funnel rates; unset rates keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FunnelSpec {
+    pub add_to_cart: Option<f64>,
+    pub checkout: Option<f64>,
+    pub purchase: Option<f64>,
+}
+
+/// Traffic mix and session shape. Weight maps replace the default mix when
+/// set; unset parameters keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShapeSpec {
+    #[serde(default)]
+    pub platforms: BTreeMap<Platform, f64>,
+    #[serde(default)]
+    pub visit_sources: BTreeMap<VisitSource, f64>,
+    #[serde(default)]
+    pub categories: BTreeMap<ProductCategory, f64>,
+    pub widget_views_median: Option<f64>,
+    pub widget_views_sigma: Option<f64>,
+    pub return_exponent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnomaliesSpec {
+    #[serde(default)]
+    pub traffic_spikes: Vec<TrafficSpikeSpec>,
+    #[serde(default)]
+    pub platform_outages: Vec<OutageSpec>,
+    #[serde(default)]
+    pub duplicate_rate: f64,
+    #[serde(default)]
+    pub bot_visitors: usize,
+    #[serde(default)]
+    pub bot_sessions_per_day: usize,
+    #[serde(default)]
+    pub bot_personas: Vec<BotPersonaSpec>,
+    #[serde(default)]
+    pub fraud_rate: f64,
+    #[serde(default)]
+    pub ground_truth: bool,
+}
+
+/// A bot persona preset; unset values keep the preset's.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotPersonaSpec {
+    pub preset: BotPreset,
+    pub visitors: usize,
+    pub name: Option<String>,
+    pub sessions_per_day: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotPreset {
+    Scraper,
+    Crawler,
+    CartHoarder,
+}
+
+impl BotPersonaSpec {
+    fn persona(&self) -> BotPersona {
+        let mut persona = match self.preset {
+            BotPreset::Scraper => BotPersona::scraper(self.visitors),
+            BotPreset::Crawler => BotPersona::crawler(self.visitors),
+            BotPreset::CartHoarder => BotPersona::cart_hoarder(self.visitors),
+        };
+        if let Some(name) = &self.name {
+            persona = persona.with_name(name.clone());
+        }
+        if let Some(sessions_per_day) = self.sessions_per_day {
+            persona = persona.with_sessions_per_day(sessions_per_day);
+        }
+        persona
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrafficSpikeSpec {
+    pub date: NaiveDate,
+    pub multiplier: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutageSpec {
+    pub platform: Platform,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// Account settings; unset values keep their defaults. `path` also writes
+/// the accounts as Parquet.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountsSpec {
+    pub churn_rate: Option<f64>,
+    pub signup_lookback_days: Option<u32>,
+    pub path: Option<PathBuf>,
+}
+
+/// Catalog settings; unset values keep their defaults. `path` also writes
+/// the catalog as Parquet.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogSpec {
+    pub items_per_category: Option<usize>,
+    pub exponent: Option<f64>,
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnrichmentSpec {
+    #[serde(default = "enabled")]
+    pub geo: bool,
+    #[serde(default = "enabled")]
+    pub device: bool,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PropertySpec {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: PropertyKindSpec,
+    #[serde(default)]
+    pub categories: Vec<ProductCategory>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PropertyKindSpec {
+    OneOf { values: Vec<String> },
+    IntRange { min: i64, max: i64 },
+    FloatRange { min: f64, max: f64 },
+    UtmParams,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutSpec {
+    #[default]
+    Columns,
+    Json,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirtyDataSpec {
+    #[serde(default)]
+    pub null_rates: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub malformed_rates: BTreeMap<String, f64>,
+}
+
+/// Event timing; unset values keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventsSpec {
+    pub min_gap_seconds: Option<i64>,
+    pub max_gap_seconds: Option<i64>,
+    /// Start sessions at local times of day in each visitor's time zone,
+    /// placed by the `enrichment` geo settings.
+    #[serde(default)]
+    pub local_time: bool,
+}
+
+/// Order settings; unset values keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrdersSpec {
+    pub refund_rate: Option<f64>,
+    pub max_quantity: Option<i32>,
+    pub refund_window_days: Option<i64>,
+}
+
+/// Conversion path settings; unset values keep their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttributionSpec {
+    pub lookback_days: Option<i64>,
+    pub prior_touch_rate: Option<f64>,
+    pub max_touches: Option<i32>,
+}
+
+/// Renames keyed by generated name. Column keys may be qualified as
+/// `table.column` to rename in one table only.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamingSpec {
+    #[serde(default)]
+    pub tables: BTreeMap<String, String>,
+    #[serde(default)]
+    pub columns: BTreeMap<String, String>,
+}
+
+/// Where to write. Text and SQL outputs accept `-` for stdout.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case", deny_unknown_fields)]
+pub enum OutputSpec {
+    /// Hive-partitioned sessions, plus visitors if `visitors_path` is set
+    /// and events if `events_path` is.
+    Parquet {
+        path: PathBuf,
+        visitors_path: Option<PathBuf>,
+        events_path: Option<PathBuf>,
+    },
+    /// A directory per table (`visitors`, `sessions`, `orders`, ...), each
+    /// Hive-partitioned by date where it has one.
+    ParquetTables { path: PathBuf },
+    Csv {
+        path: PathBuf,
+        #[serde(default = "default_delimiter")]
+        delimiter: char,
+        #[serde(default = "enabled")]
+        header: bool,
+        #[serde(default = "default_date_format")]
+        date_format: String,
+    },
+    Ndjson {
+        path: PathBuf,
+        #[serde(default = "default_date_format")]
+        date_format: String,
+    },
+    /// `visitors` and `sessions` tables in a DuckDB database file.
+    Duckdb { database: PathBuf },
+    /// `CREATE TABLE` and `INSERT` statements.
+    Sql { path: PathBuf },
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_date_format() -> String {
+    DEFAULT_DATE_FORMAT.to_string()
+}
+
+impl TestDataConfig {
+    /// Parse and validate a scenario.
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let config: Self = serde_yaml::from_str(yaml).context("Invalid scenario")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read a scenario file. Relative output paths are left as written, so
+    /// they resolve against the working directory.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let yaml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scenario: {:?}", path))?;
+        Self::from_yaml(&yaml).with_context(|| format!("In scenario {:?}", path))
+    }
+
+    /// Number of days in the range.
+    pub fn num_days(&self) -> u32 {
+        match (self.days, self.end_date) {
+            (Some(days), _) => days,
+            (None, Some(end)) => (end - self.start_date).num_days() as u32 + 1,
+            (None, None) => 0,
+        }
+    }
+
+    /// The last day of the range.
+    pub fn last_date(&self) -> NaiveDate {
+        self.start_date + Duration::days(self.num_days() as i64 - 1)
+    }
+
+    fn validate(&self) -> Result<()> {
+        match (self.days, self.end_date) {
+            (Some(_), Some(_)) => bail!("Set either days or end_date, not both"),
+            (None, None) => bail!("Set days or end_date"),
+            (Some(0), _) => bail!("days must be at least 1"),
+            (None, Some(end)) if end < self.start_date => {
+                bail!("end_date {} is before start_date {}", end, self.start_date)
+            }
+            _ => {}
+        }
+        if let Some(shape) = self.shape_config() {
+            shape.validate().context("Invalid shape")?;
+        }
+        if self.orders.is_some() && self.catalog.is_none() {
+            bail!("orders need a catalog to price their items");
+        }
+        if let Some(spec) = &self.attribution {
+            if spec.max_touches.is_some_and(|max| max < 1) {
+                bail!("attribution max_touches must be at least 1");
+            }
+            if spec
+                .prior_touch_rate
+                .is_some_and(|rate| !(0.0..=1.0).contains(&rate))
+            {
+                bail!("attribution prior_touch_rate must be between 0 and 1");
+            }
+        }
+        if self.outputs.is_empty() {
+            bail!("Scenario has no outputs");
+        }
+        for output in &self.outputs {
+            if let OutputSpec::Csv { delimiter, .. } = output {
+                if !delimiter.is_ascii() {
+                    bail!("CSV delimiter must be a single ASCII character");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn options(&self) -> Result<OutputOptions> {
+        let mut options = OutputOptions::default();
+
+        if let Some(spec) = &self.funnel {
+            let defaults = Funnel::default();
+            options.funnel = Some(Funnel {
+                add_to_cart: spec.add_to_cart.unwrap_or(defaults.add_to_cart),
+                checkout: spec.checkout.unwrap_or(defaults.checkout),
+                purchase: spec.purchase.unwrap_or(defaults.purchase),
+            });
+        }
+
+        options.shape = self.shape_config().map(Arc::new);
+        options.ids = self.ids;
+
+        if let Some(spec) = &self.anomalies {
+            options.anomalies = Some(Arc::new(AnomalyConfig {
+                traffic_spikes: spec
+                    .traffic_spikes
+                    .iter()
+                    .map(|s| (s.date, s.multiplier))
+                    .collect(),
+                platform_outages: spec
+                    .platform_outages
+                    .iter()
+                    .map(|o| PlatformOutage {
+                        platform: o.platform,
+                        start: o.start,
+                        end: o.end,
+                    })
+                    .collect(),
+                duplicate_rate: spec.duplicate_rate,
+                bot_visitors: spec.bot_visitors,
+                bot_sessions_per_day: spec.bot_sessions_per_day,
+                bot_personas: spec.bot_personas.iter().map(|p| p.persona()).collect(),
+                fraud_rate: spec.fraud_rate,
+                ground_truth: spec.ground_truth,
+            }));
+        }
+
+        options.accounts = self.account_config();
+        if let Some(config) = self.catalog_config() {
+            options.catalog = Some(Arc::new(Catalog::generate(self.seed, &config)?));
+        }
+
+        if let Some(spec) = &self.enrichment {
+            let mut enrichment = EnrichmentConfig::new()
+                .with_geo(spec.geo)
+                .with_device(spec.device);
+            enrichment.seed = spec.seed;
+            options.enrichment = Some(enrichment);
+        }
+
+        if !self.properties.is_empty() {
+            options.properties = Some(PropertiesConfig {
+                properties: self
+                    .properties
+                    .iter()
+                    .map(|p| PropertyDef {
+                        name: p.name.clone(),
+                        kind: match &p.kind {
+                            PropertyKindSpec::OneOf { values } => {
+                                PropertyKind::OneOf(values.clone())
+                            }
+                            PropertyKindSpec::IntRange { min, max } => PropertyKind::IntRange {
+                                min: *min,
+                                max: *max,
+                            },
+                            PropertyKindSpec::FloatRange { min, max } => PropertyKind::FloatRange {
+                                min: *min,
+                                max: *max,
+                            },
+                            PropertyKindSpec::UtmParams => PropertyKind::UtmParams,
+                        },
+                        categories: p.categories.clone(),
+                    })
+                    .collect(),
+                layout: match self.properties_layout {
+                    LayoutSpec::Columns => PropertiesLayout::Columns,
+                    LayoutSpec::Json => PropertiesLayout::Json,
+                },
+            });
+        }
+
+        if let Some(spec) = &self.dirty_data {
+            options.dirty_data = Some(DirtyDataConfig {
+                null_rates: spec.null_rates.clone(),
+                malformed_rates: spec.malformed_rates.clone(),
+            });
+        }
+
+        if let Some(spec) = &self.events {
+            let defaults = EventsConfig::default();
+            options.events = Some(EventsConfig {
+                min_gap_seconds: spec.min_gap_seconds.unwrap_or(defaults.min_gap_seconds),
+                max_gap_seconds: spec.max_gap_seconds.unwrap_or(defaults.max_gap_seconds),
+                local_time: spec
+                    .local_time
+                    .then(|| options.enrichment.clone().unwrap_or_default()),
+            });
+        }
+
+        if let Some(spec) = &self.orders {
+            let defaults = OrdersConfig::default();
+            options.orders = Some(OrdersConfig {
+                refund_rate: spec.refund_rate.unwrap_or(defaults.refund_rate),
+                max_quantity: spec.max_quantity.unwrap_or(defaults.max_quantity),
+                refund_window_days: spec
+                    .refund_window_days
+                    .unwrap_or(defaults.refund_window_days),
+            });
+        }
+
+        if let Some(spec) = &self.attribution {
+            let defaults = AttributionConfig::default();
+            options.attribution = Some(AttributionConfig {
+                lookback_days: spec.lookback_days.unwrap_or(defaults.lookback_days),
+                prior_touch_rate: spec.prior_touch_rate.unwrap_or(defaults.prior_touch_rate),
+                max_touches: spec.max_touches.unwrap_or(defaults.max_touches),
+            });
+        }
+
+        options.expected_aggregates = self.expected_aggregates;
+
+        Ok(options)
+    }
+
+    fn naming(&self) -> SchemaConfig {
+        let naming = self
+            .naming
+            .tables
+            .iter()
+            .fold(SchemaConfig::new(), |naming, (from, to)| {
+                naming.with_table_name(from, to)
+            });
+        self.naming
+            .columns
+            .iter()
+            .fold(naming, |naming, (from, to)| {
+                naming.with_column_name(from, to)
+            })
+    }
+
+    fn shape_config(&self) -> Option<TrafficShape> {
+        fn weights<T: Copy>(spec: &BTreeMap<T, f64>, defaults: Vec<(T, f64)>) -> Vec<(T, f64)> {
+            if spec.is_empty() {
+                defaults
+            } else {
+                spec.iter().map(|(k, w)| (*k, *w)).collect()
+            }
+        }
+        self.shape.as_ref().map(|spec| {
+            let defaults = TrafficShape::default();
+            TrafficShape {
+                platforms: weights(&spec.platforms, defaults.platforms),
+                visit_sources: weights(&spec.visit_sources, defaults.visit_sources),
+                categories: weights(&spec.categories, defaults.categories),
+                widget_views_median: spec
+                    .widget_views_median
+                    .unwrap_or(defaults.widget_views_median),
+                widget_views_sigma: spec
+                    .widget_views_sigma
+                    .unwrap_or(defaults.widget_views_sigma),
+                return_exponent: spec.return_exponent.unwrap_or(defaults.return_exponent),
+            }
+        })
+    }
+
+    fn account_config(&self) -> Option<AccountConfig> {
+        self.accounts.as_ref().map(|spec| {
+            let defaults = AccountConfig::default();
+            AccountConfig {
+                churn_rate: spec.churn_rate.unwrap_or(defaults.churn_rate),
+                signup_lookback_days: spec
+                    .signup_lookback_days
+                    .unwrap_or(defaults.signup_lookback_days),
+            }
+        })
+    }
+
+    fn catalog_config(&self) -> Option<CatalogConfig> {
+        self.catalog.as_ref().map(|spec| {
+            let defaults = CatalogConfig::default();
+            CatalogConfig {
+                items_per_category: spec
+                    .items_per_category
+                    .unwrap_or(defaults.items_per_category),
+                exponent: spec.exponent.unwrap_or(defaults.exponent),
+            }
+        })
+    }
+
+    /// Generate every output, returning the sessions written to each in order.
+    pub fn generate(&self) -> Result<Vec<usize>> {
+        let options = self.options()?;
+        let num_days = self.num_days();
+        let (seed, sessions, start) = (self.seed, self.sessions, self.start_date);
+        let naming = self.naming();
+
+        if let (Some(config), Some(path)) = (
+            self.account_config(),
+            self.accounts.as_ref().and_then(|a| a.path.as_ref()),
+        ) {
+            crate::parquet::write_accounts_to_parquet(
+                path,
+                seed,
+                sessions,
+                num_days,
+                start,
+                &config,
+                options.ids,
+            )?;
+        }
+        if let (Some(catalog), Some(path)) = (
+            &options.catalog,
+            self.catalog.as_ref().and_then(|c| c.path.as_ref()),
+        ) {
+            crate::parquet::write_catalog_to_parquet(path, catalog)?;
+        }
+
+        let mut counts = Vec::with_capacity(self.outputs.len());
+        for output in &self.outputs {
+            let count = match output {
+                OutputSpec::Parquet {
+                    path,
+                    visitors_path,
+                    events_path,
+                } => {
+                    let mut parquet = ParquetOutput::new().with_naming(naming.clone());
+                    parquet.options = options.clone();
+                    if let Some(visitors_path) = visitors_path {
+                        parquet = parquet.with_visitors(visitors_path);
+                    }
+                    if let Some(events_path) = events_path {
+                        let events = options.events.clone().unwrap_or_default();
+                        parquet = parquet.with_events(events_path, events);
+                    }
+                    parquet.write_days(path, seed, sessions, num_days, start, None)?
+                }
+                OutputSpec::ParquetTables { path } => {
+                    let mut streaming = StreamingOutput::new();
+                    streaming.options = options.clone();
+                    let mut sink = ParquetSink::new(path).with_naming(naming.clone());
+                    streaming.write_to(&mut sink, seed, sessions, num_days, start)?
+                }
+                OutputSpec::Csv {
+                    path,
+                    delimiter,
+                    header,
+                    date_format,
+                } => {
+                    let mut csv = CsvOutput::new()
+                        .with_delimiter(*delimiter as u8)
+                        .with_header(*header)
+                        .with_date_format(date_format)
+                        .with_naming(naming.clone());
+                    csv.options = options.clone();
+                    write_to_path(path, |w| csv.write_days(w, seed, sessions, num_days, start))?
+                }
+                OutputSpec::Ndjson { path, date_format } => {
+                    let mut json = JsonLinesOutput::new()
+                        .with_date_format(date_format)
+                        .with_naming(naming.clone());
+                    json.options = options.clone();
+                    write_to_path(path, |w| {
+                        json.write_days(w, seed, sessions, num_days, start)
+                    })?
+                }
+                OutputSpec::Duckdb { database } => {
+                    let mut streaming = StreamingOutput::new();
+                    streaming.options = options.clone();
+                    let mut sink = DuckDbSink::open(database)?.with_naming(naming.clone());
+                    streaming.write_to(&mut sink, seed, sessions, num_days, start)?
+                }
+                OutputSpec::Sql { path } => {
+                    let mut streaming = StreamingOutput::new();
+                    streaming.options = options.clone();
+                    write_to_path(path, |w| {
+                        let mut sink = SqlSink::new(w).with_naming(naming.clone());
+                        streaming.write_to(&mut sink, seed, sessions, num_days, start)
+                    })?
+                }
+            };
+            counts.push(count);
+        }
+        Ok(counts)
+    }
+}
+
+/// Run `write` against a buffered file, or stdout for `-`.
+fn write_to_path(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<usize>,
+) -> Result<usize> {
+    let writer: Box<dyn Write> = if path.as_os_str() == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {:?}", parent))?;
+        }
+        Box::new(File::create(path).with_context(|| format!("Failed to create {:?}", path))?)
+    };
+    let mut writer = BufWriter::new(writer);
+    let count = write(&mut writer)?;
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SCENARIO: &str = r#"
+seed: 7
+sessions: 600
+start_date: 2024-01-01
+end_date: 2024-01-03
+funnel: { add_to_cart: 0.5 }
+anomalies:
+  traffic_spikes: [{ date: 2024-01-02, multiplier: 2.0 }]
+  platform_outages: [{ platform: ios, start: 2024-01-03, end: 2024-01-03 }]
+properties:
+  - { name: page_url, type: one_of, values: [/home, /cart] }
+  - { name: amount, type: float_range, min: 1.0, max: 10.0, categories: [food] }
+dirty_data:
+  null_rates: { visit_campaign: 0.5 }
+outputs:
+  - { format: parquet, path: parquet }
+  - { format: csv, path: sessions.csv, delimiter: "|" }
+"#;
+
+    #[test]
+    fn test_parse_scenario() {
+        let config = TestDataConfig::from_yaml(SCENARIO).unwrap();
+        assert_eq!(config.seed, 7);
+        assert_eq!(config.num_days(), 3);
+        assert_eq!(
+            config.last_date(),
+            NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()
+        );
+        assert_eq!(config.outputs.len(), 2);
+
+        let options = config.options().unwrap();
+        assert_eq!(options.funnel.unwrap().add_to_cart, 0.5);
+        let anomalies = options.anomalies.unwrap();
+        assert!(anomalies.is_outage(Platform::Ios, NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()));
+        let properties = options.properties.unwrap();
+        assert_eq!(
+            properties.properties[1].categories,
+            vec![ProductCategory::Food]
+        );
+    }
+
+    #[test]
+    fn test_generate_outputs() {
+        let temp_dir = TempDir::new().unwrap();
+        let yaml = SCENARIO
+            .replace(
+                "path: parquet",
+                &format!("path: {:?}", temp_dir.path().join("parquet")),
+            )
+            .replace(
+                "path: sessions.csv",
+                &format!("path: {:?}", temp_dir.path().join("sessions.csv")),
+            );
+        let counts = TestDataConfig::from_yaml(&yaml)
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert_eq!(counts[0], counts[1]);
+
+        assert!(temp_dir
+            .path()
+            .join("parquet/session_date=2024-01-02")
+            .exists());
+        let csv = std::fs::read_to_string(temp_dir.path().join("sessions.csv")).unwrap();
+        assert!(csv.lines().next().unwrap().contains("|page_url|amount"));
+        assert_eq!(csv.lines().count(), counts[1] + 1);
+    }
+
+    #[test]
+    fn test_orders_scenario() {
+        let temp_dir = TempDir::new().unwrap();
+        let yaml = format!(
+            "sessions: 500\nstart_date: 2024-01-01\ndays: 2\ncatalog: {{ items_per_category: 20 }}\n\
+             orders: {{ refund_rate: 0.5 }}\nattribution: {{ lookback_days: 14 }}\n\
+             outputs: [{{ format: parquet_tables, path: {:?} }}]",
+            temp_dir.path()
+        );
+        TestDataConfig::from_yaml(&yaml)
+            .unwrap()
+            .generate()
+            .unwrap();
+        for table in [
+            "visitors",
+            "sessions",
+            "orders",
+            "order_items",
+            "touchpoints",
+        ] {
+            assert!(temp_dir.path().join(table).exists(), "missing {}", table);
+        }
+
+        let without_catalog = "sessions: 10\nstart_date: 2024-01-01\ndays: 2\norders: {}\noutputs: [{ format: sql, path: '-' }]";
+        assert!(TestDataConfig::from_yaml(without_catalog).is_err());
+
+        let no_touches = "sessions: 10\nstart_date: 2024-01-01\ndays: 2\nattribution: { max_touches: 0 }\noutputs: [{ format: sql, path: '-' }]";
+        assert!(TestDataConfig::from_yaml(no_touches).is_err());
+    }
+
+    #[test]
+    fn test_naming_scenario() {
+        let temp_dir = TempDir::new().unwrap();
+        let database = temp_dir.path().join("raw.duckdb");
+        let yaml = format!(
+            "sessions: 200\nstart_date: 2024-01-01\ndays: 2\n\
+             naming: {{ tables: {{ sessions: stg_sessions }},\n\
+             columns: {{ visitor_id: anonymous_id, visitors.visitor_id: id }} }}\n\
+             outputs: [{{ format: duckdb, database: {:?} }}]",
+            database
+        );
+        let counts = TestDataConfig::from_yaml(&yaml)
+            .unwrap()
+            .generate()
+            .unwrap();
+
+        let conn = duckdb::Connection::open(&database).unwrap();
+        let joined: usize = conn
+            .query_row(
+                "SELECT count(*) FROM stg_sessions s JOIN visitors v ON v.id = s.anonymous_id",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(joined, counts[0]);
+    }
+
+    #[test]
+    fn test_invalid_scenarios() {
+        let both = "sessions: 10\nstart_date: 2024-01-01\ndays: 2\nend_date: 2024-01-02\noutputs: [{ format: sql, path: '-' }]";
+        assert!(TestDataConfig::from_yaml(both).is_err());
+
+        let unknown = "sessions: 10\nstart_date: 2024-01-01\ndays: 2\nsizes: 3\noutputs: []";
+        assert!(TestDataConfig::from_yaml(unknown).is_err());
+
+        let no_outputs = "sessions: 10\nstart_date: 2024-01-01\ndays: 2\noutputs: []";
+        let err = TestDataConfig::from_yaml(no_outputs).unwrap_err();
+        assert!(err.to_string().contains("no outputs"));
+    }
+}
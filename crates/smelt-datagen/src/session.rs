@@ -1,15 +1,22 @@
 //! Session summary table generator.
 
+use crate::accounts::Account;
+use crate::anomaly::AnomalyConfig;
+use crate::funnel::Funnel;
 use crate::gen::Gen;
 use crate::generators::*;
+use crate::shape::TrafficShape;
 use chrono::NaiveDate;
 use rand::{Rng, RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
 /// Platform types for sessions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Platform {
     WebDesktop,
     Android,
@@ -29,7 +36,8 @@ impl Platform {
 }
 
 /// Visit source types.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VisitSource {
     Seo,
     Sem,
@@ -65,7 +73,8 @@ impl VisitSource {
 }
 
 /// Product categories.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ProductCategory {
     Electronics,
     Clothing,
@@ -109,7 +118,7 @@ pub struct Visitor {
 }
 
 /// A session record.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Session {
     pub visitor_id: Uuid,
     pub session_id: Uuid,
@@ -119,6 +128,8 @@ pub struct Session {
     pub widget_views: i32,
     pub session_date: NaiveDate,
     pub product_views: i32,
+    pub product_add_to_cart_count: i32,
+    pub product_checkout_count: i32,
     pub product_category: ProductCategory,
     pub product_revenue: i32,
     pub product_purchase_count: i32,
@@ -133,10 +144,30 @@ pub struct VisitorPool {
 impl VisitorPool {
     /// Create a visitor pool from a seed.
     pub fn new(seed: u64, target_sessions: usize) -> Self {
-        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        Self::with_shape(seed, target_sessions, &TrafficShape::default())
+    }
+
+    /// Create a visitor pool whose platform preferences and return
+    /// probabilities follow `shape`.
+    pub fn with_shape(seed: u64, target_sessions: usize, shape: &TrafficShape) -> Self {
         // Assume average 3-7 sessions per visitor over the period
         let num_visitors = target_sessions / 5;
-        let visitors = generate_visitors(&mut rng, num_visitors);
+        let visitors = generate_visitors(seed, num_visitors, shape);
+        Self {
+            visitors: Arc::new(visitors),
+        }
+    }
+
+    /// The same pool with each visitor's id passed through `f`.
+    pub(crate) fn map_ids(&self, f: impl Fn(Uuid) -> Uuid) -> Self {
+        let visitors = self
+            .visitors
+            .iter()
+            .map(|visitor| Visitor {
+                id: f(visitor.id),
+                ..visitor.clone()
+            })
+            .collect();
         Self {
             visitors: Arc::new(visitors),
         }
@@ -151,6 +182,11 @@ impl VisitorPool {
     pub fn is_empty(&self) -> bool {
         self.visitors.is_empty()
     }
+
+    /// The visitors in the pool, in generation order.
+    pub fn visitors(&self) -> &[Visitor] {
+        &self.visitors
+    }
 }
 
 /// Generate deterministic per-day seeds from a root seed.
@@ -166,6 +202,12 @@ pub struct DayGenerator {
     day_seed: u64,
     date: NaiveDate,
     sessions_per_day: usize,
+    funnel: Funnel,
+    /// Anomalies to inject, with the root seed that places them.
+    anomalies: Option<(Arc<AnomalyConfig>, u64)>,
+    /// Accounts in visitor pool order; only active accounts get sessions.
+    accounts: Option<Arc<Vec<Account>>>,
+    shape: Arc<TrafficShape>,
 }
 
 impl DayGenerator {
@@ -181,13 +223,63 @@ impl DayGenerator {
             day_seed,
             date,
             sessions_per_day,
+            funnel: Funnel::default(),
+            anomalies: None,
+            accounts: None,
+            shape: Arc::new(TrafficShape::default()),
         }
     }
 
+    /// Use `funnel` for view-to-purchase conversion instead of the default rates.
+    pub fn with_funnel(mut self, funnel: Funnel) -> Self {
+        self.funnel = funnel;
+        self
+    }
+
+    /// Inject `anomalies`, placed deterministically from the root `seed`.
+    pub fn with_anomalies(mut self, anomalies: Arc<AnomalyConfig>, seed: u64) -> Self {
+        self.anomalies = Some((anomalies, seed));
+        self
+    }
+
+    /// Only generate sessions for visitors whose account is active on this
+    /// day. `accounts` must be in visitor pool order.
+    pub fn with_accounts(mut self, accounts: Arc<Vec<Account>>) -> Self {
+        self.accounts = Some(accounts);
+        self
+    }
+
+    /// Draw platforms, sources, categories, and session lengths from `shape`.
+    /// The visitor pool should come from [`VisitorPool::with_shape`] with
+    /// the same shape.
+    pub fn with_shape(mut self, shape: Arc<TrafficShape>) -> Self {
+        self.shape = shape;
+        self
+    }
+
     /// Generate all sessions for this day, returning a Vec.
     pub fn generate(&self) -> Vec<Session> {
+        let mut sessions = self.generate_baseline();
+        if let Some((anomalies, seed)) = &self.anomalies {
+            anomalies.apply(*seed, self.day_seed, self.date, &mut sessions);
+        }
+        sessions
+    }
+
+    fn generate_baseline(&self) -> Vec<Session> {
         let mut rng = ChaCha8Rng::seed_from_u64(self.day_seed);
         let mut sessions = Vec::new();
+        let sessions_per_day = match &self.anomalies {
+            Some((anomalies, _)) => {
+                (self.sessions_per_day as f64 * anomalies.traffic_multiplier(self.date)) as usize
+            }
+            None => self.sessions_per_day,
+        };
+
+        let is_active = |idx: usize| match &self.accounts {
+            Some(accounts) => accounts[idx].is_active(self.date),
+            None => true,
+        };
 
         // Sample visitors for this day based on return probability
         let mut daily_visitor_indices: Vec<usize> = Vec::new();
@@ -195,15 +287,22 @@ impl DayGenerator {
         for (idx, visitor) in self.visitor_pool.visitors.iter().enumerate() {
             // Higher return probability = more likely to visit any given day
             let daily_visit_prob = 0.05 + visitor.return_probability * 0.15;
-            if rng.gen_bool(daily_visit_prob.min(1.0)) {
+            if rng.gen_bool(daily_visit_prob.min(1.0)) && is_active(idx) {
                 daily_visitor_indices.push(idx);
             }
         }
 
         // If we don't have enough visitors, sample more randomly
-        while daily_visitor_indices.len() < self.sessions_per_day / 2 {
+        let active_visitors = match &self.accounts {
+            Some(_) => (0..self.visitor_pool.len())
+                .filter(|&i| is_active(i))
+                .count(),
+            None => self.visitor_pool.len(),
+        };
+        let min_visitors = (sessions_per_day / 2).min(active_visitors);
+        while daily_visitor_indices.len() < min_visitors {
             let idx = rng.gen_range(0..self.visitor_pool.visitors.len());
-            if !daily_visitor_indices.contains(&idx) {
+            if is_active(idx) && !daily_visitor_indices.contains(&idx) {
                 daily_visitor_indices.push(idx);
             }
         }
@@ -218,7 +317,7 @@ impl DayGenerator {
                 let session_rows = self.generate_session(&mut rng, visitor);
                 sessions.extend(session_rows);
 
-                if sessions.len() >= self.sessions_per_day {
+                if sessions.len() >= sessions_per_day {
                     return sessions;
                 }
             }
@@ -236,18 +335,18 @@ impl DayGenerator {
         let platform = if rng.gen_bool(0.90) {
             visitor.platform_preference
         } else {
-            platform_gen().generate(rng)
+            self.shape.platform_gen().generate(rng)
         };
 
-        let visit_source = visit_source_gen().generate(rng);
+        let visit_source = self.shape.visit_source_gen().generate(rng);
         let visit_campaign = if visit_source.has_campaign() {
             Some(campaign_gen().generate(rng))
         } else {
             None
         };
 
-        // Widget views: log-normal, median ~5
-        let widget_views = log_normal(5.0, 1.0, 100).generate(rng);
+        // Widget views: log-normal, median ~5 by default
+        let widget_views = self.shape.widget_views_gen().generate(rng);
 
         // Generate 1-4 categories for this session (average ~2)
         let num_categories = {
@@ -261,12 +360,14 @@ impl DayGenerator {
             } else {
                 4
             }
-        };
+        }
+        .min(self.shape.category_count());
 
         // Select distinct categories for this session
         let mut selected_categories: Vec<ProductCategory> = Vec::with_capacity(num_categories);
+        let category_g = self.shape.category_gen();
         while selected_categories.len() < num_categories {
-            let cat = product_category_gen().generate(rng);
+            let cat = category_g.generate(rng);
             if !selected_categories.contains(&cat) {
                 selected_categories.push(cat);
             }
@@ -277,12 +378,9 @@ impl DayGenerator {
             // Product views: log-normal, median ~3 (split across categories)
             let product_views = log_normal(3.0 / num_categories as f64, 1.0, 50).generate(rng);
 
-            // Purchase: 80% zero, otherwise geometric
-            let product_purchase_count = if rng.gen_bool(0.80) {
-                0
-            } else {
-                geometric(0.5).generate(rng) + 1
-            };
+            // Each view walks the cart/checkout/purchase funnel
+            let funnel = self.funnel.walk(rng, product_views);
+            let product_purchase_count = funnel.purchase;
 
             // Revenue based on purchase count and category price
             let product_revenue = if product_purchase_count > 0 {
@@ -302,6 +400,8 @@ impl DayGenerator {
                 widget_views,
                 session_date: self.date,
                 product_views,
+                product_add_to_cart_count: funnel.add_to_cart,
+                product_checkout_count: funnel.checkout,
                 product_category,
                 product_revenue,
                 product_purchase_count,
@@ -312,17 +412,43 @@ impl DayGenerator {
     }
 }
 
-/// Generate the visitor pool.
-fn generate_visitors(rng: &mut impl Rng, count: usize) -> Vec<Visitor> {
+/// Visitors per independently seeded block. Fixed, so the pool doesn't depend
+/// on how many threads generate it.
+const VISITOR_BLOCK_SIZE: usize = 1 << 16;
+
+/// Generate the visitor pool in parallel.
+///
+/// Each block of [`VISITOR_BLOCK_SIZE`] visitors draws from its own ChaCha
+/// stream of `seed`, so the result is identical to generating the blocks one
+/// after another on a single thread.
+fn generate_visitors(seed: u64, count: usize, shape: &TrafficShape) -> Vec<Visitor> {
+    let blocks: Vec<Vec<Visitor>> = (0..count.div_ceil(VISITOR_BLOCK_SIZE))
+        .into_par_iter()
+        .map(|block| generate_visitor_block(seed, block, count, shape))
+        .collect();
+    blocks.concat()
+}
+
+/// Generate block `block` of a pool of `count` visitors.
+fn generate_visitor_block(
+    seed: u64,
+    block: usize,
+    count: usize,
+    shape: &TrafficShape,
+) -> Vec<Visitor> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(block as u64);
+    let len = VISITOR_BLOCK_SIZE.min(count - block * VISITOR_BLOCK_SIZE);
+
     let uuid_g = uuid_gen();
-    let platform_g = platform_gen();
+    let platform_g = shape.platform_gen();
 
-    (0..count)
+    (0..len)
         .map(|_| {
-            let id = uuid_g.generate(rng);
-            let platform_preference = platform_g.generate(rng);
+            let id = uuid_g.generate(&mut rng);
+            let platform_preference = platform_g.generate(&mut rng);
             // Power-law distribution for return probability
-            let return_probability = rng.gen::<f64>().powf(2.0) * 0.8;
+            let return_probability = shape.return_probability(rng.gen());
 
             Visitor {
                 id,
@@ -367,44 +493,8 @@ const CAMPAIGNS: &[&str] = &[
     "referral_bonus",
 ];
 
-/// Generator for the visitor's platform preference.
-fn platform_gen() -> WeightedChoice<Platform> {
-    weighted_choice(vec![
-        (Platform::WebDesktop, 0.40),
-        (Platform::Android, 0.25),
-        (Platform::Ios, 0.20),
-        (Platform::WebMobile, 0.15),
-    ])
-}
-
-/// Generator for visit source.
-fn visit_source_gen() -> WeightedChoice<VisitSource> {
-    weighted_choice(vec![
-        (VisitSource::Seo, 0.30),
-        (VisitSource::Direct, 0.25),
-        (VisitSource::Sem, 0.15),
-        (VisitSource::Referral, 0.10),
-        (VisitSource::Affiliate, 0.08),
-        (VisitSource::Email, 0.07),
-        (VisitSource::Social, 0.03),
-        (VisitSource::OrganicSocial, 0.02),
-    ])
-}
-
-/// Generator for product category.
-fn product_category_gen() -> WeightedChoice<ProductCategory> {
-    weighted_choice(vec![
-        (ProductCategory::Electronics, 0.20),
-        (ProductCategory::Clothing, 0.25),
-        (ProductCategory::Home, 0.15),
-        (ProductCategory::Sports, 0.15),
-        (ProductCategory::Beauty, 0.15),
-        (ProductCategory::Food, 0.10),
-    ])
-}
-
 /// Generator for campaign names.
-fn campaign_gen() -> OneOf<String> {
+pub(crate) fn campaign_gen() -> OneOf<String> {
     one_of(CAMPAIGNS.iter().map(|s| s.to_string()).collect())
 }
 
@@ -414,6 +504,8 @@ pub struct SessionGenerator {
     num_days: u32,
     target_sessions: usize,
     visitors: Vec<Visitor>,
+    funnel: Funnel,
+    shape: TrafficShape,
 }
 
 impl SessionGenerator {
@@ -425,21 +517,28 @@ impl SessionGenerator {
     /// * `num_days` - Number of days to generate sessions for
     /// * `target_sessions` - Approximate total number of sessions to generate
     pub fn new(seed: u64, start_date: NaiveDate, num_days: u32, target_sessions: usize) -> Self {
-        let mut rng = ChaCha8Rng::seed_from_u64(seed);
-
         // Calculate number of visitors needed
         // Assume average 3-7 sessions per visitor over the period
         let num_visitors = target_sessions / 5;
-        let visitors = generate_visitors(&mut rng, num_visitors);
+        let shape = TrafficShape::default();
+        let visitors = generate_visitors(seed, num_visitors, &shape);
 
         Self {
             start_date,
             num_days,
             target_sessions,
             visitors,
+            funnel: Funnel::default(),
+            shape,
         }
     }
 
+    /// Use `funnel` for view-to-purchase conversion instead of the default rates.
+    pub fn with_funnel(mut self, funnel: Funnel) -> Self {
+        self.funnel = funnel;
+        self
+    }
+
     /// Generate all sessions as an iterator.
     pub fn generate(self, seed: u64) -> SessionIterator {
         SessionIterator::new(self, seed)
@@ -567,10 +666,10 @@ impl Iterator for SessionIterator {
         let platform = if self.rng.gen_bool(0.90) {
             visitor.platform_preference
         } else {
-            platform_gen().generate(&mut self.rng)
+            self.config.shape.platform_gen().generate(&mut self.rng)
         };
 
-        let visit_source = visit_source_gen().generate(&mut self.rng);
+        let visit_source = self.config.shape.visit_source_gen().generate(&mut self.rng);
         let visit_campaign = if visit_source.has_campaign() {
             Some(campaign_gen().generate(&mut self.rng))
         } else {
@@ -578,7 +677,7 @@ impl Iterator for SessionIterator {
         };
 
         // Widget views: log-normal, median ~5
-        let widget_views = log_normal(5.0, 1.0, 100).generate(&mut self.rng);
+        let widget_views = self.config.shape.widget_views_gen().generate(&mut self.rng);
 
         let session_date =
             self.config.start_date + chrono::Duration::days((self.current_day - 1) as i64);
@@ -602,7 +701,7 @@ impl Iterator for SessionIterator {
         // There are 6 categories total, so num_categories (1-4) is always achievable
         let mut selected_categories: Vec<ProductCategory> = Vec::with_capacity(num_categories);
         while selected_categories.len() < num_categories {
-            let cat = product_category_gen().generate(&mut self.rng);
+            let cat = self.config.shape.category_gen().generate(&mut self.rng);
             if !selected_categories.contains(&cat) {
                 selected_categories.push(cat);
             }
@@ -615,12 +714,9 @@ impl Iterator for SessionIterator {
             let product_views =
                 log_normal(3.0 / num_categories as f64, 1.0, 50).generate(&mut self.rng);
 
-            // Purchase: 80% zero, otherwise geometric
-            let product_purchase_count = if self.rng.gen_bool(0.80) {
-                0
-            } else {
-                geometric(0.5).generate(&mut self.rng) + 1
-            };
+            // Each view walks the cart/checkout/purchase funnel
+            let funnel = self.config.funnel.walk(&mut self.rng, product_views);
+            let product_purchase_count = funnel.purchase;
 
             // Revenue based on purchase count and category price
             let product_revenue = if product_purchase_count > 0 {
@@ -641,6 +737,8 @@ impl Iterator for SessionIterator {
                 widget_views,
                 session_date,
                 product_views,
+                product_add_to_cart_count: funnel.add_to_cart,
+                product_checkout_count: funnel.checkout,
                 product_category,
                 product_revenue,
                 product_purchase_count,
@@ -683,6 +781,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parallel_visitors_match_serial_blocks() {
+        let count = VISITOR_BLOCK_SIZE * 2 + 5;
+        let shape = TrafficShape::default();
+        let parallel = generate_visitors(42, count, &shape);
+        let serial: Vec<_> = (0..3)
+            .flat_map(|block| generate_visitor_block(42, block, count, &shape))
+            .collect();
+
+        assert_eq!(parallel.len(), count);
+        assert_eq!(parallel.len(), serial.len());
+        for (p, s) in parallel.iter().zip(serial.iter()) {
+            assert_eq!(p.id, s.id);
+            assert_eq!(p.platform_preference, s.platform_preference);
+            assert_eq!(p.return_probability, s.return_probability);
+        }
+
+        // Blocks draw from distinct streams
+        assert_ne!(parallel[0].id, parallel[VISITOR_BLOCK_SIZE].id);
+    }
+
     #[test]
     fn test_campaign_only_for_relevant_sources() {
         let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
@@ -697,6 +816,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_funnel_counts_nested_within_views() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let gen = SessionGenerator::new(42, start, 30, 10000);
+
+        let mut purchases = 0;
+        for session in gen.generate(42).take(1000) {
+            assert!(session.product_add_to_cart_count <= session.product_views);
+            assert!(session.product_checkout_count <= session.product_add_to_cart_count);
+            assert!(session.product_purchase_count <= session.product_checkout_count);
+            purchases += session.product_purchase_count;
+        }
+        assert!(purchases > 0);
+    }
+
     #[test]
     fn test_revenue_correlates_with_purchases() {
         let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
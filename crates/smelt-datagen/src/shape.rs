@@ -0,0 +1,122 @@
+//! Traffic mix and session shape.
+//!
+//! The distributions behind each session that aren't part of the funnel:
+//! which platforms, sources, and categories traffic comes from, how long
+//! sessions run, and how often visitors come back. The defaults are the
+//! generator's built-in shape; [`crate::calibrate`] fits them to real data.
+
+use crate::generators::{log_normal, weighted_choice, LogNormal, WeightedChoice};
+use crate::session::{Platform, ProductCategory, VisitSource};
+use anyhow::{bail, Result};
+
+/// Longest session, in widget views.
+const MAX_WIDGET_VIEWS: i32 = 100;
+
+/// Weights and parameters of the session distributions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrafficShape {
+    /// Platform weights, for visitors' preferred platform and the sessions
+    /// that stray from it.
+    pub platforms: Vec<(Platform, f64)>,
+    pub visit_sources: Vec<(VisitSource, f64)>,
+    pub categories: Vec<(ProductCategory, f64)>,
+    /// Median widget views per session, which sets session length.
+    pub widget_views_median: f64,
+    /// Log-normal spread of widget views per session.
+    pub widget_views_sigma: f64,
+    /// Visitors return with probability `u^return_exponent` for uniform `u`,
+    /// so higher exponents mean more one-off visitors and a heavier tail of
+    /// regulars.
+    pub return_exponent: f64,
+}
+
+impl Default for TrafficShape {
+    fn default() -> Self {
+        Self {
+            platforms: vec![
+                (Platform::WebDesktop, 0.40),
+                (Platform::Android, 0.25),
+                (Platform::Ios, 0.20),
+                (Platform::WebMobile, 0.15),
+            ],
+            visit_sources: vec![
+                (VisitSource::Seo, 0.30),
+                (VisitSource::Direct, 0.25),
+                (VisitSource::Sem, 0.15),
+                (VisitSource::Referral, 0.10),
+                (VisitSource::Affiliate, 0.08),
+                (VisitSource::Email, 0.07),
+                (VisitSource::Social, 0.03),
+                (VisitSource::OrganicSocial, 0.02),
+            ],
+            categories: vec![
+                (ProductCategory::Electronics, 0.20),
+                (ProductCategory::Clothing, 0.25),
+                (ProductCategory::Home, 0.15),
+                (ProductCategory::Sports, 0.15),
+                (ProductCategory::Beauty, 0.15),
+                (ProductCategory::Food, 0.10),
+            ],
+            widget_views_median: 5.0,
+            widget_views_sigma: 1.0,
+            return_exponent: 2.0,
+        }
+    }
+}
+
+impl TrafficShape {
+    /// Check the weights can be sampled from.
+    pub fn validate(&self) -> Result<()> {
+        fn check<T>(name: &str, weights: &[(T, f64)]) -> Result<()> {
+            if weights.iter().any(|(_, w)| !w.is_finite() || *w < 0.0)
+                || !weights.iter().any(|(_, w)| *w > 0.0)
+            {
+                bail!(
+                    "{} weights must be non-negative with at least one positive",
+                    name
+                );
+            }
+            Ok(())
+        }
+        check("Platform", &self.platforms)?;
+        check("Visit source", &self.visit_sources)?;
+        check("Category", &self.categories)?;
+        if self.widget_views_median <= 0.0 || self.widget_views_sigma < 0.0 {
+            bail!("Widget views need a positive median and non-negative sigma");
+        }
+        if self.return_exponent <= 0.0 {
+            bail!("return_exponent must be positive");
+        }
+        Ok(())
+    }
+
+    pub fn platform_gen(&self) -> WeightedChoice<Platform> {
+        weighted_choice(self.platforms.clone())
+    }
+
+    pub fn visit_source_gen(&self) -> WeightedChoice<VisitSource> {
+        weighted_choice(self.visit_sources.clone())
+    }
+
+    pub fn category_gen(&self) -> WeightedChoice<ProductCategory> {
+        weighted_choice(self.categories.clone())
+    }
+
+    /// Categories with a positive weight; sessions can't span more.
+    pub fn category_count(&self) -> usize {
+        self.categories.iter().filter(|(_, w)| *w > 0.0).count()
+    }
+
+    pub fn widget_views_gen(&self) -> LogNormal {
+        log_normal(
+            self.widget_views_median,
+            self.widget_views_sigma,
+            MAX_WIDGET_VIEWS,
+        )
+    }
+
+    /// A visitor's return probability from a uniform draw `u`.
+    pub fn return_probability(&self, u: f64) -> f64 {
+        u.powf(self.return_exponent) * 0.8
+    }
+}
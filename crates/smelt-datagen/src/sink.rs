@@ -0,0 +1,1130 @@
+//! Destinations for generated batches.
+//!
+//! A [`DataSink`] receives visitors, sessions, and events one batch at a time,
+//! so [`StreamingOutput`] can generate a day, hand it off, and drop it before
+//! moving on. Sinks are provided for Parquet files, DuckDB (through the Arrow
+//! appender), and SQL statements written to any [`Write`].
+
+use crate::attribution::AttributionConfig;
+use crate::events::EventsConfig;
+use crate::naming::SchemaConfig;
+use crate::orders::OrdersConfig;
+use crate::output::{FirstSeen, OutputBuilder, OutputOptions};
+use crate::parquet::{session_schema, sessions_to_record_batch, write_batch};
+use crate::session::{Session, VisitorPool};
+use anyhow::{bail, Context, Result};
+use arrow::array::{Array, ArrayRef, Date32Array};
+use arrow::datatypes::{DataType, Date32Type, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use arrow::util::display::array_value_to_string;
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Rows per `INSERT` statement written by [`SqlSink`].
+const SQL_ROWS_PER_INSERT: usize = 1000;
+/// Most rows DuckDB's Arrow appender accepts per call (its vector size).
+const APPEND_CHUNK_SIZE: usize = 2048;
+
+/// Somewhere to put generated data, one batch at a time.
+///
+/// Session and event batches belong to a single `date` and don't include
+/// their date column; sinks add it however suits the destination.
+pub trait DataSink {
+    fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()>;
+
+    fn write_sessions(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()>;
+
+    fn write_events(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()>;
+
+    /// One day's orders and their line items.
+    fn write_orders(
+        &mut self,
+        date: NaiveDate,
+        orders: &RecordBatch,
+        items: &RecordBatch,
+    ) -> Result<()>;
+
+    /// Touchpoints on the paths to one day's conversions.
+    fn write_touchpoints(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()>;
+
+    /// One day's expected per-platform and per-category totals.
+    fn write_expected(
+        &mut self,
+        date: NaiveDate,
+        platforms: &RecordBatch,
+        categories: &RecordBatch,
+    ) -> Result<()>;
+
+    /// Flush anything buffered. Called once after the last batch.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes `visitors/`, `sessions/session_date=…/`, and `events/event_date=…/`
+/// under a root directory, one Parquet file per batch.
+#[derive(Debug)]
+pub struct ParquetSink {
+    root: PathBuf,
+    naming: SchemaConfig,
+    /// Files written so far in each directory.
+    files: HashMap<PathBuf, usize>,
+}
+
+impl ParquetSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            naming: SchemaConfig::default(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Rename tables (directories) and columns, including partition keys.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Write `batch` to `table`, under the `partition` column's `date`
+    /// directory if it has one.
+    fn write(
+        &mut self,
+        table: &str,
+        partition: Option<(&str, NaiveDate)>,
+        batch: &RecordBatch,
+    ) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        let mut dir = self.root.join(self.naming.table_name(table));
+        if let Some((column, date)) = partition {
+            dir = dir.join(format!(
+                "{}={}",
+                self.naming.column_name(table, column),
+                date
+            ));
+        }
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create output directory: {:?}", dir))?;
+        let part = self.files.entry(dir.clone()).or_default();
+        let path = dir.join(format!("part-{:05}.parquet", part));
+        *part += 1;
+        let batch = self.naming.apply(table, batch)?;
+        write_batch(&path, batch.schema(), &batch)
+    }
+}
+
+impl DataSink for ParquetSink {
+    fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.write("visitors", None, batch)
+    }
+
+    fn write_sessions(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("sessions", Some(("session_date", date)), batch)
+    }
+
+    fn write_events(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("events", Some(("event_date", date)), batch)
+    }
+
+    fn write_orders(
+        &mut self,
+        date: NaiveDate,
+        orders: &RecordBatch,
+        items: &RecordBatch,
+    ) -> Result<()> {
+        self.write("orders", Some(("order_date", date)), orders)?;
+        self.write("order_items", Some(("order_date", date)), items)
+    }
+
+    fn write_touchpoints(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("touchpoints", Some(("conversion_date", date)), batch)
+    }
+
+    fn write_expected(
+        &mut self,
+        date: NaiveDate,
+        platforms: &RecordBatch,
+        categories: &RecordBatch,
+    ) -> Result<()> {
+        let partition = Some(("session_date", date));
+        self.write("expected_platform_totals", partition, platforms)?;
+        self.write("expected_category_totals", partition, categories)
+    }
+}
+
+/// File format of the data files behind [`SqlSink::with_copy_from`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyFormat {
+    /// Read by DuckDB's `COPY ... FROM`.
+    Parquet,
+    /// CSV with a header row, read by both DuckDB and PostgreSQL.
+    Csv,
+}
+
+impl CopyFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            CopyFormat::Parquet => "parquet",
+            CopyFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Where [`SqlSink`] puts data files in COPY mode.
+#[derive(Debug)]
+struct CopyFiles {
+    dir: PathBuf,
+    format: CopyFormat,
+    /// Files written so far for each table.
+    files: HashMap<&'static str, usize>,
+}
+
+/// Writes `CREATE TABLE IF NOT EXISTS` and multi-row `INSERT` statements,
+/// e.g. to stdout for piping into a database shell.
+///
+/// With [`SqlSink::with_copy_from`] rows go to data files instead, loaded by
+/// one `COPY ... FROM` statement per file, which is much faster to execute.
+#[derive(Debug)]
+pub struct SqlSink<W: Write> {
+    writer: W,
+    naming: SchemaConfig,
+    created: HashSet<&'static str>,
+    copy: Option<CopyFiles>,
+}
+
+impl<W: Write> SqlSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            naming: SchemaConfig::default(),
+            created: HashSet::new(),
+            copy: None,
+        }
+    }
+
+    /// Rename tables and columns in the written statements.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Write each batch to a `format` file under `dir` and load it with
+    /// `COPY ... FROM` instead of `INSERT`. Statements reference files by
+    /// absolute path, so the database must be able to read `dir`.
+    pub fn with_copy_from(mut self, dir: impl Into<PathBuf>, format: CopyFormat) -> Self {
+        self.copy = Some(CopyFiles {
+            dir: dir.into(),
+            format,
+            files: HashMap::new(),
+        });
+        self
+    }
+
+    /// The underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, generated: &'static str, batch: &RecordBatch) -> Result<()> {
+        let batch = &self.naming.apply(generated, batch)?;
+        let table = self.naming.table_name(generated);
+        if self.created.insert(generated) {
+            writeln!(
+                self.writer,
+                "{};",
+                create_table_sql(table, &batch.schema())?
+            )?;
+        }
+
+        let columns = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| quote_ident(f.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Some(copy) = &mut self.copy {
+            if batch.num_rows() == 0 {
+                return Ok(());
+            }
+            let path = copy.write_file(generated, table, batch)?;
+            let options = match copy.format {
+                CopyFormat::Parquet => "FORMAT parquet",
+                CopyFormat::Csv => "FORMAT csv, HEADER",
+            };
+            writeln!(
+                self.writer,
+                "COPY {} ({}) FROM '{}' ({});",
+                quote_ident(table),
+                columns,
+                path.display().to_string().replace('\'', "''"),
+                options
+            )?;
+            return Ok(());
+        }
+        for start in (0..batch.num_rows()).step_by(SQL_ROWS_PER_INSERT) {
+            let chunk = batch.slice(start, SQL_ROWS_PER_INSERT.min(batch.num_rows() - start));
+            writeln!(
+                self.writer,
+                "INSERT INTO {} ({}) VALUES",
+                quote_ident(table),
+                columns
+            )?;
+            for row in 0..chunk.num_rows() {
+                let values = chunk
+                    .columns()
+                    .iter()
+                    .map(|column| sql_literal(column, row))
+                    .collect::<Result<Vec<_>>>()?;
+                let end = if row + 1 == chunk.num_rows() {
+                    ";"
+                } else {
+                    ","
+                };
+                writeln!(self.writer, "  ({}){}", values.join(", "), end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CopyFiles {
+    /// Write `batch` to the next file for `generated`, returning its
+    /// absolute path.
+    fn write_file(
+        &mut self,
+        generated: &'static str,
+        table: &str,
+        batch: &RecordBatch,
+    ) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create COPY directory: {:?}", self.dir))?;
+        let dir = fs::canonicalize(&self.dir)
+            .with_context(|| format!("Failed to resolve COPY directory: {:?}", self.dir))?;
+        let part = self.files.entry(generated).or_default();
+        let path = dir.join(format!("{}-{:05}.{}", table, part, self.format.extension()));
+        *part += 1;
+
+        match self.format {
+            CopyFormat::Parquet => write_batch(&path, batch.schema(), batch)?,
+            CopyFormat::Csv => {
+                let file = fs::File::create(&path)
+                    .with_context(|| format!("Failed to create CSV file: {:?}", path))?;
+                let mut writer = arrow::csv::WriterBuilder::new()
+                    .with_header(true)
+                    .build(std::io::BufWriter::new(file));
+                writer
+                    .write(batch)
+                    .with_context(|| format!("Failed to write CSV file: {:?}", path))?;
+                writer
+                    .into_inner()
+                    .flush()
+                    .with_context(|| format!("Failed to flush CSV file: {:?}", path))?;
+            }
+        }
+        Ok(path)
+    }
+}
+
+impl<W: Write> DataSink for SqlSink<W> {
+    fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.write("visitors", batch)
+    }
+
+    fn write_sessions(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("sessions", &with_date_column(batch, "session_date", date)?)
+    }
+
+    fn write_events(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("events", &with_date_column(batch, "event_date", date)?)
+    }
+
+    fn write_orders(
+        &mut self,
+        date: NaiveDate,
+        orders: &RecordBatch,
+        items: &RecordBatch,
+    ) -> Result<()> {
+        self.write("orders", &with_date_column(orders, "order_date", date)?)?;
+        self.write("order_items", items)
+    }
+
+    fn write_touchpoints(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write(
+            "touchpoints",
+            &with_date_column(batch, "conversion_date", date)?,
+        )
+    }
+
+    fn write_expected(
+        &mut self,
+        date: NaiveDate,
+        platforms: &RecordBatch,
+        categories: &RecordBatch,
+    ) -> Result<()> {
+        self.write(
+            "expected_platform_totals",
+            &with_date_column(platforms, "session_date", date)?,
+        )?;
+        self.write(
+            "expected_category_totals",
+            &with_date_column(categories, "session_date", date)?,
+        )
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush().context("Failed to flush SQL output")
+    }
+}
+
+/// Appends to `visitors`, `sessions`, and `events` tables in a DuckDB
+/// database, creating each from the first batch's schema if it's missing.
+pub struct DuckDbSink {
+    conn: duckdb::Connection,
+    naming: SchemaConfig,
+    created: HashSet<&'static str>,
+}
+
+impl DuckDbSink {
+    pub fn new(conn: duckdb::Connection) -> Self {
+        Self {
+            conn,
+            naming: SchemaConfig::default(),
+            created: HashSet::new(),
+        }
+    }
+
+    /// Rename tables and columns as they're created.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Open (or create) the database file at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = duckdb::Connection::open(path)
+            .with_context(|| format!("Failed to open DuckDB database: {:?}", path))?;
+        Ok(Self::new(conn))
+    }
+
+    /// The underlying connection.
+    pub fn into_inner(self) -> duckdb::Connection {
+        self.conn
+    }
+
+    fn write(&mut self, generated: &'static str, batch: &RecordBatch) -> Result<()> {
+        let batch = &self.naming.apply(generated, batch)?;
+        let table = self.naming.table_name(generated);
+        if self.created.insert(generated) {
+            self.conn
+                .execute_batch(&create_table_sql(table, &batch.schema())?)
+                .with_context(|| format!("Failed to create table {}", table))?;
+        }
+
+        let mut appender = self
+            .conn
+            .appender(table)
+            .with_context(|| format!("Failed to open appender for {}", table))?;
+        for start in (0..batch.num_rows()).step_by(APPEND_CHUNK_SIZE) {
+            let len = APPEND_CHUNK_SIZE.min(batch.num_rows() - start);
+            appender
+                .append_record_batch(batch.slice(start, len))
+                .with_context(|| format!("Failed to append to {}", table))?;
+        }
+        appender
+            .flush()
+            .with_context(|| format!("Failed to flush appender for {}", table))
+    }
+}
+
+impl DataSink for DuckDbSink {
+    fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.write("visitors", batch)
+    }
+
+    fn write_sessions(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("sessions", &with_date_column(batch, "session_date", date)?)
+    }
+
+    fn write_events(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write("events", &with_date_column(batch, "event_date", date)?)
+    }
+
+    fn write_orders(
+        &mut self,
+        date: NaiveDate,
+        orders: &RecordBatch,
+        items: &RecordBatch,
+    ) -> Result<()> {
+        self.write("orders", &with_date_column(orders, "order_date", date)?)?;
+        self.write("order_items", items)
+    }
+
+    fn write_touchpoints(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+        self.write(
+            "touchpoints",
+            &with_date_column(batch, "conversion_date", date)?,
+        )
+    }
+
+    fn write_expected(
+        &mut self,
+        date: NaiveDate,
+        platforms: &RecordBatch,
+        categories: &RecordBatch,
+    ) -> Result<()> {
+        self.write(
+            "expected_platform_totals",
+            &with_date_column(platforms, "session_date", date)?,
+        )?;
+        self.write(
+            "expected_category_totals",
+            &with_date_column(categories, "session_date", date)?,
+        )
+    }
+}
+
+/// `batch` with a `DATE` column named `name` appended, set to `date` in every row.
+pub(crate) fn with_date_column(
+    batch: &RecordBatch,
+    name: &str,
+    date: NaiveDate,
+) -> Result<RecordBatch> {
+    let mut fields: Vec<Field> = batch
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.as_ref().clone())
+        .collect();
+    fields.push(Field::new(name, DataType::Date32, false));
+    let mut columns: Vec<ArrayRef> = batch.columns().to_vec();
+    columns.push(Arc::new(Date32Array::from(vec![
+        Date32Type::from_naive_date(
+            date
+        );
+        batch.num_rows()
+    ])));
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .with_context(|| format!("Failed to add {} column", name))
+}
+
+fn create_table_sql(table: &str, schema: &Schema) -> Result<String> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let not_null = if field.is_nullable() { "" } else { " NOT NULL" };
+            Ok(format!(
+                "{} {}{}",
+                quote_ident(field.name()),
+                sql_type(field.data_type())?,
+                not_null
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!(
+        "CREATE TABLE IF NOT EXISTS {} ({})",
+        quote_ident(table),
+        columns.join(", ")
+    ))
+}
+
+fn sql_type(data_type: &DataType) -> Result<&'static str> {
+    Ok(match data_type {
+        DataType::Utf8 => "VARCHAR",
+        DataType::Int32 => "INTEGER",
+        DataType::Int64 => "BIGINT",
+        DataType::Float64 => "DOUBLE",
+        DataType::Boolean => "BOOLEAN",
+        DataType::Date32 => "DATE",
+        DataType::Timestamp(_, None) => "TIMESTAMP",
+        other => bail!("No SQL type for {}", other),
+    })
+}
+
+fn sql_literal(column: &ArrayRef, row: usize) -> Result<String> {
+    if column.is_null(row) {
+        return Ok("NULL".to_string());
+    }
+    let value = array_value_to_string(column, row)?;
+    Ok(match column.data_type() {
+        DataType::Utf8 => format!("'{}'", value.replace('\'', "''")),
+        DataType::Date32 => format!("DATE '{}'", value),
+        DataType::Timestamp(_, None) => format!("TIMESTAMP '{}'", value),
+        _ => value,
+    })
+}
+
+pub(crate) fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Streams generated visitors and sessions into a [`DataSink`].
+///
+/// Each day's sessions are written in date order, then the visitors, whose
+/// `first_seen` dates need every day; at most one day per thread is held in
+/// memory at a time.
+#[derive(Debug, Clone, Default)]
+pub struct StreamingOutput {
+    pub(crate) options: OutputOptions,
+}
+
+impl OutputBuilder for StreamingOutput {
+    fn options_mut(&mut self) -> &mut OutputOptions {
+        &mut self.options
+    }
+}
+
+impl StreamingOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write each session's page view and product events.
+    pub fn with_events(mut self, events: EventsConfig) -> Self {
+        self.options.events = Some(events);
+        self
+    }
+
+    /// Write an order with line items for every session with purchases.
+    /// Needs a catalog for product prices.
+    pub fn with_orders(mut self, orders: OrdersConfig) -> Self {
+        self.options.orders = Some(orders);
+        self
+    }
+
+    /// Write the marketing touchpoints leading up to every conversion.
+    pub fn with_attribution(mut self, attribution: AttributionConfig) -> Self {
+        self.options.attribution = Some(attribution);
+        self
+    }
+
+    /// Write each day's per-platform and per-category totals alongside the
+    /// raw data, for asserting model outputs exactly.
+    pub fn with_expected_aggregates(mut self) -> Self {
+        self.options.expected_aggregates = true;
+        self
+    }
+
+    /// Generate the visitor pool and every day's sessions into `sink`,
+    /// returning the number of sessions written.
+    pub fn write_to(
+        &self,
+        sink: &mut dyn DataSink,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+    ) -> Result<usize> {
+        let visitor_pool = self.options.visitor_pool(seed, num_sessions, start_date);
+        let mut first_seen = FirstSeen::default();
+        let count = self.options.for_each_day(
+            &visitor_pool,
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            |date, day_seed, sessions| {
+                first_seen.record(date, sessions);
+                self.write_sessions(sink, seed, date, day_seed, sessions)
+            },
+        )?;
+        self.write_pool(sink, &visitor_pool, &first_seen)?;
+        sink.finish()?;
+        Ok(count)
+    }
+
+    /// Write only the visitor pool for the range, e.g. once before loading
+    /// days one at a time with [`Self::write_day`]. Generates the range's
+    /// sessions (without writing them) to find each visitor's `first_seen`.
+    pub fn write_visitors(
+        &self,
+        sink: &mut dyn DataSink,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+    ) -> Result<usize> {
+        let visitor_pool = self.options.visitor_pool(seed, num_sessions, start_date);
+        let first_seen =
+            self.options
+                .first_seen(&visitor_pool, seed, num_sessions, num_days, start_date)?;
+        self.write_pool(sink, &visitor_pool, &first_seen)?;
+        sink.finish()?;
+        Ok(visitor_pool.len())
+    }
+
+    /// Write only `date`'s sessions from the range `write_to` would generate
+    /// with the same arguments, for simulating incremental daily loads.
+    pub fn write_day(
+        &self,
+        sink: &mut dyn DataSink,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+        date: NaiveDate,
+    ) -> Result<usize> {
+        let visitor_pool = self.options.visitor_pool(seed, num_sessions, start_date);
+        let (day_seed, sessions) = self.options.single_day(
+            &visitor_pool,
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            date,
+        )?;
+        self.write_sessions(sink, seed, date, day_seed, &sessions)?;
+        sink.finish()?;
+        Ok(sessions.len())
+    }
+
+    /// Write one day's sessions, and its events, orders, touchpoints, and
+    /// expected aggregates if enabled.
+    fn write_sessions(
+        &self,
+        sink: &mut dyn DataSink,
+        seed: u64,
+        date: NaiveDate,
+        day_seed: u64,
+        sessions: &[Session],
+    ) -> Result<()> {
+        let batch = sessions_to_record_batch(sessions, &Arc::new(session_schema()))?;
+        sink.write_sessions(date, &self.options.finish_batch(batch, seed, day_seed)?)?;
+        if let Some(events) = self.options.event_batch(sessions, day_seed)? {
+            sink.write_events(date, &events)?;
+        }
+        if let Some((orders, items)) = self.options.order_batches(sessions, day_seed)? {
+            sink.write_orders(date, &orders, &items)?;
+        }
+        if let Some(touchpoints) = self.options.touchpoint_batch(sessions, day_seed)? {
+            sink.write_touchpoints(date, &touchpoints)?;
+        }
+        if let Some((platforms, categories)) = self.options.expected_batches(sessions)? {
+            sink.write_expected(date, &platforms, &categories)?;
+        }
+        Ok(())
+    }
+
+    fn write_pool(
+        &self,
+        sink: &mut dyn DataSink,
+        visitor_pool: &VisitorPool,
+        first_seen: &FirstSeen,
+    ) -> Result<()> {
+        sink.write_visitors(&self.options.visitors_batch(visitor_pool, first_seen)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::AccountConfig;
+    use crate::anomaly::AnomalyConfig;
+    use crate::catalog::Catalog;
+    use crate::dirty::DirtyDataConfig;
+    use crate::ids::IdFormat;
+    use tempfile::TempDir;
+
+    fn start_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    }
+
+    /// Keeps every batch, for comparing runs.
+    #[derive(Default)]
+    struct MemorySink {
+        visitors: Vec<RecordBatch>,
+        sessions: Vec<(NaiveDate, RecordBatch)>,
+    }
+
+    impl DataSink for MemorySink {
+        fn write_visitors(&mut self, batch: &RecordBatch) -> Result<()> {
+            self.visitors.push(batch.clone());
+            Ok(())
+        }
+
+        fn write_sessions(&mut self, date: NaiveDate, batch: &RecordBatch) -> Result<()> {
+            self.sessions.push((date, batch.clone()));
+            Ok(())
+        }
+
+        fn write_events(&mut self, _date: NaiveDate, _batch: &RecordBatch) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_orders(
+            &mut self,
+            _date: NaiveDate,
+            _orders: &RecordBatch,
+            _items: &RecordBatch,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_touchpoints(&mut self, _date: NaiveDate, _batch: &RecordBatch) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_expected(
+            &mut self,
+            _date: NaiveDate,
+            _platforms: &RecordBatch,
+            _categories: &RecordBatch,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_duckdb_sink() {
+        let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+        let count = StreamingOutput::new()
+            .write_to(&mut sink, 42, 1000, 5, start_date())
+            .unwrap();
+
+        let conn = sink.into_inner();
+        let sessions: usize = conn
+            .query_row("SELECT count(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sessions, count);
+
+        let days: usize = conn
+            .query_row(
+                "SELECT count(DISTINCT session_date) FROM sessions",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(days, 5);
+
+        let orphans: usize = conn
+            .query_row(
+                "SELECT count(*) FROM sessions s \
+                 LEFT JOIN visitors v USING (visitor_id) WHERE v.visitor_id IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(orphans, 0);
+    }
+
+    #[test]
+    fn test_parquet_sink() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut sink = ParquetSink::new(temp_dir.path());
+        StreamingOutput::new()
+            .write_to(&mut sink, 42, 300, 3, start_date())
+            .unwrap();
+
+        assert!(temp_dir.path().join("visitors/part-00000.parquet").exists());
+        for day in 1..=3 {
+            let partition = format!("sessions/session_date=2024-01-0{}/part-00000.parquet", day);
+            assert!(temp_dir.path().join(partition).exists());
+        }
+    }
+
+    #[test]
+    fn test_sql_sink_round_trips_through_duckdb() {
+        let mut sink = SqlSink::new(Vec::new());
+        let count = StreamingOutput::new()
+            .with_dirty_data(DirtyDataConfig::new().with_malformed_rate("visit_source", 0.5))
+            .write_to(&mut sink, 42, 200, 2, start_date())
+            .unwrap();
+        let sql = String::from_utf8(sink.into_inner()).unwrap();
+        assert_eq!(sql.matches("CREATE TABLE").count(), 2);
+
+        let conn = duckdb::Connection::open_in_memory().unwrap();
+        conn.execute_batch(&sql).unwrap();
+        let sessions: usize = conn
+            .query_row("SELECT count(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sessions, count);
+    }
+
+    #[test]
+    fn test_sql_sink_copy_matches_inserts() {
+        let conn = duckdb::Connection::open_in_memory().unwrap();
+        let mut sink = SqlSink::new(Vec::new());
+        StreamingOutput::new()
+            .write_to(&mut sink, 42, 200, 2, start_date())
+            .unwrap();
+        conn.execute_batch("CREATE SCHEMA inserts; SET schema = 'inserts';")
+            .unwrap();
+        conn.execute_batch(&String::from_utf8(sink.into_inner()).unwrap())
+            .unwrap();
+
+        for (schema, format) in [("parquet", CopyFormat::Parquet), ("csv", CopyFormat::Csv)] {
+            let temp_dir = TempDir::new().unwrap();
+            let mut sink = SqlSink::new(Vec::new()).with_copy_from(temp_dir.path(), format);
+            StreamingOutput::new()
+                .write_to(&mut sink, 42, 200, 2, start_date())
+                .unwrap();
+            let sql = String::from_utf8(sink.into_inner()).unwrap();
+            assert!(!sql.contains("INSERT"));
+            assert!(sql.contains("COPY \"sessions\""));
+
+            conn.execute_batch(&format!("CREATE SCHEMA {0}; SET schema = '{0}';", schema))
+                .unwrap();
+            conn.execute_batch(&sql).unwrap();
+            let differing: usize = conn
+                .query_row(
+                    &format!(
+                        "SELECT count(*) FROM (
+                             (SELECT * FROM inserts.sessions EXCEPT ALL SELECT * FROM {0}.sessions)
+                             UNION ALL
+                             (SELECT * FROM {0}.sessions EXCEPT ALL SELECT * FROM inserts.sessions))",
+                        schema
+                    ),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(differing, 0, "{:?} COPY differs from INSERT", format);
+        }
+    }
+
+    #[test]
+    fn test_id_formats_keep_references() {
+        let catalog = Catalog::generate(42, &crate::CatalogConfig::default()).unwrap();
+        for ids in [IdFormat::Uuid7, IdFormat::Hex, IdFormat::Snowflake] {
+            let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+            StreamingOutput::new()
+                .with_id_format(ids)
+                .with_catalog(catalog.clone())
+                .with_events(EventsConfig::default())
+                .with_orders(OrdersConfig::default())
+                .write_to(&mut sink, 42, 1000, 3, start_date())
+                .unwrap();
+            let conn = sink.into_inner();
+            let count = |sql: &str| -> usize { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+
+            assert_eq!(
+                count(
+                    "SELECT count(*) FROM sessions s \
+                     LEFT JOIN visitors v USING (visitor_id) WHERE v.visitor_id IS NULL"
+                ),
+                0,
+                "{:?}",
+                ids
+            );
+            assert_eq!(
+                count(
+                    "SELECT count(*) FROM events e \
+                     LEFT JOIN sessions s USING (session_id) WHERE s.session_id IS NULL"
+                ),
+                0,
+                "{:?}",
+                ids
+            );
+            assert_eq!(
+                count(
+                    "SELECT count(*) FROM order_items i \
+                     LEFT JOIN orders o USING (order_id) WHERE o.order_id IS NULL"
+                ),
+                0,
+                "{:?}",
+                ids
+            );
+            // Ids sort by creation time, so later days' sessions sort after earlier ones.
+            assert_eq!(
+                count(
+                    "SELECT count(*) FROM sessions a JOIN sessions b \
+                     ON a.session_date < b.session_date AND a.session_id >= b.session_id"
+                ),
+                0,
+                "{:?}",
+                ids
+            );
+        }
+    }
+
+    #[test]
+    fn test_orders_reconcile_in_duckdb() {
+        let catalog = Catalog::generate(42, &crate::CatalogConfig::default()).unwrap();
+        let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+        StreamingOutput::new()
+            .with_catalog(catalog)
+            .with_orders(OrdersConfig::default())
+            .write_to(&mut sink, 42, 2000, 3, start_date())
+            .unwrap();
+
+        let conn = sink.into_inner();
+        let (orders, mismatched, orphans): (usize, usize, usize) = conn
+            .query_row(
+                "SELECT count(*), \
+                   count(*) FILTER (WHERE o.total <> i.items_total), \
+                   count(*) FILTER (WHERE s.session_id IS NULL) \
+                 FROM orders o \
+                 JOIN (SELECT order_id, sum(line_total) AS items_total \
+                       FROM order_items GROUP BY order_id) i USING (order_id) \
+                 LEFT JOIN sessions s ON s.session_id = o.session_id \
+                   AND s.session_date = o.order_date",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert!(orders > 0);
+        assert_eq!(mismatched, 0);
+        assert_eq!(orphans, 0);
+    }
+
+    #[test]
+    fn test_expected_aggregates_match_sessions() {
+        let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+        StreamingOutput::new()
+            .with_anomalies(AnomalyConfig::new().with_duplicate_rate(0.05))
+            .with_expected_aggregates()
+            .write_to(&mut sink, 42, 2000, 3, start_date())
+            .unwrap();
+
+        let conn = sink.into_inner();
+        let count_differences = |actual: &str, expected: &str| -> usize {
+            let sql = format!(
+                "SELECT count(*) FROM (({actual} EXCEPT ALL {expected}) \
+                 UNION ALL ({expected} EXCEPT ALL {actual}))"
+            );
+            conn.query_row(&sql, [], |row| row.get(0)).unwrap()
+        };
+
+        assert_eq!(
+            count_differences(
+                "SELECT session_date, platform, count(DISTINCT session_id), \
+                   count(DISTINCT visitor_id), count(*) \
+                 FROM sessions GROUP BY ALL",
+                "SELECT session_date, platform, sessions, visitors, \"rows\" \
+                 FROM expected_platform_totals",
+            ),
+            0
+        );
+        assert_eq!(
+            count_differences(
+                "SELECT session_date, product_category, sum(product_views)::BIGINT, \
+                   sum(product_add_to_cart_count)::BIGINT, sum(product_checkout_count)::BIGINT, \
+                   sum(product_purchase_count)::BIGINT, sum(product_revenue)::BIGINT \
+                 FROM sessions GROUP BY ALL",
+                "SELECT session_date, product_category, product_views, add_to_cart_count, \
+                   checkout_count, purchase_count, revenue \
+                 FROM expected_category_totals",
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_events_reconcile_with_sessions() {
+        let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+        StreamingOutput::new()
+            .with_events(EventsConfig::default())
+            .write_to(&mut sink, 42, 2000, 3, start_date())
+            .unwrap();
+
+        let conn = sink.into_inner();
+        let (mismatched, orphans): (usize, usize) = conn
+            .query_row(
+                "WITH s AS ( \
+                   SELECT session_id, session_date, sum(product_views) AS views, \
+                     sum(product_revenue) AS revenue \
+                   FROM sessions GROUP BY ALL), \
+                 e AS ( \
+                   SELECT session_id, event_date, \
+                     count(*) FILTER (WHERE event_type = 'product_view') AS views, \
+                     coalesce(sum(revenue), 0) AS revenue \
+                   FROM events GROUP BY ALL) \
+                 SELECT \
+                   count(*) FILTER (WHERE s.views <> e.views OR s.revenue <> e.revenue), \
+                   count(*) FILTER (WHERE s.session_id IS NULL) \
+                 FROM e LEFT JOIN s ON s.session_id = e.session_id \
+                   AND s.session_date = e.event_date",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(mismatched, 0);
+        assert_eq!(orphans, 0);
+    }
+
+    #[test]
+    fn test_visitors_first_seen_matches_sessions() {
+        let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+        StreamingOutput::new()
+            .write_to(&mut sink, 42, 2000, 5, start_date())
+            .unwrap();
+
+        let conn = sink.into_inner();
+        let (mismatched, unseen): (usize, usize) = conn
+            .query_row(
+                "SELECT \
+                   count(*) FILTER (WHERE v.first_seen IS DISTINCT FROM s.first_session), \
+                   count(*) FILTER (WHERE v.first_seen IS NULL) \
+                 FROM visitors v LEFT JOIN ( \
+                   SELECT visitor_id, min(session_date) AS first_session \
+                   FROM sessions GROUP BY visitor_id) s USING (visitor_id)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(mismatched, 0);
+        assert!(unseen < 400);
+    }
+
+    #[test]
+    fn test_orders_need_catalog() {
+        let err = StreamingOutput::new()
+            .with_orders(OrdersConfig::default())
+            .write_to(&mut MemorySink::default(), 42, 500, 1, start_date())
+            .unwrap_err();
+        assert!(err.to_string().contains("catalog"));
+    }
+
+    #[test]
+    fn test_single_days_match_full_range() {
+        let output = StreamingOutput::new()
+            .with_accounts(AccountConfig::default())
+            .with_anomalies(
+                AnomalyConfig::new()
+                    .with_traffic_spike(NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), 2.0)
+                    .with_bot_visitors(2, 5),
+            );
+        let mut full = MemorySink::default();
+        output
+            .write_to(&mut full, 42, 1000, 5, start_date())
+            .unwrap();
+
+        let mut incremental = MemorySink::default();
+        output
+            .write_visitors(&mut incremental, 42, 1000, 5, start_date())
+            .unwrap();
+        for day in 0..5 {
+            let date = start_date() + chrono::Duration::days(day);
+            output
+                .write_day(&mut incremental, 42, 1000, 5, start_date(), date)
+                .unwrap();
+        }
+
+        assert_eq!(incremental.visitors, full.visitors);
+        assert_eq!(incremental.sessions, full.sessions);
+    }
+
+    #[test]
+    fn test_single_day_outside_range() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let err = StreamingOutput::new()
+            .write_day(&mut MemorySink::default(), 42, 100, 5, start_date(), date)
+            .unwrap_err();
+        assert!(err.to_string().contains("outside"));
+    }
+
+    #[test]
+    fn test_sql_literal_quoting() {
+        let column: ArrayRef = Arc::new(arrow::array::StringArray::from(vec![Some("it's"), None]));
+        assert_eq!(sql_literal(&column, 0).unwrap(), "'it''s'");
+        assert_eq!(sql_literal(&column, 1).unwrap(), "NULL");
+    }
+}
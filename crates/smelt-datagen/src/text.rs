@@ -0,0 +1,332 @@
+//! CSV and newline-delimited JSON writers.
+//!
+//! Unlike the Parquet writer these produce a single unpartitioned stream with
+//! `session_date` as a regular column, so output can be piped straight into
+//! tools that don't read Arrow or Parquet.
+
+use crate::naming::SchemaConfig;
+use crate::output::{OutputBuilder, OutputOptions};
+use crate::parquet::{session_schema, sessions_to_record_batch};
+use crate::session::Session;
+use anyhow::{bail, Context, Result};
+use arrow::array::{ArrayRef, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Default `session_date` format (ISO 8601).
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Writes sessions as delimited text.
+#[derive(Debug, Clone)]
+pub struct CsvOutput {
+    delimiter: u8,
+    header: bool,
+    date_format: String,
+    naming: SchemaConfig,
+    pub(crate) options: OutputOptions,
+}
+
+impl Default for CsvOutput {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            naming: SchemaConfig::default(),
+            options: OutputOptions::default(),
+        }
+    }
+}
+
+impl OutputBuilder for CsvOutput {
+    fn options_mut(&mut self) -> &mut OutputOptions {
+        &mut self.options
+    }
+}
+
+impl CsvOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Field delimiter (default `,`).
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether to write a header row (default `true`).
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// strftime-style format for `session_date` (default `%Y-%m-%d`).
+    pub fn with_date_format(mut self, format: impl Into<String>) -> Self {
+        self.date_format = format.into();
+        self
+    }
+
+    /// Rename columns, including `session_date`.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Write `sessions` to `writer`, returning the number of rows written.
+    pub fn write_sessions<W: Write>(&self, writer: W, sessions: &[Session]) -> Result<usize> {
+        let mut csv = self.writer(writer)?;
+        let batch = self.batch(sessions, 0, 0)?;
+        csv.write(&batch).context("Failed to write CSV rows")?;
+        Ok(sessions.len())
+    }
+
+    /// Generate sessions day by day and write them to `writer` in date order.
+    ///
+    /// Uses the same visitor pool and per-day seeds as
+    /// [`write_sessions_to_parquet`](crate::parquet::write_sessions_to_parquet),
+    /// so the rows match the Parquet output for the same arguments.
+    pub fn write_days<W: Write>(
+        &self,
+        writer: W,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+    ) -> Result<usize> {
+        let mut csv = self.writer(writer)?;
+        self.options.for_each_day(
+            &self.options.visitor_pool(seed, num_sessions, start_date),
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            |_, day_seed, sessions| {
+                let batch = self.batch(sessions, seed, day_seed)?;
+                csv.write(&batch).context("Failed to write CSV rows")
+            },
+        )
+    }
+
+    fn batch(&self, sessions: &[Session], seed: u64, day_seed: u64) -> Result<RecordBatch> {
+        let batch = sessions_to_text_batch(sessions, &self.date_format)?;
+        self.naming.apply(
+            "sessions",
+            &self.options.finish_batch(batch, seed, day_seed)?,
+        )
+    }
+
+    fn writer<W: Write>(&self, writer: W) -> Result<arrow::csv::Writer<W>> {
+        validate_date_format(&self.date_format)?;
+        Ok(arrow::csv::WriterBuilder::new()
+            .with_delimiter(self.delimiter)
+            .with_header(self.header)
+            .build(writer))
+    }
+}
+
+/// Writes sessions as newline-delimited JSON, one object per row.
+#[derive(Debug, Clone)]
+pub struct JsonLinesOutput {
+    date_format: String,
+    naming: SchemaConfig,
+    pub(crate) options: OutputOptions,
+}
+
+impl Default for JsonLinesOutput {
+    fn default() -> Self {
+        Self {
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            naming: SchemaConfig::default(),
+            options: OutputOptions::default(),
+        }
+    }
+}
+
+impl OutputBuilder for JsonLinesOutput {
+    fn options_mut(&mut self) -> &mut OutputOptions {
+        &mut self.options
+    }
+}
+
+impl JsonLinesOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// strftime-style format for `session_date` (default `%Y-%m-%d`).
+    pub fn with_date_format(mut self, format: impl Into<String>) -> Self {
+        self.date_format = format.into();
+        self
+    }
+
+    /// Rename columns, including `session_date`.
+    pub fn with_naming(mut self, naming: SchemaConfig) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Write `sessions` to `writer`, returning the number of rows written.
+    pub fn write_sessions<W: Write>(&self, writer: W, sessions: &[Session]) -> Result<usize> {
+        validate_date_format(&self.date_format)?;
+        let mut json = arrow::json::LineDelimitedWriter::new(writer);
+        let batch = self.batch(sessions, 0, 0)?;
+        json.write(&batch).context("Failed to write JSON rows")?;
+        json.finish().context("Failed to finish JSON output")?;
+        Ok(sessions.len())
+    }
+
+    /// Generate sessions day by day and write them to `writer` in date order.
+    ///
+    /// See [`CsvOutput::write_days`].
+    pub fn write_days<W: Write>(
+        &self,
+        writer: W,
+        seed: u64,
+        num_sessions: usize,
+        num_days: u32,
+        start_date: NaiveDate,
+    ) -> Result<usize> {
+        validate_date_format(&self.date_format)?;
+        let mut json = arrow::json::LineDelimitedWriter::new(writer);
+        let count = self.options.for_each_day(
+            &self.options.visitor_pool(seed, num_sessions, start_date),
+            seed,
+            num_sessions,
+            num_days,
+            start_date,
+            |_, day_seed, sessions| {
+                let batch = self.batch(sessions, seed, day_seed)?;
+                json.write(&batch).context("Failed to write JSON rows")
+            },
+        )?;
+        json.finish().context("Failed to finish JSON output")?;
+        Ok(count)
+    }
+
+    fn batch(&self, sessions: &[Session], seed: u64, day_seed: u64) -> Result<RecordBatch> {
+        let batch = sessions_to_text_batch(sessions, &self.date_format)?;
+        self.naming.apply(
+            "sessions",
+            &self.options.finish_batch(batch, seed, day_seed)?,
+        )
+    }
+}
+
+/// Reject formats chrono can't render, which would otherwise panic mid-write.
+fn validate_date_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        bail!("Invalid date format: {:?}", format);
+    }
+    Ok(())
+}
+
+/// Session batch with `session_date` appended as a formatted string column.
+fn sessions_to_text_batch(sessions: &[Session], date_format: &str) -> Result<RecordBatch> {
+    let base = sessions_to_record_batch(sessions, &Arc::new(session_schema()))?;
+
+    let mut dates = StringBuilder::new();
+    for session in sessions {
+        dates.append_value(session.session_date.format(date_format).to_string());
+    }
+
+    let mut fields: Vec<Field> = base
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.as_ref().clone())
+        .collect();
+    fields.push(Field::new("session_date", DataType::Utf8, false));
+
+    let mut columns: Vec<ArrayRef> = base.columns().to_vec();
+    columns.push(Arc::new(dates.finish()));
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .context("Failed to create record batch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dirty::DirtyDataConfig;
+
+    fn start_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_csv_header_delimiter_and_date_format() {
+        let mut out = Vec::new();
+        let count = CsvOutput::new()
+            .with_delimiter(b'|')
+            .with_date_format("%d/%m/%Y")
+            .write_days(&mut out, 42, 1000, 5, start_date())
+            .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        let header = lines.next().unwrap();
+        assert!(header.starts_with("visitor_id|session_id|"));
+        assert!(header.ends_with("|session_date"));
+        assert_eq!(lines.clone().count(), count);
+        assert!(lines.next().unwrap().ends_with("|01/01/2024"));
+    }
+
+    #[test]
+    fn test_csv_without_header() {
+        let mut out = Vec::new();
+        let count = CsvOutput::new()
+            .with_header(false)
+            .write_days(&mut out, 42, 1000, 5, start_date())
+            .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), count);
+        assert!(!text.starts_with("visitor_id"));
+    }
+
+    #[test]
+    fn test_json_lines_one_object_per_session() {
+        let mut out = Vec::new();
+        let count = JsonLinesOutput::new()
+            .write_days(&mut out, 42, 1000, 5, start_date())
+            .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), count);
+        let first = text.lines().next().unwrap();
+        assert!(first.starts_with("{\"visitor_id\":"));
+        assert!(first.contains("\"session_date\":\"2024-01-01\""));
+    }
+
+    #[test]
+    fn test_csv_dirty_dates() {
+        let mut out = Vec::new();
+        CsvOutput::new()
+            .with_dirty_data(DirtyDataConfig::new().with_malformed_rate("session_date", 0.2))
+            .write_days(&mut out, 42, 1000, 5, start_date())
+            .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let dates: Vec<_> = text
+            .lines()
+            .skip(1)
+            .map(|line| line.rsplit(',').next().unwrap())
+            .collect();
+        assert!(dates.iter().any(|d| d.starts_with("2024-01-0")));
+        assert!(dates.iter().any(|d| !d.starts_with("2024-01-0")));
+    }
+
+    #[test]
+    fn test_invalid_date_format_is_rejected() {
+        let err = JsonLinesOutput::new()
+            .with_date_format("%Q")
+            .write_sessions(Vec::new(), &[])
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid date format"));
+    }
+}
@@ -0,0 +1,392 @@
+//! Structural invariants of generated data.
+//!
+//! Each rule is a SQL query over the generated tables counting the rows
+//! that break it, run through DuckDB against a database the generator
+//! loaded (see [`crate::DuckDbSink`]) or a directory of Parquet it wrote.
+//! Rules whose tables are missing are skipped, so the same checks cover a
+//! sessions-only dataset and one with every table. Clean generated data
+//! passes all of them; dirty data and anomalies are reported.
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use std::fmt;
+use std::path::Path;
+
+/// Campaign sources; sessions from them have a campaign and others don't.
+const CAMPAIGN_SOURCES: &str = "('sem', 'referral', 'affiliate', 'email')";
+
+/// Tables a Parquet root may hold, one directory each.
+const TABLES: [&str; 6] = [
+    "visitors",
+    "sessions",
+    "events",
+    "orders",
+    "order_items",
+    "touchpoints",
+];
+
+/// A rule and the rows breaking it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub count: u64,
+    /// The key of one offending row.
+    pub example: Option<String>,
+}
+
+/// Outcome of a validation run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Rules run, including those that passed.
+    pub rules_checked: Vec<&'static str>,
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Fail with the report if any rule was broken.
+    pub fn ensure_ok(&self) -> Result<()> {
+        if !self.is_ok() {
+            bail!("Generated data breaks its invariants:\n{}", self);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} rules checked, {} broken",
+            self.rules_checked.len(),
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "  {}: {} rows", violation.rule, violation.count)?;
+            if let Some(example) = &violation.example {
+                write!(f, " (e.g. {})", example)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+struct Rule {
+    name: &'static str,
+    tables: &'static [&'static str],
+    /// Selects the offending rows' keys as `example`.
+    sql: String,
+}
+
+/// Checks generated tables against the generator's invariants.
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    date_range: Option<(NaiveDate, NaiveDate)>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also require every session to fall between `first` and `last`.
+    pub fn with_date_range(mut self, first: NaiveDate, last: NaiveDate) -> Self {
+        self.date_range = Some((first, last));
+        self
+    }
+
+    /// Validate the generated tables in `conn`.
+    pub fn validate(&self, conn: &duckdb::Connection) -> Result<ValidationReport> {
+        let mut stmt = conn.prepare(
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = 'main'",
+        )?;
+        let present = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+        if !present.iter().any(|table| table == "sessions") {
+            bail!("No sessions table to validate");
+        }
+        let has_first_seen: bool = conn.query_row(
+            "SELECT count(*) > 0 FROM information_schema.columns \
+             WHERE table_name = 'visitors' AND column_name = 'first_seen'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let mut report = ValidationReport::default();
+        for rule in self.rules(has_first_seen) {
+            if !rule.tables.iter().all(|t| present.iter().any(|p| p == t)) {
+                continue;
+            }
+            let (count, example): (u64, Option<String>) = conn
+                .query_row(
+                    &format!(
+                        "SELECT count(*), min(CAST(example AS VARCHAR)) FROM ({})",
+                        rule.sql
+                    ),
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .with_context(|| format!("Failed to check {}", rule.name))?;
+            report.rules_checked.push(rule.name);
+            if count > 0 {
+                report.violations.push(Violation {
+                    rule: rule.name,
+                    count,
+                    example,
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Validate Parquet written under `root`: either a directory per table,
+    /// as [`crate::ParquetSink`] writes, or a sessions dataset on its own.
+    pub fn validate_parquet(&self, root: &Path) -> Result<ValidationReport> {
+        let conn = duckdb::Connection::open_in_memory()?;
+        let view = |table: &str, dir: &Path| {
+            conn.execute_batch(&format!(
+                "CREATE VIEW {} AS SELECT * FROM \
+                 read_parquet('{}/**/*.parquet', hive_partitioning = true)",
+                table,
+                dir.to_string_lossy().replace('\'', "''")
+            ))
+            .with_context(|| format!("Failed to read {} from {:?}", table, dir))
+        };
+        if root.join("sessions").is_dir() {
+            for table in TABLES {
+                let dir = root.join(table);
+                if dir.is_dir() {
+                    view(table, &dir)?;
+                }
+            }
+        } else {
+            view("sessions", root)?;
+        }
+        self.validate(&conn)
+    }
+
+    fn rules(&self, has_first_seen: bool) -> Vec<Rule> {
+        let mut rules = vec![
+            Rule {
+                name: "sessions.keys_not_null",
+                tables: &["sessions"],
+                sql: "SELECT coalesce(session_id, visitor_id, 'session_date') AS example \
+                      FROM sessions \
+                      WHERE session_id IS NULL OR visitor_id IS NULL OR session_date IS NULL"
+                    .into(),
+            },
+            Rule {
+                name: "sessions.revenue_non_negative",
+                tables: &["sessions"],
+                sql: "SELECT session_id AS example FROM sessions WHERE product_revenue < 0".into(),
+            },
+            Rule {
+                name: "sessions.funnel_nested",
+                tables: &["sessions"],
+                sql: "SELECT session_id AS example FROM sessions \
+                      WHERE NOT (product_views >= product_add_to_cart_count \
+                        AND product_add_to_cart_count >= product_checkout_count \
+                        AND product_checkout_count >= product_purchase_count \
+                        AND product_purchase_count >= 0)"
+                    .into(),
+            },
+            Rule {
+                name: "sessions.revenue_needs_purchase",
+                tables: &["sessions"],
+                sql: "SELECT session_id AS example FROM sessions \
+                      WHERE (product_purchase_count > 0) <> (product_revenue > 0)"
+                    .into(),
+            },
+            Rule {
+                name: "sessions.campaign_rules",
+                tables: &["sessions"],
+                sql: format!(
+                    "SELECT session_id AS example FROM sessions \
+                     WHERE (visit_campaign IS NOT NULL) <> (visit_source IN {})",
+                    CAMPAIGN_SOURCES
+                ),
+            },
+            Rule {
+                name: "sessions.rows_agree",
+                tables: &["sessions"],
+                sql: "SELECT session_id AS example FROM sessions GROUP BY session_id \
+                      HAVING count(DISTINCT visitor_id) > 1 OR count(DISTINCT platform) > 1 \
+                        OR count(DISTINCT session_date) > 1"
+                    .into(),
+            },
+            Rule {
+                name: "sessions.one_row_per_category",
+                tables: &["sessions"],
+                sql: "SELECT session_id AS example FROM sessions \
+                      GROUP BY session_id, product_category HAVING count(*) > 1"
+                    .into(),
+            },
+            Rule {
+                name: "sessions.visitor_exists",
+                tables: &["sessions", "visitors"],
+                sql: "SELECT s.visitor_id AS example FROM sessions s \
+                      ANTI JOIN visitors v ON v.visitor_id = s.visitor_id"
+                    .into(),
+            },
+            Rule {
+                name: "events.session_exists",
+                tables: &["events", "sessions"],
+                sql: "SELECT e.event_id AS example FROM events e \
+                      ANTI JOIN sessions s ON s.session_id = e.session_id"
+                    .into(),
+            },
+            Rule {
+                // Late sessions may run past midnight, but not into a third day.
+                name: "events.timestamp_in_session_day",
+                tables: &["events", "sessions"],
+                sql: "SELECT e.event_id AS example FROM events e \
+                      JOIN (SELECT DISTINCT session_id, session_date FROM sessions) s \
+                        USING (session_id) \
+                      WHERE e.event_timestamp < s.session_date \
+                        OR e.event_timestamp >= s.session_date + INTERVAL 2 DAY"
+                    .into(),
+            },
+            Rule {
+                name: "orders.session_exists",
+                tables: &["orders", "sessions"],
+                sql: "SELECT o.order_id AS example FROM orders o \
+                      ANTI JOIN sessions s ON s.session_id = o.session_id"
+                    .into(),
+            },
+            Rule {
+                name: "orders.total_matches_items",
+                tables: &["orders", "order_items"],
+                sql: "SELECT o.order_id AS example FROM orders o \
+                      LEFT JOIN (SELECT order_id, sum(line_total) AS items_total \
+                                 FROM order_items GROUP BY order_id) i USING (order_id) \
+                      WHERE o.total IS DISTINCT FROM i.items_total OR o.total < 0"
+                    .into(),
+            },
+            Rule {
+                name: "order_items.line_total",
+                tables: &["order_items"],
+                sql: "SELECT order_id AS example FROM order_items \
+                      WHERE line_total <> quantity * unit_price OR quantity <= 0"
+                    .into(),
+            },
+            Rule {
+                name: "touchpoints.before_conversion",
+                tables: &["touchpoints"],
+                sql: "SELECT touchpoint_id AS example FROM touchpoints \
+                      WHERE touch_date > conversion_date OR position > path_length"
+                    .into(),
+            },
+            Rule {
+                name: "touchpoints.conversion_exists",
+                tables: &["touchpoints", "sessions"],
+                sql: "SELECT t.touchpoint_id AS example FROM touchpoints t \
+                      ANTI JOIN sessions s ON s.session_id = t.conversion_session_id"
+                    .into(),
+            },
+        ];
+        if has_first_seen {
+            rules.push(Rule {
+                name: "visitors.first_seen",
+                tables: &["visitors", "sessions"],
+                sql: "SELECT v.visitor_id AS example FROM visitors v \
+                      LEFT JOIN (SELECT visitor_id, min(session_date) AS first_session \
+                                 FROM sessions GROUP BY visitor_id) s USING (visitor_id) \
+                      WHERE v.first_seen IS DISTINCT FROM s.first_session"
+                    .into(),
+            });
+        }
+        if let Some((first, last)) = self.date_range {
+            rules.push(Rule {
+                name: "sessions.date_in_range",
+                tables: &["sessions"],
+                sql: format!(
+                    "SELECT session_id AS example FROM sessions \
+                     WHERE session_date NOT BETWEEN DATE '{}' AND DATE '{}'",
+                    first, last
+                ),
+            });
+        }
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::{Catalog, CatalogConfig};
+    use crate::dirty::DirtyDataConfig;
+    use crate::orders::OrdersConfig;
+    use crate::output::OutputBuilder;
+    use crate::sink::{DuckDbSink, ParquetSink, StreamingOutput};
+    use crate::{AttributionConfig, EventsConfig};
+    use tempfile::TempDir;
+
+    fn start_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+    }
+
+    fn everything() -> StreamingOutput {
+        StreamingOutput::new()
+            .with_catalog(Catalog::generate(42, &CatalogConfig::default()).unwrap())
+            .with_orders(OrdersConfig::default())
+            .with_events(EventsConfig::default())
+            .with_attribution(AttributionConfig::default())
+    }
+
+    #[test]
+    fn test_generated_tables_pass() {
+        let mut sink = DuckDbSink::new(duckdb::Connection::open_in_memory().unwrap());
+        everything()
+            .write_to(&mut sink, 42, 3000, 3, start_date())
+            .unwrap();
+
+        let report = Validator::new()
+            .with_date_range(start_date(), NaiveDate::from_ymd_opt(2024, 1, 3).unwrap())
+            .validate(&sink.into_inner())
+            .unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.rules_checked.len(), 17);
+    }
+
+    #[test]
+    fn test_parquet_tables_pass_and_dirty_data_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        everything()
+            .write_to(
+                &mut ParquetSink::new(temp_dir.path()),
+                42,
+                3000,
+                3,
+                start_date(),
+            )
+            .unwrap();
+        let report = Validator::new().validate_parquet(temp_dir.path()).unwrap();
+        assert!(report.is_ok(), "{}", report);
+        assert!(report
+            .rules_checked
+            .contains(&"touchpoints.conversion_exists"));
+
+        let dirty_dir = TempDir::new().unwrap();
+        let mut dirty = DirtyDataConfig::default();
+        dirty.null_rates.insert("visitor_id".into(), 0.05);
+        StreamingOutput::new()
+            .with_dirty_data(dirty)
+            .write_to(
+                &mut ParquetSink::new(dirty_dir.path()),
+                42,
+                3000,
+                3,
+                start_date(),
+            )
+            .unwrap();
+        let report = Validator::new().validate_parquet(dirty_dir.path()).unwrap();
+        let rules: Vec<_> = report.violations.iter().map(|v| v.rule).collect();
+        assert!(rules.contains(&"sessions.keys_not_null"));
+        assert!(report.ensure_ok().is_err());
+    }
+}
@@ -3,7 +3,7 @@
 /// This module defines the Salsa queries that power the LSP and optimizer.
 /// Salsa automatically handles incremental recomputation when inputs change.
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use serde::Deserialize;
@@ -27,6 +27,10 @@ pub trait Inputs {
     /// Get the raw YAML content of sources.yml
     #[salsa::input]
     fn sources_yaml(&self) -> Arc<String>;
+
+    /// Get the packages whose files are part of the project
+    #[salsa::input]
+    fn packages(&self) -> Arc<Vec<Package>>;
 }
 
 /// Syntax queries - parsing and CST construction
@@ -80,13 +84,45 @@ pub trait Schema: Semantic {
 
 /// The main database that combines all query groups
 #[salsa::database(InputsStorage, SyntaxStorage, SemanticStorage, SchemaStorage)]
-#[derive(Default)]
 pub struct Database {
     storage: salsa::Storage<Self>,
 }
 
 impl salsa::Database for Database {}
 
+impl Default for Database {
+    fn default() -> Self {
+        let mut db = Self {
+            storage: salsa::Storage::default(),
+        };
+        db.set_packages(Arc::new(Vec::new()));
+        db
+    }
+}
+
+/// The package a file belongs to, if it's under a package's root
+fn file_package(db: &dyn Inputs, path: &Path) -> Option<String> {
+    db.packages()
+        .iter()
+        .find(|package| path.starts_with(&package.root))
+        .map(|package| package.name.clone())
+}
+
+/// The model a ref in `path` resolves to.
+///
+/// `smelt.ref('package', 'model')` names `package.model`. A plain
+/// `smelt.ref('model')` in a package's file names a model in that package.
+pub fn ref_name(db: &dyn Inputs, path: &Path, ref_call: &RefCall) -> Option<String> {
+    if ref_call.package_name().is_some() {
+        return ref_call.qualified_name();
+    }
+    let model = ref_call.model_name()?;
+    Some(match file_package(db, path) {
+        Some(package) => format!("{}.{}", package, model),
+        None => model,
+    })
+}
+
 // Query implementations
 
 fn parse_file(db: &dyn Syntax, path: PathBuf) -> Arc<smelt_parser::Parse> {
@@ -95,8 +131,13 @@ fn parse_file(db: &dyn Syntax, path: PathBuf) -> Arc<smelt_parser::Parse> {
 }
 
 fn parse_model(db: &dyn Syntax, path: PathBuf) -> Option<Arc<Model>> {
-    // Extract model name from file path (e.g., models/users.sql -> users)
-    let model_name = path.file_stem()?.to_str()?.to_string();
+    // Extract model name from file path (e.g., models/users.sql -> users),
+    // qualified by the package it's in
+    let stem = path.file_stem()?.to_str()?;
+    let model_name = match file_package(db, &path) {
+        Some(package) => format!("{}.{}", package, stem),
+        None => stem.to_string(),
+    };
 
     // Parse file and check if it contains a valid SELECT statement
     let parse = db.parse_file(path.clone());
@@ -114,7 +155,7 @@ fn parse_model(db: &dyn Syntax, path: PathBuf) -> Option<Arc<Model>> {
 
 fn model_refs(db: &dyn Syntax, path: PathBuf) -> Arc<Vec<RefLocation>> {
     let parse = db.parse_file(path.clone());
-    let text = db.file_text(path);
+    let text = db.file_text(path.clone());
     let syntax = parse.syntax();
 
     // Use AST to extract all ref calls with positions
@@ -122,7 +163,7 @@ fn model_refs(db: &dyn Syntax, path: PathBuf) -> Arc<Vec<RefLocation>> {
         let refs: Vec<RefLocation> = file
             .refs()
             .filter_map(|ref_call| {
-                let name = ref_call.model_name()?;
+                let name = ref_name(db, &path, &ref_call)?;
                 let text_range = ref_call.name_range().unwrap_or(ref_call.range());
                 let range = smelt_parser::ast::text_range_to_range(&text, text_range);
 
@@ -291,6 +332,14 @@ pub struct Model {
     pub path: PathBuf,
 }
 
+/// Another smelt project whose models are included in this one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// Directory containing the package's smelt.yml
+    pub root: PathBuf,
+}
+
 /// Reference location with position information
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RefLocation {
@@ -467,7 +516,7 @@ fn model_schema(db: &dyn Schema, path: PathBuf) -> Arc<ModelSchema> {
                 table_ref
                     .function_call()
                     .and_then(RefCall::from_function_call)
-                    .and_then(|r| r.model_name())
+                    .and_then(|r| ref_name(db, &path, &r))
             })
             .collect()
     } else {
@@ -479,22 +528,24 @@ fn model_schema(db: &dyn Schema, path: PathBuf) -> Arc<ModelSchema> {
 
     for item in select_list.items() {
         // Handle SELECT *
-        if let Some(expr) = item.expression() {
-            if expr.text().trim() == "*" {
-                // Wildcard - need to expand from source(s)
-                for ref_name in &from_refs {
-                    columns.push(Column {
-                        name: "*".to_string(),
-                        alias: None,
-                        source: ColumnSource::Wildcard {
-                            model_name: ref_name.clone(),
-                        },
-                        expression: "*".to_string(),
-                        range: item.range(),
-                    });
-                }
-                continue;
+        let wildcard = item.is_wildcard()
+            || item
+                .expression()
+                .is_some_and(|expr| expr.text().trim() == "*");
+        if wildcard {
+            // Wildcard - need to expand from source(s)
+            for ref_name in &from_refs {
+                columns.push(Column {
+                    name: "*".to_string(),
+                    alias: None,
+                    source: ColumnSource::Wildcard {
+                        model_name: ref_name.clone(),
+                    },
+                    expression: "*".to_string(),
+                    range: item.range(),
+                });
             }
+            continue;
         }
 
         // Regular column
@@ -572,7 +623,7 @@ fn available_columns(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Column>> {
                 for table_ref in from_clause.table_refs() {
                     if let Some(func) = table_ref.function_call() {
                         if let Some(ref_call) = RefCall::from_function_call(func) {
-                            if let Some(model_name) = ref_call.model_name() {
+                            if let Some(model_name) = ref_name(db, &path, &ref_call) {
                                 // Resolve upstream model schema
                                 if let Some(upstream_path) = db.resolve_ref(model_name.clone()) {
                                     let upstream_schema = db.model_schema(upstream_path);
@@ -699,6 +750,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_schema_extraction_wildcard() {
+        let mut db = Database::default();
+
+        let path = PathBuf::from("models/stg_events.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new("SELECT * FROM smelt.ref('raw_events')".to_string()),
+        );
+
+        let schema = db.model_schema(path);
+
+        assert_eq!(schema.columns.len(), 1);
+        assert_eq!(schema.columns[0].name, "*");
+        assert_eq!(
+            schema.columns[0].source,
+            ColumnSource::Wildcard {
+                model_name: "raw_events".to_string()
+            }
+        );
+    }
+
     #[test]
     fn test_available_columns_includes_upstream() {
         let mut db = Database::default();
@@ -735,6 +808,55 @@ mod tests {
         assert!(column_names.contains(&"event_time"));
     }
 
+    #[test]
+    fn test_package_refs() {
+        let mut db = Database::default();
+        db.set_packages(Arc::new(vec![Package {
+            name: "shared".to_string(),
+            root: PathBuf::from("/shared"),
+        }]));
+
+        // A package model refs its own models without naming the package
+        let customers_path = PathBuf::from("/shared/models/customers.sql");
+        db.set_file_text(
+            customers_path.clone(),
+            Arc::new("SELECT customer_id, region FROM source.customers".to_string()),
+        );
+        let orders_path = PathBuf::from("/shared/models/orders.sql");
+        db.set_file_text(
+            orders_path.clone(),
+            Arc::new("SELECT customer_id FROM smelt.ref('customers')".to_string()),
+        );
+        let report_path = PathBuf::from("/project/models/customers.sql");
+        db.set_file_text(
+            report_path.clone(),
+            Arc::new("SELECT region FROM smelt.ref('shared', 'customers')".to_string()),
+        );
+        db.set_all_files(Arc::new(vec![
+            customers_path.clone(),
+            orders_path.clone(),
+            report_path.clone(),
+        ]));
+
+        assert_eq!(
+            db.resolve_ref("shared.customers".to_string()),
+            Some(customers_path)
+        );
+        assert_eq!(
+            db.resolve_ref("customers".to_string()),
+            Some(report_path.clone())
+        );
+        assert_eq!(db.model_refs(orders_path)[0].name, "shared.customers");
+        assert!(db.file_diagnostics(report_path.clone()).is_empty());
+        assert_eq!(
+            db.model_schema(report_path).columns[0].source,
+            ColumnSource::FromModel {
+                model_name: "shared.customers".to_string(),
+                column_name: "region".to_string(),
+            }
+        );
+    }
+
     #[test]
     fn test_undefined_ref_diagnostic_position() {
         let mut db = Database::default();
@@ -224,7 +224,7 @@ impl LanguageServer for Backend {
 
                 // Check if cursor is within this ref call
                 if cursor_offset >= start && cursor_offset <= end {
-                    if let Some(ref_name) = ref_call.model_name() {
+                    if let Some(ref_name) = smelt_db::ref_name(&*db, &path, &ref_call) {
                         // Resolve the ref
                         if let Some(target_path) = db.resolve_ref(ref_name) {
                             if let Ok(target_uri) = Url::from_file_path(&target_path) {
@@ -292,7 +292,7 @@ impl LanguageServer for Backend {
 
                 // Check if cursor is within this ref call
                 if cursor_offset >= start && cursor_offset <= end {
-                    if let Some(model_name) = ref_call.model_name() {
+                    if let Some(model_name) = smelt_db::ref_name(&*db, &path, &ref_call) {
                         // Resolve upstream model and show its schema
                         if let Some(upstream_path) = db.resolve_ref(model_name.clone()) {
                             let schema = db.model_schema(upstream_path);
@@ -320,11 +320,11 @@ impl LanguageServer for Backend {
                                             model_name, column_name
                                         ));
                                     }
-                                    smelt_db::ColumnSource::Computed => {
-                                        if !col.expression.is_empty() && col.expression != col.name
-                                        {
-                                            content.push_str(&format!(" = `{}`", col.expression));
-                                        }
+                                    smelt_db::ColumnSource::Computed
+                                        if !col.expression.is_empty()
+                                            && col.expression != col.name =>
+                                    {
+                                        content.push_str(&format!(" = `{}`", col.expression));
                                     }
                                     _ => {}
                                 }
@@ -1,5 +1,5 @@
 /// Typed AST wrappers over Rowan CST
-use crate::syntax_kind::SyntaxNode;
+use crate::syntax_kind::{SyntaxNode, SyntaxToken};
 use crate::SyntaxKind::*;
 use rowan::TextRange;
 
@@ -76,6 +76,10 @@ impl SelectStmt {
         self.0.children().find_map(HavingClause::cast)
     }
 
+    pub fn qualify_clause(&self) -> Option<QualifyClause> {
+        self.0.children().find_map(QualifyClause::cast)
+    }
+
     pub fn order_by_clause(&self) -> Option<OrderByClause> {
         self.0.children().find_map(OrderByClause::cast)
     }
@@ -134,6 +138,13 @@ impl SelectItem {
         self.0.children().find_map(Expr::cast)
     }
 
+    /// Whether this is a bare `*` (as in `SELECT *`)
+    pub fn is_wildcard(&self) -> bool {
+        self.0
+            .children_with_tokens()
+            .any(|child| child.kind() == STAR)
+    }
+
     /// Get the explicit alias if present (the identifier after AS keyword)
     pub fn alias(&self) -> Option<String> {
         let mut found_as = false;
@@ -676,7 +687,7 @@ impl NamedParam {
     }
 }
 
-/// ref('model_name') function call wrapper
+/// ref('model_name') or ref('package', 'model_name') function call wrapper
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RefCall(FunctionCall);
 
@@ -699,23 +710,40 @@ impl RefCall {
         &self.0
     }
 
-    /// Get the model name from the ref call (first argument)
-    pub fn model_name(&self) -> Option<String> {
-        // Look for the first STRING token in the function call arguments
+    /// String literals passed as positional arguments, in order
+    fn string_args(&self) -> Vec<SyntaxToken> {
         self.0
              .0
             .descendants_with_tokens()
             .filter_map(|e| e.into_token())
-            .find(|t| t.kind() == STRING)
-            .map(|t| {
-                let text = t.text();
-                // Strip quotes
-                text.trim_start_matches('\'')
-                    .trim_start_matches('"')
-                    .trim_end_matches('\'')
-                    .trim_end_matches('"')
-                    .to_string()
-            })
+            .filter(|t| t.kind() == STRING)
+            .filter(|t| !t.parent_ancestors().any(|n| n.kind() == NAMED_PARAM))
+            .collect()
+    }
+
+    /// Get the model name from the ref call (the last positional argument)
+    pub fn model_name(&self) -> Option<String> {
+        self.string_args().last().map(|t| unquote(t.text()))
+    }
+
+    /// Get the package named by a two-argument ref, e.g. "shared" from
+    /// smelt.ref('shared', 'orders')
+    pub fn package_name(&self) -> Option<String> {
+        let args = self.string_args();
+        match args.as_slice() {
+            [package, _] => Some(unquote(package.text())),
+            _ => None,
+        }
+    }
+
+    /// Get the name the ref resolves by: `package.model` for package refs,
+    /// otherwise the model name
+    pub fn qualified_name(&self) -> Option<String> {
+        let model = self.model_name()?;
+        Some(match self.package_name() {
+            Some(package) => format!("{}.{}", package, model),
+            None => model,
+        })
     }
 
     /// Get the text range of the entire ref call
@@ -725,12 +753,7 @@ impl RefCall {
 
     /// Get the text range of just the model name string (inside quotes)
     pub fn name_range(&self) -> Option<TextRange> {
-        self.0
-             .0
-            .descendants_with_tokens()
-            .filter_map(|e| e.into_token())
-            .find(|t| t.kind() == STRING)
-            .map(|t| t.text_range())
+        self.string_args().last().map(|t| t.text_range())
     }
 
     /// Get all named parameters from this ref call
@@ -739,6 +762,14 @@ impl RefCall {
     }
 }
 
+fn unquote(text: &str) -> String {
+    text.trim_start_matches('\'')
+        .trim_start_matches('"')
+        .trim_end_matches('\'')
+        .trim_end_matches('"')
+        .to_string()
+}
+
 /// source('source.table') function call wrapper
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SourceCall(FunctionCall);
@@ -1096,6 +1127,29 @@ impl HavingClause {
     }
 }
 
+/// QUALIFY clause (filters rows on window function results)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QualifyClause(SyntaxNode);
+
+impl QualifyClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == QUALIFY_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    /// Get the text range of this QUALIFY clause
+    pub fn text_range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
 /// ORDER BY clause
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct OrderByClause(SyntaxNode);
@@ -299,6 +299,8 @@ fn keyword_or_ident(text: &str) -> SyntaxKind {
         "REPEATABLE" => REPEATABLE_KW,
         // Phase 15: Aggregate function keywords
         "FILTER" => FILTER_KW,
+        // Phase 16: Window filter keyword
+        "QUALIFY" => QUALIFY_KW,
         _ => IDENT,
     }
 }
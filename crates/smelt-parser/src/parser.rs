@@ -85,6 +85,15 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Kind of the next token that isn't trivia, without consuming anything
+    fn peek_past_trivia(&self) -> SyntaxKind {
+        self.tokens[self.pos.min(self.tokens.len())..]
+            .iter()
+            .map(|t| t.kind)
+            .find(|kind| !kind.is_trivia())
+            .unwrap_or(EOF)
+    }
+
     /// Skip trivia (whitespace, comments)
     fn skip_trivia(&mut self) {
         while self.current().is_trivia() {
@@ -142,7 +151,7 @@ impl<'a> Parser<'a> {
     fn at_keyword_that_ends_table_ref(&self) -> bool {
         // Keywords that can follow a table reference in the FROM clause
         self.at_any(&[
-            WHERE_KW, GROUP_KW, HAVING_KW, ORDER_KW, LIMIT_KW, // JOIN keywords
+            WHERE_KW, GROUP_KW, HAVING_KW, QUALIFY_KW, ORDER_KW, LIMIT_KW, // JOIN keywords
             JOIN_KW, INNER_KW, LEFT_KW, RIGHT_KW, FULL_KW, CROSS_KW,
         ])
     }
@@ -246,6 +255,12 @@ impl<'a> Parser<'a> {
             self.parse_having_clause();
         }
 
+        // QUALIFY clause (filters on window functions, after HAVING)
+        self.skip_trivia();
+        if self.at(QUALIFY_KW) {
+            self.parse_qualify_clause();
+        }
+
         // ORDER BY clause
         self.skip_trivia();
         if self.at(ORDER_KW) {
@@ -299,8 +314,8 @@ impl<'a> Parser<'a> {
                     self.skip_trivia();
                     // Allow trailing comma - break if next token ends the SELECT list
                     if self.at_any(&[
-                        FROM_KW, WHERE_KW, GROUP_KW, HAVING_KW, ORDER_KW, LIMIT_KW, EOF, INNER_KW,
-                        LEFT_KW, RIGHT_KW, FULL_KW, CROSS_KW, JOIN_KW,
+                        FROM_KW, WHERE_KW, GROUP_KW, HAVING_KW, QUALIFY_KW, ORDER_KW, LIMIT_KW,
+                        EOF, INNER_KW, LEFT_KW, RIGHT_KW, FULL_KW, CROSS_KW, JOIN_KW,
                     ]) {
                         break;
                     }
@@ -601,7 +616,7 @@ impl<'a> Parser<'a> {
                 self.advance();
                 self.skip_trivia();
                 // Allow trailing comma - break if next token ends GROUP BY
-                if self.at_any(&[HAVING_KW, ORDER_KW, LIMIT_KW, EOF]) {
+                if self.at_any(&[HAVING_KW, QUALIFY_KW, ORDER_KW, LIMIT_KW, EOF]) {
                     break;
                 }
             } else {
@@ -619,6 +634,13 @@ impl<'a> Parser<'a> {
         self.finish_node();
     }
 
+    fn parse_qualify_clause(&mut self) {
+        self.start_node(QUALIFY_CLAUSE);
+        self.expect(QUALIFY_KW);
+        self.parse_expression();
+        self.finish_node();
+    }
+
     fn parse_order_by_clause(&mut self) {
         self.start_node(ORDER_BY_CLAUSE);
         self.expect(ORDER_KW);
@@ -864,7 +886,21 @@ impl<'a> Parser<'a> {
 
     fn parse_primary_expr(&mut self) {
         self.skip_trivia();
+        let checkpoint = self.builder.checkpoint();
+        self.parse_operand();
 
+        // PostgreSQL cast: expr::type, possibly chained (expr::text::int)
+        while self.peek_past_trivia() == DOUBLE_COLON {
+            self.start_node_at(checkpoint, CAST_EXPR);
+            self.skip_trivia();
+            self.advance(); // consume ::
+            self.skip_trivia();
+            self.parse_type_spec();
+            self.finish_node();
+        }
+    }
+
+    fn parse_operand(&mut self) {
         if self.at(CASE_KW) {
             self.parse_case_expr();
         } else if self.at(CAST_KW) {
@@ -929,13 +965,6 @@ impl<'a> Parser<'a> {
                     }
                 }
                 // else: just a qualified name (table.column), no extra node needed
-            } else if self.at(DOUBLE_COLON) {
-                // PostgreSQL cast: expr::type
-                self.start_node_at(checkpoint, CAST_EXPR);
-                self.advance(); // consume ::
-                self.skip_trivia();
-                self.parse_type_spec();
-                self.finish_node();
             }
             // else: just an identifier, no extra node needed
         } else if self.current().is_literal() || self.at(STAR) {
@@ -1578,6 +1607,41 @@ mod tests {
         assert_eq!(parse.errors.len(), 0);
     }
 
+    #[test]
+    fn test_cast_double_colon_after_any_operand() {
+        let input =
+            "SELECT '2024-01-01'::DATE, t.amount::DECIMAL(10, 2), (a + b)::BIGINT::VARCHAR FROM t";
+        let parse = parse(input);
+        assert_eq!(parse.errors, vec![]);
+
+        let casts: Vec<_> = parse
+            .syntax()
+            .descendants()
+            .filter(|n| n.kind() == CAST_EXPR && n.parent().unwrap().kind() != CAST_EXPR)
+            .map(|n| n.text().to_string().trim_end().to_string())
+            .collect();
+        assert_eq!(
+            casts,
+            vec![
+                "'2024-01-01'::DATE",
+                "t.amount::DECIMAL(10, 2)",
+                "(a + b)::BIGINT::VARCHAR"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_qualify_clause() {
+        let input = "SELECT id, ts FROM events QUALIFY ROW_NUMBER() OVER (PARTITION BY id ORDER BY ts DESC) = 1 ORDER BY id";
+        let parse = parse(input);
+        assert_eq!(parse.errors, vec![]);
+
+        let file = crate::File::cast(parse.syntax()).unwrap();
+        let select = file.select_stmt().unwrap();
+        assert!(select.qualify_clause().is_some());
+        assert!(select.order_by_clause().is_some());
+    }
+
     #[test]
     fn test_cast_with_params() {
         let input = "SELECT CAST(name AS VARCHAR(255)) FROM users";
@@ -2156,6 +2220,26 @@ LIMIT 50
         assert!(ref_names.contains(&"users".to_string()));
     }
 
+    #[test]
+    fn test_smelt_ref_with_package() {
+        let input = "SELECT o.id FROM smelt.ref('shared', 'orders', filter => status = 'open') o \
+                     JOIN smelt.ref('users') u ON o.user_id = u.id";
+        let parse = parse(input);
+        assert_eq!(parse.errors.len(), 0);
+
+        use crate::ast::File;
+        let file = File::cast(parse.syntax()).unwrap();
+        let refs: Vec<_> = file.refs().collect();
+        assert_eq!(refs[0].package_name().as_deref(), Some("shared"));
+        assert_eq!(refs[0].model_name().as_deref(), Some("orders"));
+        assert_eq!(refs[0].qualified_name().as_deref(), Some("shared.orders"));
+        let name_range = refs[0].name_range().unwrap();
+        assert_eq!(&input[name_range], "'orders'");
+
+        assert_eq!(refs[1].package_name(), None);
+        assert_eq!(refs[1].qualified_name().as_deref(), Some("users"));
+    }
+
     #[test]
     fn test_complex_recursive_cte_with_all_features() {
         // Comprehensive test combining CTEs, recursive queries, window functions, JOINs, etc.
@@ -128,6 +128,13 @@ impl Display for SelectStmt {
             write!(f, " HAVING {}", having_clause)?;
         }
 
+        // QUALIFY clause
+        if let Some(qualify_clause) = self.qualify_clause() {
+            if let Some(expr) = qualify_clause.expression() {
+                write!(f, " QUALIFY {}", expr.text())?;
+            }
+        }
+
         // ORDER BY clause
         if let Some(order_by_clause) = self.order_by_clause() {
             write!(f, " {}", order_by_clause)?;
@@ -612,6 +619,13 @@ mod tests {
         assert_round_trip("SELECT city, COUNT(*) FROM users GROUP BY city HAVING COUNT(*) > 5");
     }
 
+    #[test]
+    fn test_select_qualify() {
+        assert_round_trip(
+            "SELECT id FROM events QUALIFY ROW_NUMBER() OVER (PARTITION BY id ORDER BY ts) = 1",
+        );
+    }
+
     #[test]
     fn test_round_trip_mixed_case_where() {
         // Regression test for fuzzer crash with mixed-case WHERE keyword
@@ -72,6 +72,8 @@ pub enum SyntaxKind {
     REPEATABLE_KW,
     // Phase 15: Aggregate function keywords
     FILTER_KW,
+    // Phase 16: Window filter keyword
+    QUALIFY_KW,
 
     // Operators & punctuation
     LPAREN,       // (
@@ -142,6 +144,8 @@ pub enum SyntaxKind {
     TABLESAMPLE_CLAUSE, // TABLESAMPLE method (percentage) REPEATABLE (seed)
     // Phase 15: Aggregate function nodes
     FILTER_CLAUSE, // FILTER (WHERE condition)
+    // Phase 16: Window filter nodes
+    QUALIFY_CLAUSE, // QUALIFY expression (DuckDB, Snowflake)
 
     // Error handling
     ERROR, // Invalid syntax
@@ -218,6 +222,7 @@ impl SyntaxKind {
                 | SYSTEM_KW
                 | REPEATABLE_KW
                 | FILTER_KW
+                | QUALIFY_KW
         )
     }
 